@@ -327,6 +327,8 @@ struct Shared {
     vec: Vec<u8>,
     ref_count: AtomicUsize,
     pool: PoolRef,
+    #[cfg(unix)]
+    mmap: Option<crate::mmap::Mmap>,
 }
 
 struct SharedVec {
@@ -487,6 +489,73 @@ impl Bytes {
         }
     }
 
+    /// Creates `Bytes` instance backed by memory mapped file region.
+    ///
+    /// File region is mapped read-only and privately, `Bytes` data is never
+    /// modified in place, conversion to `BytesMut` copies data. Mapped memory
+    /// is accounted in default memory pool.
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure that the file is not truncated or modified while
+    /// returned `Bytes` or any of its clones and slices is alive. Accessing
+    /// data of a truncated mapping is undefined behavior, on most platforms
+    /// process receives `SIGBUS` signal.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use ntex_bytes::Bytes;
+    ///
+    /// let file = std::fs::File::open("index.html").unwrap();
+    /// let len = file.metadata().unwrap().len() as usize;
+    /// let b = unsafe { Bytes::from_file(&file, 0, len).unwrap() };
+    ///
+    /// assert_eq!(b.len(), len);
+    /// assert!(b.is_file_backed());
+    /// ```
+    #[cfg(unix)]
+    pub unsafe fn from_file(
+        file: &std::fs::File,
+        offset: u64,
+        len: usize,
+    ) -> std::io::Result<Self> {
+        Bytes::from_file_in(file, offset, len, PoolId::DEFAULT.pool_ref())
+    }
+
+    /// Creates `Bytes` instance backed by memory mapped file region.
+    ///
+    /// Mapped memory is accounted in specified memory pool.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Bytes::from_file`], caller must ensure that the file
+    /// is not truncated while returned `Bytes` or any of its clones is alive.
+    #[cfg(unix)]
+    pub unsafe fn from_file_in<T>(
+        file: &std::fs::File,
+        offset: u64,
+        len: usize,
+        pool: T,
+    ) -> std::io::Result<Self>
+    where
+        PoolRef: From<T>,
+    {
+        if len == 0 {
+            Ok(Bytes::new())
+        } else {
+            let mmap = crate::mmap::Mmap::new(file, offset, len)?;
+            Ok(Bytes {
+                inner: Inner::from_mmap(mmap, len, PoolRef::from(pool)),
+            })
+        }
+    }
+
+    /// Return true if the `Bytes` is backed by memory mapped file
+    pub fn is_file_backed(&self) -> bool {
+        self.inner.is_mapped()
+    }
+
     /// Returns a slice of self for the provided range.
     ///
     /// This will increment the reference count for the underlying memory and
@@ -1865,6 +1934,8 @@ impl Inner {
             vec,
             pool,
             ref_count: AtomicUsize::new(1),
+            #[cfg(unix)]
+            mmap: None,
         }));
 
         // The pointer should be aligned, so this assert should always succeed.
@@ -1879,6 +1950,27 @@ impl Inner {
         }
     }
 
+    #[cfg(unix)]
+    fn from_mmap(mmap: crate::mmap::Mmap, len: usize, pool: PoolRef) -> Inner {
+        let ptr = mmap.as_ptr();
+        pool.acquire(mmap.size());
+
+        let shared = Box::into_raw(Box::new(Shared {
+            pool,
+            vec: Vec::new(),
+            mmap: Some(mmap),
+            ref_count: AtomicUsize::new(1),
+        }));
+        debug_assert!(0 == (shared as usize & KIND_MASK));
+
+        Inner {
+            ptr,
+            len,
+            cap: len,
+            arc: unsafe { NonNull::new_unchecked(shared) },
+        }
+    }
+
     #[inline]
     fn with_capacity(capacity: usize, pool: PoolRef) -> Inner {
         Inner::from_slice(capacity, &[], pool)
@@ -1966,7 +2058,7 @@ impl Inner {
         } else if kind == KIND_ARC {
             let arc = self.arc.as_ptr();
             unsafe {
-                let cap = (*arc).vec.capacity();
+                let cap = (*arc).size();
                 pool.acquire(cap);
                 let pool = mem::replace(&mut (*arc).pool, pool);
                 pool.release(cap);
@@ -2269,11 +2361,18 @@ impl Inner {
             unsafe { (*self.shared_vec()).is_unique() }
         } else {
             // Otherwise, the underlying buffer is potentially shared with other
-            // handles, so the ref_count needs to be checked.
-            unsafe { (*self.arc.as_ptr()).is_unique() }
+            // handles, so the ref_count needs to be checked. File backed
+            // buffers are never mutated in place.
+            unsafe { (*self.arc.as_ptr()).is_unique() && !(*self.arc.as_ptr()).is_mapped() }
         }
     }
 
+    /// Returns true if the buffer is backed by memory mapped file
+    #[inline]
+    fn is_mapped(&self) -> bool {
+        self.kind() == KIND_ARC && unsafe { (*self.arc.as_ptr()).is_mapped() }
+    }
+
     /// Increments the ref count. This should only be done if it is known that
     /// it can be done safely. As such, this fn is not public, instead other
     /// fns will use this one while maintaining the guarantees.
@@ -2538,7 +2637,7 @@ fn release_shared(ptr: *mut Shared) {
 
         // Drop the data
         let arc = Box::from_raw(ptr);
-        arc.pool.release(arc.vec.capacity());
+        arc.pool.release(arc.size());
     }
 }
 
@@ -2590,6 +2689,28 @@ impl Shared {
         // visible to the current thread.
         self.ref_count.load(Acquire) == 1
     }
+
+    /// Size of the storage
+    fn size(&self) -> usize {
+        #[cfg(unix)]
+        {
+            if let Some(ref mmap) = self.mmap {
+                return mmap.size();
+            }
+        }
+        self.vec.capacity()
+    }
+
+    fn is_mapped(&self) -> bool {
+        #[cfg(unix)]
+        {
+            self.mmap.is_some()
+        }
+        #[cfg(not(unix))]
+        {
+            false
+        }
+    }
 }
 
 impl SharedVec {
@@ -0,0 +1,266 @@
+//! Segmented buffer of `Bytes` chunks.
+use std::{collections::VecDeque, fmt, io::IoSlice};
+
+use crate::{Buf, Bytes, BytesMut};
+
+/// A segmented buffer that holds sequence of [`Bytes`] chunks.
+///
+/// `BytesChain` allows to concatenate buffers without copying data,
+/// for example encoded message head and large message body.
+/// Chunks could be consumed with [`Buf`] api or written with vectored io.
+///
+/// ```
+/// use ntex_bytes::{Buf, Bytes, BytesChain};
+///
+/// let mut chain = BytesChain::new();
+/// chain.push(Bytes::from_static(b"hello "));
+/// chain.push(Bytes::from_static(b"world"));
+///
+/// assert_eq!(chain.len(), 11);
+/// assert_eq!(chain.chunk(), b"hello ");
+///
+/// chain.advance(8);
+/// assert_eq!(chain.chunk(), b"rld");
+/// assert_eq!(chain.to_bytes(), Bytes::from_static(b"rld"));
+/// ```
+#[derive(Clone, Default)]
+pub struct BytesChain {
+    chunks: VecDeque<Bytes>,
+    len: usize,
+}
+
+impl BytesChain {
+    /// Creates a new empty `BytesChain`.
+    #[inline]
+    pub fn new() -> Self {
+        BytesChain {
+            chunks: VecDeque::new(),
+            len: 0,
+        }
+    }
+
+    /// Creates a new empty `BytesChain` with the specified number of chunks.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        BytesChain {
+            chunks: VecDeque::with_capacity(capacity),
+            len: 0,
+        }
+    }
+
+    /// Returns the number of bytes contained in this `BytesChain`.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the `BytesChain` has a length of 0.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of chunks contained in this `BytesChain`.
+    #[inline]
+    pub fn chunks_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Appends chunk to the end of the chain.
+    ///
+    /// Empty chunks are ignored.
+    pub fn push<T: Into<Bytes>>(&mut self, chunk: T) {
+        let chunk = chunk.into();
+        if !chunk.is_empty() {
+            self.len += chunk.len();
+            self.chunks.push_back(chunk);
+        }
+    }
+
+    /// Prepends chunk to the beginning of the chain.
+    ///
+    /// Empty chunks are ignored.
+    pub fn push_front<T: Into<Bytes>>(&mut self, chunk: T) {
+        let chunk = chunk.into();
+        if !chunk.is_empty() {
+            self.len += chunk.len();
+            self.chunks.push_front(chunk);
+        }
+    }
+
+    /// Appends all chunks of other chain to the end of this chain.
+    pub fn append(&mut self, other: &mut BytesChain) {
+        self.len += other.len;
+        self.chunks.append(&mut other.chunks);
+        other.len = 0;
+    }
+
+    /// Removes first chunk from the chain.
+    pub fn pop_front(&mut self) -> Option<Bytes> {
+        let chunk = self.chunks.pop_front();
+        if let Some(ref chunk) = chunk {
+            self.len -= chunk.len();
+        }
+        chunk
+    }
+
+    /// Clears the chain, removing all chunks.
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.len = 0;
+    }
+
+    /// Returns an iterator over the chunks of the chain.
+    pub fn chunks(&self) -> impl Iterator<Item = &Bytes> {
+        self.chunks.iter()
+    }
+
+    /// Fills `dst` with io slices of the chain chunks.
+    ///
+    /// Returns number of filled slices.
+    pub fn io_slices<'a>(&'a self, dst: &mut [IoSlice<'a>]) -> usize {
+        let mut n = 0;
+        for (chunk, slice) in self.chunks.iter().zip(dst.iter_mut()) {
+            *slice = IoSlice::new(chunk);
+            n += 1;
+        }
+        n
+    }
+
+    /// Converts chain to `Bytes`.
+    ///
+    /// Data is copied only if chain contains more than one chunk.
+    pub fn into_bytes(mut self) -> Bytes {
+        match self.chunks.len() {
+            0 => Bytes::new(),
+            1 => self.chunks.pop_front().unwrap(),
+            _ => {
+                let mut buf = BytesMut::with_capacity(self.len);
+                for chunk in &self.chunks {
+                    buf.extend_from_slice(chunk);
+                }
+                buf.freeze()
+            }
+        }
+    }
+
+    fn advance_chunks(&mut self, mut cnt: usize) {
+        assert!(cnt <= self.len, "cannot advance past `remaining`");
+        self.len -= cnt;
+
+        while cnt > 0 {
+            let chunk = self.chunks.front_mut().unwrap();
+            if cnt < chunk.len() {
+                Buf::advance(chunk, cnt);
+                return;
+            }
+            cnt -= chunk.len();
+            self.chunks.pop_front();
+        }
+    }
+}
+
+impl Buf for BytesChain {
+    #[inline]
+    fn remaining(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    fn chunk(&self) -> &[u8] {
+        self.chunks.front().map(|b| b.as_ref()).unwrap_or_default()
+    }
+
+    #[inline]
+    fn advance(&mut self, cnt: usize) {
+        self.advance_chunks(cnt)
+    }
+
+    #[inline]
+    fn to_bytes(&mut self) -> Bytes {
+        std::mem::take(self).into_bytes()
+    }
+}
+
+impl bytes::buf::Buf for BytesChain {
+    #[inline]
+    fn remaining(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    fn chunk(&self) -> &[u8] {
+        self.chunks.front().map(|b| b.as_ref()).unwrap_or_default()
+    }
+
+    #[inline]
+    fn advance(&mut self, cnt: usize) {
+        self.advance_chunks(cnt)
+    }
+
+    #[inline]
+    fn chunks_vectored<'a>(&'a self, dst: &mut [IoSlice<'a>]) -> usize {
+        self.io_slices(dst)
+    }
+}
+
+impl From<Bytes> for BytesChain {
+    fn from(chunk: Bytes) -> Self {
+        let mut chain = BytesChain::new();
+        chain.push(chunk);
+        chain
+    }
+}
+
+impl From<BytesMut> for BytesChain {
+    fn from(chunk: BytesMut) -> Self {
+        BytesChain::from(chunk.freeze())
+    }
+}
+
+impl<T: Into<Bytes>> Extend<T> for BytesChain {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for chunk in iter {
+            self.push(chunk)
+        }
+    }
+}
+
+impl<T: Into<Bytes>> std::iter::FromIterator<T> for BytesChain {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut chain = BytesChain::new();
+        chain.extend(iter);
+        chain
+    }
+}
+
+impl IntoIterator for BytesChain {
+    type Item = Bytes;
+    type IntoIter = std::collections::vec_deque::IntoIter<Bytes>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.chunks.into_iter()
+    }
+}
+
+impl PartialEq for BytesChain {
+    fn eq(&self, other: &BytesChain) -> bool {
+        self.len == other.len
+            && self
+                .chunks
+                .iter()
+                .flat_map(|c| c.iter())
+                .eq(other.chunks.iter().flat_map(|c| c.iter()))
+    }
+}
+
+impl Eq for BytesChain {}
+
+impl fmt::Debug for BytesChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BytesChain")
+            .field("len", &self.len)
+            .field("chunks", &self.chunks)
+            .finish()
+    }
+}
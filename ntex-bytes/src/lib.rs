@@ -63,14 +63,21 @@ pub mod buf;
 pub use crate::buf::{Buf, BufMut};
 
 mod bytes;
+mod chain;
 mod debug;
 mod hex;
+#[cfg(unix)]
+mod mmap;
 mod pool;
 mod serde;
 mod string;
 
 pub use crate::bytes::{Bytes, BytesMut};
+pub use crate::chain::BytesChain;
 pub use crate::string::ByteString;
 
 #[doc(hidden)]
-pub use crate::pool::{Pool, PoolId, PoolRef};
+pub use crate::pool::{BufParams, Pool, PoolId, PoolRef, PoolStats, ShrinkPolicy};
+
+#[cfg(feature = "pool-canary")]
+pub use crate::pool::CanaryViolation;
@@ -0,0 +1,74 @@
+//! Read-only memory mapped file regions
+use std::{fs::File, io, os::unix::io::AsRawFd, ptr};
+
+/// Private read-only mapping of a file region.
+///
+/// Mapping starts at page boundary, `offset` points to the first
+/// requested byte within the mapping.
+pub(crate) struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+    offset: usize,
+}
+
+impl Mmap {
+    pub(crate) fn new(file: &File, offset: u64, len: usize) -> io::Result<Mmap> {
+        let file_len = file.metadata()?.len();
+        if offset
+            .checked_add(len as u64)
+            .map(|end| end > file_len)
+            .unwrap_or(true)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "File region is out of file bounds",
+            ));
+        }
+
+        let page = page_size();
+        let delta = (offset % page as u64) as usize;
+        let map_len = len + delta;
+
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                map_len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                (offset - delta as u64) as libc::off_t,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(Mmap {
+                ptr,
+                offset: delta,
+                len: map_len,
+            })
+        }
+    }
+
+    /// Pointer to the first requested byte
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        unsafe { (self.ptr as *mut u8).add(self.offset) }
+    }
+
+    /// Size of the mapping
+    pub(crate) fn size(&self) -> usize {
+        self.len
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr, self.len);
+        }
+    }
+}
+
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
@@ -2,9 +2,11 @@
 use std::sync::atomic::Ordering::{Relaxed, Release};
 use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::task::{Context, Poll, Waker};
-use std::{cell::Cell, cell::RefCell, fmt, future::Future, mem, pin::Pin, rc::Rc};
+use std::time::{Duration, Instant};
+use std::{cell::Cell, cell::RefCell, cmp, fmt, future::Future, mem, pin::Pin, rc::Rc};
 
 use futures_core::task::__internal::AtomicWaker;
+use futures_timer::Delay;
 
 use crate::BytesMut;
 
@@ -16,15 +18,86 @@ pub struct Pool {
 #[derive(Copy, Clone)]
 pub struct PoolRef(&'static MemoryPool);
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct PoolId(u8);
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct BufParams {
     pub high: u16,
     pub low: u16,
 }
 
+/// Memory pool shrink policy
+///
+/// Cached io buffers that stay unused during idle period are released back
+/// to the allocator, if size of cached buffers exceeds `high` watermark.
+/// Cache is never shrunk below `low` watermark.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ShrinkPolicy {
+    /// Size of cached buffers that enables shrinking
+    pub high: usize,
+    /// Size of cached buffers to keep
+    pub low: usize,
+    /// Idle period
+    pub idle: Duration,
+}
+
+impl ShrinkPolicy {
+    /// Create new shrink policy
+    pub fn new(high: usize, low: usize, idle: Duration) -> Self {
+        assert!(low <= high);
+        ShrinkPolicy { high, low, idle }
+    }
+}
+
+/// Memory pool statistics
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Total number of allocated bytes
+    pub allocated: usize,
+    /// Number of allocated bytes, excluding cached io buffers
+    pub in_use: usize,
+    /// Number of times pool size limit was reached
+    pub hw_hits: usize,
+}
+
+/// Memory pool buffer misuse detected in canary mode
+#[cfg(feature = "pool-canary")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CanaryViolation {
+    /// Cached buffer is modified after release
+    Modified {
+        /// Memory pool id
+        pool: PoolId,
+        /// Offset of first modified byte
+        offset: usize,
+    },
+    /// Buffer is released to the pool cache twice
+    DoubleRelease {
+        /// Memory pool id
+        pool: PoolId,
+    },
+}
+
+#[cfg(feature = "pool-canary")]
+const CANARY: u8 = 0xA5;
+
+/// Memory pool counters shared by all threads
+struct PoolCounters {
+    allocated: AtomicUsize,
+    cached: AtomicUsize,
+    hw_hits: AtomicUsize,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const COUNTERS_INIT: PoolCounters = PoolCounters {
+    allocated: AtomicUsize::new(0),
+    cached: AtomicUsize::new(0),
+    hw_hits: AtomicUsize::new(0),
+};
+
+static COUNTERS: [PoolCounters; 16] = [COUNTERS_INIT; 16];
+
 bitflags::bitflags! {
     struct Flags: u8 {
         const SPAWNED    = 0b0000_0001;
@@ -54,7 +127,17 @@ struct MemoryPool {
     write_wm: Cell<BufParams>,
     write_cache: RefCell<Vec<BytesMut>>,
 
+    // cache shrink policy and state
+    shrink: Cell<Option<ShrinkPolicy>>,
+    shrink_start: Cell<Instant>,
+    shrink_spawned: Cell<bool>,
+    cache_size: Cell<usize>,
+    cache_min: Cell<usize>,
+
     spawn: RefCell<Option<Rc<dyn Fn(Pin<Box<dyn Future<Output = ()>>>)>>>,
+
+    #[cfg(feature = "pool-canary")]
+    canary: RefCell<Option<Rc<dyn Fn(CanaryViolation)>>>,
 }
 
 const CACHE_SIZE: usize = 16;
@@ -77,6 +160,20 @@ impl PoolId {
     pub const P14: PoolId = PoolId(14);
     pub const DEFAULT: PoolId = PoolId(15);
 
+    #[inline]
+    /// Get pool statistics.
+    ///
+    /// Pools are thread local, statistics is aggregated for all threads.
+    pub fn stats(self) -> PoolStats {
+        let counters = &COUNTERS[self.0 as usize];
+        let allocated = counters.allocated.load(Relaxed);
+        PoolStats {
+            allocated,
+            in_use: allocated.saturating_sub(counters.cached.load(Relaxed)),
+            hw_hits: counters.hw_hits.load(Relaxed),
+        }
+    }
+
     #[inline]
     pub fn pool(self) -> Pool {
         POOLS.with(|pools| Pool {
@@ -97,6 +194,13 @@ impl PoolId {
         self
     }
 
+    #[inline]
+    /// Set cache shrink policy
+    pub fn set_shrink_policy(self, policy: Option<ShrinkPolicy>) -> Self {
+        self.pool_ref().set_shrink_policy(policy);
+        self
+    }
+
     #[doc(hidden)]
     #[inline]
     pub fn set_read_params(self, h: u16, l: u16) -> Self {
@@ -122,6 +226,24 @@ impl PoolId {
         POOLS.with(move |pools| {
             *pools[self.0 as usize].spawn.borrow_mut() = Some(spawn.clone());
         });
+        self.pool_ref().spawn_shrinker();
+
+        self
+    }
+
+    #[cfg(feature = "pool-canary")]
+    /// Set canary violation handler
+    ///
+    /// By default violation causes panic.
+    pub fn set_canary_fn<T>(self, f: T) -> Self
+    where
+        T: Fn(CanaryViolation) + 'static,
+    {
+        let f: Rc<dyn Fn(CanaryViolation)> = Rc::new(f);
+
+        POOLS.with(move |pools| {
+            *pools[self.0 as usize].canary.borrow_mut() = Some(f);
+        });
 
         self
     }
@@ -137,6 +259,7 @@ impl PoolId {
         POOLS.with(move |pools| {
             for pool in pools.iter().take(15) {
                 *pool.spawn.borrow_mut() = Some(spawn.clone());
+                PoolRef(pool).spawn_shrinker();
             }
         });
     }
@@ -232,6 +355,70 @@ impl PoolRef {
         self
     }
 
+    /// Set cache shrink policy.
+    ///
+    /// By default cached io buffers are never released. Idle check runs
+    /// periodically in a task started with pool's spawn fn, without spawn fn
+    /// cache could be shrunk only with `shrink_idle()` calls.
+    pub fn set_shrink_policy(self, policy: Option<ShrinkPolicy>) -> Self {
+        self.0.shrink.set(policy);
+        self.0.shrink_start.set(Instant::now());
+        self.0.cache_min.set(self.0.cache_size.get());
+        self.spawn_shrinker();
+        self
+    }
+
+    #[inline]
+    /// Get cache shrink policy.
+    pub fn shrink_policy(self) -> Option<ShrinkPolicy> {
+        self.0.shrink.get()
+    }
+
+    /// Release idle cached buffers according to shrink policy.
+    ///
+    /// Policy is checked periodically if spawn fn is set, this method could
+    /// be used for manual checks. Returns size of released buffers.
+    pub fn shrink_idle(self) -> usize {
+        let policy = if let Some(policy) = self.0.shrink.get() {
+            policy
+        } else {
+            return 0;
+        };
+
+        let now = Instant::now();
+        if now.duration_since(self.0.shrink_start.get()) < policy.idle {
+            return 0;
+        }
+
+        // cache_min is size of buffers that were not used during idle period
+        let size = self.0.cache_size.get();
+        let mut released = 0;
+        if size > policy.high {
+            let target = cmp::max(policy.low, size - self.0.cache_min.get());
+            while self.0.cache_size.get() > target {
+                let buf = {
+                    let mut read = self.0.read_cache.borrow_mut();
+                    let mut write = self.0.write_cache.borrow_mut();
+                    if read.len() >= write.len() {
+                        read.pop()
+                    } else {
+                        write.pop()
+                    }
+                };
+                if let Some(buf) = buf {
+                    released += buf.capacity();
+                    self.cache_pop(&buf);
+                } else {
+                    break;
+                }
+            }
+        }
+
+        self.0.shrink_start.set(now);
+        self.0.cache_min.set(self.0.cache_size.get());
+        released
+    }
+
     #[doc(hidden)]
     #[inline]
     pub fn read_params(self) -> BufParams {
@@ -275,7 +462,10 @@ impl PoolRef {
     #[doc(hidden)]
     #[inline]
     pub fn get_read_buf(self) -> BytesMut {
-        if let Some(buf) = self.0.read_cache.borrow_mut().pop() {
+        let buf = self.0.read_cache.borrow_mut().pop();
+        if let Some(mut buf) = buf {
+            self.cache_pop(&buf);
+            self.canary_check(&mut buf);
             buf
         } else {
             BytesMut::with_capacity_in_priv(self.0.read_wm.get().high as usize, self)
@@ -289,9 +479,17 @@ impl PoolRef {
         let cap = buf.capacity();
         let (hw, lw) = self.0.read_wm.get().unpack();
         if cap > lw && cap <= hw {
-            let v = &mut self.0.read_cache.borrow_mut();
+            let mut v = self.0.read_cache.borrow_mut();
             if v.len() < CACHE_SIZE {
+                if self.canary_released(&v, &buf) {
+                    drop(v);
+                    self.canary_double_release();
+                    mem::forget(buf);
+                    return;
+                }
+                self.cache_push(cap);
                 buf.clear();
+                self.canary_fill(&mut buf);
                 v.push(buf);
             }
         }
@@ -300,7 +498,10 @@ impl PoolRef {
     #[doc(hidden)]
     #[inline]
     pub fn get_write_buf(self) -> BytesMut {
-        if let Some(buf) = self.0.write_cache.borrow_mut().pop() {
+        let buf = self.0.write_cache.borrow_mut().pop();
+        if let Some(mut buf) = buf {
+            self.cache_pop(&buf);
+            self.canary_check(&mut buf);
             buf
         } else {
             BytesMut::with_capacity_in_priv(self.0.write_wm.get().high as usize, self)
@@ -314,16 +515,124 @@ impl PoolRef {
         let cap = buf.capacity();
         let (hw, lw) = self.0.write_wm.get().unpack();
         if cap > lw && cap <= hw {
-            let v = &mut self.0.write_cache.borrow_mut();
+            let mut v = self.0.write_cache.borrow_mut();
             if v.len() < CACHE_SIZE {
+                if self.canary_released(&v, &buf) {
+                    drop(v);
+                    self.canary_double_release();
+                    mem::forget(buf);
+                    return;
+                }
+                self.cache_push(cap);
                 buf.clear();
+                self.canary_fill(&mut buf);
                 v.push(buf);
             }
         }
     }
 
+    /// Start idle check task if shrink policy and spawn fn are set
+    fn spawn_shrinker(self) {
+        if self.0.shrink.get().is_some() && !self.0.shrink_spawned.get() {
+            if let Some(spawn) = &*self.0.spawn.borrow() {
+                self.0.shrink_spawned.set(true);
+                spawn(Box::pin(Shrinker {
+                    pool: self.0,
+                    delay: Delay::new(Duration::ZERO),
+                }))
+            }
+        }
+    }
+
+    #[inline]
+    fn cache_push(self, cap: usize) {
+        self.counters().cached.fetch_add(cap, Relaxed);
+        self.0.cache_size.set(self.0.cache_size.get() + cap);
+    }
+
+    #[inline]
+    fn cache_pop(self, buf: &BytesMut) {
+        let cap = buf.capacity();
+        self.counters().cached.fetch_sub(cap, Relaxed);
+        let size = self.0.cache_size.get() - cap;
+        self.0.cache_size.set(size);
+        if size < self.0.cache_min.get() {
+            self.0.cache_min.set(size);
+        }
+    }
+
+    #[cfg(feature = "pool-canary")]
+    /// Fill spare capacity of released buffer with canary pattern
+    fn canary_fill(self, buf: &mut BytesMut) {
+        use crate::BufMut;
+
+        let dst = buf.chunk_mut();
+        unsafe { std::ptr::write_bytes(dst.as_mut_ptr(), CANARY, dst.len()) }
+    }
+
+    #[cfg(feature = "pool-canary")]
+    /// Validate canary pattern of cached buffer
+    fn canary_check(self, buf: &mut BytesMut) {
+        use crate::BufMut;
+
+        let dst = buf.chunk_mut();
+        // spare capacity is initialized by `canary_fill`
+        let data = unsafe { std::slice::from_raw_parts(dst.as_mut_ptr(), dst.len()) };
+        if let Some(offset) = data.iter().position(|b| *b != CANARY) {
+            self.canary_violation(CanaryViolation::Modified {
+                pool: self.0.id,
+                offset,
+            });
+        }
+    }
+
+    #[cfg(feature = "pool-canary")]
+    /// Check if buffer storage is already in cache
+    fn canary_released(self, cache: &[BytesMut], buf: &BytesMut) -> bool {
+        cache.iter().any(|b| b.as_ptr() == buf.as_ptr())
+    }
+
+    #[cfg(feature = "pool-canary")]
+    fn canary_double_release(self) {
+        self.canary_violation(CanaryViolation::DoubleRelease { pool: self.0.id })
+    }
+
+    #[cfg(feature = "pool-canary")]
+    fn canary_violation(self, violation: CanaryViolation) {
+        let f = self.0.canary.borrow().clone();
+        if let Some(f) = f {
+            f(violation)
+        } else {
+            panic!("Memory pool buffer misuse: {:?}", violation)
+        }
+    }
+
+    #[cfg(not(feature = "pool-canary"))]
+    #[inline(always)]
+    fn canary_fill(self, _: &mut BytesMut) {}
+
+    #[cfg(not(feature = "pool-canary"))]
+    #[inline(always)]
+    fn canary_check(self, _: &mut BytesMut) {}
+
+    #[cfg(not(feature = "pool-canary"))]
+    #[inline(always)]
+    fn canary_released(self, _: &[BytesMut], _: &BytesMut) -> bool {
+        false
+    }
+
+    #[cfg(not(feature = "pool-canary"))]
+    #[inline(always)]
+    fn canary_double_release(self) {}
+
+    #[inline]
+    fn counters(self) -> &'static PoolCounters {
+        &COUNTERS[self.0.id.0 as usize]
+    }
+
     #[inline]
     pub(crate) fn acquire(self, size: usize) {
+        self.counters().allocated.fetch_add(size, Relaxed);
         let prev = self.0.size.fetch_add(size, Relaxed);
         if self.0.waker_alive.load(Relaxed) {
             self.wake_driver(prev + size)
@@ -332,6 +641,7 @@ impl PoolRef {
 
     #[inline]
     pub(crate) fn release(self, size: usize) {
+        self.counters().allocated.fetch_sub(size, Relaxed);
         let prev = self.0.size.fetch_sub(size, Relaxed);
         if self.0.waker_alive.load(Relaxed) {
             self.wake_driver(prev - size)
@@ -406,7 +716,14 @@ impl MemoryPool {
                 low: 1024,
             }),
             write_cache: RefCell::new(Vec::with_capacity(CACHE_SIZE)),
+            shrink: Cell::new(None),
+            shrink_start: Cell::new(Instant::now()),
+            shrink_spawned: Cell::new(false),
+            cache_size: Cell::new(0),
+            cache_min: Cell::new(0),
             spawn: RefCell::new(None),
+            #[cfg(feature = "pool-canary")]
+            canary: RefCell::new(None),
         }))
     }
 }
@@ -535,6 +852,9 @@ impl Pool {
                 // if memory usage has increased since last window change,
                 // block all readyness check. otherwise wake up one existing waiter
                 if new {
+                    COUNTERS[self.inner.id.0 as usize]
+                        .hw_hits
+                        .fetch_add(1, Relaxed);
                     if !flags.contains(Flags::INCREASED) {
                         if let Some(waker) = waiters.consume() {
                             waker.wake()
@@ -563,6 +883,40 @@ struct Driver {
     pool: &'static MemoryPool,
 }
 
+/// Periodic idle check for cached buffers
+struct Shrinker {
+    pool: &'static MemoryPool,
+    delay: Delay,
+}
+
+impl Future for Shrinker {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            let policy = if let Some(policy) = self.pool.shrink.get() {
+                policy
+            } else {
+                self.pool.shrink_spawned.set(false);
+                return Poll::Ready(());
+            };
+
+            if Pin::new(&mut self.delay).poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+
+            let deadline = self.pool.shrink_start.get() + policy.idle;
+            let now = Instant::now();
+            if now >= deadline {
+                PoolRef(self.pool).shrink_idle();
+                self.delay.reset(policy.idle);
+            } else {
+                self.delay.reset(deadline - now);
+            }
+        }
+    }
+}
+
 impl Driver {
     fn release(&self, waiters_num: usize) {
         let mut waiters = self.pool.waiters.borrow_mut();
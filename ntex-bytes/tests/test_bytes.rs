@@ -1,7 +1,8 @@
 //#![deny(warnings, rust_2018_idioms)]
 use std::task::Poll;
+use std::{thread, time};
 
-use ntex_bytes::{Buf, BufMut, Bytes, BytesMut, PoolId};
+use ntex_bytes::{Buf, BufMut, Bytes, BytesMut, PoolId, PoolStats, ShrinkPolicy};
 
 const LONG: &'static [u8] = b"mary had a little lamb, little lamb, little lamb";
 const SHORT: &'static [u8] = b"hello world";
@@ -697,3 +698,186 @@ async fn pool_usage() {
     assert!(!p1.is_pending());
     assert!(!p2.is_pending());
 }
+
+#[ntex::test]
+async fn pool_stats() {
+    use ntex::util;
+
+    let p = PoolId::P5.pool_ref();
+    assert_eq!(PoolId::P5.stats(), PoolStats::default());
+
+    let buf = BytesMut::with_capacity_in(1024, p);
+    let stats = PoolId::P5.stats();
+    assert_eq!(stats.allocated, 1024 + shared_vec());
+    assert_eq!(stats.in_use, stats.allocated);
+    drop(buf);
+    assert_eq!(PoolId::P5.stats().allocated, 0);
+
+    // cached io buffers are not in use
+    let buf = p.get_read_buf();
+    let cap = buf.capacity();
+    p.release_read_buf(buf);
+    let stats = PoolId::P5.stats();
+    assert_eq!(stats.in_use, stats.allocated - cap);
+    let _buf = p.get_read_buf();
+    let stats = PoolId::P5.stats();
+    assert_eq!(stats.in_use, stats.allocated);
+
+    // pool size limit
+    PoolId::P5.set_spawn_fn(|f| {
+        let _ = ntex::rt::spawn(f);
+    });
+    let pool = PoolId::P5.set_pool_size(1024).pool();
+    assert_eq!(Poll::Pending, util::lazy(|cx| pool.poll_ready(cx)).await);
+    assert_eq!(PoolId::P5.stats().hw_hits, 1);
+}
+
+#[test]
+fn pool_shrink() {
+    let p = PoolId::P11
+        .set_shrink_policy(Some(ShrinkPolicy::new(
+            8 * 1024,
+            4 * 1024,
+            time::Duration::from_millis(50),
+        )))
+        .pool_ref();
+    assert!(p.shrink_policy().is_some());
+
+    let bufs: Vec<_> = (0..4).map(|_| p.get_read_buf()).collect();
+    let cap = bufs[0].capacity();
+    for buf in bufs {
+        p.release_read_buf(buf);
+    }
+    let allocated = p.allocated();
+
+    // buffers were in use during first period
+    thread::sleep(time::Duration::from_millis(60));
+    assert_eq!(p.shrink_idle(), 0);
+    assert_eq!(p.allocated(), allocated);
+
+    // idle period is not passed
+    assert_eq!(p.shrink_idle(), 0);
+
+    // release unused buffers, keep low watermark
+    thread::sleep(time::Duration::from_millis(60));
+    assert_eq!(p.shrink_idle(), 3 * cap);
+    assert_eq!(p.allocated(), allocated - 3 * (cap + shared_vec()));
+
+    // below high watermark
+    thread::sleep(time::Duration::from_millis(60));
+    assert_eq!(p.shrink_idle(), 0);
+
+    p.set_shrink_policy(None);
+    assert_eq!(p.shrink_idle(), 0);
+}
+
+#[ntex::test]
+async fn pool_shrink_on_idle() {
+    let p = PoolId::P14
+        .set_spawn_fn(|f| {
+            let _ = ntex::rt::spawn(f);
+        })
+        .set_shrink_policy(Some(ShrinkPolicy::new(
+            8 * 1024,
+            4 * 1024,
+            time::Duration::from_millis(50),
+        )))
+        .pool_ref();
+
+    let bufs: Vec<_> = (0..4).map(|_| p.get_read_buf()).collect();
+    let cap = bufs[0].capacity();
+    for buf in bufs {
+        p.release_read_buf(buf);
+    }
+    let allocated = p.allocated();
+
+    // pool is idle, no more releases
+    ntex::time::sleep(ntex::time::Millis(300)).await;
+    assert_eq!(p.allocated(), allocated - 3 * (cap + shared_vec()));
+
+    p.set_shrink_policy(None);
+}
+
+#[cfg(feature = "pool-canary")]
+#[test]
+fn pool_canary() {
+    use ntex_bytes::CanaryViolation;
+    use std::{cell::RefCell, rc::Rc};
+
+    let violations = Rc::new(RefCell::new(Vec::new()));
+    let v = violations.clone();
+    let p_ref = PoolId::P12
+        .set_canary_fn(move |err| v.borrow_mut().push(err))
+        .pool_ref();
+
+    // untouched buffer passes validation
+    let buf = p_ref.get_read_buf();
+    p_ref.release_read_buf(buf);
+    let mut buf = p_ref.get_read_buf();
+    assert!(violations.borrow().is_empty());
+
+    // buffer modified after release
+    let ptr = buf.chunk_mut().as_mut_ptr();
+    p_ref.release_read_buf(buf);
+    unsafe { *ptr.add(10) = 0 };
+    let buf = p_ref.get_read_buf();
+    assert_eq!(
+        violations.borrow_mut().pop(),
+        Some(CanaryViolation::Modified {
+            pool: PoolId::P12,
+            offset: 10
+        })
+    );
+
+    // same storage is released twice
+    let dup = unsafe { std::ptr::read(&buf) };
+    p_ref.release_read_buf(buf);
+    p_ref.release_read_buf(dup);
+    assert_eq!(
+        violations.borrow_mut().pop(),
+        Some(CanaryViolation::DoubleRelease { pool: PoolId::P12 })
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn file_backed() {
+    use std::io::Write;
+
+    let path = std::env::temp_dir().join(format!("ntex-bytes-mmap-{}", std::process::id()));
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    std::fs::File::create(&path)
+        .unwrap()
+        .write_all(&data)
+        .unwrap();
+    let file = std::fs::File::open(&path).unwrap();
+
+    let p = PoolId::P13.pool_ref();
+    // file is not modified until mapping is dropped
+    let b = unsafe { Bytes::from_file_in(&file, 5000, 4000, p).unwrap() };
+    assert!(b.is_file_backed());
+    assert_eq!(&b[..], &data[5000..9000]);
+    assert!(p.allocated() >= 4000);
+
+    // slices share mapping
+    let s = b.slice(100..1100);
+    assert!(s.is_file_backed());
+    assert_eq!(&s[..], &data[5100..6100]);
+
+    // mutation copies data
+    drop(s);
+    let mut m = BytesMut::from(b);
+    m[0] = 0xff;
+    assert_eq!(&m[1..], &data[5001..9000]);
+    drop(m);
+    assert_eq!(p.allocated(), 0);
+
+    // out of bounds
+    unsafe {
+        assert!(Bytes::from_file(&file, 9000, 2000).is_err());
+        assert!(Bytes::from_file(&file, 0, 0).unwrap().is_empty());
+    }
+
+    drop(file);
+    let _ = std::fs::remove_file(&path);
+}
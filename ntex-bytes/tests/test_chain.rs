@@ -0,0 +1,75 @@
+#![deny(warnings, rust_2018_idioms)]
+
+use std::io::IoSlice;
+
+use ntex_bytes::{Buf, Bytes, BytesChain, BytesMut};
+
+#[test]
+fn test_chain() {
+    let mut chain = BytesChain::new();
+    assert!(chain.is_empty());
+    assert_eq!(chain.chunk(), b"");
+
+    chain.push(Bytes::from_static(b"hello"));
+    chain.push(Bytes::new());
+    chain.push(BytesMut::from(&b" "[..]));
+    chain.push(Bytes::from_static(b"world"));
+    assert_eq!(chain.len(), 11);
+    assert_eq!(chain.chunks_count(), 3);
+    assert_eq!(chain.remaining(), 11);
+    assert_eq!(chain.chunk(), b"hello");
+
+    chain.advance(3);
+    assert_eq!(chain.chunk(), b"lo");
+    chain.advance(3);
+    assert_eq!(chain.chunk(), b"world");
+    assert_eq!(chain.chunks_count(), 1);
+    assert_eq!(chain.len(), 5);
+
+    chain.push_front(Bytes::from_static(b"--"));
+    assert_eq!(chain.pop_front(), Some(Bytes::from_static(b"--")));
+    assert_eq!(chain.len(), 5);
+
+    let mut other: BytesChain = vec![&b"!"[..], &b"?"[..]].into_iter().collect();
+    chain.append(&mut other);
+    assert!(other.is_empty());
+    assert_eq!(chain.len(), 7);
+    assert_eq!(chain.clone().into_bytes(), Bytes::from_static(b"world!?"));
+    assert_eq!(chain.to_bytes(), Bytes::from_static(b"world!?"));
+    assert!(chain.is_empty());
+
+    chain.extend(vec![Bytes::from_static(b"a"), Bytes::from_static(b"b")]);
+    chain.clear();
+    assert!(chain.is_empty());
+    assert_eq!(chain.chunks_count(), 0);
+}
+
+#[test]
+fn test_chain_no_copy() {
+    let body = Bytes::from(vec![0; 1024]);
+    let chain = BytesChain::from(body.clone());
+    assert_eq!(chain.into_bytes().as_ptr(), body.as_ptr());
+
+    let mut chain = BytesChain::new();
+    chain.push(Bytes::from_static(b"head"));
+    chain.push(body.clone());
+
+    let mut slices = [IoSlice::new(&[]); 4];
+    assert_eq!(chain.io_slices(&mut slices), 2);
+    assert_eq!(&*slices[0], b"head");
+    assert_eq!(slices[1].as_ptr(), body.as_ptr());
+
+    let mut slices = [IoSlice::new(&[]); 4];
+    assert_eq!(bytes::Buf::chunks_vectored(&chain, &mut slices), 2);
+
+    let mut out = b"head".to_vec();
+    out.extend_from_slice(&[0; 1024]);
+    assert_eq!(chain.into_bytes(), Bytes::from(out));
+}
+
+#[test]
+#[should_panic]
+fn test_chain_advance_overflow() {
+    let mut chain = BytesChain::from(Bytes::from_static(b"hello"));
+    chain.advance(6);
+}
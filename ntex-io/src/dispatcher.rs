@@ -11,6 +11,24 @@ use crate::{rt::spawn, DispatchItem, IoBoxed, IoRef, RecvError, Timer};
 
 type Response<U> = <U as Encoder>::Item;
 
+/// Dispatcher lifecycle hooks.
+///
+/// Middleware observes dispatched items, encoded responses and errors
+/// without wrapping codec or service. All hooks are optional.
+pub trait DispatcherMiddleware<U: Encoder + Decoder, E> {
+    /// Called before item is passed to the service
+    fn on_item(&self, _item: &DispatchItem<U>) {}
+
+    /// Called before service response is encoded
+    fn on_encode(&self, _item: &<U as Encoder>::Item) {}
+
+    /// Called on service error
+    fn on_error(&self, _err: &E) {}
+
+    /// Called when dispatcher completes
+    fn on_close(&self) {}
+}
+
 pin_project_lite::pin_project! {
     /// Framed dispatcher - is a future that reads frames from bytes stream
     /// and pass then to the service.
@@ -38,10 +56,19 @@ where
     timer: Timer,
     ka_timeout: Cell<Seconds>,
     ka_updated: Cell<time::Instant>,
+    ka_handler: Option<KeepAliveHandler<U>>,
+    ka_pings: Cell<usize>,
     error: Cell<Option<S::Error>>,
     ready_err: Cell<bool>,
     shared: Rc<DispatcherShared<S, U>>,
     pool: Pool,
+    budget_items: usize,
+    budget_time: Option<time::Duration>,
+}
+
+struct KeepAliveHandler<U: Encoder> {
+    max_pings: usize,
+    ping: Box<dyn Fn() -> Response<U>>,
 }
 
 struct DispatcherShared<S, U>
@@ -52,6 +79,7 @@ where
     codec: U,
     error: Cell<Option<DispatcherError<S::Error, <U as Encoder>::Error>>>,
     inflight: Cell<usize>,
+    middleware: Vec<Box<dyn DispatcherMiddleware<U, S::Error>>>,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -112,6 +140,8 @@ where
             inner: DispatcherInner {
                 pool: io.memory_pool().pool(),
                 ka_updated: Cell::new(updated),
+                ka_handler: None,
+                ka_pings: Cell::new(0),
                 error: Cell::new(None),
                 ready_err: Cell::new(false),
                 st: Cell::new(DispatcherState::Processing),
@@ -119,10 +149,13 @@ where
                     codec,
                     error: Cell::new(None),
                     inflight: Cell::new(0),
+                    middleware: Vec::new(),
                 }),
                 io,
                 timer,
                 ka_timeout,
+                budget_items: 0,
+                budget_time: None,
             },
         }
     }
@@ -146,6 +179,57 @@ where
         self
     }
 
+    /// Set keep-alive ping handler.
+    ///
+    /// On keep-alive timeout dispatcher encodes ping frame produced by `ping`
+    /// function instead of stopping. Any incoming frame resets unanswered
+    /// pings counter. Dispatcher emits `KeepAliveTimeout` only after
+    /// `max_pings` unanswered pings.
+    ///
+    /// By default keep-alive handler is not set.
+    pub fn keepalive_handler<F>(mut self, max_pings: usize, ping: F) -> Self
+    where
+        F: Fn() -> Response<U> + 'static,
+    {
+        self.inner.ka_handler = Some(KeepAliveHandler {
+            max_pings,
+            ping: Box::new(ping),
+        });
+        self
+    }
+
+    /// Add dispatcher middleware.
+    ///
+    /// Middlewares are called in registration order.
+    pub fn middleware<M>(mut self, middleware: M) -> Self
+    where
+        M: DispatcherMiddleware<U, S::Error> + 'static,
+    {
+        Rc::get_mut(&mut self.inner.shared)
+            .expect("Dispatcher is running")
+            .middleware
+            .push(Box::new(middleware));
+        self
+    }
+
+    /// Set cooperative budget for single dispatcher poll.
+    ///
+    /// Dispatcher yields to other tasks after passing `items` items to
+    /// the service or after spending `time` in one poll, whichever comes
+    /// first. It keeps fairness between connections on the same arbiter
+    /// under load. Zero value disables corresponding limit.
+    ///
+    /// By default budget is not set.
+    pub fn poll_budget(mut self, items: usize, time: time::Duration) -> Self {
+        self.inner.budget_items = items;
+        self.inner.budget_time = if time == time::Duration::ZERO {
+            None
+        } else {
+            Some(time)
+        };
+        self
+    }
+
     /// Set connection disconnect timeout in seconds.
     ///
     /// Defines a timeout for disconnect connection. If a disconnect procedure does not complete
@@ -168,16 +252,28 @@ where
     fn handle_result(&self, item: Result<S::Response, S::Error>, io: &IoRef) {
         self.inflight.set(self.inflight.get() - 1);
         match item {
-            Ok(Some(val)) => {
-                if let Err(err) = io.encode(val, &self.codec) {
-                    self.error.set(Some(DispatcherError::Encoder(err)))
-                }
-            }
-            Err(err) => self.error.set(Some(DispatcherError::Service(err))),
+            Ok(Some(val)) => self.encode(val, io),
+            Err(err) => self.service_error(err),
             Ok(None) => return,
         }
         io.wake_dispatcher();
     }
+
+    fn encode(&self, item: Response<U>, io: &IoRef) {
+        self.middleware.iter().for_each(|m| m.on_encode(&item));
+        if let Err(err) = io.encode(item, &self.codec) {
+            self.error.set(Some(DispatcherError::Encoder(err)))
+        }
+    }
+
+    fn service_error(&self, err: S::Error) {
+        self.middleware.iter().for_each(|m| m.on_error(&err));
+        self.error.set(Some(DispatcherError::Service(err)))
+    }
+
+    fn on_item(&self, item: &DispatchItem<U>) {
+        self.middleware.iter().for_each(|m| m.on_item(item));
+    }
 }
 
 impl<S, U> future::Future for Dispatcher<S, U>
@@ -211,6 +307,9 @@ where
             return Poll::Pending;
         }
 
+        let mut processed = 0;
+        let started = slf.budget_time.map(|_| time::Instant::now());
+
         loop {
             match slf.st.get() {
                 DispatcherState::Processing => {
@@ -220,9 +319,13 @@ where
                             match ready!(io.poll_recv(&slf.shared.codec, cx)) {
                                 Ok(el) => {
                                     slf.update_keepalive();
+                                    slf.ka_pings.set(0);
                                     DispatchItem::Item(el)
                                 }
                                 Err(RecvError::KeepAlive) => {
+                                    if slf.send_ping() {
+                                        continue;
+                                    }
                                     slf.st.set(DispatcherState::Stop);
                                     DispatchItem::KeepAliveTimeout
                                 }
@@ -251,6 +354,7 @@ where
                     };
 
                     // call service
+                    slf.shared.on_item(&item);
                     if this.fut.is_none() {
                         // optimize first service call
                         this.fut.set(Some(this.service.call(item)));
@@ -266,6 +370,12 @@ where
                     } else {
                         slf.spawn_service_call(this.service.call(item));
                     }
+
+                    // yield to other tasks if poll budget is exhausted
+                    if slf.budget_exhausted(&mut processed, started) {
+                        cx.waker().wake_by_ref();
+                        return Poll::Pending;
+                    }
                 }
                 // handle write back-pressure
                 DispatcherState::Backpressure => {
@@ -284,6 +394,7 @@ where
                     };
 
                     // call service
+                    slf.shared.on_item(&item);
                     if this.fut.is_none() {
                         // optimize first service call
                         this.fut.set(Some(this.service.call(item)));
@@ -299,6 +410,12 @@ where
                     } else {
                         slf.spawn_service_call(this.service.call(item));
                     }
+
+                    // yield to other tasks if poll budget is exhausted
+                    if slf.budget_exhausted(&mut processed, started) {
+                        cx.waker().wake_by_ref();
+                        return Poll::Pending;
+                    }
                 }
                 // drain service responses and shutdown io
                 DispatcherState::Stop => {
@@ -321,10 +438,15 @@ where
                 }
                 // shutdown service
                 DispatcherState::Shutdown => {
+                    // wait for connection scope tasks
+                    if slf.io.poll_scope_shutdown(cx).is_pending() {
+                        return Poll::Pending;
+                    }
                     let err = slf.error.take();
 
                     return if this.service.poll_shutdown(cx, err.is_some()).is_ready() {
                         log::trace!("service shutdown is completed, stop");
+                        slf.shared.middleware.iter().for_each(|m| m.on_close());
 
                         Poll::Ready(if let Some(err) = err {
                             Err(err)
@@ -364,12 +486,8 @@ where
         io: &IoRef,
     ) {
         match item {
-            Ok(Some(item)) => {
-                if let Err(err) = io.encode(item, &self.shared.codec) {
-                    self.shared.error.set(Some(DispatcherError::Encoder(err)))
-                }
-            }
-            Err(err) => self.shared.error.set(Some(DispatcherError::Service(err))),
+            Ok(Some(item)) => self.shared.encode(item, io),
+            Err(err) => self.shared.service_error(err),
             Ok(None) => (),
         }
     }
@@ -415,6 +533,7 @@ where
             // handle service readiness error
             Poll::Ready(Err(err)) => {
                 log::trace!("service readiness check failed, stopping");
+                self.shared.middleware.iter().for_each(|m| m.on_error(&err));
                 self.st.set(DispatcherState::Stop);
                 self.error.set(Some(err));
                 self.ready_err.set(true);
@@ -423,6 +542,21 @@ where
         }
     }
 
+    fn budget_exhausted(
+        &self,
+        processed: &mut usize,
+        started: Option<time::Instant>,
+    ) -> bool {
+        *processed += 1;
+        if self.budget_items != 0 && *processed >= self.budget_items {
+            return true;
+        }
+        match (started, self.budget_time) {
+            (Some(started), Some(budget)) => started.elapsed() >= budget,
+            _ => false,
+        }
+    }
+
     fn ka(&self) -> Seconds {
         self.ka_timeout.get()
     }
@@ -433,7 +567,7 @@ where
 
     /// check keepalive timeout
     fn check_keepalive(&self) {
-        if self.io.is_keepalive() {
+        if self.io.is_keepalive() && !self.send_ping() {
             log::trace!("keepalive timeout");
             if let Some(err) = self.shared.error.take() {
                 self.shared.error.set(Some(err));
@@ -443,6 +577,28 @@ where
         }
     }
 
+    /// send keep-alive ping, returns false if max unanswered pings is reached
+    fn send_ping(&self) -> bool {
+        if let Some(ref handler) = self.ka_handler {
+            let pings = self.ka_pings.get();
+            if pings < handler.max_pings && self.ka_enabled() {
+                log::trace!("keepalive timeout, send ping {}", pings + 1);
+                self.ka_pings.set(pings + 1);
+                self.io.reset_keepalive();
+                self.shared.encode((handler.ping)(), self.io.as_ref());
+
+                // restart keep-alive timer
+                let updated = now();
+                let ka = time::Duration::from(self.ka());
+                self.timer
+                    .register(updated + ka, self.ka_updated.get() + ka, &self.io);
+                self.ka_updated.set(updated);
+                return true;
+            }
+        }
+        false
+    }
+
     /// update keep-alive timer
     fn update_keepalive(&self) {
         if self.ka_enabled() {
@@ -472,11 +628,11 @@ where
 mod tests {
     use rand::Rng;
     use std::sync::{atomic::AtomicBool, atomic::Ordering::Relaxed, Arc, Mutex};
-    use std::{cell::RefCell, time::Duration};
+    use std::{cell::RefCell, future::Future, time::Duration};
 
-    use ntex_bytes::{Bytes, PoolId, PoolRef};
+    use ntex_bytes::{Bytes, BytesMut, PoolId, PoolRef};
     use ntex_codec::BytesCodec;
-    use ntex_util::future::Ready;
+    use ntex_util::future::{lazy, Ready};
     use ntex_util::time::{sleep, Millis};
 
     use crate::testing::IoTest;
@@ -524,6 +680,7 @@ mod tests {
                 codec: codec,
                 error: Cell::new(None),
                 inflight: Cell::new(0),
+                middleware: Vec::new(),
             });
             let inner = State(state.get_ref());
 
@@ -536,6 +693,8 @@ mod tests {
                     fut: None,
                     inner: DispatcherInner {
                         ka_updated: Cell::new(ka_updated),
+                        ka_handler: None,
+                        ka_pings: Cell::new(0),
                         error: Cell::new(None),
                         ready_err: Cell::new(false),
                         st: Cell::new(DispatcherState::Processing),
@@ -544,6 +703,8 @@ mod tests {
                         shared,
                         timer,
                         ka_timeout,
+                        budget_items: 0,
+                        budget_time: None,
                     },
                 },
                 inner,
@@ -585,6 +746,64 @@ mod tests {
         assert!(client.is_server_dropped());
     }
 
+    #[ntex::test]
+    async fn test_scope() {
+        struct Guard(Rc<Cell<bool>>);
+        impl Drop for Guard {
+            fn drop(&mut self) {
+                self.0.set(true);
+            }
+        }
+
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        client.write("GET /test HTTP/1\r\n\r\n");
+
+        let dropped = Rc::new(Cell::new(false));
+        let dropped2 = dropped.clone();
+        let io = Rc::new(Cell::new(None));
+        let io2 = io.clone();
+        let (disp, st) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            ntex_service::fn_service(move |msg: DispatchItem<BytesCodec>| {
+                let guard = Guard(dropped2.clone());
+                let io: Option<IoRef> = io2.take();
+                async move {
+                    if let DispatchItem::Item(msg) = msg {
+                        io.unwrap().scope().spawn(async move {
+                            let _guard = guard;
+                            sleep(Millis(100_000)).await;
+                        });
+                        Ok::<_, ()>(Some(msg.freeze()))
+                    } else {
+                        Ok(None)
+                    }
+                }
+            }),
+        );
+        io.set(Some(st.io().clone()));
+
+        let completed = Rc::new(Cell::new(None));
+        let completed2 = completed.clone();
+        let dropped3 = dropped.clone();
+        spawn(async move {
+            let _ = disp.await;
+            // scope tasks are dropped before dispatcher completes
+            completed2.set(Some(dropped3.get()));
+        });
+
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"GET /test HTTP/1\r\n\r\n"));
+        assert_eq!(st.io().scope().len(), 1);
+        assert!(!dropped.get());
+
+        client.close().await;
+        sleep(Millis(50)).await;
+        assert!(dropped.get());
+        assert_eq!(completed.get(), Some(true));
+    }
+
     #[ntex::test]
     async fn test_sink() {
         let (client, server) = IoTest::create();
@@ -837,6 +1056,58 @@ mod tests {
         assert_eq!(&data.lock().unwrap().borrow()[..], &[0, 1]);
     }
 
+    #[ntex::test]
+    async fn test_keepalive_ping() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        client.write("GET /test HTTP/1\r\n\r\n");
+
+        let timeout = Rc::new(Cell::new(false));
+        let timeout2 = timeout.clone();
+        let (disp, state) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            ntex_service::fn_service(move |msg: DispatchItem<BytesCodec>| {
+                let timeout = timeout2.clone();
+                async move {
+                    match msg {
+                        DispatchItem::Item(msg) => return Ok::<_, ()>(Some(msg.freeze())),
+                        DispatchItem::KeepAliveTimeout => timeout.set(true),
+                        _ => (),
+                    }
+                    Ok(None)
+                }
+            }),
+        );
+        spawn(async move {
+            let _ = disp
+                .keepalive_handler(2, || Bytes::from_static(b"PING"))
+                .await;
+        });
+        state.0 .0.disconnect_timeout.set(Millis::ONE_SEC);
+
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"GET /test HTTP/1\r\n\r\n"));
+
+        // first ping, peer responds
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"PING"));
+        client.write("PONG");
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"PONG"));
+
+        // two unanswered pings
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"PING"));
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"PING"));
+        assert!(!timeout.get());
+
+        sleep(Millis(2500)).await;
+        assert!(timeout.get());
+        assert!(state.flags().contains(Flags::IO_SHUTDOWN));
+    }
+
     #[ntex::test]
     async fn test_unhandled_data() {
         let handled = Arc::new(AtomicBool::new(false));
@@ -869,4 +1140,123 @@ mod tests {
 
         assert!(handled.load(Relaxed));
     }
+
+    #[ntex::test]
+    async fn test_middleware() {
+        struct Events(Rc<RefCell<Vec<&'static str>>>);
+
+        impl DispatcherMiddleware<BytesCodec, ()> for Events {
+            fn on_item(&self, item: &DispatchItem<BytesCodec>) {
+                self.0.borrow_mut().push(match item {
+                    DispatchItem::Item(_) => "item",
+                    _ => "control",
+                });
+            }
+            fn on_encode(&self, _: &Bytes) {
+                self.0.borrow_mut().push("encode");
+            }
+            fn on_error(&self, _: &()) {
+                self.0.borrow_mut().push("error");
+            }
+            fn on_close(&self) {
+                self.0.borrow_mut().push("close");
+            }
+        }
+
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        client.write("GET /test HTTP/1\r\n\r\n");
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let disp = Dispatcher::new(
+            Io::new(server),
+            BytesCodec,
+            ntex_service::fn_service(|msg: DispatchItem<BytesCodec>| async move {
+                match msg {
+                    DispatchItem::Item(msg) if msg.starts_with(b"GET") => {
+                        Ok(Some(msg.freeze()))
+                    }
+                    DispatchItem::Item(_) => Err(()),
+                    _ => Ok(None),
+                }
+            }),
+            Timer::default(),
+        )
+        .middleware(Events(events.clone()));
+        spawn(async move {
+            let _ = disp.await;
+        });
+
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"GET /test HTTP/1\r\n\r\n"));
+        assert_eq!(&*events.borrow(), &["item", "encode"]);
+
+        client.write("POST /test HTTP/1\r\n\r\n");
+        sleep(Millis(50)).await;
+        client.close().await;
+        sleep(Millis(50)).await;
+        assert_eq!(&events.borrow()[..4], &["item", "encode", "item", "error"]);
+        assert_eq!(events.borrow().last(), Some(&"close"));
+    }
+
+    /// Decodes one byte per item
+    struct ByteCodec;
+
+    impl Encoder for ByteCodec {
+        type Item = Bytes;
+        type Error = std::io::Error;
+
+        fn encode(&self, item: Bytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+            dst.extend_from_slice(&item[..]);
+            Ok(())
+        }
+    }
+
+    impl Decoder for ByteCodec {
+        type Item = BytesMut;
+        type Error = std::io::Error;
+
+        fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+            if src.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(src.split_to(1)))
+            }
+        }
+    }
+
+    #[ntex::test]
+    async fn test_poll_budget() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        client.write("abcde");
+
+        let counter = Rc::new(Cell::new(0));
+        let cnt = counter.clone();
+        let disp = Dispatcher::new(
+            Io::new(server),
+            ByteCodec,
+            ntex_service::fn_service(move |msg: DispatchItem<ByteCodec>| {
+                if let DispatchItem::Item(_) = msg {
+                    cnt.set(cnt.get() + 1);
+                }
+                Ready::<_, ()>::Ok(None)
+            }),
+            Timer::default(),
+        )
+        .poll_budget(2, Duration::ZERO);
+        let mut disp = Box::pin(disp);
+        sleep(Millis(25)).await;
+
+        // dispatcher yields after two items
+        let res = lazy(|cx| disp.as_mut().poll(cx)).await;
+        assert!(res.is_pending());
+        assert_eq!(counter.get(), 2);
+
+        let _ = lazy(|cx| disp.as_mut().poll(cx)).await;
+        assert_eq!(counter.get(), 4);
+
+        let _ = lazy(|cx| disp.as_mut().poll(cx)).await;
+        assert_eq!(counter.get(), 5);
+    }
 }
@@ -120,7 +120,7 @@ impl Filter for Base {
         if buf.is_empty() {
             pool.release_write_buf(buf);
         } else {
-            if buf.len() >= pool.write_params_high() {
+            if buf.len() >= self.0.write_params().high as usize {
                 self.0 .0.insert_flags(Flags::WR_BACKPRESSURE);
             }
             self.0 .0.write_buf.set(Some(buf));
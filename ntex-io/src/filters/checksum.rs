@@ -0,0 +1,395 @@
+//! Frame integrity filter
+use std::{any, cell::RefCell, convert::TryInto, io, task::Context, task::Poll};
+
+use ntex_bytes::{Buf, BufMut, BytesMut, PoolRef};
+use ntex_util::future::Ready;
+
+use crate::{Base, Filter, FilterFactory, Io, ReadStatus, WriteStatus};
+
+const HEADER_SIZE: usize = 4;
+const CHECKSUM_SIZE: usize = 4;
+const DEFAULT_FRAME_SIZE: usize = 16 * 1024;
+
+/// Checksum algorithm
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ChecksumType {
+    /// CRC-32 (IEEE 802.3)
+    Crc32,
+    /// CRC-32C (Castagnoli)
+    Crc32c,
+}
+
+impl ChecksumType {
+    /// Calculate checksum of the data
+    pub fn checksum(&self, data: &[u8]) -> u32 {
+        let table = match self {
+            ChecksumType::Crc32 => &CRC32_TABLE,
+            ChecksumType::Crc32c => &CRC32C_TABLE,
+        };
+        !data.iter().fold(!0u32, |crc, b| {
+            table[((crc ^ *b as u32) & 0xff) as usize] ^ (crc >> 8)
+        })
+    }
+}
+
+/// Checksum filter factory
+///
+/// Filter splits written data into frames, each frame is prefixed with
+/// payload length and followed by payload checksum. Both values are
+/// 32 bit big-endian integers. Read frames are validated and unwrapped,
+/// checksum mismatch or oversized frame terminates io stream with
+/// `InvalidData` error.
+///
+/// ```rust
+/// use ntex_io::{Io, filters::Checksum};
+///
+/// async fn wrap(io: Io) -> std::io::Result<()> {
+///     let io = io.add_filter(Checksum::crc32c().max_frame_size(4096)).await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Checksum {
+    tp: ChecksumType,
+    max_frame_size: usize,
+}
+
+impl Checksum {
+    /// Create checksum filter factory for specified algorithm
+    pub fn new(tp: ChecksumType) -> Self {
+        Checksum {
+            tp,
+            max_frame_size: DEFAULT_FRAME_SIZE,
+        }
+    }
+
+    /// Create crc32 checksum filter factory
+    pub fn crc32() -> Self {
+        Self::new(ChecksumType::Crc32)
+    }
+
+    /// Create crc32c checksum filter factory
+    pub fn crc32c() -> Self {
+        Self::new(ChecksumType::Crc32c)
+    }
+
+    /// Set max frame payload size.
+    ///
+    /// Written data is split into frames of this size, read frames
+    /// with larger payload are rejected. By default max size is 16Kb.
+    ///
+    /// Panics if size is 0.
+    pub fn max_frame_size(mut self, size: usize) -> Self {
+        assert!(size > 0, "Frame size must be greater than 0");
+        self.max_frame_size = size;
+        self
+    }
+}
+
+impl<F: Filter> FilterFactory<F> for Checksum {
+    type Filter = ChecksumFilter<F>;
+
+    type Error = io::Error;
+    type Future = Ready<Io<Self::Filter>, Self::Error>;
+
+    fn create(self, st: Io<F>) -> Self::Future {
+        let pool = st.memory_pool();
+
+        Ready::from(
+            st.map_filter(|inner: F| {
+                Ok::<_, io::Error>(ChecksumFilter {
+                    inner,
+                    pool,
+                    tp: self.tp,
+                    max_frame_size: self.max_frame_size,
+                    partial: RefCell::new(BytesMut::new()),
+                })
+            })
+            .and_then(|io| {
+                // decode frames that are already read from io stream
+                let result = io.with_read_buf(|buf| {
+                    if buf.is_empty() {
+                        Ok(())
+                    } else {
+                        let raw = buf.split();
+                        let result = io.filter().decode(&raw, buf);
+                        pool.release_read_buf(raw);
+                        result.map(|_| ())
+                    }
+                });
+                result.map(|_| io)
+            }),
+        )
+    }
+}
+
+/// Checksum filter
+pub struct ChecksumFilter<F = Base> {
+    inner: F,
+    pool: PoolRef,
+    tp: ChecksumType,
+    max_frame_size: usize,
+    partial: RefCell<BytesMut>,
+}
+
+impl<F> ChecksumFilter<F> {
+    /// Decode complete frames and append payloads to the buffer,
+    /// incomplete frame is stored until more data is available
+    fn decode(&self, src: &[u8], dst: &mut BytesMut) -> io::Result<usize> {
+        let mut partial = self.partial.borrow_mut();
+        partial.extend_from_slice(src);
+
+        let mut nbytes = 0;
+        while partial.len() >= HEADER_SIZE {
+            let len =
+                u32::from_be_bytes(partial[..HEADER_SIZE].try_into().unwrap()) as usize;
+            if len > self.max_frame_size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Frame size {} exceeds max frame size", len),
+                ));
+            }
+            if partial.len() < HEADER_SIZE + len + CHECKSUM_SIZE {
+                break;
+            }
+
+            let payload = &partial[HEADER_SIZE..HEADER_SIZE + len];
+            let checksum = u32::from_be_bytes(
+                partial[HEADER_SIZE + len..HEADER_SIZE + len + CHECKSUM_SIZE]
+                    .try_into()
+                    .unwrap(),
+            );
+            if checksum != self.tp.checksum(payload) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Frame checksum mismatch",
+                ));
+            }
+            dst.extend_from_slice(payload);
+            partial.advance(HEADER_SIZE + len + CHECKSUM_SIZE);
+            nbytes += len;
+        }
+        Ok(nbytes)
+    }
+
+    /// Split data into frames
+    fn encode(&self, src: &[u8], dst: &mut BytesMut) {
+        for chunk in src.chunks(self.max_frame_size) {
+            dst.reserve(HEADER_SIZE + chunk.len() + CHECKSUM_SIZE);
+            dst.put_u32(chunk.len() as u32);
+            dst.extend_from_slice(chunk);
+            dst.put_u32(self.tp.checksum(chunk));
+        }
+    }
+}
+
+impl<F: Filter> Filter for ChecksumFilter<F> {
+    #[inline]
+    fn query(&self, id: any::TypeId) -> Option<Box<dyn any::Any>> {
+        self.inner.query(id)
+    }
+
+    #[inline]
+    fn want_read(&self) {
+        self.inner.want_read()
+    }
+
+    #[inline]
+    fn want_shutdown(&self, err: Option<io::Error>) {
+        self.inner.want_shutdown(err)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self) -> Poll<io::Result<()>> {
+        self.inner.poll_shutdown()
+    }
+
+    #[inline]
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<ReadStatus> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    #[inline]
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<WriteStatus> {
+        self.inner.poll_write_ready(cx)
+    }
+
+    #[inline]
+    fn closed(&self, err: Option<io::Error>) {
+        self.inner.closed(err)
+    }
+
+    #[inline]
+    fn get_read_buf(&self) -> Option<BytesMut> {
+        // all incoming data is consumed by decoder
+        None
+    }
+
+    #[inline]
+    fn get_write_buf(&self) -> Option<BytesMut> {
+        // all outgoing data is consumed by encoder
+        None
+    }
+
+    fn release_read_buf(
+        &self,
+        src: BytesMut,
+        dst: &mut Option<BytesMut>,
+        nbytes: usize,
+    ) -> io::Result<usize> {
+        let mut raw = None;
+        self.inner.release_read_buf(src, &mut raw, nbytes)?;
+
+        let raw = match raw {
+            Some(raw) if !raw.is_empty() => raw,
+            Some(raw) => {
+                self.pool.release_read_buf(raw);
+                return Ok(0);
+            }
+            None => return Ok(0),
+        };
+
+        let mut buf = dst.take().unwrap_or_else(|| self.pool.get_read_buf());
+        let result = self.decode(&raw, &mut buf);
+        self.pool.release_read_buf(raw);
+        *dst = Some(buf);
+        result
+    }
+
+    fn release_write_buf(&self, buf: BytesMut) -> io::Result<()> {
+        if buf.is_empty() {
+            self.pool.release_write_buf(buf);
+            return Ok(());
+        }
+
+        let mut dst = self
+            .inner
+            .get_write_buf()
+            .unwrap_or_else(|| self.pool.get_write_buf());
+        self.encode(&buf, &mut dst);
+        self.pool.release_write_buf(buf);
+        self.inner.release_write_buf(dst)
+    }
+}
+
+const fn make_table(poly: u32) -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ poly
+            } else {
+                crc >> 1
+            };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+static CRC32_TABLE: [u32; 256] = make_table(0xEDB8_8320);
+static CRC32C_TABLE: [u32; 256] = make_table(0x82F6_3B78);
+
+#[cfg(test)]
+mod tests {
+    use ntex_bytes::Bytes;
+    use ntex_codec::BytesCodec;
+
+    use super::*;
+    use crate::testing::IoTest;
+
+    const TEXT: &[u8] = b"GET /test HTTP/1.1\r\nHost: localhost\r\n\r\n";
+
+    #[test]
+    fn checksum() {
+        assert_eq!(ChecksumType::Crc32.checksum(b"123456789"), 0xCBF4_3926);
+        assert_eq!(ChecksumType::Crc32c.checksum(b"123456789"), 0xE306_9283);
+    }
+
+    #[ntex::test]
+    async fn roundtrip() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        server.remote_buffer_cap(1024);
+
+        let client = Io::new(client)
+            .add_filter(Checksum::crc32c().max_frame_size(8))
+            .await
+            .unwrap();
+        let server = Io::new(server)
+            .add_filter(Checksum::crc32c().max_frame_size(8))
+            .await
+            .unwrap();
+
+        client
+            .send(Bytes::from_static(TEXT), &BytesCodec)
+            .await
+            .unwrap();
+        let msg = server.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(msg, Bytes::from_static(TEXT));
+
+        server
+            .send(Bytes::from_static(b"response"), &BytesCodec)
+            .await
+            .unwrap();
+        let msg = client.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(msg, Bytes::from_static(b"response"));
+    }
+
+    #[ntex::test]
+    async fn framing() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+
+        let io = Io::new(server)
+            .add_filter(Checksum::crc32().max_frame_size(4))
+            .await
+            .unwrap();
+        io.send(Bytes::from_static(b"hello"), &BytesCodec)
+            .await
+            .unwrap();
+
+        let buf = client.read().await.unwrap();
+        let mut expected = BytesMut::new();
+        expected.put_u32(4);
+        expected.extend_from_slice(b"hell");
+        expected.put_u32(ChecksumType::Crc32.checksum(b"hell"));
+        expected.put_u32(1);
+        expected.extend_from_slice(b"o");
+        expected.put_u32(ChecksumType::Crc32.checksum(b"o"));
+        assert_eq!(&buf[..], &expected[..]);
+
+        // frame split across reads
+        client.write(&expected[..6]);
+        client.write(&expected[6..]);
+        let msg = io.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(msg, Bytes::from_static(b"hello"));
+
+        // corrupted frame
+        let mut corrupted = expected.clone();
+        corrupted[5] = b'X';
+        client.write(&corrupted);
+        assert!(io.recv(&BytesCodec).await.is_err());
+    }
+
+    #[ntex::test]
+    async fn oversized_frame() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+
+        let io = Io::new(server)
+            .add_filter(Checksum::crc32().max_frame_size(4))
+            .await
+            .unwrap();
+
+        let mut frame = BytesMut::new();
+        frame.put_u32(5);
+        client.write(&frame);
+        assert!(io.recv(&BytesCodec).await.is_err());
+    }
+}
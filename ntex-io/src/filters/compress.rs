@@ -0,0 +1,567 @@
+//! Transport compression filter
+use std::{any, cell::RefCell, io, io::Write, mem, task::Context, task::Poll};
+
+use flate2::write::{DeflateDecoder, DeflateEncoder, GzDecoder, GzEncoder};
+use ntex_bytes::{BytesMut, PoolRef};
+use ntex_util::future::Ready;
+
+use crate::{Base, Filter, FilterFactory, Io, ReadStatus, WriteStatus};
+
+/// Compression algorithm
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CompressionType {
+    /// Raw deflate stream
+    Deflate,
+    /// Gzip stream
+    Gzip,
+    /// Zstandard stream
+    Zstd,
+}
+
+/// Default max size of decoded data in read buffer
+const DEFAULT_MAX_DECODED: usize = 1024 * 1024;
+
+/// Compression filter factory
+///
+/// Filter compresses all written data and decompresses all read data.
+/// Each write is flushed, so peer can decode data as soon as it arrives.
+#[derive(Copy, Clone, Debug)]
+pub struct Compression {
+    tp: CompressionType,
+    level: u32,
+    window_log: Option<u32>,
+    long_distance: bool,
+    max_decoded: usize,
+}
+
+impl Compression {
+    /// Create compression filter factory for specified algorithm
+    pub fn new(tp: CompressionType) -> Self {
+        let level = match tp {
+            CompressionType::Deflate | CompressionType::Gzip => 6,
+            CompressionType::Zstd => 3,
+        };
+        Compression {
+            tp,
+            level,
+            window_log: None,
+            long_distance: false,
+            max_decoded: DEFAULT_MAX_DECODED,
+        }
+    }
+
+    /// Create deflate compression filter factory
+    pub fn deflate() -> Self {
+        Self::new(CompressionType::Deflate)
+    }
+
+    /// Create gzip compression filter factory
+    pub fn gzip() -> Self {
+        Self::new(CompressionType::Gzip)
+    }
+
+    /// Create zstd compression filter factory
+    pub fn zstd() -> Self {
+        Self::new(CompressionType::Zstd)
+    }
+
+    /// Set compression level.
+    ///
+    /// Deflate and gzip levels are in range 0-9 (default is 6),
+    /// zstd levels are in range 1-21 (default is 3).
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Set zstd window size as log2 of bytes, range is 10-31.
+    ///
+    /// Both peers must use the same window size if it exceeds 27.
+    /// By default window size is selected by compression level.
+    pub fn window_log(mut self, log: u32) -> Self {
+        self.window_log = Some(log.clamp(10, 31));
+        self
+    }
+
+    /// Enable zstd long distance matching.
+    ///
+    /// Disabled by default.
+    pub fn long_distance_matching(mut self, enabled: bool) -> Self {
+        self.long_distance = enabled;
+        self
+    }
+
+    /// Set max size of decoded data in read buffer.
+    ///
+    /// Read fails with `InvalidData` error if decompressed data that is not
+    /// consumed yet exceeds limit. By default limit is set to 1Mb.
+    pub fn max_decoded_size(mut self, limit: usize) -> Self {
+        self.max_decoded = limit;
+        self
+    }
+}
+
+impl<F: Filter> FilterFactory<F> for Compression {
+    type Filter = CompressionFilter<F>;
+
+    type Error = io::Error;
+    type Future = Ready<Io<Self::Filter>, Self::Error>;
+
+    fn create(self, st: Io<F>) -> Self::Future {
+        let pool = st.memory_pool();
+
+        Ready::from(
+            st.map_filter(|inner: F| {
+                Ok::<_, io::Error>(CompressionFilter {
+                    inner,
+                    pool,
+                    encoder: RefCell::new(Some(Encoder::new(&self)?)),
+                    decoder: RefCell::new(Decoder::new(&self)?),
+                })
+            })
+            .and_then(|io| {
+                // decode data that is already read from io stream
+                let result = io.with_read_buf(|buf| {
+                    if buf.is_empty() {
+                        Ok(())
+                    } else {
+                        let raw = buf.split();
+                        let decoded = io
+                            .filter()
+                            .decoder
+                            .borrow_mut()
+                            .decode(&raw, mem::take(buf))?;
+                        *buf = decoded;
+                        pool.release_read_buf(raw);
+                        Ok(())
+                    }
+                });
+                result.map(|_| io)
+            }),
+        )
+    }
+}
+
+/// Compression filter
+pub struct CompressionFilter<F = Base> {
+    inner: F,
+    pool: PoolRef,
+    encoder: RefCell<Option<Encoder>>,
+    decoder: RefCell<Decoder>,
+}
+
+impl<F: Filter> Filter for CompressionFilter<F> {
+    #[inline]
+    fn query(&self, id: any::TypeId) -> Option<Box<dyn any::Any>> {
+        self.inner.query(id)
+    }
+
+    #[inline]
+    fn want_read(&self) {
+        self.inner.want_read()
+    }
+
+    #[inline]
+    fn want_shutdown(&self, err: Option<io::Error>) {
+        self.inner.want_shutdown(err)
+    }
+
+    fn poll_shutdown(&self) -> Poll<io::Result<()>> {
+        // write end of compressed stream
+        if let Some(mut encoder) = self.encoder.borrow_mut().take() {
+            let buf = self
+                .inner
+                .get_write_buf()
+                .unwrap_or_else(|| self.pool.get_write_buf());
+            let buf = encoder.finish(buf)?;
+            self.inner.release_write_buf(buf)?;
+        }
+        self.inner.poll_shutdown()
+    }
+
+    #[inline]
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<ReadStatus> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    #[inline]
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<WriteStatus> {
+        self.inner.poll_write_ready(cx)
+    }
+
+    #[inline]
+    fn closed(&self, err: Option<io::Error>) {
+        self.inner.closed(err)
+    }
+
+    #[inline]
+    fn get_read_buf(&self) -> Option<BytesMut> {
+        // all incoming data is consumed by decoder
+        None
+    }
+
+    #[inline]
+    fn get_write_buf(&self) -> Option<BytesMut> {
+        // all outgoing data is consumed by encoder
+        None
+    }
+
+    fn release_read_buf(
+        &self,
+        src: BytesMut,
+        dst: &mut Option<BytesMut>,
+        nbytes: usize,
+    ) -> io::Result<usize> {
+        let mut raw = None;
+        self.inner.release_read_buf(src, &mut raw, nbytes)?;
+
+        let raw = match raw {
+            Some(raw) if !raw.is_empty() => raw,
+            Some(raw) => {
+                self.pool.release_read_buf(raw);
+                return Ok(0);
+            }
+            None => return Ok(0),
+        };
+
+        let buf = dst.take().unwrap_or_else(|| self.pool.get_read_buf());
+        let len = buf.len();
+        let result = self.decoder.borrow_mut().decode(&raw, buf);
+        self.pool.release_read_buf(raw);
+
+        let buf = result?;
+        let nbytes = buf.len() - len;
+        *dst = Some(buf);
+        Ok(nbytes)
+    }
+
+    fn release_write_buf(&self, buf: BytesMut) -> io::Result<()> {
+        if buf.is_empty() {
+            self.pool.release_write_buf(buf);
+            return Ok(());
+        }
+
+        if let Some(ref mut encoder) = *self.encoder.borrow_mut() {
+            let dst = self
+                .inner
+                .get_write_buf()
+                .unwrap_or_else(|| self.pool.get_write_buf());
+            let result = encoder.encode(&buf, dst);
+            self.pool.release_write_buf(buf);
+            self.inner.release_write_buf(result?)
+        } else {
+            self.pool.release_write_buf(buf);
+            Err(io::Error::other("Compression stream is finished"))
+        }
+    }
+}
+
+struct Writer {
+    buf: BytesMut,
+    limit: usize,
+}
+
+impl io::Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buf.len() + buf.len() > self.limit {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Decompressed data exceeds limit",
+            ));
+        }
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Writer {
+    fn new() -> Self {
+        Writer::with_limit(usize::MAX)
+    }
+
+    fn with_limit(limit: usize) -> Self {
+        Writer {
+            buf: BytesMut::new(),
+            limit,
+        }
+    }
+}
+
+/// Run operation, stream writes output to provided buffer
+fn process<W, F>(
+    stream: &mut W,
+    writer: fn(&mut W) -> &mut Writer,
+    dst: BytesMut,
+    f: F,
+) -> io::Result<BytesMut>
+where
+    F: FnOnce(&mut W) -> io::Result<()>,
+{
+    writer(stream).buf = dst;
+    let result = f(stream);
+    let buf = mem::take(&mut writer(stream).buf);
+    result.map(|_| buf)
+}
+
+enum Encoder {
+    Deflate(Box<DeflateEncoder<Writer>>),
+    Gzip(Box<GzEncoder<Writer>>),
+    Zstd(Box<zstd::stream::write::Encoder<'static, Writer>>),
+}
+
+impl Encoder {
+    fn new(cfg: &Compression) -> io::Result<Self> {
+        Ok(match cfg.tp {
+            CompressionType::Deflate => Encoder::Deflate(Box::new(DeflateEncoder::new(
+                Writer::new(),
+                flate2::Compression::new(cfg.level),
+            ))),
+            CompressionType::Gzip => Encoder::Gzip(Box::new(GzEncoder::new(
+                Writer::new(),
+                flate2::Compression::new(cfg.level),
+            ))),
+            CompressionType::Zstd => {
+                let mut enc =
+                    zstd::stream::write::Encoder::new(Writer::new(), cfg.level as i32)?;
+                if let Some(log) = cfg.window_log {
+                    enc.window_log(log)?;
+                }
+                if cfg.long_distance {
+                    enc.long_distance_matching(true)?;
+                }
+                Encoder::Zstd(Box::new(enc))
+            }
+        })
+    }
+
+    /// Compress and flush data
+    fn encode(&mut self, src: &[u8], dst: BytesMut) -> io::Result<BytesMut> {
+        match self {
+            Encoder::Deflate(enc) => {
+                process(enc.as_mut(), DeflateEncoder::get_mut, dst, |e| {
+                    e.write_all(src)?;
+                    e.flush()
+                })
+            }
+            Encoder::Gzip(enc) => process(enc.as_mut(), GzEncoder::get_mut, dst, |e| {
+                e.write_all(src)?;
+                e.flush()
+            }),
+            Encoder::Zstd(enc) => process(
+                enc.as_mut(),
+                zstd::stream::write::Encoder::get_mut,
+                dst,
+                |e| {
+                    e.write_all(src)?;
+                    e.flush()
+                },
+            ),
+        }
+    }
+
+    /// Write end of stream
+    fn finish(&mut self, dst: BytesMut) -> io::Result<BytesMut> {
+        match self {
+            Encoder::Deflate(enc) => {
+                process(enc.as_mut(), DeflateEncoder::get_mut, dst, |e| {
+                    e.try_finish()
+                })
+            }
+            Encoder::Gzip(enc) => {
+                process(enc.as_mut(), GzEncoder::get_mut, dst, |e| e.try_finish())
+            }
+            Encoder::Zstd(enc) => process(
+                enc.as_mut(),
+                zstd::stream::write::Encoder::get_mut,
+                dst,
+                |e| e.do_finish(),
+            ),
+        }
+    }
+}
+
+enum Decoder {
+    Deflate(Box<DeflateDecoder<Writer>>),
+    Gzip(Box<GzDecoder<Writer>>),
+    Zstd(Box<zstd::stream::write::Decoder<'static, Writer>>),
+}
+
+impl Decoder {
+    fn new(cfg: &Compression) -> io::Result<Self> {
+        Ok(match cfg.tp {
+            CompressionType::Deflate => Decoder::Deflate(Box::new(DeflateDecoder::new(
+                Writer::with_limit(cfg.max_decoded),
+            ))),
+            CompressionType::Gzip => Decoder::Gzip(Box::new(GzDecoder::new(
+                Writer::with_limit(cfg.max_decoded),
+            ))),
+            CompressionType::Zstd => {
+                let mut dec =
+                    zstd::stream::write::Decoder::new(Writer::with_limit(cfg.max_decoded))?;
+                if let Some(log) = cfg.window_log {
+                    dec.window_log_max(log)?;
+                }
+                Decoder::Zstd(Box::new(dec))
+            }
+        })
+    }
+
+    /// Decompress data and append result to the buffer
+    fn decode(&mut self, src: &[u8], dst: BytesMut) -> io::Result<BytesMut> {
+        match self {
+            Decoder::Deflate(dec) => {
+                process(dec.as_mut(), DeflateDecoder::get_mut, dst, |d| {
+                    d.write_all(src)?;
+                    d.flush()
+                })
+            }
+            Decoder::Gzip(dec) => process(dec.as_mut(), GzDecoder::get_mut, dst, |d| {
+                d.write_all(src)?;
+                d.flush()
+            }),
+            Decoder::Zstd(dec) => process(
+                dec.as_mut(),
+                zstd::stream::write::Decoder::get_mut,
+                dst,
+                |d| {
+                    d.write_all(src)?;
+                    d.flush()
+                },
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex_bytes::Bytes;
+    use ntex_codec::BytesCodec;
+
+    use super::*;
+    use crate::testing::IoTest;
+
+    const TEXT: &[u8] = b"GET /test HTTP/1.1\r\nHost: localhost\r\n\r\n";
+
+    async fn roundtrip(tp: CompressionType) {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        server.remote_buffer_cap(1024);
+
+        let client = Io::new(client)
+            .add_filter(Compression::new(tp))
+            .await
+            .unwrap();
+        let server = Io::new(server)
+            .add_filter(Compression::new(tp).level(1))
+            .await
+            .unwrap();
+
+        client
+            .send(Bytes::from_static(TEXT), &BytesCodec)
+            .await
+            .unwrap();
+        let msg = server.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(msg, Bytes::from_static(TEXT));
+
+        server
+            .send(Bytes::from_static(b"response"), &BytesCodec)
+            .await
+            .unwrap();
+        let msg = client.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(msg, Bytes::from_static(b"response"));
+    }
+
+    #[ntex::test]
+    async fn compression() {
+        roundtrip(CompressionType::Deflate).await;
+        roundtrip(CompressionType::Gzip).await;
+        roundtrip(CompressionType::Zstd).await;
+    }
+
+    #[ntex::test]
+    async fn zstd_window() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        server.remote_buffer_cap(1024);
+
+        let cfg = Compression::zstd()
+            .window_log(28)
+            .long_distance_matching(true);
+        let client = Io::new(client).add_filter(cfg).await.unwrap();
+        let server = Io::new(server).add_filter(cfg).await.unwrap();
+
+        client
+            .send(Bytes::from_static(TEXT), &BytesCodec)
+            .await
+            .unwrap();
+        let msg = server.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(msg, Bytes::from_static(TEXT));
+    }
+
+    #[ntex::test]
+    async fn compressed_stream() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+
+        let io = Io::new(server)
+            .add_filter(Compression::gzip())
+            .await
+            .unwrap();
+        io.send(Bytes::from_static(TEXT), &BytesCodec)
+            .await
+            .unwrap();
+
+        // data on the wire is gzip stream
+        let buf = client.read().await.unwrap();
+        assert_eq!(&buf[..2], b"\x1f\x8b");
+        assert_ne!(&buf[..], TEXT);
+
+        // invalid data
+        client.write(b"\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a\x0b");
+        assert!(io.recv(&BytesCodec).await.is_err());
+    }
+
+    #[ntex::test]
+    async fn decoded_size_limit() {
+        for cfg in [
+            Compression::deflate(),
+            Compression::gzip(),
+            Compression::zstd(),
+        ] {
+            let mut encoder = Encoder::new(&cfg).unwrap();
+            let data = encoder.encode(&[0; 64 * 1024], BytesMut::new()).unwrap();
+            assert!(data.len() < 1024);
+
+            let (client, server) = IoTest::create();
+            client.remote_buffer_cap(1024);
+            let io = Io::new(server)
+                .add_filter(cfg.max_decoded_size(16 * 1024))
+                .await
+                .unwrap();
+
+            client.write(&data);
+            let err = io.recv(&BytesCodec).await.unwrap_err();
+            assert!(format!("{:?}", err).contains("exceeds limit"));
+        }
+    }
+
+    #[ntex::test]
+    async fn decode_buffered_data() {
+        let mut encoder = Encoder::new(&Compression::zstd()).unwrap();
+        let data = encoder.encode(TEXT, BytesMut::new()).unwrap();
+
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        client.write(&data);
+
+        let io = Io::new(server);
+        io.read_ready().await.unwrap();
+        let io = io.add_filter(Compression::zstd()).await.unwrap();
+        let msg = io.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(msg, Bytes::from_static(TEXT));
+    }
+}
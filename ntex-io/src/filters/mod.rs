@@ -0,0 +1,13 @@
+//! Io filters
+
+mod checksum;
+mod recorder;
+
+pub use self::checksum::{Checksum, ChecksumFilter, ChecksumType};
+pub use self::recorder::{Direction, Record, Recorder, RecorderFilter};
+
+#[cfg(feature = "compress")]
+mod compress;
+
+#[cfg(feature = "compress")]
+pub use self::compress::{Compression, CompressionFilter, CompressionType};
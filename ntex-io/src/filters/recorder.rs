@@ -0,0 +1,399 @@
+//! Session recording filter
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
+use std::{any, fmt, io, rc::Rc, task::Context, task::Poll};
+
+use ntex_bytes::{Bytes, BytesMut};
+use ntex_util::future::Ready;
+
+use crate::{Base, Filter, FilterFactory, Io, ReadStatus, WriteStatus};
+
+/// Recording file header
+const MAGIC: &[u8; 8] = b"NTEXREC1";
+
+/// Direction of recorded data
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Data read from io stream
+    Read,
+    /// Data written to io stream
+    Write,
+}
+
+/// Recorded chunk of data
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    /// Direction of data
+    pub direction: Direction,
+    /// Time since start of recording
+    pub time: Duration,
+    /// Raw data
+    pub data: Bytes,
+}
+
+impl Record {
+    fn write_to(&self, w: &mut dyn io::Write) -> io::Result<()> {
+        let dir = match self.direction {
+            Direction::Read => 0u8,
+            Direction::Write => 1u8,
+        };
+        w.write_all(&[dir])?;
+        w.write_all(&(self.time.as_micros() as u64).to_be_bytes())?;
+        w.write_all(&(self.data.len() as u32).to_be_bytes())?;
+        w.write_all(&self.data)
+    }
+}
+
+/// Session recorder
+///
+/// Recorder captures exact byte stream in each direction. By default records
+/// are stored in memory, recorder created with `Recorder::writer()` writes
+/// records to provided writer instead. Recorder is a filter factory, recorder
+/// handle is available via `IoRef::query::<Recorder>()`, so recording could be
+/// enabled or disabled per connection.
+///
+/// ```rust
+/// use ntex_io::{filters::Recorder, testing::IoTest, Io};
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let (_client, server) = IoTest::create();
+///     let io = Io::new(server).add_filter(Recorder::new()).await.unwrap();
+///
+///     let recorder = io.query::<Recorder>().as_ref().unwrap().clone();
+///     recorder.disable();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Recorder {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    start: Instant,
+    enabled: Cell<bool>,
+    records: RefCell<Vec<Record>>,
+    writer: RefCell<Option<Box<dyn io::Write>>>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Recorder::new()
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("enabled", &self.inner.enabled.get())
+            .field("records", &self.inner.records.borrow().len())
+            .finish()
+    }
+}
+
+impl Recorder {
+    /// Create in-memory recorder
+    pub fn new() -> Self {
+        Recorder {
+            inner: Rc::new(Inner {
+                start: Instant::now(),
+                enabled: Cell::new(true),
+                records: RefCell::new(Vec::new()),
+                writer: RefCell::new(None),
+            }),
+        }
+    }
+
+    /// Create recorder that writes records to provided writer
+    ///
+    /// Use `Recorder::load()` to read records back.
+    pub fn writer<W: io::Write + 'static>(mut writer: W) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        let rec = Recorder::new();
+        *rec.inner.writer.borrow_mut() = Some(Box::new(writer));
+        Ok(rec)
+    }
+
+    /// Load records written by recorder
+    pub fn load<R: io::Read>(mut reader: R) -> io::Result<Vec<Record>> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a session recording",
+            ));
+        }
+
+        let mut records = Vec::new();
+        loop {
+            let mut dir = [0u8; 1];
+            if reader.read(&mut dir)? == 0 {
+                return Ok(records);
+            }
+            let direction = match dir[0] {
+                0 => Direction::Read,
+                1 => Direction::Write,
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Unknown record direction",
+                    ))
+                }
+            };
+            let mut time = [0u8; 8];
+            reader.read_exact(&mut time)?;
+            let mut len = [0u8; 4];
+            reader.read_exact(&mut len)?;
+            let mut data = vec![0u8; u32::from_be_bytes(len) as usize];
+            reader.read_exact(&mut data)?;
+
+            records.push(Record {
+                direction,
+                time: Duration::from_micros(u64::from_be_bytes(time)),
+                data: Bytes::from(data),
+            });
+        }
+    }
+
+    /// Enable recording
+    pub fn enable(&self) {
+        self.inner.enabled.set(true);
+    }
+
+    /// Disable recording
+    pub fn disable(&self) {
+        self.inner.enabled.set(false);
+    }
+
+    /// Check if recording is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.inner.enabled.get()
+    }
+
+    /// Get copy of in-memory records
+    pub fn records(&self) -> Vec<Record> {
+        self.inner.records.borrow().clone()
+    }
+
+    /// Take in-memory records
+    pub fn take_records(&self) -> Vec<Record> {
+        std::mem::take(&mut *self.inner.records.borrow_mut())
+    }
+
+    fn record(&self, direction: Direction, data: &[u8]) {
+        if data.is_empty() || !self.inner.enabled.get() {
+            return;
+        }
+
+        let record = Record {
+            direction,
+            time: self.inner.start.elapsed(),
+            data: Bytes::copy_from_slice(data),
+        };
+
+        let mut writer = self.inner.writer.borrow_mut();
+        if let Some(ref mut w) = *writer {
+            if let Err(err) = record.write_to(w.as_mut()) {
+                log::error!("Cannot write session record: {:?}", err);
+                *writer = None;
+                self.inner.enabled.set(false);
+            }
+        } else {
+            self.inner.records.borrow_mut().push(record);
+        }
+    }
+}
+
+impl<F: Filter> FilterFactory<F> for Recorder {
+    type Filter = RecorderFilter<F>;
+
+    type Error = io::Error;
+    type Future = Ready<Io<Self::Filter>, Self::Error>;
+
+    fn create(self, st: Io<F>) -> Self::Future {
+        Ready::from(st.map_filter(|inner: F| {
+            Ok::<_, io::Error>(RecorderFilter {
+                inner,
+                recorder: self,
+                write_len: Cell::new(0),
+            })
+        }))
+    }
+}
+
+/// Session recording filter
+pub struct RecorderFilter<F = Base> {
+    inner: F,
+    recorder: Recorder,
+    write_len: Cell<usize>,
+}
+
+impl<F: Filter> Filter for RecorderFilter<F> {
+    #[inline]
+    fn query(&self, id: any::TypeId) -> Option<Box<dyn any::Any>> {
+        if id == any::TypeId::of::<Recorder>() {
+            Some(Box::new(self.recorder.clone()))
+        } else {
+            self.inner.query(id)
+        }
+    }
+
+    #[inline]
+    fn want_read(&self) {
+        self.inner.want_read()
+    }
+
+    #[inline]
+    fn want_shutdown(&self, err: Option<io::Error>) {
+        self.inner.want_shutdown(err)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self) -> Poll<io::Result<()>> {
+        self.inner.poll_shutdown()
+    }
+
+    #[inline]
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<ReadStatus> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    #[inline]
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<WriteStatus> {
+        self.inner.poll_write_ready(cx)
+    }
+
+    #[inline]
+    fn closed(&self, err: Option<io::Error>) {
+        self.inner.closed(err)
+    }
+
+    #[inline]
+    fn get_read_buf(&self) -> Option<BytesMut> {
+        self.inner.get_read_buf()
+    }
+
+    #[inline]
+    fn get_write_buf(&self) -> Option<BytesMut> {
+        let buf = self.inner.get_write_buf();
+        // remember size of pending data, it is already recorded
+        self.write_len
+            .set(buf.as_ref().map(|b| b.len()).unwrap_or(0));
+        buf
+    }
+
+    fn release_read_buf(
+        &self,
+        src: BytesMut,
+        dst: &mut Option<BytesMut>,
+        nbytes: usize,
+    ) -> io::Result<usize> {
+        let nbytes = self.inner.release_read_buf(src, dst, nbytes)?;
+        if let Some(ref buf) = dst {
+            if nbytes > 0 && nbytes <= buf.len() {
+                self.recorder
+                    .record(Direction::Read, &buf[buf.len() - nbytes..]);
+            }
+        }
+        Ok(nbytes)
+    }
+
+    fn release_write_buf(&self, buf: BytesMut) -> io::Result<()> {
+        let len = self.write_len.take();
+        if buf.len() > len {
+            self.recorder.record(Direction::Write, &buf[len..]);
+        }
+        self.inner.release_write_buf(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex_codec::BytesCodec;
+
+    use super::*;
+    use crate::testing::{IoTest, Scenario};
+
+    #[ntex::test]
+    async fn recorder() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        let io = Io::new(server).add_filter(Recorder::new()).await.unwrap();
+
+        client.write(b"REQ1");
+        assert_eq!(io.recv(&BytesCodec).await.unwrap().unwrap(), "REQ1");
+        io.send(Bytes::from_static(b"RES1"), &BytesCodec)
+            .await
+            .unwrap();
+        assert_eq!(client.read().await.unwrap(), "RES1");
+
+        let recorder = io.query::<Recorder>().as_ref().unwrap().clone();
+        recorder.disable();
+        client.write(b"REQ2");
+        assert_eq!(io.recv(&BytesCodec).await.unwrap().unwrap(), "REQ2");
+        recorder.enable();
+        io.send(Bytes::from_static(b"RES2"), &BytesCodec)
+            .await
+            .unwrap();
+        assert_eq!(client.read().await.unwrap(), "RES2");
+
+        let records = recorder.take_records();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].direction, Direction::Read);
+        assert_eq!(records[0].data, Bytes::from_static(b"REQ1"));
+        assert_eq!(records[1].direction, Direction::Write);
+        assert_eq!(records[1].data, Bytes::from_static(b"RES1"));
+        assert_eq!(records[2].direction, Direction::Write);
+        assert_eq!(records[2].data, Bytes::from_static(b"RES2"));
+        assert!(records[0].time <= records[2].time);
+        assert!(recorder.records().is_empty());
+    }
+
+    #[derive(Clone, Default)]
+    struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn echo(server: IoTest, recorder: Recorder) {
+        crate::rt::spawn(async move {
+            let io = Io::new(server).add_filter(recorder).await.unwrap();
+            while let Ok(Some(msg)) = io.recv(&BytesCodec).await {
+                io.send(msg.freeze(), &BytesCodec).await.unwrap();
+            }
+        });
+    }
+
+    #[ntex::test]
+    async fn record_and_replay() {
+        let buffer = Buffer::default();
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        echo(server, Recorder::writer(buffer.clone()).unwrap());
+
+        client.write(b"PING");
+        assert_eq!(client.read().await.unwrap(), "PING");
+        client.write(b"DATA");
+        assert_eq!(client.read().await.unwrap(), "DATA");
+
+        let records = Recorder::load(&buffer.0.borrow()[..]).unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[3].direction, Direction::Write);
+        assert_eq!(records[3].data, Bytes::from_static(b"DATA"));
+
+        let (client, server) = IoTest::create();
+        echo(server, Recorder::new());
+        Scenario::replay(&records).run(&client).await;
+
+        assert!(Recorder::load(&b"invalid"[..]).is_err());
+    }
+}
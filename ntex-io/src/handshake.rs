@@ -0,0 +1,349 @@
+//! Handshake stage for protocol servers
+//!
+//! Handshake service runs before main service. It has access to io stream
+//! and could exchange initial frames with peer. Handshake produces codec and
+//! per-connection state, state is used for main service construction and
+//! main service is executed by [`Dispatcher`].
+use std::task::{Context, Poll};
+use std::{fmt, future::Future, marker::PhantomData, pin::Pin, rc::Rc};
+
+use ntex_codec::{Decoder, Encoder};
+use ntex_service::{IntoServiceFactory, Service, ServiceFactory};
+use ntex_util::time::{timeout, Seconds};
+
+use crate::{DispatchItem, Dispatcher, IoBoxed, Timer};
+
+type Response<U> = <U as Encoder>::Item;
+
+/// Connection handshake
+pub struct Handshake {
+    io: IoBoxed,
+}
+
+impl Handshake {
+    fn new(io: IoBoxed) -> Self {
+        Handshake { io }
+    }
+
+    /// Get reference to io stream
+    pub fn io(&self) -> &IoBoxed {
+        &self.io
+    }
+
+    /// Ack handshake
+    ///
+    /// `codec` is used by dispatcher, `state` is passed to main service factory.
+    pub fn ack<U, St>(self, codec: U, state: St) -> HandshakeAck<U, St> {
+        HandshakeAck {
+            codec,
+            state,
+            io: self.io,
+            keepalive: Seconds(30),
+        }
+    }
+}
+
+impl fmt::Debug for Handshake {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handshake")
+            .field("io", &self.io.get_ref())
+            .finish()
+    }
+}
+
+/// Handshake ack message
+pub struct HandshakeAck<U, St> {
+    io: IoBoxed,
+    codec: U,
+    state: St,
+    keepalive: Seconds,
+}
+
+impl<U, St> HandshakeAck<U, St> {
+    /// Set dispatcher keep-alive timeout.
+    ///
+    /// By default keep-alive timeout is set to 30 seconds.
+    pub fn keepalive(mut self, timeout: Seconds) -> Self {
+        self.keepalive = timeout;
+        self
+    }
+}
+
+/// Handshake server errors
+#[derive(Debug)]
+pub enum HandshakeError<H, S> {
+    /// Handshake service error
+    Handshake(H),
+    /// Handshake timeout
+    Timeout,
+    /// Main service error
+    Service(S),
+}
+
+impl<H: fmt::Display, S: fmt::Display> fmt::Display for HandshakeError<H, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandshakeError::Handshake(e) => write!(f, "Handshake error: {}", e),
+            HandshakeError::Timeout => write!(f, "Handshake timeout"),
+            HandshakeError::Service(e) => write!(f, "Service error: {}", e),
+        }
+    }
+}
+
+/// Protocol server with handshake stage
+///
+/// ```rust
+/// use ntex_codec::BytesCodec;
+/// use ntex_io::handshake::{Handshake, HandshakeServer};
+/// use ntex_io::DispatchItem;
+/// use ntex_service::{fn_factory_with_config, fn_service};
+/// use ntex_util::future::Ready;
+///
+/// let server = HandshakeServer::new(
+///     fn_service(|hs: Handshake| async move {
+///         // read initial frame
+///         let name = hs.io().recv(&BytesCodec).await.ok().flatten();
+///         Ok::<_, ()>(hs.ack(BytesCodec, name))
+///     }),
+///     fn_factory_with_config(|name| async move {
+///         Ok::<_, ()>(fn_service(move |_: DispatchItem<BytesCodec>| {
+///             println!("{:?}", name);
+///             Ready::Ok(None)
+///         }))
+///     }),
+/// );
+/// ```
+pub struct HandshakeServer<H, F, U, St> {
+    handshake: H,
+    factory: Rc<F>,
+    timeout: Seconds,
+    _t: PhantomData<(U, St)>,
+}
+
+impl<H, F, U, St> HandshakeServer<H, F, U, St>
+where
+    H: ServiceFactory<Handshake, Response = HandshakeAck<U, St>>,
+    F: ServiceFactory<
+            DispatchItem<U>,
+            St,
+            Response = Option<Response<U>>,
+            InitError = <F as ServiceFactory<DispatchItem<U>, St>>::Error,
+        > + 'static,
+    U: Decoder + Encoder + 'static,
+{
+    /// Create new handshake server
+    pub fn new<HF, SF>(handshake: HF, factory: SF) -> Self
+    where
+        HF: IntoServiceFactory<H, Handshake>,
+        SF: IntoServiceFactory<F, DispatchItem<U>, St>,
+    {
+        HandshakeServer {
+            handshake: handshake.into_factory(),
+            factory: Rc::new(factory.into_factory()),
+            timeout: Seconds(5),
+            _t: PhantomData,
+        }
+    }
+
+    /// Set handshake timeout.
+    ///
+    /// Handshake has to be completed within this time, otherwise connection
+    /// get closed. To disable timeout set value to 0.
+    ///
+    /// By default handshake timeout is set to 5 seconds.
+    pub fn handshake_timeout(mut self, timeout: Seconds) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl<H, F, U, St> ServiceFactory<IoBoxed> for HandshakeServer<H, F, U, St>
+where
+    H: ServiceFactory<Handshake, Response = HandshakeAck<U, St>>,
+    H::Service: 'static,
+    H::Future: 'static,
+    F: ServiceFactory<
+            DispatchItem<U>,
+            St,
+            Response = Option<Response<U>>,
+            InitError = <F as ServiceFactory<DispatchItem<U>, St>>::Error,
+        > + 'static,
+    F::Service: 'static,
+    U: Decoder + Encoder + 'static,
+    St: 'static,
+{
+    type Response = ();
+    type Error = HandshakeError<H::Error, F::Error>;
+    type InitError = H::InitError;
+    type Service = HandshakeService<H::Service, F, U, St>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Service, Self::InitError>>>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let fut = self.handshake.new_service(());
+        let factory = self.factory.clone();
+        let timeout = self.timeout;
+
+        Box::pin(async move {
+            let handshake = fut.await?;
+            Ok(HandshakeService {
+                factory,
+                timeout,
+                handshake: Rc::new(handshake),
+                timer: Timer::default(),
+                _t: PhantomData,
+            })
+        })
+    }
+}
+
+/// Service for protocol server with handshake stage
+pub struct HandshakeService<H, F, U, St> {
+    handshake: Rc<H>,
+    factory: Rc<F>,
+    timer: Timer,
+    timeout: Seconds,
+    _t: PhantomData<(U, St)>,
+}
+
+impl<H, F, U, St> Service<IoBoxed> for HandshakeService<H, F, U, St>
+where
+    H: Service<Handshake, Response = HandshakeAck<U, St>> + 'static,
+    F: ServiceFactory<
+            DispatchItem<U>,
+            St,
+            Response = Option<Response<U>>,
+            InitError = <F as ServiceFactory<DispatchItem<U>, St>>::Error,
+        > + 'static,
+    F::Service: 'static,
+    U: Decoder + Encoder + 'static,
+    St: 'static,
+{
+    type Response = ();
+    type Error = HandshakeError<H::Error, F::Error>;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.handshake
+            .poll_ready(cx)
+            .map_err(HandshakeError::Handshake)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.handshake.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, io: IoBoxed) -> Self::Future {
+        let handshake = self.handshake.clone();
+        let factory = self.factory.clone();
+        let timer = self.timer.clone();
+        let tm = self.timeout;
+
+        Box::pin(async move {
+            let fut = handshake.call(Handshake::new(io));
+            let ack = if tm.is_zero() {
+                fut.await
+            } else {
+                timeout(tm, fut).await.map_err(|_| {
+                    log::trace!("Handshake timeout");
+                    HandshakeError::Timeout
+                })?
+            }
+            .map_err(HandshakeError::Handshake)?;
+
+            let srv = factory
+                .new_service(ack.state)
+                .await
+                .map_err(HandshakeError::Service)?;
+
+            Dispatcher::new(ack.io, ack.codec, srv, timer)
+                .keepalive_timeout(ack.keepalive)
+                .await
+                .map_err(HandshakeError::Service)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use ntex_bytes::Bytes;
+    use ntex_codec::BytesCodec;
+    use ntex_service::{fn_factory_with_config, fn_service};
+    use ntex_util::future::Ready;
+    use ntex_util::time::{sleep, Millis};
+
+    use super::*;
+    use crate::{testing::IoTest, Io};
+
+    #[ntex::test]
+    async fn test_handshake() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        client.write("HELLO");
+
+        let factory = HandshakeServer::new(
+            fn_service(|hs: Handshake| async move {
+                let name = hs.io().recv(&BytesCodec).await.unwrap().unwrap();
+                hs.io()
+                    .send(Bytes::from_static(b"ACK"), &BytesCodec)
+                    .await
+                    .unwrap();
+                Ok::<_, ()>(hs.ack(BytesCodec, name.freeze()))
+            }),
+            fn_factory_with_config(|name: Bytes| async move {
+                Ok::<_, ()>(fn_service(move |msg: DispatchItem<BytesCodec>| {
+                    let res = if let DispatchItem::Item(msg) = msg {
+                        let mut res = name.to_vec();
+                        res.extend_from_slice(&msg);
+                        Some(Bytes::from(res))
+                    } else {
+                        None
+                    };
+                    Ready::Ok(res)
+                }))
+            }),
+        );
+        let srv = factory.new_service(()).await.unwrap();
+        ntex_util::spawn(async move {
+            let _ = srv.call(IoBoxed::from(Io::new(server))).await;
+        });
+
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"ACK"));
+
+        client.write("-TEST");
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"HELLO-TEST"));
+    }
+
+    #[ntex::test]
+    async fn test_handshake_timeout() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        let created = Rc::new(Cell::new(false));
+        let created2 = created.clone();
+
+        let factory = HandshakeServer::new(
+            fn_service(|hs: Handshake| async move {
+                sleep(Millis(2000)).await;
+                Ok::<_, ()>(hs.ack(BytesCodec, ()))
+            }),
+            fn_factory_with_config(move |_: ()| {
+                created2.set(true);
+                async move {
+                    Ok::<_, ()>(fn_service(|_: DispatchItem<BytesCodec>| Ready::Ok(None)))
+                }
+            }),
+        )
+        .handshake_timeout(Seconds(1));
+        let srv = factory.new_service(()).await.unwrap();
+        let res = srv.call(IoBoxed::from(Io::new(server))).await;
+        assert!(matches!(res, Err(HandshakeError::Timeout)));
+        assert!(!created.get());
+        sleep(Millis(50)).await;
+        assert!(client.is_server_dropped());
+    }
+}
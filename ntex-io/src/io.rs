@@ -1,15 +1,17 @@
 use std::cell::{Cell, RefCell};
 use std::task::{Context, Poll};
-use std::{fmt, future::Future, hash, io, mem, ops::Deref, pin::Pin, ptr, rc::Rc};
+use std::time::Instant;
+use std::{any, fmt, future::Future, hash, io, mem, ops::Deref, pin::Pin, ptr, rc::Rc};
 
-use ntex_bytes::{BytesMut, PoolId, PoolRef};
+use ntex_bytes::{BufParams, BytesMut, PoolId, PoolRef};
 use ntex_codec::{Decoder, Encoder};
 use ntex_util::{future::poll_fn, future::Either, task::LocalWaker, time::Millis};
 
 use super::filter::{Base, NullFilter};
+use super::scope::TaskScope;
 use super::seal::{IoBoxed, Sealed};
 use super::tasks::{ReadContext, WriteContext};
-use super::{Filter, FilterFactory, Handle, IoStream, RecvError};
+use super::{Filter, FilterFactory, Handle, IoProfile, IoStats, IoStream, RecvError};
 
 bitflags::bitflags! {
     pub struct Flags: u16 {
@@ -56,6 +58,8 @@ pub struct IoRef(pub(super) Rc<IoState>);
 pub(crate) struct IoState {
     pub(super) flags: Cell<Flags>,
     pub(super) pool: Cell<PoolRef>,
+    pub(super) read_params: Cell<Option<BufParams>>,
+    pub(super) write_params: Cell<Option<BufParams>>,
     pub(super) disconnect_timeout: Cell<Millis>,
     pub(super) error: Cell<Option<io::Error>>,
     pub(super) read_task: LocalWaker,
@@ -65,10 +69,26 @@ pub(crate) struct IoState {
     pub(super) write_buf: Cell<Option<BytesMut>>,
     pub(super) filter: Cell<&'static dyn Filter>,
     pub(super) handle: Cell<Option<Box<dyn Handle>>>,
+    pub(super) profile: Cell<Option<IoProfile>>,
+    pub(super) stats: Cell<IoStats>,
+    pub(super) flush_start: Cell<Option<Instant>>,
+    pub(super) write_taken: Cell<usize>,
     pub(super) on_disconnect: RefCell<Vec<Option<LocalWaker>>>,
+    pub(super) scope: RefCell<Option<TaskScope>>,
+    pub(super) data: RefCell<Vec<Rc<dyn any::Any>>>,
 }
 
 impl IoState {
+    #[inline]
+    fn flush_wait_started(&self) {
+        if self.flush_start.get().is_none() {
+            self.flush_start.set(Some(Instant::now()));
+            let mut stats = self.stats.get();
+            stats.flush_waits += 1;
+            self.stats.set(stats);
+        }
+    }
+
     #[inline]
     pub(super) fn insert_flags(&self, f: Flags) {
         let mut flags = self.flags.get();
@@ -101,6 +121,16 @@ impl IoState {
                 waker.wake();
             }
         }
+        drop(on_disconnect);
+        self.cancel_scope();
+    }
+
+    #[inline]
+    pub(super) fn cancel_scope(&self) {
+        let scope = self.scope.borrow().clone();
+        if let Some(scope) = scope {
+            scope.cancel();
+        }
     }
 
     #[inline]
@@ -241,6 +271,8 @@ impl Io {
     pub fn with_memory_pool<I: IoStream>(io: I, pool: PoolRef) -> Self {
         let inner = Rc::new(IoState {
             pool: Cell::new(pool),
+            read_params: Cell::new(None),
+            write_params: Cell::new(None),
             flags: Cell::new(Flags::empty()),
             error: Cell::new(None),
             disconnect_timeout: Cell::new(Millis::ONE_SEC),
@@ -251,7 +283,13 @@ impl Io {
             write_buf: Cell::new(None),
             filter: Cell::new(NullFilter::get()),
             handle: Cell::new(None),
+            profile: Cell::new(None),
+            stats: Cell::new(IoStats::default()),
+            flush_start: Cell::new(None),
+            write_taken: Cell::new(0),
             on_disconnect: RefCell::new(Vec::new()),
+            scope: RefCell::new(None),
+            data: RefCell::new(Vec::new()),
         });
 
         let filter = Box::new(Base::new(IoRef(inner.clone())));
@@ -608,16 +646,23 @@ impl<F> Io<F> {
             if full {
                 self.0 .0.insert_flags(Flags::WR_WAIT);
                 self.0 .0.dispatch_task.register(cx.waker());
+                self.0 .0.flush_wait_started();
                 return Poll::Pending;
-            } else if len >= self.0.memory_pool().write_params_high() << 1 {
+            } else if len >= (self.0.write_params().high as usize) << 1 {
                 self.0 .0.insert_flags(Flags::WR_BACKPRESSURE);
                 self.0 .0.dispatch_task.register(cx.waker());
+                self.0 .0.flush_wait_started();
                 return Poll::Pending;
             }
         }
         self.0
              .0
             .remove_flags(Flags::WR_WAIT | Flags::WR_BACKPRESSURE);
+        if let Some(start) = self.0 .0.flush_start.take() {
+            let mut stats = self.0 .0.stats.get();
+            stats.flush_wait_time += start.elapsed();
+            self.0 .0.stats.set(stats);
+        }
         Poll::Ready(Ok(()))
     }
 
@@ -655,6 +700,7 @@ impl<F> Drop for Io<F> {
             );
 
             self.force_close();
+            self.0 .0.notify_disconnect();
             self.0 .0.filter.set(NullFilter::get());
             let _ = mem::replace(&mut self.1, FilterItem::Ptr(ptr::null_mut()));
             unsafe { Box::from_raw(p) };
@@ -664,6 +710,7 @@ impl<F> Drop for Io<F> {
                 self.0.flags()
             );
             self.force_close();
+            self.0 .0.notify_disconnect();
             self.0 .0.filter.set(NullFilter::get());
         }
     }
@@ -1,10 +1,10 @@
-use std::{any, fmt, io};
+use std::{any, fmt, io, rc::Rc, task::Context, task::Poll};
 
-use ntex_bytes::{BufMut, BytesMut, PoolRef};
+use ntex_bytes::{BufMut, BufParams, BytesMut, PoolRef};
 use ntex_codec::{Decoder, Encoder};
 
 use super::io::{Flags, IoRef, OnDisconnect};
-use super::{types, Filter};
+use super::{types, Filter, IoProfile, IoStats, TaskScope};
 
 impl IoRef {
     #[inline]
@@ -32,6 +32,79 @@ impl IoRef {
         self.0.pool.get()
     }
 
+    #[inline]
+    /// Get read buffer high and low watermarks
+    ///
+    /// By default memory pool's params are used.
+    pub fn read_params(&self) -> BufParams {
+        self.0
+            .read_params
+            .get()
+            .unwrap_or_else(|| self.memory_pool().read_params())
+    }
+
+    #[inline]
+    /// Get write buffer high and low watermarks
+    ///
+    /// By default memory pool's params are used.
+    pub fn write_params(&self) -> BufParams {
+        self.0
+            .write_params
+            .get()
+            .unwrap_or_else(|| self.memory_pool().write_params())
+    }
+
+    #[inline]
+    /// Set read buffer high and low watermarks
+    ///
+    /// Overrides memory pool's params for current io stream.
+    ///
+    /// # Panics
+    ///
+    /// Panics if low watermark is not smaller than high watermark.
+    pub fn set_read_params(&self, high: u16, low: u16) {
+        assert!(low < high);
+        self.0.read_params.set(Some(BufParams { high, low }));
+    }
+
+    #[inline]
+    /// Set write buffer high and low watermarks
+    ///
+    /// Overrides memory pool's params for current io stream.
+    ///
+    /// # Panics
+    ///
+    /// Panics if low watermark is not smaller than high watermark.
+    pub fn set_write_params(&self, high: u16, low: u16) {
+        assert!(low < high);
+        self.0.write_params.set(Some(BufParams { high, low }));
+    }
+
+    #[inline]
+    /// Get io stream profile
+    pub fn profile(&self) -> Option<IoProfile> {
+        self.0.profile.get()
+    }
+
+    /// Set io stream profile
+    ///
+    /// Profile adjusts flush strategy, transport options and read-ahead
+    /// behavior as a bundle. Transport options are applied by io stream
+    /// handle, unsupported options are ignored.
+    pub fn set_profile(&self, profile: IoProfile) {
+        self.0.profile.set(Some(profile));
+        if let Some(hnd) = self.0.handle.take() {
+            hnd.set_profile(profile);
+            self.0.handle.set(Some(hnd));
+        }
+    }
+
+    #[inline]
+    /// Get io stream transport statistics
+    pub fn stats(&self) -> IoStats {
+        self.0.stats.get()
+    }
+
     #[inline]
     /// Check if io is still active
     pub fn is_io_open(&self) -> bool {
@@ -91,6 +164,52 @@ impl IoRef {
         OnDisconnect::new(self.0.clone())
     }
 
+    /// Get connection task scope
+    ///
+    /// Tasks spawned within scope get cancelled when connection closes.
+    pub fn scope(&self) -> TaskScope {
+        let mut scope = self.0.scope.borrow_mut();
+        if let Some(ref scope) = *scope {
+            scope.clone()
+        } else {
+            let s = TaskScope::new();
+            if self.is_closed() {
+                s.cancel();
+            }
+            *scope = Some(s.clone());
+            s
+        }
+    }
+
+    /// Cancel connection task scope and wait until all tasks get dropped
+    pub(crate) fn poll_scope_shutdown(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let scope = self.0.scope.borrow().clone();
+        if let Some(scope) = scope {
+            scope.poll_shutdown(cx)
+        } else {
+            Poll::Ready(())
+        }
+    }
+
+    /// Attach connection data
+    ///
+    /// Data is available for the whole connection lifetime via `IoRef::data()`.
+    /// Previously attached data of the same type gets replaced.
+    pub fn set_data<T: 'static>(&self, data: T) {
+        let mut items = self.0.data.borrow_mut();
+        items.retain(|item| !item.is::<T>());
+        items.push(Rc::new(data));
+    }
+
+    /// Get connection data
+    pub fn data<T: 'static>(&self) -> Option<Rc<T>> {
+        self.0
+            .data
+            .borrow()
+            .iter()
+            .find_map(|item| item.clone().downcast().ok())
+    }
+
     #[inline]
     /// Query specific data
     pub fn query<T: 'static>(&self) -> types::QueryItem<T> {
@@ -119,7 +238,7 @@ impl IoRef {
         let len = self
             .0
             .with_write_buf(|buf| buf.as_ref().map(|b| b.len()).unwrap_or(0));
-        len >= self.memory_pool().write_params_high()
+        len >= self.write_params().high as usize
     }
 
     #[inline]
@@ -128,7 +247,7 @@ impl IoRef {
         let len = self
             .0
             .with_read_buf(false, |buf| buf.as_ref().map(|b| b.len()).unwrap_or(0));
-        len >= self.memory_pool().read_params_high()
+        len >= self.read_params().high as usize
     }
 
     #[inline]
@@ -178,7 +297,7 @@ impl IoRef {
 
         if !flags.intersects(Flags::IO_ERR | Flags::IO_SHUTDOWN) {
             self.with_write_buf(|buf| {
-                let (hw, lw) = self.memory_pool().write_params().unpack();
+                let (hw, lw) = self.write_params().unpack();
 
                 // make sure we've got room
                 let remaining = buf.remaining_mut();
@@ -341,6 +460,106 @@ mod tests {
         assert!(lazy(|cx| io.poll_read_ready(cx)).await.is_pending());
     }
 
+    #[ntex::test]
+    async fn buffer_params() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+
+        let io = Io::new(server);
+        let pool = io.memory_pool();
+        assert_eq!(io.read_params(), pool.read_params());
+        assert_eq!(io.write_params(), pool.write_params());
+
+        io.set_read_params(16, 8);
+        io.set_write_params(16, 4);
+        assert_eq!(io.read_params().unpack(), (16, 8));
+        assert_eq!(io.write_params().unpack(), (16, 4));
+        assert_ne!(io.read_params(), pool.read_params());
+
+        client.write(TEXT);
+        assert_eq!(io.read_ready().await.unwrap(), Some(()));
+        assert!(io.is_read_buf_full());
+
+        io.with_write_buf(|buf| buf.extend_from_slice(BIN)).unwrap();
+        assert!(io.is_write_buf_full());
+    }
+
+    #[cfg(all(unix, feature = "tokio-traits"))]
+    #[ntex::test]
+    async fn profile() {
+        use std::os::unix::io::FromRawFd;
+        use std::{io::Write, mem::ManuallyDrop, os::unix::io::AsRawFd};
+
+        let lst = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = tok_io::net::TcpStream::connect(lst.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (mut peer, _) = lst.accept().unwrap();
+        let sock =
+            ManuallyDrop::new(unsafe { socket2::Socket::from_raw_fd(stream.as_raw_fd()) });
+
+        let io = Io::new(stream);
+        assert_eq!(io.profile(), None);
+
+        io.set_profile(IoProfile::Latency);
+        assert_eq!(io.profile(), Some(IoProfile::Latency));
+        assert!(sock.nodelay().unwrap());
+
+        peer.write_all(BIN).unwrap();
+        let msg = io.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(msg, Bytes::from_static(BIN));
+
+        io.set_profile(IoProfile::Throughput);
+        assert_eq!(io.profile(), Some(IoProfile::Throughput));
+        assert!(!sock.nodelay().unwrap());
+    }
+
+    #[ntex::test]
+    async fn stats() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(0);
+        let io = Io::new(server);
+        assert_eq!(io.stats(), IoStats::default());
+
+        client.write(TEXT);
+        let msg = io.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(msg, Bytes::from_static(BIN));
+        assert_eq!(io.stats().bytes_read, BIN.len() as u64);
+
+        // remote buffer is full, flush has to wait
+        io.encode(Bytes::from_static(BIN), &BytesCodec).unwrap();
+        assert!(lazy(|cx| io.poll_flush(cx, true)).await.is_pending());
+        assert!(lazy(|cx| io.poll_flush(cx, true)).await.is_pending());
+        assert_eq!(io.stats().flush_waits, 1);
+        assert_eq!(io.stats().bytes_written, 0);
+
+        sleep(Millis(50)).await;
+        client.remote_buffer_cap(1024);
+        io.flush(true).await.unwrap();
+        let stats = io.stats();
+        assert_eq!(stats.bytes_written, BIN.len() as u64);
+        assert_eq!(stats.flush_waits, 1);
+        assert!(stats.flush_wait_time >= std::time::Duration::from_millis(40));
+        assert_eq!(client.read().await.unwrap(), Bytes::from_static(BIN));
+    }
+
+    #[ntex::test]
+    async fn connection_data() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        let state = Io::new(server);
+        assert!(state.data::<usize>().is_none());
+
+        state.set_data(1usize);
+        state.set_data("test");
+        assert_eq!(*state.data::<usize>().unwrap(), 1);
+        assert_eq!(*state.data::<&'static str>().unwrap(), "test");
+
+        state.get_ref().set_data(2usize);
+        assert_eq!(*state.data::<usize>().unwrap(), 2);
+        assert!(state.data::<u32>().is_none());
+    }
+
     #[ntex::test]
     async fn on_disconnect() {
         let (client, server) = IoTest::create();
@@ -2,17 +2,26 @@
 #![allow(clippy::return_self_not_must_use)]
 use std::{
     any::Any, any::TypeId, fmt, future::Future, io as sio, io::Error as IoError,
-    task::Context, task::Poll,
+    task::Context, task::Poll, time::Duration,
 };
 
+pub mod filters;
+pub mod handshake;
+pub mod mux;
 pub mod testing;
 pub mod types;
 
+#[cfg(feature = "process")]
+pub mod process;
+#[cfg(feature = "serial")]
+pub mod serial;
+
 mod dispatcher;
 mod filter;
 mod framed;
 mod io;
 mod ioref;
+mod scope;
 mod seal;
 mod tasks;
 mod time;
@@ -29,30 +38,54 @@ use ntex_bytes::BytesMut;
 use ntex_codec::{Decoder, Encoder};
 use ntex_util::time::Millis;
 
-pub use self::dispatcher::Dispatcher;
+pub use self::dispatcher::{Dispatcher, DispatcherMiddleware};
 pub use self::filter::Base;
 pub use self::framed::Framed;
 pub use self::io::{Io, IoRef, OnDisconnect};
+pub use self::scope::TaskScope;
 pub use self::seal::{IoBoxed, Sealed};
 pub use self::tasks::{ReadContext, WriteContext};
 pub use self::time::Timer;
 pub use self::utils::{add_filter, boxed, seal, Boxed, BoxedFactory};
 
+/// Status of read task
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ReadStatus {
+    /// Read task should read data from io stream
     Ready,
+    /// Read task must stop reading and exit
     Terminate,
 }
 
+/// Status of write task
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum WriteStatus {
+    /// Write task should flush write buffer
     Ready,
+    /// Write task should flush write buffer and shutdown io stream
+    /// within timeout, then close io stream
     Timeout(Millis),
+    /// Write task should shutdown io stream within timeout
     Shutdown(Millis),
+    /// Write task must close io stream immediately
     Terminate,
 }
 
+/// Io filter
+///
+/// Filters are stacked on top of `Base` filter, each filter wraps inner
+/// filter and transforms data between io stream and application.
+///
+/// # Buffer release contract
+///
+/// Buffers are owned by the caller between `get_*_buf()` and
+/// `release_*_buf()` calls. After release, buffer must not be retained or
+/// modified, filter must either pass buffer to inner filter, store it as
+/// own state, or return it to the memory pool. Use `pool-canary` feature
+/// of `ntex-bytes` to detect buffers modified after release.
 pub trait Filter: 'static {
+    /// Query filter specific data, filter should forward unknown
+    /// queries to inner filter
     fn query(&self, id: TypeId) -> Option<Box<dyn Any>>;
 
     /// Filter needs incoming data from io stream
@@ -61,16 +94,29 @@ pub trait Filter: 'static {
     /// Filter wants gracefully shutdown io stream
     fn want_shutdown(&self, err: Option<sio::Error>);
 
+    /// Gracefully shutdown filter, called before io stream shutdown
     fn poll_shutdown(&self) -> Poll<sio::Result<()>>;
 
+    /// Check readiness of read task
     fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<ReadStatus>;
 
+    /// Check readiness of write task
     fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<WriteStatus>;
 
+    /// Get buffer for reading from io stream
+    ///
+    /// Filter returns `None` if it does not keep unprocessed read data,
+    /// in that case buffer is allocated from memory pool.
     fn get_read_buf(&self) -> Option<BytesMut>;
 
+    /// Get buffer with pending data for writing to io stream
     fn get_write_buf(&self) -> Option<BytesMut>;
 
+    /// Release buffer with data read from io stream
+    ///
+    /// `nbytes` is the number of new bytes in `src`. Filter processes data
+    /// and appends result to `dst` buffer, result is the number of new bytes
+    /// available in `dst`.
     fn release_read_buf(
         &self,
         src: BytesMut,
@@ -78,26 +124,79 @@ pub trait Filter: 'static {
         nbytes: usize,
     ) -> sio::Result<usize>;
 
+    /// Release buffer with data for writing to io stream
     fn release_write_buf(&self, buf: BytesMut) -> sio::Result<()>;
 
+    /// Io stream is closed
     fn closed(&self, err: Option<sio::Error>);
 }
 
+/// Filter factory
 pub trait FilterFactory<F: Filter>: Sized {
+    /// Created filter
     type Filter: Filter;
 
+    /// Error of filter creation
     type Error: fmt::Debug;
+    /// Future that resolves to io stream with new filter
     type Future: Future<Output = Result<Io<Self::Filter>, Self::Error>>;
 
+    /// Add filter on top of io stream filters
     fn create(self, st: Io<F>) -> Self::Future;
 }
 
+/// Io stream
+///
+/// Io stream implementation starts read and write tasks for the underlying
+/// transport. Read task gets buffer with `ReadContext::get_read_buf()`,
+/// reads available data and releases buffer with
+/// `ReadContext::release_read_buf()`, it stops when `ReadContext::poll_ready()`
+/// returns `ReadStatus::Terminate`. Write task takes pending data with
+/// `WriteContext::get_write_buf()`, writes it and returns unwritten data with
+/// `WriteContext::release_write_buf()`. Both tasks must call `close()` on
+/// its context when transport is disconnected or failed.
+///
+/// Use `testing::conformance::check_stream()` to validate implementation.
 pub trait IoStream {
+    /// Start read and write tasks, returned handle is used for queries
     fn start(self, _: ReadContext, _: WriteContext) -> Option<Box<dyn Handle>>;
 }
 
+/// Io stream tuning profile
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum IoProfile {
+    /// Minimize latency
+    ///
+    /// Disables write coalescing (`TCP_NODELAY`), enables `TCP_QUICKACK`
+    /// and passes data to dispatcher after each read.
+    Latency,
+    /// Maximize throughput
+    ///
+    /// Enables write coalescing and fills read buffer before
+    /// passing data to dispatcher.
+    Throughput,
+}
+
+/// Io stream transport statistics
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct IoStats {
+    /// Number of bytes read from transport
+    pub bytes_read: u64,
+    /// Number of bytes written to transport
+    pub bytes_written: u64,
+    /// Number of times write buffer flush was awaited
+    pub flush_waits: u64,
+    /// Total time spent waiting for write buffer flush
+    pub flush_wait_time: Duration,
+}
+
+/// Io stream handle
 pub trait Handle {
+    /// Query io stream specific data, for example `types::PeerAddr`
     fn query(&self, id: TypeId) -> Option<Box<dyn Any>>;
+
+    /// Apply io stream profile to underlying transport
+    fn set_profile(&self, _profile: IoProfile) {}
 }
 
 /// Recv error
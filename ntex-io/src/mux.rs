@@ -0,0 +1,1031 @@
+//! Stream multiplexing over single io stream
+//!
+//! Session runs multiple logical streams over one io stream, each logical
+//! stream is a regular [`Io`] object and could be used with its own codec
+//! and dispatcher. Framing follows yamux specification, streams opened by
+//! client side use odd ids and streams opened by server side use even ids.
+//! Each stream has its own flow control window, data is sent only if peer
+//! has capacity for it, so slow stream does not block other streams.
+//!
+//! ```rust
+//! use ntex_bytes::Bytes;
+//! use ntex_codec::BytesCodec;
+//! use ntex_io::{mux, testing::IoTest, Io};
+//!
+//! #[ntex::main]
+//! async fn main() {
+//!     let (client, server) = IoTest::create();
+//!     client.remote_buffer_cap(usize::MAX);
+//!     server.remote_buffer_cap(usize::MAX);
+//!
+//!     let client = mux::Session::client(Io::new(client), mux::Config::default());
+//!     let server = mux::Session::server(Io::new(server), mux::Config::default());
+//!
+//!     let stream = client.open().unwrap();
+//!     stream.send(Bytes::from_static(b"PING"), &BytesCodec).await.unwrap();
+//!
+//!     let stream = server.accept().await.unwrap();
+//!     let data = stream.recv(&BytesCodec).await.unwrap().unwrap();
+//!     assert_eq!(&data[..], b"PING");
+//! }
+//! ```
+use std::task::{Context, Poll};
+use std::{any, cell::Cell, cell::RefCell, cmp, collections::VecDeque, fmt, io};
+use std::{future::Future, pin::Pin, rc::Rc};
+
+use ntex_bytes::{Buf, BufMut, Bytes, BytesMut};
+use ntex_codec::{Decoder, Encoder};
+use ntex_util::time::{sleep, Sleep};
+use ntex_util::{future::poll_fn, future::Either, task::LocalWaker};
+
+use crate::{Handle, Io, IoBoxed, IoRef, IoStream, ReadContext, ReadStatus};
+use crate::{WriteContext, WriteStatus};
+
+/// Initial stream window defined by protocol
+const DEFAULT_WINDOW: u32 = 256 * 1024;
+/// Max size of data frame sent by session
+const MAX_CHUNK: usize = 16 * 1024;
+/// Max size of data frame accepted by session
+const MAX_FRAME: u32 = 16 * 1024 * 1024;
+const HEADER_SIZE: usize = 12;
+
+const TYPE_DATA: u8 = 0;
+const TYPE_WINDOW_UPDATE: u8 = 1;
+const TYPE_PING: u8 = 2;
+const TYPE_GO_AWAY: u8 = 3;
+
+const FLAG_SYN: u16 = 1;
+const FLAG_ACK: u16 = 2;
+const FLAG_FIN: u16 = 4;
+const FLAG_RST: u16 = 8;
+
+const GO_AWAY_NORMAL: u32 = 0;
+const GO_AWAY_PROTO: u32 = 1;
+
+/// Stream id of multiplexed io stream
+///
+/// Stream id is available via `io.query::<StreamId>()`, other queries are
+/// forwarded to underlying io stream.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StreamId(pub u32);
+
+/// Multiplexing session configuration
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    window: u32,
+    max_streams: usize,
+    max_pending: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::new()
+    }
+}
+
+impl Config {
+    /// Create default configuration
+    pub fn new() -> Self {
+        Config {
+            window: DEFAULT_WINDOW,
+            max_streams: 256,
+            max_pending: 64,
+        }
+    }
+
+    /// Set receive window size of each stream.
+    ///
+    /// Peer could send this amount of data before stream data is read by
+    /// application. Window could not be smaller than protocol default
+    /// of 256Kb. By default protocol default is used.
+    pub fn window_size(mut self, size: u32) -> Self {
+        self.window = cmp::max(size, DEFAULT_WINDOW);
+        self
+    }
+
+    /// Set max number of concurrent streams.
+    ///
+    /// Streams opened by peer above the limit are reset. By default
+    /// limit is set to 256.
+    pub fn max_streams(mut self, num: usize) -> Self {
+        self.max_streams = num;
+        self
+    }
+
+    /// Set max number of streams opened by peer and not accepted yet.
+    ///
+    /// Streams above the limit are reset. By default limit is set to 64.
+    pub fn max_pending(mut self, num: usize) -> Self {
+        self.max_pending = num;
+        self
+    }
+}
+
+bitflags::bitflags! {
+    struct SessionFlags: u8 {
+        /// Io stream is closed
+        const CLOSED      = 0b0000_0001;
+        /// Peer does not accept new streams
+        const REMOTE_AWAY = 0b0000_0010;
+        /// Session is shutting down
+        const LOCAL_AWAY  = 0b0000_0100;
+    }
+}
+
+bitflags::bitflags! {
+    struct StreamFlags: u8 {
+        /// Fin frame is sent
+        const LOCAL_FIN  = 0b0000_0001;
+        /// Fin frame is received
+        const REMOTE_FIN = 0b0000_0010;
+        /// Stream is reset
+        const RESET      = 0b0000_0100;
+    }
+}
+
+/// Multiplexing session
+///
+/// Session runs io stream processing task, it is closed when io stream is
+/// disconnected or all session handles and streams are dropped.
+pub struct Session(Rc<Inner>);
+
+struct Inner {
+    io: IoRef,
+    cfg: Config,
+    flags: Cell<SessionFlags>,
+    next_id: Cell<u32>,
+    handles: Cell<usize>,
+    streams: RefCell<fxhash::FxHashMap<u32, Rc<StreamState>>>,
+    incoming: RefCell<VecDeque<Io>>,
+    accept_task: LocalWaker,
+}
+
+struct StreamState {
+    id: u32,
+    flags: Cell<StreamFlags>,
+    recv_buf: RefCell<BytesMut>,
+    recv_window: Cell<u32>,
+    consumed: Cell<u32>,
+    send_window: Cell<u32>,
+    read_task: LocalWaker,
+    write_task: LocalWaker,
+}
+
+impl Session {
+    /// Start client side of multiplexing session
+    pub fn client<T: Into<IoBoxed>>(io: T, cfg: Config) -> Self {
+        Session::start(io.into(), cfg, 1)
+    }
+
+    /// Start server side of multiplexing session
+    pub fn server<T: Into<IoBoxed>>(io: T, cfg: Config) -> Self {
+        Session::start(io.into(), cfg, 2)
+    }
+
+    fn start(io: IoBoxed, cfg: Config, next_id: u32) -> Self {
+        let inner = Rc::new(Inner {
+            cfg,
+            io: io.get_ref(),
+            flags: Cell::new(SessionFlags::empty()),
+            next_id: Cell::new(next_id),
+            handles: Cell::new(1),
+            streams: RefCell::new(fxhash::FxHashMap::default()),
+            incoming: RefCell::new(VecDeque::new()),
+            accept_task: LocalWaker::new(),
+        });
+        crate::rt::spawn(run(inner.clone(), io));
+        Session(inner)
+    }
+
+    #[inline]
+    /// Get reference to underlying io stream
+    pub fn get_ref(&self) -> &IoRef {
+        &self.0.io
+    }
+
+    #[inline]
+    /// Check if session is closed
+    pub fn is_closed(&self) -> bool {
+        self.0.flags.get().contains(SessionFlags::CLOSED)
+    }
+
+    #[inline]
+    /// Number of open streams
+    pub fn num_streams(&self) -> usize {
+        self.0.streams.borrow().len()
+    }
+
+    /// Open new stream
+    pub fn open(&self) -> io::Result<Io> {
+        let flags = self.0.flags.get();
+        if flags.contains(SessionFlags::CLOSED) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "Session is closed",
+            ));
+        }
+        if flags.intersects(SessionFlags::REMOTE_AWAY | SessionFlags::LOCAL_AWAY) {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                "Session is shutting down",
+            ));
+        }
+
+        let id = self.0.next_id.get();
+        if id > u32::MAX - 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                "Stream ids are exhausted",
+            ));
+        }
+        self.0.next_id.set(id + 2);
+
+        log::trace!("Open stream {}", id);
+        self.0.send(Frame::window_update(
+            id,
+            FLAG_SYN,
+            self.0.cfg.window - DEFAULT_WINDOW,
+        ));
+        Ok(self.0.stream(id))
+    }
+
+    #[inline]
+    /// Accept stream opened by peer
+    ///
+    /// Returns `None` if session is closed.
+    pub async fn accept(&self) -> Option<Io> {
+        poll_fn(|cx| self.poll_accept(cx)).await
+    }
+
+    /// Poll for stream opened by peer
+    pub fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<Option<Io>> {
+        if let Some(io) = self.0.incoming.borrow_mut().pop_front() {
+            Poll::Ready(Some(io))
+        } else if self.is_closed() {
+            Poll::Ready(None)
+        } else {
+            self.0.accept_task.register(cx.waker());
+            Poll::Pending
+        }
+    }
+
+    /// Gracefully close session
+    ///
+    /// Peer is notified that session does not accept new streams, io stream
+    /// is closed, open streams are reset.
+    pub fn close(&self) {
+        self.0.go_away(GO_AWAY_NORMAL);
+        self.0.io.close();
+    }
+}
+
+impl Clone for Session {
+    fn clone(&self) -> Self {
+        self.0.handles.set(self.0.handles.get() + 1);
+        Session(self.0.clone())
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.0.handles.set(self.0.handles.get() - 1);
+        if self.0.handles.get() == 0 {
+            // nobody accepts new streams
+            self.0.go_away(GO_AWAY_NORMAL);
+            self.0.incoming.borrow_mut().clear();
+            self.0.check_idle();
+        }
+    }
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("io", &self.0.io)
+            .field("streams", &self.num_streams())
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+/// Read and dispatch frames from io stream
+async fn run(inner: Rc<Inner>, io: IoBoxed) {
+    let err = loop {
+        match io.recv(&Codec).await {
+            Ok(Some(frame)) => {
+                if let Err(err) = inner.dispatch(frame) {
+                    log::trace!("Session protocol error: {:?}", err);
+                    inner.go_away(GO_AWAY_PROTO);
+                    break Some(err);
+                }
+            }
+            Ok(None) => break None,
+            Err(Either::Left(err)) | Err(Either::Right(err)) => {
+                log::trace!("Session io error: {:?}", err);
+                inner.go_away(GO_AWAY_PROTO);
+                break Some(err);
+            }
+        }
+    };
+    inner.terminate(err);
+    let _ = io.shutdown().await;
+}
+
+impl Inner {
+    fn send(&self, frame: Frame) {
+        let _ = self.io.encode(frame, &Codec);
+    }
+
+    fn go_away(&self, code: u32) {
+        let flags = self.flags.get();
+        if !flags.intersects(SessionFlags::LOCAL_AWAY | SessionFlags::CLOSED) {
+            self.flags.set(flags | SessionFlags::LOCAL_AWAY);
+            self.send(Frame::go_away(code));
+        }
+    }
+
+    /// Create stream and io object for it
+    fn stream(self: &Rc<Self>, id: u32) -> Io {
+        let st = Rc::new(StreamState {
+            id,
+            flags: Cell::new(StreamFlags::empty()),
+            recv_buf: RefCell::new(BytesMut::new()),
+            recv_window: Cell::new(self.cfg.window),
+            consumed: Cell::new(0),
+            send_window: Cell::new(DEFAULT_WINDOW),
+            read_task: LocalWaker::new(),
+            write_task: LocalWaker::new(),
+        });
+        self.streams.borrow_mut().insert(id, st.clone());
+
+        Io::with_memory_pool(
+            MuxStream {
+                st,
+                inner: self.clone(),
+            },
+            self.io.memory_pool(),
+        )
+    }
+
+    fn is_remote(&self, id: u32) -> bool {
+        // client streams use odd ids
+        (id % 2 == 1) != (self.next_id.get() % 2 == 1)
+    }
+
+    fn dispatch(self: &Rc<Self>, frame: Frame) -> io::Result<()> {
+        match frame.kind {
+            TYPE_PING => {
+                if frame.flags & FLAG_SYN != 0 {
+                    self.send(Frame::ping(FLAG_ACK, frame.len));
+                }
+                return Ok(());
+            }
+            TYPE_GO_AWAY => {
+                log::trace!("Peer does not accept new streams, code: {}", frame.len);
+                self.flags.set(self.flags.get() | SessionFlags::REMOTE_AWAY);
+                return Ok(());
+            }
+            _ => (),
+        }
+
+        let id = frame.id;
+        if frame.flags & FLAG_SYN != 0 {
+            self.open_remote(id)?;
+        }
+
+        let st = if let Some(st) = self.streams.borrow().get(&id) {
+            st.clone()
+        } else {
+            // stream is closed or rejected
+            if frame.kind == TYPE_DATA && frame.flags & FLAG_RST == 0 {
+                self.send(Frame::window_update(id, FLAG_RST, 0));
+            }
+            return Ok(());
+        };
+
+        if frame.kind == TYPE_WINDOW_UPDATE {
+            st.send_window
+                .set(st.send_window.get().saturating_add(frame.len));
+            st.write_task.wake();
+        } else if !frame.payload.is_empty() {
+            let len = frame.payload.len() as u32;
+            if len > st.recv_window.get()
+                || st.flags.get().contains(StreamFlags::REMOTE_FIN)
+            {
+                log::trace!("Stream {} receive window is exceeded", id);
+                self.send(Frame::window_update(id, FLAG_RST, 0));
+                st.insert_flags(StreamFlags::RESET);
+                self.remove(&st);
+                return Ok(());
+            }
+            st.recv_window.set(st.recv_window.get() - len);
+            st.recv_buf.borrow_mut().extend_from_slice(&frame.payload);
+            st.read_task.wake();
+        }
+
+        if frame.flags & FLAG_RST != 0 {
+            log::trace!("Stream {} is reset by peer", id);
+            st.insert_flags(StreamFlags::RESET);
+            self.remove(&st);
+        } else if frame.flags & FLAG_FIN != 0 {
+            log::trace!("Stream {} is closed by peer", id);
+            st.insert_flags(StreamFlags::REMOTE_FIN);
+            st.read_task.wake();
+            st.write_task.wake();
+            if st.flags.get().contains(StreamFlags::LOCAL_FIN) {
+                self.remove(&st);
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle stream opened by peer
+    fn open_remote(self: &Rc<Self>, id: u32) -> io::Result<()> {
+        if !self.is_remote(id) || self.streams.borrow().contains_key(&id) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid stream id {}", id),
+            ));
+        }
+
+        if self.flags.get().contains(SessionFlags::LOCAL_AWAY)
+            || self.streams.borrow().len() >= self.cfg.max_streams
+            || self.incoming.borrow().len() >= self.cfg.max_pending
+        {
+            log::trace!("Reject stream {}", id);
+            self.send(Frame::window_update(id, FLAG_RST, 0));
+        } else {
+            log::trace!("Accept stream {}", id);
+            self.send(Frame::window_update(
+                id,
+                FLAG_ACK,
+                self.cfg.window - DEFAULT_WINDOW,
+            ));
+            let io = self.stream(id);
+            self.incoming.borrow_mut().push_back(io);
+            self.accept_task.wake();
+        }
+        Ok(())
+    }
+
+    /// Data is read by application, update peer's window
+    fn consumed(&self, st: &StreamState, nbytes: usize) {
+        let consumed = st.consumed.get() + nbytes as u32;
+        if consumed >= self.cfg.window / 2 && !st.is_closed() {
+            st.consumed.set(0);
+            st.recv_window.set(st.recv_window.get() + consumed);
+            self.send(Frame::window_update(st.id, 0, consumed));
+        } else {
+            st.consumed.set(consumed);
+        }
+    }
+
+    fn remove(&self, st: &StreamState) {
+        self.streams.borrow_mut().remove(&st.id);
+        st.read_task.wake();
+        st.write_task.wake();
+        self.check_idle();
+    }
+
+    /// Close io stream if session is not used anymore
+    fn check_idle(&self) {
+        if self.handles.get() == 0 && self.streams.borrow().is_empty() {
+            self.io.close();
+        }
+    }
+
+    /// Io stream is closed, reset all streams
+    fn terminate(&self, err: Option<io::Error>) {
+        log::trace!("Session is closed, error: {:?}", err);
+        self.flags.set(self.flags.get() | SessionFlags::CLOSED);
+
+        let streams: Vec<_> = self
+            .streams
+            .borrow_mut()
+            .drain()
+            .map(|(_, st)| st)
+            .collect();
+        for st in streams {
+            st.insert_flags(StreamFlags::RESET);
+            st.read_task.wake();
+            st.write_task.wake();
+        }
+        self.incoming.borrow_mut().clear();
+        self.accept_task.wake();
+    }
+}
+
+impl StreamState {
+    fn insert_flags(&self, f: StreamFlags) {
+        self.flags.set(self.flags.get() | f);
+    }
+
+    fn is_closed(&self) -> bool {
+        self.flags
+            .get()
+            .intersects(StreamFlags::RESET | StreamFlags::REMOTE_FIN)
+    }
+
+    fn reset_error() -> io::Error {
+        io::Error::new(io::ErrorKind::ConnectionReset, "Stream is reset")
+    }
+}
+
+/// Logical io stream
+struct MuxStream {
+    st: Rc<StreamState>,
+    inner: Rc<Inner>,
+}
+
+impl IoStream for MuxStream {
+    fn start(self, read: ReadContext, write: WriteContext) -> Option<Box<dyn Handle>> {
+        let handle = StreamHandle {
+            id: self.st.id,
+            io: self.inner.io.clone(),
+        };
+
+        crate::rt::spawn(ReadTask {
+            st: self.st.clone(),
+            inner: self.inner.clone(),
+            state: read,
+        });
+        crate::rt::spawn(WriteTask {
+            st: self.st,
+            inner: self.inner,
+            state: write,
+            shutdown: None,
+        });
+
+        Some(Box::new(handle))
+    }
+}
+
+struct StreamHandle {
+    id: u32,
+    io: IoRef,
+}
+
+impl Handle for StreamHandle {
+    fn query(&self, id: any::TypeId) -> Option<Box<dyn any::Any>> {
+        if id == any::TypeId::of::<StreamId>() {
+            Some(Box::new(StreamId(self.id)))
+        } else {
+            // forward query to underlying io stream
+            self.io.filter().query(id)
+        }
+    }
+}
+
+/// Stream read task
+struct ReadTask {
+    st: Rc<StreamState>,
+    inner: Rc<Inner>,
+    state: ReadContext,
+}
+
+impl Future for ReadTask {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.as_ref();
+        this.st.read_task.register(cx.waker());
+
+        match this.state.poll_ready(cx) {
+            Poll::Ready(ReadStatus::Terminate) => {
+                log::trace!("read task is instructed to terminate");
+                Poll::Ready(())
+            }
+            Poll::Ready(ReadStatus::Ready) => {
+                let data = this.st.recv_buf.replace(BytesMut::new());
+                if !data.is_empty() {
+                    let nbytes = data.len();
+                    let mut buf = this.state.get_read_buf();
+                    buf.extend_from_slice(&data);
+                    this.state.release_read_buf(buf, nbytes);
+                    this.inner.consumed(&this.st, nbytes);
+                }
+
+                let flags = this.st.flags.get();
+                if flags.contains(StreamFlags::RESET) {
+                    this.state.close(Some(StreamState::reset_error()));
+                    Poll::Ready(())
+                } else if flags.contains(StreamFlags::REMOTE_FIN) {
+                    log::trace!("stream {} is disconnected", this.st.id);
+                    this.state.close(None);
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Stream write task
+struct WriteTask {
+    st: Rc<StreamState>,
+    inner: Rc<Inner>,
+    state: WriteContext,
+    shutdown: Option<Sleep>,
+}
+
+impl WriteTask {
+    /// Send buffered data within peer's window
+    fn flush(&self) -> bool {
+        let mut buf = if let Some(buf) = self.state.get_write_buf() {
+            buf
+        } else {
+            return true;
+        };
+
+        while !buf.is_empty() {
+            let window = self.st.send_window.get() as usize;
+            if window == 0 {
+                log::trace!("stream {} send window is exhausted", self.st.id);
+                break;
+            }
+            let size = cmp::min(cmp::min(window, MAX_CHUNK), buf.len());
+            self.st.send_window.set((window - size) as u32);
+            self.inner
+                .send(Frame::data(self.st.id, 0, buf.split_to(size).freeze()));
+        }
+        let flushed = buf.is_empty();
+        let _ = self.state.release_write_buf(buf);
+        flushed
+    }
+
+    /// Send fin frame, returns true if stream is closed
+    fn close(&self) -> bool {
+        let flags = self.st.flags.get();
+        if !flags.intersects(StreamFlags::LOCAL_FIN | StreamFlags::RESET) {
+            self.st.insert_flags(StreamFlags::LOCAL_FIN);
+            self.inner
+                .send(Frame::data(self.st.id, FLAG_FIN, Bytes::new()));
+            if flags.contains(StreamFlags::REMOTE_FIN) {
+                self.inner.remove(&self.st);
+            }
+        }
+        self.st.is_closed()
+    }
+}
+
+impl Future for WriteTask {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.as_mut().get_mut();
+        this.st.write_task.register(cx.waker());
+
+        if this.st.flags.get().contains(StreamFlags::RESET) {
+            this.state.close(Some(StreamState::reset_error()));
+            return Poll::Ready(());
+        }
+
+        // flush data, send fin and wait for peer's fin
+        if let Some(ref delay) = this.shutdown {
+            if this.flush() && this.close() {
+                log::trace!("write task is stopped");
+                this.state.close(None);
+                return Poll::Ready(());
+            }
+            return if delay.poll_elapsed(cx).is_ready() {
+                log::trace!("write task is stopped after delay");
+                if !this.st.is_closed() {
+                    this.st.insert_flags(StreamFlags::RESET);
+                    this.inner
+                        .send(Frame::window_update(this.st.id, FLAG_RST, 0));
+                    this.inner.remove(&this.st);
+                }
+                this.state.close(None);
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            };
+        }
+
+        match this.state.poll_ready(cx) {
+            Poll::Ready(WriteStatus::Ready) => {
+                this.flush();
+                Poll::Pending
+            }
+            Poll::Ready(WriteStatus::Timeout(time))
+            | Poll::Ready(WriteStatus::Shutdown(time)) => {
+                log::trace!("write task is instructed to shutdown");
+                this.shutdown = Some(sleep(time));
+                self.poll(cx)
+            }
+            Poll::Ready(WriteStatus::Terminate) => {
+                log::trace!("write task is instructed to terminate");
+                this.flush();
+                this.close();
+                this.state.close(None);
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Session frame
+#[derive(Debug)]
+struct Frame {
+    kind: u8,
+    flags: u16,
+    id: u32,
+    /// Payload size for data frames, window delta, ping payload
+    /// or go away code for other frames
+    len: u32,
+    payload: Bytes,
+}
+
+impl Frame {
+    fn data(id: u32, flags: u16, payload: Bytes) -> Self {
+        Frame {
+            id,
+            flags,
+            kind: TYPE_DATA,
+            len: payload.len() as u32,
+            payload,
+        }
+    }
+
+    fn window_update(id: u32, flags: u16, delta: u32) -> Self {
+        Frame {
+            id,
+            flags,
+            kind: TYPE_WINDOW_UPDATE,
+            len: delta,
+            payload: Bytes::new(),
+        }
+    }
+
+    fn ping(flags: u16, opaque: u32) -> Self {
+        Frame {
+            flags,
+            id: 0,
+            kind: TYPE_PING,
+            len: opaque,
+            payload: Bytes::new(),
+        }
+    }
+
+    fn go_away(code: u32) -> Self {
+        Frame {
+            id: 0,
+            flags: 0,
+            kind: TYPE_GO_AWAY,
+            len: code,
+            payload: Bytes::new(),
+        }
+    }
+}
+
+/// Session frames codec
+struct Codec;
+
+impl Encoder for Codec {
+    type Item = Frame;
+    type Error = io::Error;
+
+    fn encode(&self, item: Frame, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.reserve(HEADER_SIZE + item.payload.len());
+        dst.put_u8(0);
+        dst.put_u8(item.kind);
+        dst.put_u16(item.flags);
+        dst.put_u32(item.id);
+        dst.put_u32(item.len);
+        dst.extend_from_slice(&item.payload);
+        Ok(())
+    }
+}
+
+impl Decoder for Codec {
+    type Item = Frame;
+    type Error = io::Error;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<Frame>, Self::Error> {
+        if src.len() < HEADER_SIZE {
+            return Ok(None);
+        }
+
+        let mut hdr = &src[..HEADER_SIZE];
+        let version = hdr.get_u8();
+        let kind = hdr.get_u8();
+        let flags = hdr.get_u16();
+        let id = hdr.get_u32();
+        let len = hdr.get_u32();
+
+        if version != 0 || kind > TYPE_GO_AWAY {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported frame version {} or type {}", version, kind),
+            ));
+        }
+
+        let payload = if kind == TYPE_DATA {
+            if len > MAX_FRAME {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Frame is too large: {}", len),
+                ));
+            }
+            let size = HEADER_SIZE + len as usize;
+            if src.len() < size {
+                src.reserve(size - src.len());
+                return Ok(None);
+            }
+            src.advance(HEADER_SIZE);
+            src.split_to(len as usize).freeze()
+        } else {
+            src.advance(HEADER_SIZE);
+            Bytes::new()
+        };
+
+        Ok(Some(Frame {
+            kind,
+            flags,
+            id,
+            len,
+            payload,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex_codec::BytesCodec;
+    use ntex_util::future::join;
+    use ntex_util::time::{sleep, Millis};
+
+    use super::*;
+    use crate::testing::{conformance, IoTest};
+
+    fn pair(cfg: Config) -> (Session, Session) {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(usize::MAX);
+        server.remote_buffer_cap(usize::MAX);
+        (
+            Session::client(Io::new(client), cfg),
+            Session::server(Io::new(server), cfg),
+        )
+    }
+
+    #[ntex::test]
+    async fn test_conformance() {
+        conformance::check_io(|| async {
+            let (client, server) = pair(Config::default());
+            let stream = client.open().unwrap();
+            (stream, server.accept().await.unwrap())
+        })
+        .await;
+    }
+
+    #[ntex::test]
+    async fn test_streams() {
+        let (client, server) = pair(Config::default());
+
+        let s1 = client.open().unwrap();
+        let s2 = client.open().unwrap();
+        assert_eq!(s1.query::<StreamId>().get(), Some(StreamId(1)));
+        assert_eq!(s2.query::<StreamId>().get(), Some(StreamId(3)));
+        assert_eq!(client.num_streams(), 2);
+
+        let r1 = server.accept().await.unwrap();
+        let r2 = server.accept().await.unwrap();
+        assert_eq!(r1.query::<StreamId>().get(), Some(StreamId(1)));
+        assert_eq!(server.num_streams(), 2);
+
+        // server streams use even ids
+        let s3 = server.open().unwrap();
+        assert_eq!(s3.query::<StreamId>().get(), Some(StreamId(2)));
+        let r3 = client.accept().await.unwrap();
+
+        s2.send(Bytes::from_static(b"2"), &BytesCodec)
+            .await
+            .unwrap();
+        s1.send(Bytes::from_static(b"1"), &BytesCodec)
+            .await
+            .unwrap();
+        s3.send(Bytes::from_static(b"3"), &BytesCodec)
+            .await
+            .unwrap();
+        assert_eq!(&r1.recv(&BytesCodec).await.unwrap().unwrap()[..], b"1");
+        assert_eq!(&r2.recv(&BytesCodec).await.unwrap().unwrap()[..], b"2");
+        assert_eq!(&r3.recv(&BytesCodec).await.unwrap().unwrap()[..], b"3");
+
+        // closed stream does not affect others
+        s1.shutdown().await.unwrap();
+        assert!(r1.recv(&BytesCodec).await.unwrap().is_none());
+        sleep(Millis(50)).await;
+        assert_eq!(client.num_streams(), 2);
+        assert_eq!(server.num_streams(), 2);
+
+        r2.send(Bytes::from_static(b"4"), &BytesCodec)
+            .await
+            .unwrap();
+        assert_eq!(&s2.recv(&BytesCodec).await.unwrap().unwrap()[..], b"4");
+    }
+
+    #[ntex::test]
+    async fn test_flow_control() {
+        let (client, server) = pair(Config::default());
+
+        let s1 = client.open().unwrap();
+        let s2 = client.open().unwrap();
+        let r1 = server.accept().await.unwrap();
+        let r2 = server.accept().await.unwrap();
+
+        // r1 is not read, s1 exhausts its window
+        let data = Bytes::from(vec![b'x'; DEFAULT_WINDOW as usize * 2]);
+        s1.write(&data).unwrap();
+        sleep(Millis(50)).await;
+        assert_eq!(client.0.streams.borrow()[&1].send_window.get(), 0);
+
+        // other streams are not blocked
+        s2.send(Bytes::from_static(b"DATA"), &BytesCodec)
+            .await
+            .unwrap();
+        assert_eq!(&r2.recv(&BytesCodec).await.unwrap().unwrap()[..], b"DATA");
+
+        // reading stream opens window
+        let (_, received) = join(s1.flush(true), async {
+            let mut buf = BytesMut::new();
+            while buf.len() < data.len() {
+                buf.extend_from_slice(&r1.recv(&BytesCodec).await.unwrap().unwrap());
+            }
+            buf
+        })
+        .await;
+        assert_eq!(received, data);
+    }
+
+    #[ntex::test]
+    async fn test_limits() {
+        let (client, server) = pair(Config::default().max_pending(1));
+
+        let _s1 = client.open().unwrap();
+        let s2 = client.open().unwrap();
+        sleep(Millis(50)).await;
+
+        // second stream is rejected
+        assert!(s2.recv(&BytesCodec).await.is_err());
+        assert_eq!(server.num_streams(), 1);
+        assert!(server.accept().await.is_some());
+
+        // peer is shutting down
+        server.close();
+        sleep(Millis(50)).await;
+        assert!(client.open().is_err());
+        assert!(client.is_closed());
+        assert!(server.accept().await.is_none());
+    }
+
+    #[ntex::test]
+    async fn test_session_close() {
+        let (client, server) = pair(Config::default());
+
+        let s1 = client.open().unwrap();
+        let r1 = server.accept().await.unwrap();
+
+        // io stream is closed, streams are reset
+        client.get_ref().force_close();
+        assert!(r1.recv(&BytesCodec).await.is_err());
+        assert!(s1.recv(&BytesCodec).await.is_err());
+        assert!(server.accept().await.is_none());
+        assert!(server.is_closed());
+        assert!(format!("{:?}", server).contains("Session"));
+    }
+
+    #[test]
+    fn test_codec() {
+        let mut buf = BytesMut::new();
+        Codec
+            .encode(
+                Frame::data(5, FLAG_SYN, Bytes::from_static(b"DATA")),
+                &mut buf,
+            )
+            .unwrap();
+        Codec.encode(Frame::ping(FLAG_SYN, 10), &mut buf).unwrap();
+        assert_eq!(
+            &buf[..12],
+            b"\x00\x00\x00\x01\x00\x00\x00\x05\x00\x00\x00\x04"
+        );
+
+        let mut partial = BytesMut::from(&buf[..14]);
+        assert!(Codec.decode(&mut partial).unwrap().is_none());
+
+        let frame = Codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(frame.id, 5);
+        assert_eq!(frame.flags, FLAG_SYN);
+        assert_eq!(&frame.payload[..], b"DATA");
+        let frame = Codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(frame.kind, TYPE_PING);
+        assert_eq!(frame.len, 10);
+        assert!(buf.is_empty());
+
+        let mut buf =
+            BytesMut::from(&b"\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00"[..]);
+        assert!(Codec.decode(&mut buf).is_err());
+    }
+}
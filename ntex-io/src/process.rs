@@ -0,0 +1,177 @@
+//! Child process stdio transport
+//!
+//! Child process stdin and stdout are used as io stream, data written
+//! to io is sent to child stdin and data read from io is child stdout.
+//!
+//! ```rust,no_run
+//! use ntex_io::process::{ChildIo, Command};
+//! use ntex_io::Io;
+//!
+//! #[ntex::main]
+//! async fn main() -> std::io::Result<()> {
+//!     let child = ChildIo::spawn(Command::new("language-server").arg("--stdio"))?;
+//!     let io = Io::new(child);
+//!     Ok(())
+//! }
+//! ```
+use std::task::{Context, Poll};
+use std::{any, cell::RefCell, io, pin::Pin, process::Stdio, rc::Rc};
+
+use tok_io::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tok_io::process::{Child, ChildStdin, ChildStdout};
+
+pub use tok_io::process::Command;
+
+use crate::tokio_impl::{ReadTask, WriteTask};
+use crate::{types, Handle, IoStream, ReadContext, WriteContext};
+
+/// Child process io stream
+///
+/// Child process is killed when io stream is dropped.
+pub struct ChildIo {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: ChildStdout,
+}
+
+impl ChildIo {
+    /// Spawn child process with piped stdin and stdout
+    pub fn spawn(cmd: &mut Command) -> io::Result<Self> {
+        let child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        ChildIo::from_child(child)
+    }
+
+    /// Create io stream from spawned child process
+    ///
+    /// Child process stdin and stdout must be piped.
+    pub fn from_child(mut child: Child) -> io::Result<Self> {
+        let stdin = child.stdin.take().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Child stdin is not piped")
+        })?;
+        let stdout = child.stdout.take().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Child stdout is not piped")
+        })?;
+
+        Ok(ChildIo {
+            child,
+            stdout,
+            stdin: Some(stdin),
+        })
+    }
+
+    /// Returns the OS-assigned process identifier of the child process
+    pub fn id(&self) -> Option<u32> {
+        self.child.id()
+    }
+}
+
+impl IoStream for ChildIo {
+    fn start(self, read: ReadContext, write: WriteContext) -> Option<Box<dyn Handle>> {
+        let io = Rc::new(RefCell::new(self));
+
+        tok_io::task::spawn_local(ReadTask::new(io.clone(), read));
+        tok_io::task::spawn_local(WriteTask::new(io.clone(), write));
+        Some(Box::new(io))
+    }
+}
+
+impl Handle for Rc<RefCell<ChildIo>> {
+    fn query(&self, id: any::TypeId) -> Option<Box<dyn any::Any>> {
+        if id == any::TypeId::of::<types::ProcessId>() {
+            if let Some(pid) = self.borrow().id() {
+                return Some(Box::new(types::ProcessId(pid)));
+            }
+        }
+        None
+    }
+}
+
+impl AsyncRead for ChildIo {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stdout).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ChildIo {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.stdin {
+            Some(ref mut stdin) => Pin::new(stdin).poll_write(cx, buf),
+            None => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "Child stdin is closed",
+            ))),
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.stdin {
+            Some(ref mut stdin) => Pin::new(stdin).poll_flush(cx),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        // closing stdin signals eof to child process
+        if let Some(ref mut stdin) = self.stdin {
+            ntex_util::ready!(Pin::new(stdin).poll_shutdown(cx))?;
+        }
+        self.stdin.take();
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use ntex_bytes::Bytes;
+    use ntex_codec::BytesCodec;
+
+    use super::*;
+    use crate::Io;
+
+    #[ntex::test]
+    async fn echo() {
+        let child = ChildIo::spawn(&mut Command::new("cat")).unwrap();
+        let pid = child.id().unwrap();
+        let io = Io::new(child);
+        assert_eq!(
+            io.query::<types::ProcessId>().get(),
+            Some(types::ProcessId(pid))
+        );
+
+        io.send(Bytes::from_static(b"PING"), &BytesCodec)
+            .await
+            .unwrap();
+        let msg = io.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(msg, Bytes::from_static(b"PING"));
+
+        // child exits on stdin eof
+        io.shutdown().await.unwrap();
+        assert!(io.is_closed());
+    }
+
+    #[ntex::test]
+    async fn not_piped() {
+        let child = Command::new("cat")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        assert!(ChildIo::from_child(child).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use std::cell::{Cell, RefCell};
+use std::task::{Context, Poll, Waker};
+use std::{fmt, future::Future, pin::Pin, rc::Rc};
+
+use ntex_util::task::LocalWaker;
+
+/// Per-connection task scope
+///
+/// Tasks spawned within scope are bound to connection lifetime. Scope gets
+/// cancelled when connection closes, all scope tasks get dropped on next
+/// poll. Dispatcher waits until all scope tasks are dropped before
+/// it completes.
+#[derive(Clone)]
+pub struct TaskScope(Rc<Inner>);
+
+struct Inner {
+    cancelled: Cell<bool>,
+    next: Cell<usize>,
+    tasks: RefCell<Vec<(usize, Option<Waker>)>>,
+    waiter: LocalWaker,
+}
+
+impl TaskScope {
+    pub(crate) fn new() -> Self {
+        TaskScope(Rc::new(Inner {
+            cancelled: Cell::new(false),
+            next: Cell::new(0),
+            tasks: RefCell::new(Vec::new()),
+            waiter: LocalWaker::new(),
+        }))
+    }
+
+    /// Spawn task within scope
+    ///
+    /// Task is dropped immediately if scope is already cancelled.
+    pub fn spawn<F>(&self, fut: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        if self.is_cancelled() {
+            log::trace!("Task scope is cancelled, drop task");
+            return;
+        }
+
+        let id = self.0.next.get();
+        self.0.next.set(id.wrapping_add(1));
+        self.0.tasks.borrow_mut().push((id, None));
+
+        ntex_util::spawn(ScopedTask {
+            id,
+            fut: Box::pin(fut),
+            scope: self.0.clone(),
+        });
+    }
+
+    /// Number of active tasks
+    pub fn len(&self) -> usize {
+        self.0.tasks.borrow().len()
+    }
+
+    /// Check if scope has no active tasks
+    pub fn is_empty(&self) -> bool {
+        self.0.tasks.borrow().is_empty()
+    }
+
+    /// Check if scope is cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.get()
+    }
+
+    /// Cancel all scope tasks
+    pub fn cancel(&self) {
+        if !self.0.cancelled.replace(true) {
+            log::trace!("Cancelling {} scope tasks", self.len());
+            let wakers: Vec<_> = self
+                .0
+                .tasks
+                .borrow_mut()
+                .iter_mut()
+                .filter_map(|item| item.1.take())
+                .collect();
+            wakers.into_iter().for_each(|w| w.wake());
+        }
+    }
+
+    /// Cancel scope and wait until all tasks get dropped
+    pub fn poll_shutdown(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.cancel();
+        if self.is_empty() {
+            Poll::Ready(())
+        } else {
+            self.0.waiter.register(cx.waker());
+            Poll::Pending
+        }
+    }
+
+    /// Cancel scope and wait until all tasks get dropped
+    pub async fn shutdown(&self) {
+        ntex_util::future::poll_fn(|cx| self.poll_shutdown(cx)).await
+    }
+}
+
+impl fmt::Debug for TaskScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskScope")
+            .field("tasks", &self.len())
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+struct ScopedTask {
+    id: usize,
+    fut: Pin<Box<dyn Future<Output = ()>>>,
+    scope: Rc<Inner>,
+}
+
+impl Future for ScopedTask {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.scope.cancelled.get() {
+            return Poll::Ready(());
+        }
+        let id = self.id;
+        if let Some(item) = self
+            .scope
+            .tasks
+            .borrow_mut()
+            .iter_mut()
+            .find(|item| item.0 == id)
+        {
+            item.1 = Some(cx.waker().clone());
+        }
+        self.fut.as_mut().poll(cx)
+    }
+}
+
+impl Drop for ScopedTask {
+    fn drop(&mut self) {
+        let mut tasks = self.scope.tasks.borrow_mut();
+        if let Some(idx) = tasks.iter().position(|item| item.0 == self.id) {
+            tasks.swap_remove(idx);
+        }
+        if tasks.is_empty() {
+            self.scope.waiter.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use ntex_util::time::{sleep, Millis};
+
+    use super::*;
+
+    #[ntex::test]
+    async fn test_scope() {
+        let scope = TaskScope::new();
+        let counter = Rc::new(Cell::new(0));
+
+        let counter2 = counter.clone();
+        scope.spawn(async move {
+            counter2.set(counter2.get() + 1);
+        });
+        let counter2 = counter.clone();
+        scope.spawn(async move {
+            sleep(Millis(100_000)).await;
+            counter2.set(counter2.get() + 1);
+        });
+        assert_eq!(scope.len(), 2);
+
+        sleep(Millis(50)).await;
+        assert_eq!(counter.get(), 1);
+        assert_eq!(scope.len(), 1);
+
+        scope.shutdown().await;
+        assert!(scope.is_cancelled());
+        assert!(scope.is_empty());
+        assert_eq!(counter.get(), 1);
+
+        // scope is cancelled, task is not spawned
+        scope.spawn(async {});
+        assert!(scope.is_empty());
+        assert!(format!("{:?}", scope).contains("TaskScope"));
+    }
+}
@@ -0,0 +1,163 @@
+//! Serial port transport
+//!
+//! Serial devices are supported with tokio runtime only.
+//!
+//! ```rust,no_run
+//! use ntex_io::serial::{Parity, SerialConfig};
+//!
+//! #[ntex::main]
+//! async fn main() -> std::io::Result<()> {
+//!     let io = SerialConfig::new("/dev/ttyUSB0", 115_200)
+//!         .parity(Parity::Even)
+//!         .connect()?;
+//!     Ok(())
+//! }
+//! ```
+use std::{cell::RefCell, io, rc::Rc};
+
+pub use tokio_serial::{DataBits, FlowControl, Parity, SerialStream, StopBits};
+
+use crate::tokio_impl::{ReadTask, WriteTask};
+use crate::{Handle, Io, IoStream, ReadContext, WriteContext};
+
+/// Serial port configuration
+///
+/// Default configuration is 8 data bits, no parity, one stop bit
+/// and no flow control.
+#[derive(Clone, Debug)]
+pub struct SerialConfig {
+    path: String,
+    baud_rate: u32,
+    data_bits: DataBits,
+    parity: Parity,
+    stop_bits: StopBits,
+    flow_control: FlowControl,
+    exclusive: bool,
+}
+
+impl SerialConfig {
+    /// Create serial port configuration for device path and baud rate
+    pub fn new<T: Into<String>>(path: T, baud_rate: u32) -> Self {
+        SerialConfig {
+            path: path.into(),
+            baud_rate,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+            exclusive: true,
+        }
+    }
+
+    /// Set number of bits per character
+    pub fn data_bits(mut self, data_bits: DataBits) -> Self {
+        self.data_bits = data_bits;
+        self
+    }
+
+    /// Set parity checking mode
+    pub fn parity(mut self, parity: Parity) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    /// Set number of stop bits
+    pub fn stop_bits(mut self, stop_bits: StopBits) -> Self {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    /// Set flow control mode
+    pub fn flow_control(mut self, flow_control: FlowControl) -> Self {
+        self.flow_control = flow_control;
+        self
+    }
+
+    /// Set exclusive access to the device.
+    ///
+    /// Opening exclusive device second time fails. Option is supported
+    /// on unix platforms only. By default device is opened in exclusive mode.
+    pub fn exclusive(mut self, exclusive: bool) -> Self {
+        self.exclusive = exclusive;
+        self
+    }
+
+    /// Open serial device
+    pub fn open(&self) -> io::Result<SerialStream> {
+        let builder = tokio_serial::new(self.path.as_str(), self.baud_rate)
+            .data_bits(self.data_bits)
+            .parity(self.parity)
+            .stop_bits(self.stop_bits)
+            .flow_control(self.flow_control);
+
+        #[allow(unused_mut)]
+        let mut stream = SerialStream::open(&builder)?;
+        #[cfg(unix)]
+        stream.set_exclusive(self.exclusive)?;
+        Ok(stream)
+    }
+
+    /// Open serial device and create `Io` object
+    pub fn connect(&self) -> io::Result<Io> {
+        Ok(Io::new(self.open()?))
+    }
+}
+
+impl IoStream for SerialStream {
+    fn start(self, read: ReadContext, write: WriteContext) -> Option<Box<dyn Handle>> {
+        let io = Rc::new(RefCell::new(self));
+
+        tok_io::task::spawn_local(ReadTask::new(io.clone(), read));
+        tok_io::task::spawn_local(WriteTask::new(io, write));
+        None
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use ntex_bytes::Bytes;
+    use ntex_codec::BytesCodec;
+
+    use super::*;
+
+    #[ntex::test]
+    async fn pty_pair() {
+        let (master, slave) = SerialStream::pair().unwrap();
+        let master = Io::new(master);
+        let slave = Io::new(slave);
+
+        master
+            .send(Bytes::from_static(b"PING"), &BytesCodec)
+            .await
+            .unwrap();
+        let msg = slave.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(msg, Bytes::from_static(b"PING"));
+
+        slave
+            .send(Bytes::from_static(b"PONG"), &BytesCodec)
+            .await
+            .unwrap();
+        let msg = master.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(msg, Bytes::from_static(b"PONG"));
+    }
+
+    #[test]
+    fn config() {
+        let cfg = SerialConfig::new("/dev/ttyS0", 9600)
+            .data_bits(DataBits::Seven)
+            .parity(Parity::Odd)
+            .stop_bits(StopBits::Two)
+            .flow_control(FlowControl::Hardware)
+            .exclusive(false);
+        assert_eq!(cfg.baud_rate, 9600);
+        assert_eq!(cfg.data_bits, DataBits::Seven);
+        assert_eq!(cfg.parity, Parity::Odd);
+        assert_eq!(cfg.stop_bits, StopBits::Two);
+        assert_eq!(cfg.flow_control, FlowControl::Hardware);
+        assert!(!cfg.exclusive);
+
+        assert!(SerialConfig::new("/dev/non-existing-tty", 9600)
+            .open()
+            .is_err());
+    }
+}
@@ -1,28 +1,47 @@
 use std::{io, task::Context, task::Poll};
 
-use ntex_bytes::{BytesMut, PoolRef};
+use ntex_bytes::{BufParams, BytesMut, PoolRef};
 
-use super::{io::Flags, IoRef, ReadStatus, WriteStatus};
+use super::{io::Flags, IoProfile, IoRef, ReadStatus, WriteStatus};
 
+/// Context of io stream read task
 pub struct ReadContext(pub(super) IoRef);
 
 impl ReadContext {
     #[inline]
+    /// Get memory pool of io stream
     pub fn memory_pool(&self) -> PoolRef {
         self.0.memory_pool()
     }
 
     #[inline]
+    /// Get read buffer high and low watermarks
+    pub fn read_params(&self) -> BufParams {
+        self.0.read_params()
+    }
+
+    #[inline]
+    /// Get io stream profile
+    pub fn profile(&self) -> Option<IoProfile> {
+        self.0.profile()
+    }
+
+    #[inline]
+    /// Check if read task should continue reading
     pub fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<ReadStatus> {
         self.0.filter().poll_read_ready(cx)
     }
 
     #[inline]
+    /// Notify io stream that transport is closed
     pub fn close(&self, err: Option<io::Error>) {
         self.0.filter().closed(err);
     }
 
     #[inline]
+    /// Get buffer for reading
+    ///
+    /// Buffer may contain unprocessed data, new data must be appended.
     pub fn get_read_buf(&self) -> BytesMut {
         self.0
             .filter()
@@ -31,7 +50,16 @@ impl ReadContext {
     }
 
     #[inline]
+    /// Release read buffer, `nbytes` is the number of newly read bytes
+    ///
+    /// Buffer must be released even if no data was read.
     pub fn release_read_buf(&self, buf: BytesMut, nbytes: usize) {
+        if nbytes > 0 {
+            let mut stats = self.0 .0.stats.get();
+            stats.bytes_read += nbytes as u64;
+            self.0 .0.stats.set(stats);
+        }
+
         if buf.is_empty() {
             self.0.memory_pool().release_read_buf(buf);
         } else {
@@ -42,7 +70,7 @@ impl ReadContext {
 
             if let Some(dst) = dst {
                 if nbytes > 0 {
-                    if dst.len() > self.0.memory_pool().read_params().high as usize {
+                    if dst.len() > self.0.read_params().high as usize {
                         log::trace!(
                             "buffer is too large {}, enable read back-pressure",
                             dst.len()
@@ -73,31 +101,58 @@ impl ReadContext {
     }
 }
 
+/// Context of io stream write task
 pub struct WriteContext(pub(super) IoRef);
 
 impl WriteContext {
     #[inline]
+    /// Get memory pool of io stream
     pub fn memory_pool(&self) -> PoolRef {
         self.0.memory_pool()
     }
 
     #[inline]
+    /// Get write buffer high and low watermarks
+    pub fn write_params(&self) -> BufParams {
+        self.0.write_params()
+    }
+
+    #[inline]
+    /// Check if write task should flush data or shutdown io stream
     pub fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<WriteStatus> {
         self.0.filter().poll_write_ready(cx)
     }
 
     #[inline]
+    /// Notify io stream that transport is closed
     pub fn close(&self, err: Option<io::Error>) {
         self.0.filter().closed(err)
     }
 
     #[inline]
+    /// Take buffer with pending data
     pub fn get_write_buf(&self) -> Option<BytesMut> {
-        self.0 .0.write_buf.take()
+        let buf = self.0 .0.write_buf.take();
+        self.0
+             .0
+            .write_taken
+            .set(buf.as_ref().map(|b| b.len()).unwrap_or(0));
+        buf
     }
 
     #[inline]
+    /// Return buffer with unwritten data
+    ///
+    /// Written data must be removed from buffer, empty buffer is returned
+    /// to memory pool.
     pub fn release_write_buf(&self, buf: BytesMut) -> Result<(), io::Error> {
+        let written = self.0 .0.write_taken.replace(0).saturating_sub(buf.len());
+        if written > 0 {
+            let mut stats = self.0 .0.stats.get();
+            stats.bytes_written += written as u64;
+            self.0 .0.stats.set(stats);
+        }
+
         let pool = self.0.memory_pool();
         let mut flags = self.0.flags();
 
@@ -111,7 +166,7 @@ impl WriteContext {
         } else {
             // if write buffer is smaller than high watermark value, turn off back-pressure
             if flags.contains(Flags::WR_BACKPRESSURE)
-                && buf.len() < pool.write_params_high() << 1
+                && buf.len() < (self.0.write_params().high as usize) << 1
             {
                 flags.remove(Flags::WR_BACKPRESSURE);
                 self.0.set_flags(flags);
@@ -1,13 +1,20 @@
 use std::cell::{Cell, RefCell};
 use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
 use std::{any, cmp, fmt, future::Future, io, mem, net, pin::Pin, rc::Rc};
 
-use ntex_bytes::{Buf, BufMut, BytesMut};
+use ntex_bytes::{Buf, BufMut, Bytes, BytesMut};
+use ntex_codec::{Decoder, Encoder};
+use ntex_service::{IntoService, Service};
 use ntex_util::future::poll_fn;
-use ntex_util::time::{sleep, Millis, Sleep};
+use ntex_util::time::{sleep, timeout, Millis, Sleep};
 
-use crate::{types, Handle, IoStream, ReadContext, ReadStatus, WriteContext, WriteStatus};
+use crate::filters::{Direction, Record, Recorder};
+use crate::{rt::spawn, types, DispatchItem, Dispatcher, Handle, Io, IoStream, Timer};
+use crate::{ReadContext, ReadStatus, WriteContext, WriteStatus};
+
+pub mod conformance;
 
 #[derive(Default)]
 struct AtomicWaker(Arc<Mutex<RefCell<Option<Waker>>>>);
@@ -65,12 +72,58 @@ struct Channel {
     waker: AtomicWaker,
     read: IoTestState,
     write: IoTestState,
+    faults: Faults,
+}
+
+/// Injected io faults
+#[derive(Default, Debug)]
+struct Faults {
+    write_chunk: usize,
+    write_block: usize,
+    writes: usize,
+    read_delay: Millis,
+    read_sleep: Option<Sleep>,
 }
 
 impl Channel {
     fn is_closed(&self) -> bool {
         self.flags.contains(IoTestFlags::CLOSED)
     }
+
+    /// Wait for injected read delay
+    fn poll_read_delay(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let faults = &mut self.faults;
+        if faults.read_delay == Millis::ZERO {
+            return Poll::Ready(());
+        }
+
+        let delay = faults.read_delay;
+        let read_sleep = faults.read_sleep.get_or_insert_with(|| sleep(delay));
+        if read_sleep.poll_elapsed(cx).is_pending() {
+            Poll::Pending
+        } else {
+            faults.read_sleep = None;
+            Poll::Ready(())
+        }
+    }
+
+    /// Max number of bytes for next write, `None` means `WouldBlock`
+    fn write_limit(&mut self, cx: &mut Context<'_>, len: usize) -> Option<usize> {
+        let faults = &mut self.faults;
+        if faults.write_block != 0 {
+            faults.writes += 1;
+            if faults.writes == faults.write_block {
+                faults.writes = 0;
+                cx.waker().wake_by_ref();
+                return None;
+            }
+        }
+        if faults.write_chunk != 0 {
+            Some(cmp::min(len, faults.write_chunk))
+        } else {
+            Some(len)
+        }
+    }
 }
 
 impl Default for IoTestFlags {
@@ -195,6 +248,33 @@ impl IoTest {
         write.waker.wake();
     }
 
+    /// Limit every write of remote side to `size` bytes
+    ///
+    /// Zero value disables limit.
+    pub fn write_chunk_size(&self, size: usize) {
+        self.local.lock().unwrap().borrow_mut().faults.write_chunk = size;
+    }
+
+    /// Fail every `n`-th write of remote side with `WouldBlock`
+    ///
+    /// Zero value disables `WouldBlock` injection.
+    pub fn write_would_block(&self, n: usize) {
+        let guard = self.local.lock().unwrap();
+        let mut ch = guard.borrow_mut();
+        ch.faults.write_block = n;
+        ch.faults.writes = 0;
+    }
+
+    /// Delay read readiness of remote side
+    ///
+    /// Remote side observes available data only after specified delay.
+    pub fn read_delay(&self, delay: Millis) {
+        let guard = self.remote.lock().unwrap();
+        let mut ch = guard.borrow_mut();
+        ch.faults.read_delay = delay;
+        ch.faults.read_sleep = None;
+    }
+
     /// Read any available data
     pub fn remote_buffer_cap(&self, cap: usize) {
         // change cap
@@ -249,6 +329,9 @@ impl IoTest {
         *ch.waker.0.lock().unwrap().borrow_mut() = Some(cx.waker().clone());
 
         if !ch.buf.is_empty() {
+            if ch.poll_read_delay(cx).is_pending() {
+                return Poll::Pending;
+            }
             let size = std::cmp::min(ch.buf.len(), buf.remaining_mut());
             let b = ch.buf.split_to(size);
             buf.put_slice(&b);
@@ -276,7 +359,11 @@ impl IoTest {
 
         match mem::take(&mut ch.write) {
             IoTestState::Ok => {
-                let cap = cmp::min(buf.len(), ch.buf_cap);
+                let len = match ch.write_limit(cx, buf.len()) {
+                    Some(len) => len,
+                    None => return Poll::Pending,
+                };
+                let cap = cmp::min(len, ch.buf_cap);
                 if cap > 0 {
                     ch.buf.extend(&buf[..cap]);
                     ch.buf_cap -= cap;
@@ -334,6 +421,25 @@ impl Clone for IoTest {
     }
 }
 
+impl IoTest {
+    /// Shutdown write side, peer reads eof after buffered data
+    fn shutdown_write(&self) {
+        self.local
+            .lock()
+            .unwrap()
+            .borrow_mut()
+            .flags
+            .insert(IoTestFlags::CLOSED);
+
+        let guard = self.remote.lock().unwrap();
+        let mut remote = guard.borrow_mut();
+        if let IoTestState::Ok = remote.read {
+            remote.read = IoTestState::Close;
+        }
+        remote.waker.wake();
+    }
+}
+
 impl Drop for IoTest {
     fn drop(&mut self) {
         let mut state = self.state.get();
@@ -394,9 +500,8 @@ impl Future for ReadTask {
             }
             Poll::Ready(ReadStatus::Ready) => {
                 let io = &this.io;
-                let pool = this.state.memory_pool();
+                let (hw, lw) = this.state.read_params().unpack();
                 let mut buf = self.state.get_read_buf();
-                let (hw, lw) = pool.read_params().unpack();
 
                 // read data from socket
                 let mut new_bytes = 0;
@@ -499,13 +604,7 @@ impl Future for WriteTask {
                     Poll::Ready(WriteStatus::Terminate) => {
                         log::trace!("write task is instructed to terminate");
                         // shutdown WRITE side
-                        this.io
-                            .local
-                            .lock()
-                            .unwrap()
-                            .borrow_mut()
-                            .flags
-                            .insert(IoTestFlags::CLOSED);
+                        this.io.shutdown_write();
                         this.state.close(None);
                         Poll::Ready(())
                     }
@@ -536,13 +635,7 @@ impl Future for WriteTask {
                         }
                         Shutdown::Flushed => {
                             // shutdown WRITE side
-                            this.io
-                                .local
-                                .lock()
-                                .unwrap()
-                                .borrow_mut()
-                                .flags
-                                .insert(IoTestFlags::CLOSED);
+                            this.io.shutdown_write();
                             *st = Shutdown::Stopping;
                             continue;
                         }
@@ -659,6 +752,9 @@ mod tokio_impl {
             *ch.waker.0.lock().unwrap().borrow_mut() = Some(cx.waker().clone());
 
             if !ch.buf.is_empty() {
+                if ch.poll_read_delay(cx).is_pending() {
+                    return Poll::Pending;
+                }
                 let size = std::cmp::min(ch.buf.len(), buf.remaining());
                 let b = ch.buf.split_to(size);
                 buf.put_slice(&b);
@@ -688,7 +784,11 @@ mod tokio_impl {
 
             match mem::take(&mut ch.write) {
                 IoTestState::Ok => {
-                    let cap = cmp::min(buf.len(), ch.buf_cap);
+                    let len = match ch.write_limit(cx, buf.len()) {
+                        Some(len) => len,
+                        None => return Poll::Pending,
+                    };
+                    let cap = cmp::min(len, ch.buf_cap);
                     if cap > 0 {
                         ch.buf.extend(&buf[..cap]);
                         ch.buf_cap -= cap;
@@ -735,17 +835,473 @@ mod tokio_impl {
             self: Pin<&mut Self>,
             _: &mut Context<'_>,
         ) -> Poll<io::Result<()>> {
-            self.local
-                .lock()
-                .unwrap()
-                .borrow_mut()
-                .flags
-                .insert(IoTestFlags::CLOSED);
+            self.shutdown_write();
             Poll::Ready(Ok(()))
         }
     }
 }
 
+/// Scripted io scenario
+///
+/// Scenario is an ordered list of steps that is executed on the peer side
+/// of the `IoTest` pair. Test fails if io under test deviates from the script.
+///
+/// ```rust
+/// use ntex_io::testing::{IoTest, Scenario};
+/// use ntex_util::time::Millis;
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let (client, server) = IoTest::create();
+///
+///     // echo server
+///     ntex::rt::spawn(async move {
+///         let io = ntex_io::Io::new(server);
+///         while let Ok(Some(msg)) = io.recv(&ntex_codec::BytesCodec).await {
+///             io.send(msg.freeze(), &ntex_codec::BytesCodec).await.unwrap();
+///         }
+///     });
+///
+///     Scenario::new()
+///         .read("PING")
+///         .expect_write("PING")
+///         .advance(Millis(10))
+///         .close()
+///         .expect_close()
+///         .run(&client)
+///         .await;
+/// }
+/// ```
+pub struct Scenario {
+    steps: Vec<Step>,
+    timeout: Millis,
+}
+
+type Predicate = Box<dyn Fn(&[u8]) -> bool>;
+
+enum Step {
+    Read(Bytes),
+    ReadError(io::Error),
+    Close,
+    Advance(Millis),
+    ExpectWrite(Bytes),
+    ExpectWriteWith(Predicate),
+    ExpectClose,
+}
+
+impl fmt::Debug for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Read(data) => write!(f, "Read({:?})", data),
+            Step::ReadError(err) => write!(f, "ReadError({:?})", err),
+            Step::Close => write!(f, "Close"),
+            Step::Advance(d) => write!(f, "Advance({:?})", d),
+            Step::ExpectWrite(data) => write!(f, "ExpectWrite({:?})", data),
+            Step::ExpectWriteWith(_) => write!(f, "ExpectWriteWith"),
+            Step::ExpectClose => write!(f, "ExpectClose"),
+        }
+    }
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario::new()
+    }
+}
+
+impl Scenario {
+    /// Create empty scenario
+    pub fn new() -> Self {
+        Scenario {
+            steps: Vec::new(),
+            timeout: Millis(5_000),
+        }
+    }
+
+    /// Set max wait time for expect steps.
+    ///
+    /// By default timeout is set to 5 seconds.
+    pub fn timeout(mut self, timeout: Millis) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Create scenario from recorded session
+    ///
+    /// Recorded reads are injected into io under test, recorded writes
+    /// are expected from io under test. Timing of records is ignored.
+    pub fn replay(records: &[Record]) -> Self {
+        let mut scenario = Scenario::new();
+        for rec in records {
+            scenario.steps.push(match rec.direction {
+                Direction::Read => Step::Read(rec.data.clone()),
+                Direction::Write => Step::ExpectWrite(rec.data.clone()),
+            });
+        }
+        scenario
+    }
+
+    /// Inject bytes for io under test to read
+    pub fn read<T: AsRef<[u8]>>(mut self, data: T) -> Self {
+        self.steps
+            .push(Step::Read(Bytes::copy_from_slice(data.as_ref())));
+        self
+    }
+
+    /// Inject read error
+    pub fn read_error(mut self, err: io::Error) -> Self {
+        self.steps.push(Step::ReadError(err));
+        self
+    }
+
+    /// Close read side of io under test
+    pub fn close(mut self) -> Self {
+        self.steps.push(Step::Close);
+        self
+    }
+
+    /// Advance time.
+    ///
+    /// Step waits for specified period of time, if runtime clock is paused
+    /// time advances immediately.
+    pub fn advance(mut self, period: Millis) -> Self {
+        self.steps.push(Step::Advance(period));
+        self
+    }
+
+    /// Expect io under test to write exact bytes
+    pub fn expect_write<T: AsRef<[u8]>>(mut self, data: T) -> Self {
+        self.steps
+            .push(Step::ExpectWrite(Bytes::copy_from_slice(data.as_ref())));
+        self
+    }
+
+    /// Expect io under test to write data matching predicate.
+    ///
+    /// Predicate receives all available written data, data is consumed.
+    pub fn expect_write_with<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&[u8]) -> bool + 'static,
+    {
+        self.steps.push(Step::ExpectWriteWith(Box::new(f)));
+        self
+    }
+
+    /// Expect io under test to close connection
+    pub fn expect_close(mut self) -> Self {
+        self.steps.push(Step::ExpectClose);
+        self
+    }
+
+    /// Execute scenario on the peer side of io under test.
+    ///
+    /// Panics if io under test deviates from the scenario or
+    /// if io under test writes unexpected data.
+    pub async fn run(self, peer: &IoTest) {
+        let mut buf = BytesMut::new();
+        peer.remote_buffer_cap(usize::MAX);
+
+        for (idx, step) in self.steps.into_iter().enumerate() {
+            log::trace!("scenario step {}: {:?}", idx, step);
+            match step {
+                Step::Read(data) => peer.write(data),
+                Step::ReadError(err) => peer.read_error(err),
+                Step::Close => peer.close().await,
+                Step::Advance(period) => sleep(period).await,
+                Step::ExpectWrite(data) => {
+                    while buf.len() < data.len() {
+                        read_step(peer, &mut buf, self.timeout, idx, &data).await;
+                    }
+                    let received = buf.split_to(data.len());
+                    assert_eq!(
+                        &received[..],
+                        &data[..],
+                        "Scenario step {}: unexpected write",
+                        idx
+                    );
+                }
+                Step::ExpectWriteWith(f) => {
+                    if buf.is_empty() {
+                        read_step(peer, &mut buf, self.timeout, idx, "data").await;
+                    }
+                    let received = buf.split();
+                    assert!(
+                        f(&received),
+                        "Scenario step {}: unexpected write {:?}",
+                        idx,
+                        received
+                    );
+                }
+                Step::ExpectClose => {
+                    assert!(
+                        buf.is_empty(),
+                        "Scenario step {}: unexpected write {:?}",
+                        idx,
+                        buf
+                    );
+                    match timeout(self.timeout, peer.read()).await {
+                        Ok(Ok(data)) if data.is_empty() => (),
+                        Ok(Ok(data)) => {
+                            panic!("Scenario step {}: unexpected write {:?}", idx, data)
+                        }
+                        Ok(Err(err)) => panic!("Scenario step {}: io error {:?}", idx, err),
+                        Err(_) => panic!("Scenario step {}: connection is not closed", idx),
+                    }
+                }
+            }
+        }
+
+        let rest = peer.read_any();
+        buf.extend_from_slice(&rest);
+        assert!(buf.is_empty(), "Scenario: unexpected write {:?}", buf);
+    }
+}
+
+async fn read_step<T: fmt::Debug + ?Sized>(
+    peer: &IoTest,
+    buf: &mut BytesMut,
+    period: Millis,
+    idx: usize,
+    expected: &T,
+) {
+    match timeout(period, peer.read()).await {
+        Ok(Ok(data)) if data.is_empty() => panic!(
+            "Scenario step {}: connection is closed, expected {:?}, received {:?}",
+            idx, expected, buf
+        ),
+        Ok(Ok(data)) => buf.extend_from_slice(&data),
+        Ok(Err(err)) => panic!("Scenario step {}: io error {:?}", idx, err),
+        Err(_) => panic!(
+            "Scenario step {}: timeout, expected {:?}, received {:?}",
+            idx, expected, buf
+        ),
+    }
+}
+
+/// Replay timing
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Timing {
+    Original,
+    Accelerated(u32),
+    Immediate,
+}
+
+/// Recorded session replay
+///
+/// Replay feeds recorded reads into io under test, preserving original
+/// or accelerated timing, and collects everything io under test writes.
+/// Recorded writes are used as expected output. Recording could be loaded
+/// from `Recorder` file or created from raw inbound byte stream, for example
+/// extracted from a pcap capture.
+///
+/// ```rust
+/// use ntex_io::{filters::{Direction, Record}, testing::Replay, DispatchItem};
+/// use ntex_util::{future::Ready, time::Millis};
+/// use ntex_codec::BytesCodec;
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let records = vec![Record {
+///         direction: Direction::Read,
+///         time: std::time::Duration::from_millis(100),
+///         data: "PING".into(),
+///     }, Record {
+///         direction: Direction::Write,
+///         time: std::time::Duration::from_millis(110),
+///         data: "PING".into(),
+///     }];
+///
+///     // echo service
+///     let result = Replay::new(records)
+///         .speed(10)
+///         .dispatch(BytesCodec, |msg: DispatchItem<BytesCodec>| async move {
+///             match msg {
+///                 DispatchItem::Item(msg) => Ok::<_, ()>(Some(msg.freeze())),
+///                 _ => Ok(None),
+///             }
+///         })
+///         .await;
+///     assert!(result.is_match());
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Replay {
+    records: Vec<Record>,
+    timing: Timing,
+    idle: Millis,
+}
+
+impl Replay {
+    /// Create replay from recorded session
+    pub fn new(records: Vec<Record>) -> Self {
+        Replay {
+            records,
+            timing: Timing::Original,
+            idle: Millis(100),
+        }
+    }
+
+    /// Load session written by `Recorder::writer()`
+    pub fn load<R: io::Read>(reader: R) -> io::Result<Self> {
+        Recorder::load(reader).map(Replay::new)
+    }
+
+    /// Create replay from raw inbound byte stream
+    ///
+    /// Whole stream is delivered to io under test at once.
+    pub fn from_stream<T: AsRef<[u8]>>(data: T) -> Self {
+        Replay::new(vec![Record {
+            direction: Direction::Read,
+            time: Duration::from_secs(0),
+            data: Bytes::copy_from_slice(data.as_ref()),
+        }])
+    }
+
+    /// Replay session `factor` times faster than original
+    ///
+    /// Panics if factor is 0.
+    pub fn speed(mut self, factor: u32) -> Self {
+        assert!(factor > 0, "Speed factor must be greater than 0");
+        self.timing = Timing::Accelerated(factor);
+        self
+    }
+
+    /// Ignore timing of recorded reads
+    pub fn immediate(mut self) -> Self {
+        self.timing = Timing::Immediate;
+        self
+    }
+
+    /// Time to wait for io under test writes after last read.
+    ///
+    /// By default idle timeout is set to 100 millis.
+    pub fn idle_timeout(mut self, timeout: Millis) -> Self {
+        self.idle = timeout;
+        self
+    }
+
+    /// Replay session on the peer side of io under test
+    pub async fn run(self, peer: &IoTest) -> ReplayResult {
+        peer.remote_buffer_cap(usize::MAX);
+
+        let start = Instant::now();
+        let mut writes = Vec::new();
+        let mut expected = Vec::new();
+        let collect = |writes: &mut Vec<Record>| {
+            let data = peer.read_any();
+            if !data.is_empty() {
+                writes.push(Record {
+                    direction: Direction::Write,
+                    time: start.elapsed(),
+                    data: data.freeze(),
+                });
+            }
+        };
+
+        for rec in self.records {
+            if rec.direction == Direction::Write {
+                expected.push(rec);
+                continue;
+            }
+
+            let at = match self.timing {
+                Timing::Original => rec.time,
+                Timing::Accelerated(factor) => rec.time / factor,
+                Timing::Immediate => Duration::from_secs(0),
+            };
+            let elapsed = start.elapsed();
+            if at > elapsed {
+                sleep(at - elapsed).await;
+            }
+            collect(&mut writes);
+            log::trace!("replay read at {:?}: {:?}", at, rec.data);
+            peer.write(rec.data);
+        }
+
+        // collect writes until io under test is idle or closed
+        let mut closed = false;
+        loop {
+            collect(&mut writes);
+            match timeout(self.idle, peer.read()).await {
+                Ok(Ok(data)) if !data.is_empty() => writes.push(Record {
+                    direction: Direction::Write,
+                    time: start.elapsed(),
+                    data: data.freeze(),
+                }),
+                Ok(_) => {
+                    closed = true;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+
+        ReplayResult {
+            writes,
+            expected,
+            closed,
+        }
+    }
+
+    /// Replay session into dispatcher with provided codec and service
+    pub async fn dispatch<S, U, F>(self, codec: U, service: F) -> ReplayResult
+    where
+        F: IntoService<S, DispatchItem<U>>,
+        S: Service<DispatchItem<U>, Response = Option<<U as Encoder>::Item>> + 'static,
+        U: Decoder + Encoder + 'static,
+    {
+        let (client, server) = IoTest::create();
+        let disp = Dispatcher::new(Io::new(server), codec, service, Timer::default());
+        spawn(async move {
+            let _ = disp.await;
+        });
+        self.run(&client).await
+    }
+}
+
+/// Result of session replay
+#[derive(Clone, Debug)]
+pub struct ReplayResult {
+    writes: Vec<Record>,
+    expected: Vec<Record>,
+    closed: bool,
+}
+
+impl ReplayResult {
+    /// Data written by io under test
+    pub fn writes(&self) -> &[Record] {
+        &self.writes
+    }
+
+    /// Recorded writes
+    pub fn expected(&self) -> &[Record] {
+        &self.expected
+    }
+
+    /// All data written by io under test
+    pub fn written(&self) -> Bytes {
+        concat(&self.writes)
+    }
+
+    /// Check if io under test wrote same data as recorded
+    pub fn is_match(&self) -> bool {
+        concat(&self.writes) == concat(&self.expected)
+    }
+
+    /// Check if io under test closed connection
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+fn concat(records: &[Record]) -> Bytes {
+    let mut buf = BytesMut::new();
+    for rec in records {
+        buf.extend_from_slice(&rec.data);
+    }
+    buf.freeze()
+}
+
 #[cfg(test)]
 #[allow(clippy::redundant_clone)]
 mod tests {
@@ -768,4 +1324,160 @@ mod tests {
         drop(server);
         assert!(server2.is_server_dropped());
     }
+
+    fn echo(server: IoTest) {
+        crate::rt::spawn(async move {
+            let io = crate::Io::new(server);
+            while let Ok(Some(msg)) = io.recv(&ntex_codec::BytesCodec).await {
+                io.send(msg.freeze(), &ntex_codec::BytesCodec)
+                    .await
+                    .unwrap();
+            }
+        });
+    }
+
+    #[ntex::test]
+    async fn scenario() {
+        let (client, server) = IoTest::create();
+        echo(server);
+
+        Scenario::new()
+            .read("GET /test")
+            .expect_write("GET ")
+            .expect_write("/test")
+            .read("data")
+            .expect_write_with(|buf| buf == b"data")
+            .advance(Millis(10))
+            .close()
+            .expect_close()
+            .run(&client)
+            .await;
+
+        let (client, server) = IoTest::create();
+        echo(server);
+
+        Scenario::new()
+            .read_error(io::Error::new(io::ErrorKind::Other, "err"))
+            .expect_close()
+            .run(&client)
+            .await;
+    }
+
+    #[ntex::test]
+    async fn faults() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        client.write_chunk_size(3);
+        client.write_would_block(2);
+
+        let res = poll_fn(|cx| Poll::Ready(server.poll_write_buf(cx, b"hello"))).await;
+        assert!(matches!(res, Poll::Ready(Ok(3))));
+        let res = poll_fn(|cx| Poll::Ready(server.poll_write_buf(cx, b"lo"))).await;
+        assert!(res.is_pending());
+        let res = poll_fn(|cx| Poll::Ready(server.poll_write_buf(cx, b"lo"))).await;
+        assert!(matches!(res, Poll::Ready(Ok(2))));
+        assert_eq!(client.read_any(), Bytes::from_static(b"hello"));
+
+        client.read_delay(Millis(50));
+        client.write(b"data");
+        let mut buf = BytesMut::new();
+        let res = poll_fn(|cx| Poll::Ready(server.poll_read_buf(cx, &mut buf))).await;
+        assert!(res.is_pending());
+        sleep(Millis(100)).await;
+        let res = poll_fn(|cx| Poll::Ready(server.poll_read_buf(cx, &mut buf))).await;
+        assert!(matches!(res, Poll::Ready(Ok(4))));
+        assert_eq!(buf, Bytes::from_static(b"data"));
+
+        // io still delivers complete data
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        client.write_chunk_size(2);
+        client.write_would_block(3);
+        client.read_delay(Millis(10));
+        echo(server);
+
+        client.write(b"0123456789");
+        let mut buf = BytesMut::new();
+        while buf.len() < 10 {
+            buf.extend_from_slice(&client.read().await.unwrap());
+        }
+        assert_eq!(buf, Bytes::from_static(b"0123456789"));
+    }
+
+    #[ntex::test]
+    #[should_panic(expected = "Scenario step 1: unexpected write")]
+    async fn scenario_deviation() {
+        let (client, server) = IoTest::create();
+        echo(server);
+
+        Scenario::new()
+            .read("PING")
+            .expect_write("PONG")
+            .run(&client)
+            .await;
+    }
+
+    #[ntex::test]
+    #[should_panic(expected = "Scenario step 0: timeout")]
+    async fn scenario_timeout() {
+        let (client, _server) = IoTest::create();
+
+        Scenario::new()
+            .timeout(Millis(50))
+            .expect_write("PONG")
+            .run(&client)
+            .await;
+    }
+
+    #[ntex::test]
+    async fn replay() {
+        let start = Instant::now();
+        let records = vec![
+            Record {
+                direction: Direction::Read,
+                time: Duration::from_millis(200),
+                data: Bytes::from_static(b"PING"),
+            },
+            Record {
+                direction: Direction::Write,
+                time: Duration::from_millis(210),
+                data: Bytes::from_static(b"PING"),
+            },
+            Record {
+                direction: Direction::Read,
+                time: Duration::from_millis(400),
+                data: Bytes::from_static(b"DATA"),
+            },
+        ];
+
+        let result = Replay::new(records)
+            .speed(4)
+            .idle_timeout(Millis(50))
+            .dispatch(
+                ntex_codec::BytesCodec,
+                |msg: DispatchItem<ntex_codec::BytesCodec>| async move {
+                    match msg {
+                        DispatchItem::Item(msg) => Ok::<_, ()>(Some(msg.freeze())),
+                        _ => Ok(None),
+                    }
+                },
+            )
+            .await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(!result.is_match());
+        assert!(!result.is_closed());
+        assert_eq!(result.written(), Bytes::from_static(b"PINGDATA"));
+        assert_eq!(result.expected().len(), 1);
+        assert!(result.writes()[0].time >= Duration::from_millis(50));
+
+        // raw stream
+        let (client, server) = IoTest::create();
+        echo(server);
+        let result = Replay::from_stream("GET /test")
+            .immediate()
+            .idle_timeout(Millis(50))
+            .run(&client)
+            .await;
+        assert_eq!(result.written(), Bytes::from_static(b"GET /test"));
+    }
 }
@@ -0,0 +1,264 @@
+//! Conformance checks for io stream and filter implementations
+//!
+//! Checks exercise data exchange in both directions, large transfers with
+//! write back-pressure, graceful shutdown, peer disconnect and queries.
+//! Each check panics with check name if implementation misbehaves.
+//!
+//! ```rust
+//! use ntex_io::testing::{conformance, IoTest};
+//!
+//! #[ntex::main]
+//! async fn main() {
+//!     conformance::check_stream(|| async {
+//!         let (client, server) = IoTest::create();
+//!         client.remote_buffer_cap(usize::MAX);
+//!         server.remote_buffer_cap(usize::MAX);
+//!         (client, server)
+//!     })
+//!     .await;
+//! }
+//! ```
+use std::{fmt, future::Future};
+
+use ntex_bytes::{Bytes, BytesMut};
+use ntex_codec::BytesCodec;
+use ntex_util::future::join;
+use ntex_util::time::{timeout, Millis};
+
+use super::IoTest;
+use crate::{Base, Filter, FilterFactory, Io, IoStream};
+
+const TIMEOUT: Millis = Millis(5_000);
+const LARGE_SIZE: usize = 256 * 1024;
+
+/// Run conformance checks against io stream implementation
+///
+/// `pair` creates pair of connected io streams, it is called for each check.
+pub async fn check_stream<F, R, T>(pair: F)
+where
+    F: Fn() -> R,
+    R: Future<Output = (T, T)>,
+    T: IoStream,
+{
+    let (a, b) = pair().await;
+    exchange(Io::new(a), Io::new(b)).await;
+    let (a, b) = pair().await;
+    large_transfer(Io::new(a), Io::new(b)).await;
+    let (a, b) = pair().await;
+    shutdown(Io::new(a), Io::new(b)).await;
+    let (a, b) = pair().await;
+    peer_gone(Io::new(a), Io::new(b)).await;
+    let (a, b) = pair().await;
+    query(Io::new(a), Io::new(b)).await;
+}
+
+/// Run conformance checks against connected io objects
+///
+/// `pair` creates pair of connected io objects, it is called for each check.
+/// Could be used for io streams that are constructed as io objects, for
+/// example streams of multiplexing session.
+pub async fn check_io<F, R, A, B>(pair: F)
+where
+    F: Fn() -> R,
+    R: Future<Output = (Io<A>, Io<B>)>,
+    A: Filter,
+    B: Filter,
+{
+    let (a, b) = pair().await;
+    exchange(a, b).await;
+    let (a, b) = pair().await;
+    large_transfer(a, b).await;
+    let (a, b) = pair().await;
+    shutdown(a, b).await;
+    let (a, b) = pair().await;
+    peer_gone(a, b).await;
+    let (a, b) = pair().await;
+    query(a, b).await;
+}
+
+/// Run conformance checks against filter implementation
+///
+/// Filter is added to both sides of `IoTest` pair, so filter must be able
+/// to decode data produced by itself.
+pub async fn check_filter<F, T>(factory: F)
+where
+    F: Fn() -> T,
+    T: FilterFactory<Base>,
+{
+    let (a, b) = filtered(&factory).await;
+    exchange(a, b).await;
+    let (a, b) = filtered(&factory).await;
+    large_transfer(a, b).await;
+    let (a, b) = filtered(&factory).await;
+    shutdown(a, b).await;
+    let (a, b) = filtered(&factory).await;
+    peer_gone(a, b).await;
+    let (a, b) = filtered(&factory).await;
+    query(a, b).await;
+}
+
+async fn filtered<F, T>(factory: &F) -> (Io<T::Filter>, Io<T::Filter>)
+where
+    F: Fn() -> T,
+    T: FilterFactory<Base>,
+{
+    let (client, server) = IoTest::create();
+    client.remote_buffer_cap(usize::MAX);
+    server.remote_buffer_cap(usize::MAX);
+
+    let client = expect(Io::new(client).add_filter(factory()).await, "filter");
+    let server = expect(Io::new(server).add_filter(factory()).await, "filter");
+    (client, server)
+}
+
+/// Data is delivered in both directions
+async fn exchange<A: Filter, B: Filter>(a: Io<A>, b: Io<B>) {
+    send(&a, Bytes::from_static(b"PING"), "exchange").await;
+    let data = recv_exact(&b, 4, "exchange").await;
+    assert_eq!(
+        &data[..],
+        b"PING",
+        "Conformance check `exchange`: data mismatch"
+    );
+
+    send(&b, Bytes::from_static(b"PONG"), "exchange").await;
+    let data = recv_exact(&a, 4, "exchange").await;
+    assert_eq!(
+        &data[..],
+        b"PONG",
+        "Conformance check `exchange`: data mismatch"
+    );
+}
+
+/// Data larger than io buffers is delivered intact
+async fn large_transfer<A: Filter, B: Filter>(a: Io<A>, b: Io<B>) {
+    let data: Bytes = (0..LARGE_SIZE).map(|i| (i % 251) as u8).collect();
+
+    let (_, received) = join(
+        send(&a, data.clone(), "large_transfer"),
+        recv_exact(&b, LARGE_SIZE, "large_transfer"),
+    )
+    .await;
+    assert!(
+        received == data,
+        "Conformance check `large_transfer`: data mismatch"
+    );
+}
+
+/// Graceful shutdown is observed by peer
+async fn shutdown<A: Filter, B: Filter>(a: Io<A>, b: Io<B>) {
+    send(&a, Bytes::from_static(b"DATA"), "shutdown").await;
+    match timeout(TIMEOUT, a.shutdown()).await {
+        Ok(Ok(())) => (),
+        Ok(Err(err)) => panic!("Conformance check `shutdown`: io error {:?}", err),
+        Err(_) => panic!("Conformance check `shutdown`: shutdown timeout"),
+    }
+    assert!(
+        a.is_closed(),
+        "Conformance check `shutdown`: io is not closed"
+    );
+
+    let data = recv_exact(&b, 4, "shutdown").await;
+    assert_eq!(
+        &data[..],
+        b"DATA",
+        "Conformance check `shutdown`: data mismatch"
+    );
+    match timeout(TIMEOUT, b.recv(&BytesCodec)).await {
+        Ok(Ok(None)) | Ok(Err(_)) => (),
+        Ok(Ok(Some(data))) => {
+            panic!("Conformance check `shutdown`: unexpected data {:?}", data)
+        }
+        Err(_) => panic!("Conformance check `shutdown`: peer is not notified"),
+    }
+}
+
+/// Dropped io closes connection
+async fn peer_gone<A: Filter, B: Filter>(a: Io<A>, b: Io<B>) {
+    drop(a);
+    match timeout(TIMEOUT, b.recv(&BytesCodec)).await {
+        Ok(Ok(None)) | Ok(Err(_)) => (),
+        Ok(Ok(Some(data))) => {
+            panic!("Conformance check `peer_gone`: unexpected data {:?}", data)
+        }
+        Err(_) => panic!("Conformance check `peer_gone`: peer is not notified"),
+    }
+}
+
+/// Unknown queries are handled
+async fn query<A: Filter, B: Filter>(a: Io<A>, b: Io<B>) {
+    struct Unknown;
+
+    assert!(
+        a.query::<Unknown>().as_ref().is_none(),
+        "Conformance check `query`: unknown type"
+    );
+    assert!(
+        b.query::<Unknown>().as_ref().is_none(),
+        "Conformance check `query`: unknown type"
+    );
+}
+
+async fn send<F>(io: &Io<F>, data: Bytes, check: &str) {
+    match timeout(TIMEOUT, io.send(data, &BytesCodec)).await {
+        Ok(Ok(())) => (),
+        Ok(Err(err)) => panic!("Conformance check `{}`: send error {:?}", check, err),
+        Err(_) => panic!("Conformance check `{}`: send timeout", check),
+    }
+}
+
+async fn recv_exact<F>(io: &Io<F>, size: usize, check: &str) -> Bytes {
+    let mut buf = BytesMut::new();
+    while buf.len() < size {
+        match timeout(TIMEOUT, io.recv(&BytesCodec)).await {
+            Ok(Ok(Some(data))) => buf.extend_from_slice(&data),
+            Ok(Ok(None)) => panic!(
+                "Conformance check `{}`: connection is closed, received {} of {} bytes",
+                check,
+                buf.len(),
+                size
+            ),
+            Ok(Err(err)) => panic!("Conformance check `{}`: recv error {:?}", check, err),
+            Err(_) => panic!(
+                "Conformance check `{}`: recv timeout, received {} of {} bytes",
+                check,
+                buf.len(),
+                size
+            ),
+        }
+    }
+    buf.freeze()
+}
+
+fn expect<T, E: fmt::Debug>(res: Result<T, E>, check: &str) -> T {
+    res.unwrap_or_else(|err| panic!("Conformance check `{}`: {:?}", check, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::{Checksum, Recorder};
+
+    #[ntex::test]
+    async fn test_stream() {
+        check_stream(|| async {
+            let (client, server) = IoTest::create();
+            client.remote_buffer_cap(usize::MAX);
+            server.remote_buffer_cap(usize::MAX);
+            (client, server)
+        })
+        .await;
+    }
+
+    #[ntex::test]
+    async fn test_filters() {
+        check_filter(Recorder::new).await;
+        check_filter(|| Checksum::crc32c().max_frame_size(1024)).await;
+    }
+
+    #[cfg(feature = "compress")]
+    #[ntex::test]
+    async fn test_compress() {
+        check_filter(crate::filters::Compression::gzip).await;
+    }
+}
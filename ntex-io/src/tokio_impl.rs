@@ -7,8 +7,8 @@ use tok_io::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tok_io::net::TcpStream;
 
 use crate::{
-    types, Filter, Handle, Io, IoBoxed, IoStream, ReadContext, ReadStatus, WriteContext,
-    WriteStatus,
+    types, Filter, Handle, Io, IoBoxed, IoProfile, IoStream, ReadContext, ReadStatus,
+    WriteContext, WriteStatus,
 };
 
 impl IoStream for TcpStream {
@@ -30,22 +30,40 @@ impl Handle for Rc<RefCell<TcpStream>> {
         }
         None
     }
+
+    fn set_profile(&self, profile: IoProfile) {
+        let io = self.borrow();
+        let latency = profile == IoProfile::Latency;
+        if let Err(e) = io.set_nodelay(latency) {
+            log::trace!("Cannot set TCP_NODELAY: {}", e);
+        }
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        if let Err(e) = socket2::SockRef::from(&*io).set_quickack(latency) {
+            log::trace!("Cannot set TCP_QUICKACK: {}", e);
+        }
+    }
 }
 
 /// Read io task
-struct ReadTask {
-    io: Rc<RefCell<TcpStream>>,
+pub(crate) struct ReadTask<T> {
+    io: Rc<RefCell<T>>,
     state: ReadContext,
 }
 
-impl ReadTask {
+impl<T> ReadTask<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+{
     /// Create new read io task
-    fn new(io: Rc<RefCell<TcpStream>>, state: ReadContext) -> Self {
+    pub(crate) fn new(io: Rc<RefCell<T>>, state: ReadContext) -> Self {
         Self { io, state }
     }
 }
 
-impl Future for ReadTask {
+impl<T> Future for ReadTask<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+{
     type Output = ();
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
@@ -54,10 +72,10 @@ impl Future for ReadTask {
         loop {
             match ready!(this.state.poll_ready(cx)) {
                 ReadStatus::Ready => {
-                    let pool = this.state.memory_pool();
+                    let (hw, lw) = this.state.read_params().unpack();
+                    let latency = this.state.profile() == Some(IoProfile::Latency);
                     let mut io = this.io.borrow_mut();
                     let mut buf = self.state.get_read_buf();
-                    let (hw, lw) = pool.read_params().unpack();
 
                     // read data from socket
                     let mut new_bytes = 0;
@@ -81,7 +99,7 @@ impl Future for ReadTask {
                                     close = true;
                                 } else {
                                     new_bytes += n;
-                                    if new_bytes <= hw {
+                                    if new_bytes <= hw && !latency {
                                         continue;
                                     }
                                 }
@@ -106,6 +124,10 @@ impl Future for ReadTask {
                         Poll::Ready(())
                     } else if pending {
                         Poll::Pending
+                    } else if latency {
+                        // let dispatcher process data before next read
+                        cx.waker().wake_by_ref();
+                        Poll::Pending
                     } else {
                         continue;
                     };
@@ -133,15 +155,18 @@ enum Shutdown {
 }
 
 /// Write io task
-struct WriteTask {
+pub(crate) struct WriteTask<T> {
     st: IoWriteState,
-    io: Rc<RefCell<TcpStream>>,
+    io: Rc<RefCell<T>>,
     state: WriteContext,
 }
 
-impl WriteTask {
+impl<T> WriteTask<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+{
     /// Create new write io task
-    fn new(io: Rc<RefCell<TcpStream>>, state: WriteContext) -> Self {
+    pub(crate) fn new(io: Rc<RefCell<T>>, state: WriteContext) -> Self {
         Self {
             io,
             state,
@@ -150,7 +175,10 @@ impl WriteTask {
     }
 }
 
-impl Future for WriteTask {
+impl<T> Future for WriteTask<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+{
     type Output = ();
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
@@ -486,10 +514,9 @@ mod unixstream {
             loop {
                 match ready!(this.state.poll_ready(cx)) {
                     ReadStatus::Ready => {
-                        let pool = this.state.memory_pool();
+                        let (hw, lw) = this.state.read_params().unpack();
                         let mut io = this.io.borrow_mut();
                         let mut buf = self.state.get_read_buf();
-                        let (hw, lw) = pool.read_params().unpack();
 
                         // read data from socket
                         let mut new_bytes = 0;
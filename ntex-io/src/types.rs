@@ -21,6 +21,10 @@ impl fmt::Debug for PeerAddr {
     }
 }
 
+/// Child process identifier
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ProcessId(pub u32);
+
 pub struct QueryItem<T> {
     item: Option<Box<dyn any::Any>>,
     _t: PhantomData<T>,
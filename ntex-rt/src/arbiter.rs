@@ -1,9 +1,11 @@
 use std::any::{Any, TypeId};
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::task::{Context, Poll};
-use std::{cell::RefCell, collections::HashMap, fmt, future::Future, pin::Pin, thread};
+use std::thread;
+use std::{cell::RefCell, collections::HashMap, error, fmt, future::Future, pin::Pin};
 
-use async_channel::{unbounded, Receiver, Sender};
+use async_channel::{bounded, unbounded, Receiver, Sender};
 use async_oneshot as oneshot;
 use ntex_util::Stream;
 
@@ -84,6 +86,7 @@ impl Arbiter {
         let sys = System::current();
         let (arb_tx, arb_rx) = unbounded();
         let arb_tx2 = arb_tx.clone();
+        let name2 = name.clone();
 
         let handle = thread::Builder::new()
             .name(name.clone())
@@ -104,6 +107,9 @@ impl Arbiter {
                 ADDR.with(|cell| *cell.borrow_mut() = Some(arb.clone()));
 
                 // register arbiter
+                if let Some(watchdog) = System::current().watchdog() {
+                    watchdog.register(id, name2, arb.clone());
+                }
                 let _ = System::current()
                     .sys()
                     .try_send(SystemCommand::RegisterArbiter(id, arb));
@@ -114,6 +120,9 @@ impl Arbiter {
                 }));
 
                 // unregister arbiter
+                if let Some(watchdog) = System::current().watchdog() {
+                    watchdog.unregister(id);
+                }
                 let _ = System::current()
                     .sys()
                     .try_send(SystemCommand::UnregisterArbiter(id));
@@ -138,6 +147,26 @@ impl Arbiter {
             .try_send(ArbiterCommand::Execute(Box::new(future)));
     }
 
+    /// Send a future to the Arbiter's thread, spawn it and return handle
+    /// for future's result.
+    ///
+    /// Panic in spawned future does not affect arbiter's thread, it is
+    /// reported to the caller as `ArbiterJoinError::Panic` error.
+    pub fn spawn_with_result<F>(&self, future: F) -> ArbiterJoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let (tx, rx) = bounded(1);
+        let _ = self
+            .sender
+            .try_send(ArbiterCommand::Execute(Box::new(Box::pin(CatchUnwind {
+                tx,
+                fut: Box::pin(future),
+            }))));
+        ArbiterJoinHandle { rx }
+    }
+
     /// Send a function to the Arbiter's thread. This function will be executed asynchronously.
     /// A future is created, and when resolved will contain the result of the function sent
     /// to the Arbiters thread.
@@ -232,6 +261,89 @@ impl Arbiter {
     }
 }
 
+/// Error returned by `ArbiterJoinHandle`
+#[derive(Debug)]
+pub enum ArbiterJoinError {
+    /// Spawned future panicked, contains panic payload
+    Panic(Box<dyn Any + Send + 'static>),
+    /// Future was dropped before completion, arbiter is stopped
+    Cancelled,
+}
+
+impl ArbiterJoinError {
+    /// Check if future panicked
+    pub fn is_panic(&self) -> bool {
+        matches!(self, ArbiterJoinError::Panic(_))
+    }
+}
+
+impl fmt::Display for ArbiterJoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArbiterJoinError::Panic(payload) => {
+                if let Some(msg) = payload.downcast_ref::<&'static str>() {
+                    write!(f, "Spawned future panicked: {}", msg)
+                } else if let Some(msg) = payload.downcast_ref::<String>() {
+                    write!(f, "Spawned future panicked: {}", msg)
+                } else {
+                    write!(f, "Spawned future panicked")
+                }
+            }
+            ArbiterJoinError::Cancelled => write!(f, "Spawned future is cancelled"),
+        }
+    }
+}
+
+impl error::Error for ArbiterJoinError {}
+
+/// Handle for result of the future spawned with `Arbiter::spawn_with_result()`
+pub struct ArbiterJoinHandle<T> {
+    rx: Receiver<Result<T, ArbiterJoinError>>,
+}
+
+impl<T> fmt::Debug for ArbiterJoinHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ArbiterJoinHandle")
+    }
+}
+
+impl<T> Future for ArbiterJoinHandle<T> {
+    type Output = Result<T, ArbiterJoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match Pin::new(&mut self.rx).poll_next(cx) {
+            Poll::Ready(Some(result)) => Poll::Ready(result),
+            Poll::Ready(None) => Poll::Ready(Err(ArbiterJoinError::Cancelled)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Runs future on arbiter's thread and sends result or panic to the caller
+struct CatchUnwind<T> {
+    tx: Sender<Result<T, ArbiterJoinError>>,
+    fut: Pin<Box<dyn Future<Output = T> + Send>>,
+}
+
+impl<T> Future for CatchUnwind<T> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = &mut *self;
+        if this.tx.is_closed() {
+            return Poll::Ready(());
+        }
+
+        let result = match catch_unwind(AssertUnwindSafe(|| this.fut.as_mut().poll(cx))) {
+            Ok(Poll::Ready(val)) => Ok(val),
+            Ok(Poll::Pending) => return Poll::Pending,
+            Err(payload) => Err(ArbiterJoinError::Panic(payload)),
+        };
+        let _ = this.tx.try_send(result);
+        Poll::Ready(())
+    }
+}
+
 struct ArbiterController {
     stop: Option<oneshot::Sender<i32>>,
     rx: Receiver<ArbiterCommand>,
@@ -247,6 +359,10 @@ impl Drop for ArbiterController {
                 eprintln!("Panic in Arbiter thread.");
             }
         }
+
+        // drop pending commands, so callers get notified
+        self.rx.close();
+        while self.rx.try_recv().is_ok() {}
     }
 }
 
@@ -313,6 +429,9 @@ impl Future for SystemArbiter {
                 Poll::Ready(None) => return Poll::Ready(()),
                 Poll::Ready(Some(cmd)) => match cmd {
                     SystemCommand::Exit(code) => {
+                        if let Some(watchdog) = System::current().watchdog() {
+                            watchdog.stop();
+                        }
                         // stop arbiters
                         for arb in self.arbiters.values() {
                             arb.stop();
@@ -361,4 +480,31 @@ mod tests {
         assert!(Arbiter::get_mut_item::<&'static str, _, _>(|s| *s == "test"));
         assert!(format!("{:?}", Arbiter::current()).contains("Arbiter"));
     }
+
+    #[test]
+    fn test_spawn_with_result() {
+        let sys = System::new("test");
+        let arb = Arbiter::new();
+
+        let res = sys.block_on(arb.spawn_with_result(async { 1 + 1 }));
+        assert_eq!(res.unwrap(), 2);
+
+        let res = sys.block_on(arb.spawn_with_result(async {
+            if true {
+                panic!("test panic");
+            }
+        }));
+        let err = res.unwrap_err();
+        assert!(err.is_panic());
+        assert_eq!(err.to_string(), "Spawned future panicked: test panic");
+
+        // arbiter keeps working after panic
+        let res = sys.block_on(arb.spawn_with_result(async { "ok" }));
+        assert_eq!(res.unwrap(), "ok");
+
+        // pending futures are dropped on stop
+        arb.stop();
+        let res = sys.block_on(arb.spawn_with_result(async { "ok" }));
+        assert!(matches!(res, Err(ArbiterJoinError::Cancelled)));
+    }
 }
@@ -1,7 +1,8 @@
 #![allow(dead_code)]
 use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
 use std::task::{Context, Poll};
-use std::{any, cell::RefCell, io, net, net::SocketAddr, pin::Pin, rc::Rc};
+use std::{any, cell::RefCell, io, net, pin::Pin, rc::Rc};
 
 use async_oneshot as oneshot;
 use async_std::io::{Read, Write};
@@ -44,6 +45,30 @@ pub async fn tcp_connect_in(addr: SocketAddr, pool: PoolRef) -> Result<Io, io::E
     Ok(Io::with_memory_pool(TcpStream(sock), pool))
 }
 
+/// Opens a TCP connection to a remote host from specified local address
+/// or network interface and use specified memory pool.
+///
+/// Binding to network interface is supported only on linux.
+pub async fn tcp_connect_bind_in(
+    addr: SocketAddr,
+    local_addr: Option<IpAddr>,
+    interface: Option<String>,
+    pool: PoolRef,
+) -> Result<Io, io::Error> {
+    let sock = crate::bind_socket(addr, local_addr, interface.as_deref())?;
+    let sock: net::TcpStream = async_std::task::spawn_blocking(move || {
+        sock.connect(&addr.into())?;
+        Ok::<_, io::Error>(sock.into())
+    })
+    .await?;
+    sock.set_nonblocking(true)?;
+    sock.set_nodelay(true)?;
+    Ok(Io::with_memory_pool(
+        TcpStream(async_std::net::TcpStream::from(sock)),
+        pool,
+    ))
+}
+
 #[cfg(unix)]
 /// Opens a unix stream connection.
 pub async fn unix_connect<P>(addr: P) -> Result<Io, io::Error>
@@ -247,10 +272,9 @@ impl Future for ReadTask {
         loop {
             match ready!(this.state.poll_ready(cx)) {
                 ReadStatus::Ready => {
-                    let pool = this.state.memory_pool();
+                    let (hw, lw) = this.state.read_params().unpack();
                     let mut buf = this.state.get_read_buf();
                     let io = &mut this.io;
-                    let (hw, lw) = pool.read_params().unpack();
 
                     // read data from socket
                     let mut new_bytes = 0;
@@ -602,10 +626,9 @@ mod unixstream {
             loop {
                 match ready!(this.state.poll_ready(cx)) {
                     ReadStatus::Ready => {
-                        let pool = this.state.memory_pool();
+                        let (hw, lw) = this.state.read_params().unpack();
                         let mut buf = this.state.get_read_buf();
                         let io = &mut this.io;
-                        let (hw, lw) = pool.read_params().unpack();
 
                         // read data from socket
                         let mut new_bytes = 0;
@@ -5,6 +5,7 @@ use async_oneshot as oneshot;
 use ntex_util::future::lazy;
 
 use crate::arbiter::{Arbiter, SystemArbiter};
+use crate::watchdog::{Watchdog, SYSTEM_ARBITER};
 use crate::{create_runtime, Runtime, System};
 
 /// Builder struct for a ntex runtime.
@@ -17,6 +18,8 @@ pub struct Builder {
     name: String,
     /// Whether the Arbiter will stop the whole System on uncaught panic. Defaults to false.
     stop_on_panic: bool,
+    /// Blocked arbiters detector
+    watchdog: Option<Watchdog>,
 }
 
 impl Builder {
@@ -24,6 +27,7 @@ impl Builder {
         Builder {
             name: "ntex".into(),
             stop_on_panic: false,
+            watchdog: None,
         }
     }
 
@@ -42,6 +46,17 @@ impl Builder {
         self
     }
 
+    /// Enable blocked arbiters detector.
+    ///
+    /// Watchdog thread pings event loops of system's arbiters and reports
+    /// arbiters that do not make progress, see `Watchdog` for details.
+    ///
+    /// By default watchdog is disabled.
+    pub fn watchdog(mut self, watchdog: Watchdog) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
     /// Create new System.
     ///
     /// This method panics if it can not create tokio runtime
@@ -68,9 +83,15 @@ impl Builder {
 
         let rt = create_runtime();
 
+        // blocked arbiters detector
+        let watchdog = self.watchdog.map(|w| w.start());
+
         // system arbiter
-        let _system =
-            System::construct(sys_sender, Arbiter::new_system(&rt), self.stop_on_panic);
+        let arbiter = Arbiter::new_system(&rt);
+        if let Some(ref watchdog) = watchdog {
+            watchdog.register(SYSTEM_ARBITER, self.name.clone(), arbiter.clone());
+        }
+        let _system = System::construct(sys_sender, arbiter, self.stop_on_panic, watchdog);
         let arb = SystemArbiter::new(stop_tx, sys_receiver);
         rt.spawn(Box::pin(arb));
 
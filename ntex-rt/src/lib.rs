@@ -5,10 +5,12 @@ use std::{future::Future, pin::Pin};
 mod arbiter;
 mod builder;
 mod system;
+mod watchdog;
 
-pub use self::arbiter::Arbiter;
+pub use self::arbiter::{Arbiter, ArbiterJoinError, ArbiterJoinHandle};
 pub use self::builder::{Builder, SystemRunner};
 pub use self::system::System;
+pub use self::watchdog::{BlockedArbiter, Watchdog, WatchdogPolicy};
 
 #[cfg(feature = "tokio")]
 mod tokio;
@@ -40,6 +42,44 @@ pub enum Signal {
     Term,
     /// SIGQUIT
     Quit,
+    /// SIGUSR2
+    Usr2,
+}
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+/// Create tcp socket and bind it to local address and network interface.
+fn bind_socket(
+    addr: std::net::SocketAddr,
+    local_addr: Option<std::net::IpAddr>,
+    interface: Option<&str>,
+) -> std::io::Result<socket2::Socket> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let domain = if addr.is_ipv4() {
+        Domain::IPV4
+    } else {
+        Domain::IPV6
+    };
+    let sock = Socket::new(domain, Type::STREAM, Some(Protocol::TCP))?;
+
+    if let Some(interface) = interface {
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        sock.bind_device(Some(interface.as_bytes()))?;
+
+        #[cfg(not(any(
+            target_os = "android",
+            target_os = "fuchsia",
+            target_os = "linux"
+        )))]
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("Cannot bind to interface {:?}, not supported", interface),
+        ));
+    }
+    if let Some(local_addr) = local_addr {
+        sock.bind(&std::net::SocketAddr::new(local_addr, 0).into())?;
+    }
+    Ok(sock)
 }
 
 #[cfg(all(not(feature = "tokio"), not(feature = "async-std")))]
@@ -3,6 +3,7 @@ use std::{cell::RefCell, io, sync::atomic::AtomicUsize, sync::atomic::Ordering};
 
 use super::arbiter::{Arbiter, SystemCommand};
 use super::builder::{Builder, SystemRunner};
+use super::watchdog::WatchdogHandle;
 
 static SYSTEM_COUNT: AtomicUsize = AtomicUsize::new(0);
 
@@ -13,6 +14,7 @@ pub struct System {
     sys: Sender<SystemCommand>,
     arbiter: Arbiter,
     stop_on_panic: bool,
+    watchdog: Option<WatchdogHandle>,
 }
 
 thread_local!(
@@ -25,11 +27,13 @@ impl System {
         sys: Sender<SystemCommand>,
         arbiter: Arbiter,
         stop_on_panic: bool,
+        watchdog: Option<WatchdogHandle>,
     ) -> Self {
         let sys = System {
             sys,
             arbiter,
             stop_on_panic,
+            watchdog,
             id: SYSTEM_COUNT.fetch_add(1, Ordering::SeqCst),
         };
         System::set_current(sys.clone());
@@ -87,6 +91,10 @@ impl System {
         &self.sys
     }
 
+    pub(super) fn watchdog(&self) -> Option<&WatchdogHandle> {
+        self.watchdog.as_ref()
+    }
+
     /// Return status of 'stop_on_panic' option which controls whether the System is stopped when an
     /// uncaught panic is thrown from a worker thread.
     pub fn stop_on_panic(&self) -> bool {
@@ -1,6 +1,7 @@
 use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
 use std::task::{Context, Poll};
-use std::{cell::RefCell, io, mem, net, net::SocketAddr, path::Path, pin::Pin, rc::Rc};
+use std::{cell::RefCell, io, mem, net, path::Path, pin::Pin, rc::Rc};
 
 use async_oneshot as oneshot;
 use ntex_bytes::PoolRef;
@@ -30,6 +31,25 @@ pub async fn tcp_connect_in(addr: SocketAddr, pool: PoolRef) -> Result<Io, io::E
     Ok(Io::with_memory_pool(sock, pool))
 }
 
+/// Opens a TCP connection to a remote host from specified local address
+/// or network interface and use specified memory pool.
+///
+/// Binding to network interface is supported only on linux.
+pub async fn tcp_connect_bind_in(
+    addr: SocketAddr,
+    local_addr: Option<IpAddr>,
+    interface: Option<String>,
+    pool: PoolRef,
+) -> Result<Io, io::Error> {
+    let sock = crate::bind_socket(addr, local_addr, interface.as_deref())?;
+    sock.set_nonblocking(true)?;
+    let sock = tok_io::net::TcpSocket::from_std_stream(sock.into())
+        .connect(addr)
+        .await?;
+    sock.set_nodelay(true)?;
+    Ok(Io::with_memory_pool(sock, pool))
+}
+
 #[cfg(unix)]
 /// Opens a unix stream connection.
 pub async fn unix_connect<'a, P>(addr: P) -> Result<Io, io::Error>
@@ -181,6 +201,7 @@ impl Signals {
                 (unix::SignalKind::hangup(), Signal::Hup),
                 (unix::SignalKind::terminate(), Signal::Term),
                 (unix::SignalKind::quit(), Signal::Quit),
+                (unix::SignalKind::user_defined2(), Signal::Usr2),
             ];
 
             let mut signals = Vec::new();
@@ -0,0 +1,267 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{collections::HashMap, fmt, thread};
+
+use crate::arbiter::Arbiter;
+
+/// Key of the system arbiter in watchdog registry
+pub(super) const SYSTEM_ARBITER: usize = usize::MAX;
+
+type OnBlocked = Arc<dyn Fn(&BlockedArbiter) + Send + Sync>;
+
+/// Action performed by watchdog for blocked arbiter
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WatchdogPolicy {
+    /// Log error message
+    Log,
+    /// Log error message and abort the process
+    Abort,
+}
+
+/// Information about blocked arbiter
+#[derive(Clone, Debug)]
+pub struct BlockedArbiter {
+    name: String,
+    elapsed: Duration,
+}
+
+impl BlockedArbiter {
+    /// Name of the arbiter's thread
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Time since arbiter's event loop made progress
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+/// Blocked arbiter detector.
+///
+/// Watchdog runs in separate thread and periodically pings event loop
+/// of each arbiter of the system. If arbiter does not respond within
+/// specified timeout, watchdog reports it according to configured policy.
+/// Blocked arbiter is reported once, until it makes progress again.
+///
+/// Note that system arbiter event loop runs only within `SystemRunner::run()`
+/// or `SystemRunner::block_on()` calls.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use ntex_rt::{System, Watchdog};
+///
+/// let sys = System::build()
+///     .watchdog(Watchdog::new(Duration::from_secs(5)).on_blocked(|arb| {
+///         eprintln!("arbiter {} is blocked for {:?}", arb.name(), arb.elapsed());
+///     }))
+///     .finish();
+/// ```
+#[derive(Clone)]
+pub struct Watchdog {
+    timeout: Duration,
+    interval: Duration,
+    policy: WatchdogPolicy,
+    on_blocked: Option<OnBlocked>,
+}
+
+impl fmt::Debug for Watchdog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watchdog")
+            .field("timeout", &self.timeout)
+            .field("interval", &self.interval)
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+impl Watchdog {
+    /// Create watchdog that reports arbiters blocked for longer than `timeout`.
+    ///
+    /// By default arbiters are pinged every `timeout / 4` and blocked arbiters
+    /// are logged.
+    pub fn new(timeout: Duration) -> Self {
+        Watchdog {
+            timeout,
+            interval: timeout / 4,
+            policy: WatchdogPolicy::Log,
+            on_blocked: None,
+        }
+    }
+
+    /// Set ping interval.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set action for blocked arbiters.
+    pub fn policy(mut self, policy: WatchdogPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Set callback for blocked arbiters.
+    ///
+    /// Callback is called from watchdog thread before policy is applied.
+    pub fn on_blocked<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&BlockedArbiter) + Send + Sync + 'static,
+    {
+        self.on_blocked = Some(Arc::new(f));
+        self
+    }
+
+    /// Start watchdog thread
+    pub(super) fn start(self) -> WatchdogHandle {
+        let inner = Arc::new(Inner {
+            arbiters: Mutex::new(HashMap::new()),
+            stopped: AtomicBool::new(false),
+        });
+        let handle = WatchdogHandle(inner.clone());
+
+        thread::Builder::new()
+            .name("ntex-rt:watchdog".to_string())
+            .spawn(move || self.run(inner))
+            .unwrap_or_else(|err| panic!("Cannot spawn watchdog thread: {:?}", err));
+        handle
+    }
+
+    fn run(self, inner: Arc<Inner>) {
+        while !inner.stopped.load(Ordering::Acquire) {
+            thread::sleep(self.interval);
+
+            let now = Instant::now();
+            let mut blocked = Vec::new();
+            for entry in inner.arbiters.lock().unwrap().values() {
+                let mut st = entry.state.lock().unwrap();
+                if let Some(since) = st.pending {
+                    let elapsed = now.duration_since(since);
+                    if elapsed >= self.timeout && !st.reported {
+                        st.reported = true;
+                        blocked.push(BlockedArbiter {
+                            elapsed,
+                            name: entry.name.clone(),
+                        });
+                    }
+                } else {
+                    st.pending = Some(now);
+                    let state = entry.state.clone();
+                    let name = entry.name.clone();
+                    entry.arbiter.exec_fn(move || {
+                        let mut st = state.lock().unwrap();
+                        if st.reported {
+                            log::warn!("Arbiter {:?} is not blocked anymore", name);
+                        }
+                        st.pending = None;
+                        st.reported = false;
+                    });
+                }
+            }
+
+            for arb in blocked {
+                log::error!("Arbiter {:?} is blocked for {:?}", arb.name, arb.elapsed);
+                if let Some(ref f) = self.on_blocked {
+                    f(&arb);
+                }
+                if self.policy == WatchdogPolicy::Abort {
+                    std::process::abort();
+                }
+            }
+        }
+    }
+}
+
+/// Registry of watched arbiters
+#[derive(Clone)]
+pub(super) struct WatchdogHandle(Arc<Inner>);
+
+struct Inner {
+    arbiters: Mutex<HashMap<usize, Entry>>,
+    stopped: AtomicBool,
+}
+
+struct Entry {
+    name: String,
+    arbiter: Arbiter,
+    state: Arc<Mutex<PingState>>,
+}
+
+#[derive(Default)]
+struct PingState {
+    pending: Option<Instant>,
+    reported: bool,
+}
+
+impl WatchdogHandle {
+    pub(super) fn register(&self, id: usize, name: String, arbiter: Arbiter) {
+        self.0.arbiters.lock().unwrap().insert(
+            id,
+            Entry {
+                name,
+                arbiter,
+                state: Arc::new(Mutex::new(PingState::default())),
+            },
+        );
+    }
+
+    pub(super) fn unregister(&self, id: usize) {
+        self.0.arbiters.lock().unwrap().remove(&id);
+    }
+
+    pub(super) fn stop(&self) {
+        self.0.stopped.store(true, Ordering::Release);
+        self.0.arbiters.lock().unwrap().clear();
+    }
+}
+
+impl fmt::Debug for WatchdogHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WatchdogHandle")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::System;
+
+    #[test]
+    fn test_watchdog() {
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+
+        let sys = System::build()
+            .watchdog(
+                Watchdog::new(Duration::from_millis(100))
+                    .interval(Duration::from_millis(10))
+                    .on_blocked(move |arb| {
+                        if arb.name().starts_with("ntex-rt:worker") {
+                            let _ = tx.lock().unwrap().send(arb.clone());
+                        }
+                    }),
+            )
+            .finish();
+        let arb = Arbiter::new();
+
+        // responsive arbiter is not reported
+        assert!(rx.recv_timeout(Duration::from_millis(300)).is_err());
+
+        arb.exec_fn(|| thread::sleep(Duration::from_millis(500)));
+        let blocked = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert!(blocked.elapsed() >= Duration::from_millis(100));
+
+        // blocked arbiter is reported once
+        assert!(rx.recv_timeout(Duration::from_millis(300)).is_err());
+
+        // arbiter is reported again after recovery
+        arb.exec_fn(|| thread::sleep(Duration::from_millis(300)));
+        assert!(rx.recv_timeout(Duration::from_secs(1)).is_ok());
+
+        arb.stop();
+        sys.exec(|| System::current().stop());
+    }
+}
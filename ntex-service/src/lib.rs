@@ -21,7 +21,7 @@ mod transform;
 pub use self::apply::{apply_fn, apply_fn_factory};
 pub use self::fn_service::{fn_factory, fn_factory_with_config, fn_service};
 // pub use self::fn_transform::fn_transform;
-pub use self::map_config::{map_config, map_config_service, unit_config};
+pub use self::map_config::{map_config, map_config_service, request_config, unit_config};
 pub use self::pipeline::{pipeline, pipeline_factory, Pipeline, PipelineFactory};
 pub use self::transform::{apply, Identity, Transform};
 
@@ -327,7 +327,9 @@ pub mod dev {
     pub use crate::then::{Then, ThenFactory};
     //     pub use crate::fn_transform::FnTransform;
     pub use crate::map::{Map, MapServiceFactory};
-    pub use crate::map_config::{MapConfig, UnitConfig};
+    pub use crate::map_config::{
+        MapConfig, RequestConfig, RequestConfigService, UnitConfig,
+    };
     pub use crate::map_err::{MapErr, MapErrServiceFactory};
     pub use crate::map_init_err::MapInitErr;
     pub use crate::transform::ApplyTransform;
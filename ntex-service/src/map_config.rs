@@ -1,6 +1,8 @@
 use std::task::{Context, Poll};
 use std::{cell::RefCell, future::Future, marker::PhantomData, pin::Pin, rc::Rc};
 
+use ntex_util::future::Ready;
+
 use super::{IntoServiceFactory, Service, ServiceFactory};
 
 /// Adapt external config argument to a config for provided service factory
@@ -41,6 +43,21 @@ where
     UnitConfig::new(factory.into_factory())
 }
 
+/// Create new service for each request, config is built from the request
+///
+/// Resulting factory creates service that constructs config for each
+/// incoming request with `f`, creates inner service with this config and
+/// passes request to it. It is useful for per-connection services.
+pub fn request_config<T, R, U, F, C>(factory: U, f: F) -> RequestConfig<T, R, F, C>
+where
+    T: ServiceFactory<R, C>,
+    T::InitError: Into<T::Error>,
+    U: IntoServiceFactory<T, R, C>,
+    F: Fn(&R) -> C,
+{
+    RequestConfig::new(factory.into_factory(), f)
+}
+
 /// `map_config()` adapter service factory
 pub struct MapConfig<A, R, F, C, C2> {
     a: A,
@@ -138,6 +155,125 @@ where
     }
 }
 
+/// `request_config()` adapter service factory
+pub struct RequestConfig<A, R, F, C>(Rc<(A, F)>, PhantomData<(R, C)>);
+
+impl<A, R, F, C> RequestConfig<A, R, F, C>
+where
+    A: ServiceFactory<R, C>,
+    F: Fn(&R) -> C,
+{
+    /// Create new `RequestConfig` combinator
+    pub(crate) fn new(a: A, f: F) -> Self {
+        Self(Rc::new((a, f)), PhantomData)
+    }
+}
+
+impl<A, R, F, C> Clone for RequestConfig<A, R, F, C> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), PhantomData)
+    }
+}
+
+impl<A, R, F, C> ServiceFactory<R> for RequestConfig<A, R, F, C>
+where
+    A: ServiceFactory<R, C>,
+    A::InitError: Into<A::Error>,
+    F: Fn(&R) -> C,
+{
+    type Response = A::Response;
+    type Error = A::Error;
+
+    type Service = RequestConfigService<A, R, F, C>;
+    type InitError = A::InitError;
+    type Future = Ready<Self::Service, A::InitError>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        Ready::Ok(RequestConfigService(self.0.clone(), PhantomData))
+    }
+}
+
+/// `request_config()` adapter service
+pub struct RequestConfigService<A, R, F, C>(Rc<(A, F)>, PhantomData<(R, C)>);
+
+impl<A, R, F, C> Service<R> for RequestConfigService<A, R, F, C>
+where
+    A: ServiceFactory<R, C>,
+    A::InitError: Into<A::Error>,
+    F: Fn(&R) -> C,
+{
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = RequestConfigResponse<A, R, C>;
+
+    #[inline]
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: R) -> Self::Future {
+        let cfg = (self.0 .1)(&req);
+        RequestConfigResponse {
+            req: Some(req),
+            state: RequestConfigState::CreateService {
+                fut: self.0 .0.new_service(cfg),
+            },
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    pub struct RequestConfigResponse<A: ServiceFactory<R, C>, R, C> {
+        req: Option<R>,
+        #[pin]
+        state: RequestConfigState<A, R, C>,
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[project = RequestConfigStateProject]
+    enum RequestConfigState<A: ServiceFactory<R, C>, R, C> {
+        CreateService { #[pin] fut: A::Future },
+        Ready { srv: Option<A::Service> },
+        Call { #[pin] fut: <A::Service as Service<R>>::Future, srv: A::Service },
+    }
+}
+
+impl<A, R, C> Future for RequestConfigResponse<A, R, C>
+where
+    A: ServiceFactory<R, C>,
+    A::InitError: Into<A::Error>,
+{
+    type Output = Result<A::Response, A::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.as_mut().project();
+
+        match this.state.as_mut().project() {
+            RequestConfigStateProject::CreateService { fut } => {
+                let srv = match fut.poll(cx) {
+                    Poll::Ready(Ok(srv)) => srv,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                    Poll::Pending => return Poll::Pending,
+                };
+                this.state.set(RequestConfigState::Ready { srv: Some(srv) });
+                self.poll(cx)
+            }
+            RequestConfigStateProject::Ready { srv } => {
+                match srv.as_ref().unwrap().poll_ready(cx) {
+                    Poll::Ready(result) => result?,
+                    Poll::Pending => return Poll::Pending,
+                };
+                let srv = srv.take().unwrap();
+                let fut = srv.call(this.req.take().unwrap());
+                this.state.set(RequestConfigState::Call { fut, srv });
+                self.poll(cx)
+            }
+            RequestConfigStateProject::Call { fut, .. } => fut.poll(cx),
+        }
+    }
+}
+
 /// `map_config_service()` adapter service factory
 pub struct MapConfigService<A, R, M: ServiceFactory<C, ()>, C, C2>(
     Rc<Inner<A, R, M, C, C2>>,
@@ -282,7 +418,6 @@ where
 #[cfg(test)]
 #[allow(clippy::redundant_closure)]
 mod tests {
-    use ntex_util::future::Ready;
     use std::{cell::Cell, rc::Rc};
 
     use super::*;
@@ -312,6 +447,35 @@ mod tests {
             .await;
     }
 
+    #[ntex::test]
+    async fn test_request_config() {
+        let created = Rc::new(Cell::new(0usize));
+        let created2 = created.clone();
+
+        let srv = request_config(
+            fn_factory_with_config(move |cfg: usize| {
+                created2.set(created2.get() + 1);
+                async move {
+                    if cfg == 0 {
+                        Err(())
+                    } else {
+                        Ok(fn_service(move |id: usize| Ready::<_, ()>::Ok(id + cfg)))
+                    }
+                }
+            }),
+            |req: &usize| req * 10,
+        )
+        .clone()
+        .new_service(())
+        .await
+        .unwrap();
+
+        assert_eq!(srv.call(1usize).await.unwrap(), 11);
+        assert_eq!(srv.call(2usize).await.unwrap(), 22);
+        assert!(srv.call(0usize).await.is_err());
+        assert_eq!(created.get(), 3);
+    }
+
     #[ntex::test]
     async fn test_map_config_service() {
         let item = Rc::new(Cell::new(10usize));
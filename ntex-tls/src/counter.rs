@@ -1,5 +1,7 @@
 #![allow(dead_code)]
-use std::{cell::Cell, rc::Rc, task};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::{future::Future, net::IpAddr, pin::Pin, rc::Rc, task, task::Poll};
 
 use ntex_util::task::LocalWaker;
 
@@ -13,6 +15,7 @@ struct CounterInner {
     count: Cell<usize>,
     capacity: usize,
     task: LocalWaker,
+    queue: RefCell<FairQueue>,
 }
 
 impl Counter {
@@ -22,6 +25,7 @@ impl Counter {
             capacity,
             count: Cell::new(0),
             task: LocalWaker::new(),
+            queue: RefCell::new(FairQueue::default()),
         }))
     }
 
@@ -35,6 +39,31 @@ impl Counter {
     pub fn available(&self, cx: &mut task::Context<'_>) -> bool {
         self.0.available(cx)
     }
+
+    /// Get counter guard, wait in queue if counter is at capacity.
+    ///
+    /// Released counter slots are passed to waiters in round-robin order
+    /// of waiters keys.
+    pub fn acquire(&self, key: Option<IpAddr>) -> Acquire {
+        if self.0.count.get() < self.0.capacity && self.0.queue.borrow().len == 0 {
+            Acquire::Ready(Some(self.get()))
+        } else {
+            let waiter = Rc::new(Waiter::default());
+            self.0.queue.borrow_mut().push(key, waiter.clone());
+            Acquire::Wait(self.clone(), waiter)
+        }
+    }
+
+    /// Check if number of waiters is below limit. If queue is full
+    /// it registers notification for current task.
+    pub fn queue_available(&self, limit: usize, cx: &mut task::Context<'_>) -> bool {
+        if self.0.queue.borrow().len < limit {
+            true
+        } else {
+            self.0.task.register(cx.waker());
+            false
+        }
+    }
 }
 
 pub(super) struct CounterGuard(Rc<CounterInner>);
@@ -58,6 +87,15 @@ impl CounterInner {
     }
 
     fn dec(&self) {
+        // pass slot to next waiter
+        let waiter = self.queue.borrow_mut().pop();
+        if let Some(waiter) = waiter {
+            waiter.ready.set(true);
+            waiter.task.wake();
+            self.task.wake();
+            return;
+        }
+
         let num = self.count.get();
         self.count.set(num - 1);
         if num == self.capacity {
@@ -74,3 +112,138 @@ impl CounterInner {
         }
     }
 }
+
+/// Counter guard acquisition future
+pub(super) enum Acquire {
+    Ready(Option<CounterGuard>),
+    Wait(Counter, Rc<Waiter>),
+}
+
+impl Future for Acquire {
+    type Output = CounterGuard;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<CounterGuard> {
+        match self.get_mut() {
+            Acquire::Ready(ref mut guard) => {
+                Poll::Ready(guard.take().expect("Acquire polled after completion"))
+            }
+            Acquire::Wait(ref inner, ref waiter) => {
+                if waiter.ready.get() && !waiter.taken.get() {
+                    waiter.taken.set(true);
+                    // slot is already counted
+                    Poll::Ready(CounterGuard(inner.0.clone()))
+                } else {
+                    waiter.task.register(cx.waker());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        if let Acquire::Wait(ref inner, ref waiter) = self {
+            if waiter.ready.get() {
+                // release passed slot
+                if !waiter.taken.get() {
+                    drop(CounterGuard(inner.0.clone()));
+                }
+            } else if !waiter.cancelled.get() {
+                waiter.cancelled.set(true);
+                inner.0.queue.borrow_mut().len -= 1;
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct Waiter {
+    ready: Cell<bool>,
+    taken: Cell<bool>,
+    cancelled: Cell<bool>,
+    task: LocalWaker,
+}
+
+/// Waiters queue, waiters with different keys are served in round-robin order
+#[derive(Default)]
+struct FairQueue {
+    len: usize,
+    order: VecDeque<Option<IpAddr>>,
+    waiters: HashMap<Option<IpAddr>, VecDeque<Rc<Waiter>>>,
+}
+
+impl FairQueue {
+    fn push(&mut self, key: Option<IpAddr>, waiter: Rc<Waiter>) {
+        self.len += 1;
+        let waiters = self.waiters.entry(key).or_default();
+        if waiters.is_empty() {
+            self.order.push_back(key);
+        }
+        waiters.push_back(waiter);
+    }
+
+    fn pop(&mut self) -> Option<Rc<Waiter>> {
+        while let Some(key) = self.order.pop_front() {
+            let waiters = self.waiters.get_mut(&key).unwrap();
+            let mut result = None;
+            while let Some(waiter) = waiters.pop_front() {
+                if !waiter.cancelled.get() {
+                    result = Some(waiter);
+                    break;
+                }
+            }
+            if waiters.is_empty() {
+                self.waiters.remove(&key);
+            } else {
+                self.order.push_back(key);
+            }
+            if result.is_some() {
+                self.len -= 1;
+                return result;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use ntex_util::future::lazy;
+
+    use super::*;
+
+    #[ntex::test]
+    async fn test_fair_queue() {
+        let ip1 = Some(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
+        let ip2 = Some(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)));
+
+        let counter = Counter::new(1);
+        let mut g1 = counter.acquire(ip1);
+        let g1 = lazy(|cx| Pin::new(&mut g1).poll(cx)).await;
+        assert!(matches!(g1, Poll::Ready(_)));
+
+        let mut w1 = counter.acquire(ip1);
+        let mut w2 = counter.acquire(ip1);
+        let mut w3 = counter.acquire(ip2);
+        assert!(lazy(|cx| Pin::new(&mut w1).poll(cx)).await.is_pending());
+        assert!(lazy(|cx| !counter.queue_available(3, cx)).await);
+
+        // slots are passed in round-robin order
+        drop(g1);
+        let g = lazy(|cx| Pin::new(&mut w1).poll(cx)).await;
+        assert!(lazy(|cx| Pin::new(&mut w2).poll(cx)).await.is_pending());
+        drop(g);
+        assert!(lazy(|cx| Pin::new(&mut w2).poll(cx)).await.is_pending());
+        let g = lazy(|cx| Pin::new(&mut w3).poll(cx)).await;
+        assert!(g.is_ready());
+
+        // dropped waiter releases passed slot
+        drop(g);
+        drop(w2);
+        assert_eq!(counter.0.count.get(), 0);
+        assert!(lazy(|cx| counter.available(cx)).await);
+    }
+}
@@ -0,0 +1,228 @@
+//! Tls and plain text connections on the same listener
+use std::task::{Context, Poll};
+use std::{future::Future, marker::PhantomData, pin::Pin, rc::Rc};
+
+use ntex_io::{Filter, Io};
+use ntex_service::{Service, ServiceFactory};
+use ntex_util::{future::join, time, time::Millis};
+
+/// Tls record content type of handshake message
+const TLS_HANDSHAKE: u8 = 0x16;
+
+/// Route tls and plain text connections to different services
+///
+/// Acceptor waits for first bytes of the connection. If connection starts
+/// with tls handshake record, connection is passed to `tls` service,
+/// otherwise connection is passed to `plain` service. Connection is passed
+/// to `plain` service as well if peer does not send any data within timeout.
+///
+/// Inspected data is not consumed, selected service receives full stream.
+///
+/// ```rust,ignore
+/// use ntex::{http::HttpService, server::openssl, service::pipeline_factory};
+/// use ntex::server::dual::DualAcceptor;
+///
+/// Server::build().bind("http", "127.0.0.1:8443", move |_| {
+///     DualAcceptor::new(
+///         // tls connections
+///         pipeline_factory(openssl::Acceptor::new(acceptor.clone()))
+///             .map_err(|_| ())
+///             .and_then(HttpService::build().finish(app).map_err(|_| ())),
+///         // plain text connections
+///         HttpService::build().finish(app).map_err(|_| ()),
+///     )
+/// })?
+/// ```
+pub struct DualAcceptor<F, T, P> {
+    tls: T,
+    plain: P,
+    timeout: Millis,
+    _t: PhantomData<F>,
+}
+
+impl<F, T, P> DualAcceptor<F, T, P> {
+    /// Create new dual mode acceptor
+    pub fn new(tls: T, plain: P) -> Self {
+        DualAcceptor {
+            tls,
+            plain,
+            timeout: Millis(5_000),
+            _t: PhantomData,
+        }
+    }
+
+    /// Set timeout for first bytes of the connection.
+    ///
+    /// Default is set to 5 seconds.
+    pub fn timeout<U: Into<Millis>>(mut self, timeout: U) -> Self {
+        self.timeout = timeout.into();
+        self
+    }
+}
+
+impl<F, T, P> Clone for DualAcceptor<F, T, P>
+where
+    T: Clone,
+    P: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            tls: self.tls.clone(),
+            plain: self.plain.clone(),
+            timeout: self.timeout,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<F, T, P, C> ServiceFactory<Io<F>, C> for DualAcceptor<F, T, P>
+where
+    F: Filter,
+    C: Clone,
+    T: ServiceFactory<Io<F>, C>,
+    T::Service: 'static,
+    T::Future: 'static,
+    P: ServiceFactory<
+        Io<F>,
+        C,
+        Response = T::Response,
+        Error = T::Error,
+        InitError = T::InitError,
+    >,
+    P::Service: 'static,
+    P::Future: 'static,
+{
+    type Response = T::Response;
+    type Error = T::Error;
+    type Service = DualAcceptorService<F, T::Service, P::Service>;
+    type InitError = T::InitError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Service, Self::InitError>>>>;
+
+    fn new_service(&self, cfg: C) -> Self::Future {
+        let tls = self.tls.new_service(cfg.clone());
+        let plain = self.plain.new_service(cfg);
+        let timeout = self.timeout;
+
+        Box::pin(async move {
+            let (tls, plain) = join(tls, plain).await;
+            Ok(DualAcceptorService {
+                timeout,
+                tls: Rc::new(tls?),
+                plain: Rc::new(plain?),
+                _t: PhantomData,
+            })
+        })
+    }
+}
+
+/// Service routes tls and plain text connections to different services
+pub struct DualAcceptorService<F, T, P> {
+    tls: Rc<T>,
+    plain: Rc<P>,
+    timeout: Millis,
+    _t: PhantomData<F>,
+}
+
+impl<F, T, P> Service<Io<F>> for DualAcceptorService<F, T, P>
+where
+    F: Filter,
+    T: Service<Io<F>> + 'static,
+    P: Service<Io<F>, Response = T::Response, Error = T::Error> + 'static,
+{
+    type Response = T::Response;
+    type Error = T::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let tls = self.tls.poll_ready(cx)?.is_ready();
+        let plain = self.plain.poll_ready(cx)?.is_ready();
+        if tls && plain {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let tls = self.tls.poll_shutdown(cx, is_error).is_ready();
+        let plain = self.plain.poll_shutdown(cx, is_error).is_ready();
+        if tls && plain {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&self, io: Io<F>) -> Self::Future {
+        let tls = self.tls.clone();
+        let plain = self.plain.clone();
+        let timeout = self.timeout;
+
+        Box::pin(async move {
+            // wait for first bytes, connection errors are handled by services
+            let _ = time::timeout(timeout, io.read_ready()).await;
+
+            if io.with_read_buf(|buf| buf.first() == Some(&TLS_HANDSHAKE)) {
+                tls.call(io).await
+            } else {
+                plain.call(io).await
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::{codec::BytesCodec, util::Bytes};
+    use ntex_io::testing::IoTest;
+    use ntex_service::fn_service;
+
+    use super::*;
+
+    #[ntex::test]
+    async fn dual_acceptor() {
+        let factory = DualAcceptor::new(
+            fn_service(|io: Io| async move {
+                let msg = io.recv(&BytesCodec).await.unwrap().unwrap();
+                Ok::<_, ()>(("tls", msg.freeze()))
+            }),
+            fn_service(|io: Io| async move {
+                let msg = io.recv(&BytesCodec).await.unwrap().unwrap();
+                Ok::<_, ()>(("plain", msg.freeze()))
+            }),
+        )
+        .timeout(Millis(50));
+        let srv = factory.clone().new_service(()).await.unwrap();
+
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        client.write(b"\x16\x03\x01\x00\x05hello");
+        let res = srv.call(Io::new(server)).await.unwrap();
+        assert_eq!(
+            res,
+            ("tls", Bytes::from_static(b"\x16\x03\x01\x00\x05hello"))
+        );
+
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        client.write(b"GET / HTTP/1.1\r\n\r\n");
+        let res = srv.call(Io::new(server)).await.unwrap();
+        assert_eq!(
+            res,
+            ("plain", Bytes::from_static(b"GET / HTTP/1.1\r\n\r\n"))
+        );
+
+        // no data within timeout
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        let fut = ntex::rt::spawn(srv.call(Io::new(server)));
+        time::sleep(Millis(100)).await;
+        client.write(b"\x16\x03");
+        assert_eq!(
+            fut.await.unwrap().unwrap(),
+            ("plain", Bytes::from_static(b"\x16\x03"))
+        );
+    }
+}
@@ -2,6 +2,7 @@
 #![allow(clippy::return_self_not_must_use)]
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+pub mod dual;
 pub mod types;
 
 #[cfg(feature = "openssl")]
@@ -11,6 +12,9 @@ pub mod openssl;
 pub mod rustls;
 
 mod counter;
+mod limits;
+
+pub use self::limits::{HandshakeLimitError, HandshakeLimits};
 
 /// Sets the maximum per-worker concurrent ssl connection establish process.
 ///
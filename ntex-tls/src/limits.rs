@@ -0,0 +1,287 @@
+use std::task::{Context, Poll};
+use std::{cell::RefCell, collections::HashMap, error, fmt, future::Future, io};
+use std::{net::IpAddr, pin::Pin, rc::Rc, time::Duration, time::Instant};
+
+use ntex_io::{types::PeerAddr, Io};
+use ntex_util::time::{now, Millis};
+
+use crate::counter::{Acquire, Counter, CounterGuard};
+
+/// Cleanup of per-ip statistics starts after this number of tracked ips
+const CLEANUP_THRESHOLD: usize = 1024;
+
+/// Tls handshake limits.
+///
+/// Limits are applied to connections by peer ip address. Handshakes
+/// statistics are tracked per worker, so actual limits are multiplied
+/// by number of workers.
+///
+/// By default handshakes are not limited. Pending handshakes wait for
+/// handshake concurrency budget (`max_concurrent_ssl_accept()`) in order
+/// of accepted connections.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct HandshakeLimits {
+    max_per_ip: usize,
+    rate: Option<(usize, Millis)>,
+    queue: usize,
+}
+
+impl HandshakeLimits {
+    /// Create default limits
+    pub fn new() -> Self {
+        HandshakeLimits::default()
+    }
+
+    /// Set max number of concurrent handshakes from one ip address.
+    ///
+    /// Includes queued handshakes. Connections over limit are closed.
+    pub fn max_per_ip(mut self, num: usize) -> Self {
+        self.max_per_ip = num;
+        self
+    }
+
+    /// Set max number of handshakes from one ip address within time window.
+    ///
+    /// Connections over limit are closed.
+    pub fn rate_per_ip<T: Into<Millis>>(mut self, num: usize, window: T) -> Self {
+        self.rate = Some((num, window.into()));
+        self
+    }
+
+    /// Enable fair queueing of pending handshakes.
+    ///
+    /// If handshake concurrency budget is exhausted, up to `size` pending
+    /// handshakes are queued. Released budget is passed to queued handshakes
+    /// in round-robin order of peer ip addresses, so single client could not
+    /// monopolize handshakes budget. Acceptor stops accepting new connections
+    /// if queue is full.
+    pub fn fair_queue(mut self, size: usize) -> Self {
+        self.queue = size;
+        self
+    }
+
+    pub(crate) fn limiter(&self, conns: Counter) -> Limiter {
+        Limiter {
+            conns,
+            limits: *self,
+            ips: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+}
+
+/// Handshake limit error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandshakeLimitError {
+    /// Too many concurrent handshakes from ip address
+    TooManyHandshakes(IpAddr),
+    /// Handshakes rate limit is exceeded for ip address
+    RateExceeded(IpAddr),
+}
+
+impl fmt::Display for HandshakeLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandshakeLimitError::TooManyHandshakes(ip) => {
+                write!(f, "Too many concurrent tls handshakes from {}", ip)
+            }
+            HandshakeLimitError::RateExceeded(ip) => {
+                write!(f, "Tls handshakes rate limit is exceeded for {}", ip)
+            }
+        }
+    }
+}
+
+impl error::Error for HandshakeLimitError {}
+
+impl From<HandshakeLimitError> for io::Error {
+    fn from(err: HandshakeLimitError) -> io::Error {
+        io::Error::new(io::ErrorKind::ConnectionRefused, err)
+    }
+}
+
+#[derive(Default)]
+struct IpState {
+    active: usize,
+    started: usize,
+    window: Option<Instant>,
+}
+
+/// Applies handshake limits to connections
+pub(crate) struct Limiter {
+    conns: Counter,
+    limits: HandshakeLimits,
+    ips: Rc<RefCell<HashMap<IpAddr, IpState>>>,
+}
+
+impl Limiter {
+    pub(crate) fn poll_ready(&self, cx: &mut Context<'_>) -> bool {
+        if self.limits.queue == 0 {
+            self.conns.available(cx)
+        } else {
+            self.conns.queue_available(self.limits.queue, cx)
+        }
+    }
+
+    /// Check connection against limits and acquire handshake budget
+    pub(crate) fn admit<F>(&self, io: &Io<F>) -> Result<Admission, HandshakeLimitError> {
+        let ip = io.query::<PeerAddr>().get().map(|addr| addr.0.ip());
+
+        let ip_guard = if let Some(ip) = ip {
+            self.admit_ip(ip)?
+        } else {
+            None
+        };
+
+        let acquire = if self.limits.queue == 0 {
+            Acquire::Ready(Some(self.conns.get()))
+        } else {
+            self.conns.acquire(ip)
+        };
+        Ok(Admission {
+            acquire,
+            ip: ip_guard,
+        })
+    }
+
+    fn admit_ip(&self, ip: IpAddr) -> Result<Option<IpGuard>, HandshakeLimitError> {
+        if self.limits.max_per_ip == 0 && self.limits.rate.is_none() {
+            return Ok(None);
+        }
+
+        let now = now();
+        let mut ips = self.ips.borrow_mut();
+        if ips.len() >= CLEANUP_THRESHOLD {
+            let window = self.limits.rate.map(|(_, w)| w);
+            ips.retain(|_, st| st.active > 0 || is_active_window(st.window, window, now));
+        }
+
+        let st = ips.entry(ip).or_default();
+        if self.limits.max_per_ip != 0 && st.active >= self.limits.max_per_ip {
+            return Err(HandshakeLimitError::TooManyHandshakes(ip));
+        }
+        if let Some((max, window)) = self.limits.rate {
+            if !is_active_window(st.window, Some(window), now) {
+                st.window = Some(now);
+                st.started = 0;
+            }
+            if st.started >= max {
+                return Err(HandshakeLimitError::RateExceeded(ip));
+            }
+            st.started += 1;
+        }
+        st.active += 1;
+
+        Ok(Some(IpGuard {
+            ip,
+            ips: self.ips.clone(),
+        }))
+    }
+}
+
+fn is_active_window(start: Option<Instant>, window: Option<Millis>, now: Instant) -> bool {
+    match (start, window) {
+        (Some(start), Some(window)) => now < start + Duration::from(window),
+        _ => false,
+    }
+}
+
+/// Handshake budget acquisition future
+pub(crate) struct Admission {
+    acquire: Acquire,
+    ip: Option<IpGuard>,
+}
+
+impl Future for Admission {
+    type Output = AdmissionGuard;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let conn = match Pin::new(&mut self.acquire).poll(cx) {
+            Poll::Ready(guard) => guard,
+            Poll::Pending => return Poll::Pending,
+        };
+        Poll::Ready(AdmissionGuard {
+            _conn: conn,
+            _ip: self.ip.take(),
+        })
+    }
+}
+
+/// Guard of admitted handshake
+pub(crate) struct AdmissionGuard {
+    _conn: CounterGuard,
+    _ip: Option<IpGuard>,
+}
+
+struct IpGuard {
+    ip: IpAddr,
+    ips: Rc<RefCell<HashMap<IpAddr, IpState>>>,
+}
+
+impl Drop for IpGuard {
+    fn drop(&mut self) {
+        let mut ips = self.ips.borrow_mut();
+        if let Some(st) = ips.get_mut(&self.ip) {
+            st.active -= 1;
+            if st.active == 0 && st.window.is_none() {
+                ips.remove(&self.ip);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr};
+
+    use ntex_io::testing::IoTest;
+    use ntex_util::future::lazy;
+
+    use super::*;
+
+    fn io(ip: u8) -> Io {
+        let (_, server) = IoTest::create();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, ip)), 1);
+        Io::new(server.set_peer_addr(addr))
+    }
+
+    #[ntex::test]
+    async fn test_limits() {
+        let limiter = HandshakeLimits::new()
+            .max_per_ip(2)
+            .rate_per_ip(3, Millis(50))
+            .fair_queue(2)
+            .limiter(Counter::new(1));
+
+        let mut a1 = limiter.admit(&io(1)).unwrap();
+        let g1 = lazy(|cx| Pin::new(&mut a1).poll(cx)).await;
+        assert!(g1.is_ready());
+
+        // queued handshake
+        let mut a2 = limiter.admit(&io(1)).unwrap();
+        assert!(lazy(|cx| Pin::new(&mut a2).poll(cx)).await.is_pending());
+        assert_eq!(
+            limiter.admit(&io(1)).err(),
+            Some(HandshakeLimitError::TooManyHandshakes(IpAddr::V4(
+                Ipv4Addr::new(127, 0, 0, 1)
+            )))
+        );
+
+        let mut a3 = limiter.admit(&io(2)).unwrap();
+        assert!(lazy(|cx| !limiter.poll_ready(cx)).await);
+
+        drop(g1);
+        drop(a2);
+        assert!(lazy(|cx| Pin::new(&mut a3).poll(cx)).await.is_ready());
+
+        // rate limit
+        let _ = limiter.admit(&io(1)).unwrap();
+        assert_eq!(
+            limiter.admit(&io(1)).err(),
+            Some(HandshakeLimitError::RateExceeded(IpAddr::V4(
+                Ipv4Addr::new(127, 0, 0, 1)
+            )))
+        );
+        ntex::time::sleep(Millis(60)).await;
+        assert!(limiter.admit(&io(1)).is_ok());
+    }
+}
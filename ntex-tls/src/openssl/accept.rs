@@ -6,8 +6,9 @@ use ntex_service::{Service, ServiceFactory};
 use ntex_util::{future::Ready, time::Millis};
 use tls_openssl::ssl::SslAcceptor;
 
-use crate::counter::{Counter, CounterGuard};
+use crate::limits::{Admission, AdmissionGuard, Limiter};
 use crate::MAX_SSL_ACCEPT_COUNTER;
+use crate::{HandshakeLimitError, HandshakeLimits};
 
 use super::{SslAcceptor as IoSslAcceptor, SslFilter};
 
@@ -16,6 +17,7 @@ use super::{SslAcceptor as IoSslAcceptor, SslFilter};
 /// `openssl` feature enables `Acceptor` type
 pub struct Acceptor<F> {
     acceptor: IoSslAcceptor,
+    limits: HandshakeLimits,
     _t: PhantomData<F>,
 }
 
@@ -24,6 +26,7 @@ impl<F> Acceptor<F> {
     pub fn new(acceptor: SslAcceptor) -> Self {
         Acceptor {
             acceptor: IoSslAcceptor::new(acceptor),
+            limits: HandshakeLimits::default(),
             _t: PhantomData,
         }
     }
@@ -36,6 +39,14 @@ impl<F> Acceptor<F> {
         self
     }
 
+    /// Set handshake limits.
+    ///
+    /// By default handshakes are not limited.
+    pub fn limits(mut self, limits: HandshakeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     pub fn seal(self) -> BoxedFactory<Acceptor<F>, Io<F>> {
         BoxedFactory::new(self)
     }
@@ -51,6 +62,7 @@ impl<F> Clone for Acceptor<F> {
     fn clone(&self) -> Self {
         Self {
             acceptor: self.acceptor.clone(),
+            limits: self.limits,
             _t: PhantomData,
         }
     }
@@ -68,7 +80,7 @@ impl<F: Filter, C> ServiceFactory<Io<F>, C> for Acceptor<F> {
         MAX_SSL_ACCEPT_COUNTER.with(|conns| {
             Ready::Ok(AcceptorService {
                 acceptor: self.acceptor.clone(),
-                conns: conns.clone(),
+                limiter: self.limits.limiter(conns.clone()),
                 _t: PhantomData,
             })
         })
@@ -80,7 +92,7 @@ impl<F: Filter, C> ServiceFactory<Io<F>, C> for Acceptor<F> {
 /// `openssl` feature enables `Acceptor` type
 pub struct AcceptorService<F> {
     acceptor: IoSslAcceptor,
-    conns: Counter,
+    limiter: Limiter,
     _t: PhantomData<F>,
 }
 
@@ -91,7 +103,7 @@ impl<F: Filter> Service<Io<F>> for AcceptorService<F> {
 
     #[inline]
     fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        if self.conns.available(cx) {
+        if self.limiter.poll_ready(cx) {
             Poll::Ready(Ok(()))
         } else {
             Poll::Pending
@@ -100,28 +112,45 @@ impl<F: Filter> Service<Io<F>> for AcceptorService<F> {
 
     #[inline]
     fn call(&self, req: Io<F>) -> Self::Future {
+        let (admission, err) = match self.limiter.admit(&req) {
+            Ok(admission) => (Some(admission), None),
+            Err(err) => (None, Some(err)),
+        };
         AcceptorServiceResponse {
-            _guard: self.conns.get(),
+            admission,
+            err,
+            _guard: None,
             fut: self.acceptor.clone().create(req),
         }
     }
 }
 
-pin_project_lite::pin_project! {
-    pub struct AcceptorServiceResponse<F>
-    where
-        F: Filter,
-    {
-        #[pin]
-        fut: <IoSslAcceptor as FilterFactory<F>>::Future,
-        _guard: CounterGuard,
-    }
+pub struct AcceptorServiceResponse<F: Filter> {
+    fut: <IoSslAcceptor as FilterFactory<F>>::Future,
+    admission: Option<Admission>,
+    err: Option<HandshakeLimitError>,
+    _guard: Option<AdmissionGuard>,
 }
 
 impl<F: Filter> Future for AcceptorServiceResponse<F> {
     type Output = Result<Io<SslFilter<F>>, Box<dyn Error>>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.project().fut.poll(cx)
+        let this = self.get_mut();
+        if let Some(err) = this.err.take() {
+            return Poll::Ready(Err(Box::new(err)));
+        }
+
+        // wait for handshake budget
+        if let Some(ref mut admission) = this.admission {
+            match Pin::new(admission).poll(cx) {
+                Poll::Ready(guard) => {
+                    this._guard = Some(guard);
+                    this.admission = None;
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        this.fut.as_mut().poll(cx)
     }
 }
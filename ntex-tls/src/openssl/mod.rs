@@ -1,11 +1,12 @@
 #![allow(clippy::type_complexity)]
 //! An implementation of SSL streams for ntex backed by OpenSSL
 use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
 use std::{
     any, cmp, error::Error, future::Future, io, pin::Pin, task::Context, task::Poll,
 };
 
-use ntex_bytes::{BufMut, BytesMut, PoolRef};
+use ntex_bytes::{Buf, BufMut, BytesMut, PoolRef};
 use ntex_io::{Base, Filter, FilterFactory, Io, ReadStatus, WriteStatus};
 use ntex_util::{future::poll_fn, ready, time, time::Millis};
 use tls_openssl::ssl::{self, SslStream};
@@ -28,6 +29,7 @@ pub struct PeerCertChain(pub Vec<X509>);
 pub struct SslFilter<F = Base> {
     inner: RefCell<SslStream<IoInner<F>>>,
     handshake: Cell<bool>,
+    handshake_time: Cell<Option<Duration>>,
 }
 
 struct IoInner<F> {
@@ -39,13 +41,19 @@ struct IoInner<F> {
 
 impl<F: Filter> io::Read for IoInner<F> {
     fn read(&mut self, dst: &mut [u8]) -> io::Result<usize> {
-        if let Some(ref mut buf) = self.read_buf {
+        if let Some(mut buf) = self.read_buf.take() {
             if buf.is_empty() {
-                buf.clear();
+                release_read_buf(self.pool, buf);
                 Err(io::Error::from(io::ErrorKind::WouldBlock))
             } else {
                 let len = cmp::min(buf.len(), dst.len());
-                dst[..len].copy_from_slice(&buf.split_to(len));
+                dst[..len].copy_from_slice(&buf[..len]);
+                buf.advance(len);
+                if buf.is_empty() {
+                    release_read_buf(self.pool, buf);
+                } else {
+                    self.read_buf = Some(buf);
+                }
                 Ok(len)
             }
         } else {
@@ -56,11 +64,11 @@ impl<F: Filter> io::Read for IoInner<F> {
 
 impl<F: Filter> io::Write for IoInner<F> {
     fn write(&mut self, src: &[u8]) -> io::Result<usize> {
-        let mut buf = if let Some(mut buf) = self.inner.get_write_buf() {
-            buf.reserve(src.len());
+        // encrypted data is written to the pooled buffer in place
+        let mut buf = if let Some(buf) = self.inner.get_write_buf() {
             buf
         } else {
-            BytesMut::with_capacity_in(src.len(), self.pool)
+            self.pool.get_write_buf()
         };
         buf.extend_from_slice(src);
         self.inner.release_write_buf(buf)?;
@@ -86,6 +94,18 @@ impl<F: Filter> Filter for SslFilter<F> {
                     types::HttpProtocol::Http1
                 };
             Some(Box::new(proto))
+        } else if id == any::TypeId::of::<types::TlsInfo>() {
+            let inner = self.inner.borrow();
+            let ssl = inner.ssl();
+            Some(Box::new(types::TlsInfo {
+                version: ssl.version_str().to_string(),
+                cipher: ssl.current_cipher().map(|c| c.name().to_string()),
+                alpn: ssl.selected_alpn_protocol().map(|p| p.to_vec()),
+            }))
+        } else if id == any::TypeId::of::<types::HandshakeTime>() {
+            self.handshake_time
+                .get()
+                .map(|t| Box::new(types::HandshakeTime(t)) as Box<dyn any::Any>)
         } else if id == any::TypeId::of::<PeerCert>() {
             if let Some(cert) = self.inner.borrow().ssl().peer_certificate() {
                 Some(Box::new(PeerCert(cert)))
@@ -154,20 +174,24 @@ impl<F: Filter> Filter for SslFilter<F> {
 
     #[inline]
     fn get_read_buf(&self) -> Option<BytesMut> {
-        if let Some(buf) = self.inner.borrow_mut().get_mut().read_buf.take() {
+        let mut inner = self.inner.borrow_mut();
+        if let Some(buf) = inner.get_mut().read_buf.take() {
             if !buf.is_empty() {
                 return Some(buf);
             }
+            release_read_buf(inner.get_ref().pool, buf);
         }
         None
     }
 
     #[inline]
     fn get_write_buf(&self) -> Option<BytesMut> {
-        if let Some(buf) = self.inner.borrow_mut().get_mut().write_buf.take() {
+        let mut inner = self.inner.borrow_mut();
+        if let Some(buf) = inner.get_mut().write_buf.take() {
             if !buf.is_empty() {
                 return Some(buf);
             }
+            release_write_buf(inner.get_ref().pool, buf);
         }
         None
     }
@@ -244,12 +268,13 @@ impl<F: Filter> Filter for SslFilter<F> {
     fn release_write_buf(&self, mut buf: BytesMut) -> Result<(), io::Error> {
         loop {
             if buf.is_empty() {
+                release_write_buf(self.inner.borrow().get_ref().pool, buf);
                 return Ok(());
             }
             let ssl_result = self.inner.borrow_mut().ssl_write(&buf);
             match ssl_result {
                 Ok(v) => {
-                    buf.split_to(v);
+                    buf.advance(v);
                     continue;
                 }
                 Err(e) => {
@@ -311,6 +336,7 @@ impl<F: Filter> FilterFactory<F> for SslAcceptor {
     fn create(self, st: Io<F>) -> Self::Future {
         let timeout = self.timeout;
         let ctx_result = ssl::Ssl::new(self.acceptor.context());
+        let start = Instant::now();
 
         Box::pin(async move {
             time::timeout(timeout, async {
@@ -329,6 +355,7 @@ impl<F: Filter> FilterFactory<F> for SslAcceptor {
                     Ok::<_, Box<dyn Error>>(SslFilter {
                         inner: RefCell::new(ssl_stream),
                         handshake: Cell::new(true),
+                        handshake_time: Cell::new(None),
                     })
                 })?;
 
@@ -336,6 +363,7 @@ impl<F: Filter> FilterFactory<F> for SslAcceptor {
                     handle_result(st.filter().inner.borrow_mut().accept(), &st, cx)
                 })
                 .await?;
+                st.filter().handshake_time.set(Some(start.elapsed()));
 
                 Ok(st)
             })
@@ -366,6 +394,7 @@ impl<F: Filter> FilterFactory<F> for SslConnector {
     type Future = Pin<Box<dyn Future<Output = Result<Io<Self::Filter>, Self::Error>>>>;
 
     fn create(self, st: Io<F>) -> Self::Future {
+        let start = Instant::now();
         Box::pin(async move {
             let ssl = self.ssl;
             let pool = st.memory_pool();
@@ -382,11 +411,13 @@ impl<F: Filter> FilterFactory<F> for SslConnector {
                 Ok::<_, Box<dyn Error>>(SslFilter {
                     inner: RefCell::new(ssl_stream),
                     handshake: Cell::new(true),
+                    handshake_time: Cell::new(None),
                 })
             })?;
 
             poll_fn(|cx| handle_result(st.filter().inner.borrow_mut().connect(), &st, cx))
                 .await?;
+            st.filter().handshake_time.set(Some(start.elapsed()));
 
             Ok(st)
         })
@@ -420,6 +451,91 @@ fn handle_result<T, F>(
     }
 }
 
+/// Return consumed read buffer to the pool cache
+fn release_read_buf(pool: PoolRef, mut buf: BytesMut) {
+    // reclaim space of consumed data, buffer is empty so nothing is copied
+    buf.clear();
+    buf.reserve(pool.read_params_high());
+    pool.release_read_buf(buf);
+}
+
+/// Return consumed write buffer to the pool cache
+fn release_write_buf(pool: PoolRef, mut buf: BytesMut) {
+    buf.clear();
+    buf.reserve(pool.write_params_high());
+    pool.release_write_buf(buf);
+}
+
 fn map_to_ioerr<E: Into<Box<dyn Error + Send + Sync>>>(err: E) -> io::Error {
     io::Error::new(io::ErrorKind::Other, err)
 }
+
+#[cfg(test)]
+mod tests {
+    use ntex::{codec::BytesCodec, util::Bytes};
+    use ntex_bytes::PoolId;
+    use ntex_io::testing::IoTest;
+    use tls_openssl::ssl::{SslFiletype, SslMethod, SslVerifyMode};
+
+    use super::*;
+
+    async fn exchange(pool: PoolRef) {
+        let mut builder = ssl::SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+        builder
+            .set_private_key_file("./examples/key.pem", SslFiletype::PEM)
+            .unwrap();
+        builder
+            .set_certificate_chain_file("./examples/cert.pem")
+            .unwrap();
+        let acceptor = SslAcceptor::new(builder.build());
+
+        let mut builder = ssl::SslConnector::builder(SslMethod::tls()).unwrap();
+        builder.set_verify(SslVerifyMode::NONE);
+        let ssl = builder
+            .build()
+            .configure()
+            .unwrap()
+            .into_ssl("localhost")
+            .unwrap();
+        let connector = SslConnector::new(ssl);
+
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024 * 64);
+        server.remote_buffer_cap(1024 * 64);
+        let server = Io::with_memory_pool(server, pool);
+        let client = Io::with_memory_pool(client, pool);
+
+        let srv = ntex::rt::spawn(async move {
+            let io = acceptor.create(server).await.unwrap();
+            assert!(io.query::<types::HandshakeTime>().get().is_some());
+            let msg = io.recv(&BytesCodec).await.unwrap().unwrap();
+            io.send(msg.freeze(), &BytesCodec).await.unwrap();
+            io
+        });
+        let io = connector.create(client).await.unwrap();
+        assert!(io.query::<types::HandshakeTime>().get().is_some());
+        io.send(Bytes::from_static(b"hello"), &BytesCodec)
+            .await
+            .unwrap();
+        let msg = io.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(msg, Bytes::from_static(b"hello"));
+        drop(srv.await.unwrap());
+    }
+
+    #[ntex::test]
+    async fn pooled_buffers() {
+        let pool = PoolId::P9.pool_ref();
+        exchange(pool).await;
+        time::sleep(Millis(50)).await;
+
+        // tls buffers are allocated from io memory pool and
+        // returned to the pool cache
+        let stats = PoolId::P9.stats();
+        assert!(stats.allocated > stats.in_use);
+
+        exchange(pool).await;
+        time::sleep(Millis(50)).await;
+        // cached buffers are reused
+        assert_eq!(PoolId::P9.stats().allocated, stats.allocated);
+    }
+}
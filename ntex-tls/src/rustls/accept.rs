@@ -8,13 +8,15 @@ use ntex_service::{Service, ServiceFactory};
 use ntex_util::{future::Ready, time::Millis};
 
 use super::{TlsAcceptor, TlsFilter};
-use crate::{counter::Counter, counter::CounterGuard, MAX_SSL_ACCEPT_COUNTER};
+use crate::limits::{Admission, AdmissionGuard, Limiter};
+use crate::{HandshakeLimitError, HandshakeLimits, MAX_SSL_ACCEPT_COUNTER};
 
 /// Support `SSL` connections via rustls package
 ///
 /// `rust-tls` feature enables `RustlsAcceptor` type
 pub struct Acceptor<F> {
     inner: TlsAcceptor,
+    limits: HandshakeLimits,
     _t: PhantomData<F>,
 }
 
@@ -23,6 +25,7 @@ impl<F> Acceptor<F> {
     pub fn new(config: Arc<ServerConfig>) -> Self {
         Acceptor {
             inner: TlsAcceptor::new(config),
+            limits: HandshakeLimits::default(),
             _t: PhantomData,
         }
     }
@@ -34,6 +37,14 @@ impl<F> Acceptor<F> {
         self.inner.timeout(timeout.into());
         self
     }
+
+    /// Set handshake limits.
+    ///
+    /// By default handshakes are not limited.
+    pub fn limits(mut self, limits: HandshakeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
 }
 
 impl<F> From<ServerConfig> for Acceptor<F> {
@@ -46,6 +57,7 @@ impl<F> Clone for Acceptor<F> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            limits: self.limits,
             _t: PhantomData,
         }
     }
@@ -63,7 +75,7 @@ impl<F: Filter, C> ServiceFactory<Io<F>, C> for Acceptor<F> {
         MAX_SSL_ACCEPT_COUNTER.with(|conns| {
             Ready::Ok(AcceptorService {
                 acceptor: self.inner.clone(),
-                conns: conns.clone(),
+                limiter: self.limits.limiter(conns.clone()),
                 io: PhantomData,
             })
         })
@@ -74,7 +86,7 @@ impl<F: Filter, C> ServiceFactory<Io<F>, C> for Acceptor<F> {
 pub struct AcceptorService<F> {
     acceptor: TlsAcceptor,
     io: PhantomData<F>,
-    conns: Counter,
+    limiter: Limiter,
 }
 
 impl<F: Filter> Service<Io<F>> for AcceptorService<F> {
@@ -84,7 +96,7 @@ impl<F: Filter> Service<Io<F>> for AcceptorService<F> {
 
     #[inline]
     fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        if self.conns.available(cx) {
+        if self.limiter.poll_ready(cx) {
             Poll::Ready(Ok(()))
         } else {
             Poll::Pending
@@ -93,28 +105,45 @@ impl<F: Filter> Service<Io<F>> for AcceptorService<F> {
 
     #[inline]
     fn call(&self, req: Io<F>) -> Self::Future {
+        let (admission, err) = match self.limiter.admit(&req) {
+            Ok(admission) => (Some(admission), None),
+            Err(err) => (None, Some(err)),
+        };
         AcceptorServiceFut {
-            _guard: self.conns.get(),
+            admission,
+            err,
+            _guard: None,
             fut: self.acceptor.clone().create(req),
         }
     }
 }
 
-pin_project_lite::pin_project! {
-    pub struct AcceptorServiceFut<F>
-    where
-        F: Filter,
-    {
-        #[pin]
-        fut: <TlsAcceptor as FilterFactory<F>>::Future,
-        _guard: CounterGuard,
-    }
+pub struct AcceptorServiceFut<F: Filter> {
+    fut: <TlsAcceptor as FilterFactory<F>>::Future,
+    admission: Option<Admission>,
+    err: Option<HandshakeLimitError>,
+    _guard: Option<AdmissionGuard>,
 }
 
 impl<F: Filter> Future for AcceptorServiceFut<F> {
     type Output = Result<Io<TlsFilter<F>>, io::Error>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.project().fut.poll(cx)
+        let this = self.get_mut();
+        if let Some(err) = this.err.take() {
+            return Poll::Ready(Err(err.into()));
+        }
+
+        // wait for handshake budget
+        if let Some(ref mut admission) = this.admission {
+            match Pin::new(admission).poll(cx) {
+                Poll::Ready(guard) => {
+                    this._guard = Some(guard);
+                    this.admission = None;
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        this.fut.as_mut().poll(cx)
     }
 }
@@ -1,6 +1,7 @@
 //! An implementation of SSL streams for ntex backed by OpenSSL
 use std::io::{self, Read as IoRead, Write as IoWrite};
-use std::{any, cell::RefCell, cmp, sync::Arc, task::Context, task::Poll};
+use std::time::{Duration, Instant};
+use std::{any, cell::Cell, cell::RefCell, cmp, sync::Arc, task::Context, task::Poll};
 
 use ntex_bytes::{BufMut, BytesMut, PoolRef};
 use ntex_io::{Filter, Io, ReadStatus, WriteStatus};
@@ -14,6 +15,7 @@ use crate::types;
 pub struct TlsClientFilter<F> {
     inner: RefCell<IoInner<F>>,
     session: RefCell<ClientConnection>,
+    handshake_time: Cell<Option<Duration>>,
 }
 
 struct IoInner<F> {
@@ -41,6 +43,12 @@ impl<F: Filter> Filter for TlsClientFilter<F> {
                 types::HttpProtocol::Http1
             };
             Some(Box::new(proto))
+        } else if id == any::TypeId::of::<types::TlsInfo>() {
+            super::tls_info(&self.session.borrow())
+        } else if id == any::TypeId::of::<types::HandshakeTime>() {
+            self.handshake_time
+                .get()
+                .map(|t| Box::new(types::HandshakeTime(t)) as Box<dyn any::Any>)
         } else {
             self.inner.borrow().inner.query(id)
         }
@@ -231,6 +239,7 @@ impl<F: Filter> TlsClientFilter<F> {
         cfg: Arc<ClientConfig>,
         domain: ServerName,
     ) -> Result<Io<TlsFilter<F>>, io::Error> {
+        let start = Instant::now();
         let pool = io.memory_pool();
         let session = match ClientConnection::new(cfg, domain) {
             Ok(session) => session,
@@ -248,6 +257,7 @@ impl<F: Filter> TlsClientFilter<F> {
             Ok::<_, io::Error>(TlsFilter::new_client(TlsClientFilter {
                 inner: RefCell::new(inner),
                 session: RefCell::new(session),
+                handshake_time: Cell::new(None),
             }))
         })?;
 
@@ -282,7 +292,10 @@ impl<F: Filter> TlsClientFilter<F> {
                 .await?;
             }
             match result {
-                Ok(_) => return Ok(io),
+                Ok(_) => {
+                    filter.client().handshake_time.set(Some(start.elapsed()));
+                    return Ok(io);
+                }
                 Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
                     poll_fn(|cx| {
                         let read_ready = if wants_read {
@@ -6,7 +6,9 @@ use std::{any, future::Future, io, pin::Pin, task::Context, task::Poll};
 use ntex_bytes::BytesMut;
 use ntex_io::{Base, Filter, FilterFactory, Io, ReadStatus, WriteStatus};
 use ntex_util::time::Millis;
-use tls_rust::{ClientConfig, ServerConfig, ServerName};
+use tls_rust::{ClientConfig, CommonState, ServerConfig, ServerName};
+
+use crate::types;
 
 mod accept;
 mod client;
@@ -16,6 +18,22 @@ pub use accept::{Acceptor, AcceptorService};
 use self::client::TlsClientFilter;
 use self::server::TlsServerFilter;
 
+/// Negotiated session parameters, available after handshake
+fn tls_info(session: &CommonState) -> Option<Box<dyn any::Any>> {
+    let version = session.protocol_version()?;
+    Some(Box::new(types::TlsInfo {
+        version: version
+            .as_str()
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("{:?}", version)),
+        cipher: session
+            .negotiated_cipher_suite()
+            .and_then(|s| s.suite().as_str())
+            .map(|s| s.to_string()),
+        alpn: session.alpn_protocol().map(|p| p.to_vec()),
+    }))
+}
+
 /// An implementation of SSL streams
 pub struct TlsFilter<F = Base> {
     inner: InnerTlsFilter<F>,
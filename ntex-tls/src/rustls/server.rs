@@ -1,7 +1,7 @@
 //! An implementation of SSL streams for ntex backed by OpenSSL
 use std::io::{self, Read as IoRead, Write as IoWrite};
-use std::sync::Arc;
-use std::{any, cell::RefCell, cmp, task::Context, task::Poll};
+use std::{any, cell::Cell, cell::RefCell, cmp, task::Context, task::Poll};
+use std::{sync::Arc, time::Duration, time::Instant};
 
 use ntex_bytes::{BufMut, BytesMut, PoolRef};
 use ntex_io::{Filter, Io, ReadStatus, WriteStatus};
@@ -14,6 +14,7 @@ use crate::{rustls::TlsFilter, types};
 pub struct TlsServerFilter<F> {
     inner: RefCell<IoInner<F>>,
     session: RefCell<ServerConnection>,
+    handshake_time: Cell<Option<Duration>>,
 }
 
 struct IoInner<F> {
@@ -41,6 +42,12 @@ impl<F: Filter> Filter for TlsServerFilter<F> {
                 types::HttpProtocol::Http1
             };
             Some(Box::new(proto))
+        } else if id == any::TypeId::of::<types::TlsInfo>() {
+            super::tls_info(&self.session.borrow())
+        } else if id == any::TypeId::of::<types::HandshakeTime>() {
+            self.handshake_time
+                .get()
+                .map(|t| Box::new(types::HandshakeTime(t)) as Box<dyn any::Any>)
         } else {
             self.inner.borrow().inner.query(id)
         }
@@ -230,6 +237,7 @@ impl<F: Filter> TlsServerFilter<F> {
         cfg: Arc<ServerConfig>,
         timeout: Millis,
     ) -> Result<Io<TlsFilter<F>>, io::Error> {
+        let start = Instant::now();
         time::timeout(timeout, async {
             let pool = io.memory_pool();
             let session = match ServerConnection::new(cfg) {
@@ -248,6 +256,7 @@ impl<F: Filter> TlsServerFilter<F> {
                 Ok::<_, io::Error>(TlsFilter::new_server(TlsServerFilter {
                     inner: RefCell::new(inner),
                     session: RefCell::new(session),
+                    handshake_time: Cell::new(None),
                 }))
             })?;
 
@@ -283,7 +292,10 @@ impl<F: Filter> TlsServerFilter<F> {
                     .await?;
                 }
                 match result {
-                    Ok(_) => return Ok(io),
+                    Ok(_) => {
+                        filter.server().handshake_time.set(Some(start.elapsed()));
+                        return Ok(io);
+                    }
                     Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
                         poll_fn(|cx| {
                             let read_ready = if wants_read {
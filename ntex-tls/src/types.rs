@@ -1,6 +1,23 @@
+use std::time::Duration;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum HttpProtocol {
     Http1,
     Http2,
     Unknown,
 }
+
+/// Negotiated tls session parameters
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlsInfo {
+    /// Protocol version
+    pub version: String,
+    /// Cipher suite name
+    pub cipher: Option<String>,
+    /// Selected application protocol
+    pub alpn: Option<Vec<u8>>,
+}
+
+/// Duration of tls handshake
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HandshakeTime(pub Duration);
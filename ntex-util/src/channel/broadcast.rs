@@ -0,0 +1,467 @@
+//! A bounded multi-producer, multi-consumer broadcast channel.
+//!
+//! Each sent message is delivered to all receivers. Channel keeps last
+//! `capacity` messages, receivers that fall behind are handled according
+//! to the [`LagPolicy`].
+use std::{
+    collections::VecDeque, fmt, future::Future, pin::Pin, task::Context, task::Poll,
+};
+
+use futures_core::Stream;
+use slab::Slab;
+
+use super::cell::Cell;
+use crate::{future::poll_fn, task::LocalWaker};
+
+/// Slow receiver handling policy
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LagPolicy {
+    /// Receiver skips missed messages and gets `RecvError::Lagged` error
+    /// with number of skipped messages.
+    Skip,
+    /// Receiver is disconnected and gets `RecvError::Disconnected` error.
+    Disconnect,
+}
+
+/// Creates a bounded broadcast channel.
+///
+/// Panics if capacity is 0.
+///
+/// ```rust
+/// use ntex_util::channel::broadcast::{channel, LagPolicy, RecvError};
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let (tx, rx) = channel(2, LagPolicy::Skip);
+///     let rx2 = tx.subscribe();
+///
+///     tx.send(1).unwrap();
+///     assert_eq!(rx.recv().await, Ok(1));
+///
+///     tx.send(2).unwrap();
+///     tx.send(3).unwrap();
+///     assert_eq!(rx2.recv().await, Err(RecvError::Lagged(1)));
+///     assert_eq!(rx2.recv().await, Ok(2));
+/// }
+/// ```
+pub fn channel<T: Clone>(capacity: usize, policy: LagPolicy) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "Capacity must be greater than 0");
+
+    let shared = Cell::new(Shared {
+        capacity,
+        policy,
+        buffer: VecDeque::with_capacity(capacity),
+        head: 0,
+        receivers: Slab::new(),
+        senders: 1,
+        closed: false,
+    });
+    let receiver = Receiver::new(shared.clone());
+    (Sender { shared }, receiver)
+}
+
+struct Shared<T> {
+    capacity: usize,
+    policy: LagPolicy,
+    buffer: VecDeque<T>,
+    /// Position of first buffered message
+    head: u64,
+    receivers: Slab<Slot>,
+    senders: usize,
+    closed: bool,
+}
+
+impl<T> Shared<T> {
+    fn tail(&self) -> u64 {
+        self.head + self.buffer.len() as u64
+    }
+}
+
+struct Slot {
+    pos: u64,
+    state: SlotState,
+    waker: LocalWaker,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum SlotState {
+    Active,
+    Disconnected,
+    Closed,
+}
+
+/// The transmission end of a broadcast channel.
+pub struct Sender<T> {
+    shared: Cell<Shared<T>>,
+}
+
+impl<T> Unpin for Sender<T> {}
+
+impl<T> Sender<T> {
+    /// Sends message to all active receivers.
+    ///
+    /// Returns number of receivers that would receive message.
+    /// Fails if channel is closed or there are no active receivers.
+    pub fn send(&self, item: T) -> Result<usize, SendError<T>> {
+        let shared = self.shared.get_mut();
+        if shared.closed {
+            return Err(SendError(item));
+        }
+
+        if shared.buffer.len() == shared.capacity {
+            shared.buffer.pop_front();
+            shared.head += 1;
+
+            if shared.policy == LagPolicy::Disconnect {
+                let head = shared.head;
+                for (_, slot) in shared.receivers.iter_mut() {
+                    if slot.state == SlotState::Active && slot.pos < head {
+                        slot.state = SlotState::Disconnected;
+                        slot.waker.wake();
+                    }
+                }
+            }
+        }
+
+        let mut count = 0;
+        for (_, slot) in shared.receivers.iter() {
+            if slot.state == SlotState::Active {
+                slot.waker.wake();
+                count += 1;
+            }
+        }
+        if count == 0 {
+            return Err(SendError(item));
+        }
+        shared.buffer.push_back(item);
+        Ok(count)
+    }
+
+    /// Creates new receiver.
+    ///
+    /// Receiver gets messages sent after this call.
+    pub fn subscribe(&self) -> Receiver<T> {
+        Receiver::new(self.shared.clone())
+    }
+
+    /// Returns number of active receivers.
+    pub fn receivers(&self) -> usize {
+        self.shared
+            .get_ref()
+            .receivers
+            .iter()
+            .filter(|(_, slot)| slot.state == SlotState::Active)
+            .count()
+    }
+
+    /// Closes the channel.
+    ///
+    /// Receivers could drain buffered messages.
+    pub fn close(&self) {
+        let shared = self.shared.get_mut();
+        shared.closed = true;
+        for (_, slot) in shared.receivers.iter() {
+            slot.waker.wake();
+        }
+    }
+
+    /// Returns whether this channel is closed.
+    pub fn is_closed(&self) -> bool {
+        self.shared.get_ref().closed
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.get_mut().senders += 1;
+        Sender {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let shared = self.shared.get_mut();
+        shared.senders -= 1;
+        if shared.senders == 0 {
+            self.close();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("broadcast::Sender")
+            .field("receivers", &self.receivers())
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+/// The receiving end of a broadcast channel.
+pub struct Receiver<T> {
+    token: usize,
+    shared: Cell<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+    fn new(shared: Cell<Shared<T>>) -> Self {
+        let inner = shared.get_mut();
+        let token = inner.receivers.insert(Slot {
+            pos: inner.tail(),
+            state: SlotState::Active,
+            waker: LocalWaker::new(),
+        });
+        Receiver { token, shared }
+    }
+
+    /// Returns number of messages available for this receiver.
+    pub fn len(&self) -> usize {
+        let shared = self.shared.get_ref();
+        let slot = &shared.receivers[self.token];
+        (shared.tail() - slot.pos.max(shared.head)) as usize
+    }
+
+    /// Returns true if there are no available messages.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns whether this channel is closed for receiver.
+    pub fn is_closed(&self) -> bool {
+        let shared = self.shared.get_ref();
+        shared.closed || shared.receivers[self.token].state != SlotState::Active
+    }
+}
+
+impl<T: Clone> Receiver<T> {
+    /// Receives next message.
+    pub fn recv(&self) -> impl Future<Output = Result<T, RecvError>> + '_ {
+        poll_fn(move |cx| self.poll_recv(cx))
+    }
+
+    /// Attempt to pull out the next message of this receiver, registering
+    /// the current task for wakeup if the message is not yet available.
+    pub fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Result<T, RecvError>> {
+        let shared = self.shared.get_mut();
+        let head = shared.head;
+        let tail = shared.tail();
+        let slot = &mut shared.receivers[self.token];
+
+        match slot.state {
+            SlotState::Active => (),
+            SlotState::Disconnected => {
+                slot.state = SlotState::Closed;
+                return Poll::Ready(Err(RecvError::Disconnected));
+            }
+            SlotState::Closed => return Poll::Ready(Err(RecvError::Closed)),
+        }
+
+        if slot.pos < head {
+            let lag = head - slot.pos;
+            slot.pos = head;
+            Poll::Ready(Err(RecvError::Lagged(lag)))
+        } else if slot.pos < tail {
+            let item = shared.buffer[(slot.pos - head) as usize].clone();
+            slot.pos += 1;
+            Poll::Ready(Ok(item))
+        } else if shared.closed {
+            slot.state = SlotState::Closed;
+            Poll::Ready(Err(RecvError::Closed))
+        } else {
+            slot.waker.register(cx.waker());
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        let inner = self.shared.get_mut();
+        let slot = &inner.receivers[self.token];
+        let slot = Slot {
+            pos: slot.pos,
+            state: slot.state,
+            waker: LocalWaker::new(),
+        };
+        let token = inner.receivers.insert(slot);
+        Receiver {
+            token,
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Unpin for Receiver<T> {}
+
+impl<T: Clone> Stream for Receiver<T> {
+    type Item = Result<T, RecvError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.poll_recv(cx) {
+            Poll::Ready(Err(RecvError::Closed)) => Poll::Ready(None),
+            Poll::Ready(res) => Poll::Ready(Some(res)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.get_mut().receivers.remove(self.token);
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("broadcast::Receiver")
+            .field("len", &self.len())
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+/// Error type for sending, used when channel is closed or
+/// there are no active receivers
+pub struct SendError<T>(T);
+
+impl<T> std::error::Error for SendError<T> {}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_tuple("SendError").field(&"...").finish()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "send failed because there are no receivers")
+    }
+}
+
+impl<T> SendError<T> {
+    /// Returns the message that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+/// Error type for receiving
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RecvError {
+    /// Receiver fell behind, number of skipped messages
+    Lagged(u64),
+    /// Receiver fell behind and is disconnected
+    Disconnected,
+    /// Channel is closed and all messages are received
+    Closed,
+}
+
+impl std::error::Error for RecvError {}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Lagged(n) => write!(fmt, "receiver lagged by {} messages", n),
+            RecvError::Disconnected => write!(fmt, "receiver is disconnected"),
+            RecvError::Closed => write!(fmt, "channel is closed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::future::{lazy, next};
+
+    #[ntex_macros::rt_test2]
+    async fn test_broadcast() {
+        let (tx, rx) = channel(4, LagPolicy::Skip);
+        let rx2 = rx.clone();
+        assert!(format!("{:?}", tx).contains("broadcast::Sender"));
+        assert!(format!("{:?}", rx).contains("broadcast::Receiver"));
+        assert_eq!(tx.receivers(), 2);
+
+        assert_eq!(tx.send("test").unwrap(), 2);
+        assert_eq!(rx.len(), 1);
+        assert_eq!(rx.recv().await, Ok("test"));
+        assert_eq!(rx2.recv().await, Ok("test"));
+        assert!(rx.is_empty());
+        assert_eq!(lazy(|cx| rx.poll_recv(cx)).await, Poll::Pending);
+
+        // late subscriber gets only new messages
+        tx.send("test2").unwrap();
+        let rx3 = tx.subscribe();
+        tx.send("test3").unwrap();
+        assert_eq!(rx3.recv().await, Ok("test3"));
+        assert_eq!(rx.recv().await, Ok("test2"));
+
+        drop(rx);
+        drop(rx2);
+        drop(rx3);
+        assert_eq!(tx.receivers(), 0);
+        let err = tx.send("test").err().unwrap();
+        assert!(format!("{:?}", err).contains("SendError"));
+        assert!(format!("{}", err).contains("no receivers"));
+        assert_eq!(err.into_inner(), "test");
+    }
+
+    #[ntex_macros::rt_test2]
+    async fn test_lag_skip() {
+        let (tx, rx) = channel(2, LagPolicy::Skip);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.len(), 2);
+        assert_eq!(rx.recv().await, Err(RecvError::Lagged(3)));
+        assert_eq!(rx.recv().await, Ok(3));
+        assert_eq!(rx.recv().await, Ok(4));
+        assert!(!rx.is_closed());
+    }
+
+    #[ntex_macros::rt_test2]
+    async fn test_lag_disconnect() {
+        let (tx, rx) = channel(2, LagPolicy::Disconnect);
+        let mut rx2 = tx.subscribe();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(rx2.recv().await, Ok(1));
+        assert_eq!(rx2.recv().await, Ok(2));
+
+        // rx is too slow
+        assert_eq!(tx.send(3).unwrap(), 1);
+        assert_eq!(tx.receivers(), 1);
+        assert!(rx.is_closed());
+        assert_eq!(rx.recv().await, Err(RecvError::Disconnected));
+        assert_eq!(rx.recv().await, Err(RecvError::Closed));
+        assert_eq!(next(&mut rx2).await, Some(Ok(3)));
+
+        drop(rx2);
+        assert!(tx.send(4).is_err());
+    }
+
+    #[ntex_macros::rt_test2]
+    async fn test_close() {
+        let (tx, mut rx) = channel(2, LagPolicy::Skip);
+        let tx2 = tx.clone();
+        tx.send(1).unwrap();
+        drop(tx);
+        assert!(!tx2.is_closed());
+        drop(tx2);
+
+        assert!(rx.is_closed());
+        assert_eq!(next(&mut rx).await, Some(Ok(1)));
+        assert_eq!(next(&mut rx).await, None);
+
+        let (tx, rx) = channel::<()>(2, LagPolicy::Skip);
+        tx.close();
+        assert!(tx.is_closed());
+        assert!(tx.send(()).is_err());
+        assert_eq!(rx.recv().await, Err(RecvError::Closed));
+        assert_eq!(
+            format!("{}", RecvError::Lagged(2)),
+            "receiver lagged by 2 messages"
+        );
+    }
+}
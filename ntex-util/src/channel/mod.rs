@@ -1,5 +1,6 @@
 //! Communication primitives
 
+pub mod broadcast;
 mod cell;
 pub mod condition;
 pub mod mpsc;
@@ -0,0 +1,545 @@
+use std::cell::{Cell, RefCell};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use std::{cmp, fmt, future::Future, marker, net::SocketAddr, pin::Pin, rc::Rc};
+
+use crate::io::Io;
+use crate::rt::tcp_connect_in;
+use crate::service::{Service, ServiceFactory};
+use crate::time::{sleep, timeout, Millis};
+use crate::util::{PoolId, PoolRef, Ready};
+
+use super::service::Bind;
+use super::{Address, Connect, ConnectError};
+
+/// Smoothing factor of connect latency average
+const EWMA_ALPHA: f64 = 0.3;
+
+/// Load balancing strategy
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Balance {
+    /// Endpoints are selected in turn
+    RoundRobin,
+    /// Endpoint with least number of active connections is selected
+    LeastConnections,
+    /// Endpoint with lowest connect latency is selected.
+    ///
+    /// Latency is tracked as exponentially weighted moving average and
+    /// is multiplied by number of active connections of the endpoint.
+    Ewma,
+}
+
+/// Dynamic set of upstream endpoints
+///
+/// Endpoints could be cloned, all clones share same set. Set could be
+/// updated at any time, for example by service discovery watcher, new
+/// connections use updated set.
+#[derive(Clone)]
+pub struct Endpoints(Rc<RefCell<Inner>>);
+
+struct Inner {
+    endpoints: Vec<Rc<Endpoint>>,
+    next: usize,
+    health_check: bool,
+}
+
+struct Endpoint {
+    addr: SocketAddr,
+    active: Cell<usize>,
+    failures: Cell<usize>,
+    ejected: Cell<Option<Instant>>,
+    latency: Cell<Option<f64>>,
+}
+
+impl Endpoint {
+    fn new(addr: SocketAddr) -> Rc<Self> {
+        Rc::new(Endpoint {
+            addr,
+            active: Cell::new(0),
+            failures: Cell::new(0),
+            ejected: Cell::new(None),
+            latency: Cell::new(None),
+        })
+    }
+
+    fn is_ejected(&self, now: Instant) -> bool {
+        matches!(self.ejected.get(), Some(until) if until > now)
+    }
+
+    fn load(&self) -> f64 {
+        self.latency.get().unwrap_or(0.0) * (self.active.get() + 1) as f64
+    }
+
+    fn success(&self, latency: Duration) {
+        let latency = latency.as_secs_f64();
+        self.failures.set(0);
+        self.ejected.set(None);
+        self.latency.set(Some(match self.latency.get() {
+            Some(avg) => avg + (latency - avg) * EWMA_ALPHA,
+            None => latency,
+        }));
+    }
+
+    fn failure(&self, max_failures: usize, ejection: Millis) {
+        let failures = self.failures.get() + 1;
+        self.failures.set(failures);
+        if failures >= max_failures {
+            trace!("Balancer: eject endpoint {:?}", self.addr);
+            self.ejected
+                .set(Some(Instant::now() + Duration::from(ejection)));
+        }
+    }
+}
+
+impl Endpoints {
+    /// Create endpoints set
+    pub fn new<I: IntoIterator<Item = SocketAddr>>(addrs: I) -> Self {
+        let endpoints = Endpoints(Rc::new(RefCell::new(Inner {
+            endpoints: Vec::new(),
+            next: 0,
+            health_check: false,
+        })));
+        endpoints.set(addrs);
+        endpoints
+    }
+
+    /// Replace endpoints.
+    ///
+    /// Statistics of endpoints that remain in set are preserved.
+    pub fn set<I: IntoIterator<Item = SocketAddr>>(&self, addrs: I) {
+        let mut inner = self.0.borrow_mut();
+        let mut endpoints: Vec<Rc<Endpoint>> = Vec::new();
+        for addr in addrs {
+            if endpoints.iter().any(|ep| ep.addr == addr) {
+                continue;
+            }
+            if let Some(ep) = inner.endpoints.iter().find(|ep| ep.addr == addr) {
+                endpoints.push(ep.clone());
+            } else {
+                endpoints.push(Endpoint::new(addr));
+            }
+        }
+        inner.endpoints = endpoints;
+    }
+
+    /// Add endpoint, returns false if endpoint is in set already.
+    pub fn add(&self, addr: SocketAddr) -> bool {
+        let mut inner = self.0.borrow_mut();
+        if inner.endpoints.iter().any(|ep| ep.addr == addr) {
+            false
+        } else {
+            inner.endpoints.push(Endpoint::new(addr));
+            true
+        }
+    }
+
+    /// Remove endpoint, returns false if endpoint is not in set.
+    pub fn remove(&self, addr: &SocketAddr) -> bool {
+        let mut inner = self.0.borrow_mut();
+        let len = inner.endpoints.len();
+        inner.endpoints.retain(|ep| ep.addr != *addr);
+        len != inner.endpoints.len()
+    }
+
+    /// Addresses of all endpoints
+    pub fn addrs(&self) -> Vec<SocketAddr> {
+        self.0.borrow().endpoints.iter().map(|ep| ep.addr).collect()
+    }
+
+    /// Addresses of endpoints that are not ejected
+    pub fn healthy(&self) -> Vec<SocketAddr> {
+        let now = Instant::now();
+        self.0
+            .borrow()
+            .endpoints
+            .iter()
+            .filter(|ep| !ep.is_ejected(now))
+            .map(|ep| ep.addr)
+            .collect()
+    }
+
+    /// Number of endpoints
+    pub fn len(&self) -> usize {
+        self.0.borrow().endpoints.len()
+    }
+
+    /// Check if set is empty
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().endpoints.is_empty()
+    }
+
+    /// Select endpoint for new connection
+    ///
+    /// Ejected endpoints are used only if all endpoints are ejected.
+    fn select(&self, strategy: Balance, tried: &[SocketAddr]) -> Option<Rc<Endpoint>> {
+        let mut inner = self.0.borrow_mut();
+        let len = inner.endpoints.len();
+        if len == 0 {
+            return None;
+        }
+        let start = inner.next;
+        inner.next = inner.next.wrapping_add(1);
+
+        let now = Instant::now();
+        let candidates: Vec<_> = (0..len)
+            .map(|idx| &inner.endpoints[(start + idx) % len])
+            .filter(|ep| !tried.contains(&ep.addr))
+            .collect();
+        let healthy: Vec<_> = candidates
+            .iter()
+            .filter(|ep| !ep.is_ejected(now))
+            .copied()
+            .collect();
+        let list = if healthy.is_empty() {
+            candidates
+        } else {
+            healthy
+        };
+
+        let ep = match strategy {
+            Balance::RoundRobin => list.first(),
+            Balance::LeastConnections => list.iter().min_by_key(|ep| ep.active.get()),
+            Balance::Ewma => list.iter().min_by(|a, b| {
+                a.load()
+                    .partial_cmp(&b.load())
+                    .unwrap_or(cmp::Ordering::Equal)
+            }),
+        };
+        ep.map(|ep| (*ep).clone())
+    }
+}
+
+impl fmt::Debug for Endpoints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Endpoints")
+            .field("addrs", &self.addrs())
+            .finish()
+    }
+}
+
+/// Load balancing connector
+///
+/// Connector ignores address of the connect request and connects to one
+/// of the upstream endpoints. If connection to selected endpoint fails,
+/// other endpoints are tried. Endpoints that fail `max_failures` times
+/// in a row are ejected for configured period of time.
+///
+/// ```rust
+/// use ntex::connect::{Balance, Balancer, Endpoints};
+/// use ntex::time::Millis;
+///
+/// let endpoints = Endpoints::new(vec!["127.0.0.1:8080".parse().unwrap()]);
+/// let balancer = Balancer::<String>::new(endpoints.clone())
+///     .strategy(Balance::LeastConnections)
+///     .health_check(Millis::from_secs(5));
+///
+/// // update endpoints
+/// endpoints.add("127.0.0.1:8081".parse().unwrap());
+/// ```
+pub struct Balancer<T> {
+    endpoints: Endpoints,
+    strategy: Balance,
+    max_failures: usize,
+    ejection: Millis,
+    health_check: Option<Millis>,
+    pool: PoolRef,
+    _t: marker::PhantomData<T>,
+}
+
+impl<T> Balancer<T> {
+    /// Construct new balancer for endpoints.
+    ///
+    /// By default round-robin strategy is used.
+    pub fn new(endpoints: Endpoints) -> Self {
+        Balancer {
+            endpoints,
+            strategy: Balance::RoundRobin,
+            max_failures: 3,
+            ejection: Millis::from_secs(30),
+            health_check: None,
+            pool: PoolId::P0.pool_ref(),
+            _t: marker::PhantomData,
+        }
+    }
+
+    /// Set load balancing strategy.
+    pub fn strategy(mut self, strategy: Balance) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Set number of failed connects in a row after which endpoint get ejected.
+    ///
+    /// By default it is set to 3.
+    pub fn max_failures(mut self, num: usize) -> Self {
+        self.max_failures = cmp::max(num, 1);
+        self
+    }
+
+    /// Set ejection period.
+    ///
+    /// By default it is set to 30 seconds.
+    pub fn ejection_time<U: Into<Millis>>(mut self, time: U) -> Self {
+        self.ejection = time.into();
+        self
+    }
+
+    /// Enable active health checks.
+    ///
+    /// Endpoints are checked by tcp connect with specified interval,
+    /// failed endpoints get ejected and recovered endpoints get restored.
+    /// Checks run in the thread of the first connect, only one health
+    /// check task runs for endpoints set.
+    pub fn health_check<U: Into<Millis>>(mut self, interval: U) -> Self {
+        self.health_check = Some(interval.into());
+        self
+    }
+
+    /// Set memory pool.
+    ///
+    /// Use specified memory pool for memory allocations. By default P0
+    /// memory pool is used.
+    pub fn memory_pool(mut self, id: PoolId) -> Self {
+        self.pool = id.pool_ref();
+        self
+    }
+
+    /// Get reference to endpoints
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    fn start_health_check(&self) {
+        let interval = if let Some(interval) = self.health_check {
+            interval
+        } else {
+            return;
+        };
+        let mut inner = self.endpoints.0.borrow_mut();
+        if inner.health_check {
+            return;
+        }
+        inner.health_check = true;
+
+        let endpoints = Rc::downgrade(&self.endpoints.0);
+        let ejection = self.ejection;
+        let pool = self.pool;
+        crate::rt::spawn(async move {
+            loop {
+                sleep(interval).await;
+                let list = if let Some(inner) = endpoints.upgrade() {
+                    let list = inner.borrow().endpoints.clone();
+                    list
+                } else {
+                    return;
+                };
+
+                for ep in list {
+                    match timeout(interval, tcp_connect_in(ep.addr, pool)).await {
+                        Ok(Ok(_)) => {
+                            ep.failures.set(0);
+                            ep.ejected.set(None);
+                        }
+                        _ => {
+                            trace!("Balancer: health check failed for {:?}", ep.addr);
+                            ep.failure(1, ejection);
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl<T> Clone for Balancer<T> {
+    fn clone(&self) -> Self {
+        Balancer {
+            endpoints: self.endpoints.clone(),
+            strategy: self.strategy,
+            max_failures: self.max_failures,
+            ejection: self.ejection,
+            health_check: self.health_check,
+            pool: self.pool,
+            _t: marker::PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Balancer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Balancer")
+            .field("endpoints", &self.endpoints)
+            .field("strategy", &self.strategy)
+            .field("max_failures", &self.max_failures)
+            .field("ejection", &self.ejection)
+            .field("health_check", &self.health_check)
+            .finish()
+    }
+}
+
+impl<T: Address> Balancer<T> {
+    /// Connect to one of the endpoints
+    pub fn connect<U>(&self, message: U) -> impl Future<Output = Result<Io, ConnectError>>
+    where
+        Connect<T>: From<U>,
+    {
+        self.call(message.into())
+    }
+}
+
+impl<T: Address, C> ServiceFactory<Connect<T>, C> for Balancer<T> {
+    type Response = Io;
+    type Error = ConnectError;
+    type Service = Balancer<T>;
+    type InitError = ();
+    type Future = Ready<Self::Service, Self::InitError>;
+
+    #[inline]
+    fn new_service(&self, _: C) -> Self::Future {
+        Ready::Ok(self.clone())
+    }
+}
+
+impl<T: Address> Service<Connect<T>> for Balancer<T> {
+    type Response = Io;
+    type Error = ConnectError;
+    type Future = Pin<Box<dyn Future<Output = Result<Io, ConnectError>>>>;
+
+    #[inline]
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: Connect<T>) -> Self::Future {
+        self.start_health_check();
+
+        let slf = self.clone();
+        let bind = Bind {
+            local_addr: req.local_addr,
+            interface: req.interface,
+            pool: self.pool,
+        };
+
+        Box::pin(async move {
+            let mut tried = Vec::new();
+            let mut error = None;
+
+            while let Some(ep) = slf.endpoints.select(slf.strategy, &tried) {
+                tried.push(ep.addr);
+
+                let start = Instant::now();
+                match bind.connect(ep.addr).await {
+                    Ok(io) => {
+                        trace!("Balancer: connected to {:?}", ep.addr);
+                        ep.success(start.elapsed());
+                        ep.active.set(ep.active.get() + 1);
+
+                        let on_disconnect = io.on_disconnect();
+                        crate::rt::spawn(async move {
+                            on_disconnect.await;
+                            ep.active.set(ep.active.get() - 1);
+                        });
+                        return Ok(io);
+                    }
+                    Err(err) => {
+                        trace!(
+                            "Balancer: failed to connect to {:?} err: {:?}",
+                            ep.addr,
+                            err
+                        );
+                        ep.failure(slf.max_failures, slf.ejection);
+                        error = Some(err);
+                    }
+                }
+            }
+            Err(error
+                .map(ConnectError::Io)
+                .unwrap_or(ConnectError::NoRecords))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{io::types::PeerAddr, service::fn_service};
+
+    fn dead_addr() -> SocketAddr {
+        let lst = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        lst.local_addr().unwrap()
+    }
+
+    fn peer(io: &Io) -> SocketAddr {
+        io.query::<PeerAddr>().get().unwrap().0
+    }
+
+    #[crate::rt_test]
+    async fn test_balancer() {
+        let srv1 = crate::server::test_server(|| fn_service(|_| async { Ok::<_, ()>(()) }));
+        let srv2 = crate::server::test_server(|| fn_service(|_| async { Ok::<_, ()>(()) }));
+        let dead = dead_addr();
+
+        let endpoints = Endpoints::new(vec![srv1.addr(), srv2.addr()]);
+        let balancer = Balancer::<&'static str>::new(endpoints.clone());
+        assert!(format!("{:?}", balancer).contains("RoundRobin"));
+
+        // round-robin
+        let io1 = balancer.connect("").await.unwrap();
+        let io2 = balancer.connect("").await.unwrap();
+        assert_ne!(peer(&io1), peer(&io2));
+
+        // least connections
+        let balancer = balancer.strategy(Balance::LeastConnections);
+        let addr1 = peer(&io1);
+        drop(io1);
+        crate::time::sleep(Millis(50)).await;
+        let io3 = balancer.connect("").await.unwrap();
+        assert_eq!(peer(&io3), addr1);
+        drop((io2, io3));
+
+        // failed endpoint get ejected
+        endpoints.set(vec![dead, srv1.addr()]);
+        let balancer = balancer.strategy(Balance::RoundRobin).max_failures(1);
+        for _ in 0..3 {
+            let io = balancer.connect("").await.unwrap();
+            assert_eq!(peer(&io), srv1.addr());
+        }
+        assert_eq!(endpoints.healthy(), vec![srv1.addr()]);
+        assert_eq!(endpoints.addrs(), vec![dead, srv1.addr()]);
+
+        let balancer = balancer.strategy(Balance::Ewma);
+        let io = balancer.connect("").await.unwrap();
+        assert_eq!(peer(&io), srv1.addr());
+
+        // endpoints update
+        assert!(endpoints.remove(&srv1.addr()));
+        assert!(!endpoints.remove(&srv1.addr()));
+        assert!(balancer.connect("").await.is_err());
+        assert!(endpoints.add(srv2.addr()));
+        assert!(!endpoints.add(srv2.addr()));
+        let io = balancer.connect("").await.unwrap();
+        assert_eq!(peer(&io), srv2.addr());
+        assert_eq!(endpoints.len(), 2);
+
+        endpoints.set(vec![]);
+        assert!(endpoints.is_empty());
+        assert!(matches!(
+            balancer.connect("").await,
+            Err(ConnectError::NoRecords)
+        ));
+    }
+
+    #[crate::rt_test]
+    async fn test_health_check() {
+        let srv = crate::server::test_server(|| fn_service(|_| async { Ok::<_, ()>(()) }));
+        let dead = dead_addr();
+
+        let endpoints = Endpoints::new(vec![srv.addr(), dead]);
+        let balancer =
+            Balancer::<&'static str>::new(endpoints.clone()).health_check(Millis(50));
+        let _ = balancer.connect("").await.unwrap();
+
+        crate::time::sleep(Millis(200)).await;
+        assert_eq!(endpoints.healthy(), vec![srv.addr()]);
+    }
+}
@@ -0,0 +1,294 @@
+use std::{cell::RefCell, fmt, net::SocketAddr, rc::Rc, time::Duration, time::Instant};
+
+use crate::time::{now, Seconds};
+use crate::util::HashMap;
+
+use super::ConnectError;
+
+/// In-process dns cache
+///
+/// Cache stores results of name resolution, successful lookups are
+/// stored for `ttl` period, failed lookups are stored for `negative_ttl` period.
+/// System resolver does not expose record's ttl, so configured values are used
+/// for all records.
+///
+/// Cache could be cloned, all clones share same storage.
+///
+/// ```rust
+/// use ntex::connect::{Connector, DnsCache};
+/// use ntex::time::Seconds;
+///
+/// let cache = DnsCache::new()
+///     .ttl(Seconds(120))
+///     .negative_ttl(Seconds(5))
+///     .stale_while_revalidate(Seconds(30));
+///
+/// let connector = Connector::<String>::new().dns_cache(cache.clone());
+///
+/// // drop cached records for the host
+/// cache.invalidate("www.rust-lang.org");
+/// ```
+#[derive(Clone)]
+pub struct DnsCache {
+    ttl: Duration,
+    negative_ttl: Duration,
+    stale_ttl: Duration,
+    capacity: usize,
+    entries: Rc<RefCell<HashMap<String, Entry>>>,
+}
+
+struct Entry {
+    result: Result<Vec<SocketAddr>, ConnectError>,
+    expires: Instant,
+    refreshing: bool,
+}
+
+/// Result of cache lookup
+pub(super) enum Lookup {
+    /// Fresh record
+    Hit(Result<Vec<SocketAddr>, ConnectError>),
+    /// Expired record within stale period, caller must revalidate record
+    Stale(Vec<SocketAddr>),
+    /// Record is not available
+    Miss,
+}
+
+impl Default for DnsCache {
+    fn default() -> Self {
+        DnsCache::new()
+    }
+}
+
+impl fmt::Debug for DnsCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsCache")
+            .field("ttl", &self.ttl)
+            .field("negative_ttl", &self.negative_ttl)
+            .field("stale_ttl", &self.stale_ttl)
+            .field("capacity", &self.capacity)
+            .field("entries", &self.len())
+            .finish()
+    }
+}
+
+impl DnsCache {
+    /// Create new dns cache.
+    ///
+    /// By default records are cached for 60 seconds, failed lookups
+    /// for 5 seconds, stale records are not used.
+    pub fn new() -> Self {
+        DnsCache {
+            ttl: Duration::from_secs(60),
+            negative_ttl: Duration::from_secs(5),
+            stale_ttl: Duration::ZERO,
+            capacity: 1024,
+            entries: Rc::new(RefCell::new(HashMap::default())),
+        }
+    }
+
+    /// Set time to live for successfully resolved records.
+    ///
+    /// To disable caching of resolved records set value to 0.
+    ///
+    /// By default ttl is set to 60 seconds.
+    pub fn ttl(mut self, ttl: Seconds) -> Self {
+        self.ttl = ttl.into();
+        self
+    }
+
+    /// Set time to live for failed lookups.
+    ///
+    /// To disable negative caching set value to 0.
+    ///
+    /// By default negative ttl is set to 5 seconds.
+    pub fn negative_ttl(mut self, ttl: Seconds) -> Self {
+        self.negative_ttl = ttl.into();
+        self
+    }
+
+    /// Use expired records for specified period of time.
+    ///
+    /// Expired record is returned to the caller immediately and new lookup
+    /// get started in background. Stale records are used only for successful
+    /// lookups.
+    ///
+    /// By default stale records are not used.
+    pub fn stale_while_revalidate(mut self, period: Seconds) -> Self {
+        self.stale_ttl = period.into();
+        self
+    }
+
+    /// Set max number of cached records.
+    ///
+    /// If cache is full, record with closest expiration time get evicted.
+    ///
+    /// By default capacity is set to 1024.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Remove all cached records for the host.
+    ///
+    /// Returns `true` if any record is removed.
+    pub fn invalidate(&self, host: &str) -> bool {
+        let mut entries = self.entries.borrow_mut();
+        let len = entries.len();
+        entries.retain(|key, _| {
+            !(key == host
+                || (key.starts_with(host) && key.as_bytes().get(host.len()) == Some(&b':')))
+        });
+        len != entries.len()
+    }
+
+    /// Remove all cached records.
+    pub fn clear(&self) {
+        self.entries.borrow_mut().clear();
+    }
+
+    /// Number of cached records.
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    /// Check if cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+
+    pub(super) fn get(&self, key: &str) -> Lookup {
+        let mut entries = self.entries.borrow_mut();
+
+        if let Some(entry) = entries.get_mut(key) {
+            let now = now();
+            if entry.expires > now {
+                return Lookup::Hit(entry.result.clone());
+            }
+            if let Ok(ref addrs) = entry.result {
+                if entry.expires + self.stale_ttl > now {
+                    return if entry.refreshing {
+                        Lookup::Hit(Ok(addrs.clone()))
+                    } else {
+                        entry.refreshing = true;
+                        Lookup::Stale(addrs.clone())
+                    };
+                }
+            }
+            entries.remove(key);
+        }
+        Lookup::Miss
+    }
+
+    pub(super) fn insert(
+        &self,
+        key: String,
+        result: &Result<Vec<SocketAddr>, ConnectError>,
+    ) {
+        let ttl = if result.is_ok() {
+            self.ttl
+        } else {
+            self.negative_ttl
+        };
+        if ttl.is_zero() || self.capacity == 0 {
+            self.entries.borrow_mut().remove(&key);
+            return;
+        }
+
+        let mut entries = self.entries.borrow_mut();
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let evict = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires)
+                .map(|(key, _)| key.clone());
+            if let Some(evict) = evict {
+                entries.remove(&evict);
+            }
+        }
+        entries.insert(
+            key,
+            Entry {
+                result: result.clone(),
+                expires: now() + ttl,
+                refreshing: false,
+            },
+        );
+    }
+
+    /// Background revalidation failed, keep using stale record
+    pub(super) fn refresh_failed(&self, key: &str) {
+        if let Some(entry) = self.entries.borrow_mut().get_mut(key) {
+            entry.refreshing = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[crate::rt_test]
+    async fn test_cache() {
+        let addr: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let cache = DnsCache::new().ttl(Seconds(60)).capacity(2);
+        assert!(cache.is_empty());
+        assert!(matches!(cache.get("localhost:80"), Lookup::Miss));
+
+        cache.insert("localhost:80".to_string(), &Ok(vec![addr]));
+        assert_eq!(cache.len(), 1);
+        assert!(
+            matches!(cache.get("localhost:80"), Lookup::Hit(Ok(ref a)) if a == &[addr])
+        );
+
+        cache.insert("localhost:443".to_string(), &Ok(vec![addr]));
+        cache.insert("example.com".to_string(), &Err(ConnectError::NoRecords));
+        assert_eq!(cache.len(), 2);
+        assert!(matches!(
+            cache.get("example.com"),
+            Lookup::Hit(Err(ConnectError::NoRecords))
+        ));
+
+        assert!(!cache.invalidate("local"));
+        assert!(cache.invalidate("localhost"));
+        assert_eq!(cache.len(), 1);
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(format!("{:?}", cache).contains("DnsCache"));
+
+        // negative caching is disabled
+        let cache = DnsCache::new().negative_ttl(Seconds::ZERO);
+        cache.insert("example.com".to_string(), &Err(ConnectError::NoRecords));
+        assert!(cache.is_empty());
+    }
+
+    #[crate::rt_test]
+    async fn test_stale() {
+        let addr: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let cache = DnsCache::new()
+            .ttl(Seconds(1))
+            .stale_while_revalidate(Seconds(60));
+        cache.insert("localhost".to_string(), &Ok(vec![addr]));
+        cache
+            .entries
+            .borrow_mut()
+            .get_mut("localhost")
+            .unwrap()
+            .expires = now();
+
+        assert!(matches!(cache.get("localhost"), Lookup::Stale(_)));
+        // revalidation is in progress
+        assert!(matches!(cache.get("localhost"), Lookup::Hit(Ok(_))));
+        cache.refresh_failed("localhost");
+        assert!(matches!(cache.get("localhost"), Lookup::Stale(_)));
+
+        let cache = DnsCache::new().ttl(Seconds(1));
+        cache.insert("localhost".to_string(), &Ok(vec![addr]));
+        cache
+            .entries
+            .borrow_mut()
+            .get_mut("localhost")
+            .unwrap()
+            .expires = now();
+        assert!(matches!(cache.get("localhost"), Lookup::Miss));
+        assert!(cache.is_empty());
+    }
+}
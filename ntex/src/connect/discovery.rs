@@ -0,0 +1,619 @@
+use std::{fmt, fs, io, net, rc::Rc, time::Duration};
+
+use nanorand::{Rng, WyRand};
+
+use crate::time::Millis;
+
+const TYPE_SRV: u16 = 33;
+const TYPE_HTTPS: u16 = 65;
+const CLASS_IN: u16 = 1;
+
+const PARAM_ALPN: u16 = 1;
+const PARAM_PORT: u16 = 3;
+
+/// Dns based endpoint discovery
+///
+/// Discovery queries `SRV` and `HTTPS` (`SVCB`) dns records of the host
+/// before regular name resolution. `HTTPS` record provides alternative
+/// endpoint, port and alpn protocols of the host. `SRV` records provide list
+/// of endpoints, endpoints are ordered by priority and by weight within the
+/// same priority. If host does not have such records, regular name resolution
+/// is used.
+///
+/// Records are queried from the first nameserver of `/etc/resolv.conf`, if
+/// nameserver is not set explicitly.
+///
+/// ```rust
+/// use ntex::connect::{Connector, Discovery};
+///
+/// let connector = Connector::<String>::new()
+///     .discovery(Discovery::new().srv("_http._tcp").https(true));
+/// ```
+#[derive(Clone)]
+pub struct Discovery(Rc<Inner>);
+
+#[derive(Clone)]
+struct Inner {
+    srv: Option<String>,
+    https: bool,
+    nameserver: Option<net::SocketAddr>,
+    timeout: Millis,
+}
+
+impl Discovery {
+    /// Create discovery configuration, by default no records are queried.
+    pub fn new() -> Self {
+        Discovery(Rc::new(Inner {
+            srv: None,
+            https: false,
+            nameserver: None,
+            timeout: Millis(2_000),
+        }))
+    }
+
+    /// Query `SRV` records of the service, for example `_http._tcp`.
+    ///
+    /// Records are queried for `<service>.<host>` name.
+    pub fn srv<U: Into<String>>(mut self, service: U) -> Self {
+        self.inner_mut().srv = Some(service.into());
+        self
+    }
+
+    /// Query `HTTPS` records of the host.
+    ///
+    /// Alpn protocols of the record are available via `Connect::alpn_hints()`.
+    pub fn https(mut self, enabled: bool) -> Self {
+        self.inner_mut().https = enabled;
+        self
+    }
+
+    /// Use nameserver for records queries.
+    pub fn nameserver(mut self, addr: net::SocketAddr) -> Self {
+        self.inner_mut().nameserver = Some(addr);
+        self
+    }
+
+    /// Set timeout for records queries.
+    ///
+    /// By default timeout is set to 2 seconds.
+    pub fn timeout<U: Into<Millis>>(mut self, timeout: U) -> Self {
+        self.inner_mut().timeout = timeout.into();
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::make_mut(&mut self.0)
+    }
+
+    /// Discover endpoints of the host
+    pub(super) async fn discover(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> io::Result<Option<Endpoints>> {
+        if self.0.srv.is_none() && !self.0.https {
+            return Ok(None);
+        }
+
+        let cfg = (*self.0).clone();
+        let host = host.trim_end_matches('.').to_string();
+        let fut = crate::rt::spawn_blocking(move || cfg.discover(host, port));
+        match fut.await {
+            Ok(res) => res,
+            Err(e) => {
+                trace!("Discovery: failed to run records query: {:?}", e);
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl Default for Discovery {
+    fn default() -> Self {
+        Discovery::new()
+    }
+}
+
+impl fmt::Debug for Discovery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Discovery")
+            .field("srv", &self.0.srv)
+            .field("https", &self.0.https)
+            .field("nameserver", &self.0.nameserver)
+            .field("timeout", &self.0.timeout)
+            .finish()
+    }
+}
+
+/// Discovered endpoints
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Endpoints {
+    pub(super) targets: Vec<(String, u16)>,
+    pub(super) alpn: Option<Vec<String>>,
+}
+
+impl Inner {
+    fn discover(&self, host: String, port: u16) -> io::Result<Option<Endpoints>> {
+        let ns = self.nameserver.unwrap_or_else(system_nameserver);
+        let timeout = Duration::from(self.timeout);
+
+        let mut target = None;
+        let mut alpn = None;
+        if self.https {
+            let (msg, answers) = query(ns, &host, TYPE_HTTPS, timeout)?;
+            let mut records = answers
+                .iter()
+                .filter_map(|rdata| SvcbRecord::parse(&msg, rdata))
+                .filter(|rec| rec.priority != 0)
+                .collect::<Vec<_>>();
+            records.sort_by_key(|rec| rec.priority);
+
+            if let Some(rec) = records.into_iter().next() {
+                let name = if rec.target.is_empty() {
+                    host.clone()
+                } else {
+                    rec.target
+                };
+                target = Some((name, rec.port.unwrap_or(port)));
+                if !rec.alpn.is_empty() {
+                    alpn = Some(rec.alpn);
+                }
+            }
+        }
+
+        if let Some(ref service) = self.srv {
+            let name = target.as_ref().map(|t| &t.0).unwrap_or(&host);
+            let (msg, answers) =
+                query(ns, &format!("{}.{}", service, name), TYPE_SRV, timeout)?;
+            let records = answers
+                .iter()
+                .filter_map(|rdata| SrvRecord::parse(&msg, rdata))
+                .collect();
+            let targets: Vec<_> = srv_order(records, &mut WyRand::new())
+                .into_iter()
+                .filter(|rec| !rec.target.is_empty())
+                .map(|rec| (rec.target, rec.port))
+                .collect();
+
+            if !targets.is_empty() {
+                return Ok(Some(Endpoints { targets, alpn }));
+            }
+        }
+
+        Ok(target.map(|t| Endpoints {
+            alpn,
+            targets: vec![t],
+        }))
+    }
+}
+
+/// Address of the first nameserver from `/etc/resolv.conf`
+fn system_nameserver() -> net::SocketAddr {
+    fs::read_to_string("/etc/resolv.conf")
+        .ok()
+        .and_then(|conf| {
+            conf.lines().find_map(|line| {
+                let mut parts = line.split_whitespace();
+                if parts.next() == Some("nameserver") {
+                    parts.next().and_then(|ip| ip.parse().ok())
+                } else {
+                    None
+                }
+            })
+        })
+        .map(|ip| net::SocketAddr::new(ip, 53))
+        .unwrap_or_else(|| net::SocketAddr::from(([127, 0, 0, 1], 53)))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct SrvRecord {
+    priority: u16,
+    weight: u16,
+    port: u16,
+    target: String,
+}
+
+impl SrvRecord {
+    fn parse(msg: &[u8], rdata: &Rdata) -> Option<SrvRecord> {
+        let data = &msg[rdata.start..rdata.end];
+        if data.len() < 7 {
+            return None;
+        }
+        let (target, _) = read_name(msg, rdata.start + 6)?;
+        Some(SrvRecord {
+            target,
+            priority: u16::from_be_bytes([data[0], data[1]]),
+            weight: u16::from_be_bytes([data[2], data[3]]),
+            port: u16::from_be_bytes([data[4], data[5]]),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct SvcbRecord {
+    priority: u16,
+    target: String,
+    port: Option<u16>,
+    alpn: Vec<String>,
+}
+
+impl SvcbRecord {
+    fn parse(msg: &[u8], rdata: &Rdata) -> Option<SvcbRecord> {
+        if rdata.end - rdata.start < 3 {
+            return None;
+        }
+        let priority = u16::from_be_bytes([msg[rdata.start], msg[rdata.start + 1]]);
+        let (target, mut pos) = read_name(msg, rdata.start + 2)?;
+
+        let mut rec = SvcbRecord {
+            priority,
+            target,
+            port: None,
+            alpn: Vec::new(),
+        };
+        while pos + 4 <= rdata.end {
+            let key = u16::from_be_bytes([msg[pos], msg[pos + 1]]);
+            let len = u16::from_be_bytes([msg[pos + 2], msg[pos + 3]]) as usize;
+            let start = pos + 4;
+            pos = start + len;
+            if pos > rdata.end {
+                return None;
+            }
+            let value = &msg[start..pos];
+
+            match key {
+                PARAM_ALPN => {
+                    let mut idx = 0;
+                    while idx < value.len() {
+                        let end = idx + 1 + value[idx] as usize;
+                        let proto = value.get(idx + 1..end)?;
+                        rec.alpn.push(String::from_utf8_lossy(proto).into_owned());
+                        idx = end;
+                    }
+                }
+                PARAM_PORT if len == 2 => {
+                    rec.port = Some(u16::from_be_bytes([value[0], value[1]]));
+                }
+                _ => (),
+            }
+        }
+        Some(rec)
+    }
+}
+
+/// Order `SRV` records by priority and weight (rfc2782)
+fn srv_order(mut records: Vec<SrvRecord>, rng: &mut WyRand) -> Vec<SrvRecord> {
+    records.sort_by_key(|rec| (rec.priority, rec.weight != 0));
+
+    let mut result = Vec::with_capacity(records.len());
+    while !records.is_empty() {
+        let priority = records[0].priority;
+        let len = records
+            .iter()
+            .position(|rec| rec.priority != priority)
+            .unwrap_or(records.len());
+        let mut group: Vec<_> = records.drain(..len).collect();
+
+        while !group.is_empty() {
+            let total: u32 = group.iter().map(|rec| rec.weight as u32).sum();
+            let num: u32 = rng.generate_range(0..=total);
+            let mut sum = 0;
+            let idx = group
+                .iter()
+                .position(|rec| {
+                    sum += rec.weight as u32;
+                    sum >= num
+                })
+                .unwrap_or(0);
+            result.push(group.remove(idx));
+        }
+    }
+    result
+}
+
+/// Position of record data in dns message
+struct Rdata {
+    start: usize,
+    end: usize,
+}
+
+/// Query records of specified type, returns message and positions of answers
+fn query(
+    ns: net::SocketAddr,
+    name: &str,
+    qtype: u16,
+    timeout: Duration,
+) -> io::Result<(Vec<u8>, Vec<Rdata>)> {
+    let id = WyRand::new().generate::<u16>();
+    let request = encode_query(id, name, qtype)?;
+
+    let local: net::SocketAddr = if ns.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let sock = net::UdpSocket::bind(local)?;
+    sock.set_read_timeout(Some(timeout))?;
+    sock.connect(ns)?;
+    sock.send(&request)?;
+
+    let mut buf = vec![0; 4096];
+    loop {
+        let size = sock.recv(&mut buf)?;
+        if size >= 2 && u16::from_be_bytes([buf[0], buf[1]]) == id {
+            buf.truncate(size);
+            break;
+        }
+    }
+    let answers = decode_response(&buf, qtype)?;
+    Ok((buf, answers))
+}
+
+fn encode_query(id: u16, name: &str, qtype: u16) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(name.len() + 18);
+    buf.extend_from_slice(&id.to_be_bytes());
+    // recursion desired, one question
+    buf.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        if label.len() > 63 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Dns label is too long",
+            ));
+        }
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+    buf.extend_from_slice(&qtype.to_be_bytes());
+    buf.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(buf)
+}
+
+fn decode_response(msg: &[u8], qtype: u16) -> io::Result<Vec<Rdata>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Malformed dns response");
+
+    if msg.len() < 12 {
+        return Err(invalid());
+    }
+    match msg[3] & 0x0f {
+        0 => (),
+        // name does not exist
+        3 => return Ok(Vec::new()),
+        rcode => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Dns query failed with code {}", rcode),
+            ))
+        }
+    }
+    let qdcount = u16::from_be_bytes([msg[4], msg[5]]);
+    let ancount = u16::from_be_bytes([msg[6], msg[7]]);
+
+    let mut pos = 12;
+    for _ in 0..qdcount {
+        pos = read_name(msg, pos).ok_or_else(invalid)?.1 + 4;
+    }
+
+    let mut answers = Vec::new();
+    for _ in 0..ancount {
+        pos = read_name(msg, pos).ok_or_else(invalid)?.1;
+        let header = msg.get(pos..pos + 10).ok_or_else(invalid)?;
+        let rtype = u16::from_be_bytes([header[0], header[1]]);
+        let len = u16::from_be_bytes([header[8], header[9]]) as usize;
+        let start = pos + 10;
+        pos = start + len;
+        if pos > msg.len() {
+            return Err(invalid());
+        }
+        if rtype == qtype {
+            answers.push(Rdata { start, end: pos });
+        }
+    }
+    Ok(answers)
+}
+
+/// Read domain name, returns name and position after name
+fn read_name(msg: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut name = String::new();
+    let mut end = None;
+    let mut jumps = 0;
+    loop {
+        let len = *msg.get(pos)? as usize;
+        if len & 0xc0 == 0xc0 {
+            // compressed name
+            let ptr = ((len & 0x3f) << 8) | *msg.get(pos + 1)? as usize;
+            if end.is_none() {
+                end = Some(pos + 2);
+            }
+            jumps += 1;
+            if jumps > 16 {
+                return None;
+            }
+            pos = ptr;
+        } else if len == 0 {
+            return Some((name, end.unwrap_or(pos + 1)));
+        } else {
+            let label = msg.get(pos + 1..pos + 1 + len)?;
+            if !name.is_empty() {
+                name.push('.');
+            }
+            name.push_str(&String::from_utf8_lossy(label));
+            pos += 1 + len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::connect::{Connect, Resolver};
+
+    fn record(msg: &mut Vec<u8>, rtype: u16, rdata: &[u8]) {
+        // pointer to question name
+        msg.extend_from_slice(&[0xc0, 12]);
+        msg.extend_from_slice(&rtype.to_be_bytes());
+        msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+        msg.extend_from_slice(&60u32.to_be_bytes());
+        msg.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        msg.extend_from_slice(rdata);
+    }
+
+    fn srv(priority: u16, weight: u16, port: u16, target: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&priority.to_be_bytes());
+        data.extend_from_slice(&weight.to_be_bytes());
+        data.extend_from_slice(&port.to_be_bytes());
+        data.extend_from_slice(&encode_query(0, target, 0).unwrap()[12..]);
+        data.truncate(data.len() - 4);
+        data
+    }
+
+    fn response(req: &[u8]) -> Vec<u8> {
+        let (name, pos) = read_name(req, 12).unwrap();
+        let qtype = u16::from_be_bytes([req[pos], req[pos + 1]]);
+
+        let mut msg = req[..pos + 4].to_vec();
+        msg[2] = 0x81;
+        msg[3] = 0x80;
+        match (name.as_str(), qtype) {
+            ("svc.internal", TYPE_HTTPS) => {
+                // priority 1, target ".", alpn h2, port 8443
+                let data = [0, 1, 0, 0, 1, 0, 3, 2, b'h', b'2', 0, 3, 0, 2, 0x20, 0xfb];
+                record(&mut msg, TYPE_HTTPS, &data);
+                msg[7] = 1;
+            }
+            ("_http._tcp.svc.internal", TYPE_SRV) => {
+                record(&mut msg, TYPE_SRV, &srv(20, 0, 9002, "localhost"));
+                record(&mut msg, TYPE_SRV, &srv(10, 0, 9001, "localhost"));
+                msg[7] = 2;
+            }
+            _ => msg[3] = 0x83,
+        }
+        msg
+    }
+
+    fn nameserver() -> net::SocketAddr {
+        let sock = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = sock.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0; 512];
+            while let Ok((size, peer)) = sock.recv_from(&mut buf) {
+                let _ = sock.send_to(&response(&buf[..size]), peer);
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_srv_order() {
+        let rec = |priority, weight, port| SrvRecord {
+            priority,
+            weight,
+            port,
+            target: "localhost".to_string(),
+        };
+        let mut rng = WyRand::new();
+        let records = vec![rec(20, 0, 3), rec(10, 1, 1), rec(10, 0, 2), rec(30, 5, 4)];
+        let ordered = srv_order(records, &mut rng);
+        assert_eq!(ordered.len(), 4);
+        assert_eq!(ordered[2].port, 3);
+        assert_eq!(ordered[3].port, 4);
+
+        // weighted selection
+        let mut first = 0;
+        for _ in 0..1000 {
+            let records = vec![rec(10, 90, 1), rec(10, 10, 2)];
+            if srv_order(records, &mut rng)[0].port == 1 {
+                first += 1;
+            }
+        }
+        assert!(first > 700 && first < 1000);
+    }
+
+    #[test]
+    fn test_parse() {
+        let req = encode_query(1, "svc.internal", TYPE_HTTPS).unwrap();
+        let msg = response(&req);
+        let answers = decode_response(&msg, TYPE_HTTPS).unwrap();
+        assert_eq!(answers.len(), 1);
+        let rec = SvcbRecord::parse(&msg, &answers[0]).unwrap();
+        assert_eq!(rec.priority, 1);
+        assert_eq!(rec.target, "");
+        assert_eq!(rec.port, Some(8443));
+        assert_eq!(rec.alpn, vec!["h2".to_string()]);
+
+        let req = encode_query(1, "_http._tcp.svc.internal", TYPE_SRV).unwrap();
+        let msg = response(&req);
+        let answers = decode_response(&msg, TYPE_SRV).unwrap();
+        let rec = SrvRecord::parse(&msg, &answers[0]).unwrap();
+        assert_eq!(rec.priority, 20);
+        assert_eq!(rec.port, 9002);
+        assert_eq!(rec.target, "localhost");
+
+        let req = encode_query(1, "unknown.internal", TYPE_SRV).unwrap();
+        assert!(decode_response(&response(&req), TYPE_SRV)
+            .unwrap()
+            .is_empty());
+        assert!(decode_response(&[0; 4], TYPE_SRV).is_err());
+        assert!(encode_query(1, &"a".repeat(64), TYPE_SRV).is_err());
+    }
+
+    #[crate::rt_test]
+    async fn test_discovery() {
+        let ns = nameserver();
+        let discovery = Discovery::new().srv("_http._tcp").nameserver(ns);
+        assert!(format!("{:?}", discovery).contains("_http._tcp"));
+
+        let res = discovery.discover("svc.internal", 80).await.unwrap();
+        assert_eq!(
+            res,
+            Some(Endpoints {
+                targets: vec![
+                    ("localhost".to_string(), 9001),
+                    ("localhost".to_string(), 9002)
+                ],
+                alpn: None
+            })
+        );
+
+        let discovery = Discovery::new().https(true).nameserver(ns);
+        let res = discovery.discover("svc.internal.", 80).await.unwrap();
+        assert_eq!(
+            res,
+            Some(Endpoints {
+                targets: vec![("svc.internal".to_string(), 8443)],
+                alpn: Some(vec!["h2".to_string()])
+            })
+        );
+        let res = discovery.discover("unknown.internal", 80).await.unwrap();
+        assert_eq!(res, None);
+
+        // resolver uses discovered endpoints
+        let resolver = Resolver::new().discovery(
+            Discovery::new()
+                .srv("_http._tcp")
+                .https(true)
+                .nameserver(ns)
+                .timeout(Millis(500)),
+        );
+        let res = resolver
+            .lookup(Connect::new("svc.internal").set_port(80))
+            .await
+            .unwrap();
+        assert_eq!(res.alpn_hints(), Some(&["h2".to_string()][..]));
+        let ports: Vec<_> = res.addrs().map(|addr| addr.port()).collect();
+        assert_eq!(ports.first(), Some(&9001));
+        assert_eq!(ports.last(), Some(&9002));
+
+        // fallback to regular resolution
+        let res = resolver
+            .lookup(Connect::new("localhost").set_port(8080))
+            .await
+            .unwrap();
+        assert_eq!(res.alpn_hints(), None);
+        assert!(res.addrs().all(|addr| addr.port() == 8080));
+    }
+}
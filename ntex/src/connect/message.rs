@@ -1,7 +1,7 @@
 use std::collections::{vec_deque, VecDeque};
 use std::fmt;
 use std::iter::{FromIterator, FusedIterator};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 
 use crate::util::Either;
 
@@ -54,11 +54,15 @@ impl Address for SocketAddr {
 }
 
 /// Connect request
-#[derive(Eq, PartialEq, Debug, Hash)]
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub struct Connect<T> {
     pub(super) req: T,
     pub(super) port: u16,
     pub(super) addr: Option<Either<SocketAddr, VecDeque<SocketAddr>>>,
+    pub(super) local_addr: Option<IpAddr>,
+    pub(super) interface: Option<String>,
+    pub(super) server_name: Option<String>,
+    pub(super) alpn: Option<Vec<String>>,
 }
 
 impl<T: Address> Connect<T> {
@@ -69,6 +73,10 @@ impl<T: Address> Connect<T> {
             req,
             port: port.unwrap_or(0),
             addr: None,
+            local_addr: None,
+            interface: None,
+            server_name: None,
+            alpn: None,
         }
     }
 
@@ -78,6 +86,10 @@ impl<T: Address> Connect<T> {
             req,
             port: 0,
             addr: Some(Either::Left(addr)),
+            local_addr: None,
+            interface: None,
+            server_name: None,
+            alpn: None,
         }
     }
 
@@ -111,6 +123,41 @@ impl<T: Address> Connect<T> {
         self
     }
 
+    /// Use local address for connection.
+    ///
+    /// Outgoing connection is bound to specified ip address,
+    /// by default os selects local address.
+    pub fn set_local_addr(mut self, addr: Option<IpAddr>) -> Self {
+        self.local_addr = addr;
+        self
+    }
+
+    /// Use network interface for connection.
+    ///
+    /// Binding to network interface is supported only on linux.
+    pub fn set_interface(mut self, interface: Option<String>) -> Self {
+        self.interface = interface;
+        self
+    }
+
+    /// Use server name for tls handshake.
+    ///
+    /// Server name is used for SNI and certificate verification instead
+    /// of host name of the request. By default host name is used.
+    pub fn set_server_name(mut self, name: Option<String>) -> Self {
+        self.server_name = name;
+        self
+    }
+
+    /// Use alpn protocols hints.
+    ///
+    /// Resolver sets hints from `HTTPS` dns record of the host,
+    /// if endpoint discovery is enabled.
+    pub fn set_alpn_hints(mut self, protos: Option<Vec<String>>) -> Self {
+        self.alpn = protos;
+        self
+    }
+
     /// Host name
     pub fn host(&self) -> &str {
         self.req.host()
@@ -121,6 +168,26 @@ impl<T: Address> Connect<T> {
         self.req.port().unwrap_or(self.port)
     }
 
+    /// Local address of the request
+    pub fn local_addr(&self) -> Option<IpAddr> {
+        self.local_addr
+    }
+
+    /// Network interface of the request
+    pub fn interface(&self) -> Option<&str> {
+        self.interface.as_deref()
+    }
+
+    /// Tls server name of the request
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// Alpn protocols hints of the request
+    pub fn alpn_hints(&self) -> Option<&[String]> {
+        self.alpn.as_deref()
+    }
+
     /// Preresolved addresses of the request.
     pub fn addrs(&self) -> ConnectAddrsIter<'_> {
         if let Some(addr) = self.req.addr() {
@@ -237,7 +304,7 @@ impl ExactSizeIterator for ConnectTakeAddrsIter {}
 
 impl FusedIterator for ConnectTakeAddrsIter {}
 
-fn parse(host: &str) -> (&str, Option<u16>) {
+pub(super) fn parse(host: &str) -> (&str, Option<u16>) {
     let mut parts_iter = host.splitn(2, ':');
     if let Some(host) = parts_iter.next() {
         let port_str = parts_iter.next().unwrap_or("");
@@ -301,6 +368,22 @@ mod tests {
         connect = connect.set_addrs(vec![addr]);
         assert_eq!(format!("{}", connect), "www.rust-lang.org:80");
 
+        assert_eq!(connect.local_addr(), None);
+        assert_eq!(connect.interface(), None);
+        connect = connect
+            .set_local_addr(Some("127.0.0.1".parse().unwrap()))
+            .set_interface(Some("lo".to_string()));
+        assert_eq!(connect.local_addr(), Some("127.0.0.1".parse().unwrap()));
+        assert_eq!(connect.interface(), Some("lo"));
+
+        assert_eq!(connect.server_name(), None);
+        connect = connect.set_server_name(Some("example.com".to_string()));
+        assert_eq!(connect.server_name(), Some("example.com"));
+
+        assert_eq!(connect.alpn_hints(), None);
+        connect = connect.set_alpn_hints(Some(vec!["h2".to_string()]));
+        assert_eq!(connect.alpn_hints(), Some(&["h2".to_string()][..]));
+
         let addr: SocketAddr = "127.0.0.1:8080".parse().unwrap();
         let mut connect = Connect::new(addr);
         assert_eq!(connect.host(), "");
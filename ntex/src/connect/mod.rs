@@ -1,8 +1,12 @@
 //! Tcp connector service
 use std::future::Future;
 
+mod balance;
+mod cache;
+mod discovery;
 mod error;
 mod message;
+mod reconnect;
 mod resolve;
 mod service;
 mod uri;
@@ -13,8 +17,12 @@ pub mod openssl;
 #[cfg(feature = "rustls")]
 pub mod rustls;
 
+pub use self::balance::{Balance, Balancer, Endpoints};
+pub use self::cache::DnsCache;
+pub use self::discovery::Discovery;
 pub use self::error::ConnectError;
 pub use self::message::{Address, Connect};
+pub use self::reconnect::{ConnectionState, ReconnectingIo, ReconnectingIoBuilder};
 pub use self::resolve::Resolver;
 pub use self::service::Connector;
 
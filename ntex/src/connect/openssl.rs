@@ -9,7 +9,9 @@ use crate::io::{Base, Boxed, Io};
 use crate::service::{Service, ServiceFactory};
 use crate::util::{PoolId, Ready};
 
-use super::{Address, Connect, ConnectError, Connector as BaseConnector};
+use super::{
+    Address, Connect, ConnectError, Connector as BaseConnector, Discovery, DnsCache,
+};
 
 pub struct Connector<T> {
     connector: BaseConnector<T>,
@@ -35,6 +37,26 @@ impl<T> Connector<T> {
             openssl: self.openssl,
         }
     }
+
+    /// Use dns cache for name resolution.
+    ///
+    /// By default name resolution results are not cached.
+    pub fn dns_cache(self, cache: DnsCache) -> Self {
+        Self {
+            connector: self.connector.dns_cache(cache),
+            openssl: self.openssl,
+        }
+    }
+
+    /// Use dns records for endpoint discovery.
+    ///
+    /// By default endpoint discovery is disabled.
+    pub fn discovery(self, discovery: Discovery) -> Self {
+        Self {
+            connector: self.connector.discovery(discovery),
+            openssl: self.openssl,
+        }
+    }
 }
 
 impl<T: Address + 'static> Connector<T> {
@@ -47,7 +69,10 @@ impl<T: Address + 'static> Connector<T> {
         Connect<T>: From<U>,
     {
         let message = Connect::from(message);
-        let host = message.host().to_string();
+        let host = message
+            .server_name()
+            .unwrap_or_else(|| message.host())
+            .to_string();
         let conn = self.connector.call(message);
         let openssl = self.openssl.clone();
 
@@ -0,0 +1,486 @@
+use std::task::{Context, Poll};
+use std::{cell::Cell, cell::RefCell, cmp, fmt, future::Future, pin::Pin, rc::Rc};
+
+use crate::channel::mpsc;
+use crate::io::{IoBoxed, IoRef};
+use crate::service::Service;
+use crate::task::LocalWaker;
+use crate::time::{sleep, timeout, Millis, Seconds};
+use crate::util::{poll_fn, select, Either};
+
+type HandshakeFn = Rc<
+    dyn Fn(IoBoxed) -> Pin<Box<dyn Future<Output = Result<IoBoxed, Box<dyn fmt::Debug>>>>>,
+>;
+
+/// Connection state
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Connection is being established
+    Connecting,
+    /// Connection is established and handshake is completed
+    Connected,
+    /// Connection is lost or could not be established, waiting for next attempt
+    Disconnected,
+    /// Reconnecting is stopped
+    Closed,
+}
+
+/// Automatically reconnecting io stream
+///
+/// `ReconnectingIo` establishes connection with connect service, runs
+/// handshake hook and passes ready io stream to the application. When io
+/// stream is disconnected new connection is established, failed attempts
+/// are retried with exponential backoff. Each established connection is
+/// returned by `next()` method once.
+///
+/// ```rust,no_run
+/// use ntex::connect::{Connect, Connector, ReconnectingIo};
+/// use ntex::time::Millis;
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let conn = ReconnectingIo::build(Connector::default(), Connect::new("localhost:6379"))
+///         .backoff(Millis(100), Millis(5_000))
+///         .max_attempts(3)
+///         .handshake(|io| async move {
+///             // authenticate, select database, etc
+///             Ok::<_, std::io::Error>(io)
+///         })
+///         .finish();
+///
+///     while let Some(io) = conn.next().await {
+///         // use connection until it is disconnected
+///     }
+/// }
+/// ```
+pub struct ReconnectingIo(Rc<Inner>);
+
+/// Reconnecting io stream builder
+pub struct ReconnectingIoBuilder<S, R> {
+    connector: S,
+    req: R,
+    base_delay: Millis,
+    max_delay: Millis,
+    max_attempts: usize,
+    handshake: Option<HandshakeFn>,
+    handshake_timeout: Seconds,
+}
+
+struct Inner {
+    state: Cell<ConnectionState>,
+    io: RefCell<Option<IoBoxed>>,
+    current: RefCell<Option<IoRef>>,
+    handles: Cell<usize>,
+    waker: LocalWaker,
+    stop_waker: LocalWaker,
+    stopped: Cell<bool>,
+    subscribers: RefCell<Vec<mpsc::Sender<ConnectionState>>>,
+}
+
+impl ReconnectingIo {
+    /// Create reconnecting io stream builder
+    ///
+    /// `connector` is used for establishing connection, `req` is cloned
+    /// for each attempt.
+    pub fn build<S, R>(connector: S, req: R) -> ReconnectingIoBuilder<S, R>
+    where
+        S: Service<R> + 'static,
+        S::Response: Into<IoBoxed>,
+        S::Error: fmt::Debug,
+        R: Clone + 'static,
+    {
+        ReconnectingIoBuilder {
+            connector,
+            req,
+            base_delay: Millis(100),
+            max_delay: Millis(10_000),
+            max_attempts: 0,
+            handshake: None,
+            handshake_timeout: Seconds(5),
+        }
+    }
+
+    #[inline]
+    /// Current connection state
+    pub fn state(&self) -> ConnectionState {
+        self.0.state.get()
+    }
+
+    #[inline]
+    /// Check if connection is established
+    pub fn is_connected(&self) -> bool {
+        self.0.state.get() == ConnectionState::Connected
+    }
+
+    /// Subscribe to connection state changes
+    pub fn subscribe(&self) -> mpsc::Receiver<ConnectionState> {
+        let (tx, rx) = mpsc::channel();
+        self.0.subscribers.borrow_mut().push(tx);
+        rx
+    }
+
+    #[inline]
+    /// Wait for next established connection
+    ///
+    /// Returns `None` if reconnecting is stopped.
+    pub async fn next(&self) -> Option<IoBoxed> {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+
+    /// Poll for next established connection
+    pub fn poll_next(&self, cx: &mut Context<'_>) -> Poll<Option<IoBoxed>> {
+        if let Some(io) = self.0.io.borrow_mut().take() {
+            Poll::Ready(Some(io))
+        } else if self.0.stopped.get() {
+            Poll::Ready(None)
+        } else {
+            self.0.waker.register(cx.waker());
+            Poll::Pending
+        }
+    }
+
+    /// Stop reconnecting and close current connection
+    pub fn stop(&self) {
+        self.0.stop();
+    }
+}
+
+impl Clone for ReconnectingIo {
+    fn clone(&self) -> Self {
+        self.0.handles.set(self.0.handles.get() + 1);
+        ReconnectingIo(self.0.clone())
+    }
+}
+
+impl Drop for ReconnectingIo {
+    fn drop(&mut self) {
+        self.0.handles.set(self.0.handles.get() - 1);
+        if self.0.handles.get() == 0 {
+            self.0.stop();
+        }
+    }
+}
+
+impl fmt::Debug for ReconnectingIo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReconnectingIo")
+            .field("state", &self.state())
+            .finish()
+    }
+}
+
+impl<S, R> ReconnectingIoBuilder<S, R>
+where
+    S: Service<R> + 'static,
+    S::Response: Into<IoBoxed>,
+    S::Error: fmt::Debug,
+    R: Clone + 'static,
+{
+    /// Set exponential backoff parameters.
+    ///
+    /// Delay before next attempt is doubled after each failed attempt,
+    /// starting at `base` and limited by `max`. First attempt after
+    /// disconnect is made immediately. By default backoff starts at
+    /// 100 milliseconds and is limited by 10 seconds.
+    pub fn backoff<T: Into<Millis>>(mut self, base: T, max: T) -> Self {
+        self.base_delay = base.into();
+        self.max_delay = max.into();
+        self
+    }
+
+    /// Set max number of consecutive failed attempts.
+    ///
+    /// Reconnecting is stopped if limit is reached. By default
+    /// number of attempts is not limited.
+    pub fn max_attempts(mut self, num: usize) -> Self {
+        self.max_attempts = num;
+        self
+    }
+
+    /// Set handshake hook.
+    ///
+    /// Hook is executed for each established connection before it is
+    /// passed to the application, failed handshake is treated as failed
+    /// connect attempt.
+    pub fn handshake<F, Fut, E>(mut self, f: F) -> Self
+    where
+        F: Fn(IoBoxed) -> Fut + 'static,
+        Fut: Future<Output = Result<IoBoxed, E>> + 'static,
+        E: fmt::Debug + 'static,
+    {
+        self.handshake = Some(Rc::new(move |io| {
+            let fut = f(io);
+            Box::pin(
+                async move { fut.await.map_err(|e| Box::new(e) as Box<dyn fmt::Debug>) },
+            )
+        }));
+        self
+    }
+
+    /// Set handshake timeout.
+    ///
+    /// By default handshake timeout is 5 seconds.
+    pub fn handshake_timeout(mut self, timeout: Seconds) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// Start connecting
+    pub fn finish(self) -> ReconnectingIo {
+        let inner = Rc::new(Inner {
+            state: Cell::new(ConnectionState::Connecting),
+            io: RefCell::new(None),
+            current: RefCell::new(None),
+            handles: Cell::new(1),
+            waker: LocalWaker::new(),
+            stop_waker: LocalWaker::new(),
+            stopped: Cell::new(false),
+            subscribers: RefCell::new(Vec::new()),
+        });
+        crate::rt::spawn(run(inner.clone(), self));
+        ReconnectingIo(inner)
+    }
+
+    fn delay(&self, attempt: usize) -> Millis {
+        let delay = self
+            .base_delay
+            .0
+            .saturating_mul(1u64 << cmp::min(attempt, 32) as u32);
+        Millis(cmp::min(delay, self.max_delay.0))
+    }
+
+    /// Connect and run handshake
+    async fn connect(&self) -> Result<IoBoxed, String> {
+        poll_fn(|cx| self.connector.poll_ready(cx))
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+        let io: IoBoxed = self
+            .connector
+            .call(self.req.clone())
+            .await
+            .map_err(|e| format!("{:?}", e))?
+            .into();
+
+        if let Some(ref handshake) = self.handshake {
+            match timeout(self.handshake_timeout, handshake(io)).await {
+                Ok(Ok(io)) => Ok(io),
+                Ok(Err(e)) => Err(format!("Handshake failed: {:?}", e)),
+                Err(_) => Err("Handshake timeout".to_string()),
+            }
+        } else {
+            Ok(io)
+        }
+    }
+}
+
+async fn run<S, R>(inner: Rc<Inner>, cfg: ReconnectingIoBuilder<S, R>)
+where
+    S: Service<R> + 'static,
+    S::Response: Into<IoBoxed>,
+    S::Error: fmt::Debug,
+    R: Clone + 'static,
+{
+    let mut attempt = 0;
+
+    while !inner.stopped.get() {
+        inner.set_state(ConnectionState::Connecting);
+
+        let res = match select(cfg.connect(), inner.on_stop()).await {
+            Either::Left(res) => res,
+            Either::Right(_) => break,
+        };
+
+        match res {
+            Ok(io) => {
+                log::trace!("Connection is established");
+                attempt = 0;
+                let on_disconnect = io.on_disconnect();
+                *inner.current.borrow_mut() = Some(io.get_ref());
+                *inner.io.borrow_mut() = Some(io);
+                inner.set_state(ConnectionState::Connected);
+                inner.waker.wake();
+
+                on_disconnect.await;
+                log::trace!("Connection is lost");
+                inner.current.borrow_mut().take();
+                inner.io.borrow_mut().take();
+                inner.set_state(ConnectionState::Disconnected);
+            }
+            Err(err) => {
+                attempt += 1;
+                log::error!("Connect attempt {} failed: {}", attempt, err);
+                if cfg.max_attempts > 0 && attempt >= cfg.max_attempts {
+                    log::error!("Max number of connect attempts is reached");
+                    break;
+                }
+                inner.set_state(ConnectionState::Disconnected);
+
+                let delay = cfg.delay(attempt - 1);
+                if let Either::Right(_) = select(sleep(delay), inner.on_stop()).await {
+                    break;
+                }
+            }
+        }
+    }
+    inner.stop();
+}
+
+impl Inner {
+    fn set_state(&self, state: ConnectionState) {
+        if self.state.get() != ConnectionState::Closed && self.state.get() != state {
+            self.state.set(state);
+            self.subscribers
+                .borrow_mut()
+                .retain(|tx| tx.send(state).is_ok());
+        }
+    }
+
+    fn stop(&self) {
+        self.stopped.set(true);
+        if let Some(io) = self.current.borrow_mut().take() {
+            io.close();
+        }
+        self.io.borrow_mut().take();
+        self.set_state(ConnectionState::Closed);
+        self.subscribers.borrow_mut().clear();
+        self.waker.wake();
+        self.stop_waker.wake();
+    }
+
+    /// Resolves when reconnecting is stopped
+    async fn on_stop(&self) {
+        poll_fn(|cx| {
+            if self.stopped.get() {
+                Poll::Ready(())
+            } else {
+                self.stop_waker.register(cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::BytesCodec;
+    use crate::io::Io;
+    use crate::service::fn_service;
+    use crate::testing::IoTest;
+    use crate::util::Bytes;
+
+    #[crate::rt_test]
+    async fn test_reconnect() {
+        let attempts = Rc::new(Cell::new(0));
+        let servers = Rc::new(RefCell::new(Vec::new()));
+        let (attempts2, servers2) = (attempts.clone(), servers.clone());
+
+        let conn = ReconnectingIo::build(
+            fn_service(move |_: ()| {
+                attempts2.set(attempts2.get() + 1);
+                let res = if attempts2.get() == 2 {
+                    Err(std::io::Error::new(std::io::ErrorKind::Other, "failed"))
+                } else {
+                    let (client, server) = IoTest::create();
+                    client.remote_buffer_cap(1024);
+                    server.remote_buffer_cap(1024);
+                    servers2.borrow_mut().push(Io::new(server));
+                    Ok(Io::new(client))
+                };
+                async move { res }
+            }),
+            (),
+        )
+        .backoff(Millis(10), Millis(50))
+        .handshake(|io: IoBoxed| async move {
+            io.send(Bytes::from_static(b"HELLO"), &BytesCodec)
+                .await
+                .map(|_| io)
+        })
+        .finish();
+        let events = conn.subscribe();
+
+        let io = conn.next().await.unwrap();
+        assert!(conn.is_connected());
+        assert_eq!(attempts.get(), 1);
+        let srv = servers.borrow_mut().pop().unwrap();
+        let data = srv.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(&data[..], b"HELLO");
+
+        // disconnect, first attempt fails
+        srv.get_ref().force_close();
+        drop(srv);
+        assert!(io.recv(&BytesCodec).await.unwrap().is_none());
+        let _io = conn.next().await.unwrap();
+        assert_eq!(attempts.get(), 3);
+        assert_eq!(conn.state(), ConnectionState::Connected);
+
+        conn.stop();
+        assert_eq!(conn.state(), ConnectionState::Closed);
+        assert!(conn.next().await.is_none());
+
+        let mut states = Vec::new();
+        while let Some(state) = poll_fn(|cx| events.poll_recv(cx)).await {
+            states.push(state);
+        }
+        assert_eq!(
+            states,
+            vec![
+                ConnectionState::Connected,
+                ConnectionState::Disconnected,
+                ConnectionState::Connecting,
+                ConnectionState::Disconnected,
+                ConnectionState::Connecting,
+                ConnectionState::Connected,
+                ConnectionState::Closed,
+            ]
+        );
+        assert!(format!("{:?}", conn).contains("Closed"));
+    }
+
+    #[crate::rt_test]
+    async fn test_max_attempts() {
+        let attempts = Rc::new(Cell::new(0));
+        let attempts2 = attempts.clone();
+
+        let conn = ReconnectingIo::build(
+            fn_service(move |_: ()| {
+                attempts2.set(attempts2.get() + 1);
+                async {
+                    Err::<Io, _>(std::io::Error::new(std::io::ErrorKind::Other, "failed"))
+                }
+            }),
+            (),
+        )
+        .backoff(Millis(1), Millis(10))
+        .max_attempts(3)
+        .finish();
+
+        assert!(conn.next().await.is_none());
+        assert_eq!(attempts.get(), 3);
+        assert_eq!(conn.state(), ConnectionState::Closed);
+    }
+
+    #[crate::rt_test]
+    async fn test_handshake_failure() {
+        let attempts = Rc::new(Cell::new(0));
+        let attempts2 = attempts.clone();
+
+        let conn = ReconnectingIo::build(
+            fn_service(move |_: ()| {
+                attempts2.set(attempts2.get() + 1);
+                let (client, _) = IoTest::create();
+                async move { Ok::<_, ()>(Io::new(client)) }
+            }),
+            (),
+        )
+        .backoff(Millis(1), Millis(10))
+        .max_attempts(2)
+        .handshake(|_: IoBoxed| async { Err::<IoBoxed, _>("rejected") })
+        .finish();
+
+        assert!(conn.next().await.is_none());
+        assert_eq!(attempts.get(), 2);
+    }
+}
@@ -1,22 +1,52 @@
 use std::{fmt, future::Future, io, marker, net, pin::Pin, task::Context, task::Poll};
 
+use super::cache::{DnsCache, Lookup};
+use super::{discovery::Discovery, message::parse};
 use super::{Address, Connect, ConnectError};
 use crate::service::{Service, ServiceFactory};
 use crate::util::{Either, Ready};
 
 /// DNS Resolver Service
-pub struct Resolver<T>(marker::PhantomData<T>);
+pub struct Resolver<T> {
+    cache: Option<DnsCache>,
+    discovery: Option<Discovery>,
+    _t: marker::PhantomData<T>,
+}
 
 impl<T> fmt::Debug for Resolver<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Resolver").finish()
+        f.debug_struct("Resolver")
+            .field("cache", &self.cache)
+            .field("discovery", &self.discovery)
+            .finish()
     }
 }
 
 impl<T> Resolver<T> {
     /// Create new resolver instance with custom configuration and options.
     pub fn new() -> Self {
-        Resolver(marker::PhantomData)
+        Resolver {
+            cache: None,
+            discovery: None,
+            _t: marker::PhantomData,
+        }
+    }
+
+    /// Use dns cache for name resolution.
+    pub fn cache(mut self, cache: DnsCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Use dns records for endpoint discovery.
+    pub fn discovery(mut self, discovery: Discovery) -> Self {
+        self.discovery = Some(discovery);
+        self
+    }
+
+    /// Get reference to dns cache
+    pub fn get_cache(&self) -> Option<&DnsCache> {
+        self.cache.as_ref()
     }
 }
 
@@ -33,22 +63,84 @@ impl<T: Address> Resolver<T> {
             Either::Right(Ready::Ok(req))
         } else {
             trace!("DNS resolver: resolving host {:?}", req.host());
+            let cache = self.cache.clone();
+            let discovery = self.discovery.clone();
 
             Either::Left(async move {
+                if let Some(discovery) = discovery {
+                    let (host, _) = parse(req.host());
+                    match discovery.discover(host, req.port()).await {
+                        Ok(Some(endpoints)) => {
+                            let mut addrs = Vec::new();
+                            for (target, port) in endpoints.targets {
+                                match resolve(format!("{}:{}", target, port)).await {
+                                    Ok(ips) => addrs.extend(ips),
+                                    Err(e) => trace!(
+                                        "DNS resolver: failed to resolve endpoint {:?} err: {}",
+                                        target,
+                                        e
+                                    ),
+                                }
+                            }
+                            if !addrs.is_empty() {
+                                trace!(
+                                    "DNS resolver: discovered endpoints {:?} for host {:?}",
+                                    addrs,
+                                    req.host()
+                                );
+                                return Ok(req
+                                    .set_addrs(addrs)
+                                    .set_alpn_hints(endpoints.alpn));
+                            }
+                        }
+                        Ok(None) => (),
+                        Err(e) => trace!(
+                            "DNS resolver: endpoint discovery failed for host {:?} err: {}",
+                            req.host(),
+                            e
+                        ),
+                    }
+                }
+
                 let host = if req.host().contains(':') {
                     req.host().to_string()
                 } else {
                     format!("{}:{}", req.host(), req.port())
                 };
 
-                let fut = crate::rt::spawn_blocking(move || {
-                    net::ToSocketAddrs::to_socket_addrs(&host)
-                });
+                let result = if let Some(ref cache) = cache {
+                    match cache.get(&host) {
+                        Lookup::Hit(result) => {
+                            trace!("DNS resolver: use cached record for {:?}", host);
+                            result
+                        }
+                        Lookup::Stale(addrs) => {
+                            trace!("DNS resolver: revalidate stale record for {:?}", host);
+                            let cache = cache.clone();
+                            crate::rt::spawn(async move {
+                                let result = resolve(host.clone()).await;
+                                if result.is_ok() {
+                                    cache.insert(host, &result);
+                                } else {
+                                    cache.refresh_failed(&host);
+                                }
+                            });
+                            Ok(addrs)
+                        }
+                        Lookup::Miss => {
+                            let result = resolve(host.clone()).await;
+                            cache.insert(host, &result);
+                            result
+                        }
+                    }
+                } else {
+                    resolve(host).await
+                };
 
-                match fut.await {
-                    Ok(Ok(ips)) => {
+                match result {
+                    Ok(ips) => {
                         let port = req.port();
-                        let req = req.set_addrs(ips.map(|mut ip| {
+                        let req = req.set_addrs(ips.into_iter().map(|mut ip| {
                             ip.set_port(port);
                             ip
                         }));
@@ -65,24 +157,13 @@ impl<T: Address> Resolver<T> {
                             Ok(req)
                         }
                     }
-                    Ok(Err(e)) => {
-                        trace!(
-                            "DNS resolver: failed to resolve host {:?} err: {}",
-                            req.host(),
-                            e
-                        );
-                        Err(ConnectError::Resolver(e))
-                    }
                     Err(e) => {
                         trace!(
                             "DNS resolver: failed to resolve host {:?} err: {}",
                             req.host(),
                             e
                         );
-                        Err(ConnectError::Resolver(io::Error::new(
-                            io::ErrorKind::Other,
-                            e,
-                        )))
+                        Err(e)
                     }
                 }
             })
@@ -90,15 +171,37 @@ impl<T: Address> Resolver<T> {
     }
 }
 
+/// Resolve host with system resolver
+async fn resolve(host: String) -> Result<Vec<net::SocketAddr>, ConnectError> {
+    let fut = crate::rt::spawn_blocking(move || net::ToSocketAddrs::to_socket_addrs(&host));
+
+    match fut.await {
+        Ok(Ok(ips)) => {
+            let ips: Vec<_> = ips.collect();
+            if ips.is_empty() {
+                Err(ConnectError::NoRecords)
+            } else {
+                Ok(ips)
+            }
+        }
+        Ok(Err(e)) => Err(ConnectError::Resolver(e)),
+        Err(e) => Err(ConnectError::Resolver(io::Error::other(e))),
+    }
+}
+
 impl<T> Default for Resolver<T> {
     fn default() -> Resolver<T> {
-        Resolver(marker::PhantomData)
+        Resolver::new()
     }
 }
 
 impl<T> Clone for Resolver<T> {
     fn clone(&self) -> Self {
-        Resolver(marker::PhantomData)
+        Resolver {
+            cache: self.cache.clone(),
+            discovery: self.discovery.clone(),
+            _t: marker::PhantomData,
+        }
     }
 }
 
@@ -158,4 +261,37 @@ mod tests {
         assert_eq!(addrs.len(), 1);
         assert!(addrs.contains(&addr));
     }
+
+    #[crate::rt_test]
+    async fn resolver_cache() {
+        let cache = DnsCache::new();
+        let resolver = Resolver::new().cache(cache.clone());
+        assert!(resolver.get_cache().is_some());
+        assert!(format!("{:?}", resolver).contains("DnsCache"));
+
+        let res = resolver
+            .lookup(Connect::new("localhost").set_port(8080))
+            .await
+            .unwrap();
+        assert!(res.addrs().all(|addr| addr.port() == 8080));
+        assert_eq!(cache.len(), 1);
+
+        // cached record, port is set from request
+        let res = resolver
+            .lookup(Connect::new("localhost:9090"))
+            .await
+            .unwrap();
+        assert!(res.addrs().all(|addr| addr.port() == 9090));
+        assert_eq!(cache.len(), 2);
+
+        // ip addresses are not cached
+        let _ = resolver
+            .lookup(Connect::new("127.0.0.1").set_port(8080))
+            .await
+            .unwrap();
+        assert_eq!(cache.len(), 2);
+
+        assert!(cache.invalidate("localhost"));
+        assert!(cache.is_empty());
+    }
 }
@@ -9,7 +9,9 @@ use crate::io::{Base, Boxed, Io};
 use crate::service::{Service, ServiceFactory};
 use crate::util::{PoolId, Ready};
 
-use super::{Address, Connect, ConnectError, Connector as BaseConnector};
+use super::{
+    Address, Connect, ConnectError, Connector as BaseConnector, Discovery, DnsCache,
+};
 
 /// Rustls connector factory
 pub struct Connector<T> {
@@ -44,6 +46,26 @@ impl<T> Connector<T> {
             inner: self.inner,
         }
     }
+
+    /// Use dns cache for name resolution.
+    ///
+    /// By default name resolution results are not cached.
+    pub fn dns_cache(self, cache: DnsCache) -> Self {
+        Self {
+            connector: self.connector.dns_cache(cache),
+            inner: self.inner,
+        }
+    }
+
+    /// Use dns records for endpoint discovery.
+    ///
+    /// By default endpoint discovery is disabled.
+    pub fn discovery(self, discovery: Discovery) -> Self {
+        Self {
+            connector: self.connector.discovery(discovery),
+            inner: self.inner,
+        }
+    }
 }
 
 impl<T: Address + 'static> Connector<T> {
@@ -56,7 +78,11 @@ impl<T: Address + 'static> Connector<T> {
         Connect<T>: From<U>,
     {
         let req = Connect::from(message);
-        let host = req.host().split(':').next().unwrap().to_owned();
+        let host = if let Some(name) = req.server_name() {
+            name.to_owned()
+        } else {
+            req.host().split(':').next().unwrap().to_owned()
+        };
         let conn = self.connector.call(req);
         let connector = self.inner.clone();
 
@@ -1,12 +1,13 @@
+use std::net::{IpAddr, SocketAddr};
 use std::task::{Context, Poll};
-use std::{collections::VecDeque, future::Future, io, net::SocketAddr, pin::Pin};
+use std::{collections::VecDeque, future::Future, io, pin::Pin};
 
 use crate::io::{types, Boxed, Io};
-use crate::rt::tcp_connect_in;
+use crate::rt::{tcp_connect_bind_in, tcp_connect_in};
 use crate::service::{Service, ServiceFactory};
 use crate::util::{Either, PoolId, PoolRef, Ready};
 
-use super::{Address, Connect, ConnectError, Resolver};
+use super::{Address, Connect, ConnectError, Discovery, DnsCache, Resolver};
 
 pub struct Connector<T> {
     resolver: Resolver<T>,
@@ -30,6 +31,22 @@ impl<T> Connector<T> {
         self.pool = id.pool_ref();
         self
     }
+
+    /// Use dns cache for name resolution.
+    ///
+    /// By default name resolution results are not cached.
+    pub fn dns_cache(mut self, cache: DnsCache) -> Self {
+        self.resolver = self.resolver.cache(cache);
+        self
+    }
+
+    /// Use dns records for endpoint discovery.
+    ///
+    /// By default endpoint discovery is disabled.
+    pub fn discovery(mut self, discovery: Discovery) -> Self {
+        self.resolver = self.resolver.discovery(discovery);
+        self
+    }
 }
 
 impl<T: Address> Connector<T> {
@@ -123,11 +140,22 @@ impl<T: Address> Future for ConnectServiceResponse<T> {
                 Poll::Pending => Poll::Pending,
                 Poll::Ready(address) => {
                     let port = address.port();
-                    let Connect { req, addr, .. } = address;
+                    let Connect {
+                        req,
+                        addr,
+                        local_addr,
+                        interface,
+                        ..
+                    } = address;
+                    let bind = Bind {
+                        local_addr,
+                        interface,
+                        pool: self.pool,
+                    };
 
                     if let Some(addr) = addr {
                         self.state = ConnectState::Connect(TcpConnectorResponse::new(
-                            req, port, addr, self.pool,
+                            req, port, addr, bind,
                         ));
                         self.poll(cx)
                     } else if let Some(addr) = req.addr() {
@@ -135,7 +163,7 @@ impl<T: Address> Future for ConnectServiceResponse<T> {
                             req,
                             addr.port(),
                             Either::Left(addr),
-                            self.pool,
+                            bind,
                         ));
                         self.poll(cx)
                     } else {
@@ -149,13 +177,38 @@ impl<T: Address> Future for ConnectServiceResponse<T> {
     }
 }
 
+/// Local endpoint of tcp connection
+pub(super) struct Bind {
+    pub(super) local_addr: Option<IpAddr>,
+    pub(super) interface: Option<String>,
+    pub(super) pool: PoolRef,
+}
+
+impl Bind {
+    pub(super) fn connect(
+        &self,
+        addr: SocketAddr,
+    ) -> Pin<Box<dyn Future<Output = Result<Io, io::Error>>>> {
+        if self.local_addr.is_none() && self.interface.is_none() {
+            Box::pin(tcp_connect_in(addr, self.pool))
+        } else {
+            Box::pin(tcp_connect_bind_in(
+                addr,
+                self.local_addr,
+                self.interface.clone(),
+                self.pool,
+            ))
+        }
+    }
+}
+
 /// Tcp stream connector response future
 struct TcpConnectorResponse<T> {
     req: Option<T>,
     port: u16,
     addrs: Option<VecDeque<SocketAddr>>,
     stream: Option<Pin<Box<dyn Future<Output = Result<Io, io::Error>>>>>,
-    pool: PoolRef,
+    bind: Bind,
 }
 
 impl<T: Address> TcpConnectorResponse<T> {
@@ -163,7 +216,7 @@ impl<T: Address> TcpConnectorResponse<T> {
         req: T,
         port: u16,
         addr: Either<SocketAddr, VecDeque<SocketAddr>>,
-        pool: PoolRef,
+        bind: Bind,
     ) -> TcpConnectorResponse<T> {
         trace!(
             "TCP connector - connecting to {:?} port:{}",
@@ -175,13 +228,13 @@ impl<T: Address> TcpConnectorResponse<T> {
             Either::Left(addr) => TcpConnectorResponse {
                 req: Some(req),
                 addrs: None,
-                stream: Some(Box::pin(tcp_connect_in(addr, pool))),
-                pool,
+                stream: Some(bind.connect(addr)),
+                bind,
                 port,
             },
             Either::Right(addrs) => TcpConnectorResponse {
                 port,
-                pool,
+                bind,
                 req: Some(req),
                 addrs: Some(addrs),
                 stream: None,
@@ -230,7 +283,7 @@ impl<T: Address> Future for TcpConnectorResponse<T> {
 
             // try to connect
             let addr = this.addrs.as_mut().unwrap().pop_front().unwrap();
-            this.stream = Some(Box::pin(tcp_connect_in(addr, this.pool)));
+            this.stream = Some(this.bind.connect(addr));
         }
     }
 }
@@ -267,5 +320,16 @@ mod tests {
         let msg = Connect::new(server.addr());
         let result = crate::connect::connect(msg).await;
         assert!(result.is_ok());
+
+        let msg =
+            Connect::new(server.addr()).set_local_addr(Some("127.0.0.1".parse().unwrap()));
+        let io = crate::connect::connect(msg).await.unwrap();
+        assert_eq!(
+            io.query::<types::PeerAddr>().get().unwrap().0,
+            server.addr()
+        );
+
+        let msg = Connect::new(server.addr()).set_local_addr(Some("::1".parse().unwrap()));
+        assert!(crate::connect::connect(msg).await.is_err());
     }
 }
@@ -0,0 +1,134 @@
+//! Access log records
+use std::{fmt, net::SocketAddr, time};
+
+use crate::http::{Method, Request, StatusCode, Uri, Version};
+use crate::io::{types::PeerAddr, IoRef};
+use crate::time::now;
+
+pub use ntex_tls::types::TlsInfo;
+
+/// Request/response exchange log record
+///
+/// Record is passed to the access log handler after response is sent
+/// or connection is closed. `Display` implementation renders record in
+/// common log format, custom formats could use record fields directly.
+#[derive(Clone, Debug)]
+pub struct RequestLogRecord {
+    /// Time when request head is received
+    pub timestamp: time::SystemTime,
+    /// Request method
+    pub method: Method,
+    /// Request uri
+    pub uri: Uri,
+    /// Request http version
+    pub version: Version,
+    /// Response status, `None` if response is not sent
+    pub status: Option<StatusCode>,
+    /// Peer address
+    pub peer_addr: Option<SocketAddr>,
+    /// Negotiated tls parameters
+    pub tls: Option<TlsInfo>,
+    /// Http/2 stream id
+    pub stream_id: Option<u32>,
+    /// Number of received request body bytes
+    pub bytes_received: u64,
+    /// Number of sent response body bytes
+    pub bytes_sent: u64,
+    /// Time from receiving request head to sending response head
+    pub response_time: Option<time::Duration>,
+    /// Time from receiving request head to completion of the exchange
+    pub duration: time::Duration,
+    /// Response is sent completely
+    pub completed: bool,
+    started: time::Instant,
+}
+
+impl RequestLogRecord {
+    pub(super) fn new(req: &Request, io: &IoRef) -> Self {
+        let head = req.head();
+        RequestLogRecord {
+            timestamp: time::SystemTime::now(),
+            method: head.method.clone(),
+            uri: head.uri.clone(),
+            version: head.version,
+            status: None,
+            peer_addr: io.query::<PeerAddr>().get().map(|addr| addr.0),
+            tls: io.query::<TlsInfo>().as_ref().cloned(),
+            stream_id: None,
+            bytes_received: 0,
+            bytes_sent: 0,
+            response_time: None,
+            duration: time::Duration::ZERO,
+            completed: false,
+            started: now(),
+        }
+    }
+
+    /// Response head is sent
+    pub(super) fn response(&mut self, status: StatusCode) {
+        self.status = Some(status);
+        self.response_time = Some(now() - self.started);
+    }
+
+    /// Exchange is finished
+    pub(super) fn finish(mut self, completed: bool) -> Self {
+        self.completed = completed;
+        self.duration = now() - self.started;
+        self
+    }
+}
+
+impl fmt::Display for RequestLogRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(addr) = self.peer_addr {
+            write!(f, "{} ", addr.ip())?;
+        } else {
+            f.write_str("- ")?;
+        }
+        write!(f, "\"{} {} {:?}\" ", self.method, self.uri, self.version)?;
+        if let Some(status) = self.status {
+            write!(f, "{} ", status.as_u16())?;
+        } else {
+            f.write_str("- ")?;
+        }
+        write!(f, "{} {:.6}", self.bytes_sent, self.duration.as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::test::TestRequest;
+
+    #[crate::rt_test]
+    async fn test_display() {
+        let req = TestRequest::with_uri("/index.html").finish();
+        let mut rec = RequestLogRecord {
+            timestamp: time::SystemTime::now(),
+            method: req.head().method.clone(),
+            uri: req.head().uri.clone(),
+            version: req.head().version,
+            status: None,
+            peer_addr: None,
+            tls: None,
+            stream_id: None,
+            bytes_received: 0,
+            bytes_sent: 10,
+            response_time: None,
+            duration: time::Duration::from_millis(5),
+            completed: false,
+            started: now(),
+        };
+        assert_eq!(
+            rec.to_string(),
+            "- \"GET /index.html HTTP/1.1\" - 10 0.005000"
+        );
+
+        rec.peer_addr = Some("127.0.0.1:8080".parse().unwrap());
+        rec.status = Some(StatusCode::OK);
+        assert_eq!(
+            rec.to_string(),
+            "127.0.0.1 \"GET /index.html HTTP/1.1\" 200 10 0.005000"
+        );
+    }
+}
@@ -27,6 +27,15 @@ pub trait MessageBody {
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>>;
+
+    /// Check if body requested flush of written data.
+    ///
+    /// Dispatcher checks flush request after each `poll_next_chunk()` call,
+    /// request is reset by this call. Next chunk is not polled until all
+    /// written data is sent to the peer.
+    fn take_flush(&mut self) -> bool {
+        false
+    }
 }
 
 impl MessageBody for () {
@@ -53,6 +62,10 @@ impl<T: MessageBody> MessageBody for Box<T> {
     ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
         self.as_mut().poll_next_chunk(cx)
     }
+
+    fn take_flush(&mut self) -> bool {
+        self.as_mut().take_flush()
+    }
 }
 
 pub enum ResponseBody<B> {
@@ -112,6 +125,13 @@ impl<B: MessageBody> MessageBody for ResponseBody<B> {
             ResponseBody::Other(ref mut body) => body.poll_next_chunk(cx),
         }
     }
+
+    fn take_flush(&mut self) -> bool {
+        match self {
+            ResponseBody::Body(ref mut body) => body.take_flush(),
+            ResponseBody::Other(ref mut body) => body.take_flush(),
+        }
+    }
 }
 
 impl<B: MessageBody + Unpin> Stream for ResponseBody<B> {
@@ -177,6 +197,14 @@ impl MessageBody for Body {
             Body::Message(ref mut body) => body.poll_next_chunk(cx),
         }
     }
+
+    fn take_flush(&mut self) -> bool {
+        if let Body::Message(ref mut body) = self {
+            body.take_flush()
+        } else {
+            false
+        }
+    }
 }
 
 impl PartialEq for Body {
@@ -258,9 +286,10 @@ where
     }
 }
 
-impl<S, E> From<BodyStream<S, E>> for Body
+impl<S, E, T> From<BodyStream<S, E>> for Body
 where
-    S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
+    S: Stream<Item = Result<T, E>> + Unpin + 'static,
+    T: Into<BodyItem>,
     E: Error + 'static,
 {
     fn from(s: BodyStream<S, E>) -> Body {
@@ -370,29 +399,57 @@ impl MessageBody for String {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+/// Streaming body item
+pub enum BodyItem {
+    /// Body data chunk
+    Chunk(Bytes),
+    /// Send all written data to the peer before polling next item
+    Flush,
+}
+
+impl BodyItem {
+    /// Create flush signal item
+    pub fn flush() -> Self {
+        BodyItem::Flush
+    }
+}
+
+impl From<Bytes> for BodyItem {
+    fn from(b: Bytes) -> Self {
+        BodyItem::Chunk(b)
+    }
+}
+
 /// Type represent streaming body.
 /// Response does not contain `content-length` header and appropriate transfer encoding is used.
+///
+/// Stream could yield [`BodyItem::Flush`] items to force flush mid-stream.
 pub struct BodyStream<S, E> {
     stream: S,
+    flush: bool,
     _t: PhantomData<E>,
 }
 
-impl<S, E> BodyStream<S, E>
+impl<S, E, T> BodyStream<S, E>
 where
-    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    S: Stream<Item = Result<T, E>> + Unpin,
+    T: Into<BodyItem>,
     E: Error,
 {
     pub fn new(stream: S) -> Self {
         BodyStream {
             stream,
+            flush: false,
             _t: PhantomData,
         }
     }
 }
 
-impl<S, E> MessageBody for BodyStream<S, E>
+impl<S, E, T> MessageBody for BodyStream<S, E>
 where
-    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    S: Stream<Item = Result<T, E>> + Unpin,
+    T: Into<BodyItem>,
     E: Error + 'static,
 {
     fn size(&self) -> BodySize {
@@ -403,19 +460,31 @@ where
     ///
     /// Empty values are skipped to prevent [`BodyStream`]'s transmission being
     /// ended on a zero-length chunk, but rather proceed until the underlying
-    /// [`Stream`] ends.
+    /// [`Stream`] ends. Flush items are recorded and reported by `take_flush()`.
     fn poll_next_chunk(
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
         loop {
             return Poll::Ready(match Pin::new(&mut self.stream).poll_next(cx) {
-                Poll::Ready(Some(Ok(ref bytes))) if bytes.is_empty() => continue,
-                Poll::Ready(opt) => opt.map(|res| res.map_err(Into::into)),
+                Poll::Ready(Some(Ok(item))) => match item.into() {
+                    BodyItem::Chunk(bytes) if bytes.is_empty() => continue,
+                    BodyItem::Chunk(bytes) => Some(Ok(bytes)),
+                    BodyItem::Flush => {
+                        self.flush = true;
+                        continue;
+                    }
+                },
+                Poll::Ready(Some(Err(err))) => Some(Err(err.into())),
+                Poll::Ready(None) => None,
                 Poll::Pending => return Poll::Pending,
             });
         }
     }
+
+    fn take_flush(&mut self) -> bool {
+        mem::replace(&mut self.flush, false)
+    }
 }
 
 /// Type represent streaming body.
@@ -712,6 +781,34 @@ mod tests {
         );
     }
 
+    #[crate::rt_test]
+    async fn body_stream_flush() {
+        let mut body = BodyStream::new(stream::iter(vec![
+            Ok::<_, io::Error>(BodyItem::from(Bytes::from("1"))),
+            Ok(BodyItem::flush()),
+            Ok(BodyItem::Chunk(Bytes::from("2"))),
+        ]));
+        assert!(!body.take_flush());
+        assert_eq!(
+            poll_fn(|cx| body.poll_next_chunk(cx)).await.unwrap().ok(),
+            Some(Bytes::from("1")),
+        );
+        assert!(!body.take_flush());
+        assert_eq!(
+            poll_fn(|cx| body.poll_next_chunk(cx)).await.unwrap().ok(),
+            Some(Bytes::from("2")),
+        );
+        assert!(body.take_flush());
+        assert!(!body.take_flush());
+        assert!(poll_fn(|cx| body.poll_next_chunk(cx)).await.is_none());
+
+        let mut body = Body::from(BodyStream::new(stream::iter(vec![Ok::<_, io::Error>(
+            BodyItem::Flush,
+        )])));
+        assert!(poll_fn(|cx| body.poll_next_chunk(cx)).await.is_none());
+        assert!(body.take_flush());
+    }
+
     #[crate::rt_test]
     async fn sized_skips_empty_chunks() {
         let mut body = SizedStream::new(
@@ -1,9 +1,13 @@
-use std::{error::Error, fmt, marker::PhantomData};
+use std::{error::Error, fmt, marker::PhantomData, rc::Rc};
 
+use crate::http::access_log::RequestLogRecord;
 use crate::http::body::MessageBody;
-use crate::http::config::{KeepAlive, OnRequest, ServiceConfig};
-use crate::http::error::ResponseError;
-use crate::http::h1::{Codec, ExpectHandler, H1Service, UpgradeHandler};
+use crate::http::config::{
+    H2Settings, HpackConfig, KeepAlive, OnAccessLog, OnConnect, OnRequest, OnRequestError,
+    OnUpgrade, ServiceConfig,
+};
+use crate::http::error::{HttpDispatchError, ResponseError};
+use crate::http::h1::{Codec, ExpectHandler, H1Service, Tunnel, UpgradeHandler, Upgraded};
 use crate::http::h2::H2Service;
 use crate::http::request::Request;
 use crate::http::response::Response;
@@ -19,11 +23,24 @@ use crate::time::{Millis, Seconds};
 pub struct HttpServiceBuilder<F, S, X = ExpectHandler, U = UpgradeHandler<F>> {
     keep_alive: KeepAlive,
     client_timeout: Millis,
+    headers_timeout: Millis,
     client_disconnect: Seconds,
     handshake_timeout: Millis,
     expect: X,
     upgrade: Option<U>,
     on_request: Option<OnRequest>,
+    connect: Option<OnConnect>,
+    upgrades: Vec<(String, OnUpgrade)>,
+    on_error: Option<OnRequestError>,
+    access_log: Option<OnAccessLog>,
+    read_params: Option<(u16, u16)>,
+    write_params: Option<(u16, u16)>,
+    pipeline: usize,
+    max_requests: usize,
+    absolute_form: bool,
+    authority_form: bool,
+    hpack: HpackConfig,
+    h2: H2Settings,
     _t: PhantomData<(F, S)>,
 }
 
@@ -33,11 +50,24 @@ impl<F, S> HttpServiceBuilder<F, S, ExpectHandler, UpgradeHandler<F>> {
         HttpServiceBuilder {
             keep_alive: KeepAlive::Timeout(Seconds(5)),
             client_timeout: Millis::from_secs(3),
+            headers_timeout: Millis::ZERO,
             client_disconnect: Seconds(3),
             handshake_timeout: Millis::from_secs(5),
             expect: ExpectHandler,
             upgrade: None,
             on_request: None,
+            connect: None,
+            upgrades: Vec::new(),
+            on_error: None,
+            access_log: None,
+            read_params: None,
+            write_params: None,
+            pipeline: 1,
+            max_requests: 0,
+            absolute_form: true,
+            authority_form: true,
+            hpack: HpackConfig::default(),
+            h2: H2Settings::default(),
             _t: PhantomData,
         }
     }
@@ -78,6 +108,21 @@ where
         self
     }
 
+    /// Set server client timeout for reading request headers.
+    ///
+    /// Defines a timeout for reading request headers once first bytes of
+    /// the request are received. Timeout applies to every request on the
+    /// connection and does not depend on keep-alive timeout, so clients that
+    /// trickle header bytes get cut off with the 408 (Request Time-out) error.
+    /// Number of such connections is available with
+    /// `ServiceConfig::headers_timeouts()`, see `HttpService::config()`.
+    ///
+    /// By default headers timeout is disabled.
+    pub fn client_headers_timeout(mut self, timeout: Seconds) -> Self {
+        self.headers_timeout = timeout.into();
+        self
+    }
+
     /// Set server connection disconnect timeout in seconds.
     ///
     /// Defines a timeout for disconnect connection. If a disconnect procedure does not complete
@@ -102,6 +147,138 @@ where
         self
     }
 
+    /// Set read buffer high and low watermarks for new connections.
+    ///
+    /// Overrides memory pool's params. Service could change params
+    /// for specific connection with `IoRef::set_read_params()` method.
+    pub fn read_params(mut self, high: u16, low: u16) -> Self {
+        assert!(low < high);
+        self.read_params = Some((high, low));
+        self
+    }
+
+    /// Set write buffer high and low watermarks for new connections.
+    ///
+    /// Overrides memory pool's params. Service could change params
+    /// for specific connection with `IoRef::set_write_params()` method.
+    pub fn write_params(mut self, high: u16, low: u16) -> Self {
+        assert!(low < high);
+        self.write_params = Some((high, low));
+        self
+    }
+
+    /// Set max number of concurrently processed pipelined requests.
+    ///
+    /// Pipelined http/1 requests without payload are passed to the service
+    /// concurrently, up to specified number of in-flight requests. Responses
+    /// are sent in order of requests. Requests with payload and requests with
+    /// `EXPECT` header are processed after all in-flight requests complete.
+    ///
+    /// By default pipelined requests are processed sequentially.
+    pub fn pipeline_concurrency(mut self, max: usize) -> Self {
+        assert!(max > 0);
+        self.pipeline = max;
+        self
+    }
+
+    /// Set max number of http/1 requests served by one connection.
+    ///
+    /// Response to the last request is sent with `Connection: close` header,
+    /// remaining request payload is read and connection gets closed after
+    /// response is sent. Pipelined requests received after the last request
+    /// are ignored. Limit allows to recycle long-living keep-alive connections.
+    ///
+    /// By default number of requests is not limited.
+    pub fn max_requests_per_connection(mut self, num: usize) -> Self {
+        self.max_requests = num;
+        self
+    }
+
+    /// Accept absolute-form request targets for http/1 requests.
+    ///
+    /// Absolute-form, `GET http://example.org/ HTTP/1.1`, is used by clients
+    /// for requests to forward proxies. Parsed target form is available via
+    /// `Request::target()` method. Requests with disabled target form get
+    /// rejected with `400 Bad Request` response.
+    ///
+    /// By default absolute-form is accepted.
+    pub fn h1_absolute_form(mut self, enabled: bool) -> Self {
+        self.absolute_form = enabled;
+        self
+    }
+
+    /// Accept authority-form request targets for http/1 `CONNECT` requests.
+    ///
+    /// Authority-form, `CONNECT example.org:443 HTTP/1.1`, is used by clients
+    /// to establish tunnels through proxies. Authority-form is never accepted
+    /// for methods other than `CONNECT`.
+    ///
+    /// By default authority-form is accepted.
+    pub fn h1_authority_form(mut self, enabled: bool) -> Self {
+        self.authority_form = enabled;
+        self
+    }
+
+    /// Set HPACK header compression settings for http/2 connections.
+    ///
+    /// By default protocol defaults are used.
+    pub fn hpack(mut self, cfg: HpackConfig) -> Self {
+        self.hpack = cfg;
+        self
+    }
+
+    /// Set http/2 initial stream-level flow control window size.
+    ///
+    /// By default window size is set to 65,535 bytes.
+    pub fn h2_initial_window_size(mut self, size: u32) -> Self {
+        self.h2.initial_window_size = Some(size);
+        self
+    }
+
+    /// Set http/2 initial connection-level flow control window size.
+    ///
+    /// By default window size is set to 65,535 bytes.
+    pub fn h2_initial_connection_window_size(mut self, size: u32) -> Self {
+        self.h2.initial_connection_window_size = Some(size);
+        self
+    }
+
+    /// Set max number of concurrent http/2 streams per connection.
+    ///
+    /// By default number of streams is not limited.
+    pub fn h2_max_concurrent_streams(mut self, max: u32) -> Self {
+        self.h2.max_concurrent_streams = Some(max);
+        self
+    }
+
+    /// Set max http/2 frame size.
+    ///
+    /// Size must be between 16,384 and 16,777,215 bytes.
+    /// By default frame size is set to 16,384 bytes.
+    pub fn h2_max_frame_size(mut self, size: u32) -> Self {
+        assert!((16_384..=16_777_215).contains(&size));
+        self.h2.max_frame_size = Some(size);
+        self
+    }
+
+    /// Set max size of http/2 decoded header list.
+    pub fn h2_max_header_list_size(mut self, size: u32) -> Self {
+        self.hpack = self.hpack.max_header_list_size(size);
+        self
+    }
+
+    /// Set http/2 keep-alive ping interval and timeout.
+    ///
+    /// Server sends ping frame if connection is idle for `interval`,
+    /// connection is closed if ping is not acknowledged within `timeout`.
+    ///
+    /// By default keep-alive pings are disabled.
+    pub fn h2_ping(mut self, interval: Seconds, timeout: Seconds) -> Self {
+        self.h2.ping_interval = interval.into();
+        self.h2.ping_timeout = timeout.into();
+        self
+    }
+
     /// Provide service for `EXPECT: 100-Continue` support.
     ///
     /// Service get called with request that contains `EXPECT` header.
@@ -116,11 +293,24 @@ where
         HttpServiceBuilder {
             keep_alive: self.keep_alive,
             client_timeout: self.client_timeout,
+            headers_timeout: self.headers_timeout,
             client_disconnect: self.client_disconnect,
             handshake_timeout: self.handshake_timeout,
             expect: expect.into_factory(),
             upgrade: self.upgrade,
             on_request: self.on_request,
+            connect: self.connect,
+            upgrades: self.upgrades,
+            on_error: self.on_error,
+            access_log: self.access_log,
+            read_params: self.read_params,
+            write_params: self.write_params,
+            pipeline: self.pipeline,
+            max_requests: self.max_requests,
+            absolute_form: self.absolute_form,
+            authority_form: self.authority_form,
+            hpack: self.hpack,
+            h2: self.h2,
             _t: PhantomData,
         }
     }
@@ -139,11 +329,24 @@ where
         HttpServiceBuilder {
             keep_alive: self.keep_alive,
             client_timeout: self.client_timeout,
+            headers_timeout: self.headers_timeout,
             client_disconnect: self.client_disconnect,
             handshake_timeout: self.handshake_timeout,
             expect: self.expect,
             upgrade: Some(upgrade.into_factory()),
             on_request: self.on_request,
+            connect: self.connect,
+            upgrades: self.upgrades,
+            on_error: self.on_error,
+            access_log: self.access_log,
+            read_params: self.read_params,
+            write_params: self.write_params,
+            pipeline: self.pipeline,
+            max_requests: self.max_requests,
+            absolute_form: self.absolute_form,
+            authority_form: self.authority_form,
+            hpack: self.hpack,
+            h2: self.h2,
             _t: PhantomData,
         }
     }
@@ -160,6 +363,108 @@ where
         self
     }
 
+    /// Set handler for http/1 `CONNECT` requests.
+    ///
+    /// Handler get called with `CONNECT` request and `Tunnel` object, normal
+    /// requests handling is halted. Handler either accepts tunnel with
+    /// successful response and gets connection's io for raw data transfer,
+    /// or rejects it with final response.
+    ///
+    /// By default `CONNECT` requests are handled by main service.
+    pub fn h1_connect<C, FC>(mut self, f: FC) -> Self
+    where
+        FC: IntoService<C, (Request, Tunnel)>,
+        C: Service<(Request, Tunnel), Response = ()> + 'static,
+        C::Error: fmt::Debug,
+    {
+        self.connect = Some(boxed::service(f.into_service().map_err(|e| {
+            log::error!("Error in CONNECT handler: {:?}", e);
+        })));
+        self
+    }
+
+    /// Set handler for http/1 protocol upgrade.
+    ///
+    /// Handler get called for requests with `Upgrade` header that contains
+    /// `protocol`, normal requests handling is halted. Handler either accepts
+    /// upgrade and gets connection's io, or rejects it with final response.
+    /// Connection's io keeps all io filters, so upgraded protocol works
+    /// over tls as well. Upgrade requests with body are handled by main service.
+    ///
+    /// Protocol handlers take precedence over upgrade service.
+    pub fn on_upgrade<P, C, FC>(mut self, protocol: P, f: FC) -> Self
+    where
+        P: Into<String>,
+        FC: IntoService<C, (Request, Upgraded)>,
+        C: Service<(Request, Upgraded), Response = ()> + 'static,
+        C::Error: fmt::Debug,
+    {
+        let protocol = protocol.into();
+        let name = protocol.clone();
+        self.upgrades.push((
+            protocol,
+            boxed::service(f.into_service().map_err(move |e| {
+                log::error!("Error in {:?} upgrade handler: {:?}", name, e);
+            })),
+        ));
+        self
+    }
+
+    /// Set handler for request error responses.
+    ///
+    /// Handler builds response that is sent to the peer for malformed
+    /// requests or if request head is not received within client timeout.
+    /// By default, responses with empty body and 400 or 408 status
+    /// are sent.
+    pub fn on_request_error<FR>(mut self, f: FR) -> Self
+    where
+        FR: Fn(&HttpDispatchError) -> Response + 'static,
+    {
+        self.on_error = Some(Rc::new(f));
+        self
+    }
+
+    /// Set access log handler.
+    ///
+    /// Handler is called with `RequestLogRecord` after each request/response
+    /// exchange, for http/1 and http/2 connections. Handler is called from
+    /// connection's dispatcher, so it must not block. Records could be
+    /// sent to a separate task over channel for writing.
+    ///
+    /// ```rust,no_run
+    /// use ntex::http::{HttpService, Response};
+    /// use ntex::{channel::mpsc, util::next};
+    ///
+    /// #[ntex::main]
+    /// async fn main() -> std::io::Result<()> {
+    ///     ntex::server::build()
+    ///         .bind("http", "127.0.0.1:8080", |_| {
+    ///             // write records from separate task
+    ///             let (tx, mut rx) = mpsc::channel();
+    ///             ntex::rt::spawn(async move {
+    ///                 while let Some(rec) = next(&mut rx).await {
+    ///                     println!("{}", rec);
+    ///                 }
+    ///             });
+    ///
+    ///             HttpService::build()
+    ///                 .on_access_log(move |rec| {
+    ///                     let _ = tx.send(rec);
+    ///                 })
+    ///                 .h1(|_| async { Ok::<_, std::io::Error>(Response::Ok().finish()) })
+    ///         })?
+    ///         .run()
+    ///         .await
+    /// }
+    /// ```
+    pub fn on_access_log<FL>(mut self, f: FL) -> Self
+    where
+        FL: Fn(RequestLogRecord) + 'static,
+    {
+        self.access_log = Some(Rc::new(f));
+        self
+    }
+
     /// Finish service configuration and create *http service* for HTTP/1 protocol.
     pub fn h1<B, SF>(self, service: SF) -> H1Service<F, S, B, X, U>
     where
@@ -174,7 +479,18 @@ where
             self.client_timeout,
             self.client_disconnect,
             self.handshake_timeout,
-        );
+        )
+        .buffer_params(self.read_params, self.write_params)
+        .headers_timeout(self.headers_timeout)
+        .pipeline(self.pipeline)
+        .max_requests(self.max_requests)
+        .request_targets(self.absolute_form, self.authority_form)
+        .connect(self.connect)
+        .upgrades(self.upgrades)
+        .on_error(self.on_error)
+        .access_log(self.access_log)
+        .hpack(self.hpack)
+        .h2(self.h2);
         H1Service::with_config(cfg, service.into_factory())
             .expect(self.expect)
             .upgrade(self.upgrade)
@@ -196,7 +512,18 @@ where
             self.client_timeout,
             self.client_disconnect,
             self.handshake_timeout,
-        );
+        )
+        .buffer_params(self.read_params, self.write_params)
+        .headers_timeout(self.headers_timeout)
+        .pipeline(self.pipeline)
+        .max_requests(self.max_requests)
+        .request_targets(self.absolute_form, self.authority_form)
+        .connect(self.connect)
+        .upgrades(self.upgrades)
+        .on_error(self.on_error)
+        .access_log(self.access_log)
+        .hpack(self.hpack)
+        .h2(self.h2);
 
         H2Service::with_config(cfg, service.into_factory())
     }
@@ -217,7 +544,18 @@ where
             self.client_timeout,
             self.client_disconnect,
             self.handshake_timeout,
-        );
+        )
+        .buffer_params(self.read_params, self.write_params)
+        .headers_timeout(self.headers_timeout)
+        .pipeline(self.pipeline)
+        .max_requests(self.max_requests)
+        .request_targets(self.absolute_form, self.authority_form)
+        .connect(self.connect)
+        .upgrades(self.upgrades)
+        .on_error(self.on_error)
+        .access_log(self.access_log)
+        .hpack(self.hpack)
+        .h2(self.h2);
         HttpService::with_config(cfg, service.into_factory())
             .expect(self.expect)
             .upgrade(self.upgrade)
@@ -6,7 +6,8 @@ use crate::{time::Millis, Service};
 
 use super::connect::ConnectorWrapper;
 use super::error::ConnectError;
-use super::{Client, ClientConfig, Connect, Connection, Connector};
+use super::{default_sensitive, Client, ClientConfig, Connect, Connection, Connector};
+use super::{RedirectPolicy, RequestSigner, RetryPolicy};
 
 /// An HTTP Client builder
 ///
@@ -16,7 +17,7 @@ pub struct ClientBuilder {
     config: ClientConfig,
     default_headers: bool,
     allow_redirects: bool,
-    max_redirects: usize,
+    redirect: RedirectPolicy,
 }
 
 impl Default for ClientBuilder {
@@ -30,11 +31,16 @@ impl ClientBuilder {
         ClientBuilder {
             default_headers: true,
             allow_redirects: true,
-            max_redirects: 10,
+            redirect: RedirectPolicy::default(),
             config: ClientConfig {
                 headers: HeaderMap::new(),
+                sensitive: default_sensitive(),
                 timeout: Millis(5_000),
-                connector: Box::new(ConnectorWrapper(Connector::default().finish())),
+                signer: None,
+                decompress_limit: usize::MAX,
+                retry: None,
+                redirect: None,
+                connector: Rc::new(ConnectorWrapper(Connector::default().finish())),
             },
         }
     }
@@ -44,7 +50,7 @@ impl ClientBuilder {
     where
         T: Service<Connect, Response = Connection, Error = ConnectError> + 'static,
     {
-        self.config.connector = Box::new(ConnectorWrapper(connector));
+        self.config.connector = Rc::new(ConnectorWrapper(connector));
         self
     }
 
@@ -63,6 +69,15 @@ impl ClientBuilder {
         self
     }
 
+    /// Set retry policy for requests.
+    ///
+    /// Policy could be overridden per request with `ClientRequest::retry()`
+    /// method. By default requests are not retried.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.config.retry = Some(Rc::new(policy));
+        self
+    }
+
     /// Do not follow redirects.
     ///
     /// Redirects are allowed by default.
@@ -75,12 +90,21 @@ impl ClientBuilder {
     ///
     /// Max redirects is set to 10 by default.
     pub fn max_redirects(mut self, num: usize) -> Self {
-        self.max_redirects = num;
+        self.redirect = self.redirect.max_redirects(num);
+        self
+    }
+
+    /// Set redirect policy.
+    ///
+    /// Redirects are followed with default policy if not set.
+    pub fn redirect(mut self, policy: RedirectPolicy) -> Self {
+        self.allow_redirects = true;
+        self.redirect = policy;
         self
     }
 
     /// Do not add default request headers.
-    /// By default `Date` and `User-Agent` headers are set.
+    /// By default `User-Agent` header is set.
     pub fn no_default_headers(mut self) -> Self {
         self.default_headers = false;
         self
@@ -107,6 +131,29 @@ impl ClientBuilder {
         self
     }
 
+    /// Mark header as sensitive.
+    ///
+    /// Values of sensitive headers are marked with `HeaderValue::set_sensitive()`
+    /// for requests and responses, so they are masked in debug output and
+    /// never get indexed by http/2 header compression. `Authorization`,
+    /// `Proxy-Authorization`, `Cookie` and `Set-Cookie` headers are
+    /// sensitive by default.
+    pub fn sensitive_header<K>(mut self, key: K) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: fmt::Debug,
+    {
+        match HeaderName::try_from(key) {
+            Ok(key) => {
+                if !self.config.sensitive.contains(&key) {
+                    Rc::make_mut(&mut self.config.sensitive).push(key);
+                }
+            }
+            Err(e) => log::error!("Header name error: {:?}", e),
+        }
+        self
+    }
+
     /// Set client wide HTTP basic authorization header
     pub fn basic_auth<U>(self, username: U, password: Option<&str>) -> Self
     where
@@ -130,8 +177,37 @@ impl ClientBuilder {
         self.header(header::AUTHORIZATION, format!("Bearer {}", token))
     }
 
+    /// Set request signer.
+    ///
+    /// Signer is applied to every request right before it is sent.
+    pub fn signer<T: RequestSigner + 'static>(mut self, signer: T) -> Self {
+        self.config.signer = Some(Rc::new(signer));
+        self
+    }
+
+    /// Set max size of decompressed response payload.
+    ///
+    /// Decompressed payload stream returns `PayloadError::Overflow` error
+    /// if size of decoded payload exceeds limit. By default size is not limited.
+    pub fn decompress_limit(mut self, limit: usize) -> Self {
+        self.config.decompress_limit = limit;
+        self
+    }
+
     /// Finish build process and create `Client` instance.
-    pub fn finish(self) -> Client {
+    pub fn finish(mut self) -> Client {
+        if self.default_headers && !self.config.headers.contains_key(header::USER_AGENT) {
+            self.config.headers.insert(
+                header::USER_AGENT,
+                HeaderValue::from_static(concat!("ntex/", env!("CARGO_PKG_VERSION"))),
+            );
+        }
+        for name in self.config.sensitive.iter() {
+            self.config.headers.set_sensitive(name);
+        }
+        if self.allow_redirects {
+            self.config.redirect = Some(Rc::new(self.redirect));
+        }
         Client(Rc::new(self.config))
     }
 }
@@ -149,7 +225,7 @@ mod tests {
             .no_default_headers();
         assert!(!builder.allow_redirects);
         assert!(!builder.default_headers);
-        assert_eq!(builder.max_redirects, 10);
+        assert_eq!(builder.redirect.max_redirects, 10);
     }
 
     #[crate::rt_test]
@@ -179,6 +255,51 @@ mod tests {
         );
     }
 
+    #[crate::rt_test]
+    async fn client_default_headers() {
+        let client = ClientBuilder::new().finish();
+        assert!(client.0.headers.contains_key(header::USER_AGENT));
+
+        let client = ClientBuilder::new().no_default_headers().finish();
+        assert!(!client.0.headers.contains_key(header::USER_AGENT));
+
+        let client = ClientBuilder::new()
+            .header(header::USER_AGENT, "test")
+            .finish();
+        assert_eq!(client.0.headers.get(header::USER_AGENT).unwrap(), "test");
+    }
+
+    #[crate::rt_test]
+    async fn client_sensitive_headers() {
+        let client = ClientBuilder::new()
+            .sensitive_header("x-api-key")
+            .header("x-api-key", "s3cr3t")
+            .bearer_auth("t0k3n")
+            .finish();
+        assert!(client.0.headers.get("x-api-key").unwrap().is_sensitive());
+        assert!(client
+            .0
+            .headers
+            .get(header::AUTHORIZATION)
+            .unwrap()
+            .is_sensitive());
+        assert!(!client
+            .0
+            .headers
+            .get(header::USER_AGENT)
+            .unwrap()
+            .is_sensitive());
+
+        let req = client
+            .get("http://localhost/")
+            .header(header::COOKIE, "session=s3cr3t");
+        let repr = format!("{:?}", req);
+        assert!(repr.contains("\"x-api-key\": <REDACTED>"));
+        assert!(repr.contains("\"cookie\": <REDACTED>"));
+        assert!(!repr.contains("s3cr3t"));
+        assert!(!repr.contains("t0k3n"));
+    }
+
     #[crate::rt_test]
     async fn client_bearer_auth() {
         let client = ClientBuilder::new().bearer_auth("someS3cr3tAutht0k3n");
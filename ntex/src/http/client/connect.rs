@@ -1,12 +1,13 @@
-use std::{future::Future, net, pin::Pin};
+use std::{future::Future, pin::Pin};
 
 use crate::http::body::Body;
 use crate::http::RequestHeadType;
+use crate::io::IoBoxed;
 use crate::service::Service;
 
 use super::error::{ConnectError, SendRequestError};
 use super::response::ClientResponse;
-use super::{Connect as ClientConnect, Connection};
+use super::{Connect as ClientConnect, Connection, RequestAddrs};
 
 pub(super) struct ConnectorWrapper<T>(pub(crate) T);
 
@@ -15,8 +16,20 @@ pub(super) trait Connect {
         &self,
         head: RequestHeadType,
         body: Body,
-        addr: Option<net::SocketAddr>,
+        addr: RequestAddrs,
     ) -> Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>>;
+
+    fn open_tunnel(
+        &self,
+        head: RequestHeadType,
+        addr: RequestAddrs,
+    ) -> Pin<
+        Box<
+            dyn Future<
+                Output = Result<(ClientResponse, Option<IoBoxed>), SendRequestError>,
+            >,
+        >,
+    >;
 }
 
 impl<T> Connect for ConnectorWrapper<T>
@@ -28,12 +41,15 @@ where
         &self,
         head: RequestHeadType,
         body: Body,
-        addr: Option<net::SocketAddr>,
+        addr: RequestAddrs,
     ) -> Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>> {
         // connect to the host
         let fut = self.0.call(ClientConnect {
             uri: head.as_ref().uri.clone(),
-            addr,
+            addr: addr.addr,
+            local_addr: addr.local_addr,
+            interface: addr.interface,
+            server_name: addr.server_name,
         });
 
         Box::pin(async move {
@@ -46,4 +62,35 @@ where
                 .map(|(head, payload)| ClientResponse::new(head, payload))
         })
     }
+
+    fn open_tunnel(
+        &self,
+        head: RequestHeadType,
+        addr: RequestAddrs,
+    ) -> Pin<
+        Box<
+            dyn Future<
+                Output = Result<(ClientResponse, Option<IoBoxed>), SendRequestError>,
+            >,
+        >,
+    > {
+        // connect to the host
+        let fut = self.0.call(ClientConnect {
+            uri: head.as_ref().uri.clone(),
+            addr: addr.addr,
+            local_addr: addr.local_addr,
+            interface: addr.interface,
+            server_name: addr.server_name,
+        });
+
+        Box::pin(async move {
+            let connection = fut.await?;
+
+            // send request and take over connection
+            connection
+                .open_tunnel(head)
+                .await
+                .map(|(head, payload, io)| (ClientResponse::new(head, payload), io))
+        })
+    }
 }
@@ -85,4 +85,20 @@ impl Connection {
             }
         }
     }
+
+    pub(super) async fn open_tunnel<H: Into<RequestHeadType>>(
+        mut self,
+        head: H,
+    ) -> Result<(ResponseHead, Payload, Option<IoBoxed>), SendRequestError> {
+        match self.io.take().unwrap() {
+            ConnectionType::H1(io) => {
+                h1proto::open_tunnel(io, head.into(), self.created, self.pool).await
+            }
+            ConnectionType::H2(io) => {
+                self.io = Some(ConnectionType::H2(io));
+                self.release();
+                Err(SendRequestError::TunnelNotSupported)
+            }
+        }
+    }
 }
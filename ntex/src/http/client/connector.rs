@@ -1,7 +1,7 @@
-use std::{rc::Rc, task::Context, task::Poll, time::Duration};
+use std::{net::IpAddr, rc::Rc, task::Context, task::Poll, time::Duration};
 
 use crate::connect::{Connect as TcpConnect, Connector as TcpConnector};
-use crate::http::Uri;
+use crate::http::{HpackConfig, Uri};
 use crate::io::IoBoxed;
 use crate::service::{apply_fn, boxed, Service};
 use crate::time::{Millis, Seconds};
@@ -10,7 +10,7 @@ use crate::util::{Either, Ready};
 
 use super::connection::Connection;
 use super::error::ConnectError;
-use super::pool::ConnectionPool;
+use super::pool::{ConnectionPool, HostStatus, PoolLimits};
 use super::Connect;
 
 #[cfg(feature = "openssl")]
@@ -20,6 +20,7 @@ use crate::connect::openssl::SslConnector;
 use crate::connect::rustls::ClientConfig;
 
 type BoxedConnector = boxed::BoxService<TcpConnect<Uri>, IoBoxed, ConnectError>;
+type BoxedPoolConnector = boxed::BoxService<Connect, IoBoxed, ConnectError>;
 
 /// Manages http client network connectivity.
 ///
@@ -40,6 +41,14 @@ pub struct Connector {
     conn_keep_alive: Duration,
     disconnect_timeout: Millis,
     limit: usize,
+    host_limit: usize,
+    max_idle: usize,
+    max_waiters: usize,
+    hpack: HpackConfig,
+    h2_window: (Option<u32>, Option<u32>),
+    local_addr: Option<IpAddr>,
+    interface: Option<String>,
+    server_name: Option<String>,
     connector: BoxedConnector,
     ssl_connector: Option<BoxedConnector>,
 }
@@ -64,6 +73,14 @@ impl Connector {
             conn_keep_alive: Duration::from_secs(15),
             disconnect_timeout: Millis(3_000),
             limit: 100,
+            host_limit: 0,
+            max_idle: 0,
+            max_waiters: 0,
+            hpack: HpackConfig::default(),
+            h2_window: (None, None),
+            local_addr: None,
+            interface: None,
+            server_name: None,
         };
 
         #[cfg(feature = "openssl")]
@@ -140,6 +157,63 @@ impl Connector {
         self
     }
 
+    /// Set number of simultaneous connections per host.
+    ///
+    /// Host is identified by uri authority. Requests to a host that reached
+    /// the limit wait for available connection without blocking other hosts.
+    /// If limit is 0, the connector has no per-host limit.
+    /// By default per-host limit is not set.
+    ///
+    /// Concurrent requests to http/2 hosts are multiplexed over single
+    /// connection and do not count against limits.
+    pub fn host_limit(mut self, limit: usize) -> Self {
+        self.host_limit = limit;
+        self
+    }
+
+    /// Set max number of idle connections per host.
+    ///
+    /// Least recently used idle connections are closed when
+    /// the number of idle connections exceeds this limit.
+    /// If limit is 0, the connector keeps all idle connections.
+    /// By default idle connections limit is not set.
+    pub fn max_idle(mut self, limit: usize) -> Self {
+        self.max_idle = limit;
+        self
+    }
+
+    /// Set max number of requests waiting for available connection.
+    ///
+    /// If the pool is full and waiters queue reached the limit, new requests
+    /// fail immediately with `ConnectError::PoolOverflow` error.
+    /// If limit is 0, waiters queue is not limited.
+    /// By default waiters queue is not limited.
+    pub fn max_waiters(mut self, limit: usize) -> Self {
+        self.max_waiters = limit;
+        self
+    }
+
+    /// Set HPACK header compression settings for http/2 connections.
+    ///
+    /// By default protocol defaults are used.
+    pub fn hpack(mut self, cfg: HpackConfig) -> Self {
+        self.hpack = cfg;
+        self
+    }
+
+    /// Set initial http/2 flow control window sizes.
+    ///
+    /// `stream` is initial window size of each stream, `connection` is
+    /// initial window size of the whole connection. All requests to the same
+    /// host are multiplexed over single http/2 connection, connection window
+    /// should be large enough for concurrent responses.
+    ///
+    /// By default protocol defaults are used, 65,535 bytes for both windows.
+    pub fn h2_window_size(mut self, stream: u32, connection: u32) -> Self {
+        self.h2_window = (Some(stream), Some(connection));
+        self
+    }
+
     /// Set keep-alive period for opened connection.
     ///
     /// Keep-alive period is the period between connection usage. If
@@ -151,6 +225,14 @@ impl Connector {
         self
     }
 
+    /// Set idle timeout for pooled connections.
+    ///
+    /// Idle connections that are not used for this period are closed.
+    /// This is an alias for `keep_alive()` method.
+    pub fn idle_timeout(self, dur: Seconds) -> Self {
+        self.keep_alive(dur)
+    }
+
     /// Set max lifetime period for connection.
     ///
     /// Connection lifetime is max lifetime of any opened connection
@@ -174,6 +256,37 @@ impl Connector {
         self
     }
 
+    /// Set local address for outgoing connections.
+    ///
+    /// Address could be overridden per request with
+    /// `ClientRequest::local_address()` method.
+    /// By default os selects local address.
+    pub fn local_address(mut self, addr: IpAddr) -> Self {
+        self.local_addr = Some(addr);
+        self
+    }
+
+    /// Bind outgoing connections to network interface.
+    ///
+    /// Interface could be overridden per request with
+    /// `ClientRequest::interface()` method.
+    /// Binding to network interface is supported only on linux.
+    pub fn interface<T: Into<String>>(mut self, interface: T) -> Self {
+        self.interface = Some(interface.into());
+        self
+    }
+
+    /// Set tls server name for outgoing connections.
+    ///
+    /// Server name is used for SNI and certificate verification instead
+    /// of url's host name, for example if server is dialed by ip address.
+    /// Server name could be overridden per request with
+    /// `ClientRequest::server_name()` method.
+    pub fn server_name<T: Into<String>>(mut self, name: T) -> Self {
+        self.server_name = Some(name.into());
+        self
+    }
+
     /// Use custom connector to open un-secured connections.
     pub fn connector<Io, T>(mut self, connector: T) -> Self
     where
@@ -207,34 +320,51 @@ impl Connector {
     /// Finish configuration process and create connector service.
     /// The Connector builder always concludes by calling `finish()` last in
     /// its combinator chain.
-    pub fn finish(
-        self,
-    ) -> impl Service<Connect, Response = Connection, Error = ConnectError> + Clone {
-        let tcp_service = connector(self.connector, self.timeout, self.disconnect_timeout);
+    pub fn finish(self) -> ConnectorService {
+        let bind = (self.local_addr, self.interface, self.server_name);
+        let limits = PoolLimits {
+            limit: self.limit,
+            host_limit: self.host_limit,
+            max_idle: self.max_idle,
+            max_waiters: self.max_waiters,
+        };
+        let tcp_service = connector(
+            self.connector,
+            self.timeout,
+            self.disconnect_timeout,
+            bind.clone(),
+        );
 
         let ssl_pool = if let Some(ssl_connector) = self.ssl_connector {
-            let srv = connector(ssl_connector, self.timeout, self.disconnect_timeout);
-            Some(ConnectionPool::new(
-                srv,
-                self.conn_lifetime,
-                self.conn_keep_alive,
-                self.disconnect_timeout,
-                self.limit,
-            ))
+            let srv = connector(ssl_connector, self.timeout, self.disconnect_timeout, bind);
+            Some(
+                ConnectionPool::new(
+                    srv,
+                    self.conn_lifetime,
+                    self.conn_keep_alive,
+                    self.disconnect_timeout,
+                    limits,
+                )
+                .hpack(self.hpack.clone())
+                .h2_window_size(self.h2_window.0, self.h2_window.1)
+                .probe_h2(),
+            )
         } else {
             None
         };
 
-        Rc::new(InnerConnector {
+        ConnectorService(Rc::new(InnerConnector {
             tcp_pool: ConnectionPool::new(
                 tcp_service,
                 self.conn_lifetime,
                 self.conn_keep_alive,
                 self.disconnect_timeout,
-                self.limit,
-            ),
+                limits,
+            )
+            .hpack(self.hpack)
+            .h2_window_size(self.h2_window.0, self.h2_window.1),
             ssl_pool,
-        })
+        }))
     }
 }
 
@@ -242,46 +372,66 @@ fn connector(
     connector: BoxedConnector,
     timeout: Millis,
     disconnect_timeout: Millis,
-) -> impl Service<Connect, Response = IoBoxed, Error = ConnectError, Future = impl Unpin> + Unpin
-{
-    TimeoutService::new(
-        timeout,
-        apply_fn(connector, |msg: Connect, srv| {
-            srv.call(TcpConnect::new(msg.uri).set_addr(msg.addr))
-        })
-        .map(move |io: IoBoxed| {
-            io.set_disconnect_timeout(disconnect_timeout);
-            io
-        })
-        .map_err(ConnectError::from),
+    (local_addr, interface, server_name): (Option<IpAddr>, Option<String>, Option<String>),
+) -> BoxedPoolConnector {
+    boxed::service(
+        TimeoutService::new(
+            timeout,
+            apply_fn(connector, move |msg: Connect, srv| {
+                srv.call(
+                    TcpConnect::new(msg.uri)
+                        .set_addr(msg.addr)
+                        .set_local_addr(msg.local_addr.or(local_addr))
+                        .set_interface(msg.interface.or_else(|| interface.clone()))
+                        .set_server_name(msg.server_name.or_else(|| server_name.clone())),
+                )
+            })
+            .map(move |io: IoBoxed| {
+                io.set_disconnect_timeout(disconnect_timeout);
+                io
+            })
+            .map_err(ConnectError::from),
+        )
+        .map_err(|e| match e {
+            TimeoutError::Service(e) => e,
+            TimeoutError::Timeout => ConnectError::Timeout,
+        }),
     )
-    .map_err(|e| match e {
-        TimeoutError::Service(e) => e,
-        TimeoutError::Timeout => ConnectError::Timeout,
-    })
 }
 
-struct InnerConnector<T> {
-    tcp_pool: ConnectionPool<T>,
-    ssl_pool: Option<ConnectionPool<T>>,
+/// Http client connector service
+///
+/// Service is created by `Connector::finish()` method.
+#[derive(Clone)]
+pub struct ConnectorService(Rc<InnerConnector>);
+
+impl ConnectorService {
+    /// Snapshot of connection pool usage per host.
+    pub fn pool_status(&self) -> Vec<HostStatus> {
+        let mut status = Vec::new();
+        self.0.tcp_pool.status(false, &mut status);
+        if let Some(ref pool) = self.0.ssl_pool {
+            pool.status(true, &mut status);
+        }
+        status
+    }
+}
+
+struct InnerConnector {
+    tcp_pool: ConnectionPool<BoxedPoolConnector>,
+    ssl_pool: Option<ConnectionPool<BoxedPoolConnector>>,
 }
 
-impl<T> Service<Connect> for InnerConnector<T>
-where
-    T: Service<Connect, Response = IoBoxed, Error = ConnectError> + Unpin + 'static,
-    T::Future: Unpin,
-{
-    type Response = <ConnectionPool<T> as Service<Connect>>::Response;
+impl Service<Connect> for ConnectorService {
+    type Response = Connection;
     type Error = ConnectError;
-    type Future = Either<
-        <ConnectionPool<T> as Service<Connect>>::Future,
-        Ready<Self::Response, Self::Error>,
-    >;
+    type Future =
+        Either<boxed::BoxFuture<Connection, ConnectError>, Ready<Connection, ConnectError>>;
 
     #[inline]
     fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        let ready = self.tcp_pool.poll_ready(cx)?.is_ready();
-        let ready = if let Some(ref ssl_pool) = self.ssl_pool {
+        let ready = self.0.tcp_pool.poll_ready(cx)?.is_ready();
+        let ready = if let Some(ref ssl_pool) = self.0.ssl_pool {
             ssl_pool.poll_ready(cx)?.is_ready() && ready
         } else {
             ready
@@ -295,8 +445,9 @@ where
 
     #[inline]
     fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
-        let tcp_ready = self.tcp_pool.poll_shutdown(cx, is_error).is_ready();
+        let tcp_ready = self.0.tcp_pool.poll_shutdown(cx, is_error).is_ready();
         let ssl_ready = self
+            .0
             .ssl_pool
             .as_ref()
             .map(|pool| pool.poll_shutdown(cx, is_error).is_ready())
@@ -311,13 +462,13 @@ where
     fn call(&self, req: Connect) -> Self::Future {
         match req.uri.scheme_str() {
             Some("https") | Some("wss") => {
-                if let Some(ref conn) = self.ssl_pool {
+                if let Some(ref conn) = self.0.ssl_pool {
                     Either::Left(conn.call(req))
                 } else {
                     Either::Right(Ready::Err(ConnectError::SslIsNotSupported))
                 }
             }
-            _ => Either::Left(self.tcp_pool.call(req)),
+            _ => Either::Left(self.0.tcp_pool.call(req)),
         }
     }
 }
@@ -67,6 +67,10 @@ pub enum ConnectError {
     /// Unresolved host name
     #[display(fmt = "Connector received `Connect` method with unresolved host")]
     Unresolved,
+
+    /// Connection pool waiters queue is full
+    #[display(fmt = "Connection pool waiters queue is full")]
+    PoolOverflow,
 }
 
 impl std::error::Error for ConnectError {}
@@ -129,6 +133,9 @@ pub enum SendRequestError {
     /// Tunnels are not supported for http2 connection
     #[display(fmt = "Tunnels are not supported for http2 connection")]
     TunnelNotSupported,
+    /// Max number of redirects is exceeded
+    #[display(fmt = "Too many redirects")]
+    TooManyRedirects,
     /// Error sending request body
     Error(Box<dyn Error>),
 }
@@ -1,4 +1,6 @@
-use std::{convert::TryFrom, error::Error, fmt, net, rc::Rc};
+use std::{convert::TryFrom, error::Error, fmt, rc::Rc};
+
+use tok_io::io::AsyncRead;
 
 use crate::http::body::Body;
 use crate::http::error::HttpError;
@@ -6,17 +8,19 @@ use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
 use crate::http::{Method, RequestHead, RequestHeadType, Uri};
 use crate::{time::Millis, util::Bytes, Stream};
 
+use super::multipart::Form;
 use super::sender::SendClientRequest;
-use super::ClientConfig;
+use super::{ClientConfig, RequestAddrs, RetryPolicy};
 
 /// `FrozenClientRequest` struct represents clonable client request.
 /// It could be used to send same request multiple times.
 #[derive(Clone)]
 pub struct FrozenClientRequest {
     pub(super) head: Rc<RequestHead>,
-    pub(super) addr: Option<net::SocketAddr>,
+    pub(super) addr: RequestAddrs,
     pub(super) response_decompress: bool,
     pub(super) timeout: Millis,
+    pub(super) retry: Option<Rc<RetryPolicy>>,
     pub(super) config: Rc<ClientConfig>,
 }
 
@@ -42,10 +46,11 @@ impl FrozenClientRequest {
         B: Into<Body>,
     {
         RequestHeadType::Rc(self.head.clone(), None).send_body(
-            self.addr,
+            self.addr.clone(),
             self.response_decompress,
             self.timeout,
-            self.config.as_ref(),
+            self.retry.clone(),
+            &self.config,
             body,
         )
     }
@@ -53,10 +58,11 @@ impl FrozenClientRequest {
     /// Send a json body.
     pub fn send_json<T: serde::Serialize>(&self, value: &T) -> SendClientRequest {
         RequestHeadType::Rc(self.head.clone(), None).send_json(
-            self.addr,
+            self.addr.clone(),
             self.response_decompress,
             self.timeout,
-            self.config.as_ref(),
+            self.retry.clone(),
+            &self.config,
             value,
         )
     }
@@ -64,10 +70,11 @@ impl FrozenClientRequest {
     /// Send an urlencoded body.
     pub fn send_form<T: serde::Serialize>(&self, value: &T) -> SendClientRequest {
         RequestHeadType::Rc(self.head.clone(), None).send_form(
-            self.addr,
+            self.addr.clone(),
             self.response_decompress,
             self.timeout,
-            self.config.as_ref(),
+            self.retry.clone(),
+            &self.config,
             value,
         )
     }
@@ -79,21 +86,48 @@ impl FrozenClientRequest {
         E: Error + 'static,
     {
         RequestHeadType::Rc(self.head.clone(), None).send_stream(
-            self.addr,
+            self.addr.clone(),
             self.response_decompress,
             self.timeout,
-            self.config.as_ref(),
+            &self.config,
             stream,
         )
     }
 
+    /// Send a body read from `AsyncRead` object.
+    pub fn send_reader<R>(&self, reader: R, len: Option<u64>) -> SendClientRequest
+    where
+        R: AsyncRead + Unpin + 'static,
+    {
+        RequestHeadType::Rc(self.head.clone(), None).send_reader(
+            self.addr.clone(),
+            self.response_decompress,
+            self.timeout,
+            &self.config,
+            reader,
+            len,
+        )
+    }
+
+    /// Send a `multipart/form-data` body.
+    pub fn send_multipart(&self, form: Form) -> SendClientRequest {
+        RequestHeadType::Rc(self.head.clone(), None).send_multipart(
+            self.addr.clone(),
+            self.response_decompress,
+            self.timeout,
+            &self.config,
+            form,
+        )
+    }
+
     /// Send an empty body.
     pub fn send(&self) -> SendClientRequest {
         RequestHeadType::Rc(self.head.clone(), None).send(
-            self.addr,
+            self.addr.clone(),
             self.response_decompress,
             self.timeout,
-            self.config.as_ref(),
+            self.retry.clone(),
+            &self.config,
         )
     }
 
@@ -178,10 +212,11 @@ impl FrozenSendBuilder {
         }
 
         RequestHeadType::Rc(self.req.head, Some(self.extra_headers)).send_body(
-            self.req.addr,
+            self.req.addr.clone(),
             self.req.response_decompress,
             self.req.timeout,
-            self.req.config.as_ref(),
+            self.req.retry,
+            &self.req.config,
             body,
         )
     }
@@ -193,10 +228,11 @@ impl FrozenSendBuilder {
         }
 
         RequestHeadType::Rc(self.req.head, Some(self.extra_headers)).send_json(
-            self.req.addr,
+            self.req.addr.clone(),
             self.req.response_decompress,
             self.req.timeout,
-            self.req.config.as_ref(),
+            self.req.retry,
+            &self.req.config,
             value,
         )
     }
@@ -208,10 +244,11 @@ impl FrozenSendBuilder {
         }
 
         RequestHeadType::Rc(self.req.head, Some(self.extra_headers)).send_form(
-            self.req.addr,
+            self.req.addr.clone(),
             self.req.response_decompress,
             self.req.timeout,
-            self.req.config.as_ref(),
+            self.req.retry,
+            &self.req.config,
             value,
         )
     }
@@ -227,14 +264,48 @@ impl FrozenSendBuilder {
         }
 
         RequestHeadType::Rc(self.req.head, Some(self.extra_headers)).send_stream(
-            self.req.addr,
+            self.req.addr.clone(),
             self.req.response_decompress,
             self.req.timeout,
-            self.req.config.as_ref(),
+            &self.req.config,
             stream,
         )
     }
 
+    /// Complete request construction and send a body read from `AsyncRead` object.
+    pub fn send_reader<R>(self, reader: R, len: Option<u64>) -> SendClientRequest
+    where
+        R: AsyncRead + Unpin + 'static,
+    {
+        if let Some(e) = self.err {
+            return e.into();
+        }
+
+        RequestHeadType::Rc(self.req.head, Some(self.extra_headers)).send_reader(
+            self.req.addr.clone(),
+            self.req.response_decompress,
+            self.req.timeout,
+            &self.req.config,
+            reader,
+            len,
+        )
+    }
+
+    /// Complete request construction and send a `multipart/form-data` body.
+    pub fn send_multipart(self, form: Form) -> SendClientRequest {
+        if let Some(e) = self.err {
+            return e.into();
+        }
+
+        RequestHeadType::Rc(self.req.head, Some(self.extra_headers)).send_multipart(
+            self.req.addr.clone(),
+            self.req.response_decompress,
+            self.req.timeout,
+            &self.req.config,
+            form,
+        )
+    }
+
     /// Complete request construction and send an empty body.
     pub fn send(self) -> SendClientRequest {
         if let Some(e) = self.err {
@@ -242,10 +313,11 @@ impl FrozenSendBuilder {
         }
 
         RequestHeadType::Rc(self.req.head, Some(self.extra_headers)).send(
-            self.req.addr,
+            self.req.addr.clone(),
             self.req.response_decompress,
             self.req.timeout,
-            self.req.config.as_ref(),
+            self.req.retry,
+            &self.req.config,
         )
     }
 }
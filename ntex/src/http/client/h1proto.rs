@@ -1,14 +1,15 @@
-use std::{io, io::Write, pin::Pin, task::Context, task::Poll, time::Instant};
+use std::time::Instant;
+use std::{future::Future, io, io::Write, pin::Pin, task::Context, task::Poll};
 
-use crate::http::body::{BodySize, MessageBody};
-use crate::http::error::PayloadError;
+use crate::http::body::{Body, BodySize, MessageBody};
 use crate::http::h1;
 use crate::http::header::{HeaderMap, HeaderValue, HOST};
 use crate::http::message::{RequestHeadType, ResponseHead};
-use crate::http::payload::{Payload, PayloadStream};
+use crate::http::payload::Payload;
+use crate::http::{Method, StatusCode};
 use crate::io::{IoBoxed, RecvError};
-use crate::util::{poll_fn, ready, BufMut, Bytes, BytesMut};
-use crate::Stream;
+use crate::rt::spawn;
+use crate::util::{poll_fn, ready, BufMut, BytesMut};
 
 use super::connection::{Connection, ConnectionType};
 use super::error::{ConnectError, SendRequestError};
@@ -16,7 +17,7 @@ use super::pool::Acquired;
 
 pub(super) async fn send_request<B>(
     io: IoBoxed,
-    mut head: RequestHeadType,
+    head: RequestHeadType,
     body: B,
     created: Instant,
     pool: Option<Acquired>,
@@ -24,6 +25,34 @@ pub(super) async fn send_request<B>(
 where
     B: MessageBody,
 {
+    send(io, head, body, created, pool, false)
+        .await
+        .map(|(head, payload, _)| (head, payload))
+}
+
+/// Send request and take over connection if server switches protocols
+pub(super) async fn open_tunnel(
+    io: IoBoxed,
+    head: RequestHeadType,
+    created: Instant,
+    pool: Option<Acquired>,
+) -> Result<(ResponseHead, Payload, Option<IoBoxed>), SendRequestError> {
+    send(io, head, Body::None, created, pool, true).await
+}
+
+async fn send<B>(
+    io: IoBoxed,
+    mut head: RequestHeadType,
+    body: B,
+    created: Instant,
+    mut pool: Option<Acquired>,
+    tunnel: bool,
+) -> Result<(ResponseHead, Payload, Option<IoBoxed>), SendRequestError>
+where
+    B: MessageBody,
+{
+    let is_connect = head.as_ref().method == Method::CONNECT;
+
     // set request host header
     if !head.as_ref().headers.contains_key(HOST)
         && !head.extra_headers().iter().any(|h| h.contains_key(HOST))
@@ -85,15 +114,27 @@ where
         return Err(SendRequestError::from(ConnectError::Disconnected(None)));
     };
 
+    // switched connection never returns to the pool
+    if head.status == StatusCode::SWITCHING_PROTOCOLS
+        || (is_connect && head.status.is_success())
+    {
+        log::trace!("http1 connection is switched, remove from pool");
+        drop(pool.take());
+        if tunnel {
+            return Ok((head, Payload::None, Some(io)));
+        }
+    }
+
     match codec.message_type() {
         h1::MessageType::None => {
             let force_close = !codec.keepalive();
             release_connection(io, force_close, created, pool);
-            Ok((head, Payload::None))
+            Ok((head, Payload::None, None))
         }
         _ => {
-            let pl: PayloadStream = Box::pin(PlStream::new(io, codec, created, pool));
-            Ok((head, pl.into()))
+            let (tx, pl) = h1::Payload::create(false);
+            spawn(PayloadReader::new(io, codec, tx, created, pool));
+            Ok((head, pl.into(), None))
         }
     }
 }
@@ -124,71 +165,83 @@ where
     Ok(())
 }
 
-pub(super) struct PlStream {
+/// Reads response payload from connection and feeds it to the `Payload`
+struct PayloadReader {
     io: Option<IoBoxed>,
     codec: h1::ClientPayloadCodec,
+    tx: h1::PayloadSender,
     created: Instant,
     pool: Option<Acquired>,
 }
 
-impl PlStream {
+impl PayloadReader {
     fn new(
         io: IoBoxed,
         codec: h1::ClientCodec,
+        tx: h1::PayloadSender,
         created: Instant,
         pool: Option<Acquired>,
     ) -> Self {
-        PlStream {
-            io: Some(io),
-            codec: codec.into_payload_codec(),
+        PayloadReader {
+            tx,
             created,
             pool,
+            io: Some(io),
+            codec: codec.into_payload_codec(),
         }
     }
 }
 
-impl Stream for PlStream {
-    type Item = Result<Bytes, PayloadError>;
+impl Future for PayloadReader {
+    type Output = ();
 
-    fn poll_next(
-        mut self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-    ) -> Poll<Option<Self::Item>> {
-        let mut this = self.as_mut();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.as_mut().get_mut();
         loop {
-            return Poll::Ready(Some(
-                match ready!(this.io.as_ref().unwrap().poll_recv(&this.codec, cx)) {
-                    Ok(chunk) => {
-                        if let Some(chunk) = chunk {
-                            Ok(chunk)
-                        } else {
-                            let io = this.io.take().unwrap();
-                            let force_close = !this.codec.keepalive();
-                            release_connection(
-                                io,
-                                force_close,
-                                this.created,
-                                this.pool.take(),
-                            );
-                            return Poll::Ready(None);
-                        }
-                    }
-                    Err(RecvError::KeepAlive) => {
-                        Err(io::Error::new(io::ErrorKind::Other, "Keep-alive").into())
-                    }
-                    Err(RecvError::Stop) => {
-                        Err(io::Error::new(io::ErrorKind::Other, "Dispatcher stopped")
-                            .into())
+            match this.tx.poll_data_required(cx) {
+                h1::PayloadStatus::Read => (),
+                h1::PayloadStatus::Pause => return Poll::Pending,
+                h1::PayloadStatus::Dropped => {
+                    // payload is not consumed, connection cannot be reused
+                    let io = this.io.take().unwrap();
+                    release_connection(io, true, this.created, this.pool.take());
+                    return Poll::Ready(());
+                }
+            }
+
+            let err = match ready!(this.io.as_ref().unwrap().poll_recv(&this.codec, cx)) {
+                Ok(Some(chunk)) => {
+                    this.tx.feed_data(chunk);
+                    continue;
+                }
+                Ok(None) => {
+                    if let Some(trailers) = this.codec.take_trailers() {
+                        this.tx.feed_trailers(trailers);
                     }
-                    Err(RecvError::WriteBackpressure) => {
-                        ready!(this.io.as_ref().unwrap().poll_flush(cx, false))?;
-                        continue;
+                    this.tx.feed_eof();
+
+                    let io = this.io.take().unwrap();
+                    let force_close = !this.codec.keepalive();
+                    release_connection(io, force_close, this.created, this.pool.take());
+                    return Poll::Ready(());
+                }
+                Err(RecvError::KeepAlive) => io::Error::other("Keep-alive").into(),
+                Err(RecvError::Stop) => io::Error::other("Dispatcher stopped").into(),
+                Err(RecvError::WriteBackpressure) => {
+                    match ready!(this.io.as_ref().unwrap().poll_flush(cx, false)) {
+                        Ok(_) => continue,
+                        Err(err) => err.into(),
                     }
-                    Err(RecvError::Decoder(err)) => Err(err),
-                    Err(RecvError::PeerGone(Some(err))) => Err(err.into()),
-                    Err(RecvError::PeerGone(None)) => return Poll::Ready(None),
-                },
-            ));
+                }
+                Err(RecvError::Decoder(err)) => err,
+                Err(RecvError::PeerGone(Some(err))) => err.into(),
+                Err(RecvError::PeerGone(None)) => {
+                    this.tx.feed_eof();
+                    return Poll::Ready(());
+                }
+            };
+            this.tx.set_error(err);
+            return Poll::Ready(());
         }
     }
 }
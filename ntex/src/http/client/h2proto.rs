@@ -72,14 +72,19 @@ where
         .filter(|(name, _)| !extra_headers.contains_key(*name))
         .chain(extra_headers.iter());
 
-    // copy headers
+    // copy headers, sensitive headers are never indexed
+    let hpack = pool.as_ref().and_then(|pool| pool.hpack());
     for (key, value) in headers {
         match *key {
             CONNECTION | TRANSFER_ENCODING => continue, // http2 specific
             CONTENT_LENGTH if skip_len => continue,
             _ => (),
         }
-        req.headers_mut().append(key, value.clone());
+        if let Some(ref hpack) = hpack {
+            req.headers_mut().append(key, hpack.value(key, value));
+        } else {
+            req.headers_mut().append(key, value.clone());
+        }
     }
 
     let res = poll_fn(|cx| io.poll_ready(cx)).await;
@@ -26,22 +26,33 @@ pub mod error;
 mod frozen;
 mod h1proto;
 mod h2proto;
+pub mod multipart;
 mod pool;
+mod redirect;
 mod request;
 mod response;
+mod retry;
 mod sender;
+mod sign;
 mod test;
 
 pub use self::builder::ClientBuilder;
 pub use self::connection::Connection;
-pub use self::connector::Connector;
+pub use self::connector::{Connector, ConnectorService};
 pub use self::frozen::{FrozenClientRequest, FrozenSendBuilder};
+pub use self::pool::HostStatus;
+pub use self::redirect::RedirectPolicy;
 pub use self::request::ClientRequest;
 pub use self::response::{ClientResponse, JsonBody, MessageBody};
+pub use self::retry::RetryPolicy;
 pub use self::sender::SendClientRequest;
+pub use self::sign::RequestSigner;
+#[cfg(feature = "signing")]
+pub use self::sign::{HmacSigner, SigV4};
 pub use self::test::TestResponse;
 
 use crate::http::error::HttpError;
+use crate::http::header::{self, HeaderName};
 use crate::http::{HeaderMap, Method, RequestHead, Uri};
 use crate::time::Millis;
 
@@ -51,6 +62,18 @@ use self::connect::{Connect as HttpConnect, ConnectorWrapper};
 pub struct Connect {
     pub uri: Uri,
     pub addr: Option<std::net::SocketAddr>,
+    pub local_addr: Option<std::net::IpAddr>,
+    pub interface: Option<String>,
+    pub server_name: Option<String>,
+}
+
+/// Remote and local addresses of the request
+#[derive(Clone, Debug, Default)]
+pub(super) struct RequestAddrs {
+    pub(super) addr: Option<std::net::SocketAddr>,
+    pub(super) local_addr: Option<std::net::IpAddr>,
+    pub(super) interface: Option<String>,
+    pub(super) server_name: Option<String>,
 }
 
 /// An HTTP Client
@@ -74,17 +97,37 @@ pub struct Connect {
 pub struct Client(Rc<ClientConfig>);
 
 pub(self) struct ClientConfig {
-    pub(self) connector: Box<dyn HttpConnect>,
+    pub(self) connector: Rc<dyn HttpConnect>,
     pub(self) headers: HeaderMap,
+    pub(self) sensitive: Rc<Vec<HeaderName>>,
     pub(self) timeout: Millis,
+    pub(self) signer: Option<Rc<dyn RequestSigner>>,
+    pub(self) decompress_limit: usize,
+    pub(self) retry: Option<Rc<RetryPolicy>>,
+    pub(self) redirect: Option<Rc<RedirectPolicy>>,
+}
+
+/// Headers with credentials, values are redacted by default
+fn default_sensitive() -> Rc<Vec<HeaderName>> {
+    Rc::new(vec![
+        header::AUTHORIZATION,
+        header::PROXY_AUTHORIZATION,
+        header::COOKIE,
+        header::SET_COOKIE,
+    ])
 }
 
 impl Default for Client {
     fn default() -> Self {
         Client(Rc::new(ClientConfig {
-            connector: Box::new(ConnectorWrapper(Connector::default().finish())),
+            connector: Rc::new(ConnectorWrapper(Connector::default().finish())),
             headers: HeaderMap::new(),
+            sensitive: default_sensitive(),
             timeout: Millis(5_000),
+            signer: None,
+            decompress_limit: usize::MAX,
+            retry: None,
+            redirect: Some(Rc::new(RedirectPolicy::default())),
         }))
     }
 }
@@ -0,0 +1,392 @@
+//! Multipart form builder (`multipart/form-data`)
+use std::task::{Context, Poll};
+use std::{collections::VecDeque, error::Error, fmt};
+
+use mime::Mime;
+use nanorand::{Rng, WyRand};
+use tok_io::io::AsyncRead;
+
+use crate::http::body::{Body, BodySize, BodyStream, MessageBody};
+use crate::http::header::{HeaderMap, HeaderName, HeaderValue};
+use crate::util::{Bytes, BytesMut};
+use crate::Stream;
+
+use super::sender::{ReaderBody, SizedBody};
+
+/// Multipart form
+///
+/// Form consists of text fields, in-memory and streaming parts.
+/// Form is sent with known `Content-Length` if size of all parts is known,
+/// otherwise chunked transfer encoding is used.
+///
+/// ```rust,no_run
+/// use ntex::http::client::{multipart::{Form, Part}, Client};
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let form = Form::new()
+///         .text("title", "report")
+///         .part(
+///             Part::bytes("file", "content")
+///                 .filename("report.txt")
+///                 .content_type(mime::TEXT_PLAIN),
+///         );
+///
+///     let res = Client::new()
+///         .post("http://www.rust-lang.org")
+///         .send_multipart(form)
+///         .await;
+/// }
+/// ```
+pub struct Form {
+    boundary: String,
+    parts: Vec<(Bytes, Body)>,
+}
+
+impl Form {
+    /// Create new form with random boundary
+    pub fn new() -> Self {
+        let mut rng = WyRand::new();
+        let boundary = (0..32)
+            .map(|_| {
+                let n = rng.generate_range(0..36u8);
+                (if n < 10 { b'0' + n } else { b'a' + n - 10 }) as char
+            })
+            .collect::<String>();
+        Self::with_boundary(boundary)
+    }
+
+    /// Create new form with specified boundary
+    pub fn with_boundary<T: Into<String>>(boundary: T) -> Self {
+        Form {
+            boundary: boundary.into(),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Add text field
+    pub fn text<N: Into<String>, V: Into<String>>(self, name: N, value: V) -> Self {
+        self.part(Part::text(name, value))
+    }
+
+    /// Add part to the form
+    pub fn part(mut self, part: Part) -> Self {
+        let mut buf = BytesMut::with_capacity(128);
+        if !self.parts.is_empty() {
+            buf.extend_from_slice(b"\r\n");
+        }
+        buf.extend_from_slice(b"--");
+        buf.extend_from_slice(self.boundary.as_bytes());
+        buf.extend_from_slice(b"\r\nContent-Disposition: form-data; name=\"");
+        buf.extend_from_slice(escape(&part.name).as_bytes());
+        buf.extend_from_slice(b"\"");
+        if let Some(ref filename) = part.filename {
+            buf.extend_from_slice(b"; filename=\"");
+            buf.extend_from_slice(escape(filename).as_bytes());
+            buf.extend_from_slice(b"\"");
+        }
+        buf.extend_from_slice(b"\r\n");
+
+        let content_type = if part.filename.is_some() {
+            Some(part.content_type.unwrap_or(mime::APPLICATION_OCTET_STREAM))
+        } else {
+            part.content_type
+        };
+        if let Some(ct) = content_type {
+            buf.extend_from_slice(b"Content-Type: ");
+            buf.extend_from_slice(ct.as_ref().as_bytes());
+            buf.extend_from_slice(b"\r\n");
+        }
+        for (key, value) in part.headers.iter() {
+            buf.extend_from_slice(key.as_str().as_bytes());
+            buf.extend_from_slice(b": ");
+            buf.extend_from_slice(value.as_bytes());
+            buf.extend_from_slice(b"\r\n");
+        }
+        buf.extend_from_slice(b"\r\n");
+
+        self.parts.push((buf.freeze(), part.body));
+        self
+    }
+
+    /// Form boundary
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Value of `Content-Type` header for the form
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Size of encoded form, if size of all parts is known
+    pub fn content_length(&self) -> Option<u64> {
+        let mut size = self.tail().len() as u64;
+        for (hdr, body) in &self.parts {
+            size += hdr.len() as u64;
+            match body.size() {
+                BodySize::Sized(len) => size += len,
+                BodySize::None | BodySize::Empty => (),
+                BodySize::Stream => return None,
+            }
+        }
+        Some(size)
+    }
+
+    /// Convert form to message body
+    ///
+    /// Body could be used as response body, `Content-Type` header must be
+    /// set to `Form::content_type()` value.
+    pub fn into_body(self) -> Body {
+        let size = match self.content_length() {
+            Some(size) => BodySize::Sized(size),
+            None => BodySize::Stream,
+        };
+        let tail = self.tail();
+        Body::from_message(FormBody {
+            size,
+            tail: Some(tail),
+            current: None,
+            parts: self.parts.into_iter().collect(),
+        })
+    }
+
+    fn tail(&self) -> Bytes {
+        let nl = if self.parts.is_empty() { "" } else { "\r\n" };
+        Bytes::from(format!("{}--{}--\r\n", nl, self.boundary))
+    }
+}
+
+impl Default for Form {
+    fn default() -> Self {
+        Form::new()
+    }
+}
+
+impl fmt::Debug for Form {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Form")
+            .field("boundary", &self.boundary)
+            .field("parts", &self.parts.len())
+            .finish()
+    }
+}
+
+/// Part of the multipart form
+pub struct Part {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+    headers: HeaderMap,
+    body: Body,
+}
+
+impl Part {
+    fn new<N: Into<String>>(name: N, body: Body) -> Self {
+        Part {
+            body,
+            name: name.into(),
+            filename: None,
+            content_type: None,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Create text part
+    pub fn text<N: Into<String>, V: Into<String>>(name: N, value: V) -> Self {
+        Part::new(name, Body::Bytes(Bytes::from(value.into())))
+    }
+
+    /// Create part from in-memory data
+    pub fn bytes<N: Into<String>, B: Into<Bytes>>(name: N, data: B) -> Self {
+        Part::new(name, Body::Bytes(data.into()))
+    }
+
+    /// Create streaming part
+    ///
+    /// If `len` is not provided, form is sent with chunked transfer encoding.
+    pub fn stream<N, S, E>(name: N, stream: S, len: Option<u64>) -> Self
+    where
+        N: Into<String>,
+        S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
+        E: Error + 'static,
+    {
+        let body = if let Some(len) = len {
+            Body::from_message(SizedBody(len, BodyStream::new(stream)))
+        } else {
+            Body::from_message(BodyStream::new(stream))
+        };
+        Part::new(name, body)
+    }
+
+    /// Create part that reads data from `AsyncRead` object, i.e. file
+    ///
+    /// If `len` is not provided, form is sent with chunked transfer encoding.
+    pub fn reader<N, R>(name: N, reader: R, len: Option<u64>) -> Self
+    where
+        N: Into<String>,
+        R: AsyncRead + Unpin + 'static,
+    {
+        Part::new(name, Body::from_message(ReaderBody::new(reader, len)))
+    }
+
+    /// Set file name of the part
+    ///
+    /// File parts without content type are sent as `application/octet-stream`.
+    pub fn filename<F: Into<String>>(mut self, filename: F) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Set content type of the part
+    pub fn content_type(mut self, mime: Mime) -> Self {
+        self.content_type = Some(mime);
+        self
+    }
+
+    /// Append header to the part
+    pub fn header(mut self, key: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(key, value);
+        self
+    }
+}
+
+impl fmt::Debug for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Part")
+            .field("name", &self.name)
+            .field("filename", &self.filename)
+            .field("content_type", &self.content_type)
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
+
+/// Escape field name or file name, same way browsers do
+fn escape(val: &str) -> String {
+    val.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+struct FormBody {
+    size: BodySize,
+    parts: VecDeque<(Bytes, Body)>,
+    current: Option<Body>,
+    tail: Option<Bytes>,
+}
+
+impl MessageBody for FormBody {
+    fn size(&self) -> BodySize {
+        self.size
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if let Some(ref mut body) = self.current {
+            match body.poll_next_chunk(cx) {
+                Poll::Ready(None) => self.current = None,
+                res => return res,
+            }
+        }
+        if let Some((hdr, body)) = self.parts.pop_front() {
+            self.current = Some(body);
+            Poll::Ready(Some(Ok(hdr)))
+        } else {
+            Poll::Ready(self.tail.take().map(Ok))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::poll_fn;
+
+    async fn read_body(body: Body) -> Bytes {
+        let mut body = body;
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = poll_fn(|cx| body.poll_next_chunk(cx)).await {
+            buf.extend_from_slice(&chunk.unwrap());
+        }
+        buf.freeze()
+    }
+
+    #[crate::rt_test]
+    async fn test_form() {
+        let form = Form::with_boundary("b0undary")
+            .text("na\"me", "value")
+            .part(Part::bytes("file", "data").filename("a.txt"))
+            .part(
+                Part::text("json", "{}")
+                    .content_type(mime::APPLICATION_JSON)
+                    .header(
+                        HeaderName::from_static("x-test"),
+                        HeaderValue::from_static("1"),
+                    ),
+            );
+        assert_eq!(form.boundary(), "b0undary");
+        assert_eq!(
+            form.content_type(),
+            "multipart/form-data; boundary=b0undary"
+        );
+        assert!(format!("{:?}", form).contains("Form"));
+
+        let expected = "--b0undary\r\n\
+            Content-Disposition: form-data; name=\"na%22me\"\r\n\r\n\
+            value\r\n\
+            --b0undary\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+            Content-Type: application/octet-stream\r\n\r\n\
+            data\r\n\
+            --b0undary\r\n\
+            Content-Disposition: form-data; name=\"json\"\r\n\
+            Content-Type: application/json\r\n\
+            x-test: 1\r\n\r\n\
+            {}\r\n\
+            --b0undary--\r\n";
+        assert_eq!(form.content_length(), Some(expected.len() as u64));
+        let body = form.into_body();
+        assert_eq!(body.size(), BodySize::Sized(expected.len() as u64));
+        assert_eq!(read_body(body).await, Bytes::from(expected));
+
+        let form = Form::default();
+        assert_eq!(form.boundary().len(), 32);
+        let boundary = form.boundary().to_string();
+        assert_eq!(
+            read_body(form.into_body()).await,
+            Bytes::from(format!("--{}--\r\n", boundary))
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_streaming_parts() {
+        let (tx, rx) = crate::channel::mpsc::channel::<Result<Bytes, std::io::Error>>();
+        tx.send(Ok(Bytes::from_static(b"chunk1"))).unwrap();
+        tx.send(Ok(Bytes::from_static(b"chunk2"))).unwrap();
+        drop(tx);
+
+        let form = Form::with_boundary("b")
+            .part(Part::stream("stream", rx, None).filename("s.bin"))
+            .part(Part::reader("reader", &b"reader data"[..], Some(11)));
+        assert_eq!(form.content_length(), None);
+        let body = form.into_body();
+        assert_eq!(body.size(), BodySize::Stream);
+        assert_eq!(
+            read_body(body).await,
+            Bytes::from_static(
+                b"--b\r\nContent-Disposition: form-data; name=\"stream\"; filename=\"s.bin\"\r\n\
+                  Content-Type: application/octet-stream\r\n\r\n\
+                  chunk1chunk2\r\n\
+                  --b\r\nContent-Disposition: form-data; name=\"reader\"\r\n\r\n\
+                  reader data\r\n\
+                  --b--\r\n"
+            )
+        );
+
+        let form = Form::with_boundary("b").part(Part::reader("r", &b"12"[..], Some(2)));
+        assert!(form.content_length().is_some());
+    }
+}
@@ -1,12 +1,13 @@
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
-use std::{cell::RefCell, collections::VecDeque, future::Future, pin::Pin, rc::Rc};
+use std::{cell::RefCell, collections::VecDeque, future::Future, net, pin::Pin, rc::Rc};
 
-use h2::client::{Builder, Connection as H2Connection, SendRequest};
+use h2::client::{Connection as H2Connection, SendRequest};
 use http::uri::Authority;
 use ntex_tls::types::HttpProtocol;
 
 use crate::channel::pool;
+use crate::http::config::HpackConfig;
 use crate::io::IoBoxed;
 use crate::rt::spawn;
 use crate::service::Service;
@@ -21,14 +22,46 @@ use super::Connect;
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 pub(super) struct Key {
     authority: Authority,
+    local_addr: Option<net::IpAddr>,
+    interface: Option<String>,
+    server_name: Option<String>,
 }
 
-impl From<Authority> for Key {
-    fn from(authority: Authority) -> Key {
-        Key { authority }
+impl Key {
+    fn new(req: &Connect) -> Option<Key> {
+        req.uri.authority().map(|authority| Key {
+            authority: authority.clone(),
+            local_addr: req.local_addr,
+            interface: req.interface.clone(),
+            server_name: req.server_name.clone(),
+        })
     }
 }
 
+/// Connection pool usage snapshot for a single host
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostStatus {
+    /// Host authority
+    pub authority: Authority,
+    /// Connection is secure
+    pub secure: bool,
+    /// Number of idle connections
+    pub idle: usize,
+    /// Number of acquired and opening connections
+    pub in_flight: usize,
+    /// Number of requests waiting for a connection
+    pub waiting: usize,
+}
+
+/// Connection pool limits
+#[derive(Copy, Clone, Debug)]
+pub(super) struct PoolLimits {
+    pub(super) limit: usize,
+    pub(super) host_limit: usize,
+    pub(super) max_idle: usize,
+    pub(super) max_waiters: usize,
+}
+
 type Waiter = pool::Sender<Result<Connection, ConnectError>>;
 type WaiterReceiver = pool::Receiver<Result<Connection, ConnectError>>;
 
@@ -45,19 +78,25 @@ where
         conn_lifetime: Duration,
         conn_keep_alive: Duration,
         disconnect_timeout: Millis,
-        limit: usize,
+        limits: PoolLimits,
     ) -> Self {
         let connector = Rc::new(connector);
         let inner = Rc::new(RefCell::new(Inner {
             conn_lifetime,
             conn_keep_alive,
             disconnect_timeout,
-            limit,
+            limits,
             acquired: 0,
+            hosts: HashMap::default(),
             waiters: VecDeque::new(),
             available: HashMap::default(),
             pool: pool::new(),
             waker: LocalWaker::new(),
+            hpack: Rc::new(HpackConfig::default()),
+            h2: HashMap::default(),
+            h2_window: (None, None),
+            h2_id: 0,
+            h2_probe: false,
         }));
 
         // start pool support future
@@ -68,6 +107,30 @@ where
 
         ConnectionPool(connector, inner)
     }
+
+    /// Set HPACK settings for http/2 connections
+    pub(super) fn hpack(self, hpack: HpackConfig) -> Self {
+        self.1.borrow_mut().hpack = Rc::new(hpack);
+        self
+    }
+
+    /// Connections could negotiate http/2, requests to unknown hosts
+    /// wait for first connection
+    pub(super) fn probe_h2(self) -> Self {
+        self.1.borrow_mut().h2_probe = true;
+        self
+    }
+
+    /// Set initial http/2 stream and connection flow control window sizes
+    pub(super) fn h2_window_size(self, stream: Option<u32>, conn: Option<u32>) -> Self {
+        self.1.borrow_mut().h2_window = (stream, conn);
+        self
+    }
+
+    /// Collect pool usage per host
+    pub(super) fn status(&self, secure: bool, status: &mut Vec<HostStatus>) {
+        self.1.borrow().status(secure, status)
+    }
 }
 
 impl<T> Drop for ConnectionPool<T> {
@@ -108,8 +171,8 @@ where
         let inner = self.1.clone();
 
         Box::pin(async move {
-            let key = if let Some(authority) = req.uri.authority() {
-                authority.clone().into()
+            let key = if let Some(key) = Key::new(&req) {
+                key
             } else {
                 return Err(ConnectError::Unresolved);
             };
@@ -123,9 +186,27 @@ where
                     Ok(Connection::new(
                         io,
                         created,
-                        Some(Acquired(key, Some(inner))),
+                        Some(Acquired(key, Some(inner), false)),
                     ))
                 }
+                // multiplex request over shared http/2 connection
+                Acquire::Shared(io, created) => {
+                    trace!("Use shared http/2 connection for {:?}", req.uri);
+                    Ok(Connection::new(
+                        ConnectionType::H2(io),
+                        created,
+                        Some(Acquired::shared(key, inner)),
+                    ))
+                }
+                // http/2 connection is opening, wait for handshake
+                Acquire::Opening => {
+                    trace!("Waiting for http/2 connection for {:?}", req.uri);
+                    let rx = inner.borrow_mut().wait_for_h2(&key, req);
+                    match rx.await {
+                        Err(_) => Err(ConnectError::Disconnected(None)),
+                        Ok(res) => res,
+                    }
+                }
                 // open new tcp connection
                 Acquire::Available => {
                     trace!("Connecting to {:?}", req.uri);
@@ -143,7 +224,7 @@ where
                         "Pool is full, waiting for available connections for {:?}",
                         req.uri
                     );
-                    let rx = inner.borrow_mut().wait_for(req);
+                    let rx = inner.borrow_mut().wait_for(req)?;
                     match rx.await {
                         Err(_) => Err(ConnectError::Disconnected(None)),
                         Ok(res) => res,
@@ -156,10 +237,31 @@ where
 
 enum Acquire {
     Acquired(ConnectionType, Instant),
+    Shared(SendRequest<Bytes>, Instant),
+    Opening,
     Available,
     NotAvailable,
 }
 
+/// Http/2 connection state of the host
+enum H2State {
+    /// Connection is opening, requests wait for handshake
+    Opening(Vec<(Connect, Waiter)>),
+    /// Connection is ready, requests are multiplexed over it
+    Ready(SharedConnection),
+    /// Host supports http/2, but there is no open connection
+    Closed,
+    /// Host does not support http/2
+    Http1,
+}
+
+struct SharedConnection {
+    id: usize,
+    io: SendRequest<Bytes>,
+    used: Instant,
+    created: Instant,
+}
+
 struct AvailableConnection {
     io: ConnectionType,
     used: Instant,
@@ -170,32 +272,117 @@ pub(super) struct Inner {
     conn_lifetime: Duration,
     conn_keep_alive: Duration,
     disconnect_timeout: Millis,
-    limit: usize,
+    limits: PoolLimits,
     acquired: usize,
+    hosts: HashMap<Key, usize>,
     available: HashMap<Key, VecDeque<AvailableConnection>>,
     waiters: VecDeque<(Key, Connect, Waiter)>,
     waker: LocalWaker,
     pool: pool::Pool<Result<Connection, ConnectError>>,
+    hpack: Rc<HpackConfig>,
+    h2: HashMap<Key, H2State>,
+    h2_window: (Option<u32>, Option<u32>),
+    h2_id: usize,
+    h2_probe: bool,
 }
 
 impl Inner {
-    fn reserve(&mut self) {
+    fn reserve(&mut self, key: &Key) {
         self.acquired += 1;
+        *self.hosts.entry(key.clone()).or_insert(0) += 1;
     }
 
-    fn release(&mut self) {
+    fn release(&mut self, key: &Key) {
         self.acquired -= 1;
+        if let Some(n) = self.hosts.get_mut(key) {
+            *n -= 1;
+            if *n == 0 {
+                self.hosts.remove(key);
+            }
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.limits.limit > 0 && self.acquired >= self.limits.limit
+    }
+
+    fn is_host_full(&self, key: &Key) -> bool {
+        self.limits.host_limit > 0
+            && self
+                .hosts
+                .get(key)
+                .map(|n| *n >= self.limits.host_limit)
+                .unwrap_or(false)
+    }
+
+    fn status(&self, secure: bool, status: &mut Vec<HostStatus>) {
+        fn entry<'a>(
+            status: &'a mut Vec<HostStatus>,
+            start: usize,
+            key: &Key,
+            secure: bool,
+        ) -> &'a mut HostStatus {
+            let idx = status[start..]
+                .iter()
+                .position(|st| st.authority == key.authority);
+            if let Some(idx) = idx {
+                &mut status[start + idx]
+            } else {
+                status.push(HostStatus {
+                    secure,
+                    authority: key.authority.clone(),
+                    idle: 0,
+                    in_flight: 0,
+                    waiting: 0,
+                });
+                status.last_mut().unwrap()
+            }
+        }
+
+        let start = status.len();
+        for (key, conns) in &self.available {
+            if !conns.is_empty() {
+                entry(status, start, key, secure).idle += conns.len();
+            }
+        }
+        for (key, state) in &self.h2 {
+            match state {
+                H2State::Ready(_) => entry(status, start, key, secure).idle += 1,
+                H2State::Opening(waiters) => {
+                    let n = waiters.iter().filter(|(_, tx)| !tx.is_canceled()).count();
+                    if n > 0 {
+                        entry(status, start, key, secure).waiting += n;
+                    }
+                }
+                _ => (),
+            }
+        }
+        for (key, n) in &self.hosts {
+            entry(status, start, key, secure).in_flight += *n;
+        }
+        for (key, _, tx) in &self.waiters {
+            if !tx.is_canceled() {
+                entry(status, start, key, secure).waiting += 1;
+            }
+        }
     }
 }
 
 impl Inner {
     /// connection is not available, wait
-    fn wait_for(&mut self, connect: Connect) -> WaiterReceiver {
+    fn wait_for(&mut self, connect: Connect) -> Result<WaiterReceiver, ConnectError> {
+        if self.limits.max_waiters > 0 && self.waiters.len() >= self.limits.max_waiters {
+            self.waiters.retain(|(_, _, tx)| !tx.is_canceled());
+            if self.waiters.len() >= self.limits.max_waiters {
+                return Err(ConnectError::PoolOverflow);
+            }
+        }
+
         let (tx, rx) = self.pool.channel();
-        let key: Key = connect.uri.authority().unwrap().clone().into();
+        let key = Key::new(&connect).unwrap();
         self.waiters.push_back((key, connect, tx));
 
-        rx
+        Ok(rx)
     }
 
     /// cleanup dropped waiters
@@ -212,15 +399,46 @@ impl Inner {
         }
     }
 
+    /// wait for opening http/2 connection
+    fn wait_for_h2(&mut self, key: &Key, connect: Connect) -> WaiterReceiver {
+        let (tx, rx) = self.pool.channel();
+        if let Some(H2State::Opening(ref mut waiters)) = self.h2.get_mut(key) {
+            waiters.push((connect, tx));
+        } else {
+            // connection state changed, use regular queue
+            self.waiters.push_back((key.clone(), connect, tx));
+            self.waker.wake();
+        }
+        rx
+    }
+
     fn acquire(&mut self, key: &Key) -> Acquire {
         self.cleanup();
 
+        // http/2 connections are shared and do not count against limits
+        match self.h2.get_mut(key) {
+            Some(H2State::Ready(ref mut conn)) => {
+                let now = now();
+                if (now - conn.used) > self.conn_keep_alive
+                    || (now - conn.created) > self.conn_lifetime
+                {
+                    // connection is closed when last in-flight stream is done
+                    self.h2.insert(key.clone(), H2State::Closed);
+                } else {
+                    conn.used = now;
+                    return Acquire::Shared(conn.io.clone(), conn.created);
+                }
+            }
+            Some(H2State::Opening(_)) => return Acquire::Opening,
+            _ => (),
+        }
+
         // check limits
-        if self.limit > 0 && self.acquired >= self.limit {
+        if self.is_full() || self.is_host_full(key) {
             return Acquire::NotAvailable;
         }
 
-        self.reserve();
+        self.reserve(key);
 
         // check if open connection is available
         // cleanup stale connections at the same time
@@ -258,35 +476,120 @@ impl Inner {
                 }
             }
         }
+
+        // host could support http/2, other requests wait for new connection
+        match self.h2.get(key) {
+            Some(H2State::Closed) => {
+                self.h2.insert(key.clone(), H2State::Opening(Vec::new()));
+            }
+            None if self.h2_probe => {
+                self.h2.insert(key.clone(), H2State::Opening(Vec::new()));
+            }
+            _ => (),
+        }
         Acquire::Available
     }
 
-    fn release_conn(&mut self, key: &Key, io: ConnectionType, created: Instant) {
-        self.acquired -= 1;
-        self.available
-            .entry(key.clone())
-            .or_insert_with(VecDeque::new)
-            .push_back(AvailableConnection {
-                io,
+    /// http/2 connection is established, wake up waiting requests
+    fn h2_ready(
+        &mut self,
+        key: &Key,
+        io: &SendRequest<Bytes>,
+        created: Instant,
+        inner: &Rc<RefCell<Inner>>,
+    ) -> usize {
+        self.h2_id += 1;
+        let id = self.h2_id;
+        let state = self.h2.insert(
+            key.clone(),
+            H2State::Ready(SharedConnection {
+                id,
                 created,
-                used: now(),
-            });
+                io: io.clone(),
+                used: created,
+            }),
+        );
+        if let Some(H2State::Opening(waiters)) = state {
+            for (_, tx) in waiters {
+                let _ = tx.send(Ok(Connection::new(
+                    ConnectionType::H2(io.clone()),
+                    created,
+                    Some(Acquired::shared(key.clone(), inner.clone())),
+                )));
+            }
+        }
+        id
+    }
+
+    /// http/2 connection is closed
+    fn h2_closed(&mut self, key: &Key, id: Option<usize>) {
+        if let Some(state) = self.h2.get_mut(key) {
+            if let H2State::Ready(ref conn) = state {
+                if id.map(|id| id == conn.id).unwrap_or(true) {
+                    *state = H2State::Closed;
+                }
+            }
+        }
+    }
+
+    /// Connection is not opened or it negotiated http/1,
+    /// move waiting requests to regular queue
+    fn h2_failed(&mut self, key: &Key, state: H2State) {
+        if let Some(H2State::Opening(_)) = self.h2.get(key) {
+            if let Some(H2State::Opening(waiters)) = self.h2.insert(key.clone(), state) {
+                for (connect, tx) in waiters {
+                    self.waiters.push_back((key.clone(), connect, tx));
+                }
+                self.waker.wake();
+            }
+        }
+    }
+
+    fn release_conn(&mut self, key: &Key, io: ConnectionType, created: Instant) {
+        self.release(key);
+        if let ConnectionType::H2(_) = io {
+            // http/2 connection is kept in shared state
+            self.check_availibility();
+            return;
+        }
+        let connections = self.available.entry(key.clone()).or_default();
+        connections.push_back(AvailableConnection {
+            io,
+            created,
+            used: now(),
+        });
+
+        // close least recently used connections
+        while self.limits.max_idle > 0 && connections.len() > self.limits.max_idle {
+            if let Some(AvailableConnection {
+                io: ConnectionType::H1(io),
+                ..
+            }) = connections.pop_front()
+            {
+                spawn(async move {
+                    let _ = io.shutdown().await;
+                });
+            }
+        }
         self.check_availibility();
     }
 
-    fn release_close(&mut self, io: ConnectionType) {
-        self.acquired -= 1;
-        if let ConnectionType::H1(io) = io {
-            spawn(async move {
-                let _ = io.shutdown().await;
-            });
+    fn release_close(&mut self, key: &Key, io: ConnectionType) {
+        self.release(key);
+        match io {
+            ConnectionType::H1(io) => {
+                spawn(async move {
+                    let _ = io.shutdown().await;
+                });
+            }
+            ConnectionType::H2(_) => self.h2_closed(key, None),
         }
         self.check_availibility();
     }
 
     fn check_availibility(&mut self) {
         self.cleanup();
-        if !self.waiters.is_empty() && self.acquired < self.limit {
+        if !self.waiters.is_empty() && !self.is_full() {
             self.waker.wake();
         }
     }
@@ -315,27 +618,47 @@ where
         let mut inner = this.inner.as_ref().borrow_mut();
         inner.waker.register(cx.waker());
 
-        // check waiters
-        while let Some((key, _, tx)) = inner.waiters.front() {
+        // check waiters, waiters for hosts that reached
+        // per-host limit do not block other hosts
+        let mut idx = 0;
+        while let Some((key, _, tx)) = inner.waiters.get(idx) {
             // is waiter still alive
             if tx.is_canceled() {
-                inner.waiters.pop_front();
+                inner.waiters.remove(idx);
                 continue;
             };
+            if inner.is_full() {
+                break;
+            }
             let key = key.clone();
 
             match inner.acquire(&key) {
-                Acquire::NotAvailable => break,
+                Acquire::NotAvailable => idx += 1,
                 Acquire::Acquired(io, created) => {
-                    let (key, _, tx) = inner.waiters.pop_front().unwrap();
+                    let (key, _, tx) = inner.waiters.remove(idx).unwrap();
                     let _ = tx.send(Ok(Connection::new(
                         io,
                         created,
-                        Some(Acquired(key.clone(), Some(this.inner.clone()))),
+                        Some(Acquired(key.clone(), Some(this.inner.clone()), false)),
+                    )));
+                }
+                Acquire::Shared(io, created) => {
+                    let (key, _, tx) = inner.waiters.remove(idx).unwrap();
+                    let _ = tx.send(Ok(Connection::new(
+                        ConnectionType::H2(io),
+                        created,
+                        Some(Acquired::shared(key, this.inner.clone())),
                     )));
                 }
+                Acquire::Opening => {
+                    let (key, connect, tx) = inner.waiters.remove(idx).unwrap();
+                    if let Some(H2State::Opening(ref mut waiters)) = inner.h2.get_mut(&key)
+                    {
+                        waiters.push((connect, tx));
+                    }
+                }
                 Acquire::Available => {
-                    let (key, connect, tx) = inner.waiters.pop_front().unwrap();
+                    let (key, connect, tx) = inner.waiters.remove(idx).unwrap();
                     OpenConnection::spawn(
                         key,
                         tx,
@@ -367,6 +690,8 @@ struct OpenConnection<F> {
     tx: Option<Waiter>,
     guard: Option<OpenGuard>,
     disconnect_timeout: Millis,
+    hpack: Rc<HpackConfig>,
+    h2_window: (Option<u32>, Option<u32>),
 }
 
 impl<F> OpenConnection<F>
@@ -375,9 +700,13 @@ where
 {
     fn spawn(key: Key, tx: Waiter, inner: Rc<RefCell<Inner>>, fut: F) {
         let disconnect_timeout = inner.borrow().disconnect_timeout;
+        let hpack = inner.borrow().hpack.clone();
+        let h2_window = inner.borrow().h2_window;
 
         spawn(OpenConnection {
             fut,
+            hpack,
+            h2_window,
             disconnect_timeout,
             h2: None,
             tx: Some(tx),
@@ -402,11 +731,19 @@ where
         if let Some(ref mut h2) = this.h2 {
             return match Pin::new(h2).poll(cx) {
                 Poll::Ready(Ok((snd, connection))) => {
-                    // h2 connection is ready
+                    // h2 connection is ready, share it with waiting requests
+                    let created = now();
+                    let guard = this.guard.take().unwrap();
+                    let inner = guard.inner.clone().unwrap();
+                    let id = inner
+                        .borrow_mut()
+                        .h2_ready(&guard.key, &snd, created, &inner);
+                    let key = guard.key.clone();
+
                     let conn = Connection::new(
                         ConnectionType::H2(snd),
-                        now(),
-                        Some(this.guard.take().unwrap().consume()),
+                        created,
+                        Some(guard.consume()),
                     );
                     if let Err(Ok(conn)) = this.tx.take().unwrap().send(Ok(conn)) {
                         // waiter is gone, return connection to pool
@@ -414,6 +751,7 @@ where
                     }
                     spawn(async move {
                         let _ = connection.await;
+                        inner.borrow_mut().h2_closed(&key, Some(id));
                     });
                     Poll::Ready(())
                 }
@@ -443,14 +781,25 @@ where
                 if io.query::<HttpProtocol>().get() == Some(HttpProtocol::Http2) {
                     log::trace!("Connection is established, start http2 handshake");
                     // init http2 handshake
-                    this.h2 = Some(Box::pin(Builder::new().handshake(io)));
+                    let mut builder = this.hpack.client_builder();
+                    if let Some(size) = this.h2_window.0 {
+                        builder.initial_window_size(size);
+                    }
+                    if let Some(size) = this.h2_window.1 {
+                        builder.initial_connection_window_size(size);
+                    }
+                    this.h2 = Some(Box::pin(builder.handshake(io)));
                     self.poll(cx)
                 } else {
                     log::trace!("Connection is established, init http1 connection");
+                    let guard = this.guard.take().unwrap();
+                    if let Some(ref inner) = guard.inner {
+                        inner.borrow_mut().h2_failed(&guard.key, H2State::Http1);
+                    }
                     let conn = Connection::new(
                         ConnectionType::H1(io),
                         now(),
-                        Some(this.guard.take().unwrap().consume()),
+                        Some(guard.consume()),
                     );
                     if let Err(Ok(conn)) = this.tx.take().unwrap().send(Ok(conn)) {
                         // waiter is gone, return connection to pool
@@ -471,7 +820,7 @@ struct OpenGuard {
 
 impl OpenGuard {
     fn consume(mut self) -> Acquired {
-        Acquired(self.key.clone(), self.inner.take())
+        Acquired(self.key.clone(), self.inner.take(), false)
     }
 }
 
@@ -479,24 +828,42 @@ impl Drop for OpenGuard {
     fn drop(&mut self) {
         if let Some(i) = self.inner.take() {
             let mut inner = i.as_ref().borrow_mut();
-            inner.release();
+            inner.release(&self.key);
+            inner.h2_failed(&self.key, H2State::Closed);
             inner.check_availibility();
         }
     }
 }
 
-pub(super) struct Acquired(Key, Option<Rc<RefCell<Inner>>>);
+pub(super) struct Acquired(Key, Option<Rc<RefCell<Inner>>>, bool);
 
 impl Acquired {
+    /// Stream of shared http/2 connection, it does not count against limits
+    fn shared(key: Key, inner: Rc<RefCell<Inner>>) -> Self {
+        Acquired(key, Some(inner), true)
+    }
+
+    /// HPACK settings of the pool
+    pub(super) fn hpack(&self) -> Option<Rc<HpackConfig>> {
+        self.1.as_ref().map(|inner| inner.borrow().hpack.clone())
+    }
+
     pub(super) fn close(&mut self, conn: Connection) {
         if let Some(inner) = self.1.take() {
             let (io, _) = conn.into_inner();
-            inner.as_ref().borrow_mut().release_close(io);
+            if self.2 {
+                inner.as_ref().borrow_mut().h2_closed(&self.0, None);
+            } else {
+                inner.as_ref().borrow_mut().release_close(&self.0, io);
+            }
         }
     }
 
     pub(super) fn release(&mut self, conn: Connection) {
         if let Some(inner) = self.1.take() {
+            if self.2 {
+                return;
+            }
             let (io, created) = conn.into_inner();
             inner
                 .as_ref()
@@ -509,7 +876,9 @@ impl Acquired {
 impl Drop for Acquired {
     fn drop(&mut self) {
         if let Some(inner) = self.1.take() {
-            inner.borrow_mut().release();
+            if !self.2 {
+                inner.borrow_mut().release(&self.0);
+            }
         }
     }
 }
@@ -537,7 +906,12 @@ mod tests {
             Duration::from_secs(10),
             Duration::from_secs(10),
             Millis::ZERO,
-            1,
+            PoolLimits {
+                limit: 1,
+                host_limit: 0,
+                max_idle: 0,
+                max_waiters: 0,
+            },
         )
         .clone();
 
@@ -545,6 +919,9 @@ mod tests {
         let req = Connect {
             uri: Uri::try_from("/test").unwrap(),
             addr: None,
+            local_addr: None,
+            interface: None,
+            server_name: None,
         };
         match pool.call(req).await {
             Err(ConnectError::Unresolved) => (),
@@ -555,6 +932,9 @@ mod tests {
         let req = Connect {
             uri: Uri::try_from("http://localhost/test").unwrap(),
             addr: None,
+            local_addr: None,
+            interface: None,
+            server_name: None,
         };
         let conn = pool.call(req.clone()).await.unwrap();
         assert_eq!(store.borrow().len(), 1);
@@ -583,5 +963,104 @@ mod tests {
 
         assert!(lazy(|cx| pool.poll_ready(cx)).await.is_ready());
         assert!(lazy(|cx| pool.poll_shutdown(cx, false)).await.is_ready());
+
+        // connections with different local address are not shared
+        let mut req2 = req.clone();
+        req2.local_addr = Some("127.0.0.1".parse().unwrap());
+        assert_ne!(Key::new(&req), Key::new(&req2));
+        let mut req3 = req.clone();
+        req3.interface = Some("lo".to_string());
+        assert_ne!(Key::new(&req), Key::new(&req3));
+        let mut req4 = req.clone();
+        req4.server_name = Some("example.com".to_string());
+        assert_ne!(Key::new(&req), Key::new(&req4));
+    }
+
+    #[crate::rt_test]
+    async fn test_host_limits() {
+        let store = Rc::new(RefCell::new(Vec::new()));
+        let store2 = store.clone();
+
+        let pool = ConnectionPool::new(
+            fn_service(move |req| {
+                let (client, server) = Io::create();
+                store2.borrow_mut().push((req, server));
+                Box::pin(async move { Ok(IoBoxed::from(nio::Io::new(client))) })
+            }),
+            Duration::from_secs(10),
+            Duration::from_secs(10),
+            Millis::ZERO,
+            PoolLimits {
+                limit: 10,
+                host_limit: 2,
+                max_idle: 1,
+                max_waiters: 1,
+            },
+        );
+        let status = |pool: &ConnectionPool<_>| {
+            let mut st = Vec::new();
+            pool.status(false, &mut st);
+            st.sort_by(|a: &HostStatus, b| a.authority.as_str().cmp(b.authority.as_str()));
+            st
+        };
+        let req = |uri| Connect {
+            uri: Uri::try_from(uri).unwrap(),
+            addr: None,
+            local_addr: None,
+            interface: None,
+            server_name: None,
+        };
+
+        let conn1 = pool.call(req("http://localhost/test")).await.unwrap();
+        let conn2 = pool.call(req("http://localhost/test")).await.unwrap();
+
+        // host limit is reached, wait
+        let mut fut = pool.call(req("http://localhost/test"));
+        assert!(lazy(|cx| Pin::new(&mut fut).poll(cx)).await.is_pending());
+
+        // waiters queue is full
+        match pool.call(req("http://localhost/test")).await {
+            Err(ConnectError::PoolOverflow) => (),
+            _ => panic!(),
+        }
+
+        // other hosts are not affected
+        let conn4 = pool.call(req("http://example.com/test")).await.unwrap();
+        assert_eq!(store.borrow().len(), 3);
+        assert_eq!(
+            status(&pool),
+            vec![
+                HostStatus {
+                    authority: Authority::from_static("example.com"),
+                    secure: false,
+                    idle: 0,
+                    in_flight: 1,
+                    waiting: 0,
+                },
+                HostStatus {
+                    authority: Authority::from_static("localhost"),
+                    secure: false,
+                    idle: 0,
+                    in_flight: 2,
+                    waiting: 1,
+                }
+            ]
+        );
+
+        // released connection goes to waiter
+        conn4.release();
+        conn1.release();
+        let conn3 = fut.await.unwrap();
+        assert_eq!(store.borrow().len(), 3);
+
+        // only one idle connection is kept
+        conn2.release();
+        conn3.release();
+        let st = status(&pool);
+        assert_eq!(st[0].idle, 1);
+        assert_eq!(st[0].in_flight, 0);
+        assert_eq!(st[1].idle, 1);
+        assert_eq!(st[1].in_flight, 0);
+        assert_eq!(st[1].waiting, 0);
     }
 }
@@ -0,0 +1,358 @@
+use std::{convert::TryFrom, fmt, rc::Rc};
+
+use crate::http::body::Body;
+use crate::http::header::{self, HeaderMap};
+use crate::http::{Method, RequestHead, RequestHeadType, StatusCode, Uri};
+
+use super::error::SendRequestError;
+use super::response::ClientResponse;
+use super::{retry, ClientConfig, RequestAddrs, RetryPolicy};
+
+type RedirectFilter = dyn Fn(&Uri, &Uri, &ClientResponse) -> bool;
+
+/// Redirect policy
+///
+/// Client follows `301`, `302`, `303`, `307` and `308` redirects. `303 See Other`
+/// redirects are followed with `GET` method and without request body, `301` and
+/// `302` redirects of `POST` requests are handled the same way. Other redirects
+/// keep request method and body, requests with streaming body could not be
+/// redirected and redirect response is returned as is.
+///
+/// Sensitive headers, `Authorization` and `Cookie` by default, are not
+/// forwarded to a different origin. Redirects from `https` to plain text
+/// `http` are not followed unless downgrade is allowed.
+///
+/// ```rust
+/// use ntex::http::client::{Client, RedirectPolicy};
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let client = Client::build()
+///         .redirect(
+///             RedirectPolicy::new()
+///                 .max_redirects(5)
+///                 .filter(|_, to, _| to.scheme_str() == Some("https")),
+///         )
+///         .finish();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RedirectPolicy {
+    pub(super) max_redirects: usize,
+    forward_auth: bool,
+    allow_downgrade: bool,
+    rewrite_see_other: bool,
+    filter: Option<Rc<RedirectFilter>>,
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        RedirectPolicy::new()
+    }
+}
+
+impl fmt::Debug for RedirectPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedirectPolicy")
+            .field("max_redirects", &self.max_redirects)
+            .field("forward_auth", &self.forward_auth)
+            .field("allow_downgrade", &self.allow_downgrade)
+            .field("rewrite_see_other", &self.rewrite_see_other)
+            .field("filter", &self.filter.is_some())
+            .finish()
+    }
+}
+
+impl RedirectPolicy {
+    /// Create redirect policy with default settings.
+    ///
+    /// By default client follows up to 10 redirects.
+    pub fn new() -> Self {
+        RedirectPolicy {
+            max_redirects: 10,
+            forward_auth: false,
+            allow_downgrade: false,
+            rewrite_see_other: true,
+            filter: None,
+        }
+    }
+
+    /// Set max number of redirects.
+    ///
+    /// Request fails with `SendRequestError::TooManyRedirects` error
+    /// if limit is exceeded.
+    pub fn max_redirects(mut self, num: usize) -> Self {
+        self.max_redirects = num;
+        self
+    }
+
+    /// Forward sensitive headers to a different origin.
+    ///
+    /// By default sensitive headers are removed if redirect location has
+    /// different scheme, host or port.
+    pub fn forward_auth(mut self, forward: bool) -> Self {
+        self.forward_auth = forward;
+        self
+    }
+
+    /// Follow redirects from secure to plain text scheme.
+    ///
+    /// By default redirect from `https` to `http` location is not followed
+    /// and redirect response is returned.
+    pub fn allow_downgrade(mut self, allow: bool) -> Self {
+        self.allow_downgrade = allow;
+        self
+    }
+
+    /// Follow `303 See Other` redirect with `GET` method.
+    ///
+    /// If disabled, request method and body are preserved. Enabled by default.
+    pub fn rewrite_see_other(mut self, rewrite: bool) -> Self {
+        self.rewrite_see_other = rewrite;
+        self
+    }
+
+    /// Set redirect filter.
+    ///
+    /// Filter receives current request uri, redirect location and
+    /// redirect response. If filter returns `false`, redirect is not
+    /// followed and redirect response is returned.
+    pub fn filter<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Uri, &Uri, &ClientResponse) -> bool + 'static,
+    {
+        self.filter = Some(Rc::new(f));
+        self
+    }
+}
+
+fn is_redirect(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    )
+}
+
+/// Resolve `Location` header against request uri
+fn location(uri: &Uri, res: &ClientResponse) -> Option<Uri> {
+    let value = res.headers().get(&header::LOCATION)?.to_str().ok()?.trim();
+    let value = value.split('#').next().unwrap_or_default();
+    let scheme = uri.scheme_str()?;
+    let authority = uri.authority()?;
+
+    let location = if value.starts_with("//") {
+        format!("{}:{}", scheme, value)
+    } else if value.starts_with('/') {
+        format!("{}://{}{}", scheme, authority, value)
+    } else if value.contains("://") {
+        value.to_string()
+    } else {
+        let path = uri.path();
+        let base = &path[..path.rfind('/').map(|idx| idx + 1).unwrap_or(0)];
+        format!("{}://{}{}{}", scheme, authority, base, value)
+    };
+
+    match Uri::try_from(location) {
+        Ok(uri) if uri.scheme().is_some() && uri.host().is_some() => Some(uri),
+        _ => None,
+    }
+}
+
+fn is_same_origin(uri1: &Uri, uri2: &Uri) -> bool {
+    uri1.scheme() == uri2.scheme() && uri1.host() == uri2.host() && port(uri1) == port(uri2)
+}
+
+fn is_downgrade(from: &Uri, to: &Uri) -> bool {
+    matches!(from.scheme_str(), Some("https") | Some("wss"))
+        && !matches!(to.scheme_str(), Some("https") | Some("wss"))
+}
+
+fn port(uri: &Uri) -> Option<u16> {
+    uri.port_u16().or_else(|| match uri.scheme_str() {
+        Some("https") | Some("wss") => Some(443),
+        Some("http") | Some("ws") => Some(80),
+        _ => None,
+    })
+}
+
+/// Send request, follow redirects according to the policy
+pub(super) async fn send(
+    policy: Rc<RedirectPolicy>,
+    config: Rc<ClientConfig>,
+    retry: Option<Rc<RetryPolicy>>,
+    head: RequestHeadType,
+    body: Body,
+    mut addr: RequestAddrs,
+) -> Result<ClientResponse, SendRequestError> {
+    let (mut head, mut extra_headers) = match head {
+        RequestHeadType::Owned(head) => (Rc::new(head), None),
+        RequestHeadType::Rc(head, extra_headers) => (head, extra_headers),
+    };
+    // streaming body could be sent only once
+    let (mut body, mut next_body) = if matches!(body, Body::Message(_)) {
+        (body, None)
+    } else {
+        (retry::clone_body(&body), Some(body))
+    };
+    let mut redirects = 0;
+
+    loop {
+        let res = RequestHeadType::Rc(head.clone(), extra_headers.clone())
+            .send_request(addr.clone(), retry.clone(), &config, body)?
+            .await?;
+
+        if !is_redirect(res.status()) {
+            return Ok(res);
+        }
+        let uri = match location(&head.uri, &res) {
+            Some(uri) => uri,
+            None => return Ok(res),
+        };
+        if !policy.allow_downgrade && is_downgrade(&head.uri, &uri) {
+            log::trace!("Redirect from {:?} to {:?} is refused", head.uri, uri);
+            return Ok(res);
+        }
+        if let Some(ref filter) = policy.filter {
+            if !filter(&head.uri, &uri, &res) {
+                return Ok(res);
+            }
+        }
+
+        let rewrite = match res.status() {
+            StatusCode::SEE_OTHER => {
+                policy.rewrite_see_other && head.method != Method::HEAD
+            }
+            StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => {
+                head.method == Method::POST
+            }
+            _ => false,
+        };
+        body = if rewrite {
+            next_body = Some(Body::None);
+            Body::None
+        } else if let Some(ref body) = next_body {
+            retry::clone_body(body)
+        } else {
+            return Ok(res);
+        };
+
+        if redirects >= policy.max_redirects {
+            return Err(SendRequestError::TooManyRedirects);
+        }
+        redirects += 1;
+        drop(res);
+
+        let mut headers = head.headers.clone();
+        if let Some(ref extra) = extra_headers {
+            merge_headers(&mut headers, extra);
+        }
+        if rewrite {
+            headers.remove(header::CONTENT_TYPE);
+            headers.remove(header::CONTENT_LENGTH);
+            headers.remove(header::CONTENT_ENCODING);
+            headers.remove(header::TRANSFER_ENCODING);
+        }
+        if !is_same_origin(&head.uri, &uri) {
+            headers.remove(header::HOST);
+            if !policy.forward_auth {
+                for name in config.sensitive.iter() {
+                    headers.remove(name);
+                }
+            }
+            addr.addr = None;
+            addr.server_name = None;
+        }
+        log::trace!("Redirect request from {:?} to {:?}", head.uri, uri);
+
+        head = Rc::new(RequestHead {
+            uri,
+            headers,
+            method: if rewrite {
+                Method::GET
+            } else {
+                head.method.clone()
+            },
+            version: head.version,
+            flags: head.flags,
+            ..Default::default()
+        });
+        extra_headers = None;
+    }
+}
+
+/// Extra headers override request headers
+pub(super) fn merge_headers(headers: &mut HeaderMap, extra: &HeaderMap) {
+    for key in extra.keys() {
+        headers.remove(key);
+    }
+    for (key, value) in extra.iter() {
+        headers.append(key.clone(), value.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::client::test::TestResponse;
+
+    #[crate::rt_test]
+    async fn test_location() {
+        let uri = Uri::from_static("http://localhost:8080/a/b?q=1");
+        let loc = |value: &'static str| {
+            let res = TestResponse::with_header(header::LOCATION, value).finish();
+            location(&uri, &res).map(|uri| uri.to_string())
+        };
+
+        assert_eq!(
+            loc("https://example.com/x").unwrap(),
+            "https://example.com/x"
+        );
+        assert_eq!(loc("//example.com/x").unwrap(), "http://example.com/x");
+        assert_eq!(loc("/x?y=1").unwrap(), "http://localhost:8080/x?y=1");
+        assert_eq!(loc("c#frag").unwrap(), "http://localhost:8080/a/c");
+        assert_eq!(loc("http://"), None);
+        assert_eq!(location(&uri, &TestResponse::default().finish()), None);
+    }
+
+    #[test]
+    fn test_same_origin() {
+        let uri = Uri::from_static("http://localhost/a");
+        assert!(is_same_origin(
+            &uri,
+            &Uri::from_static("http://localhost:80/b")
+        ));
+        assert!(!is_same_origin(
+            &uri,
+            &Uri::from_static("https://localhost/a")
+        ));
+        assert!(!is_same_origin(
+            &uri,
+            &Uri::from_static("http://localhost:8080/a")
+        ));
+        assert!(!is_same_origin(
+            &uri,
+            &Uri::from_static("http://example.com/a")
+        ));
+    }
+
+    #[test]
+    fn test_downgrade() {
+        let uri = Uri::from_static("https://localhost/a");
+        assert!(is_downgrade(&uri, &Uri::from_static("http://localhost/a")));
+        assert!(!is_downgrade(&uri, &Uri::from_static("https://example.com/")));
+        assert!(!is_downgrade(
+            &Uri::from_static("http://localhost/a"),
+            &Uri::from_static("https://localhost/a")
+        ));
+        assert!(!is_downgrade(
+            &Uri::from_static("http://localhost/a"),
+            &Uri::from_static("http://example.com/")
+        ));
+        assert!(!RedirectPolicy::new().allow_downgrade);
+        assert!(RedirectPolicy::new().allow_downgrade(true).allow_downgrade);
+    }
+}
@@ -3,6 +3,7 @@ use std::{convert::TryFrom, error::Error, fmt, net, rc::Rc};
 #[cfg(feature = "cookie")]
 use coo_kie::{Cookie, CookieJar};
 use serde::Serialize;
+use tok_io::io::AsyncRead;
 
 use crate::http::body::Body;
 use crate::http::error::HttpError;
@@ -10,12 +11,14 @@ use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
 use crate::http::{
     uri, ConnectionType, Method, RequestHead, RequestHeadType, Uri, Version,
 };
-use crate::{time::Millis, util::Bytes, Stream};
+use crate::{io::IoBoxed, time::Millis, util::Bytes, Stream};
 
-use super::error::{FreezeRequestError, InvalidUrl};
+use super::error::{FreezeRequestError, InvalidUrl, SendRequestError};
 use super::frozen::FrozenClientRequest;
+use super::multipart::Form;
+use super::response::ClientResponse;
 use super::sender::{PrepForSendingError, SendClientRequest};
-use super::ClientConfig;
+use super::{ClientConfig, RequestAddrs, RetryPolicy};
 
 #[cfg(feature = "compress")]
 const HTTPS_ENCODING: &str = "br, gzip, deflate";
@@ -47,11 +50,12 @@ const HTTPS_ENCODING: &str = "br";
 pub struct ClientRequest {
     pub(crate) head: RequestHead,
     err: Option<HttpError>,
-    addr: Option<net::SocketAddr>,
+    addr: RequestAddrs,
     #[cfg(feature = "cookie")]
     cookies: Option<CookieJar>,
     response_decompress: bool,
     timeout: Millis,
+    retry: Option<Rc<RetryPolicy>>,
     config: Rc<ClientConfig>,
 }
 
@@ -63,10 +67,11 @@ impl ClientRequest {
         <Uri as TryFrom<U>>::Error: Into<HttpError>,
     {
         ClientRequest {
+            retry: config.retry.clone(),
             config,
             head: RequestHead::default(),
             err: None,
-            addr: None,
+            addr: RequestAddrs::default(),
             #[cfg(feature = "cookie")]
             cookies: None,
             timeout: Millis::ZERO,
@@ -100,7 +105,36 @@ impl ClientRequest {
     /// This address is used for connection. If address is not
     /// provided url's host name get resolved.
     pub fn address(mut self, addr: net::SocketAddr) -> Self {
-        self.addr = Some(addr);
+        self.addr.addr = Some(addr);
+        self
+    }
+
+    /// Set local address for connection.
+    ///
+    /// Overrides connector's local address. Connections with
+    /// different local addresses are not shared.
+    pub fn local_address(mut self, addr: net::IpAddr) -> Self {
+        self.addr.local_addr = Some(addr);
+        self
+    }
+
+    /// Bind connection to network interface.
+    ///
+    /// Overrides connector's interface. Connections bound to
+    /// different interfaces are not shared.
+    /// Binding to network interface is supported only on linux.
+    pub fn interface<T: Into<String>>(mut self, interface: T) -> Self {
+        self.addr.interface = Some(interface.into());
+        self
+    }
+
+    /// Set tls server name for connection.
+    ///
+    /// Server name is used for SNI and certificate verification
+    /// instead of url's host name. Overrides connector's server name.
+    /// Connections with different server names are not shared.
+    pub fn server_name<T: Into<String>>(mut self, name: T) -> Self {
+        self.addr.server_name = Some(name.into());
         self
     }
 
@@ -318,6 +352,18 @@ impl ClientRequest {
         self
     }
 
+    /// Set retry policy. Overrides client wide retry policy.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(Rc::new(policy));
+        self
+    }
+
+    /// Do not retry request.
+    pub fn no_retry(mut self) -> Self {
+        self.retry = None;
+        self
+    }
+
     /// This method calls provided closure with builder reference if
     /// value is `true`.
     pub fn if_true<F>(self, value: bool, f: F) -> Self
@@ -378,6 +424,7 @@ impl ClientRequest {
             addr: slf.addr,
             response_decompress: slf.response_decompress,
             timeout: slf.timeout,
+            retry: slf.retry,
             config: slf.config,
         };
 
@@ -398,7 +445,8 @@ impl ClientRequest {
             slf.addr,
             slf.response_decompress,
             slf.timeout,
-            slf.config.as_ref(),
+            slf.retry,
+            &slf.config,
             body,
         )
     }
@@ -414,7 +462,8 @@ impl ClientRequest {
             slf.addr,
             slf.response_decompress,
             slf.timeout,
-            slf.config.as_ref(),
+            slf.retry,
+            &slf.config,
             value,
         )
     }
@@ -432,12 +481,16 @@ impl ClientRequest {
             slf.addr,
             slf.response_decompress,
             slf.timeout,
-            slf.config.as_ref(),
+            slf.retry,
+            &slf.config,
             value,
         )
     }
 
     /// Set an streaming body and generate `ClientRequest`.
+    ///
+    /// If `Content-Length` header is set, body is sent with known size,
+    /// otherwise chunked transfer encoding is used.
     pub fn send_stream<S, E>(self, stream: S) -> SendClientRequest
     where
         S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
@@ -452,11 +505,54 @@ impl ClientRequest {
             slf.addr,
             slf.response_decompress,
             slf.timeout,
-            slf.config.as_ref(),
+            &slf.config,
             stream,
         )
     }
 
+    /// Set a body read from `AsyncRead` object and generate `ClientRequest`.
+    ///
+    /// If `len` is not provided, `Content-Length` header value is used.
+    /// If body size is unknown, chunked transfer encoding is used.
+    pub fn send_reader<R>(self, reader: R, len: Option<u64>) -> SendClientRequest
+    where
+        R: AsyncRead + Unpin + 'static,
+    {
+        let slf = match self.prep_for_sending() {
+            Ok(slf) => slf,
+            Err(e) => return e.into(),
+        };
+
+        RequestHeadType::Owned(slf.head).send_reader(
+            slf.addr,
+            slf.response_decompress,
+            slf.timeout,
+            &slf.config,
+            reader,
+            len,
+        )
+    }
+
+    /// Set a `multipart/form-data` body and generate `ClientRequest`.
+    ///
+    /// `Content-Type` header is set to form's content type, if it is not set
+    /// already. If size of all form parts is known, body is sent with known
+    /// size, otherwise chunked transfer encoding is used.
+    pub fn send_multipart(self, form: Form) -> SendClientRequest {
+        let slf = match self.prep_for_sending() {
+            Ok(slf) => slf,
+            Err(e) => return e.into(),
+        };
+
+        RequestHeadType::Owned(slf.head).send_multipart(
+            slf.addr,
+            slf.response_decompress,
+            slf.timeout,
+            &slf.config,
+            form,
+        )
+    }
+
     /// Set an empty body and generate `ClientRequest`.
     pub fn send(self) -> SendClientRequest {
         let slf = match self.prep_for_sending() {
@@ -468,10 +564,48 @@ impl ClientRequest {
             slf.addr,
             slf.response_decompress,
             slf.timeout,
-            slf.config.as_ref(),
+            slf.retry,
+            &slf.config,
         )
     }
 
+    /// Send request and take over connection on protocol switch.
+    ///
+    /// If server switches protocols with `101 Switching Protocols` response or
+    /// accepts `CONNECT` request, connection is removed from the connection pool
+    /// and returned with response. Bytes received after response head are kept
+    /// in io read buffer. Otherwise regular response is returned and connection
+    /// is managed by the pool.
+    ///
+    /// Protocol switch is not supported for http/2 connections.
+    ///
+    /// ```rust
+    /// use ntex::http::{client::Client, header};
+    ///
+    /// #[ntex::main]
+    /// async fn main() {
+    ///     let res = Client::new()
+    ///         .get("http://www.rust-lang.org")
+    ///         .header(header::CONNECTION, "upgrade")
+    ///         .header(header::UPGRADE, "custom")
+    ///         .upgrade()
+    ///         .await;
+    ///
+    ///     if let Ok((_response, Some(_io))) = res {
+    ///         // use io for custom protocol
+    ///     }
+    /// }
+    /// ```
+    pub async fn upgrade(
+        self,
+    ) -> Result<(ClientResponse, Option<IoBoxed>), SendRequestError> {
+        let slf = self.prep_for_sending()?;
+
+        RequestHeadType::Owned(slf.head)
+            .open_tunnel(slf.addr, slf.timeout, slf.config.as_ref())
+            .await
+    }
+
     #[allow(unused_mut)]
     fn prep_for_sending(mut self) -> Result<Self, PrepForSendingError> {
         if let Some(e) = self.err {
@@ -518,6 +652,9 @@ impl ClientRequest {
         }
 
         let mut slf = self;
+        for name in slf.config.sensitive.iter() {
+            slf.head.headers.set_sensitive(name);
+        }
 
         if slf.response_decompress {
             let https = slf
@@ -550,7 +687,7 @@ impl fmt::Debug for ClientRequest {
         )?;
         writeln!(f, "  headers:")?;
         for (key, val) in self.head.headers.iter() {
-            if key == header::AUTHORIZATION {
+            if val.is_sensitive() || self.config.sensitive.contains(key) {
                 writeln!(f, "    {:?}: <REDACTED>", key)?;
             } else {
                 writeln!(f, "    {:?}: {:?}", key, val)?;
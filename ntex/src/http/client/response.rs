@@ -1,6 +1,7 @@
 use std::cell::{Ref, RefMut};
+use std::io::{self, Write};
 use std::task::{Context, Poll};
-use std::{fmt, future::Future, marker::PhantomData, mem, pin::Pin};
+use std::{fmt, fs, future::Future, marker::PhantomData, mem, path::PathBuf, pin::Pin};
 
 use serde::de::DeserializeOwned;
 
@@ -11,7 +12,8 @@ use crate::http::error::PayloadError;
 use crate::http::header::{AsName, HeaderValue, CONTENT_LENGTH};
 use crate::http::{HeaderMap, StatusCode, Version};
 use crate::http::{HttpMessage, Payload, ResponseHead};
-use crate::util::{Bytes, BytesMut, Extensions};
+use crate::rt::spawn_blocking;
+use crate::util::{poll_fn, Bytes, BytesMut, Extensions};
 use crate::Stream;
 
 use super::error::JsonPayloadError;
@@ -106,6 +108,26 @@ impl ClientResponse {
         mem::take(&mut self.payload)
     }
 
+    #[cfg(feature = "content-digest")]
+    /// Verify response payload digest.
+    ///
+    /// Payload digest is computed while payload is read. If it does not match
+    /// `Content-Digest` header, or `Repr-Digest` header for decompressed payload,
+    /// reading fails with `PayloadError::DigestMismatch` at the end of payload.
+    ///
+    /// Returns `false` if response does not contain digest with supported algorithm.
+    pub fn verify_digest(&mut self) -> bool {
+        use crate::http::digest::{expected, DigestVerifier};
+
+        if let Some((alg, digest)) = expected(&self.head) {
+            let payload = self.take_payload();
+            self.payload = Payload::from_stream(DigestVerifier::new(payload, alg, digest));
+            true
+        } else {
+            false
+        }
+    }
+
     /// Request extensions
     #[inline]
     pub fn extensions(&self) -> Ref<'_, Extensions> {
@@ -135,6 +157,59 @@ impl ClientResponse {
     pub fn json<T: DeserializeOwned>(&mut self) -> JsonBody<T> {
         JsonBody::new(self)
     }
+
+    /// Loads and parse `application/json` encoded body with max size of `limit` bytes.
+    pub fn json_with_limit<T: DeserializeOwned>(&mut self, limit: usize) -> JsonBody<T> {
+        JsonBody::new(self).limit(limit)
+    }
+
+    /// Loads http response's body with max size of `limit` bytes.
+    ///
+    /// Returns `PayloadError::Overflow` if body is larger than `limit`.
+    pub fn bytes_limited(&mut self, limit: usize) -> MessageBody {
+        MessageBody::new(self).limit(limit)
+    }
+
+    /// Stream http response's body to a file.
+    ///
+    /// File is created or truncated, chunks are written on blocking
+    /// thread pool as they arrive. Resolves to number of written bytes.
+    pub fn save_to_file<P: Into<PathBuf>>(
+        &mut self,
+        path: P,
+    ) -> impl Future<Output = Result<u64, PayloadError>> {
+        let path = path.into();
+        let mut payload = self.take_payload();
+
+        async move {
+            let mut file = blocking(move || fs::File::create(path)).await?;
+            let mut written = 0;
+
+            while let Some(chunk) = poll_fn(|cx| Pin::new(&mut payload).poll_next(cx)).await
+            {
+                let chunk = chunk?;
+                written += chunk.len() as u64;
+                file = blocking(move || file.write_all(&chunk).map(|_| file)).await?;
+            }
+            blocking(move || file.sync_all()).await?;
+            Ok(written)
+        }
+    }
+}
+
+/// Execute io operation on blocking thread pool
+async fn blocking<F, T>(f: F) -> Result<T, PayloadError>
+where
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    match spawn_blocking(f).await {
+        Ok(res) => res.map_err(PayloadError::Io),
+        Err(_) => Err(PayloadError::Io(io::Error::new(
+            io::ErrorKind::Interrupted,
+            "Blocking task is canceled",
+        ))),
+    }
 }
 
 impl Stream for ClientResponse {
@@ -150,7 +225,11 @@ impl fmt::Debug for ClientResponse {
         writeln!(f, "\nClientResponse {:?} {}", self.version(), self.status(),)?;
         writeln!(f, "  headers:")?;
         for (key, val) in self.headers().iter() {
-            writeln!(f, "    {:?}: {:?}", key, val)?;
+            if val.is_sensitive() {
+                writeln!(f, "    {:?}: <REDACTED>", key)?;
+            } else {
+                writeln!(f, "    {:?}: {:?}", key, val)?;
+            }
         }
         Ok(())
     }
@@ -385,6 +464,57 @@ mod tests {
         }
     }
 
+    #[crate::rt_test]
+    async fn test_body_limited() {
+        let mut req = TestResponse::default()
+            .set_payload(Bytes::from_static(b"11111111111111"))
+            .finish();
+        match req.bytes_limited(5).await.err().unwrap() {
+            PayloadError::Overflow => (),
+            _ => unreachable!("error"),
+        }
+
+        let mut req = TestResponse::default()
+            .set_payload(Bytes::from_static(b"11111"))
+            .finish();
+        assert_eq!(
+            req.bytes_limited(5).await.unwrap(),
+            Bytes::from_static(b"11111")
+        );
+
+        let mut req = TestResponse::default()
+            .header(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_static("application/json"),
+            )
+            .set_payload(Bytes::from_static(b"{\"name\": \"test\"}"))
+            .finish();
+        let json = req.json_with_limit::<MyObject>(10).await;
+        assert!(json_eq(
+            json.err().unwrap(),
+            JsonPayloadError::Payload(PayloadError::Overflow)
+        ));
+    }
+
+    #[crate::rt_test]
+    async fn test_save_to_file() {
+        let path = std::env::temp_dir()
+            .join(format!("ntex-client-save-{}.txt", std::process::id()));
+        let mut req = TestResponse::default()
+            .set_payload(Bytes::from_static(b"test data"))
+            .finish();
+        assert_eq!(req.save_to_file(&path).await.unwrap(), 9);
+        assert_eq!(fs::read(&path).unwrap(), b"test data");
+        let _ = fs::remove_file(&path);
+
+        let mut req = TestResponse::default().finish();
+        let res = req.save_to_file(std::env::temp_dir().join("ntex/not-exists/file"));
+        match res.await.err().unwrap() {
+            PayloadError::Io(_) => (),
+            _ => unreachable!("error"),
+        }
+    }
+
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
     struct MyObject {
         name: String,
@@ -0,0 +1,312 @@
+use std::{rc::Rc, time::SystemTime};
+
+use crate::http::body::Body;
+use crate::http::header::RETRY_AFTER;
+use crate::http::{Method, RequestHeadType, StatusCode};
+use crate::time::{now, sleep, Millis};
+
+use super::connect::Connect;
+use super::error::SendRequestError;
+use super::response::ClientResponse;
+use super::RequestAddrs;
+
+/// Request retry policy
+///
+/// Policy retries requests that failed to connect to the host and requests
+/// that received response with one of retryable status codes. Delay between
+/// attempts grows exponentially, `Retry-After` response header overrides
+/// computed delay but is still limited with max delay. Only idempotent requests are retried by default.
+/// Requests with streaming body are never retried.
+///
+/// ```rust
+/// use ntex::http::client::{Client, RetryPolicy};
+/// use ntex::time::Millis;
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let client = Client::build()
+///         .retry(
+///             RetryPolicy::new()
+///                 .max_retries(5)
+///                 .backoff(Millis(50), Millis(2_000))
+///                 .budget(Millis(10_000)),
+///         )
+///         .finish();
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_retries: usize,
+    base_delay: Millis,
+    max_delay: Millis,
+    budget: Millis,
+    statuses: Vec<StatusCode>,
+    all_methods: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new()
+    }
+}
+
+impl RetryPolicy {
+    /// Create retry policy with default settings.
+    ///
+    /// By default request is retried 3 times, backoff starts at 100 milliseconds
+    /// and is limited with 5 seconds. Retryable status codes are `429`, `502`,
+    /// `503` and `504`.
+    pub fn new() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Millis(100),
+            max_delay: Millis(5_000),
+            budget: Millis::ZERO,
+            statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            all_methods: false,
+        }
+    }
+
+    /// Set max number of retries.
+    pub fn max_retries(mut self, num: usize) -> Self {
+        self.max_retries = num;
+        self
+    }
+
+    /// Set exponential backoff parameters.
+    ///
+    /// Delay before n-th retry is `base * 2^n` limited with `max` delay.
+    /// Delay requested by server with `Retry-After` header is limited
+    /// with `max` delay as well.
+    pub fn backoff<T: Into<Millis>>(mut self, base: T, max: T) -> Self {
+        self.base_delay = base.into();
+        self.max_delay = max.into();
+        self
+    }
+
+    /// Set retries time budget.
+    ///
+    /// Request is not retried if the next attempt would start after the budget
+    /// is exhausted, time is counted from the first attempt. Request timeout
+    /// limits all attempts regardless of this setting.
+    ///
+    /// By default budget is not set.
+    pub fn budget<T: Into<Millis>>(mut self, budget: T) -> Self {
+        self.budget = budget.into();
+        self
+    }
+
+    /// Set retryable response status codes.
+    pub fn statuses<I>(mut self, statuses: I) -> Self
+    where
+        I: IntoIterator<Item = StatusCode>,
+    {
+        self.statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Retry requests with non-idempotent methods.
+    ///
+    /// By default only `GET`, `HEAD`, `OPTIONS`, `TRACE`, `PUT` and `DELETE`
+    /// requests are retried.
+    pub fn non_idempotent(mut self) -> Self {
+        self.all_methods = true;
+        self
+    }
+
+    /// Check if request could be retried
+    pub(super) fn is_applicable(&self, head: &RequestHeadType, body: &Body) -> bool {
+        self.max_retries > 0
+            && !matches!(body, Body::Message(_))
+            && (self.all_methods || is_idempotent(&head.as_ref().method))
+    }
+
+    /// Delay before retry
+    fn delay(&self, attempt: usize) -> Millis {
+        let delay = self
+            .base_delay
+            .0
+            .saturating_mul(1u64 << attempt.min(32) as u32);
+        Millis(std::cmp::min(delay, self.max_delay.0))
+    }
+
+    /// Delay before retry of retryable response
+    fn response_delay(&self, res: &ClientResponse, attempt: usize) -> Millis {
+        retry_after(res)
+            .map(|delay| std::cmp::min(delay, self.max_delay))
+            .unwrap_or_else(|| self.delay(attempt))
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET
+            | Method::HEAD
+            | Method::OPTIONS
+            | Method::TRACE
+            | Method::PUT
+            | Method::DELETE
+    )
+}
+
+/// Parse `Retry-After` header, delay-seconds or http-date
+fn retry_after(res: &ClientResponse) -> Option<Millis> {
+    let value = res.headers().get(&RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        Some(Millis(secs.saturating_mul(1000)))
+    } else {
+        let date = httpdate::parse_http_date(value).ok()?;
+        Some(
+            date.duration_since(SystemTime::now())
+                .map(Millis::from)
+                .unwrap_or(Millis::ZERO),
+        )
+    }
+}
+
+pub(super) fn clone_body(body: &Body) -> Body {
+    match body {
+        Body::None => Body::None,
+        Body::Empty => Body::Empty,
+        Body::Bytes(b) => Body::Bytes(b.clone()),
+        Body::Message(_) => unreachable!(),
+    }
+}
+
+/// Send request, retry failed attempts according to the policy
+pub(super) async fn send(
+    policy: Rc<RetryPolicy>,
+    connector: Rc<dyn Connect>,
+    head: RequestHeadType,
+    body: Body,
+    addr: RequestAddrs,
+) -> Result<ClientResponse, SendRequestError> {
+    let start = now();
+    let (head, extra_headers) = match head {
+        RequestHeadType::Owned(head) => (Rc::new(head), None),
+        RequestHeadType::Rc(head, extra_headers) => (head, extra_headers),
+    };
+    let mut attempt = 0;
+
+    loop {
+        let res = connector
+            .send_request(
+                RequestHeadType::Rc(head.clone(), extra_headers.clone()),
+                clone_body(&body),
+                addr.clone(),
+            )
+            .await;
+        if attempt >= policy.max_retries {
+            return res;
+        }
+
+        let delay = match res {
+            Err(SendRequestError::Connect(_)) => policy.delay(attempt),
+            Ok(ref res) if policy.statuses.contains(&res.status()) => {
+                policy.response_delay(res, attempt)
+            }
+            _ => return res,
+        };
+        if !policy.budget.is_zero()
+            && now() - start + std::time::Duration::from(delay)
+                > std::time::Duration::from(policy.budget)
+        {
+            return res;
+        }
+        drop(res);
+
+        attempt += 1;
+        log::trace!(
+            "Retry request to {:?} in {:?}, attempt {}",
+            head.uri,
+            delay,
+            attempt
+        );
+        sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::new().backoff(Millis(100), Millis(1_000));
+        assert_eq!(policy.delay(0), Millis(100));
+        assert_eq!(policy.delay(1), Millis(200));
+        assert_eq!(policy.delay(3), Millis(800));
+        assert_eq!(policy.delay(4), Millis(1_000));
+        assert_eq!(policy.delay(100), Millis(1_000));
+    }
+
+    #[crate::rt_test]
+    async fn test_retry_after() {
+        use crate::http::client::test::TestResponse;
+
+        let res = TestResponse::with_header(RETRY_AFTER, "5").finish();
+        assert_eq!(retry_after(&res), Some(Millis(5_000)));
+
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(10));
+        let res = TestResponse::with_header(RETRY_AFTER, date).finish();
+        let delay = retry_after(&res).unwrap();
+        assert!(delay > Millis(8_000) && delay <= Millis(10_000));
+
+        let date = httpdate::fmt_http_date(SystemTime::now() - Duration::from_secs(10));
+        let res = TestResponse::with_header(RETRY_AFTER, date).finish();
+        assert_eq!(retry_after(&res), Some(Millis::ZERO));
+
+        let res = TestResponse::with_header(RETRY_AFTER, "soon").finish();
+        assert_eq!(retry_after(&res), None);
+        assert_eq!(retry_after(&TestResponse::default().finish()), None);
+    }
+
+    #[crate::rt_test]
+    async fn test_retry_after_max_delay() {
+        use crate::http::client::test::TestResponse;
+
+        let policy = RetryPolicy::new().backoff(Millis(100), Millis(1_000));
+
+        let res = TestResponse::with_header(RETRY_AFTER, "3600").finish();
+        assert_eq!(policy.response_delay(&res, 0), Millis(1_000));
+
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(600));
+        let res = TestResponse::with_header(RETRY_AFTER, date).finish();
+        assert_eq!(policy.response_delay(&res, 0), Millis(1_000));
+
+        let res = TestResponse::with_header(RETRY_AFTER, "0").finish();
+        assert_eq!(policy.response_delay(&res, 2), Millis::ZERO);
+
+        let res = TestResponse::default().finish();
+        assert_eq!(policy.response_delay(&res, 2), Millis(400));
+    }
+
+    #[test]
+    fn test_is_applicable() {
+        let head = |method| {
+            let mut head = crate::http::RequestHead::default();
+            head.method = method;
+            RequestHeadType::from(head)
+        };
+
+        let policy = RetryPolicy::new();
+        assert!(policy.is_applicable(&head(Method::GET), &Body::None));
+        assert!(policy.is_applicable(&head(Method::PUT), &Body::from("data")));
+        assert!(!policy.is_applicable(&head(Method::GET), &Body::from_message("data")));
+        assert!(!policy.is_applicable(&head(Method::POST), &Body::None));
+        assert!(RetryPolicy::new()
+            .non_idempotent()
+            .is_applicable(&head(Method::POST), &Body::None));
+        assert!(!RetryPolicy::new()
+            .max_retries(0)
+            .is_applicable(&head(Method::GET), &Body::None));
+    }
+}
@@ -1,14 +1,17 @@
 use std::task::{Context, Poll};
-use std::{convert::TryFrom, error::Error, future::Future, net, pin::Pin};
+use std::{convert::TryFrom, error::Error, future::Future, io, pin::Pin, rc::Rc};
 
 use serde::Serialize;
+use tok_io::io::{AsyncRead, ReadBuf};
 
-use crate::http::body::{Body, BodyStream};
+use crate::http::body::{Body, BodySize, BodyStream, MessageBody};
 use crate::http::error::HttpError;
 use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
 use crate::http::RequestHeadType;
+use crate::io::IoBoxed;
 use crate::time::{sleep, Millis, Sleep};
-use crate::{util::Bytes, Stream};
+use crate::util::{Bytes, BytesMut};
+use crate::Stream;
 
 #[cfg(feature = "compress")]
 use crate::http::encoding::Decoder;
@@ -16,8 +19,9 @@ use crate::http::encoding::Decoder;
 use crate::http::Payload;
 
 use super::error::{FreezeRequestError, InvalidUrl, SendRequestError};
+use super::multipart::Form;
 use super::response::ClientResponse;
-use super::ClientConfig;
+use super::{redirect, retry, ClientConfig, RequestAddrs, RequestSigner, RetryPolicy};
 
 #[derive(Debug, From)]
 pub(crate) enum PrepForSendingError {
@@ -49,7 +53,7 @@ pub enum SendClientRequest {
     Fut(
         Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>>,
         Option<Sleep>,
-        bool,
+        Option<usize>,
     ),
     Err(Option<SendRequestError>),
 }
@@ -57,7 +61,7 @@ pub enum SendClientRequest {
 impl SendClientRequest {
     pub(crate) fn new(
         send: Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>>,
-        response_decompress: bool,
+        response_decompress: Option<usize>,
         timeout: Millis,
     ) -> SendClientRequest {
         SendClientRequest::Fut(send, timeout.map(sleep), response_decompress)
@@ -86,12 +90,19 @@ impl Future for SendClientRequest {
 
                 #[cfg(feature = "compress")]
                 let res = res.map(|mut res| {
-                    if *_response_decompress {
-                        let payload = res.take_payload();
-                        res.set_payload(Payload::from_stream(Decoder::from_headers(
-                            payload,
-                            &res.head.headers,
-                        )))
+                    if let Some(limit) = *_response_decompress {
+                        #[cfg(feature = "content-digest")]
+                        crate::http::digest::mark_decoded(&res.head);
+
+                        // identity payloads are not wrapped, so payload
+                        // trailers stay available
+                        if res.head.headers.contains_key(&header::CONTENT_ENCODING) {
+                            let payload = res.take_payload();
+                            res.set_payload(Payload::from_stream(
+                                Decoder::from_headers(payload, &res.head.headers)
+                                    .limit(limit),
+                            ))
+                        }
                     }
                     res
                 });
@@ -127,10 +138,11 @@ impl From<PrepForSendingError> for SendClientRequest {
 impl RequestHeadType {
     pub(super) fn send_body<B>(
         self,
-        addr: Option<net::SocketAddr>,
+        addr: RequestAddrs,
         response_decompress: bool,
         mut timeout: Millis,
-        config: &ClientConfig,
+        retry: Option<Rc<RetryPolicy>>,
+        config: &Rc<ClientConfig>,
         body: B,
     ) -> SendClientRequest
     where
@@ -140,19 +152,82 @@ impl RequestHeadType {
             timeout = config.timeout;
         }
 
+        let fut = match config.redirect {
+            Some(ref policy) => Box::pin(redirect::send(
+                policy.clone(),
+                config.clone(),
+                retry,
+                self,
+                body.into(),
+                addr,
+            )),
+            None => match self.send_request(addr, retry, config, body.into()) {
+                Ok(fut) => fut,
+                Err(e) => return e.into(),
+            },
+        };
+        let fut = if config.sensitive.is_empty() {
+            fut
+        } else {
+            let sensitive = config.sensitive.clone();
+            Box::pin(async move {
+                fut.await.map(|mut res| {
+                    for name in sensitive.iter() {
+                        res.head.headers.set_sensitive(name);
+                    }
+                    res
+                })
+            })
+        };
+
         SendClientRequest::new(
-            config.connector.send_request(self, body.into(), addr),
-            response_decompress,
+            fut,
+            if response_decompress {
+                Some(config.decompress_limit)
+            } else {
+                None
+            },
             timeout,
         )
     }
 
+    /// Sign request and send it, failed attempts are retried according to the policy
+    pub(super) fn send_request(
+        self,
+        addr: RequestAddrs,
+        retry: Option<Rc<RetryPolicy>>,
+        config: &ClientConfig,
+        body: Body,
+    ) -> Result<
+        Pin<Box<dyn Future<Output = Result<ClientResponse, SendRequestError>>>>,
+        SendRequestError,
+    > {
+        let (mut head, body) = if let Some(ref signer) = config.signer {
+            self.sign(signer.as_ref(), body)?
+        } else {
+            (self, body)
+        };
+        head.set_sensitive(&config.sensitive);
+
+        Ok(match retry {
+            Some(policy) if policy.is_applicable(&head, &body) => Box::pin(retry::send(
+                policy,
+                config.connector.clone(),
+                head,
+                body,
+                addr,
+            )),
+            _ => config.connector.send_request(head, body, addr),
+        })
+    }
+
     pub(super) fn send_json<T: Serialize>(
         mut self,
-        addr: Option<net::SocketAddr>,
+        addr: RequestAddrs,
         response_decompress: bool,
         timeout: Millis,
-        config: &ClientConfig,
+        retry: Option<Rc<RetryPolicy>>,
+        config: &Rc<ClientConfig>,
         value: &T,
     ) -> SendClientRequest {
         let body = match serde_json::to_string(value) {
@@ -168,6 +243,7 @@ impl RequestHeadType {
             addr,
             response_decompress,
             timeout,
+            retry,
             config,
             Body::Bytes(Bytes::from(body)),
         )
@@ -175,10 +251,11 @@ impl RequestHeadType {
 
     pub(super) fn send_form<T: Serialize>(
         mut self,
-        addr: Option<net::SocketAddr>,
+        addr: RequestAddrs,
         response_decompress: bool,
         timeout: Millis,
-        config: &ClientConfig,
+        retry: Option<Rc<RetryPolicy>>,
+        config: &Rc<ClientConfig>,
         value: &T,
     ) -> SendClientRequest {
         let body = match serde_urlencoded::to_string(value) {
@@ -197,6 +274,7 @@ impl RequestHeadType {
             addr,
             response_decompress,
             timeout,
+            retry,
             config,
             Body::Bytes(Bytes::from(body)),
         )
@@ -204,33 +282,173 @@ impl RequestHeadType {
 
     pub(super) fn send_stream<S, E>(
         self,
-        addr: Option<net::SocketAddr>,
+        addr: RequestAddrs,
         response_decompress: bool,
         timeout: Millis,
-        config: &ClientConfig,
+        config: &Rc<ClientConfig>,
         stream: S,
     ) -> SendClientRequest
     where
         S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
         E: Error + 'static,
     {
+        // stream with known size is sent without transfer encoding
+        let body = if let Some(size) = self.content_length() {
+            Body::from_message(SizedBody(size, BodyStream::new(stream)))
+        } else {
+            Body::from_message(BodyStream::new(stream))
+        };
+        self.send_body(addr, response_decompress, timeout, None, config, body)
+    }
+
+    pub(super) fn send_reader<R>(
+        self,
+        addr: RequestAddrs,
+        response_decompress: bool,
+        timeout: Millis,
+        config: &Rc<ClientConfig>,
+        reader: R,
+        len: Option<u64>,
+    ) -> SendClientRequest
+    where
+        R: AsyncRead + Unpin + 'static,
+    {
+        let len = len.or_else(|| self.content_length());
         self.send_body(
             addr,
             response_decompress,
             timeout,
+            None,
             config,
-            Body::from_message(BodyStream::new(stream)),
+            Body::from_message(ReaderBody::new(reader, len)),
         )
     }
 
+    pub(super) fn send_multipart(
+        mut self,
+        addr: RequestAddrs,
+        response_decompress: bool,
+        timeout: Millis,
+        config: &Rc<ClientConfig>,
+        form: Form,
+    ) -> SendClientRequest {
+        if let Err(e) = self.set_header_if_none(header::CONTENT_TYPE, form.content_type()) {
+            return e.into();
+        }
+
+        self.send_body(
+            addr,
+            response_decompress,
+            timeout,
+            None,
+            config,
+            form.into_body(),
+        )
+    }
+
+    /// Content length set by request headers
+    fn content_length(&self) -> Option<u64> {
+        let value = match self {
+            RequestHeadType::Rc(_, Some(extra))
+                if extra.contains_key(header::CONTENT_LENGTH) =>
+            {
+                extra.get(header::CONTENT_LENGTH)
+            }
+            _ => self.as_ref().headers.get(header::CONTENT_LENGTH),
+        };
+        value
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok())
+    }
+
     pub(super) fn send(
         self,
-        addr: Option<net::SocketAddr>,
+        addr: RequestAddrs,
         response_decompress: bool,
         timeout: Millis,
-        config: &ClientConfig,
+        retry: Option<Rc<RetryPolicy>>,
+        config: &Rc<ClientConfig>,
     ) -> SendClientRequest {
-        self.send_body(addr, response_decompress, timeout, config, Body::None)
+        self.send_body(
+            addr,
+            response_decompress,
+            timeout,
+            retry,
+            config,
+            Body::None,
+        )
+    }
+
+    pub(super) fn open_tunnel(
+        self,
+        addr: RequestAddrs,
+        mut timeout: Millis,
+        config: &ClientConfig,
+    ) -> Pin<
+        Box<
+            dyn Future<
+                Output = Result<(ClientResponse, Option<IoBoxed>), SendRequestError>,
+            >,
+        >,
+    > {
+        if timeout.is_zero() {
+            timeout = config.timeout;
+        }
+
+        let mut head = if let Some(ref signer) = config.signer {
+            match self.sign(signer.as_ref(), Body::None) {
+                Ok((head, _)) => head,
+                Err(e) => return Box::pin(async move { Err(e) }),
+            }
+        } else {
+            self
+        };
+        head.set_sensitive(&config.sensitive);
+
+        let fut = config.connector.open_tunnel(head, addr);
+        Box::pin(async move {
+            if timeout.is_zero() {
+                fut.await
+            } else {
+                crate::time::timeout(timeout, fut)
+                    .await
+                    .map_err(|_| SendRequestError::Timeout)
+                    .and_then(|res| res)
+            }
+        })
+    }
+
+    /// Mark values of sensitive headers
+    fn set_sensitive(&mut self, names: &[HeaderName]) {
+        let headers = match self {
+            RequestHeadType::Owned(ref mut head) => &mut head.headers,
+            RequestHeadType::Rc(_, Some(ref mut extra)) => extra,
+            RequestHeadType::Rc(_, None) => return,
+        };
+        for name in names {
+            headers.set_sensitive(name);
+        }
+    }
+
+    fn sign(
+        self,
+        signer: &dyn RequestSigner,
+        body: Body,
+    ) -> Result<(Self, Body), SendRequestError> {
+        match self {
+            RequestHeadType::Owned(mut head) => {
+                let body = signer.sign(&head.method, &head.uri, &mut head.headers, body)?;
+                Ok((RequestHeadType::Owned(head), body))
+            }
+            RequestHeadType::Rc(head, extra_headers) => {
+                let mut headers = head.headers.clone();
+                if let Some(ref extra) = extra_headers {
+                    redirect::merge_headers(&mut headers, extra);
+                }
+                let body = signer.sign(&head.method, &head.uri, &mut headers, body)?;
+                Ok((RequestHeadType::Rc(head, Some(headers)), body))
+            }
+        }
     }
 
     fn set_header_if_none<V>(&mut self, key: HeaderName, value: V) -> Result<(), HttpError>
@@ -265,3 +483,86 @@ impl RequestHeadType {
         Ok(())
     }
 }
+
+/// Streaming body with size set by request headers
+pub(super) struct SizedBody<B>(pub(super) u64, pub(super) B);
+
+impl<B: MessageBody> MessageBody for SizedBody<B> {
+    fn size(&self) -> BodySize {
+        BodySize::Sized(self.0)
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        self.1.poll_next_chunk(cx)
+    }
+}
+
+/// Size of chunk read from `AsyncRead` body
+const READ_CHUNK: usize = 64 * 1024;
+
+/// Body that reads data from `AsyncRead` object
+///
+/// Reader is polled only when previous chunk is sent, so write
+/// back-pressure is propagated to reader.
+pub(super) struct ReaderBody<R> {
+    reader: R,
+    size: Option<u64>,
+    remaining: u64,
+}
+
+impl<R: AsyncRead + Unpin> ReaderBody<R> {
+    pub(super) fn new(reader: R, size: Option<u64>) -> Self {
+        ReaderBody {
+            reader,
+            size,
+            remaining: size.unwrap_or(u64::MAX),
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> MessageBody for ReaderBody<R> {
+    fn size(&self) -> BodySize {
+        match self.size {
+            Some(size) => BodySize::Sized(size),
+            None => BodySize::Stream,
+        }
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if self.remaining == 0 {
+            return Poll::Ready(None);
+        }
+
+        let size = std::cmp::min(READ_CHUNK as u64, self.remaining) as usize;
+        let mut buf = BytesMut::with_capacity(size);
+        buf.resize(size, 0);
+        let mut rbuf = ReadBuf::new(&mut buf[..]);
+        let result = match Pin::new(&mut self.reader).poll_read(cx, &mut rbuf) {
+            Poll::Ready(Ok(())) => Ok(rbuf.filled().len()),
+            Poll::Ready(Err(e)) => Err(e),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        match result {
+            Ok(0) if self.size.is_some() => {
+                Poll::Ready(Some(Err(Box::new(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("Reader is closed, {} bytes are not sent", self.remaining),
+                )))))
+            }
+            Ok(0) => Poll::Ready(None),
+            Ok(n) => {
+                buf.truncate(n);
+                self.remaining -= n as u64;
+                Poll::Ready(Some(Ok(buf.freeze())))
+            }
+            Err(e) => Poll::Ready(Some(Err(Box::new(e)))),
+        }
+    }
+}
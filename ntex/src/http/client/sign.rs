@@ -0,0 +1,813 @@
+//! Client request signing
+use crate::http::body::Body;
+use crate::http::{HeaderMap, Method, Uri};
+
+use super::error::SendRequestError;
+
+/// Request signer
+///
+/// Signer is called before request is sent. Signer adds signature headers
+/// to request headers and could replace request body, for example to sign
+/// streamed payload chunk by chunk.
+pub trait RequestSigner {
+    /// Sign request
+    fn sign(
+        &self,
+        method: &Method,
+        uri: &Uri,
+        headers: &mut HeaderMap,
+        body: Body,
+    ) -> Result<Body, SendRequestError>;
+}
+
+#[cfg(feature = "signing")]
+pub use self::schemes::{HmacSigner, SigV4};
+
+#[cfg(feature = "signing")]
+mod schemes {
+    use std::task::{Context, Poll};
+    use std::{collections::BTreeMap, convert::TryFrom, error::Error, fmt::Write};
+    use std::{io, time::SystemTime};
+
+    use percent_encoding::{
+        percent_decode_str, percent_encode, AsciiSet, NON_ALPHANUMERIC,
+    };
+    use ring::{digest, hmac};
+
+    use crate::http::body::{BodySize, MessageBody};
+    use crate::http::header::{self, HeaderName, HeaderValue};
+    use crate::util::{Bytes, BytesMut};
+
+    use super::*;
+
+    const EMPTY_SHA256: &str =
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    const STREAMING_PAYLOAD: &str = "STREAMING-AWS4-HMAC-SHA256-PAYLOAD";
+    const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+    /// Characters that are not encoded in aws canonical query
+    const AWS_QUERY: &AsciiSet = &NON_ALPHANUMERIC
+        .remove(b'-')
+        .remove(b'_')
+        .remove(b'.')
+        .remove(b'~');
+
+    /// Headers that are never signed
+    const UNSIGNED_HEADERS: &[&str] = &[
+        "authorization",
+        "connection",
+        "expect",
+        "transfer-encoding",
+        "user-agent",
+        "x-amzn-trace-id",
+    ];
+
+    /// AWS Signature Version 4 signer
+    ///
+    /// Payload of `Bytes` body is hashed. Streamed body with known size is
+    /// sent with `aws-chunked` content encoding, every chunk is signed.
+    /// Streamed body with unknown size is sent as unsigned payload.
+    ///
+    /// ```rust
+    /// use ntex::http::client::{Client, SigV4};
+    ///
+    /// #[ntex::main]
+    /// async fn main() {
+    ///     let client = Client::build()
+    ///         .signer(SigV4::new("AKID", "SECRET", "us-east-1", "s3"))
+    ///         .finish();
+    /// }
+    /// ```
+    #[derive(Clone, Debug)]
+    pub struct SigV4 {
+        access_key: String,
+        secret_key: String,
+        session_token: Option<String>,
+        region: String,
+        service: String,
+        content_sha256: bool,
+        chunk_size: usize,
+        time: Option<SystemTime>,
+    }
+
+    impl SigV4 {
+        /// Create new signer
+        pub fn new(
+            access_key: &str,
+            secret_key: &str,
+            region: &str,
+            service: &str,
+        ) -> Self {
+            SigV4 {
+                access_key: access_key.to_string(),
+                secret_key: secret_key.to_string(),
+                session_token: None,
+                region: region.to_string(),
+                service: service.to_string(),
+                content_sha256: true,
+                chunk_size: 65536,
+                time: None,
+            }
+        }
+
+        /// Set session token, token is sent with `x-amz-security-token` header.
+        pub fn session_token(mut self, token: &str) -> Self {
+            self.session_token = Some(token.to_string());
+            self
+        }
+
+        /// Send payload hash with `x-amz-content-sha256` header.
+        ///
+        /// Header is required by s3. Header is always sent for
+        /// streamed payload. By default header is sent.
+        pub fn content_sha256(mut self, enabled: bool) -> Self {
+            self.content_sha256 = enabled;
+            self
+        }
+
+        /// Set chunk size for streamed payload.
+        ///
+        /// Min chunk size is 8Kb. By default chunk size is 64Kb.
+        pub fn chunk_size(mut self, size: usize) -> Self {
+            self.chunk_size = std::cmp::max(size, 8192);
+            self
+        }
+
+        fn signing_key(&self, date: &str) -> hmac::Key {
+            let key = sign(format!("AWS4{}", self.secret_key).as_bytes(), date);
+            let key = sign(key.as_ref(), &self.region);
+            let key = sign(key.as_ref(), &self.service);
+            let key = sign(key.as_ref(), "aws4_request");
+            hmac::Key::new(hmac::HMAC_SHA256, key.as_ref())
+        }
+    }
+
+    impl RequestSigner for SigV4 {
+        fn sign(
+            &self,
+            method: &Method,
+            uri: &Uri,
+            headers: &mut HeaderMap,
+            body: Body,
+        ) -> Result<Body, SendRequestError> {
+            let (date, timestamp) = amz_date(self.time.unwrap_or_else(SystemTime::now));
+            set_host(uri, headers)?;
+            insert(headers, "x-amz-date", &timestamp)?;
+            if let Some(ref token) = self.session_token {
+                insert(headers, "x-amz-security-token", token)?;
+            }
+
+            let (payload, stream) = match body {
+                Body::None | Body::Empty => (EMPTY_SHA256.to_string(), None),
+                Body::Bytes(ref b) => (sha256_hex(b), None),
+                Body::Message(ref msg) => match msg.size() {
+                    BodySize::Sized(size) => (STREAMING_PAYLOAD.to_string(), Some(size)),
+                    _ => (UNSIGNED_PAYLOAD.to_string(), None),
+                },
+            };
+            if let Some(size) = stream {
+                let encoding = match headers.get(header::CONTENT_ENCODING) {
+                    Some(val) => format!("aws-chunked,{}", val.to_str().unwrap_or("")),
+                    None => "aws-chunked".to_string(),
+                };
+                insert(headers, "content-encoding", &encoding)?;
+                insert(headers, "x-amz-decoded-content-length", &size.to_string())?;
+                headers.remove(header::CONTENT_LENGTH);
+            }
+            if self.content_sha256 || stream.is_some() {
+                insert(headers, "x-amz-content-sha256", &payload)?;
+            }
+
+            // canonical request
+            let (canonical_headers, signed_headers) = canonical_headers(headers);
+            let path = if uri.path().is_empty() {
+                "/"
+            } else {
+                uri.path()
+            };
+            let canonical = format!(
+                "{}\n{}\n{}\n{}\n{}\n{}",
+                method.as_str(),
+                path,
+                canonical_query(uri.query().unwrap_or("")),
+                canonical_headers,
+                signed_headers,
+                payload
+            );
+
+            let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+            let string_to_sign = format!(
+                "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+                timestamp,
+                scope,
+                sha256_hex(canonical.as_bytes())
+            );
+            let key = self.signing_key(&date);
+            let signature = hex(hmac::sign(&key, string_to_sign.as_bytes()).as_ref());
+
+            insert(
+                headers,
+                "authorization",
+                &format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key, scope, signed_headers, signature
+                ),
+            )?;
+
+            match (body, stream) {
+                (Body::Message(msg), Some(size)) => Ok(Body::from_message(ChunkedBody {
+                    key,
+                    scope,
+                    timestamp,
+                    size,
+                    body: msg,
+                    signature,
+                    read: 0,
+                    eof: false,
+                    chunk_size: self.chunk_size,
+                    buf: BytesMut::new(),
+                })),
+                (body, _) => Ok(body),
+            }
+        }
+    }
+
+    /// Aws chunked payload, every chunk contains signature of
+    /// the chunk data and previous chunk signature.
+    struct ChunkedBody {
+        body: Box<dyn MessageBody>,
+        key: hmac::Key,
+        scope: String,
+        timestamp: String,
+        signature: String,
+        size: u64,
+        read: u64,
+        eof: bool,
+        chunk_size: usize,
+        buf: BytesMut,
+    }
+
+    impl ChunkedBody {
+        fn frame_len(len: usize) -> u64 {
+            // hex length, ";chunk-signature=", signature, crlf, data, crlf
+            (format!("{:x}", len).len() + 17 + 64 + 2 + len + 2) as u64
+        }
+
+        fn encoded_len(size: u64, chunk_size: usize) -> u64 {
+            let full = size / chunk_size as u64;
+            let rem = (size % chunk_size as u64) as usize;
+            let mut len = full * Self::frame_len(chunk_size) + Self::frame_len(0);
+            if rem != 0 {
+                len += Self::frame_len(rem);
+            }
+            len
+        }
+
+        fn frame(&mut self, data: &[u8]) -> Bytes {
+            let string_to_sign = format!(
+                "AWS4-HMAC-SHA256-PAYLOAD\n{}\n{}\n{}\n{}\n{}",
+                self.timestamp,
+                self.scope,
+                self.signature,
+                EMPTY_SHA256,
+                sha256_hex(data)
+            );
+            self.signature = hex(hmac::sign(&self.key, string_to_sign.as_bytes()).as_ref());
+
+            let mut buf = BytesMut::with_capacity(data.len() + 90);
+            let _ = write!(
+                buf,
+                "{:x};chunk-signature={}\r\n",
+                data.len(),
+                self.signature
+            );
+            buf.extend_from_slice(data);
+            buf.extend_from_slice(b"\r\n");
+            buf.freeze()
+        }
+    }
+
+    impl MessageBody for ChunkedBody {
+        fn size(&self) -> BodySize {
+            BodySize::Sized(Self::encoded_len(self.size, self.chunk_size))
+        }
+
+        fn poll_next_chunk(
+            &mut self,
+            cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+            loop {
+                if self.buf.len() >= self.chunk_size {
+                    let data = self.buf.split_to(self.chunk_size);
+                    return Poll::Ready(Some(Ok(self.frame(&data))));
+                }
+                if self.eof {
+                    return if self.buf.is_empty() {
+                        Poll::Ready(None)
+                    } else {
+                        let data = self.buf.split();
+                        let mut frame = BytesMut::from(&self.frame(&data)[..]);
+                        frame.extend_from_slice(&self.frame(b"")[..]);
+                        Poll::Ready(Some(Ok(frame.freeze())))
+                    };
+                }
+
+                match self.body.poll_next_chunk(cx) {
+                    Poll::Ready(Some(Ok(chunk))) => {
+                        self.read += chunk.len() as u64;
+                        self.buf.extend_from_slice(&chunk);
+                    }
+                    Poll::Ready(None) => {
+                        if self.read != self.size {
+                            return Poll::Ready(Some(Err(Box::new(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                "Payload size does not match body size",
+                            )))));
+                        }
+                        self.eof = true;
+                        if self.buf.is_empty() {
+                            return Poll::Ready(Some(Ok(self.frame(b""))));
+                        }
+                    }
+                    Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+        }
+    }
+
+    /// Hmac http signature signer
+    ///
+    /// Signer implements `hmac-sha256` algorithm of
+    /// [HTTP Signatures](https://tools.ietf.org/html/draft-cavage-http-signatures-12)
+    /// scheme. Payload digest is sent with `digest` header, digest is not
+    /// available for streamed payload.
+    ///
+    /// ```rust
+    /// use ntex::http::client::{Client, HmacSigner};
+    ///
+    /// #[ntex::main]
+    /// async fn main() {
+    ///     let client = Client::build()
+    ///         .signer(
+    ///             HmacSigner::new("key-id", b"secret")
+    ///                 .headers(&["(request-target)", "host", "date", "digest"]),
+    ///         )
+    ///         .finish();
+    /// }
+    /// ```
+    #[derive(Clone)]
+    pub struct HmacSigner {
+        key_id: String,
+        key: hmac::Key,
+        headers: Vec<String>,
+        time: Option<SystemTime>,
+    }
+
+    impl HmacSigner {
+        /// Create new signer
+        pub fn new<T: AsRef<[u8]>>(key_id: &str, secret: T) -> Self {
+            HmacSigner {
+                key_id: key_id.to_string(),
+                key: hmac::Key::new(hmac::HMAC_SHA256, secret.as_ref()),
+                headers: vec![
+                    "(request-target)".to_string(),
+                    "host".to_string(),
+                    "date".to_string(),
+                    "digest".to_string(),
+                ],
+                time: None,
+            }
+        }
+
+        /// Set list of signed headers.
+        ///
+        /// Headers that are not present in request are skipped. By default
+        /// `(request-target)`, `host`, `date` and `digest` are signed.
+        pub fn headers(mut self, headers: &[&str]) -> Self {
+            self.headers = headers.iter().map(|h| h.to_lowercase()).collect();
+            self
+        }
+    }
+
+    impl std::fmt::Debug for HmacSigner {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("HmacSigner")
+                .field("key_id", &self.key_id)
+                .field("headers", &self.headers)
+                .finish()
+        }
+    }
+
+    impl RequestSigner for HmacSigner {
+        fn sign(
+            &self,
+            method: &Method,
+            uri: &Uri,
+            headers: &mut HeaderMap,
+            body: Body,
+        ) -> Result<Body, SendRequestError> {
+            set_host(uri, headers)?;
+            if !headers.contains_key(header::DATE) {
+                let date =
+                    httpdate::fmt_http_date(self.time.unwrap_or_else(SystemTime::now));
+                insert(headers, "date", &date)?;
+            }
+            let digest = match body {
+                Body::None | Body::Empty => Some(digest::digest(&digest::SHA256, b"")),
+                Body::Bytes(ref b) => Some(digest::digest(&digest::SHA256, b)),
+                Body::Message(_) => None,
+            };
+            if let Some(digest) = digest {
+                let digest = format!("SHA-256={}", base64::encode(digest.as_ref()));
+                insert(headers, "digest", &digest)?;
+            }
+
+            let mut names = Vec::new();
+            let mut string_to_sign = String::new();
+            for name in &self.headers {
+                let value = if name == "(request-target)" {
+                    let target = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+                    format!("{} {}", method.as_str().to_lowercase(), target)
+                } else if let Some(value) = headers.get(name.as_str()) {
+                    value.to_str().unwrap_or("").trim().to_string()
+                } else {
+                    continue;
+                };
+                if !names.is_empty() {
+                    string_to_sign.push('\n');
+                }
+                let _ = write!(string_to_sign, "{}: {}", name, value);
+                names.push(name.as_str());
+            }
+            let signature = hmac::sign(&self.key, string_to_sign.as_bytes());
+
+            insert(
+                headers,
+                "authorization",
+                &format!(
+                    "Signature keyId=\"{}\",algorithm=\"hmac-sha256\",headers=\"{}\",signature=\"{}\"",
+                    self.key_id,
+                    names.join(" "),
+                    base64::encode(signature.as_ref())
+                ),
+            )?;
+            Ok(body)
+        }
+    }
+
+    fn insert(
+        headers: &mut HeaderMap,
+        name: &str,
+        value: &str,
+    ) -> Result<(), SendRequestError> {
+        let name =
+            HeaderName::try_from(name).map_err(|e| SendRequestError::Http(e.into()))?;
+        let value =
+            HeaderValue::try_from(value).map_err(|e| SendRequestError::Http(e.into()))?;
+        headers.insert(name, value);
+        Ok(())
+    }
+
+    /// Set host header, same as http1 client does
+    fn set_host(uri: &Uri, headers: &mut HeaderMap) -> Result<(), SendRequestError> {
+        if !headers.contains_key(header::HOST) {
+            if let Some(host) = uri.host() {
+                let host = match uri.port_u16() {
+                    None | Some(80) | Some(443) => host.to_string(),
+                    Some(port) => format!("{}:{}", host, port),
+                };
+                insert(headers, "host", &host)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn canonical_headers(headers: &HeaderMap) -> (String, String) {
+        let mut map: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for (name, value) in headers.iter() {
+            if UNSIGNED_HEADERS.contains(&name.as_str()) {
+                continue;
+            }
+            let value = String::from_utf8_lossy(value.as_bytes());
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            map.entry(name.as_str()).or_default().push(value);
+        }
+
+        let mut canonical = String::new();
+        for (name, values) in &map {
+            let _ = writeln!(canonical, "{}:{}", name, values.join(","));
+        }
+        let signed = map.keys().copied().collect::<Vec<_>>().join(";");
+        (canonical, signed)
+    }
+
+    fn canonical_query(query: &str) -> String {
+        let encode = |s: &str| {
+            percent_encode(&percent_decode_str(s).collect::<Vec<_>>(), AWS_QUERY)
+                .to_string()
+        };
+
+        let mut params: Vec<(String, String)> = query
+            .split('&')
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let (key, value) = s.split_once('=').unwrap_or((s, ""));
+                (encode(key), encode(value))
+            })
+            .collect();
+        params.sort();
+        params
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    /// Returns `YYYYMMDD` date and `YYYYMMDDTHHMMSSZ` timestamp
+    fn amz_date(time: SystemTime) -> (String, String) {
+        let secs = time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let (hour, min, sec) = ((secs / 3600) % 24, (secs / 60) % 60, secs % 60);
+
+        // civil date from days since epoch
+        let z = (secs / 86400) as i64 + 719_468;
+        let era = z / 146_097;
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        let date = format!("{:04}{:02}{:02}", year, month, day);
+        let timestamp = format!("{}T{:02}{:02}{:02}Z", date, hour, min, sec);
+        (date, timestamp)
+    }
+
+    fn sign(key: &[u8], data: &str) -> hmac::Tag {
+        hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data.as_bytes())
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        hex(digest::digest(&digest::SHA256, data).as_ref())
+    }
+
+    fn hex(data: &[u8]) -> String {
+        let mut s = String::with_capacity(data.len() * 2);
+        for b in data {
+            let _ = write!(s, "{:02x}", b);
+        }
+        s
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::time::Duration;
+
+        use super::*;
+        use crate::http::body::BodyStream;
+        use crate::util::poll_fn;
+
+        fn time() -> SystemTime {
+            // 2015-08-30T12:36:00Z
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_440_938_160)
+        }
+
+        #[test]
+        fn test_amz_date() {
+            assert_eq!(
+                amz_date(time()),
+                ("20150830".to_string(), "20150830T123600Z".to_string())
+            );
+            assert_eq!(
+                amz_date(SystemTime::UNIX_EPOCH + Duration::from_secs(951_782_400)),
+                ("20000229".to_string(), "20000229T000000Z".to_string())
+            );
+        }
+
+        #[test]
+        fn test_canonical_query() {
+            assert_eq!(canonical_query(""), "");
+            assert_eq!(
+                canonical_query("b=2&a=1%2F2&c=a%20b&d"),
+                "a=1%2F2&b=2&c=a%20b&d="
+            );
+        }
+
+        #[test]
+        fn test_sigv4() {
+            // aws sigv4 test suite, get-vanilla
+            let mut signer = SigV4::new(
+                "AKIDEXAMPLE",
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "us-east-1",
+                "service",
+            )
+            .content_sha256(false);
+            signer.time = Some(time());
+
+            let uri = Uri::from_static("https://example.amazonaws.com/");
+            let mut headers = HeaderMap::new();
+            headers.insert(header::USER_AGENT, HeaderValue::from_static("ntex"));
+            let body = signer
+                .sign(&Method::GET, &uri, &mut headers, Body::None)
+                .unwrap();
+            assert!(matches!(body, Body::None));
+            assert_eq!(headers.get("x-amz-date").unwrap(), "20150830T123600Z");
+            assert_eq!(
+                headers.get(header::AUTHORIZATION).unwrap(),
+                "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+                 SignedHeaders=host;x-amz-date, \
+                 Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+            );
+
+            let mut headers = HeaderMap::new();
+            signer
+                .clone()
+                .content_sha256(true)
+                .sign(&Method::PUT, &uri, &mut headers, Body::from("data"))
+                .unwrap();
+            assert_eq!(
+                headers.get("x-amz-content-sha256").unwrap(),
+                "3a6eb0790f39ac87c94f3856b2dd2c5d110e6811602261a9a923d3bb23adc8b7"
+            );
+        }
+
+        #[crate::rt_test]
+        async fn test_sigv4_chunked() {
+            let mut signer = SigV4::new("AKID", "SECRET", "us-east-1", "s3").chunk_size(0);
+            signer.time = Some(time());
+
+            let data = Bytes::from(vec![b'a'; 10000]);
+            let stream = futures::stream::iter(vec![
+                Ok::<_, io::Error>(data.slice(..3000)),
+                Ok(data.slice(3000..)),
+            ]);
+            let uri = Uri::from_static("https://s3.amazonaws.com/bucket/key");
+            let mut headers = HeaderMap::new();
+            let body = Body::from_message(BodyStream::new(stream));
+            let body = BodySizeOverride(body, 10000);
+            let mut body = signer
+                .sign(&Method::PUT, &uri, &mut headers, Body::from_message(body))
+                .unwrap();
+
+            assert_eq!(headers.get("content-encoding").unwrap(), "aws-chunked");
+            assert_eq!(
+                headers.get("x-amz-decoded-content-length").unwrap(),
+                "10000"
+            );
+            assert_eq!(
+                headers.get("x-amz-content-sha256").unwrap(),
+                STREAMING_PAYLOAD
+            );
+            let auth = headers
+                .get(header::AUTHORIZATION)
+                .unwrap()
+                .to_str()
+                .unwrap();
+            let seed = auth.rsplit("Signature=").next().unwrap().to_string();
+
+            let size = match body.size() {
+                BodySize::Sized(size) => size,
+                _ => panic!(),
+            };
+            let mut encoded = BytesMut::new();
+            while let Some(chunk) = poll_fn(|cx| body.poll_next_chunk(cx)).await {
+                encoded.extend_from_slice(&chunk.unwrap());
+            }
+            assert_eq!(encoded.len() as u64, size);
+
+            // verify chunk signature chain
+            let key = signer.signing_key("20150830");
+            let mut prev = seed;
+            let mut buf = &encoded[..];
+            let mut lens = Vec::new();
+            loop {
+                let pos = buf.windows(2).position(|w| w == b"\r\n").unwrap();
+                let line = std::str::from_utf8(&buf[..pos]).unwrap();
+                let (len, sig) = line.split_once(";chunk-signature=").unwrap();
+                let len = usize::from_str_radix(len, 16).unwrap();
+                let data = &buf[pos + 2..pos + 2 + len];
+                let sts = format!(
+                    "AWS4-HMAC-SHA256-PAYLOAD\n20150830T123600Z\n\
+                     20150830/us-east-1/s3/aws4_request\n{}\n{}\n{}",
+                    prev,
+                    EMPTY_SHA256,
+                    sha256_hex(data)
+                );
+                assert_eq!(sig, hex(hmac::sign(&key, sts.as_bytes()).as_ref()));
+                prev = sig.to_string();
+                lens.push(len);
+                buf = &buf[pos + 2 + len + 2..];
+                if len == 0 {
+                    break;
+                }
+            }
+            assert!(buf.is_empty());
+            assert_eq!(lens, vec![8192, 1808, 0]);
+        }
+
+        #[crate::rt_test]
+        async fn test_sigv4_size_mismatch() {
+            let signer = SigV4::new("AKID", "SECRET", "us-east-1", "s3");
+            let stream = futures::stream::iter(vec![Ok::<_, io::Error>(
+                Bytes::from_static(b"data"),
+            )]);
+            let body = BodySizeOverride(Body::from_message(BodyStream::new(stream)), 10);
+            let mut body = signer
+                .sign(
+                    &Method::PUT,
+                    &Uri::from_static("https://s3.amazonaws.com/bucket/key"),
+                    &mut HeaderMap::new(),
+                    Body::from_message(body),
+                )
+                .unwrap();
+            let res = poll_fn(|cx| body.poll_next_chunk(cx)).await.unwrap();
+            assert!(res.is_err());
+        }
+
+        #[test]
+        fn test_hmac() {
+            let mut signer = HmacSigner::new("key-1", b"secret");
+            signer.time = Some(time());
+
+            let uri = Uri::from_static("http://example.com:8080/path?q=1");
+            let mut headers = HeaderMap::new();
+            signer
+                .sign(&Method::POST, &uri, &mut headers, Body::from("data"))
+                .unwrap();
+
+            assert_eq!(headers.get(header::HOST).unwrap(), "example.com:8080");
+            assert_eq!(
+                headers.get(header::DATE).unwrap(),
+                "Sun, 30 Aug 2015 12:36:00 GMT"
+            );
+            let digest = format!(
+                "SHA-256={}",
+                base64::encode(digest::digest(&digest::SHA256, b"data").as_ref())
+            );
+            assert_eq!(headers.get("digest").unwrap().to_str().unwrap(), digest);
+
+            let sts = format!(
+                "(request-target): post /path?q=1\nhost: example.com:8080\n\
+                 date: Sun, 30 Aug 2015 12:36:00 GMT\ndigest: {}",
+                digest
+            );
+            let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+            let sig = base64::encode(hmac::sign(&key, sts.as_bytes()).as_ref());
+            assert_eq!(
+                headers
+                    .get(header::AUTHORIZATION)
+                    .unwrap()
+                    .to_str()
+                    .unwrap(),
+                format!(
+                    "Signature keyId=\"key-1\",algorithm=\"hmac-sha256\",\
+                     headers=\"(request-target) host date digest\",signature=\"{}\"",
+                    sig
+                )
+            );
+
+            // streamed payload, digest is not available
+            let mut headers = HeaderMap::new();
+            let stream = futures::stream::iter(vec![Ok::<_, io::Error>(
+                Bytes::from_static(b"data"),
+            )]);
+            signer
+                .headers(&["(request-target)", "Digest"])
+                .sign(
+                    &Method::POST,
+                    &uri,
+                    &mut headers,
+                    Body::from_message(BodyStream::new(stream)),
+                )
+                .unwrap();
+            assert!(!headers.contains_key("digest"));
+            assert!(headers
+                .get(header::AUTHORIZATION)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .contains("headers=\"(request-target)\""));
+        }
+
+        struct BodySizeOverride(Body, u64);
+
+        impl MessageBody for BodySizeOverride {
+            fn size(&self) -> BodySize {
+                BodySize::Sized(self.1)
+            }
+
+            fn poll_next_chunk(
+                &mut self,
+                cx: &mut Context<'_>,
+            ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+                self.0.poll_next_chunk(cx)
+            }
+        }
+    }
+}
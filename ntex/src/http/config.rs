@@ -1,5 +1,9 @@
 use std::{cell::Cell, ptr::copy_nonoverlapping, rc::Rc, time};
 
+use crate::http::access_log::RequestLogRecord;
+use crate::http::error::{HttpDispatchError, ResponseError};
+use crate::http::h1::{Tunnel, Upgraded};
+use crate::http::header::{self, HeaderName, HeaderValue};
 use crate::http::{Request, Response};
 use crate::io::{IoRef, Timer};
 use crate::service::boxed::BoxService;
@@ -39,17 +43,157 @@ impl From<Option<usize>> for KeepAlive {
     }
 }
 
+#[derive(Debug, Clone, Default)]
+/// HPACK header compression settings for http/2 connections
+///
+/// ```rust
+/// use ntex::http::{header, HpackConfig};
+///
+/// let cfg = HpackConfig::new()
+///     .table_size(1024)
+///     .sensitive_header(header::AUTHORIZATION)
+///     .sensitive_header(header::COOKIE);
+/// ```
+pub struct HpackConfig {
+    table_size: Option<u32>,
+    max_header_list: Option<u32>,
+    sensitive: Vec<HeaderName>,
+    static_only: bool,
+}
+
+impl HpackConfig {
+    /// Create default HPACK settings
+    pub fn new() -> Self {
+        HpackConfig::default()
+    }
+
+    /// Set max size of HPACK dynamic table for decoding peer's headers.
+    ///
+    /// Value is sent to the peer as `SETTINGS_HEADER_TABLE_SIZE` setting.
+    /// Setting is applied to client connections only, server connections
+    /// use protocol default of 4096 bytes.
+    pub fn table_size(mut self, size: u32) -> Self {
+        self.table_size = Some(size);
+        self
+    }
+
+    /// Set max size of decoded header list.
+    ///
+    /// Value is sent to the peer as `SETTINGS_MAX_HEADER_LIST_SIZE` setting.
+    pub fn max_header_list_size(mut self, size: u32) -> Self {
+        self.max_header_list = Some(size);
+        self
+    }
+
+    /// Never index header in HPACK dynamic tables.
+    ///
+    /// Values of sensitive headers are always sent as never-indexed literals,
+    /// intermediaries are not allowed to index them as well.
+    pub fn sensitive_header(mut self, name: HeaderName) -> Self {
+        if !self.sensitive.contains(&name) {
+            self.sensitive.push(name);
+        }
+        self
+    }
+
+    /// Use only HPACK static table.
+    ///
+    /// All sent headers are encoded as never-indexed literals, for client
+    /// connections dynamic table size is set to 0.
+    pub fn static_only(mut self) -> Self {
+        self.static_only = true;
+        self
+    }
+
+    pub(super) fn server_builder(&self) -> h2::server::Builder {
+        let mut builder = h2::server::Builder::new();
+        if let Some(size) = self.max_header_list {
+            builder.max_header_list_size(size);
+        }
+        builder
+    }
+
+    pub(super) fn client_builder(&self) -> h2::client::Builder {
+        let mut builder = h2::client::Builder::new();
+        if self.static_only {
+            builder.header_table_size(0);
+        } else if let Some(size) = self.table_size {
+            builder.header_table_size(size);
+        }
+        if let Some(size) = self.max_header_list {
+            builder.max_header_list_size(size);
+        }
+        builder
+    }
+
+    /// Check if header must not be indexed
+    pub(super) fn is_sensitive(&self, name: &HeaderName) -> bool {
+        self.static_only || self.sensitive.contains(name)
+    }
+
+    /// Copy header value, mark value as sensitive if needed
+    pub(super) fn value(&self, name: &HeaderName, value: &HeaderValue) -> HeaderValue {
+        let mut value = value.clone();
+        if self.is_sensitive(name) {
+            value.set_sensitive(true);
+        }
+        value
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Http/2 connection settings
+pub(super) struct H2Settings {
+    pub(super) initial_window_size: Option<u32>,
+    pub(super) initial_connection_window_size: Option<u32>,
+    pub(super) max_concurrent_streams: Option<u32>,
+    pub(super) max_frame_size: Option<u32>,
+    pub(super) ping_interval: Millis,
+    pub(super) ping_timeout: Millis,
+}
+
+impl H2Settings {
+    fn apply(&self, builder: &mut h2::server::Builder) {
+        if let Some(size) = self.initial_window_size {
+            builder.initial_window_size(size);
+        }
+        if let Some(size) = self.initial_connection_window_size {
+            builder.initial_connection_window_size(size);
+        }
+        if let Some(max) = self.max_concurrent_streams {
+            builder.max_concurrent_streams(max);
+        }
+        if let Some(size) = self.max_frame_size {
+            builder.max_frame_size(size);
+        }
+    }
+}
+
 /// Http service configuration
 pub struct ServiceConfig(pub(super) Rc<Inner>);
 
 pub(super) struct Inner {
     pub(super) keep_alive: Millis,
     pub(super) client_timeout: Millis,
+    pub(super) headers_timeout: Millis,
+    pub(super) headers_timeouts: Rc<Cell<usize>>,
     pub(super) client_disconnect: Seconds,
     pub(super) ka_enabled: bool,
     pub(super) timer: DateService,
     pub(super) ssl_handshake_timeout: Millis,
     pub(super) timer_h1: Timer,
+    pub(super) read_params: Option<(u16, u16)>,
+    pub(super) write_params: Option<(u16, u16)>,
+    pub(super) pipeline: usize,
+    pub(super) max_requests: usize,
+    pub(super) absolute_form: bool,
+    pub(super) authority_form: bool,
+    pub(super) connect: Option<Rc<OnConnect>>,
+    pub(super) upgrades: Rc<Vec<(String, OnUpgrade)>>,
+    pub(super) on_error: Option<OnRequestError>,
+    pub(super) access_log: Option<OnAccessLog>,
+    pub(super) hpack: Rc<HpackConfig>,
+    pub(super) h2: H2Settings,
 }
 
 impl Clone for ServiceConfig {
@@ -88,15 +232,133 @@ impl ServiceConfig {
             keep_alive,
             ka_enabled,
             client_timeout,
+            headers_timeout: Millis::ZERO,
+            headers_timeouts: Rc::new(Cell::new(0)),
             client_disconnect,
             ssl_handshake_timeout,
             timer: DateService::new(),
             timer_h1: Timer::default(),
+            read_params: None,
+            write_params: None,
+            pipeline: 1,
+            max_requests: 0,
+            absolute_form: true,
+            authority_form: true,
+            connect: None,
+            upgrades: Rc::new(Vec::new()),
+            on_error: None,
+            access_log: None,
+            hpack: Rc::new(HpackConfig::default()),
+            h2: H2Settings::default(),
         }))
     }
+
+    /// Set read and write buffer params for new connections
+    pub(super) fn buffer_params(
+        mut self,
+        read_params: Option<(u16, u16)>,
+        write_params: Option<(u16, u16)>,
+    ) -> Self {
+        if let Some(inner) = Rc::get_mut(&mut self.0) {
+            inner.read_params = read_params;
+            inner.write_params = write_params;
+        }
+        self
+    }
+
+    /// Set timeout for reading http/1 request headers
+    pub(super) fn headers_timeout(mut self, timeout: Millis) -> Self {
+        if let Some(inner) = Rc::get_mut(&mut self.0) {
+            inner.headers_timeout = timeout;
+        }
+        self
+    }
+
+    /// Number of http/1 connections that are closed because request headers
+    /// are not received within headers timeout.
+    pub fn headers_timeouts(&self) -> usize {
+        self.0.headers_timeouts.get()
+    }
+
+    /// Set max number of concurrently processed pipelined requests
+    pub(super) fn pipeline(mut self, max: usize) -> Self {
+        if let Some(inner) = Rc::get_mut(&mut self.0) {
+            inner.pipeline = max;
+        }
+        self
+    }
+
+    /// Set max number of http/1 requests per connection
+    pub(super) fn max_requests(mut self, num: usize) -> Self {
+        if let Some(inner) = Rc::get_mut(&mut self.0) {
+            inner.max_requests = num;
+        }
+        self
+    }
+
+    /// Set accepted http/1 request target forms
+    pub(super) fn request_targets(mut self, absolute: bool, authority: bool) -> Self {
+        if let Some(inner) = Rc::get_mut(&mut self.0) {
+            inner.absolute_form = absolute;
+            inner.authority_form = authority;
+        }
+        self
+    }
+
+    /// Set handler for http/1 `CONNECT` requests
+    pub(super) fn connect(mut self, connect: Option<OnConnect>) -> Self {
+        if let Some(inner) = Rc::get_mut(&mut self.0) {
+            inner.connect = connect.map(Rc::new);
+        }
+        self
+    }
+
+    /// Set handlers for http/1 protocol upgrades
+    pub(super) fn upgrades(mut self, upgrades: Vec<(String, OnUpgrade)>) -> Self {
+        if let Some(inner) = Rc::get_mut(&mut self.0) {
+            inner.upgrades = Rc::new(upgrades);
+        }
+        self
+    }
+
+    /// Set handler for request error responses
+    pub(super) fn on_error(mut self, on_error: Option<OnRequestError>) -> Self {
+        if let Some(inner) = Rc::get_mut(&mut self.0) {
+            inner.on_error = on_error;
+        }
+        self
+    }
+
+    /// Set access log handler
+    pub(super) fn access_log(mut self, access_log: Option<OnAccessLog>) -> Self {
+        if let Some(inner) = Rc::get_mut(&mut self.0) {
+            inner.access_log = access_log;
+        }
+        self
+    }
+
+    /// Set HPACK settings for http/2 connections
+    pub(super) fn hpack(mut self, hpack: HpackConfig) -> Self {
+        if let Some(inner) = Rc::get_mut(&mut self.0) {
+            inner.hpack = Rc::new(hpack);
+        }
+        self
+    }
+
+    /// Set http/2 connection settings
+    pub(super) fn h2(mut self, h2: H2Settings) -> Self {
+        if let Some(inner) = Rc::get_mut(&mut self.0) {
+            inner.h2 = h2;
+        }
+        self
+    }
 }
 
 pub(super) type OnRequest = BoxService<(Request, IoRef), Request, Response>;
+pub(super) type OnConnect = BoxService<(Request, Tunnel), (), ()>;
+pub(super) type OnUpgrade = BoxService<(Request, Upgraded), (), ()>;
+pub(super) type OnRequestError = Rc<dyn Fn(&HttpDispatchError) -> Response>;
+pub(super) type OnAccessLog = Rc<dyn Fn(RequestLogRecord)>;
 
 pub(super) struct DispatcherConfig<S, X, U> {
     pub(super) service: S,
@@ -104,11 +366,25 @@ pub(super) struct DispatcherConfig<S, X, U> {
     pub(super) upgrade: Option<U>,
     pub(super) keep_alive: Millis,
     pub(super) client_timeout: Millis,
+    pub(super) headers_timeout: Millis,
+    pub(super) headers_timeouts: Rc<Cell<usize>>,
     pub(super) client_disconnect: Seconds,
     pub(super) ka_enabled: bool,
     pub(super) timer: DateService,
     pub(super) timer_h1: Timer,
     pub(super) on_request: Option<OnRequest>,
+    pub(super) read_params: Option<(u16, u16)>,
+    pub(super) write_params: Option<(u16, u16)>,
+    pub(super) pipeline: usize,
+    pub(super) max_requests: usize,
+    pub(super) absolute_form: bool,
+    pub(super) authority_form: bool,
+    pub(super) connect: Option<Rc<OnConnect>>,
+    pub(super) upgrades: Rc<Vec<(String, OnUpgrade)>>,
+    pub(super) on_error: Option<OnRequestError>,
+    pub(super) access_log: Option<OnAccessLog>,
+    pub(super) hpack: Rc<HpackConfig>,
+    pub(super) h2: H2Settings,
 }
 
 impl<S, X, U> DispatcherConfig<S, X, U> {
@@ -126,13 +402,67 @@ impl<S, X, U> DispatcherConfig<S, X, U> {
             on_request,
             keep_alive: cfg.0.keep_alive,
             client_timeout: cfg.0.client_timeout,
+            headers_timeout: cfg.0.headers_timeout,
+            headers_timeouts: cfg.0.headers_timeouts.clone(),
             client_disconnect: cfg.0.client_disconnect,
             ka_enabled: cfg.0.ka_enabled,
             timer: cfg.0.timer.clone(),
             timer_h1: cfg.0.timer_h1.clone(),
+            read_params: cfg.0.read_params,
+            write_params: cfg.0.write_params,
+            pipeline: cfg.0.pipeline,
+            max_requests: cfg.0.max_requests,
+            absolute_form: cfg.0.absolute_form,
+            authority_form: cfg.0.authority_form,
+            connect: cfg.0.connect.clone(),
+            upgrades: cfg.0.upgrades.clone(),
+            on_error: cfg.0.on_error.clone(),
+            access_log: cfg.0.access_log.clone(),
+            hpack: cfg.0.hpack.clone(),
+            h2: cfg.0.h2,
         }
     }
 
+    /// Build response for request error
+    pub(super) fn error_response(&self, err: &HttpDispatchError) -> Response {
+        if let Some(ref f) = self.on_error {
+            (*f)(err)
+        } else {
+            err.error_response()
+        }
+    }
+
+    /// Find handler for protocol of the upgrade request
+    pub(super) fn upgrade_handler(&self, req: &Request) -> Option<usize> {
+        if self.upgrades.is_empty() {
+            return None;
+        }
+        let protos = req.headers().get(&header::UPGRADE)?.to_str().ok()?;
+        protos.split(',').find_map(|proto| {
+            let proto = proto.trim();
+            self.upgrades
+                .iter()
+                .position(|(name, _)| name.eq_ignore_ascii_case(proto))
+        })
+    }
+
+    /// Set buffer params for new connection
+    pub(super) fn set_buffer_params(&self, io: &IoRef) {
+        if let Some((hw, lw)) = self.read_params {
+            io.set_read_params(hw, lw);
+        }
+        if let Some((hw, lw)) = self.write_params {
+            io.set_write_params(hw, lw);
+        }
+    }
+
+    /// Create http/2 connection builder
+    pub(super) fn h2_builder(&self) -> h2::server::Builder {
+        let mut builder = self.hpack.server_builder();
+        self.h2.apply(&mut builder);
+        builder
+    }
+
     /// Return state of connection keep-alive functionality
     pub(super) fn keep_alive_enabled(&self) -> bool {
         self.ka_enabled
@@ -243,6 +573,22 @@ impl DateService {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_hpack() {
+        use crate::http::header::{AUTHORIZATION, COOKIE};
+
+        let value = HeaderValue::from_static("value");
+        let cfg = HpackConfig::new()
+            .sensitive_header(AUTHORIZATION)
+            .sensitive_header(AUTHORIZATION);
+        assert_eq!(cfg.sensitive.len(), 1);
+        assert!(cfg.value(&AUTHORIZATION, &value).is_sensitive());
+        assert!(!cfg.value(&COOKIE, &value).is_sensitive());
+
+        let cfg = HpackConfig::new().static_only();
+        assert!(cfg.value(&COOKIE, &value).is_sensitive());
+    }
+
     #[crate::rt_test]
     async fn test_date() {
         let date = DateService::default();
@@ -0,0 +1,334 @@
+//! Content digest support, [RFC 9530](https://www.rfc-editor.org/rfc/rfc9530)
+use std::{fmt, pin::Pin, task::Context, task::Poll};
+
+use ring::digest;
+
+use crate::http::error::PayloadError;
+use crate::http::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_ENCODING};
+use crate::http::ResponseHead;
+use crate::util::Bytes;
+use crate::Stream;
+
+/// `Content-Digest` header name
+pub const CONTENT_DIGEST: &str = "content-digest";
+/// `Repr-Digest` header name
+pub const REPR_DIGEST: &str = "repr-digest";
+/// `Want-Content-Digest` header name
+pub const WANT_CONTENT_DIGEST: &str = "want-content-digest";
+/// `Want-Repr-Digest` header name
+pub const WANT_REPR_DIGEST: &str = "want-repr-digest";
+
+/// Digest algorithm
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    /// `sha-256` algorithm
+    Sha256,
+    /// `sha-512` algorithm
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    /// Algorithm name
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha256 => "sha-256",
+            DigestAlgorithm::Sha512 => "sha-512",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if name.eq_ignore_ascii_case("sha-256") {
+            Some(DigestAlgorithm::Sha256)
+        } else if name.eq_ignore_ascii_case("sha-512") {
+            Some(DigestAlgorithm::Sha512)
+        } else {
+            None
+        }
+    }
+
+    fn algorithm(self) -> &'static digest::Algorithm {
+        match self {
+            DigestAlgorithm::Sha256 => &digest::SHA256,
+            DigestAlgorithm::Sha512 => &digest::SHA512,
+        }
+    }
+
+    /// Select algorithm from `Want-Content-Digest` or `Want-Repr-Digest` value
+    ///
+    /// Returns supported algorithm with highest preference, `None` if
+    /// no supported algorithm is acceptable.
+    pub fn preferred(value: &HeaderValue) -> Option<Self> {
+        let value = value.to_str().ok()?;
+        let mut selected = None;
+        let mut weight = 0;
+        for item in value.split(',') {
+            let (name, w) = item.split_once('=').unwrap_or((item, "1"));
+            if let (Some(alg), Ok(w)) =
+                (DigestAlgorithm::from_name(name), w.trim().parse::<u8>())
+            {
+                if w > weight {
+                    selected = Some(alg);
+                    weight = w;
+                }
+            }
+        }
+        selected
+    }
+}
+
+impl fmt::Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Incremental digest computation
+pub struct Digest {
+    alg: DigestAlgorithm,
+    ctx: digest::Context,
+}
+
+impl Digest {
+    /// Create new digest
+    pub fn new(alg: DigestAlgorithm) -> Self {
+        Digest {
+            alg,
+            ctx: digest::Context::new(alg.algorithm()),
+        }
+    }
+
+    /// Update digest with data
+    pub fn update(&mut self, data: &[u8]) {
+        self.ctx.update(data);
+    }
+
+    /// Finish digest computation
+    pub fn finish(self) -> Vec<u8> {
+        self.ctx.finish().as_ref().to_vec()
+    }
+
+    /// Finish digest computation and build header value, `sha-256=:<base64>:`
+    pub fn header_value(self) -> HeaderValue {
+        let alg = self.alg;
+        let value = format!("{}=:{}:", alg.as_str(), base64::encode(self.finish()));
+        HeaderValue::from_str(&value).unwrap()
+    }
+}
+
+impl fmt::Debug for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Digest").field("alg", &self.alg).finish()
+    }
+}
+
+/// Parse digest header value
+///
+/// Digests with unsupported algorithms are skipped.
+pub fn parse(value: &HeaderValue) -> Vec<(DigestAlgorithm, Vec<u8>)> {
+    let value = if let Ok(value) = value.to_str() {
+        value
+    } else {
+        return Vec::new();
+    };
+
+    value
+        .split(',')
+        .filter_map(|item| {
+            let (name, value) = item.split_once('=')?;
+            let alg = DigestAlgorithm::from_name(name)?;
+            let value = value.trim().strip_prefix(':')?.strip_suffix(':')?;
+            Some((alg, base64::decode(value).ok()?))
+        })
+        .collect()
+}
+
+pub(crate) fn header_name(name: &'static str) -> HeaderName {
+    HeaderName::from_static(name)
+}
+
+/// Marks response payload as decoded, so content digest could not be verified
+pub(crate) struct Decoded;
+
+#[cfg(feature = "compress")]
+pub(crate) fn mark_decoded(head: &ResponseHead) {
+    let encoded = head
+        .headers
+        .get(&CONTENT_ENCODING)
+        .map(|enc| !enc.as_bytes().eq_ignore_ascii_case(b"identity"))
+        .unwrap_or(false);
+    if encoded {
+        head.extensions_mut().insert(Decoded);
+    }
+}
+
+/// Find expected payload digest for response
+///
+/// `Content-Digest` is used for not decoded payloads, `Repr-Digest` is used
+/// if payload is decoded or content is not encoded.
+pub(crate) fn expected(head: &ResponseHead) -> Option<(DigestAlgorithm, Vec<u8>)> {
+    let decoded = head.extensions().contains::<Decoded>();
+    let encoded = head.headers.contains_key(&CONTENT_ENCODING);
+
+    if decoded {
+        find(&head.headers, REPR_DIGEST)
+    } else if encoded {
+        find(&head.headers, CONTENT_DIGEST)
+    } else {
+        find(&head.headers, CONTENT_DIGEST).or_else(|| find(&head.headers, REPR_DIGEST))
+    }
+}
+
+/// Find strongest supported digest in header
+pub(crate) fn find(
+    headers: &HeaderMap,
+    name: &'static str,
+) -> Option<(DigestAlgorithm, Vec<u8>)> {
+    let mut items: Vec<_> = headers.get_all(name).flat_map(parse).collect();
+    items.sort_by_key(|(alg, _)| *alg == DigestAlgorithm::Sha512);
+    items.pop()
+}
+
+/// Payload stream that verifies digest of the data
+///
+/// Stream fails with `PayloadError::DigestMismatch` if digest
+/// of the data does not match expected digest.
+pub struct DigestVerifier<S> {
+    stream: S,
+    digest: Option<Digest>,
+    expected: Vec<u8>,
+}
+
+impl<S> DigestVerifier<S> {
+    /// Create new verifier
+    pub fn new(stream: S, alg: DigestAlgorithm, expected: Vec<u8>) -> Self {
+        DigestVerifier {
+            stream,
+            expected,
+            digest: Some(Digest::new(alg)),
+        }
+    }
+}
+
+impl<S> Stream for DigestVerifier<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+{
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.as_mut().get_mut();
+        match Pin::new(&mut this.stream).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                if let Some(ref mut digest) = this.digest {
+                    digest.update(&chunk);
+                }
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(None) => {
+                if let Some(digest) = this.digest.take() {
+                    if digest.finish() != this.expected {
+                        log::trace!("Payload digest mismatch");
+                        return Poll::Ready(Some(Err(PayloadError::DigestMismatch)));
+                    }
+                }
+                Poll::Ready(None)
+            }
+            res => res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::next;
+
+    #[test]
+    fn test_digest() {
+        let mut digest = Digest::new(DigestAlgorithm::Sha256);
+        digest.update(b"{\"hello\": \"world\"}");
+        // rfc 9530, appendix b.1
+        assert_eq!(
+            digest.header_value(),
+            "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:"
+        );
+
+        let mut digest = Digest::new(DigestAlgorithm::Sha512);
+        digest.update(b"{\"hello\": \"world\"}\n");
+        assert_eq!(
+            digest.header_value(),
+            "sha-512=:YMAam51Jz/jOATT6/zvHrLVgOYTGFy1d6GJiOHTohq4yP+pgk4vf2aCs\
+             yRZOtw8MjkM7iw7yZ/WkppmM44T3qg==:"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let items = parse(&HeaderValue::from_static(
+            "sha-256=:AAAA:, unknown=:AAAA:, sha-512=:AQID:, sha-256=invalid",
+        ));
+        assert_eq!(
+            items,
+            vec![
+                (DigestAlgorithm::Sha256, vec![0, 0, 0]),
+                (DigestAlgorithm::Sha512, vec![1, 2, 3])
+            ]
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.append(
+            header_name(CONTENT_DIGEST),
+            HeaderValue::from_static("sha-512=:AQID:"),
+        );
+        headers.append(
+            header_name(CONTENT_DIGEST),
+            HeaderValue::from_static("sha-256=:AAAA:"),
+        );
+        assert_eq!(
+            find(&headers, CONTENT_DIGEST),
+            Some((DigestAlgorithm::Sha512, vec![1, 2, 3]))
+        );
+        assert_eq!(find(&headers, REPR_DIGEST), None);
+    }
+
+    #[test]
+    fn test_preferred() {
+        let pref = |s| DigestAlgorithm::preferred(&HeaderValue::from_static(s));
+        assert_eq!(pref("sha-256"), Some(DigestAlgorithm::Sha256));
+        assert_eq!(pref("sha-512=3, sha-256=10"), Some(DigestAlgorithm::Sha256));
+        assert_eq!(pref("sha-512=3, sha-256=0"), Some(DigestAlgorithm::Sha512));
+        assert_eq!(pref("sha-256=0, md5=10"), None);
+    }
+
+    #[crate::rt_test]
+    async fn test_verifier() {
+        let mut digest = Digest::new(DigestAlgorithm::Sha256);
+        digest.update(b"hello world");
+        let expected = digest.finish();
+
+        let stream = futures::stream::iter(vec![
+            Ok(Bytes::from_static(b"hello ")),
+            Ok(Bytes::from_static(b"world")),
+        ]);
+        let mut verifier =
+            DigestVerifier::new(stream, DigestAlgorithm::Sha256, expected.clone());
+        assert_eq!(
+            next(&mut verifier).await.unwrap().unwrap(),
+            Bytes::from_static(b"hello ")
+        );
+        assert!(next(&mut verifier).await.unwrap().is_ok());
+        assert!(next(&mut verifier).await.is_none());
+
+        let stream = futures::stream::iter(vec![Ok(Bytes::from_static(b"hello"))]);
+        let mut verifier = DigestVerifier::new(stream, DigestAlgorithm::Sha256, expected);
+        assert!(next(&mut verifier).await.unwrap().is_ok());
+        assert!(matches!(
+            next(&mut verifier).await,
+            Some(Err(PayloadError::DigestMismatch))
+        ));
+    }
+}
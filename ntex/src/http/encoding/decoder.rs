@@ -3,7 +3,7 @@ use std::{future::Future, io, io::Write, pin::Pin, task::Context, task::Poll};
 use brotli2::write::BrotliDecoder;
 use flate2::write::{GzDecoder, ZlibDecoder};
 
-use super::Writer;
+use super::{LimitOverflow, Writer};
 use crate::http::error::PayloadError;
 use crate::http::header::{ContentEncoding, HeaderMap, CONTENT_ENCODING};
 use crate::rt::{spawn_blocking, JoinHandle};
@@ -35,6 +35,9 @@ where
             ContentEncoding::Gzip => Some(ContentDecoder::Gzip(Box::new(GzDecoder::new(
                 Writer::new(),
             )))),
+            ContentEncoding::Zstd => zstd::stream::write::Decoder::new(Writer::new())
+                .ok()
+                .map(|decoder| ContentDecoder::Zstd(Box::new(decoder))),
             _ => None,
         };
         Decoder {
@@ -61,6 +64,17 @@ where
 
         Self::new(stream, encoding)
     }
+
+    /// Set max size of decoded payload.
+    ///
+    /// Decoder returns `PayloadError::Overflow` error if size of
+    /// decoded payload exceeds limit. By default size is not limited.
+    pub fn limit(mut self, limit: usize) -> Self {
+        if let Some(ref mut decoder) = self.decoder {
+            decoder.writer().limit = limit;
+        }
+        self
+    }
 }
 
 impl<S> Stream for Decoder<S>
@@ -77,7 +91,9 @@ where
             if let Some(ref mut fut) = self.fut {
                 let (chunk, decoder) = match Pin::new(fut).poll(cx) {
                     Poll::Ready(Ok(Ok(item))) => item,
-                    Poll::Ready(Ok(Err(e))) => return Poll::Ready(Some(Err(e.into()))),
+                    Poll::Ready(Ok(Err(e))) => {
+                        return Poll::Ready(Some(Err(into_error(e))))
+                    }
                     Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
                     Poll::Pending => return Poll::Pending,
                 };
@@ -97,7 +113,7 @@ where
                 Poll::Ready(Some(Ok(chunk))) => {
                     if let Some(mut decoder) = self.decoder.take() {
                         if chunk.len() < INPLACE {
-                            let chunk = decoder.feed_data(chunk)?;
+                            let chunk = decoder.feed_data(chunk).map_err(into_error)?;
                             self.decoder = Some(decoder);
                             if let Some(chunk) = chunk {
                                 return Poll::Ready(Some(Ok(chunk)));
@@ -119,7 +135,7 @@ where
                         match decoder.feed_eof() {
                             Ok(Some(res)) => Poll::Ready(Some(Ok(res))),
                             Ok(None) => Poll::Ready(None),
-                            Err(err) => Poll::Ready(Some(Err(err.into()))),
+                            Err(err) => Poll::Ready(Some(Err(into_error(err)))),
                         }
                     } else {
                         Poll::Ready(None)
@@ -136,9 +152,28 @@ enum ContentDecoder {
     Deflate(Box<ZlibDecoder<Writer>>),
     Gzip(Box<GzDecoder<Writer>>),
     Br(Box<BrotliDecoder<Writer>>),
+    Zstd(Box<zstd::stream::write::Decoder<'static, Writer>>),
+}
+
+/// Decoder limit overflow is reported as `PayloadError::Overflow`
+fn into_error(err: io::Error) -> PayloadError {
+    if matches!(err.get_ref(), Some(e) if e.is::<LimitOverflow>()) {
+        PayloadError::Overflow
+    } else {
+        err.into()
+    }
 }
 
 impl ContentDecoder {
+    fn writer(&mut self) -> &mut Writer {
+        match self {
+            ContentDecoder::Br(ref mut decoder) => decoder.get_mut(),
+            ContentDecoder::Gzip(ref mut decoder) => decoder.get_mut(),
+            ContentDecoder::Deflate(ref mut decoder) => decoder.get_mut(),
+            ContentDecoder::Zstd(ref mut decoder) => decoder.get_mut(),
+        }
+    }
+
     fn feed_eof(&mut self) -> io::Result<Option<Bytes>> {
         match self {
             ContentDecoder::Br(ref mut decoder) => match decoder.flush() {
@@ -174,6 +209,17 @@ impl ContentDecoder {
                 }
                 Err(e) => Err(e),
             },
+            ContentDecoder::Zstd(ref mut decoder) => match decoder.flush() {
+                Ok(()) => {
+                    let b = decoder.get_mut().take();
+                    if !b.is_empty() {
+                        Ok(Some(b))
+                    } else {
+                        Ok(None)
+                    }
+                }
+                Err(e) => Err(e),
+            },
         }
     }
 
@@ -215,6 +261,18 @@ impl ContentDecoder {
                 }
                 Err(e) => Err(e),
             },
+            ContentDecoder::Zstd(ref mut decoder) => match decoder.write_all(&data) {
+                Ok(_) => {
+                    decoder.flush()?;
+                    let b = decoder.get_mut().take();
+                    if !b.is_empty() {
+                        Ok(Some(b))
+                    } else {
+                        Ok(None)
+                    }
+                }
+                Err(e) => Err(e),
+            },
         }
     }
 }
@@ -1,7 +1,8 @@
 //! Stream encoder
-use std::{future::Future, io, io::Write, pin::Pin, task::Context, task::Poll};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{future::Future, io, io::Write, mem, pin::Pin, task::Context, task::Poll};
 
-use brotli2::write::BrotliEncoder;
+use brotli2::{write::BrotliEncoder, CompressParams};
 use flate2::write::{GzEncoder, ZlibEncoder};
 
 use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
@@ -14,11 +15,139 @@ use super::Writer;
 
 const INPLACE: usize = 1024;
 
+/// Number of active response encoders, shared by all workers
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Response encoder settings
+///
+/// Generic level applies to all algorithms, per-algorithm settings
+/// take precedence over generic level. Gzip and deflate levels are 0-9,
+/// brotli quality is 0-11 and zstd levels are 1-21.
+///
+/// Budget limits number of responses that are compressed with configured
+/// levels at the same time. If number of active encoders exceeds budget,
+/// new responses are compressed with fastest level until load drops.
+/// Budget is global for the process, all workers share the same counter.
+#[derive(Debug, Clone, Default)]
+pub struct EncoderConfig {
+    level: Option<u32>,
+    gzip_level: Option<u32>,
+    brotli_quality: Option<u32>,
+    brotli_window: Option<u32>,
+    zstd_level: Option<u32>,
+    zstd_window_log: Option<u32>,
+    zstd_long_distance: bool,
+    budget: Option<usize>,
+}
+
+impl EncoderConfig {
+    /// Create default encoder settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set compression level for all algorithms.
+    ///
+    /// By default fast compression level is used.
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Set gzip and deflate compression level, range is 0-9.
+    pub fn gzip_level(mut self, level: u32) -> Self {
+        self.gzip_level = Some(level.min(9));
+        self
+    }
+
+    /// Set brotli quality, range is 0-11.
+    ///
+    /// By default quality 3 is used.
+    pub fn brotli_quality(mut self, quality: u32) -> Self {
+        self.brotli_quality = Some(quality.min(11));
+        self
+    }
+
+    /// Set brotli window size as log2 of bytes, range is 10-24.
+    ///
+    /// By default window size is 22.
+    pub fn brotli_window(mut self, lgwin: u32) -> Self {
+        self.brotli_window = Some(lgwin.clamp(10, 24));
+        self
+    }
+
+    /// Set zstd compression level, range is 1-21.
+    ///
+    /// By default level 3 is used.
+    pub fn zstd_level(mut self, level: u32) -> Self {
+        self.zstd_level = Some(level.clamp(1, 21));
+        self
+    }
+
+    /// Set zstd window size as log2 of bytes, range is 10-31.
+    ///
+    /// Browsers do not accept windows larger than 8Mb (23),
+    /// by default window size is selected by compression level.
+    pub fn zstd_window_log(mut self, log: u32) -> Self {
+        self.zstd_window_log = Some(log.clamp(10, 31));
+        self
+    }
+
+    /// Enable zstd long distance matching.
+    ///
+    /// Improves compression ratio of large responses with long repeated
+    /// sequences at the cost of memory. Disabled by default.
+    pub fn zstd_long_distance_matching(mut self, enabled: bool) -> Self {
+        self.zstd_long_distance = enabled;
+        self
+    }
+
+    /// Set max number of responses compressed with configured levels.
+    ///
+    /// By default budget is not limited.
+    pub fn budget(mut self, max: usize) -> Self {
+        self.budget = Some(max);
+        self
+    }
+
+    /// Number of currently active response encoders
+    pub fn active_encoders() -> usize {
+        ACTIVE.load(Ordering::Relaxed)
+    }
+
+    fn is_overloaded(&self) -> bool {
+        if let Some(budget) = self.budget {
+            ACTIVE.load(Ordering::Relaxed) >= budget
+        } else {
+            false
+        }
+    }
+}
+
+/// Active encoder counter guard
+struct ActiveGuard;
+
+impl ActiveGuard {
+    fn new() -> Self {
+        ACTIVE.fetch_add(1, Ordering::Relaxed);
+        ActiveGuard
+    }
+}
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        ACTIVE.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 pub struct Encoder<B> {
     eof: bool,
+    flush: bool,
+    flush_pending: bool,
     body: EncoderBody<B>,
     encoder: Option<ContentEncoder>,
     fut: Option<JoinHandle<Result<ContentEncoder, io::Error>>>,
+    _guard: ActiveGuard,
 }
 
 impl<B: MessageBody + 'static> Encoder<B> {
@@ -26,6 +155,31 @@ impl<B: MessageBody + 'static> Encoder<B> {
         encoding: ContentEncoding,
         head: &mut ResponseHead,
         body: ResponseBody<B>,
+    ) -> ResponseBody<B> {
+        Self::response_with_level(encoding, None, head, body)
+    }
+
+    /// Encode response body with specified compression level.
+    ///
+    /// Level is clamped to the range of selected algorithm, gzip and deflate
+    /// levels are 0-9, brotli levels are 0-11 and zstd levels are 1-21.
+    pub fn response_with_level(
+        encoding: ContentEncoding,
+        level: Option<u32>,
+        head: &mut ResponseHead,
+        body: ResponseBody<B>,
+    ) -> ResponseBody<B> {
+        let mut cfg = EncoderConfig::new();
+        cfg.level = level;
+        Self::response_with_config(encoding, &cfg, head, body)
+    }
+
+    /// Encode response body with specified encoder settings.
+    pub fn response_with_config(
+        encoding: ContentEncoding,
+        cfg: &EncoderConfig,
+        head: &mut ResponseHead,
+        body: ResponseBody<B>,
     ) -> ResponseBody<B> {
         let can_encode = ContentEncoder::can_encode(encoding)
             && !(head.headers().contains_key(&CONTENT_ENCODING)
@@ -48,14 +202,27 @@ impl<B: MessageBody + 'static> Encoder<B> {
             };
 
             // Modify response body only if encoder is not None
-            let encoder = ContentEncoder::encoder(encoding).unwrap();
+            let encoder = if let Some(encoder) = ContentEncoder::encoder(encoding, cfg) {
+                encoder
+            } else {
+                return match body {
+                    EncoderBody::Bytes(buf) => ResponseBody::Other(Body::Bytes(buf)),
+                    EncoderBody::Stream(stream) => ResponseBody::Body(stream),
+                    EncoderBody::BoxedStream(stream) => {
+                        ResponseBody::Other(Body::Message(stream))
+                    }
+                };
+            };
             update_head(encoding, head);
             head.no_chunking(false);
             ResponseBody::Other(Body::from_message(Encoder {
                 body,
                 eof: false,
+                flush: false,
+                flush_pending: false,
                 fut: None,
                 encoder: Some(encoder),
+                _guard: ActiveGuard::new(),
             }))
         }
     }
@@ -104,35 +271,51 @@ impl<B: MessageBody> MessageBody for Encoder<B> {
                 let chunk = encoder.take();
                 self.encoder = Some(encoder);
                 self.fut.take();
+                self.flush = mem::replace(&mut self.flush_pending, false);
                 if !chunk.is_empty() {
                     return Poll::Ready(Some(Ok(chunk)));
                 }
             }
 
-            let result = match self.body {
+            let (result, flush) = match self.body {
                 EncoderBody::Bytes(ref mut b) => {
                     if b.is_empty() {
-                        Poll::Ready(None)
+                        (Poll::Ready(None), false)
                     } else {
-                        Poll::Ready(Some(Ok(std::mem::take(b))))
+                        (Poll::Ready(Some(Ok(mem::take(b)))), false)
                     }
                 }
-                EncoderBody::Stream(ref mut b) => b.poll_next_chunk(cx),
-                EncoderBody::BoxedStream(ref mut b) => b.poll_next_chunk(cx),
+                EncoderBody::Stream(ref mut b) => {
+                    let result = b.poll_next_chunk(cx);
+                    (result, b.take_flush())
+                }
+                EncoderBody::BoxedStream(ref mut b) => {
+                    let result = b.poll_next_chunk(cx);
+                    (result, b.take_flush())
+                }
             };
             match result {
                 Poll::Ready(Some(Ok(chunk))) => {
                     if let Some(mut encoder) = self.encoder.take() {
                         if chunk.len() < INPLACE {
                             encoder.write(&chunk)?;
+                            // body requested flush, send all compressed data
+                            if flush {
+                                encoder.flush()?;
+                                self.flush = true;
+                            }
                             let chunk = encoder.take();
                             self.encoder = Some(encoder);
                             if !chunk.is_empty() {
                                 return Poll::Ready(Some(Ok(chunk)));
                             }
                         } else {
+                            self.flush_pending = flush;
                             self.fut = Some(spawn_blocking(move || {
                                 encoder.write(&chunk)?;
+                                if flush {
+                                    encoder.flush()?;
+                                }
                                 Ok(encoder)
                             }));
                         }
@@ -153,10 +336,28 @@ impl<B: MessageBody> MessageBody for Encoder<B> {
                         return Poll::Ready(None);
                     }
                 }
+                Poll::Pending => {
+                    // body requested flush and waits for more data
+                    if flush {
+                        if let Some(ref mut encoder) = self.encoder {
+                            encoder.flush()?;
+                            self.flush = true;
+                            let chunk = encoder.take();
+                            if !chunk.is_empty() {
+                                return Poll::Ready(Some(Ok(chunk)));
+                            }
+                        }
+                    }
+                    return Poll::Pending;
+                }
                 val => return val,
             }
         }
     }
+
+    fn take_flush(&mut self) -> bool {
+        mem::replace(&mut self.flush, false)
+    }
 }
 
 fn update_head(encoding: ContentEncoding, head: &mut ResponseHead) {
@@ -170,28 +371,85 @@ enum ContentEncoder {
     Deflate(ZlibEncoder<Writer>),
     Gzip(GzEncoder<Writer>),
     Br(BrotliEncoder<Writer>),
+    Zstd(Box<zstd::stream::write::Encoder<'static, Writer>>),
 }
 
 impl ContentEncoder {
     fn can_encode(encoding: ContentEncoding) -> bool {
         match encoding {
-            ContentEncoding::Deflate | ContentEncoding::Gzip | ContentEncoding::Br => true,
+            ContentEncoding::Deflate
+            | ContentEncoding::Gzip
+            | ContentEncoding::Br
+            | ContentEncoding::Zstd => true,
             _ => false,
         }
     }
 
-    fn encoder(encoding: ContentEncoding) -> Option<Self> {
+    fn encoder(encoding: ContentEncoding, cfg: &EncoderConfig) -> Option<Self> {
+        // downgrade levels if encoders budget is exceeded
+        let overloaded = cfg.is_overloaded();
+        if overloaded {
+            trace!("Encoders budget is exceeded, use fastest compression level");
+        }
+
+        let flate_level = || {
+            if overloaded {
+                flate2::Compression::fast()
+            } else {
+                cfg.gzip_level
+                    .or(cfg.level)
+                    .map(|l| flate2::Compression::new(l.min(9)))
+                    .unwrap_or_else(flate2::Compression::fast)
+            }
+        };
+
         match encoding {
             ContentEncoding::Deflate => Some(ContentEncoder::Deflate(ZlibEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate_level(),
             ))),
             ContentEncoding::Gzip => Some(ContentEncoder::Gzip(GzEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate_level(),
             ))),
             ContentEncoding::Br => {
-                Some(ContentEncoder::Br(BrotliEncoder::new(Writer::new(), 3)))
+                let mut params = CompressParams::new();
+                if overloaded {
+                    params.quality(0);
+                } else {
+                    params.quality(cfg.brotli_quality.or(cfg.level).unwrap_or(3).min(11));
+                }
+                if let Some(lgwin) = cfg.brotli_window {
+                    params.lgwin(lgwin);
+                }
+                Some(ContentEncoder::Br(BrotliEncoder::from_params(
+                    Writer::new(),
+                    &params,
+                )))
+            }
+            ContentEncoding::Zstd => {
+                let level = if overloaded {
+                    1
+                } else {
+                    cfg.zstd_level.or(cfg.level).unwrap_or(3).clamp(1, 21)
+                };
+                let result = zstd::stream::write::Encoder::new(Writer::new(), level as i32)
+                    .and_then(|mut encoder| {
+                        if let Some(log) = cfg.zstd_window_log {
+                            encoder.window_log(log)?;
+                        }
+                        if cfg.zstd_long_distance && !overloaded {
+                            encoder.long_distance_matching(true)?;
+                        }
+                        Ok(encoder)
+                    });
+                match result {
+                    Ok(encoder) => Some(ContentEncoder::Zstd(Box::new(encoder))),
+                    Err(err) => {
+                        log::error!("Cannot create zstd encoder: {}", err);
+                        None
+                    }
+                }
             }
             _ => None,
         }
@@ -202,6 +460,16 @@ impl ContentEncoder {
             ContentEncoder::Br(ref mut encoder) => encoder.get_mut().take(),
             ContentEncoder::Deflate(ref mut encoder) => encoder.get_mut().take(),
             ContentEncoder::Gzip(ref mut encoder) => encoder.get_mut().take(),
+            ContentEncoder::Zstd(ref mut encoder) => encoder.get_mut().take(),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        match *self {
+            ContentEncoder::Br(ref mut encoder) => encoder.flush(),
+            ContentEncoder::Deflate(ref mut encoder) => encoder.flush(),
+            ContentEncoder::Gzip(ref mut encoder) => encoder.flush(),
+            ContentEncoder::Zstd(ref mut encoder) => encoder.flush(),
         }
     }
 
@@ -219,6 +487,10 @@ impl ContentEncoder {
                 Ok(writer) => Ok(writer.buf.freeze()),
                 Err(err) => Err(err),
             },
+            ContentEncoder::Zstd(encoder) => match encoder.finish() {
+                Ok(writer) => Ok(writer.buf.freeze()),
+                Err(err) => Err(err),
+            },
         }
     }
 
@@ -245,6 +517,13 @@ impl ContentEncoder {
                     Err(err)
                 }
             },
+            ContentEncoder::Zstd(ref mut encoder) => match encoder.write_all(data) {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    trace!("Error encoding zstd encoding: {}", err);
+                    Err(err)
+                }
+            },
         }
     }
 }
@@ -7,16 +7,20 @@ mod decoder;
 mod encoder;
 
 pub use self::decoder::Decoder;
-pub use self::encoder::Encoder;
+pub use self::encoder::{Encoder, EncoderConfig};
 
 pub(self) struct Writer {
     buf: BytesMut,
+    limit: usize,
+    written: usize,
 }
 
 impl Writer {
     fn new() -> Writer {
         Writer {
             buf: BytesMut::with_capacity(8192),
+            limit: usize::MAX,
+            written: 0,
         }
     }
 
@@ -27,6 +31,10 @@ impl Writer {
 
 impl io::Write for Writer {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written = self.written.saturating_add(buf.len());
+        if self.written > self.limit {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, LimitOverflow));
+        }
         self.buf.extend_from_slice(buf);
         Ok(buf.len())
     }
@@ -35,3 +43,15 @@ impl io::Write for Writer {
         Ok(())
     }
 }
+
+#[derive(Debug)]
+/// Writer limit is exceeded
+struct LimitOverflow;
+
+impl std::fmt::Display for LimitOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Decoded payload exceeds limit")
+    }
+}
+
+impl std::error::Error for LimitOverflow {}
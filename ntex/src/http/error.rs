@@ -131,6 +131,12 @@ pub enum PayloadError {
     /// A payload length is unknown.
     #[display(fmt = "A payload length is unknown.")]
     UnknownLength,
+    /// A payload length does not match `Content-Length` header.
+    #[display(fmt = "A payload length does not match content-length.")]
+    LengthMismatch,
+    /// A payload digest does not match digest header.
+    #[display(fmt = "A payload digest does not match.")]
+    DigestMismatch,
     /// Http2 payload error
     #[display(fmt = "{}", _0)]
     Http2Payload(h2::Error),
@@ -183,6 +189,10 @@ pub enum DispatchError {
     #[display(fmt = "The first request did not complete within the specified timeout")]
     SlowRequestTimeout,
 
+    /// Request headers are not received within the specified timeout.
+    #[display(fmt = "Request headers are not received within the specified timeout")]
+    HeadersTimeout,
+
     /// Disconnect timeout. Makes sense for ssl streams.
     #[display(fmt = "Connection shutdown timeout")]
     DisconnectTimeout,
@@ -203,6 +213,10 @@ pub enum DispatchError {
     #[display(fmt = "Internal error")]
     InternalError,
 
+    /// Http/2 keep-alive ping is not acknowledged within timeout
+    #[display(fmt = "Keep-alive ping timeout")]
+    PingTimeout,
+
     /// Unknown error
     #[display(fmt = "Unknown error")]
     Unknown,
@@ -216,6 +230,54 @@ impl From<io::Error> for DispatchError {
     }
 }
 
+#[derive(Debug, Display)]
+/// Request error that is reported to the peer with error response
+///
+/// Default error responses could be overridden with
+/// `HttpServiceBuilder::on_request_error()`
+pub enum HttpDispatchError {
+    /// Malformed request
+    #[display(fmt = "Malformed request: {}", _0)]
+    Parse(ParseError),
+    /// Request head is not received within client timeout
+    #[display(fmt = "Request head is not received within client timeout")]
+    SlowRequest,
+    /// Request headers are not received within headers timeout
+    #[display(fmt = "Request headers are not received within headers timeout")]
+    HeadersTimeout,
+}
+
+impl HttpDispatchError {
+    /// Status code of the default error response
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            HttpDispatchError::Parse(_) => StatusCode::BAD_REQUEST,
+            HttpDispatchError::SlowRequest | HttpDispatchError::HeadersTimeout => {
+                StatusCode::REQUEST_TIMEOUT
+            }
+        }
+    }
+}
+
+impl std::error::Error for HttpDispatchError {}
+
+/// Default error responses do not contain body
+impl ResponseError for HttpDispatchError {
+    fn error_response(&self) -> Response {
+        Response::new(self.status_code())
+    }
+}
+
+impl From<HttpDispatchError> for DispatchError {
+    fn from(err: HttpDispatchError) -> Self {
+        match err {
+            HttpDispatchError::Parse(err) => DispatchError::Parse(err),
+            HttpDispatchError::SlowRequest => DispatchError::SlowRequestTimeout,
+            HttpDispatchError::HeadersTimeout => DispatchError::HeadersTimeout,
+        }
+    }
+}
+
 /// A set of error that can occure during parsing content type
 #[derive(PartialEq, Debug, Display)]
 pub enum ContentTypeError {
@@ -0,0 +1,94 @@
+use std::time::{Duration, Instant};
+
+use crate::http::header::{HeaderName, HeaderValue};
+
+pub(super) const GRPC_TIMEOUT: HeaderName = HeaderName::from_static("grpc-timeout");
+
+/// Deadline of the gRPC call
+///
+/// Deadline is set by client with `grpc-timeout` header. Server stores
+/// deadline in request extensions, handlers could use it to propagate
+/// deadline to downstream calls.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    /// Create deadline that expires after specified timeout
+    pub fn new(timeout: Duration) -> Self {
+        Deadline(Instant::now() + timeout)
+    }
+
+    /// Parse `grpc-timeout` header value
+    pub fn from_header(value: &HeaderValue) -> Option<Self> {
+        let value = value.to_str().ok()?;
+        if value.len() < 2 || value.len() > 9 {
+            return None;
+        }
+        let (num, unit) = value.split_at(value.len() - 1);
+        if !num.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let num = num.parse::<u64>().ok()?;
+
+        let timeout = match unit {
+            "H" => Duration::from_secs(num * 3600),
+            "M" => Duration::from_secs(num * 60),
+            "S" => Duration::from_secs(num),
+            "m" => Duration::from_millis(num),
+            "u" => Duration::from_micros(num),
+            "n" => Duration::from_nanos(num),
+            _ => return None,
+        };
+        Some(Deadline::new(timeout))
+    }
+
+    /// Remaining time before deadline
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    /// Check if deadline is expired
+    pub fn is_expired(&self) -> bool {
+        self.0 <= Instant::now()
+    }
+
+    /// `grpc-timeout` header value for remaining time
+    pub fn header_value(&self) -> HeaderValue {
+        let millis = self.remaining().as_millis();
+        let value = if millis < 100_000_000 {
+            format!("{}m", millis)
+        } else {
+            format!("{}S", (millis / 1000).min(99_999_999))
+        };
+        HeaderValue::from_str(&value).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let d = Deadline::from_header(&HeaderValue::from_static("10S")).unwrap();
+        assert!(d.remaining() > Duration::from_secs(9));
+        assert!(d.remaining() <= Duration::from_secs(10));
+        assert!(!d.is_expired());
+
+        let d = Deadline::from_header(&HeaderValue::from_static("1H")).unwrap();
+        assert!(d.remaining() > Duration::from_secs(3599));
+        let d = Deadline::from_header(&HeaderValue::from_static("0n")).unwrap();
+        assert!(d.is_expired());
+        assert_eq!(d.header_value(), "0m");
+
+        assert!(Deadline::from_header(&HeaderValue::from_static("S")).is_none());
+        assert!(Deadline::from_header(&HeaderValue::from_static("10")).is_none());
+        assert!(Deadline::from_header(&HeaderValue::from_static("+10S")).is_none());
+        assert!(Deadline::from_header(&HeaderValue::from_static("10x")).is_none());
+        assert!(Deadline::from_header(&HeaderValue::from_static("123456789S")).is_none());
+
+        let d = Deadline::new(Duration::from_secs(5));
+        let val = d.header_value();
+        assert!(val.to_str().unwrap().ends_with('m'));
+    }
+}
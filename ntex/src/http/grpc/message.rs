@@ -0,0 +1,199 @@
+use std::{cell::Cell, error::Error, pin::Pin, rc::Rc, task::Context, task::Poll};
+
+use crate::http::body::{BodySize, MessageBody};
+use crate::http::{HeaderMap, Payload};
+use crate::time::Sleep;
+use crate::util::{Buf, BufMut, Bytes, BytesMut};
+use crate::Stream;
+
+use super::{Code, Status};
+
+/// Size of message prefix, compressed flag and message length
+const PREFIX_SIZE: usize = 5;
+pub(super) const DEFAULT_MAX_SIZE: usize = 4 * 1024 * 1024;
+
+/// Stream of incoming gRPC messages
+///
+/// Messages are decoded from payload as they arrive. Server passes
+/// request messages stream to call handlers, clients could use it
+/// to decode response payload.
+pub struct Streaming {
+    payload: Payload,
+    buf: BytesMut,
+    max_size: usize,
+    eof: bool,
+}
+
+impl Streaming {
+    /// Create messages stream from payload
+    pub fn new(payload: Payload) -> Self {
+        Streaming {
+            payload,
+            max_size: DEFAULT_MAX_SIZE,
+            buf: BytesMut::new(),
+            eof: false,
+        }
+    }
+
+    /// Set max size of decoded message.
+    ///
+    /// Stream returns `ResourceExhausted` status if message size exceeds
+    /// limit. By default max size is 4Mb.
+    pub fn max_message_size(mut self, size: usize) -> Self {
+        self.max_size = size;
+        self
+    }
+
+    /// Read payload trailers.
+    ///
+    /// Remaining messages are read and discarded. Clients could use
+    /// `Status::from_headers()` to get call status from trailers.
+    pub async fn trailers(&mut self) -> Result<Option<HeaderMap>, Status> {
+        self.payload
+            .trailers()
+            .await
+            .map_err(|e| Status::new(Code::Internal, e.to_string()))
+    }
+
+    fn decode(&mut self) -> Result<Option<Bytes>, Status> {
+        if self.buf.len() < PREFIX_SIZE {
+            return Ok(None);
+        }
+        if self.buf[0] != 0 {
+            return Err(Status::new(
+                Code::Unimplemented,
+                "Compressed messages are not supported",
+            ));
+        }
+        let len = u32::from_be_bytes([self.buf[1], self.buf[2], self.buf[3], self.buf[4]])
+            as usize;
+        if len > self.max_size {
+            return Err(Status::new(
+                Code::ResourceExhausted,
+                format!("Message size {} exceeds limit {}", len, self.max_size),
+            ));
+        }
+        if self.buf.len() < PREFIX_SIZE + len {
+            return Ok(None);
+        }
+        self.buf.advance(PREFIX_SIZE);
+        Ok(Some(self.buf.split_to(len).freeze()))
+    }
+}
+
+impl Stream for Streaming {
+    type Item = Result<Bytes, Status>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            match this.decode() {
+                Ok(Some(msg)) => return Poll::Ready(Some(Ok(msg))),
+                Ok(None) => (),
+                Err(st) => return Poll::Ready(Some(Err(st))),
+            }
+
+            if this.eof {
+                return if this.buf.is_empty() {
+                    Poll::Ready(None)
+                } else {
+                    this.buf.clear();
+                    Poll::Ready(Some(Err(Status::new(
+                        Code::Internal,
+                        "Incomplete message",
+                    ))))
+                };
+            }
+
+            match Pin::new(&mut this.payload).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => this.buf.extend_from_slice(&chunk),
+                Poll::Ready(Some(Err(err))) => {
+                    return Poll::Ready(Some(Err(Status::new(
+                        Code::Internal,
+                        err.to_string(),
+                    ))))
+                }
+                Poll::Ready(None) => this.eof = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Encode length-prefixed gRPC message
+pub fn encode_message(msg: &[u8], dst: &mut BytesMut) {
+    dst.reserve(PREFIX_SIZE + msg.len());
+    dst.put_u8(0);
+    dst.put_u32(msg.len() as u32);
+    dst.extend_from_slice(msg);
+}
+
+/// Response body, encodes messages and stores call status
+pub(super) struct GrpcBody {
+    stream: Pin<Box<dyn Stream<Item = Result<Bytes, Status>>>>,
+    deadline: Option<Sleep>,
+    status: Rc<Cell<Option<Status>>>,
+    done: bool,
+}
+
+impl GrpcBody {
+    pub(super) fn new(
+        stream: Pin<Box<dyn Stream<Item = Result<Bytes, Status>>>>,
+        deadline: Option<Sleep>,
+        status: Rc<Cell<Option<Status>>>,
+    ) -> Self {
+        GrpcBody {
+            stream,
+            deadline,
+            status,
+            done: false,
+        }
+    }
+
+    fn complete(&mut self, status: Status) {
+        self.done = true;
+        self.deadline = None;
+        self.status.set(Some(status));
+    }
+}
+
+impl MessageBody for GrpcBody {
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        // body is completed
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        if let Some(ref deadline) = self.deadline {
+            if deadline.poll_elapsed(cx).is_ready() {
+                self.complete(Status::new(Code::DeadlineExceeded, "Deadline exceeded"));
+                return Poll::Ready(None);
+            }
+        }
+
+        match self.stream.as_mut().poll_next(cx) {
+            Poll::Ready(Some(Ok(msg))) => {
+                let mut buf = BytesMut::new();
+                encode_message(&msg, &mut buf);
+                Poll::Ready(Some(Ok(buf.freeze())))
+            }
+            Poll::Ready(Some(Err(status))) => {
+                self.complete(status);
+                Poll::Ready(None)
+            }
+            Poll::Ready(None) => {
+                self.complete(Status::ok());
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,14 @@
+//! gRPC server support
+//!
+//! gRPC calls are served over http/2 connections by `HttpService`.
+//! Messages are passed to handlers in encoded form, so any protobuf
+//! implementation could be used for message serialization.
+mod deadline;
+mod message;
+mod server;
+mod status;
+
+pub use self::deadline::Deadline;
+pub use self::message::{encode_message, Streaming};
+pub use self::server::{GrpcServer, GrpcService};
+pub use self::status::{Code, Status};
@@ -0,0 +1,367 @@
+use std::task::{Context, Poll};
+use std::{cell::Cell, fmt, future::Future, io, pin::Pin, rc::Rc};
+
+use crate::http::body::Body;
+use crate::http::error::ResponseError;
+use crate::http::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use crate::http::{Method, Request, Response};
+use crate::service::boxed::{self, BoxService, BoxServiceFactory};
+use crate::service::{IntoServiceFactory, Service, ServiceFactory};
+use crate::time::{sleep, timeout};
+use crate::util::{Bytes, HashMap};
+use crate::Stream;
+
+use super::deadline::{Deadline, GRPC_TIMEOUT};
+use super::message::{GrpcBody, Streaming, DEFAULT_MAX_SIZE};
+use super::{Code, Status};
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+type BoxStream = Pin<Box<dyn Stream<Item = Result<Bytes, Status>>>>;
+type Handler = Rc<dyn Fn(Request, Streaming) -> BoxFuture<Result<BoxStream, Status>>>;
+type DefaultFactory = BoxServiceFactory<(), Request, Response, Response, ()>;
+
+/// gRPC server
+///
+/// Server dispatches gRPC calls to registered handlers by request path,
+/// `/package.Service/Method`. Handlers work with encoded messages, message
+/// serialization is up to handler. Call status is sent to the client with
+/// `grpc-status` and `grpc-message` trailers, client's `grpc-timeout`
+/// deadline is applied to the whole call and is available for handlers
+/// as `Deadline` request extension.
+///
+/// Requests that are not gRPC calls are handled by default service,
+/// so grpc endpoints could be served next to regular http endpoints.
+///
+/// ```rust,no_run
+/// use ntex::http::{grpc, HttpService, Request};
+/// use ntex::util::Bytes;
+///
+/// async fn echo(_: Request, msg: Bytes) -> Result<Bytes, grpc::Status> {
+///     Ok(msg)
+/// }
+///
+/// #[ntex::main]
+/// async fn main() -> std::io::Result<()> {
+///     ntex::server::Server::build()
+///         .bind("grpc", "0.0.0.0:50051", |_| {
+///             HttpService::build()
+///                 .h2(grpc::GrpcServer::new().unary("/echo.Echo/Echo", echo))
+///         })?
+///         .run()
+///         .await
+/// }
+/// ```
+pub struct GrpcServer {
+    inner: Rc<Inner>,
+    default: Option<Rc<DefaultFactory>>,
+}
+
+struct Inner {
+    handlers: HashMap<String, Handler>,
+    max_message_size: usize,
+}
+
+impl Default for GrpcServer {
+    fn default() -> Self {
+        GrpcServer::new()
+    }
+}
+
+impl GrpcServer {
+    /// Create new grpc server
+    pub fn new() -> Self {
+        GrpcServer {
+            inner: Rc::new(Inner {
+                handlers: HashMap::default(),
+                max_message_size: DEFAULT_MAX_SIZE,
+            }),
+            default: None,
+        }
+    }
+
+    /// Set max size of request message.
+    ///
+    /// Calls with bigger messages fail with `ResourceExhausted` status.
+    /// By default max size is 4Mb.
+    pub fn max_message_size(mut self, size: usize) -> Self {
+        self.inner_mut().max_message_size = size;
+        self
+    }
+
+    /// Register unary call handler.
+    ///
+    /// Handler receives single request message and returns single
+    /// response message.
+    pub fn unary<F, R>(self, path: &str, f: F) -> Self
+    where
+        F: Fn(Request, Bytes) -> R + 'static,
+        R: Future<Output = Result<Bytes, Status>> + 'static,
+    {
+        let f = Rc::new(f);
+        self.handler(path, move |req, messages| {
+            let f = f.clone();
+            async move {
+                let msg = single_message(messages).await?;
+                let res = f(req, msg).await?;
+                Ok(One(Some(res)))
+            }
+        })
+    }
+
+    /// Register client streaming call handler.
+    ///
+    /// Handler receives stream of request messages and returns single
+    /// response message.
+    pub fn client_streaming<F, R>(self, path: &str, f: F) -> Self
+    where
+        F: Fn(Request, Streaming) -> R + 'static,
+        R: Future<Output = Result<Bytes, Status>> + 'static,
+    {
+        let f = Rc::new(f);
+        self.handler(path, move |req, messages| {
+            let f = f.clone();
+            async move { Ok(One(Some(f(req, messages).await?))) }
+        })
+    }
+
+    /// Register server streaming call handler.
+    ///
+    /// Handler receives single request message and returns stream of
+    /// response messages. Error in response stream completes the call
+    /// with error status.
+    pub fn server_streaming<F, R, S>(self, path: &str, f: F) -> Self
+    where
+        F: Fn(Request, Bytes) -> R + 'static,
+        R: Future<Output = Result<S, Status>> + 'static,
+        S: Stream<Item = Result<Bytes, Status>> + 'static,
+    {
+        let f = Rc::new(f);
+        self.handler(path, move |req, messages| {
+            let f = f.clone();
+            async move {
+                let msg = single_message(messages).await?;
+                f(req, msg).await
+            }
+        })
+    }
+
+    /// Register bi-directional streaming call handler.
+    pub fn streaming<F, R, S>(self, path: &str, f: F) -> Self
+    where
+        F: Fn(Request, Streaming) -> R + 'static,
+        R: Future<Output = Result<S, Status>> + 'static,
+        S: Stream<Item = Result<Bytes, Status>> + 'static,
+    {
+        self.handler(path, f)
+    }
+
+    /// Default service to be used for requests that are not grpc calls.
+    ///
+    /// By default `404 Not Found` response is returned.
+    pub fn default_service<F, U>(mut self, f: F) -> Self
+    where
+        F: IntoServiceFactory<U, Request>,
+        U: ServiceFactory<Request, Response = Response> + 'static,
+        U::Error: ResponseError,
+        U::InitError: fmt::Debug,
+    {
+        self.default = Some(Rc::new(boxed::factory(
+            f.into_factory()
+                .map_err(|e| e.error_response())
+                .map_init_err(|e| log::error!("Cannot construct default service: {:?}", e)),
+        )));
+        self
+    }
+
+    fn handler<F, R, S>(mut self, path: &str, f: F) -> Self
+    where
+        F: Fn(Request, Streaming) -> R + 'static,
+        R: Future<Output = Result<S, Status>> + 'static,
+        S: Stream<Item = Result<Bytes, Status>> + 'static,
+    {
+        let f = Rc::new(f);
+        let handler: Handler = Rc::new(move |req, messages| {
+            let fut = f(req, messages);
+            Box::pin(async move {
+                let stream: BoxStream = Box::pin(fut.await?);
+                Ok(stream)
+            })
+        });
+        self.inner_mut().handlers.insert(path.to_string(), handler);
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("Grpc server is in use")
+    }
+}
+
+impl ServiceFactory<Request> for GrpcServer {
+    type Response = Response;
+    type Error = io::Error;
+    type Service = GrpcService;
+    type InitError = ();
+    type Future = BoxFuture<Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let inner = self.inner.clone();
+        let default = self.default.clone();
+
+        Box::pin(async move {
+            let default = if let Some(ref default) = default {
+                Some(default.new_service(()).await?)
+            } else {
+                None
+            };
+            Ok(GrpcService { inner, default })
+        })
+    }
+}
+
+/// gRPC calls dispatcher service
+pub struct GrpcService {
+    inner: Rc<Inner>,
+    default: Option<BoxService<Request, Response, Response>>,
+}
+
+impl Service<Request> for GrpcService {
+    type Response = Response;
+    type Error = io::Error;
+    type Future = BoxFuture<Result<Response, io::Error>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Some(ref default) = self.default {
+            if default.poll_ready(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        if let Some(ref default) = self.default {
+            default.poll_shutdown(cx, is_error)
+        } else {
+            Poll::Ready(())
+        }
+    }
+
+    fn call(&self, req: Request) -> Self::Future {
+        if is_grpc(&req) {
+            Box::pin(self.inner.clone().dispatch(req))
+        } else if let Some(ref default) = self.default {
+            let fut = default.call(req);
+            Box::pin(async move { Ok(fut.await.unwrap_or_else(|res| res)) })
+        } else {
+            Box::pin(async move { Ok(Response::NotFound().finish()) })
+        }
+    }
+}
+
+impl Inner {
+    async fn dispatch(self: Rc<Self>, mut req: Request) -> Result<Response, io::Error> {
+        let deadline = if let Some(val) = req.headers().get(&GRPC_TIMEOUT) {
+            if let Some(deadline) = Deadline::from_header(val) {
+                Some(deadline)
+            } else {
+                return Ok(status_response(Status::new(
+                    Code::InvalidArgument,
+                    "Invalid grpc-timeout header",
+                )));
+            }
+        } else {
+            None
+        };
+
+        let handler = if let Some(handler) = self.handlers.get(req.path()) {
+            handler.clone()
+        } else {
+            return Ok(status_response(Status::new(
+                Code::Unimplemented,
+                format!("Method {} is not implemented", req.path()),
+            )));
+        };
+
+        if let Some(deadline) = deadline {
+            req.extensions_mut().insert(deadline);
+        }
+        let messages =
+            Streaming::new(req.take_payload()).max_message_size(self.max_message_size);
+        let fut = handler(req, messages);
+
+        let result = if let Some(deadline) = deadline {
+            match timeout(deadline.remaining(), fut).await {
+                Ok(result) => result,
+                Err(_) => Err(Status::new(Code::DeadlineExceeded, "Deadline exceeded")),
+            }
+        } else {
+            fut.await
+        };
+
+        Ok(match result {
+            Ok(stream) => {
+                let status = Rc::new(Cell::new(None));
+                let body = GrpcBody::new(
+                    stream,
+                    deadline.map(|d| sleep(d.remaining())),
+                    status.clone(),
+                );
+
+                Response::Ok()
+                    .header(CONTENT_TYPE, HeaderValue::from_static("application/grpc"))
+                    .trailers(move || {
+                        let mut trailers = HeaderMap::new();
+                        status
+                            .take()
+                            .unwrap_or_else(|| Status::new(Code::Unknown, ""))
+                            .set_headers(&mut trailers);
+                        trailers
+                    })
+                    .body(Body::from_message(body))
+            }
+            Err(status) => status_response(status),
+        })
+    }
+}
+
+/// Check if request is grpc call
+fn is_grpc(req: &Request) -> bool {
+    req.method() == Method::POST
+        && req
+            .headers()
+            .get(&CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.starts_with("application/grpc"))
+            .unwrap_or(false)
+}
+
+/// Trailers-only response
+fn status_response(status: Status) -> Response {
+    let mut res = Response::Ok()
+        .header(CONTENT_TYPE, HeaderValue::from_static("application/grpc"))
+        .finish();
+    status.set_headers(res.headers_mut());
+    res
+}
+
+/// Read request message of unary call
+async fn single_message(mut messages: Streaming) -> Result<Bytes, Status> {
+    match crate::util::next(&mut messages).await {
+        Some(Ok(msg)) => Ok(msg),
+        Some(Err(status)) => Err(status),
+        None => Err(Status::new(Code::Internal, "Missing request message")),
+    }
+}
+
+/// Single message stream
+struct One(Option<Bytes>);
+
+impl Stream for One {
+    type Item = Result<Bytes, Status>;
+
+    fn poll_next(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.get_mut().0.take().map(Ok))
+    }
+}
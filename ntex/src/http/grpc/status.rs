@@ -0,0 +1,205 @@
+use std::{error, fmt};
+
+use crate::http::header::{HeaderMap, HeaderName, HeaderValue};
+
+pub(super) const GRPC_STATUS: HeaderName = HeaderName::from_static("grpc-status");
+pub(super) const GRPC_MESSAGE: HeaderName = HeaderName::from_static("grpc-message");
+
+/// gRPC status codes
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Code {
+    /// The operation completed successfully
+    Ok = 0,
+    /// The operation was cancelled
+    Cancelled = 1,
+    /// Unknown error
+    Unknown = 2,
+    /// Client specified an invalid argument
+    InvalidArgument = 3,
+    /// Deadline expired before operation could complete
+    DeadlineExceeded = 4,
+    /// Some requested entity was not found
+    NotFound = 5,
+    /// Some entity that we attempted to create already exists
+    AlreadyExists = 6,
+    /// The caller does not have permission to execute the specified operation
+    PermissionDenied = 7,
+    /// Some resource has been exhausted
+    ResourceExhausted = 8,
+    /// The system is not in a state required for the operation's execution
+    FailedPrecondition = 9,
+    /// The operation was aborted
+    Aborted = 10,
+    /// Operation was attempted past the valid range
+    OutOfRange = 11,
+    /// Operation is not implemented or not supported
+    Unimplemented = 12,
+    /// Internal error
+    Internal = 13,
+    /// The service is currently unavailable
+    Unavailable = 14,
+    /// Unrecoverable data loss or corruption
+    DataLoss = 15,
+    /// The request does not have valid authentication credentials
+    Unauthenticated = 16,
+}
+
+impl Code {
+    /// Convert numeric value to status code.
+    ///
+    /// Unknown values are converted to `Code::Unknown`.
+    pub fn from_i32(code: i32) -> Code {
+        match code {
+            0 => Code::Ok,
+            1 => Code::Cancelled,
+            3 => Code::InvalidArgument,
+            4 => Code::DeadlineExceeded,
+            5 => Code::NotFound,
+            6 => Code::AlreadyExists,
+            7 => Code::PermissionDenied,
+            8 => Code::ResourceExhausted,
+            9 => Code::FailedPrecondition,
+            10 => Code::Aborted,
+            11 => Code::OutOfRange,
+            12 => Code::Unimplemented,
+            13 => Code::Internal,
+            14 => Code::Unavailable,
+            15 => Code::DataLoss,
+            16 => Code::Unauthenticated,
+            _ => Code::Unknown,
+        }
+    }
+
+    fn header_value(self) -> HeaderValue {
+        HeaderValue::from(self as u16)
+    }
+}
+
+/// gRPC call status
+#[derive(Clone, Debug, PartialEq)]
+pub struct Status {
+    code: Code,
+    message: String,
+}
+
+impl Status {
+    /// Create new status
+    pub fn new<T: Into<String>>(code: Code, message: T) -> Self {
+        Status {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Successful call status
+    pub fn ok() -> Self {
+        Status::new(Code::Ok, "")
+    }
+
+    /// Status code
+    pub fn code(&self) -> Code {
+        self.code
+    }
+
+    /// Status message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Read status from `grpc-status` and `grpc-message` headers.
+    ///
+    /// Clients use it to get status of the call from response trailers
+    /// or from headers of trailers-only responses.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Status> {
+        let code = headers
+            .get(&GRPC_STATUS)?
+            .to_str()
+            .ok()?
+            .parse::<i32>()
+            .ok()?;
+        let message = headers
+            .get(&GRPC_MESSAGE)
+            .map(|v| percent_decode(v.as_bytes()))
+            .unwrap_or_default();
+        Some(Status::new(Code::from_i32(code), message))
+    }
+
+    /// Add `grpc-status` and `grpc-message` headers to the map
+    pub(super) fn set_headers(&self, headers: &mut HeaderMap) {
+        headers.insert(GRPC_STATUS, self.code.header_value());
+        if !self.message.is_empty() {
+            if let Ok(value) = HeaderValue::from_str(&percent_encode(&self.message)) {
+                headers.insert(GRPC_MESSAGE, value);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "grpc status: {:?}, message: {:?}",
+            self.code, self.message
+        )
+    }
+}
+
+impl error::Error for Status {}
+
+/// Percent-encode `grpc-message` value
+fn percent_encode(msg: &str) -> String {
+    let mut s = String::with_capacity(msg.len());
+    for b in msg.bytes() {
+        if (0x20..=0x7e).contains(&b) && b != b'%' {
+            s.push(b as char);
+        } else {
+            s.push_str(&format!("%{:02X}", b));
+        }
+    }
+    s
+}
+
+fn percent_decode(val: &[u8]) -> String {
+    let mut buf = Vec::with_capacity(val.len());
+    let mut idx = 0;
+    while idx < val.len() {
+        if val[idx] == b'%' && idx + 2 < val.len() {
+            let hex = std::str::from_utf8(&val[idx + 1..idx + 3])
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok());
+            if let Some(b) = hex {
+                buf.push(b);
+                idx += 3;
+                continue;
+            }
+        }
+        buf.push(val[idx]);
+        idx += 1;
+    }
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_headers() {
+        let status = Status::new(Code::NotFound, "not found: 100%\n");
+        let mut headers = HeaderMap::new();
+        status.set_headers(&mut headers);
+        assert_eq!(headers.get(&GRPC_STATUS).unwrap(), "5");
+        assert_eq!(headers.get(&GRPC_MESSAGE).unwrap(), "not found: 100%25%0A");
+        assert_eq!(Status::from_headers(&headers).unwrap(), status);
+
+        let mut headers = HeaderMap::new();
+        Status::ok().set_headers(&mut headers);
+        assert_eq!(headers.get(&GRPC_STATUS).unwrap(), "0");
+        assert!(headers.get(&GRPC_MESSAGE).is_none());
+        assert_eq!(Status::from_headers(&headers).unwrap().code(), Code::Ok);
+
+        assert_eq!(Code::from_i32(100), Code::Unknown);
+        assert!(Status::from_headers(&HeaderMap::new()).is_none());
+    }
+}
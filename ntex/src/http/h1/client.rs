@@ -6,6 +6,7 @@ use crate::codec::{Decoder, Encoder};
 use crate::http::body::BodySize;
 use crate::http::config::DateService;
 use crate::http::error::{ParseError, PayloadError};
+use crate::http::header::HeaderMap;
 use crate::http::message::{ConnectionType, RequestHeadType, ResponseHead};
 use crate::http::{Method, Version};
 use crate::util::{Bytes, BytesMut};
@@ -35,6 +36,7 @@ struct ClientCodecInner {
     timer: DateService,
     decoder: decoder::MessageDecoder<ResponseHead>,
     payload: RefCell<Option<PayloadDecoder>>,
+    trailers: RefCell<Option<HeaderMap>>,
     version: Cell<Version>,
     ctype: Cell<ConnectionType>,
 
@@ -64,6 +66,7 @@ impl ClientCodec {
                 timer,
                 decoder: decoder::MessageDecoder::default(),
                 payload: RefCell::new(None),
+                trailers: RefCell::new(None),
                 version: Cell::new(Version::HTTP_11),
                 ctype: Cell::new(ConnectionType::Close),
                 flags: Cell::new(flags),
@@ -105,6 +108,13 @@ impl ClientPayloadCodec {
         self.inner.ctype.get() == ConnectionType::KeepAlive
     }
 
+    /// Take trailers of the last chunked payload
+    ///
+    /// Trailers are available after payload eof is decoded.
+    pub fn take_trailers(&self) -> Option<HeaderMap> {
+        self.inner.trailers.borrow_mut().take()
+    }
+
     /// Transform payload codec to a message codec
     pub fn into_message_codec(self) -> ClientCodec {
         ClientCodec { inner: self.inner }
@@ -176,6 +186,10 @@ impl Decoder for ClientPayloadCodec {
                 reserve_readbuf(src);
                 Some(Some(chunk))
             }
+            Some(PayloadItem::Trailers(trailers)) => {
+                *self.inner.trailers.borrow_mut() = Some(trailers);
+                return self.decode(src);
+            }
             Some(PayloadItem::Eof) => {
                 self.inner.payload.borrow_mut().take();
                 Some(None)
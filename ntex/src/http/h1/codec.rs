@@ -6,22 +6,32 @@ use crate::codec::{Decoder, Encoder};
 use crate::http::body::BodySize;
 use crate::http::config::DateService;
 use crate::http::error::ParseError;
-use crate::http::message::ConnectionType;
+use crate::http::message::{ConnectionType, RequestTarget};
 use crate::http::request::Request;
 use crate::http::response::Response;
-use crate::http::{Method, Version};
-use crate::util::BytesMut;
+use crate::http::{HeaderMap, Method, Version};
+use crate::util::{BytesChain, BytesMut};
 
-use super::{decoder, decoder::PayloadType, encoder, Message};
+use super::{decoder, decoder::PayloadType, encoder, Message, PreparedResponse};
 
 bitflags! {
     struct Flags: u8 {
         const HEAD              = 0b0000_0001;
         const STREAM            = 0b0000_0010;
         const KEEPALIVE_ENABLED = 0b0000_0100;
+        const ABSOLUTE_FORM     = 0b0000_1000;
+        const AUTHORITY_FORM    = 0b0001_0000;
     }
 }
 
+/// State of decoded request, required for response encoding
+#[derive(Copy, Clone, Debug)]
+pub(super) struct RequestState {
+    version: Version,
+    ctype: ConnectionType,
+    head: bool,
+}
+
 /// HTTP/1 Codec
 pub struct Codec {
     timer: DateService,
@@ -65,9 +75,9 @@ impl Codec {
     /// `keepalive_enabled` how response `connection` header get generated.
     pub fn new(timer: DateService, keep_alive: bool) -> Self {
         let flags = if keep_alive {
-            Flags::KEEPALIVE_ENABLED
+            Flags::KEEPALIVE_ENABLED | Flags::ABSOLUTE_FORM | Flags::AUTHORITY_FORM
         } else {
-            Flags::empty()
+            Flags::ABSOLUTE_FORM | Flags::AUTHORITY_FORM
         };
 
         Codec {
@@ -80,6 +90,28 @@ impl Codec {
         }
     }
 
+    /// Accept absolute-form request targets, `GET http://example.org/ HTTP/1.1`.
+    ///
+    /// Requests with absolute-form targets are sent to forward proxies.
+    /// By default absolute-form is accepted.
+    pub fn absolute_form(self, enabled: bool) -> Self {
+        let mut flags = self.flags.get();
+        flags.set(Flags::ABSOLUTE_FORM, enabled);
+        self.flags.set(flags);
+        self
+    }
+
+    /// Accept authority-form request targets, `CONNECT example.org:443 HTTP/1.1`.
+    ///
+    /// Authority-form is allowed only for `CONNECT` requests.
+    /// By default authority-form is accepted.
+    pub fn authority_form(self, enabled: bool) -> Self {
+        let mut flags = self.flags.get();
+        flags.set(Flags::AUTHORITY_FORM, enabled);
+        self.flags.set(flags);
+        self
+    }
+
     #[inline]
     /// Check if request is upgrade
     pub fn upgrade(&self) -> bool {
@@ -102,12 +134,94 @@ impl Codec {
         self.ctype.set(ctype)
     }
 
+    /// State of last decoded request
+    pub(super) fn request_state(&self) -> RequestState {
+        RequestState {
+            version: self.version.get(),
+            ctype: self.ctype.get(),
+            head: self.flags.get().contains(Flags::HEAD),
+        }
+    }
+
+    /// Restore request state before encoding response
+    pub(super) fn set_request_state(&self, st: RequestState) {
+        let mut flags = self.flags.get();
+        flags.set(Flags::HEAD, st.head);
+        self.flags.set(flags);
+        self.version.set(st.version);
+        self.ctype.set(st.ctype);
+    }
+
     #[inline]
     #[doc(hidden)]
     pub fn set_date_header(&self, dst: &mut BytesMut) {
         self.timer.set_date_header(dst)
     }
 
+    /// Write pre-encoded response
+    ///
+    /// Returns `false` if prepared response cannot be used for current
+    /// request, response must be encoded with regular encoder.
+    pub(super) fn encode_prepared(
+        &self,
+        res: &PreparedResponse,
+        dst: &mut BytesMut,
+    ) -> bool {
+        if self.version.get() != Version::HTTP_11
+            || self.ctype.get() == ConnectionType::Upgrade
+        {
+            return false;
+        }
+
+        // connection status
+        match res.ctype() {
+            Some(ConnectionType::Upgrade) => return false,
+            Some(ConnectionType::Close) => self.ctype.set(ConnectionType::Close),
+            _ => (),
+        }
+
+        res.encode(
+            dst,
+            self.ctype.get() == ConnectionType::Close,
+            self.flags.get().contains(Flags::HEAD),
+            &self.timer,
+        );
+        true
+    }
+
+    /// Encode end of payload with trailers
+    ///
+    /// Trailers are encoded only for chunked transfer encoding.
+    pub(super) fn encode_trailers(
+        &self,
+        trailers: &HeaderMap,
+        dst: &mut BytesMut,
+    ) -> Result<(), io::Error> {
+        self.encoder.encode_trailers(trailers, dst)
+    }
+
+    /// Encode message into segmented buffer
+    ///
+    /// Message head is encoded into separate chunk, payload chunks
+    /// are added to the buffer without copying.
+    pub fn encode_chain(
+        &self,
+        item: Message<(Response<()>, BodySize)>,
+        dst: &mut BytesChain,
+    ) -> Result<(), io::Error> {
+        match item {
+            Message::Chunk(Some(bytes)) => {
+                self.encoder.encode_chunk_chain(bytes, dst)?;
+            }
+            item => {
+                let mut buf = BytesMut::new();
+                self.encode(item, &mut buf)?;
+                dst.push(buf);
+            }
+        }
+        Ok(())
+    }
+
     fn insert_flags(&self, f: Flags) {
         let mut flags = self.flags.get();
         flags.insert(f);
@@ -123,6 +237,25 @@ impl Decoder for Codec {
         if let Some((req, payload)) = self.decoder.decode(src)? {
             let head = req.head();
             let mut flags = self.flags.get();
+
+            // check request target form
+            match head.target() {
+                RequestTarget::Absolute if !flags.contains(Flags::ABSOLUTE_FORM) => {
+                    return Err(ParseError::InvalidInput(
+                        "Absolute-form request target is not allowed",
+                    ));
+                }
+                RequestTarget::Authority
+                    if head.method != Method::CONNECT
+                        || !flags.contains(Flags::AUTHORITY_FORM) =>
+                {
+                    return Err(ParseError::InvalidInput(
+                        "Authority-form request target is not allowed",
+                    ));
+                }
+                _ => (),
+            }
+
             flags.set(Flags::HEAD, head.method == Method::HEAD);
             self.flags.set(flags);
             self.version.set(head.version);
@@ -238,4 +371,84 @@ mod tests {
         assert!(codec.upgrade());
         assert!(!codec.keepalive_enabled());
     }
+
+    #[test]
+    fn test_request_target() {
+        let codec = Codec::default();
+        let mut buf = BytesMut::from("GET http://example.org/test?q=1 HTTP/1.1\r\n\r\n");
+        let (req, _) = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.target(), RequestTarget::Absolute);
+        assert_eq!(req.uri().host(), Some("example.org"));
+        assert_eq!(req.path(), "/test");
+
+        let mut buf = BytesMut::from("CONNECT example.org:443 HTTP/1.1\r\n\r\n");
+        let (req, _) = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.target(), RequestTarget::Authority);
+        assert_eq!(req.uri().port_u16(), Some(443));
+
+        let mut buf = BytesMut::from("OPTIONS * HTTP/1.1\r\n\r\n");
+        let (req, _) = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.target(), RequestTarget::Asterisk);
+
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\n\r\n");
+        let (req, _) = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(req.target(), RequestTarget::Origin);
+
+        // authority-form is allowed only for CONNECT
+        let mut buf = BytesMut::from("GET example.org:443 HTTP/1.1\r\n\r\n");
+        assert!(codec.decode(&mut buf).is_err());
+
+        let codec = Codec::default().absolute_form(false).authority_form(false);
+        let mut buf = BytesMut::from("GET http://example.org/ HTTP/1.1\r\n\r\n");
+        assert!(codec.decode(&mut buf).is_err());
+        let mut buf = BytesMut::from("CONNECT example.org:443 HTTP/1.1\r\n\r\n");
+        assert!(codec.decode(&mut buf).is_err());
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\n\r\n");
+        assert!(codec.decode(&mut buf).unwrap().is_some());
+    }
+
+    #[crate::rt_test]
+    async fn test_encode_chain() {
+        let codec = Codec::default();
+        let mut buf = BytesMut::from("GET /test HTTP/1.1\r\n\r\n");
+        let _item = codec.decode(&mut buf).unwrap().unwrap();
+
+        let body = Bytes::from(vec![b'x'; 1024]);
+        let mut chain = BytesChain::new();
+        codec
+            .encode_chain(
+                Message::Item((Response::Ok().finish().drop_body(), BodySize::Stream)),
+                &mut chain,
+            )
+            .unwrap();
+        codec
+            .encode_chain(Message::Chunk(Some(body.clone())), &mut chain)
+            .unwrap();
+        codec
+            .encode_chain(Message::Chunk(None), &mut chain)
+            .unwrap();
+
+        let chunks: Vec<_> = chain.chunks().cloned().collect();
+        assert_eq!(chunks.len(), 5);
+        assert!(chunks[0].starts_with(b"HTTP/1.1 200 OK\r\n"));
+        assert!(chunks[0].ends_with(b"\r\n\r\n"));
+        assert_eq!(&chunks[1][..], b"400\r\n");
+        assert_eq!(chunks[2].as_ptr(), body.as_ptr());
+        assert_eq!(&chunks[3][..], b"\r\n");
+        assert_eq!(&chunks[4][..], b"0\r\n\r\n");
+
+        // sized body is truncated without copying
+        let mut chain = BytesChain::new();
+        codec
+            .encode_chain(
+                Message::Item((Response::Ok().finish().drop_body(), BodySize::Sized(10))),
+                &mut chain,
+            )
+            .unwrap();
+        codec
+            .encode_chain(Message::Chunk(Some(body.clone())), &mut chain)
+            .unwrap();
+        assert_eq!(chain.chunks_count(), 2);
+        assert_eq!(chain.chunks().last().unwrap().len(), 10);
+    }
 }
@@ -0,0 +1,614 @@
+//! Conformance test-suite for http/1 server implementations
+//!
+//! Suite sends corpus of valid and invalid requests to the server and
+//! checks responses. Server under test is expected to read request payload
+//! and to respond with successful response to any valid request. Invalid
+//! requests must be rejected with error response or connection must be closed.
+//!
+//! ```rust
+//! use ntex::http::{h1::conformance::Suite, HttpService, Request, Response};
+//! use ntex::util::next;
+//!
+//! #[ntex::main]
+//! async fn main() {
+//!     let report = Suite::default()
+//!         .run_service(HttpService::build().h1(|mut req: Request| async move {
+//!             let mut payload = req.take_payload();
+//!             while let Some(chunk) = next(&mut payload).await {
+//!                 if chunk.is_err() {
+//!                     return Ok::<_, std::io::Error>(Response::BadRequest().finish());
+//!                 }
+//!             }
+//!             Ok(Response::Ok().finish())
+//!         }))
+//!         .await;
+//!     report.assert_ok();
+//! }
+//! ```
+use std::{cell::Cell, cell::RefCell, fmt, future::Future, rc::Rc};
+
+use crate::codec::Decoder;
+use crate::http::error::ParseError;
+use crate::io::{Base, Io, IoBoxed};
+use crate::service::{Service, ServiceFactory};
+use crate::testing::Io as IoTest;
+use crate::time::{timeout, Millis};
+use crate::util::{Bytes, BytesMut};
+
+use super::decoder::{PayloadDecoder, PayloadItem};
+
+const MAX_HEADERS: usize = 96;
+
+/// Expected server behavior
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expect {
+    /// Successful response for each of pipelined requests
+    Success(usize),
+    /// `100 Continue` response followed by successful response
+    Continue,
+    /// Error response or connection close without response
+    Rejected,
+}
+
+/// Conformance test case
+#[derive(Clone, Debug)]
+pub struct Case {
+    name: String,
+    request: Bytes,
+    expect: Expect,
+}
+
+impl Case {
+    /// Create new test case
+    pub fn new<N, R>(name: N, request: R, expect: Expect) -> Self
+    where
+        N: Into<String>,
+        R: Into<Bytes>,
+    {
+        Case {
+            name: name.into(),
+            request: request.into(),
+            expect,
+        }
+    }
+
+    /// Test case name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Raw request data
+    pub fn request(&self) -> &Bytes {
+        &self.request
+    }
+
+    /// Expected server behavior
+    pub fn expect(&self) -> &Expect {
+        &self.expect
+    }
+}
+
+/// Conformance test-suite
+#[derive(Clone, Debug)]
+pub struct Suite {
+    cases: Vec<Case>,
+    timeout: Millis,
+}
+
+impl Default for Suite {
+    /// Create test-suite with built-in corpus
+    fn default() -> Self {
+        Suite {
+            cases: corpus(),
+            timeout: Millis(1_000),
+        }
+    }
+}
+
+impl Suite {
+    /// Create test-suite without test cases
+    pub fn empty() -> Self {
+        Suite {
+            cases: Vec::new(),
+            timeout: Millis(1_000),
+        }
+    }
+
+    /// Add test case
+    pub fn case(mut self, case: Case) -> Self {
+        self.cases.push(case);
+        self
+    }
+
+    /// Remove test case by name
+    ///
+    /// Could be used for cases that are intentionally handled differently
+    /// by server configuration.
+    pub fn skip(mut self, name: &str) -> Self {
+        self.cases.retain(|case| case.name != name);
+        self
+    }
+
+    /// Set response timeout for each test case
+    ///
+    /// By default timeout is 1 second.
+    pub fn timeout<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        self.timeout = timeout.into();
+        self
+    }
+
+    /// Test cases
+    pub fn cases(&self) -> &[Case] {
+        &self.cases
+    }
+
+    /// Run test-suite, `connect` opens new connection to the server
+    pub async fn run<F, R, I>(&self, connect: F) -> Report
+    where
+        F: Fn() -> R,
+        R: Future<Output = I>,
+        I: Into<IoBoxed>,
+    {
+        let mut results = Vec::with_capacity(self.cases.len());
+        for case in &self.cases {
+            let io = connect().await.into();
+            let result = run_case(&io, case, self.timeout).await;
+            io.force_close();
+            results.push((case.name.clone(), result));
+        }
+        Report { results }
+    }
+
+    /// Run test-suite against service factory
+    ///
+    /// Service is created once, each test case is executed over new
+    /// in-memory connection.
+    pub async fn run_service<T>(&self, factory: T) -> Report
+    where
+        T: ServiceFactory<Io<Base>>,
+        T::Service: 'static,
+        T::InitError: fmt::Debug,
+    {
+        let srv = Rc::new(
+            factory
+                .new_service(())
+                .await
+                .expect("Cannot create service"),
+        );
+        self.run(|| {
+            let (client, server) = IoTest::create();
+            client.remote_buffer_cap(usize::MAX);
+            server.remote_buffer_cap(usize::MAX);
+
+            let srv = srv.clone();
+            crate::rt::spawn(async move {
+                let _ = crate::util::poll_fn(|cx| srv.poll_ready(cx)).await;
+                let _ = srv.call(Io::new(server)).await;
+            });
+            async move { Io::new(client) }
+        })
+        .await
+    }
+}
+
+/// Test-suite results
+#[derive(Debug)]
+pub struct Report {
+    results: Vec<(String, Result<(), String>)>,
+}
+
+impl Report {
+    /// Check if all test cases are passed
+    pub fn is_ok(&self) -> bool {
+        self.results.iter().all(|(_, res)| res.is_ok())
+    }
+
+    /// Number of passed test cases
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|(_, res)| res.is_ok()).count()
+    }
+
+    /// Failed test cases with failure description
+    pub fn failures(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.results.iter().filter_map(|(name, res)| match res {
+            Ok(_) => None,
+            Err(err) => Some((name.as_str(), err.as_str())),
+        })
+    }
+
+    /// Panic if any of test cases is failed
+    pub fn assert_ok(&self) {
+        if !self.is_ok() {
+            panic!("{}", self)
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "h1 conformance: {} passed, {} failed",
+            self.passed(),
+            self.results.len() - self.passed()
+        )?;
+        for (name, err) in self.failures() {
+            writeln!(f, "  {}: {}", name, err)?;
+        }
+        Ok(())
+    }
+}
+
+async fn run_case(io: &IoBoxed, case: &Case, tm: Millis) -> Result<(), String> {
+    let codec = ResponseCodec::new(case.request.starts_with(b"HEAD "));
+    // server could close connection before request is flushed
+    if let Err(e) = io.write(&case.request) {
+        return Err(format!("Cannot send request: {:?}", e));
+    }
+
+    let expected = match case.expect {
+        Expect::Success(n) => n,
+        Expect::Continue => 2,
+        Expect::Rejected => 1,
+    };
+    let mut statuses = Vec::new();
+    let mut closed = false;
+    while statuses.len() < expected {
+        match timeout(tm, io.recv(&codec)).await {
+            Ok(Ok(Some(status))) => statuses.push(status),
+            Ok(Ok(None)) => {
+                closed = true;
+                break;
+            }
+            Ok(Err(e)) => {
+                if case.expect == Expect::Rejected {
+                    closed = true;
+                    break;
+                }
+                return Err(format!("Cannot read response: {:?}", e));
+            }
+            Err(_) => break,
+        }
+    }
+
+    match case.expect {
+        Expect::Success(_) => {
+            if statuses.len() < expected {
+                Err(format!(
+                    "Expected {} responses, got {:?}",
+                    expected, statuses
+                ))
+            } else if statuses.iter().all(|s| (200..300).contains(s)) {
+                Ok(())
+            } else {
+                Err(format!("Expected successful responses, got {:?}", statuses))
+            }
+        }
+        Expect::Continue => match &statuses[..] {
+            [100, s] if (200..300).contains(s) => Ok(()),
+            _ => Err(format!("Expected [100, 2xx] responses, got {:?}", statuses)),
+        },
+        Expect::Rejected => match statuses.first() {
+            Some(s) if *s >= 400 => Ok(()),
+            Some(s) => Err(format!("Expected error response, got {}", s)),
+            None if closed => Ok(()),
+            None => Err("Request is not rejected, no response".to_string()),
+        },
+    }
+}
+
+/// Decodes response status codes, skips response bodies
+struct ResponseCodec {
+    head: bool,
+    eof: Cell<bool>,
+    payload: RefCell<Option<(u16, PayloadDecoder)>>,
+}
+
+impl ResponseCodec {
+    fn new(head: bool) -> Self {
+        ResponseCodec {
+            head,
+            eof: Cell::new(false),
+            payload: RefCell::new(None),
+        }
+    }
+}
+
+impl Decoder for ResponseCodec {
+    type Item = u16;
+    type Error = ParseError;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<u16>, ParseError> {
+        if self.eof.get() {
+            // body is delimited by connection close
+            src.clear();
+            return Ok(None);
+        }
+
+        if let Some((status, payload)) = self.payload.borrow_mut().take() {
+            loop {
+                match payload.decode(src)? {
+                    Some(PayloadItem::Eof) => return Ok(Some(status)),
+                    Some(_) => continue,
+                    None => {
+                        *self.payload.borrow_mut() = Some((status, payload));
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut res = httparse::Response::new(&mut headers);
+        let (len, status, payload) = match res.parse(src)? {
+            httparse::Status::Complete(len) => {
+                let status = res.code.unwrap_or(0);
+                let mut length = None;
+                let mut chunked = false;
+                for hdr in res.headers.iter() {
+                    if hdr.name.eq_ignore_ascii_case("content-length") {
+                        length = std::str::from_utf8(hdr.value)
+                            .ok()
+                            .and_then(|s| s.trim().parse::<u64>().ok());
+                        if length.is_none() {
+                            return Err(ParseError::Header);
+                        }
+                    } else if hdr.name.eq_ignore_ascii_case("transfer-encoding") {
+                        chunked = hdr.value.eq_ignore_ascii_case(b"chunked");
+                    }
+                }
+
+                let payload = if self.head
+                    || (100..200).contains(&status)
+                    || status == 204
+                    || status == 304
+                {
+                    None
+                } else if chunked {
+                    Some(PayloadDecoder::chunked())
+                } else if let Some(len) = length {
+                    Some(PayloadDecoder::length(len))
+                } else {
+                    self.eof.set(true);
+                    None
+                };
+                (len, status, payload)
+            }
+            httparse::Status::Partial => return Ok(None),
+        };
+        let _ = src.split_to(len);
+
+        if let Some(payload) = payload {
+            *self.payload.borrow_mut() = Some((status, payload));
+            if let Some(status) = self.decode(src)? {
+                return Ok(Some(status));
+            }
+            Ok(None)
+        } else {
+            Ok(Some(status))
+        }
+    }
+}
+
+fn corpus() -> Vec<Case> {
+    use self::Expect::*;
+
+    let large_header = format!(
+        "GET / HTTP/1.1\r\nHost: localhost\r\nX-Large: {}\r\n\r\n",
+        "a".repeat(512 * 1024)
+    );
+
+    vec![
+        // valid requests
+        Case::new(
+            "get",
+            &b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n"[..],
+            Success(1),
+        ),
+        Case::new("get-http10", &b"GET / HTTP/1.0\r\n\r\n"[..], Success(1)),
+        Case::new(
+            "head",
+            &b"HEAD / HTTP/1.1\r\nHost: localhost\r\n\r\n"[..],
+            Success(1),
+        ),
+        Case::new(
+            "options-asterisk",
+            &b"OPTIONS * HTTP/1.1\r\nHost: localhost\r\n\r\n"[..],
+            Success(1),
+        ),
+        Case::new(
+            "absolute-form",
+            &b"GET http://localhost/path?q=1 HTTP/1.1\r\nHost: localhost\r\n\r\n"[..],
+            Success(1),
+        ),
+        Case::new(
+            "header-whitespace",
+            &b"GET / HTTP/1.1\r\nHost:   localhost  \r\nX-Empty:\r\n\r\n"[..],
+            Success(1),
+        ),
+        Case::new(
+            "header-name-case",
+            &b"POST / HTTP/1.1\r\nHOST: localhost\r\ncontent-LENGTH: 5\r\n\r\nhello"[..],
+            Success(1),
+        ),
+        Case::new(
+            "content-length",
+            &b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello"[..],
+            Success(1),
+        ),
+        Case::new(
+            "chunked",
+            &b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+               5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n"[..],
+            Success(1),
+        ),
+        Case::new(
+            "chunked-extensions",
+            &b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+               5;name=value\r\nhello\r\n0\r\n\r\n"[..],
+            Success(1),
+        ),
+        Case::new(
+            "chunked-trailers",
+            &b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+               5\r\nhello\r\n0\r\nX-Trailer: value\r\n\r\n"[..],
+            Success(1),
+        ),
+        Case::new(
+            "pipelining",
+            &b"GET /1 HTTP/1.1\r\nHost: localhost\r\n\r\n\
+               POST /2 HTTP/1.1\r\nHost: localhost\r\nContent-Length: 3\r\n\r\nabc\
+               GET /3 HTTP/1.1\r\nHost: localhost\r\n\r\n"[..],
+            Success(3),
+        ),
+        Case::new(
+            "expect-continue",
+            &b"POST / HTTP/1.1\r\nHost: localhost\r\nExpect: 100-continue\r\n\
+               Content-Length: 5\r\n\r\nhello"[..],
+            Continue,
+        ),
+        // invalid requests
+        Case::new(
+            "invalid-method",
+            &b"G(T / HTTP/1.1\r\nHost: localhost\r\n\r\n"[..],
+            Rejected,
+        ),
+        Case::new(
+            "invalid-version",
+            &b"GET / HTTP/1.x\r\nHost: localhost\r\n\r\n"[..],
+            Rejected,
+        ),
+        Case::new(
+            "invalid-target",
+            &b"GET /a b HTTP/1.1\r\nHost: localhost\r\n\r\n"[..],
+            Rejected,
+        ),
+        Case::new(
+            "header-without-colon",
+            &b"GET / HTTP/1.1\r\nHost localhost\r\n\r\n"[..],
+            Rejected,
+        ),
+        Case::new(
+            "space-before-colon",
+            &b"GET / HTTP/1.1\r\nHost : localhost\r\n\r\n"[..],
+            Rejected,
+        ),
+        Case::new(
+            "nul-in-header",
+            &b"GET / HTTP/1.1\r\nHost: localhost\r\nX-Test: a\x00b\r\n\r\n"[..],
+            Rejected,
+        ),
+        Case::new(
+            "bare-cr-in-header",
+            &b"GET / HTTP/1.1\r\nHost: localhost\r\nX-Test: a\rb\r\n\r\n"[..],
+            Rejected,
+        ),
+        Case::new(
+            "obs-fold",
+            &b"GET / HTTP/1.1\r\nHost: localhost\r\nX-Test: a\r\n b\r\n\r\n"[..],
+            Rejected,
+        ),
+        Case::new(
+            "invalid-content-length",
+            &b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: abc\r\n\r\n"[..],
+            Rejected,
+        ),
+        Case::new(
+            "negative-content-length",
+            &b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: -5\r\n\r\nhello"[..],
+            Rejected,
+        ),
+        Case::new(
+            "conflicting-content-length",
+            &b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\
+               Content-Length: 6\r\n\r\nhello!"[..],
+            Rejected,
+        ),
+        Case::new(
+            "content-length-and-chunked",
+            &b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\
+               Transfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n"[..],
+            Rejected,
+        ),
+        Case::new(
+            "invalid-chunk-size",
+            &b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+               zz\r\nhello\r\n0\r\n\r\n"[..],
+            Rejected,
+        ),
+        Case::new(
+            "chunk-size-overflow",
+            &b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+               fffffffffffffffffff\r\nhello\r\n0\r\n\r\n"[..],
+            Rejected,
+        ),
+        Case::new(
+            "unknown-transfer-encoding",
+            &b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: gzip\r\n\r\nhello"
+                [..],
+            Rejected,
+        ),
+        Case::new("header-too-large", large_header, Rejected),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{HttpService, Request, Response};
+    use crate::util::next;
+
+    fn service() -> impl ServiceFactory<
+        Io<Base>,
+        Response = (),
+        Error = crate::http::error::DispatchError,
+        InitError = (),
+        Service = impl Service<Io<Base>> + 'static,
+    > {
+        HttpService::build().h1(|mut req: Request| async move {
+            let mut payload = req.take_payload();
+            while let Some(chunk) = next(&mut payload).await {
+                if chunk.is_err() {
+                    return Ok::<_, std::io::Error>(Response::BadRequest().finish());
+                }
+            }
+            Ok(Response::Ok().body("ok"))
+        })
+    }
+
+    #[crate::rt_test]
+    async fn test_conformance() {
+        let report = Suite::default().run_service(service()).await;
+        assert_eq!(report.passed(), Suite::default().cases().len());
+        report.assert_ok();
+    }
+
+    #[crate::rt_test]
+    async fn test_failures() {
+        let report = Suite::empty()
+            .case(Case::new(
+                "accepted",
+                &b"GET / HTTP/1.1\r\n\r\n"[..],
+                Expect::Rejected,
+            ))
+            .case(Case::new(
+                "rejected",
+                &b"G(T / HTTP/1.1\r\n\r\n"[..],
+                Expect::Success(1),
+            ))
+            .case(Case::new(
+                "get",
+                &b"GET / HTTP/1.1\r\n\r\n"[..],
+                Expect::Success(1),
+            ))
+            .skip("get")
+            .timeout(Millis(100))
+            .run_service(service())
+            .await;
+        assert!(!report.is_ok());
+        assert_eq!(report.passed(), 0);
+        let failures: Vec<_> = report.failures().map(|(name, _)| name).collect();
+        assert_eq!(failures, vec!["accepted", "rejected"]);
+        assert!(format!("{}", report).contains("0 passed, 2 failed"));
+    }
+}
@@ -379,6 +379,7 @@ impl HeaderIndex {
 /// Http payload item
 pub enum PayloadItem {
     Chunk(Bytes),
+    Trailers(HeaderMap),
     Eof,
 }
 
@@ -444,6 +445,7 @@ enum ChunkedState {
     Body,
     BodyCr,
     BodyLf,
+    Trailers,
     EndCr,
     EndLf,
     End,
@@ -481,13 +483,19 @@ impl Decoder for PayloadDecoder {
             Kind::Chunked(ref mut state, ref mut size) => {
                 let result = loop {
                     let mut buf = None;
+                    let mut trailers = None;
                     // advances the chunked state
-                    *state = match state.step(src, size, &mut buf) {
+                    *state = match state.step(src, size, &mut buf, &mut trailers) {
                         Poll::Pending => break Ok(None),
                         Poll::Ready(Ok(state)) => state,
                         Poll::Ready(Err(e)) => break Err(e),
                     };
 
+                    if let Some(trailers) = trailers {
+                        log::trace!("Chunked stream trailers: {:?}", trailers);
+                        break Ok(Some(PayloadItem::Trailers(trailers)));
+                    }
+
                     if *state == ChunkedState::End {
                         log::trace!("End of chunked stream");
                         break Ok(Some(PayloadItem::Eof));
@@ -532,6 +540,7 @@ impl ChunkedState {
         body: &mut BytesMut,
         size: &mut u64,
         buf: &mut Option<Bytes>,
+        trailers: &mut Option<HeaderMap>,
     ) -> Poll<Result<ChunkedState, ParseError>> {
         use self::ChunkedState::*;
         match *self {
@@ -542,6 +551,7 @@ impl ChunkedState {
             Body => ChunkedState::read_body(body, size, buf),
             BodyCr => ChunkedState::read_body_cr(body),
             BodyLf => ChunkedState::read_body_lf(body),
+            Trailers => ChunkedState::read_trailers(body, trailers),
             EndCr => ChunkedState::read_end_cr(body),
             EndLf => ChunkedState::read_end_lf(body),
             End => Poll::Ready(Ok(ChunkedState::End)),
@@ -610,7 +620,7 @@ impl ChunkedState {
     ) -> Poll<Result<ChunkedState, ParseError>> {
         match byte!(rdr) {
             b'\n' if *size > 0 => Poll::Ready(Ok(ChunkedState::Body)),
-            b'\n' if *size == 0 => Poll::Ready(Ok(ChunkedState::EndCr)),
+            b'\n' if *size == 0 => Poll::Ready(Ok(ChunkedState::Trailers)),
             _ => Poll::Ready(Err(ParseError::InvalidInput("Invalid chunk size LF"))),
         }
     }
@@ -655,6 +665,43 @@ impl ChunkedState {
             _ => Poll::Ready(Err(ParseError::InvalidInput("Invalid chunk body LF"))),
         }
     }
+    fn read_trailers(
+        rdr: &mut BytesMut,
+        trailers: &mut Option<HeaderMap>,
+    ) -> Poll<Result<ChunkedState, ParseError>> {
+        // empty trailer section
+        match rdr.first() {
+            None => return Poll::Pending,
+            Some(b'\r') => return Poll::Ready(Ok(ChunkedState::EndCr)),
+            _ => (),
+        }
+
+        let mut parsed = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let len = match httparse::parse_headers(rdr, &mut parsed)? {
+            httparse::Status::Complete((len, headers)) => {
+                let mut map = HeaderMap::with_capacity(headers.len());
+                for header in headers {
+                    let name = HeaderName::from_bytes(header.name.as_bytes())
+                        .map_err(|_| ParseError::Header)?;
+                    let value = HeaderValue::from_bytes(header.value)
+                        .map_err(|_| ParseError::Header)?;
+                    map.append(name, value);
+                }
+                *trailers = Some(map);
+                len
+            }
+            httparse::Status::Partial => {
+                if rdr.len() >= MAX_BUFFER_SIZE {
+                    trace!("MAX_BUFFER_SIZE unprocessed trailers reached, closing");
+                    return Poll::Ready(Err(ParseError::TooLarge));
+                }
+                return Poll::Pending;
+            }
+        };
+        rdr.advance(len);
+        Poll::Ready(Ok(ChunkedState::End))
+    }
+
     fn read_end_cr(rdr: &mut BytesMut) -> Poll<Result<ChunkedState, ParseError>> {
         match byte!(rdr) {
             b'\r' => Poll::Ready(Ok(ChunkedState::EndLf)),
@@ -1195,10 +1242,6 @@ mod tests {
         let msg = pl.decode(&mut buf).unwrap().unwrap();
         assert_eq!(msg.chunk().as_ref(), b"li");
 
-        //trailers
-        //buf.feed_data("test: test\r\n");
-        //not_ready!(reader.parse(&mut buf, &mut readbuf));
-
         buf.extend(b"ne\r\n0\r\n");
         let msg = pl.decode(&mut buf).unwrap().unwrap();
         assert_eq!(msg.chunk().as_ref(), b"ne");
@@ -1229,6 +1272,44 @@ mod tests {
         assert!(msg.eof());
     }
 
+    #[test]
+    fn test_parse_chunked_payload_trailers() {
+        let mut buf = BytesMut::from(
+            &"GET /test HTTP/1.1\r\n\
+              transfer-encoding: chunked\r\n\r\n"[..],
+        );
+
+        let reader = MessageDecoder::<Request>::default();
+        let (_, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let pl = pl.unwrap();
+
+        buf.extend(b"4\r\ndata\r\n0\r\nx-checksum: 1234\r\n");
+        let chunk = pl.decode(&mut buf).unwrap().unwrap().chunk();
+        assert_eq!(chunk, Bytes::from_static(b"data"));
+        assert!(pl.decode(&mut buf).unwrap().is_none());
+
+        buf.extend(b"x-status: 0\r\n\r\n");
+        let trailers = match pl.decode(&mut buf).unwrap().unwrap() {
+            PayloadItem::Trailers(trailers) => trailers,
+            _ => panic!(),
+        };
+        assert_eq!(trailers.len(), 2);
+        assert_eq!(trailers.get("x-checksum").unwrap(), "1234");
+        assert_eq!(trailers.get("x-status").unwrap(), "0");
+        assert!(pl.decode(&mut buf).unwrap().unwrap().eof());
+        assert!(buf.is_empty());
+
+        // invalid trailer
+        let mut buf = BytesMut::from(
+            &"GET /test HTTP/1.1\r\n\
+              transfer-encoding: chunked\r\n\r\n"[..],
+        );
+        let (_, pl) = reader.decode(&mut buf).unwrap().unwrap();
+        let pl = pl.unwrap();
+        buf.extend(b"0\r\nx-checksum\r\n\r\n");
+        assert!(pl.decode(&mut buf).is_err());
+    }
+
     #[test]
     fn test_response_http10_read_until_eof() {
         let mut buf = BytesMut::from(&"HTTP/1.0 200 Ok\r\n\r\ntest data"[..]);
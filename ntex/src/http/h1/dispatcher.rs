@@ -1,21 +1,30 @@
 //! Framed transport dispatcher
 use std::task::{Context, Poll};
-use std::{error::Error, fmt, future::Future, io, marker, pin::Pin, rc::Rc, time};
+use std::{collections::VecDeque, error::Error, fmt, future::Future, io};
+use std::{marker, pin::Pin, rc::Rc, time};
 
-use crate::io::{Filter, Io, IoRef, RecvError};
+use crate::io::{Filter, Io, IoBoxed, IoRef, RecvError};
 use crate::service::Service;
-use crate::{time::now, util::ready, util::Bytes};
+use crate::time::{now, sleep, Sleep};
+use crate::{util::ready, util::Bytes};
 
 use crate::http;
+use crate::http::access_log::RequestLogRecord;
 use crate::http::body::{BodySize, MessageBody, ResponseBody};
 use crate::http::config::DispatcherConfig;
-use crate::http::error::{DispatchError, ParseError, PayloadError, ResponseError};
+use crate::http::error::{
+    DispatchError, HttpDispatchError, ParseError, PayloadError, ResponseError,
+};
+use crate::http::message::{ConnectionType, Trailers};
 use crate::http::request::Request;
 use crate::http::response::Response;
 
+use super::codec::{Codec, RequestState};
 use super::decoder::{PayloadDecoder, PayloadItem, PayloadType};
 use super::payload::{Payload, PayloadSender, PayloadStatus};
-use super::{codec::Codec, Message};
+use super::tunnel::Tunnel;
+use super::upgrade::Upgraded;
+use super::Message;
 
 bitflags::bitflags! {
     pub struct Flags: u16 {
@@ -27,6 +36,10 @@ bitflags::bitflags! {
         const UPGRADE         = 0b0000_0100;
         /// Stop after sending payload
         const SENDPAYLOAD_AND_STOP = 0b0000_0100;
+        /// Response body requested flush
+        const FLUSH_PAYLOAD   = 0b0000_1000;
+        /// Max number of requests per connection is reached
+        const LAST_REQUEST    = 0b0001_0000;
     }
 }
 
@@ -51,6 +64,10 @@ enum State<B> {
     },
     #[display(fmt = "State::Upgrade")]
     Upgrade(Option<Request>),
+    #[display(fmt = "State::Connect")]
+    Connect(Option<Request>),
+    #[display(fmt = "State::OnUpgrade")]
+    OnUpgrade(Option<Request>, usize),
     Stop,
 }
 
@@ -64,15 +81,33 @@ pin_project_lite::pin_project! {
     }
 }
 
-struct DispatcherInner<F, S, B, X, U> {
+/// Pipelined request
+enum Pipelined<S: Service<Request>> {
+    Call(Pin<Box<S::Future>>, RequestState),
+    Done(Result<S::Response, S::Error>, RequestState),
+}
+
+/// Decoded item that waits for completion of pipelined requests
+type Deferred = (
+    Result<(Request, PayloadType), RecvError<Codec>>,
+    RequestState,
+);
+
+struct DispatcherInner<F, S: Service<Request>, B, X, U> {
     io: Option<Io<F>>,
     flags: Flags,
     codec: Codec,
     state: IoRef,
     config: Rc<DispatcherConfig<S, X, U>>,
     expire: time::Instant,
+    headers_timer: Option<Sleep>,
     error: Option<DispatchError>,
     payload: Option<(PayloadDecoder, PayloadSender)>,
+    trailers: Option<Trailers>,
+    pipeline: VecDeque<Pipelined<S>>,
+    deferred: Option<Deferred>,
+    requests: usize,
+    records: VecDeque<RequestLogRecord>,
     _t: marker::PhantomData<(S, B)>,
 }
 
@@ -92,8 +127,11 @@ where
     pub(in crate::http) fn new(io: Io<F>, config: Rc<DispatcherConfig<S, X, U>>) -> Self {
         let mut expire = now();
         let state = io.get_ref();
-        let codec = Codec::new(config.timer.clone(), config.keep_alive_enabled());
+        let codec = Codec::new(config.timer.clone(), config.keep_alive_enabled())
+            .absolute_form(config.absolute_form)
+            .authority_form(config.authority_form);
         io.set_disconnect_timeout(config.client_disconnect.into());
+        config.set_buffer_params(&state);
 
         // slow-request timer
         if config.client_timeout.non_zero() {
@@ -109,10 +147,16 @@ where
                 flags: Flags::empty(),
                 error: None,
                 payload: None,
+                trailers: None,
+                pipeline: VecDeque::new(),
+                deferred: None,
+                requests: 0,
+                records: VecDeque::new(),
                 codec,
                 state,
                 config,
                 expire,
+                headers_timer: None,
                 _t: marker::PhantomData,
             },
         }
@@ -236,19 +280,117 @@ where
                     }
                 }
                 State::ReadRequest => {
-                    log::trace!("trying to read http message");
+                    // send responses of completed pipelined requests
+                    if let Some(st) = this.inner.poll_pipeline(cx) {
+                        *this.st = st;
+                        continue;
+                    }
 
-                    let io = this.inner.io();
+                    let result = if let Some((result, st)) = this.inner.take_deferred() {
+                        this.inner.codec.set_request_state(st);
+                        result
+                    } else if this.inner.deferred.is_some()
+                        || this.inner.pipeline.len() >= this.inner.config.pipeline
+                    {
+                        return Poll::Pending;
+                    } else if this.inner.flags.contains(Flags::LAST_REQUEST) {
+                        // last request is received, wait for in-flight requests
+                        if this.inner.pipeline.is_empty() {
+                            *this.st = State::Stop;
+                            continue;
+                        }
+                        return Poll::Pending;
+                    } else {
+                        log::trace!("trying to read http message");
+
+                        // decode incoming bytes stream
+                        match this.inner.io().poll_recv(&this.inner.codec, cx) {
+                            Poll::Ready(result) => result,
+                            Poll::Pending => {
+                                ready!(this.inner.poll_headers_timeout(cx));
+
+                                // request headers are not received within timeout
+                                log::trace!("request headers timeout");
+                                let timeouts = &this.inner.config.headers_timeouts;
+                                timeouts.set(timeouts.get() + 1);
+                                let err = HttpDispatchError::HeadersTimeout;
+                                let (res, body) =
+                                    this.inner.config.error_response(&err).into_parts();
+                                let _ = this.inner.send_response(res, body.into_body());
+                                this.inner.error = Some(err.into());
+                                *this.st = State::Stop;
+                                continue;
+                            }
+                        }
+                    };
 
-                    // decode incoming bytes stream
-                    match ready!(io.poll_recv(&this.inner.codec, cx)) {
+                    // in-flight pipelined requests must complete first
+                    if !this.inner.pipeline.is_empty() {
+                        match result {
+                            Ok((ref req, ref pl)) if this.inner.can_pipeline(req, pl) => (),
+                            Err(RecvError::KeepAlive) => return Poll::Pending,
+                            Err(RecvError::WriteBackpressure)
+                            | Err(RecvError::Stop)
+                            | Err(RecvError::PeerGone(_)) => (),
+                            _ => {
+                                let st = this.inner.codec.request_state();
+                                this.inner.deferred = Some((result, st));
+                                continue;
+                            }
+                        }
+                    }
+
+                    match result {
                         Ok((mut req, pl)) => {
                             log::trace!(
                                 "http message is received: {:?} and payload {:?}",
                                 req,
                                 pl
                             );
-                            req.head_mut().io = Some(io.get_ref());
+                            req.head_mut().io = Some(this.inner.state.clone());
+                            this.inner.headers_timer = None;
+                            if this.inner.config.access_log.is_some() {
+                                let rec = RequestLogRecord::new(&req, &this.inner.state);
+                                this.inner.records.push_back(rec);
+                            }
+
+                            // check max number of requests per connection
+                            if this.inner.config.max_requests != 0 {
+                                this.inner.requests += 1;
+                                if this.inner.requests >= this.inner.config.max_requests {
+                                    this.inner.flags.insert(Flags::LAST_REQUEST);
+                                }
+                            }
+
+                            // unregister slow-request timer
+                            if !this.inner.flags.contains(Flags::STARTED) {
+                                this.inner.flags.insert(Flags::STARTED);
+                                this.inner
+                                    .config
+                                    .timer_h1
+                                    .unregister(this.inner.expire, &this.inner.state);
+                            }
+
+                            if this.inner.can_pipeline(&req, &pl) {
+                                this.inner.call_pipelined(req);
+                                continue;
+                            }
+
+                            // Handle CONNECT request
+                            if req.head().method == http::Method::CONNECT
+                                && this.inner.config.connect.is_some()
+                            {
+                                *this.st = State::Connect(Some(req));
+                                continue;
+                            }
+
+                            // Handle protocol upgrade request
+                            if let PayloadType::Stream(_) = pl {
+                                if let Some(idx) = this.inner.config.upgrade_handler(&req) {
+                                    *this.st = State::OnUpgrade(Some(req), idx);
+                                    continue;
+                                }
+                            }
 
                             // configure request payload
                             let upgrade = match pl {
@@ -272,15 +414,6 @@ where
                                 }
                             };
 
-                            // unregister slow-request timer
-                            if !this.inner.flags.contains(Flags::STARTED) {
-                                this.inner.flags.insert(Flags::STARTED);
-                                this.inner
-                                    .config
-                                    .timer_h1
-                                    .unregister(this.inner.expire, &this.inner.state);
-                            }
-
                             if upgrade {
                                 // Handle UPGRADE request
                                 log::trace!("prep io for upgrade handler");
@@ -319,8 +452,10 @@ where
                         Err(RecvError::Decoder(err)) => {
                             // Malformed requests, respond with 400
                             log::trace!("malformed request: {:?}", err);
-                            let (res, body) = Response::BadRequest().finish().into_parts();
-                            this.inner.error = Some(DispatchError::Parse(err));
+                            let err = HttpDispatchError::Parse(err);
+                            let (res, body) =
+                                this.inner.config.error_response(&err).into_parts();
+                            this.inner.error = Some(err.into());
                             *this.st = this.inner.send_response(res, body.into_body());
                         }
                         Err(RecvError::PeerGone(err)) => {
@@ -336,10 +471,11 @@ where
                             // keep-alive timeout
                             if !this.inner.flags.contains(Flags::STARTED) {
                                 log::trace!("slow request timeout");
-                                let (req, body) =
-                                    Response::RequestTimeout().finish().into_parts();
-                                let _ = this.inner.send_response(req, body.into_body());
-                                this.inner.error = Some(DispatchError::SlowRequestTimeout);
+                                let err = HttpDispatchError::SlowRequest;
+                                let (res, body) =
+                                    this.inner.config.error_response(&err).into_parts();
+                                let _ = this.inner.send_response(res, body.into_body());
+                                this.inner.error = Some(err.into());
                             } else {
                                 log::trace!("keep-alive timeout, close connection");
                             }
@@ -366,8 +502,17 @@ where
                             this.inner.flags.insert(Flags::SENDPAYLOAD_AND_STOP);
                         }
                         loop {
-                            let _ = ready!(this.inner.io().poll_flush(cx, false));
-                            let item = ready!(body.poll_next_chunk(cx));
+                            // poll body only if write buffer is under watermark,
+                            // or is fully flushed if body requested flush
+                            let full = this.inner.flags.contains(Flags::FLUSH_PAYLOAD);
+                            let _ = ready!(this.inner.io().poll_flush(cx, full));
+                            this.inner.flags.remove(Flags::FLUSH_PAYLOAD);
+
+                            let item = body.poll_next_chunk(cx);
+                            if body.take_flush() {
+                                this.inner.flags.insert(Flags::FLUSH_PAYLOAD);
+                            }
+                            let item = ready!(item);
                             if let Some(st) = this.inner.send_payload(item) {
                                 *this.st = st;
                                 break;
@@ -379,6 +524,7 @@ where
                 State::Upgrade(ref mut req) => {
                     log::trace!("switching to upgrade service");
 
+                    this.inner.flush_log();
                     let io = this.inner.io.take().unwrap();
                     let req = req.take().unwrap();
 
@@ -390,9 +536,45 @@ where
                     )));
                     return Poll::Ready(Ok(()));
                 }
+                // stop io tasks and call connect handler
+                State::Connect(ref mut req) => {
+                    log::trace!("switching to connect handler");
+                    this.inner.unregister_keepalive();
+                    this.inner.flush_log();
+
+                    let io = IoBoxed::from(this.inner.io.take().unwrap());
+                    let req = req.take().unwrap();
+                    let tunnel = Tunnel::new(io, this.inner.codec.clone());
+
+                    crate::rt::spawn(
+                        this.inner
+                            .config
+                            .connect
+                            .as_ref()
+                            .unwrap()
+                            .call((req, tunnel)),
+                    );
+                    return Poll::Ready(Ok(()));
+                }
+                // stop io tasks and call protocol upgrade handler
+                State::OnUpgrade(ref mut req, ref idx) => {
+                    log::trace!("switching to protocol upgrade handler");
+                    this.inner.unregister_keepalive();
+                    this.inner.flush_log();
+
+                    let io = IoBoxed::from(this.inner.io.take().unwrap());
+                    let req = req.take().unwrap();
+                    let upgraded = Upgraded::new(io, this.inner.codec.clone());
+
+                    crate::rt::spawn(
+                        this.inner.config.upgrades[*idx].1.call((req, upgraded)),
+                    );
+                    return Poll::Ready(Ok(()));
+                }
                 // prepare to shutdown
                 State::Stop => {
                     this.inner.unregister_keepalive();
+                    this.inner.flush_log();
 
                     if this
                         .inner
@@ -434,6 +616,90 @@ where
         self.io.as_ref().unwrap()
     }
 
+    fn take_deferred(&mut self) -> Option<Deferred> {
+        if self.pipeline.is_empty() {
+            self.deferred.take()
+        } else {
+            None
+        }
+    }
+
+    /// Check if request could be processed concurrently with other requests
+    fn can_pipeline(&self, req: &Request, pl: &PayloadType) -> bool {
+        self.config.pipeline > 1
+            && matches!(pl, PayloadType::None)
+            && !req.head().expect()
+            && self.config.on_request.is_none()
+    }
+
+    fn call_pipelined(&mut self, req: Request) {
+        let st = self.codec.request_state();
+        let fut = Box::pin(self.config.service.call(req));
+        self.pipeline.push_back(Pipelined::Call(fut, st));
+    }
+
+    /// Poll in-flight requests, send response of first request if it is ready
+    fn poll_pipeline(&mut self, cx: &mut Context<'_>) -> Option<State<B>> {
+        for item in self.pipeline.iter_mut() {
+            if let Pipelined::Call(ref mut fut, st) = item {
+                if let Poll::Ready(result) = fut.as_mut().poll(cx) {
+                    *item = Pipelined::Done(result, *st);
+                }
+            }
+        }
+
+        if let Some(Pipelined::Done(..)) = self.pipeline.front() {
+            if let Some(Pipelined::Done(result, st)) = self.pipeline.pop_front() {
+                self.codec.set_request_state(st);
+                return Some(match result {
+                    Ok(res) => {
+                        let (res, body) = res.into().into_parts();
+                        self.send_response(res, body)
+                    }
+                    Err(e) => self.handle_error(e, false),
+                });
+            }
+        }
+        None
+    }
+
+    /// Check request headers timeout
+    ///
+    /// Timer starts when first bytes of the request are received,
+    /// timeout response could be sent only after in-flight requests
+    fn poll_headers_timeout(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if !self.pipeline.is_empty() {
+            return Poll::Pending;
+        }
+        if self.headers_timer.is_none() {
+            if !self.config.headers_timeout.non_zero()
+                || self.state.with_read_buf(|buf| buf.is_empty())
+            {
+                return Poll::Pending;
+            }
+            self.headers_timer = Some(sleep(self.config.headers_timeout));
+        }
+        self.headers_timer.as_ref().unwrap().poll_elapsed(cx)
+    }
+
+    /// Pass record of the completed exchange to access log handler
+    fn log_finished(&mut self, completed: bool) {
+        if let Some(ref f) = self.config.access_log {
+            if self.records.front().map(|rec| rec.status.is_some()) == Some(true) {
+                (*f)(self.records.pop_front().unwrap().finish(completed));
+            }
+        }
+    }
+
+    /// Pass records of unfinished exchanges to access log handler
+    fn flush_log(&mut self) {
+        if let Some(ref f) = self.config.access_log {
+            for rec in self.records.drain(..) {
+                (*f)(rec.finish(false));
+            }
+        }
+    }
+
     fn switch_to_read_request(&mut self) -> State<B> {
         // connection is not keep-alive, disconnect
         if !self.flags.contains(Flags::KEEPALIVE) || !self.codec.keepalive_enabled() {
@@ -489,29 +755,54 @@ where
         }
     }
 
-    fn send_response(&mut self, msg: Response<()>, body: ResponseBody<B>) -> State<B> {
+    fn send_response(&mut self, mut msg: Response<()>, body: ResponseBody<B>) -> State<B> {
         trace!("sending response: {:?} body: {:?}", msg, body.size());
+        self.trailers = msg.take_trailers();
+        if let Some(rec) = self.records.iter_mut().find(|rec| rec.status.is_none()) {
+            rec.response(msg.status());
+        }
         // we dont need to process responses if socket is disconnected
         // but we still want to handle requests with app service
         // so we skip response processing for droppped connection
         if self.state.is_io_open() {
-            let result = self
-                .io()
-                .encode(Message::Item((msg, body.size())), &self.codec)
-                .map_err(|err| {
-                    if let Some(mut payload) = self.payload.take() {
-                        payload.1.set_error(PayloadError::Incomplete(None));
-                    }
-                    err
-                });
+            // response to the last request closes connection
+            if self.flags.contains(Flags::LAST_REQUEST) && self.pipeline.is_empty() {
+                self.codec.set_ctype(ConnectionType::Close);
+            }
+
+            // pre-encoded response is written directly to the write buffer
+            let mut prepared = false;
+            let result = if let Some(ref res) = msg.head().prepared {
+                self.io()
+                    .with_write_buf(|buf| prepared = self.codec.encode_prepared(res, buf))
+            } else {
+                Ok(())
+            };
+            let result = if prepared || result.is_err() {
+                result
+            } else {
+                self.io()
+                    .encode(Message::Item((msg, body.size())), &self.codec)
+            }
+            .inspect_err(|_| {
+                if let Some(mut payload) = self.payload.take() {
+                    payload.1.set_error(PayloadError::Incomplete(None));
+                }
+            });
 
             if result.is_err() {
                 State::Stop
             } else {
                 self.flags.set(Flags::KEEPALIVE, self.codec.keepalive());
 
-                match body.size() {
+                let size = if prepared {
+                    BodySize::None
+                } else {
+                    body.size()
+                };
+                match size {
                     BodySize::None | BodySize::Empty => {
+                        self.log_finished(true);
                         if self.error.is_some() {
                             State::Stop
                         } else if self.payload.is_some() {
@@ -535,6 +826,9 @@ where
         match item {
             Some(Ok(item)) => {
                 trace!("got response chunk: {:?}", item.len());
+                if let Some(rec) = self.records.front_mut() {
+                    rec.bytes_sent += item.len() as u64;
+                }
                 match self.io().encode(Message::Chunk(Some(item)), &self.codec) {
                     Ok(_) => None,
                     Err(err) => {
@@ -545,10 +839,21 @@ where
             }
             None => {
                 trace!("response payload eof");
-                if let Err(err) = self.io().encode(Message::Chunk(None), &self.codec) {
+                let result = if let Some(trailers) = self.trailers.take() {
+                    let trailers = trailers.call();
+                    self.io()
+                        .with_write_buf(|buf| self.codec.encode_trailers(&trailers, buf))
+                        .and_then(|res| res)
+                } else {
+                    self.io().encode(Message::Chunk(None), &self.codec)
+                };
+                if let Err(err) = result {
                     self.error = Some(DispatchError::Encode(err));
-                    Some(State::Stop)
-                } else if self.flags.contains(Flags::SENDPAYLOAD_AND_STOP) {
+                    return Some(State::Stop);
+                }
+
+                self.log_finished(true);
+                if self.flags.contains(Flags::SENDPAYLOAD_AND_STOP) {
                     Some(State::Stop)
                 } else if self.payload.is_some() {
                     Some(State::ReadPayload)
@@ -583,8 +888,14 @@ where
                         match res {
                             Poll::Ready(Ok(PayloadItem::Chunk(chunk))) => {
                                 updated = true;
+                                if let Some(rec) = self.records.back_mut() {
+                                    rec.bytes_received += chunk.len() as u64;
+                                }
                                 payload.1.feed_data(chunk);
                             }
+                            Poll::Ready(Ok(PayloadItem::Trailers(trailers))) => {
+                                payload.1.feed_trailers(trailers);
+                            }
                             Poll::Ready(Ok(PayloadItem::Eof)) => {
                                 updated = true;
                                 payload.1.feed_eof();
@@ -674,7 +985,7 @@ mod tests {
     use super::*;
     use crate::http::config::{DispatcherConfig, ServiceConfig};
     use crate::http::h1::{ClientCodec, ExpectHandler, UpgradeHandler};
-    use crate::http::{body, Request, ResponseHead, StatusCode};
+    use crate::http::{body, header, Request, ResponseHead, StatusCode};
     use crate::io::{self as nio, Base};
     use crate::service::{boxed, fn_service, IntoService};
     use crate::util::{lazy, next, Bytes, BytesMut};
@@ -781,6 +1092,44 @@ mod tests {
         assert!(data.get());
     }
 
+    #[crate::rt_test]
+    async fn test_headers_timeout() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        client.write("GET /test HTTP/1.1\r\n");
+
+        let config = ServiceConfig::new(
+            Seconds(5).into(),
+            Millis(1_000),
+            Seconds::ZERO,
+            Millis(5_000),
+        )
+        .headers_timeout(Millis(50));
+        let mut h1 = Dispatcher::<_, _, _, _, UpgradeHandler<Base>>::new(
+            nio::Io::new(server),
+            Rc::new(DispatcherConfig::new(
+                config.clone(),
+                fn_service(|_| {
+                    Box::pin(async { Ok::<_, io::Error>(Response::Ok().finish()) })
+                }),
+                ExpectHandler,
+                None,
+                None,
+            )),
+        );
+        sleep(Millis(50)).await;
+        let _ = lazy(|cx| Pin::new(&mut h1).poll(cx)).await;
+        assert_eq!(config.headers_timeouts(), 0);
+
+        sleep(Millis(150)).await;
+        let _ = lazy(|cx| Pin::new(&mut h1).poll(cx)).await;
+        sleep(Millis(50)).await;
+
+        client.local_buffer(|buf| assert_eq!(&buf[..28], b"HTTP/1.1 408 Request Timeout"));
+        assert_eq!(config.headers_timeouts(), 1);
+        client.close().await;
+    }
+
     #[crate::rt_test]
     async fn test_req_parse_err() {
         let (client, server) = Io::create();
@@ -834,6 +1183,59 @@ mod tests {
         assert!(client.is_server_dropped());
     }
 
+    #[crate::rt_test]
+    async fn test_prepared_response() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(4096);
+        let mut decoder = ClientCodec::default();
+        let prepared = crate::http::h1::PreparedResponse::new(
+            Response::NotFound()
+                .header("x-test", "111")
+                .body("not found"),
+        );
+        spawn_h1(server, move |req: Request| {
+            let mut res = prepared.response();
+            if req.path() == "/modified" {
+                res.headers_mut().insert(
+                    crate::http::header::HeaderName::from_static("x-test"),
+                    crate::http::header::HeaderValue::from_static("222"),
+                );
+            }
+            async move { Ok::<_, io::Error>(res) }
+        });
+
+        client.write("GET /test1 HTTP/1.1\r\n\r\n");
+        let buf = client.read().await.unwrap();
+        assert!(buf.starts_with(
+            b"HTTP/1.1 404 Not Found\r\ncontent-length: 9\r\nx-test: 111\r\ndate: "
+        ));
+        assert!(buf.ends_with(b"\r\n\r\nnot found"));
+
+        // head request
+        client.write("HEAD /test1 HTTP/1.1\r\n\r\n");
+        let mut buf = client.read().await.unwrap();
+        assert!(buf.ends_with(b"\r\n\r\n"));
+        let head = load(&mut decoder, &mut buf);
+        assert_eq!(head.status, StatusCode::NOT_FOUND);
+        assert!(buf.is_empty());
+
+        // modified response uses regular encoder
+        let mut decoder = ClientCodec::default();
+        client.write("GET /modified HTTP/1.1\r\n\r\n");
+        let mut buf = client.read().await.unwrap();
+        let head = load(&mut decoder, &mut buf);
+        assert_eq!(head.headers.get("x-test").unwrap(), "222");
+
+        // http/1.0 uses regular encoder
+        client.write("GET /test2 HTTP/1.0\r\nconnection: keep-alive\r\n\r\n");
+        let buf = client.read().await.unwrap();
+        assert!(buf.starts_with(b"HTTP/1.0 404 Not Found\r\n"));
+        assert!(buf.ends_with(b"not found"));
+
+        client.close().await;
+        assert!(client.is_server_dropped());
+    }
+
     #[crate::rt_test]
     async fn test_pipeline_with_payload() {
         let (client, server) = Io::create();
@@ -865,6 +1267,149 @@ mod tests {
         assert!(client.is_server_dropped());
     }
 
+    #[crate::rt_test]
+    async fn test_pipeline_concurrency() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(4096);
+        let decoder = ClientCodec::default();
+
+        let inflight = Rc::new(Cell::new(0));
+        let max_inflight = Rc::new(Cell::new(0));
+        let inflight2 = inflight.clone();
+        let max_inflight2 = max_inflight.clone();
+
+        let config = ServiceConfig::new(
+            Seconds(5).into(),
+            Millis(1_000),
+            Seconds::ZERO,
+            Millis(5_000),
+        )
+        .pipeline(2);
+        crate::rt::spawn(
+            Dispatcher::<Base, _, body::Body, _, UpgradeHandler<Base>>::new(
+                nio::Io::new(server),
+                Rc::new(DispatcherConfig::new(
+                    config,
+                    (move |mut req: Request| {
+                        let inflight = inflight2.clone();
+                        let max_inflight = max_inflight2.clone();
+                        async move {
+                            inflight.set(inflight.get() + 1);
+                            max_inflight
+                                .set(std::cmp::max(inflight.get(), max_inflight.get()));
+
+                            let path = req.path().to_string();
+                            let mut pl = req.take_payload();
+                            while let Some(_) = next(&mut pl).await {}
+                            if path == "/slow" {
+                                sleep(Millis(100)).await;
+                            }
+                            inflight.set(inflight.get() - 1);
+                            Ok::<_, io::Error>(
+                                Response::Ok().header("x-path", path).finish(),
+                            )
+                        }
+                    })
+                    .into_service(),
+                    ExpectHandler,
+                    None,
+                    None,
+                )),
+            ),
+        );
+
+        client.write("GET /slow HTTP/1.1\r\n\r\n");
+        client.write("GET /fast HTTP/1.1\r\n\r\n");
+        client.write("GET /fast2 HTTP/1.1\r\n\r\n");
+        client.write("POST /payload HTTP/1.1\r\ncontent-length: 2\r\n\r\nxx");
+        client.write("GET /slow HTTP/1.1\r\n\r\n");
+
+        let mut buf = BytesMut::new();
+        let mut paths = Vec::new();
+        while paths.len() < 5 {
+            buf.extend_from_slice(&client.read().await.unwrap());
+            while let Some(head) = decoder.decode(&mut buf).unwrap() {
+                assert!(head.status.is_success());
+                paths.push(
+                    head.headers
+                        .get("x-path")
+                        .unwrap()
+                        .to_str()
+                        .unwrap()
+                        .to_string(),
+                );
+            }
+        }
+        assert_eq!(paths, vec!["/slow", "/fast", "/fast2", "/payload", "/slow"]);
+        assert_eq!(max_inflight.get(), 2);
+        assert!(!client.is_server_dropped());
+
+        client.close().await;
+        assert!(client.is_server_dropped());
+    }
+
+    #[crate::rt_test]
+    async fn test_max_requests() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(4096);
+        let mut decoder = ClientCodec::default();
+
+        let config = ServiceConfig::new(
+            Seconds(5).into(),
+            Millis(1_000),
+            Seconds::ZERO,
+            Millis(5_000),
+        )
+        .pipeline(2)
+        .max_requests(3);
+        crate::rt::spawn(
+            Dispatcher::<Base, _, body::Body, _, UpgradeHandler<Base>>::new(
+                nio::Io::new(server),
+                Rc::new(DispatcherConfig::new(
+                    config,
+                    (|req: Request| async move {
+                        Ok::<_, io::Error>(
+                            Response::Ok().header("x-path", req.path()).finish(),
+                        )
+                    })
+                    .into_service(),
+                    ExpectHandler,
+                    None,
+                    None,
+                )),
+            ),
+        );
+
+        client.write("GET /1 HTTP/1.1\r\n\r\n");
+        let mut buf = BytesMut::from(&client.read().await.unwrap()[..]);
+        let head = load(&mut decoder, &mut buf);
+        assert!(head.headers.get(header::CONNECTION).is_none());
+
+        // last request payload is drained before close
+        client.write("GET /2 HTTP/1.1\r\n\r\n");
+        client.write("POST /3 HTTP/1.1\r\ncontent-length: 4\r\n\r\ntest");
+        client.write("GET /4 HTTP/1.1\r\n\r\n");
+
+        let mut paths = Vec::new();
+        while paths.len() < 2 {
+            buf.extend_from_slice(&client.read().await.unwrap());
+            while let Some(head) = decoder.decode(&mut buf).unwrap() {
+                let path = head.headers.get("x-path").unwrap().to_str().unwrap();
+                if path == "/3" {
+                    assert_eq!(head.headers.get(header::CONNECTION).unwrap(), "close");
+                } else {
+                    assert!(head.headers.get(header::CONNECTION).is_none());
+                }
+                paths.push(path.to_string());
+            }
+        }
+        assert_eq!(paths, vec!["/2", "/3"]);
+
+        sleep(Millis(50)).await;
+        assert!(client.is_server_dropped());
+        assert!(buf.is_empty());
+    }
+
     #[crate::rt_test]
     async fn test_pipeline_with_delay() {
         let (client, server) = Io::create();
@@ -1061,6 +1606,56 @@ mod tests {
         assert_eq!(num.load(Ordering::Relaxed), 65_536 * 2);
     }
 
+    #[crate::rt_test]
+    async fn test_write_flush() {
+        let num = Arc::new(AtomicUsize::new(0));
+        let num2 = num.clone();
+
+        struct Stream(Arc<AtomicUsize>, bool);
+
+        impl body::MessageBody for Stream {
+            fn size(&self) -> body::BodySize {
+                body::BodySize::Stream
+            }
+            fn poll_next_chunk(
+                &mut self,
+                _: &mut Context<'_>,
+            ) -> Poll<Option<Result<Bytes, Box<dyn std::error::Error>>>> {
+                // request flush after each chunk
+                self.0.fetch_add(1, Ordering::Relaxed);
+                self.1 = true;
+                Poll::Ready(Some(Ok(Bytes::from_static(b"chunk"))))
+            }
+            fn take_flush(&mut self) -> bool {
+                std::mem::replace(&mut self.1, false)
+            }
+        }
+
+        let (client, server) = Io::create();
+        let mut h1 = h1(server, move |_| {
+            let n = num2.clone();
+            Box::pin(async move {
+                Ok::<_, io::Error>(Response::Ok().message_body(Stream(n.clone(), false)))
+            })
+        });
+
+        // do not allow to write to socket
+        client.remote_buffer_cap(0);
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        sleep(Millis(50)).await;
+        assert!(lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_pending());
+
+        // body is not polled until written data is flushed
+        assert_eq!(num.load(Ordering::Relaxed), 1);
+        assert!(lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_pending());
+        assert_eq!(num.load(Ordering::Relaxed), 1);
+
+        client.remote_buffer_cap(1024);
+        sleep(Millis(50)).await;
+        assert!(lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_pending());
+        assert!(num.load(Ordering::Relaxed) > 1);
+    }
+
     #[crate::rt_test]
     async fn test_disconnect_during_response_body_pending() {
         struct Stream(bool);
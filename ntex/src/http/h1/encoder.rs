@@ -8,7 +8,7 @@ use crate::http::helpers;
 use crate::http::message::{ConnectionType, RequestHeadType};
 use crate::http::response::Response;
 use crate::http::{HeaderMap, StatusCode, Version};
-use crate::util::{BufMut, BytesMut};
+use crate::util::{BufMut, Bytes, BytesChain, BytesMut};
 
 const AVERAGE_HEADER_SIZE: usize = 30;
 
@@ -275,6 +275,18 @@ impl<T: MessageType> MessageEncoder<T> {
         result
     }
 
+    /// Encode message chunk without copying chunk data
+    pub(super) fn encode_chunk_chain(
+        &self,
+        msg: Bytes,
+        dst: &mut BytesChain,
+    ) -> io::Result<bool> {
+        let mut te = self.te.get();
+        let result = te.encode_chain(msg, dst);
+        self.te.set(te);
+        result
+    }
+
     /// Encode eof
     pub(super) fn encode_eof(&self, buf: &mut BytesMut) -> io::Result<()> {
         let mut te = self.te.get();
@@ -283,6 +295,18 @@ impl<T: MessageType> MessageEncoder<T> {
         result
     }
 
+    /// Encode eof with trailers
+    pub(super) fn encode_trailers(
+        &self,
+        trailers: &HeaderMap,
+        buf: &mut BytesMut,
+    ) -> io::Result<()> {
+        let mut te = self.te.get();
+        let result = te.encode_trailers(trailers, buf);
+        self.te.set(te);
+        result
+    }
+
     pub(super) fn encode(
         &self,
         dst: &mut BytesMut,
@@ -386,7 +410,7 @@ impl TransferEncoding {
                     true
                 } else {
                     writeln!(helpers::Writer(buf), "{:X}\r", msg.len())
-                        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                        .map_err(io::Error::other)?;
 
                     buf.reserve(msg.len() + 2);
                     buf.extend_from_slice(msg);
@@ -414,6 +438,57 @@ impl TransferEncoding {
         }
     }
 
+    /// Encode message into chain, chunk data is not copied. Return `EOF` state of encoder
+    pub(super) fn encode_chain(
+        &mut self,
+        msg: Bytes,
+        dst: &mut BytesChain,
+    ) -> io::Result<bool> {
+        match self.kind {
+            TransferEncodingKind::Eof => {
+                let eof = msg.is_empty();
+                dst.push(msg);
+                Ok(eof)
+            }
+            TransferEncodingKind::Chunked(eof) => {
+                if eof {
+                    return Ok(true);
+                }
+
+                if msg.is_empty() {
+                    dst.push(Bytes::from_static(b"0\r\n\r\n"));
+                    self.kind = TransferEncodingKind::Chunked(true);
+                    Ok(true)
+                } else {
+                    let mut buf = BytesMut::with_capacity(16);
+                    writeln!(helpers::Writer(&mut buf), "{:X}\r", msg.len())
+                        .map_err(io::Error::other)?;
+
+                    dst.push(buf);
+                    dst.push(msg);
+                    dst.push(Bytes::from_static(b"\r\n"));
+                    Ok(false)
+                }
+            }
+            TransferEncodingKind::Length(mut remaining) => {
+                if remaining > 0 {
+                    if msg.is_empty() {
+                        return Ok(remaining == 0);
+                    }
+                    let len = cmp::min(remaining, msg.len() as u64);
+
+                    dst.push(msg.slice(..len as usize));
+
+                    remaining -= len as u64;
+                    self.kind = TransferEncodingKind::Length(remaining);
+                    Ok(remaining == 0)
+                } else {
+                    Ok(true)
+                }
+            }
+        }
+    }
+
     /// Encode eof. Return `EOF` state of encoder
     #[inline]
     pub(super) fn encode_eof(&mut self, buf: &mut BytesMut) -> io::Result<()> {
@@ -435,6 +510,29 @@ impl TransferEncoding {
             }
         }
     }
+
+    /// Encode eof with trailers, trailers are sent only for chunked encoding
+    pub(super) fn encode_trailers(
+        &mut self,
+        trailers: &HeaderMap,
+        buf: &mut BytesMut,
+    ) -> io::Result<()> {
+        match self.kind {
+            TransferEncodingKind::Chunked(false) => {
+                buf.extend_from_slice(b"0\r\n");
+                for (key, value) in trailers.iter() {
+                    buf.extend_from_slice(key.as_str().as_bytes());
+                    buf.extend_from_slice(b": ");
+                    buf.extend_from_slice(value.as_ref());
+                    buf.extend_from_slice(b"\r\n");
+                }
+                buf.extend_from_slice(b"\r\n");
+                self.kind = TransferEncodingKind::Chunked(true);
+                Ok(())
+            }
+            _ => self.encode_eof(buf),
+        }
+    }
 }
 
 const DEC_DIGITS_LUT: &[u8] = b"0001020304050607080910111213141516171819\
@@ -445,7 +543,7 @@ const DEC_DIGITS_LUT: &[u8] = b"0001020304050607080910111213141516171819\
 
 const STATUS_LINE_BUF_SIZE: usize = 13;
 
-fn write_status_line(version: Version, mut n: u16, bytes: &mut BytesMut) {
+pub(super) fn write_status_line(version: Version, mut n: u16, bytes: &mut BytesMut) {
     let mut buf: [u8; STATUS_LINE_BUF_SIZE] = match version {
         Version::HTTP_2 => *b"HTTP/2       ",
         Version::HTTP_10 => *b"HTTP/1.0     ",
@@ -484,7 +582,7 @@ fn write_status_line(version: Version, mut n: u16, bytes: &mut BytesMut) {
 }
 
 /// NOTE: bytes object has to contain enough space
-fn write_content_length(mut n: u64, bytes: &mut BytesMut) {
+pub(super) fn write_content_length(mut n: u64, bytes: &mut BytesMut) {
     if n < 10 {
         let mut buf: [u8; 21] = [
             b'\r', b'\n', b'c', b'o', b'n', b't', b'e', b'n', b't', b'-', b'l', b'e', b'n',
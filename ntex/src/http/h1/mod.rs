@@ -3,12 +3,15 @@ use crate::util::{Bytes, BytesMut};
 
 mod client;
 mod codec;
+pub mod conformance;
 mod decoder;
 mod dispatcher;
 mod encoder;
 mod expect;
 mod payload;
+mod prepared;
 mod service;
+mod tunnel;
 mod upgrade;
 
 pub use self::client::{ClientCodec, ClientPayloadCodec};
@@ -16,8 +19,11 @@ pub use self::codec::Codec;
 pub use self::decoder::{PayloadDecoder, PayloadItem, PayloadType};
 pub use self::expect::ExpectHandler;
 pub use self::payload::Payload;
+pub(crate) use self::payload::{PayloadSender, PayloadStatus};
+pub use self::prepared::PreparedResponse;
 pub use self::service::{H1Service, H1ServiceHandler};
-pub use self::upgrade::UpgradeHandler;
+pub use self::tunnel::Tunnel;
+pub use self::upgrade::{UpgradeHandler, Upgraded};
 
 pub(super) use self::dispatcher::Dispatcher;
 
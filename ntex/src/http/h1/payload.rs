@@ -3,14 +3,14 @@ use std::rc::{Rc, Weak};
 use std::task::{Context, Poll};
 use std::{cell::RefCell, collections::VecDeque, pin::Pin};
 
-use crate::http::error::PayloadError;
+use crate::http::{error::PayloadError, header::HeaderMap};
 use crate::{task::LocalWaker, util::Bytes, Stream};
 
 /// max buffer size 32k
 const MAX_BUFFER_SIZE: usize = 32_768;
 
 #[derive(Debug, PartialEq)]
-pub(super) enum PayloadStatus {
+pub(crate) enum PayloadStatus {
     Read,
     Pause,
     Dropped,
@@ -62,6 +62,14 @@ impl Payload {
         self.inner.borrow_mut().unread_data(data);
     }
 
+    /// Take payload trailers
+    ///
+    /// Trailers are available after payload is fully read.
+    #[inline]
+    pub fn take_trailers(&mut self) -> Option<HeaderMap> {
+        self.inner.borrow_mut().trailers.take()
+    }
+
     #[inline]
     pub fn readany(
         &mut self,
@@ -71,6 +79,13 @@ impl Payload {
     }
 }
 
+impl Drop for Payload {
+    fn drop(&mut self) {
+        // notify sender side, payload is not consumed anymore
+        self.inner.borrow().io_task.wake();
+    }
+}
+
 impl Stream for Payload {
     type Item = Result<Bytes, PayloadError>;
 
@@ -114,7 +129,13 @@ impl PayloadSender {
         }
     }
 
-    pub(super) fn poll_data_required(&self, cx: &mut Context<'_>) -> PayloadStatus {
+    pub fn feed_trailers(&mut self, trailers: HeaderMap) {
+        if let Some(shared) = self.inner.upgrade() {
+            shared.borrow_mut().trailers = Some(trailers);
+        }
+    }
+
+    pub(crate) fn poll_data_required(&self, cx: &mut Context<'_>) -> PayloadStatus {
         // we check only if Payload (other side) is alive,
         // otherwise always return true (consume payload)
         if let Some(shared) = self.inner.upgrade() {
@@ -135,6 +156,7 @@ struct Inner {
     len: usize,
     eof: bool,
     err: Option<PayloadError>,
+    trailers: Option<HeaderMap>,
     need_read: bool,
     items: VecDeque<Bytes>,
     task: LocalWaker,
@@ -147,6 +169,7 @@ impl Inner {
             eof,
             len: 0,
             err: None,
+            trailers: None,
             items: VecDeque::new(),
             need_read: true,
             task: LocalWaker::new(),
@@ -0,0 +1,188 @@
+use std::{fmt, rc::Rc};
+
+use crate::http::body::{Body, ResponseBody};
+use crate::http::config::DateService;
+use crate::http::header::{CONNECTION, CONTENT_LENGTH, DATE, TRANSFER_ENCODING};
+use crate::http::message::ConnectionType;
+use crate::http::{HeaderMap, Response, StatusCode, Version};
+use crate::util::{Bytes, BytesMut};
+
+use super::encoder::{write_content_length, write_status_line};
+
+/// Pre-encoded static response
+///
+/// Response head and body are encoded once, h1 dispatcher writes prepared
+/// buffer directly to the connection without running response encoder.
+/// Only `date` and `connection` headers get added on each write.
+/// Prepared responses are suitable for small fixed responses like
+/// not found pages, health-checks or redirects.
+///
+/// Fast path is used only for HTTP/1.1 connections. Other protocols and
+/// responses that get modified after creation (for example by middlewares)
+/// are encoded by regular encoder.
+///
+/// ```rust
+/// use ntex::http::{h1::PreparedResponse, Response};
+///
+/// let not_found = PreparedResponse::new(Response::NotFound().body("Not found"));
+///
+/// // create response from prepared buffers
+/// let res = not_found.response();
+/// assert_eq!(res.status(), 404);
+/// ```
+#[derive(Clone)]
+pub struct PreparedResponse(Rc<Inner>);
+
+struct Inner {
+    status: StatusCode,
+    reason: Option<&'static str>,
+    headers: HeaderMap,
+    ctype: Option<ConnectionType>,
+    body: Body,
+    has_date: bool,
+    encoded: Bytes,
+}
+
+impl PreparedResponse {
+    /// Create prepared response
+    ///
+    /// # Panics
+    ///
+    /// Panics if response has streaming body.
+    pub fn new(res: Response<Body>) -> Self {
+        let (res, body) = res.into_parts();
+        let body = match body {
+            ResponseBody::Body(body) | ResponseBody::Other(body) => body,
+        };
+        if let Body::Message(_) = body {
+            panic!("Streaming body cannot be prepared");
+        }
+        let head = res.head();
+
+        let mut buf = BytesMut::with_capacity(256);
+        write_status_line(Version::HTTP_11, head.status.as_u16(), &mut buf);
+        buf.extend_from_slice(head.reason().as_bytes());
+
+        // content length
+        let length = match head.status {
+            StatusCode::NO_CONTENT | StatusCode::CONTINUE | StatusCode::PROCESSING => {
+                &Body::None
+            }
+            _ => &body,
+        };
+        match length {
+            Body::None => buf.extend_from_slice(b"\r\n"),
+            Body::Empty => buf.extend_from_slice(b"\r\ncontent-length: 0\r\n"),
+            Body::Bytes(ref b) => write_content_length(b.len() as u64, &mut buf),
+            Body::Message(_) => unreachable!(),
+        }
+
+        // headers, connection header is added on write
+        let mut has_date = false;
+        for (key, value) in head.headers.iter() {
+            match *key {
+                CONNECTION | CONTENT_LENGTH | TRANSFER_ENCODING => continue,
+                DATE => has_date = true,
+                _ => (),
+            }
+            buf.extend_from_slice(key.as_str().as_bytes());
+            buf.extend_from_slice(b": ");
+            buf.extend_from_slice(value.as_ref());
+            buf.extend_from_slice(b"\r\n");
+        }
+
+        PreparedResponse(Rc::new(Inner {
+            body,
+            has_date,
+            status: head.status,
+            reason: head.reason,
+            headers: head.headers.clone(),
+            ctype: head.ctype(),
+            encoded: buf.freeze(),
+        }))
+    }
+
+    #[inline]
+    /// Get the response status code
+    pub fn status(&self) -> StatusCode {
+        self.0.status
+    }
+
+    #[inline]
+    /// Get the response headers
+    pub fn headers(&self) -> &HeaderMap {
+        &self.0.headers
+    }
+
+    /// Create response from prepared buffers
+    ///
+    /// Any modification of response head or body disables fast path
+    /// for created response.
+    pub fn response(&self) -> Response<Body> {
+        let body = match self.0.body {
+            Body::None => Body::None,
+            Body::Empty => Body::Empty,
+            Body::Bytes(ref b) => Body::Bytes(b.clone()),
+            Body::Message(_) => unreachable!(),
+        };
+        let mut res = Response::with_body(self.0.status, body);
+        let head = res.head_mut();
+        head.reason = self.0.reason;
+        head.headers = self.0.headers.clone();
+        if let Some(ctype) = self.0.ctype {
+            head.set_connection_type(ctype);
+        }
+        head.prepared = Some(self.clone());
+        res
+    }
+
+    pub(super) fn ctype(&self) -> Option<ConnectionType> {
+        self.0.ctype
+    }
+
+    /// Write prepared response to the buffer
+    pub(super) fn encode(
+        &self,
+        dst: &mut BytesMut,
+        close: bool,
+        head: bool,
+        timer: &DateService,
+    ) {
+        dst.extend_from_slice(&self.0.encoded);
+        if close {
+            dst.extend_from_slice(b"connection: close\r\n");
+        }
+        if self.0.has_date {
+            dst.extend_from_slice(b"\r\n");
+        } else {
+            // set_date writes \r\n
+            timer.set_date_header(dst);
+        }
+        if !head {
+            if let Body::Bytes(ref b) = self.0.body {
+                dst.extend_from_slice(b);
+            }
+        }
+    }
+}
+
+impl From<Response<Body>> for PreparedResponse {
+    fn from(res: Response<Body>) -> Self {
+        PreparedResponse::new(res)
+    }
+}
+
+impl From<&PreparedResponse> for Response<Body> {
+    fn from(res: &PreparedResponse) -> Self {
+        res.response()
+    }
+}
+
+impl fmt::Debug for PreparedResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PreparedResponse")
+            .field("status", &self.0.status)
+            .field("size", &self.0.encoded.len())
+            .finish()
+    }
+}
@@ -192,6 +192,11 @@ where
         }
     }
 
+    /// Http service configuration
+    pub fn config(&self) -> &ServiceConfig {
+        &self.cfg
+    }
+
     /// Set req request callback.
     ///
     /// It get called once per request.
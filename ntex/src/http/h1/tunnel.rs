@@ -0,0 +1,80 @@
+use std::{fmt, io};
+
+use crate::http::body::{BodySize, MessageBody};
+use crate::http::Response;
+use crate::io::IoBoxed;
+use crate::util::poll_fn;
+
+use super::{Codec, Message};
+
+/// Connection of the `CONNECT` request
+///
+/// Tunnel is passed to the `CONNECT` requests handler together with
+/// request. Handler decides if tunnel could be established and either
+/// accepts tunnel and gets connection's io for raw data transfer, or
+/// rejects it with final response.
+pub struct Tunnel {
+    io: IoBoxed,
+    codec: Codec,
+}
+
+impl fmt::Debug for Tunnel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tunnel").finish()
+    }
+}
+
+impl Tunnel {
+    pub(super) fn new(io: IoBoxed, codec: Codec) -> Self {
+        Tunnel { io, codec }
+    }
+
+    /// Send successful response and take connection's io.
+    ///
+    /// Response must have `2xx` status, response body is ignored. Returned
+    /// io contains any bytes that client sent after request head.
+    pub async fn accept<B>(self, res: Response<B>) -> io::Result<IoBoxed> {
+        self.io
+            .send(
+                Message::Item((res.drop_body(), BodySize::None)),
+                &self.codec,
+            )
+            .await
+            .map_err(|e| e.into_inner())?;
+        Ok(self.io)
+    }
+
+    /// Reject tunnel with final response and close connection.
+    pub async fn reject<B: MessageBody>(self, res: Response<B>) -> io::Result<()> {
+        let (res, mut body) = res.into_parts();
+        let size = body.size();
+        self.io
+            .send(Message::Item((res, size)), &self.codec)
+            .await
+            .map_err(|e| e.into_inner())?;
+
+        if !size.is_eof() {
+            loop {
+                match poll_fn(|cx| body.poll_next_chunk(cx)).await {
+                    Some(Ok(chunk)) => self
+                        .io
+                        .send(Message::Chunk(Some(chunk)), &self.codec)
+                        .await
+                        .map_err(|e| e.into_inner())?,
+                    Some(Err(e)) => {
+                        log::trace!("Error during response body poll: {:?}", e);
+                        break;
+                    }
+                    None => {
+                        self.io
+                            .send(Message::Chunk(None), &self.codec)
+                            .await
+                            .map_err(|e| e.into_inner())?;
+                        break;
+                    }
+                }
+            }
+        }
+        self.io.shutdown().await
+    }
+}
@@ -1,9 +1,11 @@
-use std::{io, marker::PhantomData, task::Context, task::Poll};
+use std::{fmt, io, marker::PhantomData, task::Context, task::Poll};
 
-use crate::http::h1::Codec;
-use crate::http::request::Request;
-use crate::io::Io;
-use crate::{util::Ready, Service, ServiceFactory};
+use crate::http::body::MessageBody;
+use crate::http::h1::{Codec, Tunnel};
+use crate::http::{request::Request, response::Response};
+use crate::io::{Io, IoBoxed};
+use crate::util::{Bytes, Ready};
+use crate::{Service, ServiceFactory};
 
 pub struct UpgradeHandler<F>(PhantomData<F>);
 
@@ -36,3 +38,40 @@ impl<F> Service<(Request, Io<F>, Codec)> for UpgradeHandler<F> {
         unimplemented!()
     }
 }
+
+/// Connection of the upgrade request
+///
+/// Upgraded connection is passed to the protocol upgrade handler together
+/// with request. Handler either accepts upgrade and gets connection's io
+/// with all io filters (for example tls), or rejects it with final response.
+pub struct Upgraded {
+    io: IoBoxed,
+    codec: Codec,
+}
+
+impl fmt::Debug for Upgraded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Upgraded").finish()
+    }
+}
+
+impl Upgraded {
+    pub(super) fn new(io: IoBoxed, codec: Codec) -> Self {
+        Upgraded { io, codec }
+    }
+
+    /// Send `101 Switching Protocols` response and take connection's io.
+    ///
+    /// Response body is ignored. Returns io and leftover bytes that client
+    /// sent after request head, leftover bytes are removed from io's read buffer.
+    pub async fn accept<B>(self, res: Response<B>) -> io::Result<(IoBoxed, Bytes)> {
+        let io = Tunnel::new(self.io, self.codec).accept(res).await?;
+        let leftover = io.with_read_buf(|buf| buf.split().freeze());
+        Ok((io, leftover))
+    }
+
+    /// Reject upgrade with final response and close connection.
+    pub async fn reject<B: MessageBody>(self, res: Response<B>) -> io::Result<()> {
+        Tunnel::new(self.io, self.codec).reject(res).await
+    }
+}
@@ -1,22 +1,23 @@
 use std::task::{Context, Poll};
-use std::{convert::TryFrom, future::Future, marker::PhantomData, pin::Pin, rc::Rc, time};
+use std::{cell::Cell, convert::TryFrom, future::Future, marker::PhantomData, pin::Pin};
+use std::{rc::Rc, time};
 
 use h2::server::{Connection, SendResponse};
-use h2::SendStream;
+use h2::{Ping, PingPong, SendStream};
 use log::{error, trace};
 
+use crate::http::access_log::RequestLogRecord;
 use crate::http::body::{BodySize, MessageBody, ResponseBody};
-use crate::http::config::{DateService, DispatcherConfig};
+use crate::http::config::{DateService, DispatcherConfig, HpackConfig, OnAccessLog};
 use crate::http::error::{DispatchError, ResponseError};
 use crate::http::header::{
     HeaderValue, CONNECTION, CONTENT_LENGTH, DATE, TRANSFER_ENCODING,
 };
-use crate::http::{
-    message::ResponseHead, payload::Payload, request::Request, response::Response,
-};
+use crate::http::message::{ResponseHead, Trailers};
+use crate::http::{payload::Payload, request::Request, response::Response};
 use crate::io::{Filter, Io, IoRef};
 use crate::service::Service;
-use crate::time::{now, Sleep};
+use crate::time::{now, Millis, Sleep};
 use crate::util::{Bytes, BytesMut};
 
 const CHUNK_SIZE: usize = 16_384;
@@ -29,10 +30,50 @@ pin_project_lite::pin_project! {
         connection: Connection<Io<F>, Bytes>,
         ka_expire: time::Instant,
         ka_timer: Option<Sleep>,
+        ping: Option<KeepAlivePing>,
         _t: PhantomData<B>,
     }
 }
 
+struct KeepAlivePing {
+    pong: PingPong,
+    timer: Sleep,
+    interval: Millis,
+    timeout: Millis,
+    waiting: bool,
+}
+
+impl KeepAlivePing {
+    fn poll(&mut self, cx: &mut Context<'_>) -> Result<(), DispatchError> {
+        loop {
+            if self.waiting {
+                match self.pong.poll_pong(cx) {
+                    Poll::Ready(Ok(_)) => {
+                        trace!("h2 keep-alive pong is received");
+                        self.waiting = false;
+                        self.timer.reset(self.interval);
+                        continue;
+                    }
+                    Poll::Ready(Err(err)) => return Err(err.into()),
+                    Poll::Pending => {
+                        if self.timer.poll_elapsed(cx).is_ready() {
+                            trace!("h2 keep-alive ping timeout");
+                            return Err(DispatchError::PingTimeout);
+                        }
+                    }
+                }
+            } else if self.timer.poll_elapsed(cx).is_ready() {
+                trace!("h2 keep-alive interval elapsed, sending ping");
+                self.pong.send_ping(Ping::opaque())?;
+                self.waiting = true;
+                self.timer.reset(self.timeout);
+                continue;
+            }
+            return Ok(());
+        }
+    }
+}
+
 impl<F, S, B, X, U> Dispatcher<F, S, B, X, U>
 where
     F: Filter,
@@ -44,7 +85,7 @@ where
     pub(in crate::http) fn new(
         io: IoRef,
         config: Rc<DispatcherConfig<S, X, U>>,
-        connection: Connection<Io<F>, Bytes>,
+        mut connection: Connection<Io<F>, Bytes>,
         timeout: Option<Sleep>,
     ) -> Self {
         // keep-alive timer
@@ -58,12 +99,26 @@ where
             (now(), None)
         };
 
+        // keep-alive pings
+        let ping = if config.h2.ping_interval.is_zero() {
+            None
+        } else {
+            connection.ping_pong().map(|pong| KeepAlivePing {
+                pong,
+                timer: Sleep::new(config.h2.ping_interval),
+                interval: config.h2.ping_interval,
+                timeout: config.h2.ping_timeout,
+                waiting: false,
+            })
+        };
+
         Dispatcher {
             io,
             config,
             connection,
             ka_expire,
             ka_timer,
+            ping,
             _t: PhantomData,
         }
     }
@@ -84,6 +139,10 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.get_mut();
 
+        if let Some(ref mut ping) = this.ping {
+            ping.poll(cx)?;
+        }
+
         loop {
             match Pin::new(&mut this.connection).poll_accept(cx) {
                 Poll::Ready(None) => return Poll::Ready(Ok(())),
@@ -99,9 +158,15 @@ where
                     }
 
                     let (parts, body) = req.into_parts();
-                    let mut req = Request::with_payload(Payload::H2(
-                        crate::http::h2::Payload::new(body),
-                    ));
+                    let mut payload = crate::http::h2::Payload::new(body);
+                    let received = if this.config.access_log.is_some() {
+                        let received = Rc::new(Cell::new(0));
+                        payload = payload.counter(received.clone());
+                        Some(received)
+                    } else {
+                        None
+                    };
+                    let mut req = Request::with_payload(Payload::H2(payload));
 
                     let head = &mut req.head_mut();
                     head.uri = parts.uri;
@@ -110,13 +175,28 @@ where
                     head.headers = parts.headers.into();
                     head.io = Some(this.io.clone());
 
+                    let log = if let Some(ref handler) = this.config.access_log {
+                        let mut record = RequestLogRecord::new(&req, &this.io);
+                        record.stream_id = Some(u32::from(res.stream_id()));
+                        Some(AccessLog {
+                            record: Some(record),
+                            handler: handler.clone(),
+                            received: received.unwrap(),
+                            completed: false,
+                        })
+                    } else {
+                        None
+                    };
+
                     crate::rt::spawn(ServiceResponse {
                         state: ServiceResponseState::ServiceCall {
                             call: this.config.service.call(req),
                             send: Some(res),
                         },
                         timer: this.config.timer.clone(),
+                        hpack: this.config.hpack.clone(),
                         buffer: None,
+                        log,
                         _t: PhantomData,
                     });
                 }
@@ -126,12 +206,59 @@ where
     }
 }
 
+/// Generate http/2 trailers
+fn h2_trailers(hpack: &HpackConfig, trailers: Trailers) -> http::HeaderMap {
+    let trailers = trailers.call();
+    let mut map = http::HeaderMap::with_capacity(trailers.len());
+    for (key, value) in trailers.iter() {
+        map.append(key, hpack.value(key, value));
+    }
+    map
+}
+
+/// Access log record of the stream, passed to handler on drop
+struct AccessLog {
+    record: Option<RequestLogRecord>,
+    handler: OnAccessLog,
+    received: Rc<Cell<u64>>,
+    completed: bool,
+}
+
+impl Drop for AccessLog {
+    fn drop(&mut self) {
+        if let Some(record) = self.record.take() {
+            let mut record = record.finish(self.completed);
+            record.bytes_received = self.received.get();
+            (*self.handler)(record);
+        }
+    }
+}
+
+fn log_response(log: &mut Option<AccessLog>, status: http::StatusCode, eof: bool) {
+    if let Some(ref mut log) = log {
+        if let Some(ref mut record) = log.record {
+            record.response(status);
+        }
+        log.completed = eof;
+    }
+}
+
+fn log_chunk(log: &mut Option<AccessLog>, len: usize) {
+    if let Some(ref mut log) = log {
+        if let Some(ref mut record) = log.record {
+            record.bytes_sent += len as u64;
+        }
+    }
+}
+
 pin_project_lite::pin_project! {
     struct ServiceResponse<F, I, E, B> {
         #[pin]
         state: ServiceResponseState<F, B>,
         timer: DateService,
+        hpack: Rc<HpackConfig>,
         buffer: Option<Bytes>,
+        log: Option<AccessLog>,
         _t: PhantomData<(I, E)>,
     }
 }
@@ -140,7 +267,11 @@ pin_project_lite::pin_project! {
     #[project = ServiceResponseStateProject]
     enum ServiceResponseState<F, B> {
         ServiceCall { #[pin] call: F, send: Option<SendResponse<Bytes>> },
-        SendPayload { stream: SendStream<Bytes>, body: ResponseBody<B> },
+        SendPayload {
+            stream: SendStream<Bytes>,
+            body: ResponseBody<B>,
+            trailers: Option<Trailers>,
+        },
     }
 }
 
@@ -193,7 +324,7 @@ where
                 DATE => has_date = true,
                 _ => (),
             }
-            res.headers_mut().append(key, value.clone());
+            res.headers_mut().append(key, self.hpack.value(key, value));
         }
 
         // set date header
@@ -225,26 +356,38 @@ where
             ServiceResponseStateProject::ServiceCall { call, send } => {
                 match call.poll(cx) {
                     Poll::Ready(Ok(res)) => {
-                        let (res, body) = res.into().replace_body(());
+                        let (mut res, body) = res.into().replace_body(());
+                        let trailers = res.take_trailers();
 
                         let mut send = send.take().unwrap();
                         let mut size = body.size();
                         let h2_res = self.as_mut().prepare_response(res.head(), &mut size);
                         this = self.as_mut().project();
 
-                        let stream = match send.send_response(h2_res, size.is_eof()) {
+                        let eof = size.is_eof() && trailers.is_none();
+                        let mut stream = match send.send_response(h2_res, eof) {
                             Err(e) => {
                                 trace!("Error sending h2 response: {:?}", e);
                                 return Poll::Ready(());
                             }
                             Ok(stream) => stream,
                         };
+                        log_response(this.log, res.status(), size.is_eof());
 
                         if size.is_eof() {
+                            if let Some(trailers) = trailers {
+                                let trailers = h2_trailers(this.hpack, trailers);
+                                if let Err(e) = stream.send_trailers(trailers) {
+                                    warn!("{:?}", e);
+                                }
+                            }
                             Poll::Ready(())
                         } else {
-                            this.state
-                                .set(ServiceResponseState::SendPayload { stream, body });
+                            this.state.set(ServiceResponseState::SendPayload {
+                                stream,
+                                body,
+                                trailers,
+                            });
                             self.poll(cx)
                         }
                     }
@@ -265,6 +408,7 @@ where
                             }
                             Ok(stream) => stream,
                         };
+                        log_response(this.log, res.status(), size.is_eof());
 
                         if size.is_eof() {
                             Poll::Ready(())
@@ -272,13 +416,18 @@ where
                             this.state.set(ServiceResponseState::SendPayload {
                                 stream,
                                 body: body.into_body(),
+                                trailers: None,
                             });
                             self.poll(cx)
                         }
                     }
                 }
             }
-            ServiceResponseStateProject::SendPayload { stream, body } => loop {
+            ServiceResponseStateProject::SendPayload {
+                stream,
+                body,
+                trailers,
+            } => loop {
                 loop {
                     if let Some(buffer) = this.buffer {
                         match stream.poll_capacity(cx) {
@@ -287,6 +436,7 @@ where
                             Poll::Ready(Some(Ok(cap))) => {
                                 let len = buffer.len();
                                 let bytes = buffer.split_to(std::cmp::min(cap, len));
+                                log_chunk(this.log, bytes.len());
 
                                 if let Err(e) = stream.send_data(bytes, false) {
                                     warn!("{:?}", e);
@@ -307,8 +457,15 @@ where
                         match body.poll_next_chunk(cx) {
                             Poll::Pending => return Poll::Pending,
                             Poll::Ready(None) => {
-                                if let Err(e) = stream.send_data(Bytes::new(), true) {
+                                let result = if let Some(trailers) = trailers.take() {
+                                    stream.send_trailers(h2_trailers(this.hpack, trailers))
+                                } else {
+                                    stream.send_data(Bytes::new(), true)
+                                };
+                                if let Err(e) = result {
                                     warn!("{:?}", e);
+                                } else if let Some(ref mut log) = this.log {
+                                    log.completed = true;
                                 }
                                 return Poll::Ready(());
                             }
@@ -1,6 +1,6 @@
 //! HTTP/2 implementation
-use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::{cell::Cell, pin::Pin, rc::Rc};
 
 use h2::RecvStream;
 
@@ -9,17 +9,39 @@ mod service;
 
 pub use self::dispatcher::Dispatcher;
 pub use self::service::H2Service;
-use crate::{http::error::PayloadError, util::Bytes, Stream};
+use crate::http::{error::PayloadError, header::HeaderMap};
+use crate::{util::Bytes, Stream};
 
 /// H2 receive stream
 #[derive(Debug)]
 pub struct Payload {
     pl: RecvStream,
+    received: Option<Rc<Cell<u64>>>,
 }
 
 impl Payload {
     pub(crate) fn new(pl: RecvStream) -> Self {
-        Self { pl }
+        Self { pl, received: None }
+    }
+
+    /// Count received bytes
+    pub(crate) fn counter(mut self, received: Rc<Cell<u64>>) -> Self {
+        self.received = Some(received);
+        self
+    }
+
+    /// Poll payload trailers
+    ///
+    /// Trailers are available after payload is fully read.
+    pub fn poll_trailers(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, PayloadError>> {
+        match self.pl.poll_trailers(cx) {
+            Poll::Ready(Ok(trailers)) => Poll::Ready(Ok(trailers.map(|t| t.into()))),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err.into())),
+            Poll::Pending => Poll::Pending,
+        }
     }
 }
 
@@ -32,6 +54,9 @@ impl Stream for Payload {
         match Pin::new(&mut this.pl).poll_data(cx) {
             Poll::Ready(Some(Ok(chunk))) => {
                 let len = chunk.len();
+                if let Some(ref received) = this.received {
+                    received.set(received.get() + len as u64);
+                }
                 if let Err(err) = this.pl.flow_control().release_capacity(len) {
                     Poll::Ready(Some(Err(err.into())))
                 } else {
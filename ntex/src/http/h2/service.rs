@@ -1,7 +1,7 @@
 use std::task::{Context, Poll};
 use std::{future::Future, marker::PhantomData, pin::Pin, rc::Rc};
 
-use h2::server::{self, Handshake};
+use h2::server::Handshake;
 
 use crate::http::body::MessageBody;
 use crate::http::config::{DispatcherConfig, ServiceConfig};
@@ -193,12 +193,13 @@ where
             io.query::<types::PeerAddr>().get()
         );
         io.set_disconnect_timeout(self.config.client_disconnect.into());
+        self.config.set_buffer_params(&io);
 
         H2ServiceHandlerResponse {
             state: State::Handshake(
                 io.get_ref(),
                 self.config.clone(),
-                server::Builder::new().handshake(io),
+                self.config.h2_builder().handshake(io),
             ),
         }
     }
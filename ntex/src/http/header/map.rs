@@ -10,12 +10,12 @@ use crate::util::{Either, HashMap};
 /// `HeaderMap` is an multimap of [`HeaderName`] to values.
 ///
 /// [`HeaderName`]: struct.HeaderName.html
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct HeaderMap {
     pub(crate) inner: HashMap<HeaderName, Value>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum Value {
     One(HeaderValue),
     Multi(Vec<HeaderValue>),
@@ -174,6 +174,17 @@ impl HeaderMap {
         }
     }
 
+    /// Mark all values associated with the key as sensitive.
+    pub(crate) fn set_sensitive(&mut self, name: &HeaderName) {
+        match self.inner.get_mut(name) {
+            Some(Value::One(ref mut val)) => val.set_sensitive(true),
+            Some(Value::Multi(ref mut vals)) => {
+                vals.iter_mut().for_each(|val| val.set_sensitive(true))
+            }
+            None => (),
+        }
+    }
+
     /// Returns true if the map contains a value for the specified key.
     pub fn contains_key<N: AsName>(&self, key: N) -> bool {
         match key.as_name() {
@@ -1,7 +1,8 @@
-use std::{cell::Ref, cell::RefCell, cell::RefMut, net, rc::Rc};
+use std::{cell::Ref, cell::RefCell, cell::RefMut, fmt, net, rc::Rc};
 
 use bitflags::bitflags;
 
+use crate::http::h1::PreparedResponse;
 use crate::http::header::HeaderMap;
 use crate::http::{header, Method, StatusCode, Uri, Version};
 use crate::io::{types, IoRef};
@@ -18,6 +19,20 @@ pub enum ConnectionType {
     Upgrade,
 }
 
+/// Form of the request target
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RequestTarget {
+    /// Absolute path with optional query, `/index.html?q=1`
+    Origin,
+    /// Absolute uri, `http://www.example.org/index.html`,
+    /// used for requests to forward proxies
+    Absolute,
+    /// Host and port, `www.example.org:443`, used for `CONNECT` requests
+    Authority,
+    /// Whole server, `*`, used for server-wide `OPTIONS` requests
+    Asterisk,
+}
+
 bitflags! {
     pub(crate) struct Flags: u8 {
         const CLOSE       = 0b0000_0001;
@@ -101,6 +116,19 @@ impl RequestHead {
         &mut self.headers
     }
 
+    /// Form of the request target
+    pub fn target(&self) -> RequestTarget {
+        if self.uri.scheme().is_some() {
+            RequestTarget::Absolute
+        } else if self.uri.authority().is_some() {
+            RequestTarget::Authority
+        } else if self.uri.path() == "*" {
+            RequestTarget::Asterisk
+        } else {
+            RequestTarget::Origin
+        }
+    }
+
     #[inline]
     /// Set connection type of the message
     pub fn set_connection_type(&mut self, ctype: ConnectionType) {
@@ -217,6 +245,8 @@ pub struct ResponseHead {
     pub headers: HeaderMap,
     pub reason: Option<&'static str>,
     pub(crate) extensions: RefCell<Extensions>,
+    pub(crate) prepared: Option<PreparedResponse>,
+    pub(crate) trailers: Option<Trailers>,
     flags: Flags,
 }
 
@@ -231,6 +261,8 @@ impl ResponseHead {
             reason: None,
             flags: Flags::empty(),
             extensions: RefCell::new(Extensions::new()),
+            prepared: None,
+            trailers: None,
         }
     }
 
@@ -337,6 +369,29 @@ impl ResponseHead {
     }
 }
 
+/// Response trailers callback
+pub(crate) struct Trailers(Box<dyn FnOnce() -> HeaderMap>);
+
+impl Trailers {
+    pub(crate) fn new<F>(f: F) -> Self
+    where
+        F: FnOnce() -> HeaderMap + 'static,
+    {
+        Trailers(Box::new(f))
+    }
+
+    /// Generate trailers
+    pub(crate) fn call(self) -> HeaderMap {
+        (self.0)()
+    }
+}
+
+impl fmt::Debug for Trailers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Trailers(..)")
+    }
+}
+
 impl Default for ResponseHead {
     fn default() -> Self {
         Self::new(Default::default())
@@ -348,6 +403,8 @@ impl Head for ResponseHead {
         self.reason = None;
         self.headers.clear();
         self.flags = Flags::empty();
+        self.prepared = None;
+        self.trailers = None;
     }
 
     fn with_pool<F, R>(f: F) -> R
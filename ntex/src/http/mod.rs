@@ -1,8 +1,11 @@
 //! Http protocol support.
+pub mod access_log;
 pub mod body;
 mod builder;
 pub mod client;
 mod config;
+#[cfg(feature = "content-digest")]
+pub mod digest;
 #[cfg(feature = "compress")]
 pub mod encoding;
 pub(crate) mod helpers;
@@ -10,11 +13,13 @@ mod httpcodes;
 mod httpmessage;
 mod message;
 mod payload;
+mod redirect;
 mod request;
 mod response;
 mod service;
 
 pub mod error;
+pub mod grpc;
 pub mod h1;
 pub mod h2;
 pub mod header;
@@ -22,14 +27,18 @@ pub mod test;
 
 pub(crate) use self::message::Message;
 
+pub use self::access_log::RequestLogRecord;
 pub use self::builder::HttpServiceBuilder;
 pub use self::client::Client;
-pub use self::config::{DateService, KeepAlive, ServiceConfig};
-pub use self::error::ResponseError;
+pub use self::config::{DateService, HpackConfig, KeepAlive, ServiceConfig};
+pub use self::error::{HttpDispatchError, ResponseError};
 pub use self::header::HeaderMap;
 pub use self::httpmessage::HttpMessage;
-pub use self::message::{ConnectionType, RequestHead, RequestHeadType, ResponseHead};
+pub use self::message::{
+    ConnectionType, RequestHead, RequestHeadType, RequestTarget, ResponseHead,
+};
 pub use self::payload::{Payload, PayloadStream};
+pub use self::redirect::HttpsRedirect;
 pub use self::request::Request;
 pub use self::response::{Response, ResponseBuilder};
 pub use self::service::HttpService;
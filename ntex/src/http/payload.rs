@@ -2,8 +2,9 @@ use std::{fmt, mem, pin::Pin, task::Context, task::Poll};
 
 use h2::RecvStream;
 
-use super::{error::PayloadError, h1, h2 as h2d};
-use crate::{util::Bytes, Stream};
+use super::{error::PayloadError, h1, h2 as h2d, header::HeaderMap};
+use crate::util::{poll_fn, Bytes};
+use crate::Stream;
 
 /// Type represent boxed payload
 pub type PayloadStream = Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>>;
@@ -70,6 +71,40 @@ impl Payload {
     {
         Payload::Stream(Box::pin(stream))
     }
+
+    /// Poll payload trailers
+    ///
+    /// Remaining payload data is read and discarded. Trailers are
+    /// supported for chunked http/1 payloads and for http/2 streams,
+    /// other payloads resolve to `None`.
+    pub fn poll_trailers(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, PayloadError>> {
+        // drain payload
+        loop {
+            match Pin::new(&mut *self).poll_next(cx) {
+                Poll::Ready(Some(Ok(_))) => continue,
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Err(err)),
+                Poll::Ready(None) => break,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        match self {
+            Payload::H1(ref mut pl) => Poll::Ready(Ok(pl.take_trailers())),
+            Payload::H2(ref mut pl) => pl.poll_trailers(cx),
+            Payload::None | Payload::Stream(_) => Poll::Ready(Ok(None)),
+        }
+    }
+
+    /// Read payload trailers
+    ///
+    /// Remaining payload data is read and discarded, so trailers should
+    /// be requested after payload is processed.
+    pub async fn trailers(&mut self) -> Result<Option<HeaderMap>, PayloadError> {
+        poll_fn(|cx| self.poll_trailers(cx)).await
+    }
 }
 
 impl Stream for Payload {
@@ -0,0 +1,143 @@
+use std::{io, rc::Rc, task::Context, task::Poll};
+
+use crate::http::header::LOCATION;
+use crate::http::{Method, Request, Response, StatusCode};
+use crate::{util::Ready, Service, ServiceFactory};
+
+/// Service redirects plain text http requests to https.
+///
+/// `GET` and `HEAD` requests get redirected with `301 Moved Permanently`,
+/// all other requests with `308 Permanent Redirect`, so clients keep
+/// request method and body.
+///
+/// Redirect responses do not contain `Strict-Transport-Security` header,
+/// it must not be sent over plain text http. Use
+/// [`Hsts`](crate::web::middleware::Hsts) middleware for https application.
+///
+/// ```rust,no_run
+/// use ntex::http::{HttpService, HttpsRedirect};
+///
+/// #[ntex::main]
+/// async fn main() -> std::io::Result<()> {
+///     ntex::server::Server::build()
+///         .bind("redirect", "0.0.0.0:80", |_| {
+///             HttpService::build().finish(
+///                 HttpsRedirect::new("example.com").port(8443),
+///             )
+///         })?
+///         .run()
+///         .await
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct HttpsRedirect {
+    inner: Rc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    host: String,
+    port: Option<u16>,
+}
+
+impl HttpsRedirect {
+    /// Create redirect service for specified host.
+    pub fn new<T: AsRef<str>>(host: T) -> Self {
+        HttpsRedirect {
+            inner: Rc::new(Inner {
+                host: host.as_ref().to_string(),
+                port: None,
+            }),
+        }
+    }
+
+    /// Set https port of the redirect location.
+    ///
+    /// By default location does not contain port, so default https port is used.
+    pub fn port(mut self, port: u16) -> Self {
+        self.inner_mut().port = if port == 443 { None } else { Some(port) };
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("Redirect service is in use")
+    }
+}
+
+impl ServiceFactory<Request> for HttpsRedirect {
+    type Response = Response;
+    type Error = io::Error;
+    type Service = HttpsRedirect;
+    type InitError = io::Error;
+    type Future = Ready<Self::Service, Self::InitError>;
+
+    #[inline]
+    fn new_service(&self, _: ()) -> Self::Future {
+        Ready::Ok(self.clone())
+    }
+}
+
+impl Service<Request> for HttpsRedirect {
+    type Response = Response;
+    type Error = io::Error;
+    type Future = Ready<Self::Response, Self::Error>;
+
+    #[inline]
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: Request) -> Self::Future {
+        let inner = &self.inner;
+        let path = req
+            .uri()
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("/");
+        let location = if let Some(port) = inner.port {
+            format!("https://{}:{}{}", inner.host, port, path)
+        } else {
+            format!("https://{}{}", inner.host, path)
+        };
+
+        let status = match *req.method() {
+            Method::GET | Method::HEAD => StatusCode::MOVED_PERMANENTLY,
+            _ => StatusCode::PERMANENT_REDIRECT,
+        };
+        let mut res = Response::build(status);
+        res.header(LOCATION, location);
+        Ready::Ok(res.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{header::STRICT_TRANSPORT_SECURITY, test::TestRequest};
+
+    #[crate::rt_test]
+    async fn test_redirect() {
+        let srv = HttpsRedirect::new("example.com")
+            .new_service(())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_uri("/path?q=1").finish();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            res.headers().get(LOCATION).unwrap(),
+            "https://example.com/path?q=1"
+        );
+        assert!(!res.headers().contains_key(STRICT_TRANSPORT_SECURITY));
+
+        let srv = HttpsRedirect::new("example.com").port(8443);
+        let req = TestRequest::with_uri("/").method(Method::POST).finish();
+        let res = srv.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            res.headers().get(LOCATION).unwrap(),
+            "https://example.com:8443/"
+        );
+    }
+}
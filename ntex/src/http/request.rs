@@ -1,8 +1,8 @@
-use std::{cell::Ref, cell::RefMut, fmt, mem, net};
+use std::{cell::Ref, cell::RefMut, fmt, mem, net, rc::Rc};
 
 use crate::http::header::{self, HeaderMap};
 use crate::http::httpmessage::HttpMessage;
-use crate::http::message::{Message, RequestHead};
+use crate::http::message::{Message, RequestHead, RequestTarget};
 use crate::http::{payload::Payload, Method, Uri, Version};
 use crate::io::{types, IoRef};
 use crate::util::Extensions;
@@ -85,6 +85,12 @@ impl Request {
         &mut self.head_mut().uri
     }
 
+    /// Form of the request target
+    #[inline]
+    pub fn target(&self) -> RequestTarget {
+        self.head().target()
+    }
+
     /// Read the Request method.
     #[inline]
     pub fn method(&self) -> &Method {
@@ -131,6 +137,14 @@ impl Request {
         self.head().io.as_ref()
     }
 
+    /// Connection data
+    ///
+    /// Data is attached to connection by `ServerBuilder::on_worker_connect()` hooks.
+    #[inline]
+    pub fn conn_data<T: 'static>(&self) -> Option<Rc<T>> {
+        self.io().and_then(|io| io.data::<T>())
+    }
+
     /// Peer socket address
     ///
     /// Peer address is actual socket address, if proxy is used in front of
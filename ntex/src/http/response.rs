@@ -6,10 +6,10 @@ use serde::Serialize;
 #[cfg(feature = "cookie")]
 use coo_kie::{Cookie, CookieJar};
 
-use crate::http::body::{Body, BodyStream, MessageBody, ResponseBody};
+use crate::http::body::{Body, BodyItem, BodyStream, MessageBody, ResponseBody};
 use crate::http::error::{HttpError, ResponseError};
 use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
-use crate::http::message::{ConnectionType, Message, ResponseHead};
+use crate::http::message::{ConnectionType, Message, ResponseHead, Trailers};
 use crate::http::StatusCode;
 use crate::{util::Bytes, util::BytesMut, util::Extensions, Stream};
 
@@ -73,7 +73,14 @@ impl<B> Response<B> {
     #[inline]
     /// Mutable reference to a http message part of the response
     pub fn head_mut(&mut self) -> &mut ResponseHead {
-        &mut *self.head
+        let head = &mut *self.head;
+        head.prepared = None;
+        head
+    }
+
+    /// Take response trailers callback
+    pub(crate) fn take_trailers(&mut self) -> Option<Trailers> {
+        self.head.trailers.take()
     }
 
     /// Get the response status code
@@ -85,7 +92,7 @@ impl<B> Response<B> {
     /// Set the `StatusCode` for this response
     #[inline]
     pub fn status_mut(&mut self) -> &mut StatusCode {
-        &mut self.head.status
+        &mut self.head_mut().status
     }
 
     /// Get the headers from the response
@@ -97,7 +104,7 @@ impl<B> Response<B> {
     /// Get a mutable reference to the headers
     #[inline]
     pub fn headers_mut(&mut self) -> &mut HeaderMap {
-        &mut self.head.headers
+        &mut self.head_mut().headers
     }
 
     #[cfg(feature = "cookie")]
@@ -113,7 +120,7 @@ impl<B> Response<B> {
     /// Add a cookie to this response
     #[inline]
     pub fn add_cookie(&mut self, cookie: &Cookie<'_>) -> Result<(), HttpError> {
-        let h = &mut self.head.headers;
+        let h = self.headers_mut();
         HeaderValue::from_str(&cookie.to_string())
             .map(|c| {
                 h.append(header::SET_COOKIE, c);
@@ -126,7 +133,7 @@ impl<B> Response<B> {
     /// the number of cookies removed.
     #[inline]
     pub fn del_cookie(&mut self, name: &str) -> usize {
-        let h = &mut self.head.headers;
+        let h = self.headers_mut();
         let vals: Vec<HeaderValue> = h
             .get_all(header::SET_COOKIE)
             .map(|v| v.to_owned())
@@ -178,7 +185,8 @@ impl<B> Response<B> {
     }
 
     /// Set a body
-    pub fn set_body<B2>(self, body: B2) -> Response<B2> {
+    pub fn set_body<B2>(mut self, body: B2) -> Response<B2> {
+        self.head.prepared = None;
         Response {
             head: self.head,
             body: ResponseBody::Body(body),
@@ -220,6 +228,7 @@ impl<B> Response<B> {
     where
         F: FnOnce(&mut ResponseHead, ResponseBody<B>) -> ResponseBody<B2>,
     {
+        self.head.prepared = None;
         let body = f(&mut self.head, self.body);
 
         Response {
@@ -230,6 +239,7 @@ impl<B> Response<B> {
 
     /// Extract response body
     pub fn take_body(&mut self) -> ResponseBody<B> {
+        self.head.prepared = None;
         self.body.take_body()
     }
 }
@@ -419,6 +429,23 @@ impl ResponseBuilder {
         self
     }
 
+    /// Set response trailers callback.
+    ///
+    /// Callback is called after response body is sent, generated headers are
+    /// sent as trailers. Trailers are supported for http/2 responses and for
+    /// http/1.1 responses with chunked transfer encoding, responses with known
+    /// body size do not use chunked encoding.
+    #[inline]
+    pub fn trailers<F>(&mut self, f: F) -> &mut Self
+    where
+        F: FnOnce() -> HeaderMap + 'static,
+    {
+        if let Some(parts) = parts(&mut self.head, &self.err) {
+            parts.trailers = Some(Trailers::new(f));
+        }
+        self
+    }
+
     /// Set response content type
     #[inline]
     pub fn content_type<V>(&mut self, value: V) -> &mut Self
@@ -579,10 +606,15 @@ impl ResponseBuilder {
     #[inline]
     /// Set a streaming body and generate `Response`.
     ///
+    /// Stream is polled only if connection's write buffer is under
+    /// the watermark. Stream could yield [`BodyItem::Flush`] to send
+    /// all written data to the peer before next item is polled.
+    ///
     /// `ResponseBuilder` can not be used after this call.
-    pub fn streaming<S, E>(&mut self, stream: S) -> Response
+    pub fn streaming<S, E, T>(&mut self, stream: S) -> Response
     where
-        S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
+        S: Stream<Item = Result<T, E>> + Unpin + 'static,
+        T: Into<BodyItem> + 'static,
         E: Error + 'static,
     {
         self.body(Body::from_message(BodyStream::new(stream)))
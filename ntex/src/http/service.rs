@@ -1,7 +1,7 @@
 use std::task::{Context, Poll};
 use std::{cell, error, fmt, future, marker, pin::Pin, rc::Rc};
 
-use h2::server::{self, Handshake};
+use h2::server::Handshake;
 use ntex_tls::types::HttpProtocol;
 
 use crate::io::{types, Filter, Io, IoRef};
@@ -141,6 +141,11 @@ where
         }
     }
 
+    /// Http service configuration
+    pub fn config(&self) -> &ServiceConfig {
+        &self.cfg
+    }
+
     /// Set on request callback.
     pub(crate) fn on_request(self, f: Option<OnRequest>) -> Self {
         *self.on_request.borrow_mut() = f;
@@ -387,11 +392,12 @@ where
 
         if io.query::<HttpProtocol>().get() == Some(HttpProtocol::Http2) {
             io.set_disconnect_timeout(self.config.client_disconnect.into());
+            self.config.set_buffer_params(&io);
             HttpServiceHandlerResponse {
                 state: ResponseState::H2Handshake {
                     data: Some((
                         io.get_ref(),
-                        server::Builder::new().handshake(io),
+                        self.config.h2_builder().handshake(io),
                         self.config.clone(),
                     )),
                 },
@@ -4,13 +4,15 @@ use std::{convert::TryFrom, io, net, str::FromStr, sync::mpsc, thread};
 #[cfg(feature = "cookie")]
 use coo_kie::{Cookie, CookieJar};
 
-use crate::ws::{error::WsClientError, WsClient, WsConnection};
-use crate::{io::Filter, io::Io, rt::System, server::Server, service::ServiceFactory};
-use crate::{time::Millis, time::Seconds, util::Bytes};
+use crate::util::{next, Bytes, BytesMut};
+use crate::ws::{self, error::WsClientError, WsClient, WsConnection};
+use crate::{io::Filter, io::Framed, io::Io, rt::System, server::Server};
+use crate::{service::ServiceFactory, time::Millis, time::Seconds};
 
+use super::client::error::SendRequestError;
 use super::client::{Client, ClientRequest, ClientResponse, Connector};
 use super::error::{HttpError, PayloadError};
-use super::header::{HeaderMap, HeaderName, HeaderValue};
+use super::header::{self, HeaderMap, HeaderName, HeaderValue};
 use super::payload::Payload;
 use super::{Method, Request, Uri, Version};
 
@@ -338,6 +340,24 @@ impl TestServer {
             .await
     }
 
+    /// Connect to websocket server at a given path and return framed client
+    pub async fn ws_framed_at(
+        &mut self,
+        path: &str,
+    ) -> Result<Framed<ws::Codec>, WsClientError> {
+        let (io, codec, _) = self.ws_at(path).await?.into_inner();
+        Ok(Framed::new(io, codec))
+    }
+
+    /// Send request to a given path and consume response as server-sent events
+    pub async fn sse(&self, path: &str) -> Result<SseStream, SendRequestError> {
+        self.request(Method::GET, path)
+            .header(header::ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .map(SseStream::new)
+    }
+
     #[cfg(feature = "openssl")]
     /// Connect to a websocket server
     pub async fn wss(
@@ -382,3 +402,128 @@ impl Drop for TestServer {
         self.stop()
     }
 }
+
+/// Server-sent event
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Event type
+    pub event: Option<String>,
+    /// Event data, multiple data lines are joined with `\n`
+    pub data: String,
+    /// Last event id
+    pub id: Option<String>,
+    /// Reconnection time in milliseconds
+    pub retry: Option<u64>,
+}
+
+/// Server-sent events stream
+///
+/// ```rust,no_run
+/// use ntex::http::{test, HttpService, Response};
+/// use ntex::util::Bytes;
+///
+/// #[ntex::test]
+/// async fn test_sse() {
+///     let srv = test::server(|| {
+///         HttpService::build().finish(|_| async {
+///             Ok::<_, std::io::Error>(
+///                 Response::Ok()
+///                     .content_type("text/event-stream")
+///                     .body("event: ping\ndata: 1\n\n"),
+///             )
+///         })
+///     });
+///
+///     let mut events = srv.sse("/").await.unwrap();
+///     let ev = events.recv().await.unwrap().unwrap();
+///     assert_eq!(ev.event.as_deref(), Some("ping"));
+///     assert_eq!(ev.data, "1");
+/// }
+/// ```
+pub struct SseStream {
+    response: ClientResponse,
+    buf: BytesMut,
+    eof: bool,
+}
+
+impl SseStream {
+    /// Create events stream from client response
+    pub fn new(response: ClientResponse) -> Self {
+        SseStream {
+            response,
+            buf: BytesMut::new(),
+            eof: false,
+        }
+    }
+
+    /// Get reference to client response
+    pub fn response(&self) -> &ClientResponse {
+        &self.response
+    }
+
+    /// Receive next event.
+    ///
+    /// Returns `None` if response payload is complete. Incomplete event
+    /// at the end of payload is discarded.
+    pub async fn recv(&mut self) -> Option<Result<SseEvent, PayloadError>> {
+        let mut event = SseEvent::default();
+        let mut data = Vec::new();
+        let mut has_fields = false;
+
+        loop {
+            let line = match self.buf.iter().position(|b| *b == b'\n') {
+                Some(idx) => {
+                    let line = self.buf.split_to(idx + 1);
+                    let line = &line[..idx];
+                    String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line))
+                        .into_owned()
+                }
+                None => {
+                    if self.eof {
+                        return None;
+                    }
+                    match next(&mut self.response).await {
+                        Some(Ok(chunk)) => self.buf.extend_from_slice(&chunk),
+                        Some(Err(err)) => return Some(Err(err)),
+                        None => self.eof = true,
+                    }
+                    continue;
+                }
+            };
+
+            // dispatch event
+            if line.is_empty() {
+                if has_fields {
+                    event.data = data.join("\n");
+                    return Some(Ok(event));
+                }
+                continue;
+            }
+
+            // comment
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = match line.find(':') {
+                Some(idx) => {
+                    let value = &line[idx + 1..];
+                    (&line[..idx], value.strip_prefix(' ').unwrap_or(value))
+                }
+                None => (line.as_str(), ""),
+            };
+            match field {
+                "event" => event.event = Some(value.to_string()),
+                "data" => data.push(value.to_string()),
+                "id" => event.id = Some(value.to_string()),
+                "retry" => {
+                    if let Ok(retry) = value.parse() {
+                        event.retry = Some(retry)
+                    }
+                }
+                _ => continue,
+            }
+            has_fields = true;
+        }
+    }
+}
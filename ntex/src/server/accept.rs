@@ -5,9 +5,11 @@ use polling::{Event, Poller};
 use crate::rt::System;
 use crate::time::{sleep, Millis};
 
+use super::iplimit::IpLimiter;
 use super::socket::{Listener, SocketAddr};
+use super::stats::Counters;
 use super::worker::{Connection, WorkerClient};
-use super::{Server, ServerStatus, Token};
+use super::{OverloadPolicy, Server, ServerStatus, Token};
 
 const ERR_TIMEOUT: Duration = Duration::from_millis(500);
 const ERR_SLEEP_TIMEOUT: Millis = Millis(525);
@@ -15,11 +17,20 @@ const ERR_SLEEP_TIMEOUT: Millis = Millis(525);
 #[derive(Debug)]
 pub(super) enum Command {
     Stop,
+    /// Stop accept loop, listeners are passed to new process
+    Handoff,
     Pause,
     Resume,
+    /// Stop accepting connections on listener, socket stays bound
+    PauseListener(Token),
+    ResumeListener(Token),
     Worker(WorkerClient),
+    /// Remove worker, worker is stopping
+    RemoveWorker(usize),
     Timer,
     WorkerAvailable,
+    /// Connection is closed, throttled listeners could be resumed
+    ConnectionReleased,
 }
 
 struct ServerSocketInfo {
@@ -27,7 +38,11 @@ struct ServerSocketInfo {
     token: Token,
     sock: Listener,
     registered: Cell<bool>,
+    throttled: Cell<bool>,
+    paused: Cell<bool>,
     timeout: Cell<Option<Instant>>,
+    counters: Arc<Counters>,
+    ip_limit: Option<IpLimiter>,
 }
 
 #[derive(Debug, Clone)]
@@ -48,6 +63,9 @@ pub(super) struct AcceptLoop {
     notify: AcceptNotify,
     inner: Option<(mpsc::Receiver<Command>, Arc<Poller>, Server)>,
     status_handler: Option<Box<dyn FnMut(ServerStatus) + Send>>,
+    rate_limit: Option<RateLimiter>,
+    overload: OverloadPolicy,
+    ip_limits: Vec<(Token, IpLimiter)>,
 }
 
 impl AcceptLoop {
@@ -66,6 +84,9 @@ impl AcceptLoop {
             notify,
             inner: Some((rx, poll, srv)),
             status_handler: None,
+            rate_limit: None,
+            overload: OverloadPolicy::Close,
+            ip_limits: Vec::new(),
         }
     }
 
@@ -84,6 +105,18 @@ impl AcceptLoop {
         self.status_handler = Some(Box::new(f));
     }
 
+    pub(super) fn set_rate_limit(&mut self, rate: u32, burst: u32) {
+        self.rate_limit = Some(RateLimiter::new(rate, burst));
+    }
+
+    pub(super) fn set_overload_policy(&mut self, policy: OverloadPolicy) {
+        self.overload = policy;
+    }
+
+    pub(super) fn set_ip_limit(&mut self, token: Token, limiter: IpLimiter) {
+        self.ip_limits.push((token, limiter));
+    }
+
     pub(super) fn start(
         &mut self,
         socks: Vec<(Token, Listener)>,
@@ -103,6 +136,9 @@ impl AcceptLoop {
             workers,
             self.notify.clone(),
             status_handler,
+            self.rate_limit.take(),
+            self.overload,
+            std::mem::take(&mut self.ip_limits),
         );
     }
 }
@@ -116,7 +152,10 @@ struct Accept {
     notify: AcceptNotify,
     next: usize,
     backpressure: bool,
+    handoff: bool,
     status_handler: Option<Box<dyn FnMut(ServerStatus) + Send>>,
+    rate_limit: Option<RateLimiter>,
+    overload: OverloadPolicy,
 }
 
 impl Accept {
@@ -128,6 +167,9 @@ impl Accept {
         workers: Vec<WorkerClient>,
         notify: AcceptNotify,
         status_handler: Option<Box<dyn FnMut(ServerStatus) + Send>>,
+        rate_limit: Option<RateLimiter>,
+        overload: OverloadPolicy,
+        ip_limits: Vec<(Token, IpLimiter)>,
     ) {
         let sys = System::current();
 
@@ -136,7 +178,19 @@ impl Accept {
             .name("ntex-server accept loop".to_owned())
             .spawn(move || {
                 System::set_current(sys);
-                Accept::new(rx, poller, socks, workers, srv, notify, status_handler).poll()
+                Accept::new(
+                    rx,
+                    poller,
+                    socks,
+                    workers,
+                    srv,
+                    notify,
+                    status_handler,
+                    rate_limit,
+                    overload,
+                    ip_limits,
+                )
+                .poll()
             });
     }
 
@@ -148,6 +202,9 @@ impl Accept {
         srv: Server,
         notify: AcceptNotify,
         status_handler: Option<Box<dyn FnMut(ServerStatus) + Send>>,
+        rate_limit: Option<RateLimiter>,
+        overload: OverloadPolicy,
+        ip_limits: Vec<(Token, IpLimiter)>,
     ) -> Accept {
         let mut sockets = Vec::new();
         for (hnd_token, lst) in socks.into_iter() {
@@ -156,7 +213,14 @@ impl Accept {
                 sock: lst,
                 token: hnd_token,
                 registered: Cell::new(false),
+                throttled: Cell::new(false),
+                paused: Cell::new(false),
                 timeout: Cell::new(None),
+                counters: srv.2.listener(hnd_token),
+                ip_limit: ip_limits
+                    .iter()
+                    .find(|item| item.0 == hnd_token)
+                    .map(|item| item.1.clone()),
             });
         }
 
@@ -170,6 +234,9 @@ impl Accept {
             status_handler,
             next: 0,
             backpressure: false,
+            handoff: false,
+            rate_limit,
+            overload,
         }
     }
 
@@ -214,14 +281,21 @@ impl Accept {
             events.clear();
         }
 
-        // cleanup
-        for info in &self.sockets {
-            info.sock.remove_source()
+        // cleanup, new process owns listeners after handoff
+        if !self.handoff {
+            for info in &self.sockets {
+                info.sock.remove_source()
+            }
         }
     }
 
     fn add_source(&self, idx: usize) {
         let info = &self.sockets[idx];
+        if info.throttled.get() || info.paused.get() {
+            // throttled socket re-registers itself after connection release,
+            // paused socket after resume
+            return;
+        }
 
         loop {
             // try to register poller source
@@ -281,10 +355,70 @@ impl Accept {
         }
     }
 
+    /// Stop accepting connections if connection limit is reached
+    fn throttle(&mut self, key: usize) -> bool {
+        let info = &self.sockets[key];
+        let global = self.srv.2.global();
+        let global_full = global.is_full();
+        if !global_full && !info.counters.is_full() {
+            return false;
+        }
+
+        if global_full {
+            log::warn!(
+                "Server connection limit is reached, pausing socket listener on {}",
+                info.addr
+            );
+            global.throttled();
+        } else {
+            log::warn!(
+                "Listener connection limit is reached, pausing socket listener on {}",
+                info.addr
+            );
+        }
+        info.counters.throttled();
+        info.throttled.set(true);
+        self.remove_source(key);
+        self.update_status(ServerStatus::Throttled);
+        true
+    }
+
+    fn process_released(&mut self) {
+        let global_full = self.srv.2.global().is_full();
+        let mut resumed = false;
+        for key in 0..self.sockets.len() {
+            let info = &self.sockets[key];
+            if info.throttled.get() && !global_full && !info.counters.is_full() {
+                log::info!(
+                    "Resuming socket listener on {} after connection limit",
+                    info.addr
+                );
+                info.throttled.set(false);
+                resumed = true;
+                if !self.backpressure && info.timeout.get().is_none() {
+                    self.add_source(key);
+                }
+            }
+        }
+        if resumed && !self.backpressure {
+            self.update_status(ServerStatus::Ready);
+        }
+    }
+
     fn process_cmd(&mut self) -> bool {
         loop {
             match self.rx.try_recv() {
                 Ok(cmd) => match cmd {
+                    Command::Handoff => {
+                        log::trace!("Stopping accept loop after handoff");
+                        self.handoff = true;
+                        for (key, info) in self.sockets.iter().enumerate() {
+                            log::info!("Stopping socket listener on {}", info.addr);
+                            self.remove_source(key);
+                        }
+                        self.update_status(ServerStatus::NotReady);
+                        return false;
+                    }
                     Command::Stop => {
                         log::trace!("Stopping accept loop");
                         for (key, info) in self.sockets.iter().enumerate() {
@@ -310,11 +444,46 @@ impl Accept {
                         }
                         self.update_status(ServerStatus::Ready);
                     }
+                    Command::PauseListener(token) => {
+                        if let Some(key) =
+                            self.sockets.iter().position(|s| s.token == token)
+                        {
+                            let info = &self.sockets[key];
+                            log::info!("Pausing socket listener on {}", info.addr);
+                            info.paused.set(true);
+                            info.counters.set_paused(true);
+                            self.remove_source(key);
+                        }
+                    }
+                    Command::ResumeListener(token) => {
+                        if let Some(key) =
+                            self.sockets.iter().position(|s| s.token == token)
+                        {
+                            let info = &self.sockets[key];
+                            if info.paused.replace(false) {
+                                log::info!(
+                                    "Resuming paused socket listener on {}",
+                                    info.addr
+                                );
+                                info.counters.set_paused(false);
+                                if !self.backpressure && info.timeout.get().is_none() {
+                                    self.add_source(key);
+                                }
+                            }
+                        }
+                    }
                     Command::Worker(worker) => {
                         log::trace!("Adding new worker to accept loop");
                         self.backpressure(false);
                         self.workers.push(worker);
                     }
+                    Command::RemoveWorker(idx) => {
+                        log::trace!("Removing worker {} from accept loop", idx);
+                        self.workers.retain(|w| w.idx != idx);
+                        if self.workers.len() <= self.next {
+                            self.next = 0;
+                        }
+                    }
                     Command::Timer => {
                         self.process_timer();
                     }
@@ -322,6 +491,9 @@ impl Accept {
                         log::trace!("Worker is available");
                         self.backpressure(false);
                     }
+                    Command::ConnectionReleased => {
+                        self.process_released();
+                    }
                 },
                 Err(err) => match err {
                     mpsc::TryRecvError::Empty => break,
@@ -434,12 +606,64 @@ impl Accept {
 
     fn accept(&mut self, token: usize) -> bool {
         loop {
+            if token < self.sockets.len() && self.throttle(token) {
+                return false;
+            }
+
             let msg = if let Some(info) = self.sockets.get_mut(token) {
                 match info.sock.accept() {
-                    Ok(Some(io)) => Connection {
-                        io,
-                        token: info.token,
-                    },
+                    Ok(Some(io)) => {
+                        // shed connections over accept rate limit
+                        let mut rejected = false;
+                        if let Some(ref mut limiter) = self.rate_limit {
+                            if !limiter.acquire() {
+                                log::trace!(
+                                    "Accept rate limit is reached for {}",
+                                    info.addr
+                                );
+                                rejected = true;
+                            }
+                        }
+
+                        // shed connections over per-ip limits
+                        let mut ip_guard = None;
+                        if let (false, Some(limiter), Some(ip)) =
+                            (rejected, &info.ip_limit, io.peer_ip())
+                        {
+                            ip_guard = limiter.acquire(ip);
+                            if ip_guard.is_none() {
+                                log::trace!(
+                                    "Connection limit for {} is reached for {}",
+                                    ip,
+                                    info.addr
+                                );
+                                rejected = true;
+                            }
+                        }
+
+                        if rejected {
+                            self.srv.2.global().rejected();
+                            info.counters.rejected();
+                            match self.overload {
+                                OverloadPolicy::Close => drop(io),
+                                OverloadPolicy::Reset => io.reset(),
+                            }
+                            continue;
+                        }
+
+                        let mut guard = self
+                            .srv
+                            .2
+                            .connect(info.counters.clone(), self.notify.clone());
+                        if let Some(ip_guard) = ip_guard {
+                            guard.set_ip_guard(ip_guard);
+                        }
+                        Connection {
+                            io,
+                            guard,
+                            token: info.token,
+                        }
+                    }
                     Ok(None) => return true,
                     Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return true,
                     Err(ref e) if connection_error(e) => continue,
@@ -466,6 +690,41 @@ impl Accept {
     }
 }
 
+/// Token bucket accept rate limiter
+#[derive(Debug)]
+pub(super) struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    fn new(rate: u32, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        RateLimiter {
+            burst,
+            rate: f64::from(rate),
+            tokens: burst,
+            updated: Instant::now(),
+        }
+    }
+
+    fn acquire(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
 /// This function defines errors that are per-connection. Which basically
 /// means that if we get this error from `accept()` system call it means
 /// next connection might be ready to be accepted.
@@ -478,3 +737,25 @@ fn connection_error(e: &io::Error) -> bool {
         || e.kind() == io::ErrorKind::ConnectionAborted
         || e.kind() == io::ErrorKind::ConnectionReset
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new(10, 2);
+        assert!(limiter.acquire());
+        assert!(limiter.acquire());
+        assert!(!limiter.acquire());
+
+        thread::sleep(Duration::from_millis(120));
+        assert!(limiter.acquire());
+        assert!(!limiter.acquire());
+
+        // burst is at least one connection
+        let mut limiter = RateLimiter::new(0, 0);
+        assert!(limiter.acquire());
+        assert!(!limiter.acquire());
+    }
+}
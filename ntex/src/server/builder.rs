@@ -4,21 +4,26 @@ use async_channel::{unbounded, Receiver};
 use async_oneshot as oneshot;
 use futures_core::Stream;
 use log::{error, info};
-use socket2::{Domain, SockAddr, Socket, Type};
 
-use crate::io::Io;
+use crate::io::{Io, IoRef};
 use crate::rt::{spawn, Signal, System};
 use crate::service::ServiceFactory;
-use crate::{time::sleep, time::Millis, util::join_all};
+use crate::time::{sleep, Millis};
+use crate::util::{join_all, HashMap, PoolId};
 
 use super::accept::{AcceptLoop, AcceptNotify, Command};
 use super::config::{
     Config, ConfigWrapper, ConfiguredService, ServiceConfig, ServiceRuntime,
 };
+#[cfg(unix)]
+use super::handoff::HandoffState;
+use super::hooks::{ConnectHooks, DisconnectReason};
+use super::iplimit::IpLimiter;
 use super::service::{Factory, InternalServiceFactory};
-use super::socket::Listener;
-use super::worker::{self, Worker, WorkerAvailability, WorkerClient};
-use super::{Server, ServerCommand, ServerStatus, Token};
+use super::shutdown::{ShutdownPhase, ShutdownState};
+use super::socket::{BindError, Listener, SocketConfig};
+use super::worker::{self, MemoryLimit, Worker, WorkerAvailability, WorkerClient};
+use super::{OverloadPolicy, PoolMonitor, Server, ServerCommand, ServerStatus, Token};
 
 const STOP_DELAY: Millis = Millis(300);
 
@@ -37,6 +42,14 @@ pub struct ServerBuilder {
     cmd: Receiver<ServerCommand>,
     server: Server,
     notify: Vec<oneshot::Sender<()>>,
+    pools: PoolMonitor,
+    limits: HashMap<String, usize>,
+    ip_limits: HashMap<String, IpLimiter>,
+    memory_threshold: Option<usize>,
+    hooks: ConnectHooks,
+    shutdown: ShutdownState,
+    #[cfg(unix)]
+    handoff: Option<HandoffState>,
 }
 
 impl Default for ServerBuilder {
@@ -64,6 +77,14 @@ impl ServerBuilder {
             no_signals: false,
             cmd: rx,
             notify: Vec::new(),
+            pools: PoolMonitor::default(),
+            limits: HashMap::default(),
+            ip_limits: HashMap::default(),
+            memory_threshold: None,
+            hooks: ConnectHooks::default(),
+            shutdown: ShutdownState::default(),
+            #[cfg(unix)]
+            handoff: None,
             server,
         }
     }
@@ -103,6 +124,110 @@ impl ServerBuilder {
         self
     }
 
+    /// Limit rate of accepted connections.
+    ///
+    /// Accept loop accepts up to `rate` connections per second with bursts
+    /// up to `burst` connections. Connections over the limit are shed
+    /// according to overload policy, see [`ServerBuilder::overload_policy`].
+    ///
+    /// By default accept rate is not limited.
+    pub fn accept_rate(mut self, rate: u32, burst: u32) -> Self {
+        self.accept.set_rate_limit(rate, burst);
+        self
+    }
+
+    /// Set policy for connections over accept rate or per-ip limits.
+    ///
+    /// By default connections are closed, `OverloadPolicy::Close`.
+    pub fn overload_policy(mut self, policy: OverloadPolicy) -> Self {
+        self.accept.set_overload_policy(policy);
+        self
+    }
+
+    /// Sets the maximum number of concurrent connections for the server.
+    ///
+    /// All socket listeners stop accepting connections when this limit is
+    /// reached and resume when some of connections get closed. Status
+    /// handler receives `ServerStatus::Throttled` event.
+    ///
+    /// By default connections number is not limited.
+    pub fn max_connections(self, num: usize) -> Self {
+        self.server.2.set_max_connections(num);
+        self
+    }
+
+    /// Sets the maximum number of concurrent connections for the listener.
+    ///
+    /// Listener stops accepting connections when this limit is reached
+    /// and resumes when some of its connections get closed.
+    ///
+    /// ```rust,no_run
+    /// use ntex::server::Server;
+    ///
+    /// let builder = Server::build()
+    ///     .max_connections(10_000)
+    ///     .listener_max_connections("admin", 10);
+    /// ```
+    pub fn listener_max_connections<N: AsRef<str>>(mut self, name: N, num: usize) -> Self {
+        self.limits.insert(name.as_ref().to_string(), num);
+        self
+    }
+
+    /// Set per client IP connection limiter for the listener.
+    ///
+    /// Connections over the limit are shed according to overload policy,
+    /// see [`ServerBuilder::overload_policy`].
+    ///
+    /// ```rust,no_run
+    /// use ntex::server::{IpLimiter, Server};
+    ///
+    /// let builder = Server::build()
+    ///     .listener_ip_limit("public", IpLimiter::new().max_connections(16));
+    /// ```
+    pub fn listener_ip_limit<N: AsRef<str>>(mut self, name: N, limiter: IpLimiter) -> Self {
+        self.ip_limits.insert(name.as_ref().to_string(), limiter);
+        self
+    }
+
+    /// Set memory pool for the listener.
+    ///
+    /// Memory pool overrides pool configured by service factory. Listeners
+    /// with dedicated pools report separate statistics via `PoolMonitor`.
+    ///
+    /// ```rust,no_run
+    /// use ntex::{server::Server, util::PoolId};
+    ///
+    /// let builder = Server::build()
+    ///     .memory_pool("public", PoolId::P1)
+    ///     .memory_pool("admin", PoolId::P2);
+    /// let monitor = builder.pool_monitor();
+    /// ```
+    pub fn memory_pool<N: AsRef<str>>(self, name: N, id: PoolId) -> Self {
+        self.pools.assign(name.as_ref(), id);
+        self
+    }
+
+    /// Set memory pools threshold for workers.
+    ///
+    /// Worker stops accepting new connections if size of memory allocated
+    /// by memory pools of its listeners reaches `size` bytes. New connections
+    /// are distributed to other workers, if all workers are over threshold
+    /// server stops accepting connections. Worker resumes accepting when
+    /// allocated size drops below 90% of threshold.
+    ///
+    /// By default memory pressure is not checked.
+    pub fn memory_threshold(mut self, size: usize) -> Self {
+        self.memory_threshold = Some(size);
+        self
+    }
+
+    /// Get memory pools monitor.
+    ///
+    /// Monitor reports memory pool statistics for each listener.
+    pub fn pool_monitor(&self) -> PoolMonitor {
+        self.pools.clone()
+    }
+
     /// Stop ntex runtime when server get dropped.
     ///
     /// By default "stop runtime" is disabled.
@@ -111,6 +236,22 @@ impl ServerBuilder {
         self
     }
 
+    #[cfg(unix)]
+    /// Enable zero-downtime binary upgrade.
+    ///
+    /// On `SIGUSR2` signal server starts current binary with the same
+    /// arguments and passes all listeners to the new process over unix
+    /// socket at `path`. After new process receives listeners, server
+    /// stops accepting new connections, gracefully stops workers and exits.
+    /// New process must get listeners with
+    /// [`listen_from_handoff`](super::listen_from_handoff).
+    ///
+    /// If upgrade fails, server continues to accept connections.
+    pub fn handoff<P: Into<std::path::PathBuf>>(mut self, path: P) -> Self {
+        self.handoff = Some(HandoffState::new(path.into()));
+        self
+    }
+
     /// Disable signal handling.
     ///
     /// By default signal handling is enabled.
@@ -156,7 +297,7 @@ impl ServerBuilder {
         f(&mut cfg)?;
 
         let apply = cfg.apply;
-        let mut srv = ConfiguredService::new(apply);
+        let mut srv = ConfiguredService::new(apply, self.pools.clone());
         for (name, lst) in cfg.services {
             let token = self.token.next();
             srv.stream(token, name.clone(), lst.local_addr()?);
@@ -178,19 +319,87 @@ impl ServerBuilder {
         R: Future<Output = Result<(), E>> + 'static,
         E: fmt::Display + 'static,
     {
-        self.services
-            .push(Box::new(ConfiguredService::new(Box::new(ConfigWrapper {
+        self.services.push(Box::new(ConfiguredService::new(
+            Box::new(ConfigWrapper {
                 f,
                 _t: marker::PhantomData,
-            }))));
+            }),
+            self.pools.clone(),
+        )));
+        self
+    }
+
+    /// Register connection hook.
+    ///
+    /// Hook get called in the worker thread for every accepted connection,
+    /// before connection is passed to the listener's service. Returned value
+    /// is attached to the connection and is available via `IoRef::data()`,
+    /// for example from web handlers with `HttpRequest::conn_data()`.
+    ///
+    /// ```rust,no_run
+    /// use std::time::Instant;
+    /// use ntex::server::Server;
+    ///
+    /// struct ConnectedAt(Instant);
+    ///
+    /// let builder = Server::build()
+    ///     .on_worker_connect(|_| ConnectedAt(Instant::now()));
+    /// ```
+    pub fn on_worker_connect<F, T>(mut self, f: F) -> Self
+    where
+        F: Fn(&IoRef) -> T + Send + Sync + 'static,
+        T: 'static,
+    {
+        self.hooks.on_connect(f);
+        self
+    }
+
+    /// Register disconnect hook.
+    ///
+    /// Hook get called in the worker thread once connection processing is
+    /// completed, including connections that get dropped during worker
+    /// shutdown. Connection data attached by `on_worker_connect()` hooks
+    /// is still available.
+    ///
+    /// ```rust,no_run
+    /// use ntex::server::Server;
+    ///
+    /// let builder = Server::build().on_disconnect(|io, reason| {
+    ///     log::info!("{:?} disconnected: {}", io.query::<ntex::io::types::PeerAddr>().get(), reason);
+    /// });
+    /// ```
+    pub fn on_disconnect<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&IoRef, &DisconnectReason) + Send + Sync + 'static,
+    {
+        self.hooks.on_disconnect(f);
         self
     }
 
     /// Add new service to the server.
     pub fn bind<F, U, N: AsRef<str>, R>(
+        self,
+        name: N,
+        addr: U,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        U: net::ToSocketAddrs,
+        F: Fn(Config) -> R + Send + Clone + 'static,
+        R: ServiceFactory<Io>,
+    {
+        self.bind_with(name, addr, SocketConfig::default(), factory)
+    }
+
+    /// Add new service to the server with custom socket configuration.
+    ///
+    /// If socket configuration does not specify backlog,
+    /// server builder backlog is used.
+    pub fn bind_with<F, U, N: AsRef<str>, R>(
         mut self,
         name: N,
         addr: U,
+        cfg: SocketConfig,
         factory: F,
     ) -> io::Result<Self>
     where
@@ -198,20 +407,149 @@ impl ServerBuilder {
         F: Fn(Config) -> R + Send + Clone + 'static,
         R: ServiceFactory<Io>,
     {
-        let sockets = bind_addr(addr, self.backlog)?;
+        let backlog = cfg.get_backlog().unwrap_or(self.backlog);
+        let sockets = bind_addr(addr, backlog, &cfg)?;
+        self.add_tcp_listeners(name.as_ref(), sockets, &cfg, factory)?;
+        Ok(self)
+    }
+
+    /// Add new service to the server, bind to every address.
+    ///
+    /// Bind is atomic, if any address could not be resolved or bound
+    /// none of the listeners is added. Returned error contains
+    /// [`BindError`](super::BindError) with all failed addresses.
+    ///
+    /// If addresses contain both ipv4 and ipv6 addresses, ipv6 listeners
+    /// are created with `IPV6_V6ONLY` option, so `0.0.0.0:8080` and `[::]:8080`
+    /// could be used together.
+    ///
+    /// ```rust,no_run
+    /// use ntex::{fn_service, server::Server, util::Ready};
+    ///
+    /// #[ntex::main]
+    /// async fn main() -> std::io::Result<()> {
+    ///     Server::build()
+    ///         .bind_all("echo", &["0.0.0.0:8080", "[::]:8080"], |_| {
+    ///             fn_service(|_| Ready::Ok::<_, ()>(()))
+    ///         })?
+    ///         .run()
+    ///         .await
+    /// }
+    /// ```
+    pub fn bind_all<F, U, N: AsRef<str>, R>(
+        self,
+        name: N,
+        addrs: &[U],
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        U: net::ToSocketAddrs + fmt::Debug,
+        F: Fn(Config) -> R + Send + Clone + 'static,
+        R: ServiceFactory<Io>,
+    {
+        self.bind_all_with(name, addrs, SocketConfig::default(), factory)
+    }
 
+    /// Add new service to the server with custom socket configuration,
+    /// bind to every address.
+    ///
+    /// See [`bind_all`](ServerBuilder::bind_all) for details.
+    pub fn bind_all_with<F, U, N: AsRef<str>, R>(
+        mut self,
+        name: N,
+        addrs: &[U],
+        cfg: SocketConfig,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        U: net::ToSocketAddrs + fmt::Debug,
+        F: Fn(Config) -> R + Send + Clone + 'static,
+        R: ServiceFactory<Io>,
+    {
+        let mut failed = Vec::new();
+        let mut resolved: Vec<net::SocketAddr> = Vec::new();
+        for addr in addrs {
+            match addr.to_socket_addrs() {
+                Ok(items) => {
+                    for item in items {
+                        if !resolved.contains(&item) {
+                            resolved.push(item);
+                        }
+                    }
+                }
+                Err(e) => failed.push((format!("{:?}", addr), e)),
+            }
+        }
+
+        let cfg = if resolved.iter().any(|a| a.is_ipv4())
+            && resolved.iter().any(|a| a.is_ipv6())
+        {
+            cfg.only_v6(true)
+        } else {
+            cfg
+        };
+        let backlog = cfg.get_backlog().unwrap_or(self.backlog);
+
+        let mut sockets = Vec::new();
+        for addr in resolved {
+            match cfg.create_tcp_listener(addr, backlog) {
+                Ok(lst) => sockets.push(lst),
+                Err(e) => failed.push((addr.to_string(), e)),
+            }
+        }
+
+        if !failed.is_empty() || sockets.is_empty() {
+            return Err(BindError::new(failed).into_io_error());
+        }
+        self.add_tcp_listeners(name.as_ref(), sockets, &cfg, factory)?;
+        Ok(self)
+    }
+
+    /// Add new service to the server, resolve host name and bind
+    /// to every returned address.
+    ///
+    /// See [`bind_all`](ServerBuilder::bind_all) for details.
+    pub fn bind_host<F, N: AsRef<str>, R>(
+        self,
+        name: N,
+        host: &str,
+        port: u16,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: Fn(Config) -> R + Send + Clone + 'static,
+        R: ServiceFactory<Io>,
+    {
+        self.bind_all(name, &[(host, port)], factory)
+    }
+
+    fn add_tcp_listeners<F, R>(
+        &mut self,
+        name: &str,
+        sockets: Vec<net::TcpListener>,
+        cfg: &SocketConfig,
+        factory: F,
+    ) -> io::Result<()>
+    where
+        F: Fn(Config) -> R + Send + Clone + 'static,
+        R: ServiceFactory<Io>,
+    {
         for lst in sockets {
             let token = self.token.next();
             self.services.push(Factory::create(
-                name.as_ref().to_string(),
+                name.to_string(),
                 token,
                 factory.clone(),
                 lst.local_addr()?,
+                self.pools.clone(),
+            ));
+            self.sockets.push((
+                token,
+                name.to_string(),
+                Listener::from_tcp_with(lst, cfg.stream_config()),
             ));
-            self.sockets
-                .push((token, name.as_ref().to_string(), Listener::from_tcp(lst)));
         }
-        Ok(self)
+        Ok(())
     }
 
     #[cfg(all(unix))]
@@ -238,6 +576,30 @@ impl ServerBuilder {
         self.listen_uds(name, lst, factory)
     }
 
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    /// Add new unix domain service bound to abstract socket address.
+    ///
+    /// Abstract address is not associated with filesystem path,
+    /// leading nul byte is added automatically.
+    ///
+    /// Windows named pipes are not supported, accept loop works only
+    /// with sockets that could be registered with the poller.
+    pub fn bind_uds_abstract<F, A, N, R>(
+        self,
+        name: N,
+        addr: A,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        N: AsRef<str>,
+        A: AsRef<[u8]>,
+        F: Fn(Config) -> R + Send + Clone + 'static,
+        R: ServiceFactory<Io>,
+    {
+        let lst = super::socket::create_abstract_uds_listener(addr.as_ref(), self.backlog)?;
+        self.listen_uds(name, lst, factory)
+    }
+
     #[cfg(all(unix))]
     /// Add new unix domain service to the server.
     /// Useful when running as a systemd service and
@@ -260,12 +622,42 @@ impl ServerBuilder {
             token,
             factory,
             addr,
+            self.pools.clone(),
         ));
         self.sockets
             .push((token, name.as_ref().to_string(), Listener::from_uds(lst)));
         Ok(self)
     }
 
+    #[cfg(unix)]
+    /// Add new service to the server from inherited listener file descriptor.
+    ///
+    /// File descriptor must refer to a listening tcp or unix domain socket,
+    /// server takes ownership of the descriptor. Useful for systemd socket
+    /// activation and for passing listeners to a new process during restart,
+    /// see [`listen_from_env`](super::listen_from_env).
+    pub fn bind_fd<F, N: AsRef<str>, R>(
+        self,
+        name: N,
+        fd: std::os::unix::io::OwnedFd,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: Fn(Config) -> R + Send + Clone + 'static,
+        R: ServiceFactory<Io>,
+    {
+        let is_tcp = socket2::SockRef::from(&fd)
+            .local_addr()?
+            .as_socket()
+            .is_some();
+
+        if is_tcp {
+            self.listen(name, net::TcpListener::from(fd), factory)
+        } else {
+            self.listen_uds(name, std::os::unix::net::UnixListener::from(fd), factory)
+        }
+    }
+
     /// Add new service to the server.
     pub fn listen<F, N: AsRef<str>, R>(
         mut self,
@@ -283,6 +675,7 @@ impl ServerBuilder {
             token,
             factory,
             lst.local_addr()?,
+            self.pools.clone(),
         ));
         self.sockets
             .push((token, name.as_ref().to_string(), Listener::from_tcp(lst)));
@@ -307,6 +700,17 @@ impl ServerBuilder {
             // start accept thread
             for sock in &self.sockets {
                 info!("Starting \"{}\" service on {}", sock.1, sock.2);
+                let limit = self.limits.get(&sock.1).copied().unwrap_or(0);
+                self.server.2.register(sock.0, &sock.1, limit);
+                if let Some(limiter) = self.ip_limits.get(&sock.1) {
+                    self.accept.set_ip_limit(sock.0, limiter.clone());
+                }
+
+                #[cfg(unix)]
+                if let Some(ref mut handoff) = self.handoff {
+                    use std::os::unix::io::AsRawFd;
+                    handoff.add_listener(&sock.1, sock.2.as_raw_fd());
+                }
             }
             self.accept.start(
                 mem::take(&mut self.sockets)
@@ -333,7 +737,32 @@ impl ServerBuilder {
         let services: Vec<Box<dyn InternalServiceFactory>> =
             self.services.iter().map(|v| v.clone_factory()).collect();
 
-        Worker::start(idx, services, avail, self.shutdown_timeout)
+        let memory = self
+            .memory_threshold
+            .map(|size| MemoryLimit::new(size, self.pools.clone()));
+
+        Worker::start(
+            idx,
+            services,
+            avail,
+            self.shutdown_timeout,
+            memory,
+            self.hooks.clone(),
+        )
+    }
+
+    fn next_worker_idx(&self) -> usize {
+        let mut new_idx = self.workers.len();
+        'found: loop {
+            for i in 0..self.workers.len() {
+                if self.workers[i].0 == new_idx {
+                    new_idx += 1;
+                    continue 'found;
+                }
+            }
+            break;
+        }
+        new_idx
     }
 
     fn handle_cmd(&mut self, item: ServerCommand) {
@@ -346,6 +775,17 @@ impl ServerBuilder {
                 self.accept.send(Command::Resume);
                 let _ = tx.send(());
             }
+            ServerCommand::PauseListener(name, pause, mut tx) => {
+                let tokens = self.server.2.tokens(&name);
+                for token in &tokens {
+                    self.accept.send(if pause {
+                        Command::PauseListener(*token)
+                    } else {
+                        Command::ResumeListener(*token)
+                    });
+                }
+                let _ = tx.send(!tokens.is_empty());
+            }
             ServerCommand::Signal(sig) => {
                 // Signals support
                 // Handle `SIGINT`, `SIGTERM`, `SIGQUIT` signals and stop ntex system
@@ -374,12 +814,43 @@ impl ServerBuilder {
                             completion: None,
                         })
                     }
+                    #[cfg(unix)]
+                    Signal::Usr2 => {
+                        if let Some(ref mut handoff) = self.handoff {
+                            info!("SIGUSR2 received, starting binary upgrade");
+                            handoff.start(self.server.clone());
+                        }
+                    }
                     _ => (),
                 }
             }
             ServerCommand::Notify(tx) => {
                 self.notify.push(tx);
             }
+            ServerCommand::Subscribe(tx) => {
+                self.shutdown.subscribe(tx);
+            }
+            ServerCommand::Shutdown(phase) => {
+                self.shutdown.set_phase(phase);
+            }
+            #[cfg(unix)]
+            ServerCommand::HandoffDone(result) => match result {
+                Ok(_) => {
+                    info!("Listeners are passed to new process, stopping");
+                    self.exit = true;
+                    self.accept.send(Command::Handoff);
+                    self.handle_cmd(ServerCommand::Stop {
+                        graceful: true,
+                        completion: None,
+                    })
+                }
+                Err(e) => {
+                    error!("Binary upgrade failed: {}", e);
+                    if let Some(ref mut handoff) = self.handoff {
+                        handoff.failed();
+                    }
+                }
+            },
             ServerCommand::Stop {
                 graceful,
                 completion,
@@ -388,18 +859,26 @@ impl ServerBuilder {
 
                 // stop accept thread
                 self.accept.send(Command::Stop);
+                self.shutdown.set_phase(ShutdownPhase::StopAccepting);
                 let notify = std::mem::take(&mut self.notify);
 
                 // stop workers
                 if !self.workers.is_empty() && graceful {
+                    self.shutdown
+                        .set_phase(ShutdownPhase::Draining(self.shutdown_timeout));
                     let futs: Vec<_> = self
                         .workers
                         .iter()
                         .map(move |worker| worker.1.stop(graceful))
                         .collect();
+                    let srv = self.server.clone();
 
                     spawn(async move {
-                        let _ = join_all(futs).await;
+                        let res = join_all(futs).await;
+                        if res.iter().any(|r| !matches!(r, Ok(true))) {
+                            srv.shutdown_phase(ShutdownPhase::ForceClose);
+                        }
+                        srv.shutdown_phase(ShutdownPhase::Stopped);
 
                         if let Some(mut tx) = completion {
                             let _ = tx.send(());
@@ -413,6 +892,11 @@ impl ServerBuilder {
                         }
                     });
                 } else {
+                    if !graceful {
+                        self.shutdown.set_phase(ShutdownPhase::ForceClose);
+                    }
+                    self.shutdown.set_phase(ShutdownPhase::Stopped);
+
                     // we need to stop system if server was spawned
                     if self.exit {
                         spawn(async {
@@ -428,6 +912,26 @@ impl ServerBuilder {
                     }
                 }
             }
+            ServerCommand::SetWorkers(num, mut tx) => {
+                if num > self.workers.len() {
+                    info!("Starting {} new workers", num - self.workers.len());
+                    while self.workers.len() < num {
+                        let idx = self.next_worker_idx();
+                        let worker = self.start_worker(idx, self.accept.notify());
+                        self.workers.push((idx, worker.clone()));
+                        self.accept.send(Command::Worker(worker));
+                    }
+                } else if num < self.workers.len() {
+                    info!("Stopping {} workers", self.workers.len() - num);
+                    while self.workers.len() > num {
+                        let (idx, worker) = self.workers.pop().unwrap();
+                        self.accept.send(Command::RemoveWorker(idx));
+                        worker.stop(true);
+                    }
+                }
+                self.threads = num;
+                let _ = tx.send(());
+            }
             ServerCommand::WorkerFaulted(idx) => {
                 let mut found = false;
                 for i in 0..self.workers.len() {
@@ -441,17 +945,7 @@ impl ServerBuilder {
                 if found {
                     error!("Worker has died {:?}, restarting", idx);
 
-                    let mut new_idx = self.workers.len();
-                    'found: loop {
-                        for i in 0..self.workers.len() {
-                            if self.workers[i].0 == new_idx {
-                                new_idx += 1;
-                                continue 'found;
-                            }
-                        }
-                        break;
-                    }
-
+                    let new_idx = self.next_worker_idx();
                     let worker = self.start_worker(new_idx, self.accept.notify());
                     self.workers.push((new_idx, worker.clone()));
                     self.accept.send(Command::Worker(worker));
@@ -493,12 +987,13 @@ async fn signals(srv: Server) {
 pub(super) fn bind_addr<S: net::ToSocketAddrs>(
     addr: S,
     backlog: i32,
+    cfg: &SocketConfig,
 ) -> io::Result<Vec<net::TcpListener>> {
     let mut err = None;
     let mut succ = false;
     let mut sockets = Vec::new();
     for addr in addr.to_socket_addrs()? {
-        match create_tcp_listener(addr, backlog) {
+        match cfg.create_tcp_listener(addr, backlog) {
             Ok(lst) => {
                 succ = true;
                 sockets.push(lst);
@@ -525,20 +1020,7 @@ pub(crate) fn create_tcp_listener(
     addr: net::SocketAddr,
     backlog: i32,
 ) -> io::Result<net::TcpListener> {
-    let builder = match addr {
-        net::SocketAddr::V4(_) => Socket::new(Domain::IPV4, Type::STREAM, None)?,
-        net::SocketAddr::V6(_) => Socket::new(Domain::IPV6, Type::STREAM, None)?,
-    };
-
-    // On Windows, this allows rebinding sockets which are actively in use,
-    // which allows “socket hijacking”, so we explicitly don't set it here.
-    // https://docs.microsoft.com/en-us/windows/win32/winsock/using-so-reuseaddr-and-so-exclusiveaddruse
-    #[cfg(not(windows))]
-    builder.set_reuse_address(true)?;
-
-    builder.bind(&SockAddr::from(addr))?;
-    builder.listen(backlog)?;
-    Ok(net::TcpListener::from(builder))
+    SocketConfig::default().create_tcp_listener(addr, backlog)
 }
 
 #[cfg(test)]
@@ -548,6 +1030,60 @@ mod tests {
     #[test]
     fn test_bind_addr() {
         let addrs: Vec<net::SocketAddr> = Vec::new();
-        assert!(bind_addr(&addrs[..], 10).is_err());
+        assert!(bind_addr(&addrs[..], 10, &SocketConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_bind_all() {
+        use crate::{service::fn_service, util::Ready};
+
+        let lst = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let busy = lst.local_addr().unwrap();
+
+        let res = ServerBuilder::default().bind_all(
+            "test",
+            &["127.0.0.1:0".to_string(), busy.to_string()],
+            |_| fn_service(|_| Ready::Ok::<_, ()>(())),
+        );
+        let err = match res {
+            Err(e) => e,
+            Ok(_) => panic!("bind must fail"),
+        };
+        let err = err.get_ref().unwrap().downcast_ref::<BindError>().unwrap();
+        assert_eq!(err.failed().len(), 1);
+        assert_eq!(err.failed()[0].0, busy.to_string());
+        assert!(format!("{}", err).contains(&busy.to_string()));
+
+        let builder = ServerBuilder::default()
+            .bind_all("test", &["127.0.0.1:0", "127.0.0.2:0"], |_| {
+                fn_service(|_| Ready::Ok::<_, ()>(()))
+            })
+            .unwrap();
+        assert_eq!(builder.sockets.len(), 2);
+
+        // dual-stack wildcard listeners on the same port
+        if net::TcpListener::bind("[::]:0").is_ok() {
+            let port = net::TcpListener::bind("0.0.0.0:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port();
+            let builder = ServerBuilder::default()
+                .bind_all(
+                    "test",
+                    &[format!("0.0.0.0:{}", port), format!("[::]:{}", port)],
+                    |_| fn_service(|_| Ready::Ok::<_, ()>(())),
+                )
+                .unwrap();
+            assert_eq!(builder.sockets.len(), 2);
+        }
+
+        let err = ServerBuilder::default()
+            .bind_all::<_, &str, _, _>("test", &[], |_| {
+                fn_service(|_| Ready::Ok::<_, ()>(()))
+            })
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
     }
 }
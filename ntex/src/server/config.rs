@@ -1,35 +1,49 @@
 use std::{
-    cell::Cell, cell::RefCell, fmt, future::Future, io, marker::PhantomData, mem, net,
-    pin::Pin, rc::Rc,
+    cell::Cell, cell::Ref, cell::RefCell, cell::RefMut, fmt, future::Future, io,
+    marker::PhantomData, mem, net, pin::Pin, rc::Rc,
 };
 
 use log::error;
 
-use crate::util::{counter::CounterGuard, HashMap, Ready};
-use crate::{io::Io, service, util::PoolId};
+use crate::io::{types, Io};
+use crate::util::{counter::CounterGuard, Extensions, HashMap, Ready};
+use crate::{service, util::PoolId};
 
 use super::builder::bind_addr;
 use super::service::{
     BoxedServerService, InternalServiceFactory, ServerMessage, StreamService,
 };
-use super::Token;
+use super::socket::SocketConfig;
+use super::{PoolMonitor, Token};
 
 #[derive(Clone)]
 pub struct Config(pub(super) Rc<InnerServiceConfig>);
 
 pub(super) struct InnerServiceConfig {
     pub(super) pool: Cell<PoolId>,
+    name: Rc<str>,
+    local_addr: Option<net::SocketAddr>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self(Rc::new(InnerServiceConfig {
             pool: Cell::new(PoolId::DEFAULT),
+            name: "".into(),
+            local_addr: None,
         }))
     }
 }
 
 impl Config {
+    pub(super) fn new(name: &str, local_addr: net::SocketAddr) -> Self {
+        Self(Rc::new(InnerServiceConfig {
+            pool: Cell::new(PoolId::DEFAULT),
+            name: name.into(),
+            local_addr: Some(local_addr),
+        }))
+    }
+
     /// Set memory pool for the service.
     ///
     /// Use specified memory pool for memory allocations.
@@ -37,6 +51,108 @@ impl Config {
         self.0.pool.set(id);
         self
     }
+
+    /// Listener name
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// Listener local address
+    pub fn local_addr(&self) -> Option<net::SocketAddr> {
+        self.0.local_addr
+    }
+
+    /// Create connection info for incoming io stream
+    ///
+    /// Could be used with `request_config()` service combinator for
+    /// constructing per-connection services.
+    ///
+    /// ```rust,no_run
+    /// use ntex::{io::Io, server::ConnectionInfo};
+    /// use ntex::service::{fn_factory_with_config, fn_service, request_config};
+    ///
+    /// #[ntex::main]
+    /// async fn main() -> std::io::Result<()> {
+    ///     ntex::server::build()
+    ///         .bind("echo", "127.0.0.1:8080", |cfg| {
+    ///             request_config(
+    ///                 fn_factory_with_config(|info: ConnectionInfo| async move {
+    ///                     Ok::<_, ()>(fn_service(move |_: Io| {
+    ///                         println!("{} connection from {:?}", info.name(), info.peer_addr());
+    ///                         async { Ok::<_, ()>(()) }
+    ///                     }))
+    ///                 }),
+    ///                 move |io: &Io| cfg.connection_info(io),
+    ///             )
+    ///         })?
+    ///         .run()
+    ///         .await
+    /// }
+    /// ```
+    pub fn connection_info<F>(&self, io: &Io<F>) -> ConnectionInfo {
+        ConnectionInfo(Rc::new(ConnectionInfoInner {
+            name: self.0.name.clone(),
+            local_addr: self.0.local_addr,
+            peer_addr: io.query::<types::PeerAddr>().get().map(|addr| addr.0),
+            extensions: RefCell::new(Extensions::new()),
+        }))
+    }
+}
+
+#[derive(Clone)]
+/// Per-connection information
+///
+/// Contains listener name, local and peer addresses and typed container
+/// for additional connection data, like tls information.
+pub struct ConnectionInfo(Rc<ConnectionInfoInner>);
+
+struct ConnectionInfoInner {
+    name: Rc<str>,
+    local_addr: Option<net::SocketAddr>,
+    peer_addr: Option<net::SocketAddr>,
+    extensions: RefCell<Extensions>,
+}
+
+impl ConnectionInfo {
+    #[inline]
+    /// Listener name
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    #[inline]
+    /// Listener local address
+    pub fn local_addr(&self) -> Option<net::SocketAddr> {
+        self.0.local_addr
+    }
+
+    #[inline]
+    /// Peer address
+    pub fn peer_addr(&self) -> Option<net::SocketAddr> {
+        self.0.peer_addr
+    }
+
+    #[inline]
+    /// Connection data container
+    pub fn extensions(&self) -> Ref<'_, Extensions> {
+        self.0.extensions.borrow()
+    }
+
+    #[inline]
+    /// Mutable connection data container
+    pub fn extensions_mut(&self) -> RefMut<'_, Extensions> {
+        self.0.extensions.borrow_mut()
+    }
+}
+
+impl fmt::Debug for ConnectionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionInfo")
+            .field("name", &self.name())
+            .field("local_addr", &self.local_addr())
+            .field("peer_addr", &self.peer_addr())
+            .finish()
+    }
 }
 
 pub struct ServiceConfig {
@@ -69,7 +185,7 @@ impl ServiceConfig {
     where
         U: net::ToSocketAddrs,
     {
-        let sockets = bind_addr(addr, self.backlog)?;
+        let sockets = bind_addr(addr, self.backlog, &SocketConfig::default())?;
 
         for lst in sockets {
             self.listen(name.as_ref(), lst);
@@ -114,12 +230,17 @@ pub(super) struct ConfiguredService {
     names: HashMap<Token, (String, net::SocketAddr)>,
     topics: HashMap<String, Token>,
     services: Vec<Token>,
+    pools: PoolMonitor,
 }
 
 impl ConfiguredService {
-    pub(super) fn new(rt: Box<dyn ServiceRuntimeConfiguration + Send>) -> Self {
+    pub(super) fn new(
+        rt: Box<dyn ServiceRuntimeConfiguration + Send>,
+        pools: PoolMonitor,
+    ) -> Self {
         ConfiguredService {
             rt,
+            pools,
             names: HashMap::default(),
             topics: HashMap::default(),
             services: Vec::new(),
@@ -144,6 +265,7 @@ impl InternalServiceFactory for ConfiguredService {
             names: self.names.clone(),
             topics: self.topics.clone(),
             services: self.services.clone(),
+            pools: self.pools.clone(),
         })
     }
 
@@ -151,7 +273,7 @@ impl InternalServiceFactory for ConfiguredService {
         &self,
     ) -> Pin<Box<dyn Future<Output = Result<Vec<(Token, BoxedServerService)>, ()>>>> {
         // configure services
-        let rt = ServiceRuntime::new(self.topics.clone());
+        let rt = ServiceRuntime::new(self.topics.clone(), self.pools.clone());
         let cfg_fut = self.rt.configure(ServiceRuntime(rt.0.clone()));
         let mut names = self.names.clone();
         let tokens = self.services.clone();
@@ -251,12 +373,14 @@ struct ServiceRuntimeInner {
     names: HashMap<String, Token>,
     services: HashMap<Token, BoxedNewService>,
     onstart: Vec<Pin<Box<dyn Future<Output = ()>>>>,
+    pools: PoolMonitor,
 }
 
 impl ServiceRuntime {
-    fn new(names: HashMap<String, Token>) -> Self {
+    fn new(names: HashMap<String, Token>, pools: PoolMonitor) -> Self {
         ServiceRuntime(Rc::new(RefCell::new(ServiceRuntimeInner {
             names,
+            pools,
             services: HashMap::default(),
             onstart: Vec::new(),
         })))
@@ -301,6 +425,8 @@ impl ServiceRuntime {
         let mut inner = self.0.borrow_mut();
         if let Some(token) = inner.names.get(name) {
             let token = *token;
+            let pool = inner.pools.assigned(name).unwrap_or(pool);
+            inner.pools.register(name, pool);
             inner.services.insert(
                 token,
                 Box::new(ServiceFactory {
@@ -0,0 +1,320 @@
+//! Listener handoff for zero-downtime binary upgrade
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{env, io, io::Read, io::Write, mem, process, ptr, thread};
+
+use socket2::SockRef;
+
+use super::Server;
+
+/// Environment variable with handoff socket path
+const HANDOFF_ENV: &str = "NTEX_HANDOFF_PATH";
+/// Max time to wait for new process
+const HANDOFF_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_FDS: usize = 64;
+const MAX_NAMES_SIZE: usize = 16 * 1024;
+
+pub(super) struct HandoffState {
+    path: PathBuf,
+    listeners: Vec<(String, RawFd)>,
+    active: bool,
+}
+
+impl HandoffState {
+    pub(super) fn new(path: PathBuf) -> Self {
+        HandoffState {
+            path,
+            listeners: Vec::new(),
+            active: false,
+        }
+    }
+
+    pub(super) fn add_listener(&mut self, name: &str, fd: RawFd) {
+        self.listeners.push((name.to_string(), fd));
+    }
+
+    /// Start new process and pass listeners in background thread
+    pub(super) fn start(&mut self, srv: Server) {
+        if self.active {
+            log::info!("Binary upgrade is in progress");
+            return;
+        }
+        self.active = true;
+
+        let path = self.path.clone();
+        let listeners = self.listeners.clone();
+        let _ = thread::Builder::new()
+            .name("ntex-server handoff".to_string())
+            .spawn(move || {
+                srv.handoff_done(upgrade(&path, &listeners, HANDOFF_TIMEOUT));
+            });
+    }
+
+    pub(super) fn failed(&mut self) {
+        self.active = false;
+    }
+}
+
+/// Spawn current binary and pass listeners to it
+fn upgrade(path: &Path, fds: &[(String, RawFd)], timeout: Duration) -> io::Result<()> {
+    let _ = std::fs::remove_file(path);
+    let lst = UnixListener::bind(path)?;
+    let result = upgrade_with(&lst, path, fds, timeout);
+    let _ = std::fs::remove_file(path);
+    result
+}
+
+fn upgrade_with(
+    lst: &UnixListener,
+    path: &Path,
+    fds: &[(String, RawFd)],
+    timeout: Duration,
+) -> io::Result<()> {
+    lst.set_nonblocking(true)?;
+
+    let mut child = process::Command::new(env::current_exe()?)
+        .args(env::args_os().skip(1))
+        .env(HANDOFF_ENV, path)
+        .spawn()?;
+    log::info!("Started new process {}", child.id());
+
+    let deadline = Instant::now() + timeout;
+    let stream = loop {
+        match lst.accept() {
+            Ok((stream, _)) => break stream,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if let Some(status) = child.try_wait()? {
+                    return Err(io::Error::new(
+                        io::ErrorKind::ConnectionAborted,
+                        format!("New process exited with {}", status),
+                    ));
+                }
+                if Instant::now() > deadline {
+                    let _ = child.kill();
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "New process did not connect in time",
+                    ));
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(deadline.saturating_duration_since(Instant::now())))?;
+    send_listeners(&stream, fds)?;
+
+    // wait for confirmation
+    let mut ack = [0u8; 1];
+    (&stream).read_exact(&mut ack)?;
+    Ok(())
+}
+
+/// Receive listeners from previous process.
+///
+/// Returns names and file descriptors of listeners passed by previous
+/// process during binary upgrade, see [`ServerBuilder::handoff`].
+/// Returns empty list if process is not started by binary upgrade.
+///
+/// [`ServerBuilder::handoff`]: super::ServerBuilder::handoff
+///
+/// ```rust,no_run
+/// use ntex::server::{listen_from_handoff, Server};
+/// use ntex::{fn_service, util::Ready};
+///
+/// #[ntex::main]
+/// async fn main() -> std::io::Result<()> {
+///     let mut builder = Server::build().handoff("/tmp/myapp.handoff");
+///
+///     let listeners = listen_from_handoff()?;
+///     if listeners.is_empty() {
+///         builder = builder.bind("echo", "0.0.0.0:8080", |_| {
+///             fn_service(|_| Ready::Ok::<_, ()>(()))
+///         })?;
+///     } else {
+///         for (name, fd) in listeners {
+///             builder = builder.bind_fd(name, fd, |_| {
+///                 fn_service(|_| Ready::Ok::<_, ()>(()))
+///             })?;
+///         }
+///     }
+///     builder.run().await
+/// }
+/// ```
+pub fn listen_from_handoff() -> io::Result<Vec<(String, OwnedFd)>> {
+    let path = if let Some(path) = env::var_os(HANDOFF_ENV) {
+        env::remove_var(HANDOFF_ENV);
+        path
+    } else {
+        return Ok(Vec::new());
+    };
+
+    let stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(HANDOFF_TIMEOUT))?;
+    let fds = recv_listeners(&stream)?;
+    (&stream).write_all(b"1")?;
+    Ok(fds)
+}
+
+fn send_listeners(stream: &UnixStream, fds: &[(String, RawFd)]) -> io::Result<()> {
+    let names = fds
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    if fds.is_empty() || fds.len() > MAX_FDS || names.len() >= MAX_NAMES_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Unsupported number of listeners",
+        ));
+    }
+    let raw: Vec<RawFd> = fds.iter().map(|(_, fd)| *fd).collect();
+    // names are never empty, new-line separator is always sent
+    let data = format!("{}\n", names);
+
+    unsafe {
+        let size = mem::size_of_val(&raw[..]) as u32;
+        let mut cmsg_buf = vec![0u64; libc::CMSG_SPACE(size) as usize / 8 + 1];
+
+        let mut iov = libc::iovec {
+            iov_base: data.as_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = libc::CMSG_SPACE(size) as _;
+
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(size) as _;
+        ptr::copy_nonoverlapping(
+            raw.as_ptr(),
+            libc::CMSG_DATA(cmsg) as *mut RawFd,
+            raw.len(),
+        );
+
+        if libc::sendmsg(stream.as_raw_fd(), &msg, 0) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn recv_listeners(stream: &UnixStream) -> io::Result<Vec<(String, OwnedFd)>> {
+    let mut data = vec![0u8; MAX_NAMES_SIZE];
+    let mut fds = Vec::new();
+
+    let nbytes = unsafe {
+        let size = (MAX_FDS * mem::size_of::<RawFd>()) as u32;
+        let mut cmsg_buf = vec![0u64; libc::CMSG_SPACE(size) as usize / 8 + 1];
+
+        let mut iov = libc::iovec {
+            iov_base: data.as_mut_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = libc::CMSG_SPACE(size) as _;
+
+        let nbytes = libc::recvmsg(stream.as_raw_fd(), &mut msg, 0);
+        if nbytes < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET
+                && (*cmsg).cmsg_type == libc::SCM_RIGHTS
+            {
+                let len = (*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+                let ptr = libc::CMSG_DATA(cmsg) as *const RawFd;
+                for idx in 0..len / mem::size_of::<RawFd>() {
+                    // received descriptors are new and owned by this process
+                    fds.push(OwnedFd::from_raw_fd(ptr::read_unaligned(ptr.add(idx))));
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+
+        if msg.msg_flags & libc::MSG_CTRUNC != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Listeners are truncated",
+            ));
+        }
+        nbytes as usize
+    };
+
+    let names: Vec<String> = match std::str::from_utf8(&data[..nbytes]) {
+        Ok(s) if s.ends_with('\n') => s[..s.len() - 1]
+            .split('\n')
+            .map(|s| s.to_string())
+            .collect(),
+        _ => Vec::new(),
+    };
+    if names.len() != fds.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Cannot decode listeners",
+        ));
+    }
+
+    for fd in &fds {
+        // do not leak listeners to child processes
+        SockRef::from(fd).set_cloexec(true)?;
+    }
+    Ok(names.into_iter().zip(fds).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net;
+
+    use super::*;
+
+    #[test]
+    fn test_transfer() {
+        let tcp = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = tcp.local_addr().unwrap();
+
+        let (tx, rx) = UnixStream::pair().unwrap();
+        send_listeners(
+            &tx,
+            &[
+                ("first".to_string(), tcp.as_raw_fd()),
+                ("second".to_string(), tcp.as_raw_fd()),
+            ],
+        )
+        .unwrap();
+        let mut fds = recv_listeners(&rx).unwrap();
+        assert_eq!(fds.len(), 2);
+        assert_eq!(fds[0].0, "first");
+        assert_eq!(fds[1].0, "second");
+        assert_ne!(fds[0].1.as_raw_fd(), tcp.as_raw_fd());
+
+        let lst = net::TcpListener::from(fds.remove(0).1);
+        assert_eq!(lst.local_addr().unwrap(), addr);
+        drop(fds);
+
+        // received listener accepts connections
+        drop(tcp);
+        let _client = net::TcpStream::connect(addr).unwrap();
+        assert!(lst.accept().is_ok());
+
+        assert!(send_listeners(&tx, &[]).is_err());
+    }
+
+    #[test]
+    fn test_no_handoff() {
+        assert!(listen_from_handoff().unwrap().is_empty());
+    }
+}
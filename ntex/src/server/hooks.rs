@@ -0,0 +1,125 @@
+use std::{fmt, io, sync::Arc};
+
+use crate::io::IoRef;
+
+/// Connection close reason
+///
+/// Passed to `ServerBuilder::on_disconnect()` callbacks.
+#[derive(Debug)]
+pub enum DisconnectReason {
+    /// Connection service completed successfully
+    Closed,
+    /// Io error occured
+    Error(io::Error),
+    /// Connection service returned error
+    ServiceError,
+    /// Connection got dropped during worker shutdown
+    Shutdown,
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisconnectReason::Closed => write!(f, "Connection closed"),
+            DisconnectReason::Error(err) => write!(f, "Io error: {}", err),
+            DisconnectReason::ServiceError => write!(f, "Service error"),
+            DisconnectReason::Shutdown => write!(f, "Worker shutdown"),
+        }
+    }
+}
+
+type OnConnect = Arc<dyn Fn(&IoRef) + Send + Sync>;
+type OnDisconnect = Arc<dyn Fn(&IoRef, &DisconnectReason) + Send + Sync>;
+
+#[derive(Clone, Default)]
+/// Connection lifecycle hooks
+pub(super) struct ConnectHooks(Arc<Inner>);
+
+#[derive(Clone, Default)]
+struct Inner {
+    connect: Vec<OnConnect>,
+    disconnect: Vec<OnDisconnect>,
+}
+
+impl ConnectHooks {
+    pub(super) fn on_connect<F, T>(&mut self, f: F)
+    where
+        F: Fn(&IoRef) -> T + Send + Sync + 'static,
+        T: 'static,
+    {
+        Arc::make_mut(&mut self.0)
+            .connect
+            .push(Arc::new(move |io: &IoRef| io.set_data(f(io))));
+    }
+
+    pub(super) fn on_disconnect<F>(&mut self, f: F)
+    where
+        F: Fn(&IoRef, &DisconnectReason) + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.0).disconnect.push(Arc::new(f));
+    }
+
+    /// Run connect hooks, returns disconnect guard if disconnect hooks are set
+    pub(super) fn connected(&self, io: &IoRef) -> Option<DisconnectGuard> {
+        for f in &self.0.connect {
+            f(io);
+        }
+
+        if self.0.disconnect.is_empty() {
+            None
+        } else {
+            Some(DisconnectGuard {
+                io: io.clone(),
+                hooks: self.clone(),
+                result: None,
+            })
+        }
+    }
+}
+
+impl fmt::Debug for ConnectHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectHooks")
+            .field("connect", &self.0.connect.len())
+            .field("disconnect", &self.0.disconnect.len())
+            .finish()
+    }
+}
+
+/// Calls disconnect hooks on drop
+///
+/// Guard that gets dropped without completion reports `Shutdown` reason.
+pub(super) struct DisconnectGuard {
+    io: IoRef,
+    hooks: ConnectHooks,
+    result: Option<bool>,
+}
+
+impl DisconnectGuard {
+    /// Connection service is completed
+    pub(super) fn completed(mut self, ok: bool) {
+        self.result = Some(ok);
+    }
+}
+
+impl Drop for DisconnectGuard {
+    fn drop(&mut self) {
+        let reason = match self.result {
+            None => DisconnectReason::Shutdown,
+            Some(ok) => {
+                if let Some(err) = self.io.take_error() {
+                    DisconnectReason::Error(err)
+                } else if ok {
+                    DisconnectReason::Closed
+                } else {
+                    DisconnectReason::ServiceError
+                }
+            }
+        };
+        log::trace!("Connection is disconnected: {}", reason);
+
+        for f in &self.hooks.0.disconnect {
+            f(&self.io, &reason);
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
+use std::{fmt, time::Instant};
+
+/// Per client IP connection limiter
+///
+/// Limiter caps number of concurrent connections and rate of new connections
+/// per client IP address. Limiter is attached to listener with
+/// [`ServerBuilder::listener_ip_limit`](super::ServerBuilder::listener_ip_limit),
+/// connections over the limit are rejected by accept loop according to
+/// overload policy, before any data is read from connection.
+///
+/// Limiter tracks up to `capacity` addresses, least recently seen address
+/// is evicted when capacity is reached. Addresses in allowlist are not
+/// limited.
+///
+/// ```rust,no_run
+/// use ntex::server::{IpLimiter, Server};
+///
+/// let builder = Server::build().listener_ip_limit(
+///     "public",
+///     IpLimiter::new()
+///         .max_connections(64)
+///         .rate(10, 20)
+///         .allow("127.0.0.1".parse().unwrap()),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct IpLimiter(Arc<Inner>);
+
+struct Inner {
+    max_connections: usize,
+    rate: Option<(f64, f64)>,
+    capacity: usize,
+    allow: HashSet<IpAddr>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<IpAddr, Entry>,
+    lru: BTreeMap<u64, IpAddr>,
+    stamp: u64,
+}
+
+struct Entry {
+    active: usize,
+    tokens: f64,
+    updated: Instant,
+    stamp: u64,
+}
+
+impl Default for IpLimiter {
+    fn default() -> Self {
+        IpLimiter::new()
+    }
+}
+
+impl IpLimiter {
+    /// Create new limiter.
+    ///
+    /// By default limiter does not limit connections.
+    pub fn new() -> Self {
+        IpLimiter(Arc::new(Inner {
+            max_connections: 0,
+            rate: None,
+            capacity: 10_000,
+            allow: HashSet::new(),
+            state: Mutex::new(State::default()),
+        }))
+    }
+
+    /// Set max number of concurrent connections per client IP.
+    ///
+    /// By default number of connections is not limited.
+    pub fn max_connections(mut self, num: usize) -> Self {
+        self.inner_mut().max_connections = num;
+        self
+    }
+
+    /// Limit rate of new connections per client IP.
+    ///
+    /// Client could open up to `rate` connections per second with bursts
+    /// up to `burst` connections.
+    pub fn rate(mut self, rate: u32, burst: u32) -> Self {
+        self.inner_mut().rate = Some((f64::from(rate), f64::from(burst.max(1))));
+        self
+    }
+
+    /// Set max number of tracked addresses.
+    ///
+    /// By default limiter tracks up to 10k addresses.
+    pub fn capacity(mut self, num: usize) -> Self {
+        self.inner_mut().capacity = num.max(1);
+        self
+    }
+
+    /// Do not limit connections from specified address.
+    pub fn allow(mut self, addr: IpAddr) -> Self {
+        self.inner_mut().allow.insert(canonical(addr));
+        self
+    }
+
+    /// Number of tracked addresses
+    pub fn tracked(&self) -> usize {
+        self.0.state.lock().unwrap().entries.len()
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.0).expect("Multiple copies exist")
+    }
+
+    /// Register new connection, returns `None` if connection is over the limit
+    pub(super) fn acquire(&self, addr: IpAddr) -> Option<IpGuard> {
+        let addr = canonical(addr);
+        if self.0.allow.contains(&addr) {
+            return Some(IpGuard(None));
+        }
+
+        let now = Instant::now();
+        let mut state = self.0.state.lock().unwrap();
+        state.stamp += 1;
+        let stamp = state.stamp;
+
+        let burst = self.0.rate.map(|(_, burst)| burst).unwrap_or(0.0);
+        let State { entries, lru, .. } = &mut *state;
+        let entry = entries.entry(addr).or_insert_with(|| Entry {
+            active: 0,
+            tokens: burst,
+            updated: now,
+            stamp,
+        });
+        lru.remove(&entry.stamp);
+        lru.insert(stamp, addr);
+        entry.stamp = stamp;
+
+        let mut allowed =
+            self.0.max_connections == 0 || entry.active < self.0.max_connections;
+        if let Some((rate, burst)) = self.0.rate {
+            let elapsed = now.duration_since(entry.updated).as_secs_f64();
+            entry.tokens = (entry.tokens + elapsed * rate).min(burst);
+            entry.updated = now;
+            if allowed && entry.tokens >= 1.0 {
+                entry.tokens -= 1.0;
+            } else {
+                allowed = false;
+            }
+        }
+        if allowed {
+            entry.active += 1;
+        }
+
+        // evict least recently seen addresses
+        while entries.len() > self.0.capacity {
+            if let Some(addr) = lru.values().next().copied() {
+                log::trace!("Evicting {} from ip limiter", addr);
+                lru.remove(&entries.remove(&addr).unwrap().stamp);
+            } else {
+                break;
+            }
+        }
+
+        if allowed {
+            Some(IpGuard(Some((self.clone(), addr))))
+        } else {
+            None
+        }
+    }
+
+    fn release(&self, addr: IpAddr) {
+        let mut state = self.0.state.lock().unwrap();
+        if let Some(entry) = state.entries.get_mut(&addr) {
+            entry.active = entry.active.saturating_sub(1);
+        }
+    }
+}
+
+impl fmt::Debug for IpLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IpLimiter")
+            .field("max_connections", &self.0.max_connections)
+            .field("rate", &self.0.rate)
+            .field("capacity", &self.0.capacity)
+            .field("allow", &self.0.allow)
+            .finish()
+    }
+}
+
+/// Active connection guard, releases connection on drop
+pub(super) struct IpGuard(Option<(IpLimiter, IpAddr)>);
+
+impl Drop for IpGuard {
+    fn drop(&mut self) {
+        if let Some((limiter, addr)) = self.0.take() {
+            limiter.release(addr);
+        }
+    }
+}
+
+impl fmt::Debug for IpGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IpGuard")
+            .field(&self.0.as_ref().map(|item| item.1))
+            .finish()
+    }
+}
+
+/// Convert ipv4-mapped ipv6 address to ipv4
+fn canonical(addr: IpAddr) -> IpAddr {
+    if let IpAddr::V6(v6) = addr {
+        if let [0, 0, 0, 0, 0, 0xffff, hi, lo] = v6.segments() {
+            return IpAddr::V4(Ipv4Addr::new(
+                (hi >> 8) as u8,
+                hi as u8,
+                (lo >> 8) as u8,
+                lo as u8,
+            ));
+        }
+    }
+    addr
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn test_max_connections() {
+        let limiter = IpLimiter::new().max_connections(2);
+        let addr: IpAddr = "10.0.0.1".parse().unwrap();
+        let g1 = limiter.acquire(addr).unwrap();
+        let _g2 = limiter.acquire(addr).unwrap();
+        assert!(limiter.acquire(addr).is_none());
+        // ipv4-mapped address shares limit
+        assert!(limiter
+            .acquire("::ffff:10.0.0.1".parse().unwrap())
+            .is_none());
+        assert!(limiter.acquire("10.0.0.2".parse().unwrap()).is_some());
+
+        drop(g1);
+        assert!(limiter.acquire(addr).is_some());
+        assert_eq!(limiter.tracked(), 2);
+    }
+
+    #[test]
+    fn test_rate() {
+        let limiter = IpLimiter::new().rate(10, 2);
+        let addr: IpAddr = "10.0.0.1".parse().unwrap();
+        assert!(limiter.acquire(addr).is_some());
+        assert!(limiter.acquire(addr).is_some());
+        assert!(limiter.acquire(addr).is_none());
+
+        thread::sleep(Duration::from_millis(120));
+        assert!(limiter.acquire(addr).is_some());
+        assert!(limiter.acquire(addr).is_none());
+    }
+
+    #[test]
+    fn test_allow_and_eviction() {
+        let local: IpAddr = "127.0.0.1".parse().unwrap();
+        let limiter = IpLimiter::new().max_connections(1).capacity(2).allow(local);
+        let _g1 = limiter.acquire(local).unwrap();
+        let _g2 = limiter.acquire(local).unwrap();
+        assert_eq!(limiter.tracked(), 0);
+
+        let _g3 = limiter.acquire("10.0.0.1".parse().unwrap()).unwrap();
+        let _g4 = limiter.acquire("10.0.0.2".parse().unwrap()).unwrap();
+        let _g5 = limiter.acquire("10.0.0.3".parse().unwrap()).unwrap();
+        assert_eq!(limiter.tracked(), 2);
+
+        // least recently seen address is evicted
+        assert!(limiter.acquire("10.0.0.1".parse().unwrap()).is_some());
+        assert!(limiter.acquire("10.0.0.3".parse().unwrap()).is_none());
+    }
+}
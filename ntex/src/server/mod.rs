@@ -7,24 +7,45 @@ use async_oneshot as oneshot;
 mod accept;
 mod builder;
 mod config;
+mod hooks;
+mod iplimit;
+mod monitor;
 mod service;
+mod shutdown;
 mod socket;
+mod stats;
 mod test;
 mod worker;
 
+#[cfg(unix)]
+mod handoff;
+#[cfg(unix)]
+mod systemd;
+
 #[cfg(feature = "openssl")]
 pub use ntex_tls::openssl;
 
 #[cfg(feature = "rustls")]
 pub use ntex_tls::rustls;
 
-pub use ntex_tls::max_concurrent_ssl_accept;
+pub use ntex_tls::{dual, max_concurrent_ssl_accept};
 
 pub(crate) use self::builder::create_tcp_listener;
 pub use self::builder::ServerBuilder;
-pub use self::config::{Config, ServiceConfig, ServiceRuntime};
+pub use self::config::{Config, ConnectionInfo, ServiceConfig, ServiceRuntime};
+pub use self::hooks::DisconnectReason;
+pub use self::iplimit::IpLimiter;
+pub use self::monitor::PoolMonitor;
+pub use self::shutdown::{ShutdownPhase, ShutdownSubscriber};
+pub use self::socket::{BindError, SocketConfig};
+pub use self::stats::{ListenerStats, ServerStats};
 pub use self::test::{build_test_server, test_server, TestServer};
 
+#[cfg(unix)]
+pub use self::handoff::listen_from_handoff;
+#[cfg(unix)]
+pub use self::systemd::listen_from_env;
+
 #[non_exhaustive]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 /// Server readiness status
@@ -32,6 +53,17 @@ pub enum ServerStatus {
     Ready,
     NotReady,
     WorkerFailed,
+    /// Connection limit is reached, listener does not accept new connections
+    Throttled,
+}
+
+/// Policy for connections over accept rate or per-ip limits
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OverloadPolicy {
+    /// Accept connection and close it immediately
+    Close,
+    /// Accept connection and reset it, peer receives RST
+    Reset,
 }
 
 /// Socket id token
@@ -61,8 +93,11 @@ pub enum SslError<E> {
 #[derive(Debug)]
 enum ServerCommand {
     WorkerFaulted(usize),
+    SetWorkers(usize, oneshot::Sender<()>),
     Pause(oneshot::Sender<()>),
     Resume(oneshot::Sender<()>),
+    /// Pause or resume listeners with specified name
+    PauseListener(String, bool, oneshot::Sender<bool>),
     Signal(crate::rt::Signal),
     /// Whether to try and shut down gracefully
     Stop {
@@ -71,15 +106,26 @@ enum ServerCommand {
     },
     /// Notify of server stop
     Notify(oneshot::Sender<()>),
+    /// Subscribe to shutdown phases
+    Subscribe(Sender<ShutdownPhase>),
+    /// Shutdown phase is reached
+    Shutdown(ShutdownPhase),
+    /// Listeners handoff is completed
+    #[cfg(unix)]
+    HandoffDone(io::Result<()>),
 }
 
 /// Server controller
 #[derive(Debug)]
-pub struct Server(Sender<ServerCommand>, Option<oneshot::Receiver<()>>);
+pub struct Server(
+    Sender<ServerCommand>,
+    Option<oneshot::Receiver<()>>,
+    stats::Stats,
+);
 
 impl Server {
     fn new(tx: Sender<ServerCommand>) -> Self {
-        Server(tx, None)
+        Server(tx, None, stats::Stats::default())
     }
 
     /// Start server building process
@@ -91,6 +137,15 @@ impl Server {
         let _ = self.0.try_send(ServerCommand::Signal(sig));
     }
 
+    #[cfg(unix)]
+    fn handoff_done(&self, result: io::Result<()>) {
+        let _ = self.0.try_send(ServerCommand::HandoffDone(result));
+    }
+
+    fn shutdown_phase(&self, phase: ShutdownPhase) {
+        let _ = self.0.try_send(ServerCommand::Shutdown(phase));
+    }
+
     fn worker_faulted(&self, idx: usize) {
         let _ = self.0.try_send(ServerCommand::WorkerFaulted(idx));
     }
@@ -116,6 +171,97 @@ impl Server {
         }
     }
 
+    /// Pause accepting incoming connections on listeners with specified name
+    ///
+    /// Listener socket stays bound, pending connections are kept in
+    /// the socket backlog until listener is resumed. Other listeners
+    /// are not affected. Returns `false` if listener is not found.
+    ///
+    /// Paused state is reported by [`ListenerStats::paused`].
+    pub fn pause_listener<N: AsRef<str>>(&self, name: N) -> impl Future<Output = bool> {
+        self.listener_cmd(name.as_ref(), true)
+    }
+
+    /// Resume accepting incoming connections on listeners with specified name
+    ///
+    /// Returns `false` if listener is not found.
+    pub fn resume_listener<N: AsRef<str>>(&self, name: N) -> impl Future<Output = bool> {
+        self.listener_cmd(name.as_ref(), false)
+    }
+
+    fn listener_cmd(&self, name: &str, pause: bool) -> impl Future<Output = bool> {
+        let (tx, rx) = oneshot::oneshot();
+        let _ = self
+            .0
+            .try_send(ServerCommand::PauseListener(name.to_string(), pause, tx));
+        async move { rx.await.unwrap_or(false) }
+    }
+
+    /// Get connection statistics
+    ///
+    /// Returns number of active connections, peak number of connections
+    /// and connection limits for server and for each listener.
+    pub fn stats(&self) -> ServerStats {
+        self.2.snapshot()
+    }
+
+    /// Subscribe to server shutdown phases
+    ///
+    /// Shutdown goes through `StopAccepting`, `Draining`, `ForceClose` and
+    /// `Stopped` phases. Services could use it for finishing in-flight work,
+    /// and health-check endpoints could report not-ready state before
+    /// connections get closed.
+    ///
+    /// ```rust,no_run
+    /// use std::sync::{atomic::AtomicBool, atomic::Ordering, Arc};
+    /// use ntex::server::{Server, ShutdownPhase};
+    /// use ntex::{fn_service, util::Ready};
+    ///
+    /// #[ntex::main]
+    /// async fn main() -> std::io::Result<()> {
+    ///     let srv = Server::build()
+    ///         .bind("echo", "0.0.0.0:8080", |_| {
+    ///             fn_service(|_| Ready::Ok::<_, ()>(()))
+    ///         })?
+    ///         .run();
+    ///
+    ///     let ready = Arc::new(AtomicBool::new(true));
+    ///     let subscriber = srv.on_shutdown();
+    ///     let r = ready.clone();
+    ///     ntex::rt::spawn(async move {
+    ///         if let Some(ShutdownPhase::StopAccepting) = subscriber.recv().await {
+    ///             r.store(false, Ordering::Relaxed);
+    ///         }
+    ///     });
+    ///     srv.await
+    /// }
+    /// ```
+    pub fn on_shutdown(&self) -> ShutdownSubscriber {
+        let (tx, rx) = async_channel::unbounded();
+        let _ = self.0.try_send(ServerCommand::Subscribe(tx));
+        ShutdownSubscriber::new(rx)
+    }
+
+    /// Change number of workers at runtime
+    ///
+    /// New workers are started or existing workers are gracefully
+    /// stopped. New connections are distributed across the new set
+    /// of workers, active connections of stopped workers are completed
+    /// within shutdown timeout.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num` is zero.
+    pub fn set_workers(&self, num: usize) -> impl Future<Output = ()> {
+        assert!(num > 0, "Number of workers must be greater than 0");
+
+        let (tx, rx) = oneshot::oneshot();
+        let _ = self.0.try_send(ServerCommand::SetWorkers(num, tx));
+        async move {
+            let _ = rx.await;
+        }
+    }
+
     /// Stop incoming connection processing, stop all workers and exit.
     ///
     /// If server starts with `spawn()` method, then spawned thread get terminated.
@@ -133,7 +279,7 @@ impl Server {
 
 impl Clone for Server {
     fn clone(&self) -> Self {
-        Self(self.0.clone(), None)
+        Self(self.0.clone(), None, self.2.clone())
     }
 }
 
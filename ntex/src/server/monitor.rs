@@ -0,0 +1,75 @@
+use std::sync::{Arc, Mutex};
+
+use crate::util::{HashMap, PoolId, PoolStats};
+
+/// Memory pools monitor
+///
+/// Monitor tracks memory pool used by each server listener. Handle is available
+/// via `ServerBuilder::pool_monitor()` and could be cloned and moved to other
+/// threads, for example to an admin endpoint.
+///
+/// Pool statistics are collected per memory pool, listeners that share same
+/// pool report same statistics. Use `ServerBuilder::memory_pool()` to assign
+/// dedicated pool to a listener.
+#[derive(Clone, Debug, Default)]
+pub struct PoolMonitor(Arc<Mutex<Inner>>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    assigned: HashMap<String, PoolId>,
+    active: HashMap<String, PoolId>,
+}
+
+impl PoolMonitor {
+    /// Memory pool used by listener
+    pub fn pool(&self, name: &str) -> Option<PoolId> {
+        let inner = self.0.lock().unwrap();
+        inner
+            .active
+            .get(name)
+            .or_else(|| inner.assigned.get(name))
+            .copied()
+    }
+
+    /// Memory pool statistics of listener
+    pub fn stats(&self, name: &str) -> Option<PoolStats> {
+        self.pool(name).map(|id| id.stats())
+    }
+
+    /// Memory pools and pool statistics of all started listeners
+    pub fn listeners(&self) -> Vec<(String, PoolId, PoolStats)> {
+        let mut listeners: Vec<_> = self
+            .0
+            .lock()
+            .unwrap()
+            .active
+            .iter()
+            .map(|(name, id)| (name.clone(), *id, id.stats()))
+            .collect();
+        listeners.sort_by(|a, b| a.0.cmp(&b.0));
+        listeners
+    }
+
+    pub(super) fn assign(&self, name: &str, id: PoolId) {
+        self.0.lock().unwrap().assigned.insert(name.to_string(), id);
+    }
+
+    pub(super) fn assigned(&self, name: &str) -> Option<PoolId> {
+        self.0.lock().unwrap().assigned.get(name).copied()
+    }
+
+    /// Memory pools used by started listeners
+    pub(super) fn pools(&self) -> Vec<PoolId> {
+        let mut pools: Vec<_> = Vec::new();
+        for id in self.0.lock().unwrap().active.values() {
+            if !pools.contains(id) {
+                pools.push(*id);
+            }
+        }
+        pools
+    }
+
+    pub(super) fn register(&self, name: &str, id: PoolId) {
+        self.0.lock().unwrap().active.insert(name.to_string(), id);
+    }
+}
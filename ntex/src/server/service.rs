@@ -8,12 +8,13 @@ use crate::service::{Service, ServiceFactory};
 use crate::util::{counter::CounterGuard, Pool, PoolId, Ready};
 use crate::{rt::spawn, time::Millis};
 
-use super::{socket::Stream, Config, Token};
+use super::{hooks::ConnectHooks, socket::Stream, stats::ConnectionGuard};
+use super::{Config, PoolMonitor, Token};
 
 /// Server message
 pub(super) enum ServerMessage {
     /// New stream
-    Connect(Stream),
+    Connect(Stream, ConnectionGuard, ConnectHooks),
     /// Gracefull shutdown in millis
     Shutdown(Millis),
     /// Force shutdown
@@ -87,7 +88,7 @@ where
 
     fn call(&self, (guard, req): (Option<CounterGuard>, ServerMessage)) -> Self::Future {
         match req {
-            ServerMessage::Connect(stream) => {
+            ServerMessage::Connect(stream, conn, hooks) => {
                 let stream = stream.try_into().map_err(|e| {
                     error!("Cannot convert to an async io stream: {}", e);
                 });
@@ -95,10 +96,15 @@ where
                 if let Ok(stream) = stream {
                     let stream: Io<_> = stream;
                     stream.set_memory_pool(self.pool.pool_ref());
+                    let disconnect = hooks.connected(stream.as_ref());
                     let f = self.service.call(stream);
                     spawn(async move {
-                        let _ = f.await;
+                        let res = f.await;
+                        if let Some(disconnect) = disconnect {
+                            disconnect.completed(res.is_ok());
+                        }
                         drop(guard);
+                        drop(conn);
                     });
                     Ready::Ok(())
                 } else {
@@ -115,6 +121,7 @@ pub(super) struct Factory<F: StreamServiceFactory> {
     inner: F,
     token: Token,
     addr: SocketAddr,
+    pools: PoolMonitor,
 }
 
 impl<F> Factory<F>
@@ -126,12 +133,14 @@ where
         token: Token,
         inner: F,
         addr: SocketAddr,
+        pools: PoolMonitor,
     ) -> Box<dyn InternalServiceFactory> {
         Box::new(Self {
             name,
             token,
             inner,
             addr,
+            pools,
         })
     }
 }
@@ -150,6 +159,7 @@ where
             inner: self.inner.clone(),
             token: self.token,
             addr: self.addr,
+            pools: self.pools.clone(),
         })
     }
 
@@ -157,8 +167,13 @@ where
         &self,
     ) -> Pin<Box<dyn Future<Output = Result<Vec<(Token, BoxedServerService)>, ()>>>> {
         let token = self.token;
-        let cfg = Config::default();
-        let fut = self.inner.create(cfg.clone()).new_service(());
+        let cfg = Config::new(&self.name, self.addr);
+        let factory = self.inner.create(cfg.clone());
+        if let Some(id) = self.pools.assigned(&self.name) {
+            cfg.memory_pool(id);
+        }
+        self.pools.register(&self.name, cfg.0.pool.get());
+        let fut = factory.new_service(());
 
         Box::pin(async move {
             match fut.await {
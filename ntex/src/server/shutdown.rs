@@ -0,0 +1,82 @@
+use std::{pin::Pin, task::Context, task::Poll};
+
+use async_channel::{Receiver, Sender};
+use futures_core::Stream;
+
+use crate::time::Millis;
+
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// Server shutdown phase
+pub enum ShutdownPhase {
+    /// Listeners are stopped, new connections are not accepted
+    StopAccepting,
+    /// Workers are waiting for in-flight connections, up to shutdown timeout
+    Draining(Millis),
+    /// Shutdown timeout is reached, remaining connections are closed
+    ForceClose,
+    /// All workers are stopped
+    Stopped,
+}
+
+/// Server shutdown phases subscriber
+///
+/// Subscriber receives every shutdown phase in order. If subscriber is
+/// created after shutdown is started it receives current phase first.
+#[derive(Debug)]
+pub struct ShutdownSubscriber(Receiver<ShutdownPhase>);
+
+impl ShutdownSubscriber {
+    pub(super) fn new(rx: Receiver<ShutdownPhase>) -> Self {
+        ShutdownSubscriber(rx)
+    }
+
+    /// Wait for next shutdown phase
+    ///
+    /// Returns `None` if server is stopped and all phases are received.
+    pub async fn recv(&self) -> Option<ShutdownPhase> {
+        self.0.recv().await.ok()
+    }
+}
+
+impl Stream for ShutdownSubscriber {
+    type Item = ShutdownPhase;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.0).poll_next(cx)
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct ShutdownState {
+    phase: Option<ShutdownPhase>,
+    subscribers: Vec<Sender<ShutdownPhase>>,
+}
+
+impl ShutdownState {
+    pub(super) fn subscribe(&mut self, tx: Sender<ShutdownPhase>) {
+        if let Some(phase) = self.phase {
+            let _ = tx.try_send(phase);
+            if phase == ShutdownPhase::Stopped {
+                return;
+            }
+        }
+        self.subscribers.push(tx);
+    }
+
+    pub(super) fn set_phase(&mut self, phase: ShutdownPhase) {
+        if self.phase == Some(ShutdownPhase::Stopped) {
+            return;
+        }
+        log::trace!("Server shutdown phase: {:?}", phase);
+
+        self.phase = Some(phase);
+        self.subscribers.retain(|tx| tx.try_send(phase).is_ok());
+        if phase == ShutdownPhase::Stopped {
+            self.subscribers.clear();
+        }
+    }
+}
@@ -1,9 +1,286 @@
-use std::{convert::TryFrom, fmt, io, net};
+use std::{convert::TryFrom, fmt, io, net, time::Duration};
 
-use crate::{io::Io, rt};
+use socket2::{Domain, SockAddr, SockRef, Socket, TcpKeepalive, Type};
+
+use crate::{io::Io, rt, time::Seconds};
+
+/// Listener socket configuration
+///
+/// Configuration is applied to listener socket and to accepted connections.
+///
+/// ```rust,no_run
+/// use ntex::server::{Server, SocketConfig};
+/// use ntex::{fn_service, time::Seconds, util::Ready};
+///
+/// #[ntex::main]
+/// async fn main() -> std::io::Result<()> {
+///     Server::build()
+///         .bind_with(
+///             "echo",
+///             "0.0.0.0:8080",
+///             SocketConfig::new()
+///                 .backlog(1024)
+///                 .reuse_port(true)
+///                 .keepalive(Some(Seconds(60))),
+///             |_| fn_service(|_| Ready::Ok::<_, ()>(())),
+///         )?
+///         .run()
+///         .await
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SocketConfig {
+    backlog: Option<i32>,
+    reuse_port: bool,
+    only_v6: Option<bool>,
+    bind_device: Option<String>,
+    stream: StreamConfig,
+}
+
+/// Accepted connection configuration
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct StreamConfig {
+    nodelay: bool,
+    keepalive: Option<Seconds>,
+    tos: Option<u32>,
+}
+
+impl Default for SocketConfig {
+    fn default() -> Self {
+        SocketConfig::new()
+    }
+}
+
+impl SocketConfig {
+    /// Create default socket configuration
+    pub fn new() -> Self {
+        SocketConfig {
+            backlog: None,
+            reuse_port: false,
+            only_v6: None,
+            bind_device: None,
+            stream: StreamConfig {
+                nodelay: true,
+                keepalive: None,
+                tos: None,
+            },
+        }
+    }
+
+    /// Set the maximum number of pending connections.
+    ///
+    /// By default server builder backlog is used.
+    pub fn backlog(mut self, num: i32) -> Self {
+        self.backlog = Some(num);
+        self
+    }
+
+    /// Set `SO_REUSEPORT` option on listener socket.
+    ///
+    /// Multiple processes could bind to the same address, kernel distributes
+    /// incoming connections between listeners. Option is ignored on
+    /// platforms that do not support it. By default option is not set.
+    pub fn reuse_port(mut self, enabled: bool) -> Self {
+        self.reuse_port = enabled;
+        self
+    }
+
+    /// Set `IPV6_V6ONLY` option on ipv6 listener socket.
+    ///
+    /// If option is set, ipv6 listener does not accept ipv4 connections and
+    /// could share port with ipv4 listener. By default platform default is used.
+    pub fn only_v6(mut self, enabled: bool) -> Self {
+        self.only_v6 = Some(enabled);
+        self
+    }
+
+    /// Bind listener socket to network interface, `SO_BINDTODEVICE` option.
+    ///
+    /// Option is supported on linux, android and fuchsia only.
+    pub fn bind_device<T: Into<String>>(mut self, interface: T) -> Self {
+        self.bind_device = Some(interface.into());
+        self
+    }
+
+    /// Set `TCP_NODELAY` option on accepted connections.
+    ///
+    /// By default option is set.
+    pub fn nodelay(mut self, enabled: bool) -> Self {
+        self.stream.nodelay = enabled;
+        self
+    }
+
+    /// Set tcp keep-alive idle time on accepted connections.
+    ///
+    /// By default tcp keep-alive is not enabled.
+    pub fn keepalive(mut self, time: Option<Seconds>) -> Self {
+        self.stream.keepalive = time;
+        self
+    }
+
+    /// Set `IP_TOS` option on listener socket and accepted connections.
+    pub fn tos(mut self, tos: u32) -> Self {
+        self.stream.tos = Some(tos);
+        self
+    }
+
+    pub(super) fn get_backlog(&self) -> Option<i32> {
+        self.backlog
+    }
+
+    pub(crate) fn create_tcp_listener(
+        &self,
+        addr: net::SocketAddr,
+        backlog: i32,
+    ) -> io::Result<net::TcpListener> {
+        let builder = match addr {
+            net::SocketAddr::V4(_) => Socket::new(Domain::IPV4, Type::STREAM, None)?,
+            net::SocketAddr::V6(_) => Socket::new(Domain::IPV6, Type::STREAM, None)?,
+        };
+
+        // On Windows, this allows rebinding sockets which are actively in use,
+        // which allows “socket hijacking”, so we explicitly don't set it here.
+        // https://docs.microsoft.com/en-us/windows/win32/winsock/using-so-reuseaddr-and-so-exclusiveaddruse
+        #[cfg(not(windows))]
+        builder.set_reuse_address(true)?;
+
+        #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+        if self.reuse_port {
+            builder.set_reuse_port(true)?;
+        }
+
+        if let (net::SocketAddr::V6(_), Some(only_v6)) = (addr, self.only_v6) {
+            builder.set_only_v6(only_v6)?;
+        }
+
+        if let Some(ref _device) = self.bind_device {
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            builder.bind_device(Some(_device.as_bytes()))?;
+
+            #[cfg(not(any(
+                target_os = "android",
+                target_os = "fuchsia",
+                target_os = "linux"
+            )))]
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Bind device is not supported",
+            ));
+        }
+
+        if let Some(tos) = self.stream.tos {
+            set_tos(&builder, tos)?;
+        }
+
+        builder.bind(&SockAddr::from(addr))?;
+        builder.listen(backlog)?;
+        Ok(net::TcpListener::from(builder))
+    }
+
+    pub(super) fn stream_config(&self) -> StreamConfig {
+        self.stream
+    }
+}
+
+impl StreamConfig {
+    fn apply(&self, stream: &net::TcpStream) -> io::Result<()> {
+        let sock = SockRef::from(stream);
+        if let Some(time) = self.keepalive {
+            sock.set_tcp_keepalive(&TcpKeepalive::new().with_time(time.into()))?;
+        }
+        if let Some(tos) = self.tos {
+            set_tos(&sock, tos)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error for multi-address bind
+///
+/// Contains every address that could not be resolved or bound.
+/// Error is available via `io::Error::get_ref()`.
+#[derive(Debug)]
+pub struct BindError {
+    failed: Vec<(String, io::Error)>,
+}
+
+impl BindError {
+    pub(super) fn new(failed: Vec<(String, io::Error)>) -> Self {
+        BindError { failed }
+    }
+
+    /// Addresses which failed, with corresponding errors
+    pub fn failed(&self) -> &[(String, io::Error)] {
+        &self.failed
+    }
+
+    pub(super) fn into_io_error(self) -> io::Error {
+        let kind = self
+            .failed
+            .first()
+            .map(|(_, e)| e.kind())
+            .unwrap_or(io::ErrorKind::InvalidInput);
+        io::Error::new(kind, self)
+    }
+}
+
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.failed.is_empty() {
+            return write!(f, "No addresses to bind to");
+        }
+        write!(f, "Cannot bind to: ")?;
+        for (idx, (addr, err)) in self.failed.iter().enumerate() {
+            if idx != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} ({})", addr, err)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BindError {}
+
+#[cfg(not(any(
+    target_os = "fuchsia",
+    target_os = "redox",
+    target_os = "solaris",
+    target_os = "illumos"
+)))]
+fn set_tos(sock: &Socket, tos: u32) -> io::Result<()> {
+    sock.set_tos(tos)
+}
+
+#[cfg(any(
+    target_os = "fuchsia",
+    target_os = "redox",
+    target_os = "solaris",
+    target_os = "illumos"
+))]
+fn set_tos(_: &Socket, _: u32) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(super) fn create_abstract_uds_listener(
+    name: &[u8],
+    backlog: i32,
+) -> io::Result<std::os::unix::net::UnixListener> {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let mut path = Vec::with_capacity(name.len() + 1);
+    path.push(0);
+    path.extend_from_slice(name);
+
+    let socket = Socket::new(Domain::UNIX, Type::STREAM, None)?;
+    socket.bind(&SockAddr::unix(OsStr::from_bytes(&path))?)?;
+    socket.listen(backlog)?;
+    Ok(socket.into())
+}
 
 pub(crate) enum Listener {
-    Tcp(net::TcpListener),
+    Tcp(net::TcpListener, StreamConfig),
     #[cfg(unix)]
     Uds(std::os::unix::net::UnixListener),
 }
@@ -11,7 +288,7 @@ pub(crate) enum Listener {
 impl fmt::Debug for Listener {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
-            Listener::Tcp(ref lst) => write!(f, "{:?}", lst),
+            Listener::Tcp(ref lst, _) => write!(f, "{:?}", lst),
             #[cfg(unix)]
             Listener::Uds(ref lst) => write!(f, "{:?}", lst),
         }
@@ -21,7 +298,7 @@ impl fmt::Debug for Listener {
 impl fmt::Display for Listener {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
-            Listener::Tcp(ref lst) => write!(f, "{}", lst.local_addr().ok().unwrap()),
+            Listener::Tcp(ref lst, _) => write!(f, "{}", lst.local_addr().ok().unwrap()),
             #[cfg(unix)]
             Listener::Uds(ref lst) => {
                 write!(f, "{:?}", lst.local_addr().ok().unwrap())
@@ -58,8 +335,12 @@ impl fmt::Debug for SocketAddr {
 
 impl Listener {
     pub(super) fn from_tcp(lst: net::TcpListener) -> Self {
+        Listener::from_tcp_with(lst, SocketConfig::new().stream_config())
+    }
+
+    pub(super) fn from_tcp_with(lst: net::TcpListener, cfg: StreamConfig) -> Self {
         let _ = lst.set_nonblocking(true);
-        Listener::Tcp(lst)
+        Listener::Tcp(lst, cfg)
     }
 
     #[cfg(unix)]
@@ -70,7 +351,7 @@ impl Listener {
 
     pub(crate) fn local_addr(&self) -> SocketAddr {
         match self {
-            Listener::Tcp(lst) => SocketAddr::Tcp(lst.local_addr().unwrap()),
+            Listener::Tcp(lst, _) => SocketAddr::Tcp(lst.local_addr().unwrap()),
             #[cfg(unix)]
             Listener::Uds(lst) => SocketAddr::Uds(lst.local_addr().unwrap()),
         }
@@ -78,8 +359,12 @@ impl Listener {
 
     pub(crate) fn accept(&self) -> io::Result<Option<Stream>> {
         match *self {
-            Listener::Tcp(ref lst) => {
-                lst.accept().map(|(stream, _)| Some(Stream::Tcp(stream)))
+            Listener::Tcp(ref lst, ref cfg) => {
+                let (stream, _) = lst.accept()?;
+                if let Err(e) = cfg.apply(&stream) {
+                    log::error!("Cannot set socket options: {}", e);
+                }
+                Ok(Some(Stream::Tcp(stream, cfg.nodelay)))
             }
             #[cfg(unix)]
             Listener::Uds(ref lst) => {
@@ -90,7 +375,7 @@ impl Listener {
 
     pub(crate) fn remove_source(&self) {
         match *self {
-            Listener::Tcp(_) => (),
+            Listener::Tcp(..) => (),
             #[cfg(unix)]
             Listener::Uds(ref lst) => {
                 // cleanup file path
@@ -112,7 +397,7 @@ mod listener_impl {
     impl AsRawFd for Listener {
         fn as_raw_fd(&self) -> RawFd {
             match *self {
-                Listener::Tcp(ref lst) => lst.as_raw_fd(),
+                Listener::Tcp(ref lst, _) => lst.as_raw_fd(),
                 Listener::Uds(ref lst) => lst.as_raw_fd(),
             }
         }
@@ -127,7 +412,7 @@ mod listener_impl {
     impl AsRawSocket for Listener {
         fn as_raw_socket(&self) -> RawSocket {
             match *self {
-                Listener::Tcp(ref lst) => lst.as_raw_socket(),
+                Listener::Tcp(ref lst, _) => lst.as_raw_socket(),
             }
         }
     }
@@ -135,17 +420,44 @@ mod listener_impl {
 
 #[derive(Debug)]
 pub enum Stream {
-    Tcp(net::TcpStream),
+    Tcp(net::TcpStream, bool),
     #[cfg(unix)]
     Uds(std::os::unix::net::UnixStream),
 }
 
+impl Stream {
+    /// Peer ip address
+    pub(super) fn peer_ip(&self) -> Option<net::IpAddr> {
+        if let Stream::Tcp(ref stream, _) = self {
+            stream.peer_addr().ok().map(|addr| addr.ip())
+        } else {
+            None
+        }
+    }
+
+    /// Drop connection without graceful close
+    pub(super) fn reset(self) {
+        if let Stream::Tcp(ref stream, _) = self {
+            if let Err(e) = SockRef::from(stream).set_linger(Some(Duration::ZERO)) {
+                log::trace!("Cannot set SO_LINGER: {}", e);
+            }
+        }
+    }
+}
+
 impl TryFrom<Stream> for Io {
     type Error = io::Error;
 
     fn try_from(sock: Stream) -> Result<Self, Self::Error> {
         match sock {
-            Stream::Tcp(stream) => rt::from_tcp_stream(stream),
+            Stream::Tcp(stream, true) => rt::from_tcp_stream(stream),
+            Stream::Tcp(stream, false) => {
+                // runtime enables nodelay for all tcp streams
+                let sock = stream.try_clone()?;
+                let io = rt::from_tcp_stream(stream)?;
+                sock.set_nodelay(false)?;
+                Ok(io)
+            }
             #[cfg(unix)]
             Stream::Uds(stream) => rt::from_unix_stream(stream),
         }
@@ -158,8 +470,6 @@ mod tests {
 
     #[test]
     fn socket_addr() {
-        use socket2::{Domain, SockAddr, Socket, Type};
-
         let addr = SocketAddr::Tcp("127.0.0.1:8080".parse().unwrap());
         assert!(format!("{:?}", addr).contains("127.0.0.1:8080"));
         assert_eq!(format!("{}", addr), "127.0.0.1:8080");
@@ -168,11 +478,48 @@ mod tests {
         let socket = Socket::new(Domain::IPV4, Type::STREAM, None).unwrap();
         socket.set_reuse_address(true).unwrap();
         socket.bind(&SockAddr::from(addr)).unwrap();
-        let lst = Listener::Tcp(net::TcpListener::from(socket));
+        let lst = Listener::from_tcp(net::TcpListener::from(socket));
         assert!(format!("{:?}", lst).contains("TcpListener"));
         assert!(format!("{}", lst).contains("127.0.0.1"));
     }
 
+    #[test]
+    fn socket_config() {
+        let cfg = SocketConfig::new()
+            .nodelay(false)
+            .keepalive(Some(Seconds(30)))
+            .reuse_port(true);
+        let lst = cfg
+            .create_tcp_listener("127.0.0.1:0".parse().unwrap(), 16)
+            .unwrap();
+        let addr = lst.local_addr().unwrap();
+        #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+        assert!(SockRef::from(&lst).reuse_port().unwrap());
+
+        let lst = Listener::from_tcp_with(lst, cfg.stream_config());
+        let _client = net::TcpStream::connect(addr).unwrap();
+        let stream = loop {
+            match lst.accept() {
+                Ok(Some(stream)) => break stream,
+                Ok(None) => (),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => (),
+                Err(e) => panic!("{:?}", e),
+            }
+        };
+        match stream {
+            Stream::Tcp(ref stream, nodelay) => {
+                assert!(!nodelay);
+                let sock = SockRef::from(stream);
+                assert!(sock.keepalive().unwrap());
+                assert_eq!(
+                    sock.keepalive_time().unwrap(),
+                    std::time::Duration::from_secs(30)
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
     #[test]
     #[cfg(all(unix))]
     fn uds() {
@@ -190,4 +537,26 @@ mod tests {
             assert!(format!("{}", lst).contains("/tmp/sock.xxxxx"));
         }
     }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn uds_abstract() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let lst = create_abstract_uds_listener(b"ntex-uds-abstract-test", 16).unwrap();
+        let addr = lst.local_addr().unwrap();
+        assert!(addr.as_pathname().is_none());
+
+        let client = Socket::new(Domain::UNIX, Type::STREAM, None).unwrap();
+        client
+            .connect(
+                &SockAddr::unix(OsStr::from_bytes(b"\0ntex-uds-abstract-test")).unwrap(),
+            )
+            .unwrap();
+
+        // connection is queued by the time connect() returns
+        let lst = Listener::from_uds(lst);
+        assert!(matches!(lst.accept(), Ok(Some(Stream::Uds(_)))));
+        lst.remove_source();
+    }
 }
@@ -0,0 +1,245 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::accept::{AcceptNotify, Command};
+use super::iplimit::IpGuard;
+use super::Token;
+
+/// Server connection statistics
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ServerStats {
+    /// Number of active connections
+    pub connections: usize,
+    /// Max number of active connections
+    pub peak: usize,
+    /// Total number of accepted connections
+    pub total: usize,
+    /// Connection limit, 0 if unlimited
+    pub max_connections: usize,
+    /// Number of times connection limit paused accepting
+    pub throttled: usize,
+    /// Number of connections rejected by accept rate or per-ip limits
+    pub rejected: usize,
+    /// Statistics per listener
+    pub listeners: Vec<ListenerStats>,
+}
+
+/// Listener connection statistics
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ListenerStats {
+    /// Listener name
+    pub name: String,
+    /// Number of active connections
+    pub connections: usize,
+    /// Max number of active connections
+    pub peak: usize,
+    /// Total number of accepted connections
+    pub total: usize,
+    /// Connection limit, 0 if unlimited
+    pub max_connections: usize,
+    /// Number of times connection limit paused accepting
+    pub throttled: usize,
+    /// Number of connections rejected by accept rate or per-ip limits
+    pub rejected: usize,
+    /// Listener is paused with `Server::pause_listener()`
+    pub paused: bool,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Counters {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+    total: AtomicUsize,
+    limit: AtomicUsize,
+    throttled: AtomicUsize,
+    rejected: AtomicUsize,
+    paused: AtomicBool,
+}
+
+impl Counters {
+    fn with_limit(limit: usize) -> Self {
+        let counters = Counters::default();
+        counters.limit.store(limit, Ordering::Relaxed);
+        counters
+    }
+
+    /// Check if connection limit is reached
+    pub(super) fn is_full(&self) -> bool {
+        let limit = self.limit.load(Ordering::Relaxed);
+        limit != 0 && self.current.load(Ordering::Acquire) >= limit
+    }
+
+    pub(super) fn throttled(&self) {
+        self.throttled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    fn acquire(&self) {
+        let current = self.current.fetch_add(1, Ordering::AcqRel) + 1;
+        self.peak.fetch_max(current, Ordering::Relaxed);
+        self.total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns true if limit was reached before release
+    fn release(&self) -> bool {
+        let limit = self.limit.load(Ordering::Relaxed);
+        let prev = self.current.fetch_sub(1, Ordering::AcqRel);
+        limit != 0 && prev >= limit
+    }
+}
+
+/// Connection statistics shared between accept loop, workers
+/// and server controller
+#[derive(Clone, Debug, Default)]
+pub(super) struct Stats(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    global: Counters,
+    listeners: Mutex<Vec<(Token, String, Arc<Counters>)>>,
+}
+
+impl Stats {
+    pub(super) fn set_max_connections(&self, limit: usize) {
+        self.0.global.limit.store(limit, Ordering::Relaxed);
+    }
+
+    pub(super) fn register(&self, token: Token, name: &str, limit: usize) {
+        self.0.listeners.lock().unwrap().push((
+            token,
+            name.to_string(),
+            Arc::new(Counters::with_limit(limit)),
+        ));
+    }
+
+    pub(super) fn global(&self) -> &Counters {
+        &self.0.global
+    }
+
+    pub(super) fn listener(&self, token: Token) -> Arc<Counters> {
+        self.0
+            .listeners
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|item| item.0 == token)
+            .map(|item| item.2.clone())
+            .unwrap_or_default()
+    }
+
+    /// Tokens of listeners with specified name
+    pub(super) fn tokens(&self, name: &str) -> Vec<Token> {
+        self.0
+            .listeners
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|item| item.1 == name)
+            .map(|item| item.0)
+            .collect()
+    }
+
+    /// Register new connection
+    pub(super) fn connect(
+        &self,
+        listener: Arc<Counters>,
+        notify: AcceptNotify,
+    ) -> ConnectionGuard {
+        self.0.global.acquire();
+        listener.acquire();
+        ConnectionGuard {
+            listener,
+            notify,
+            ip: None,
+            stats: self.clone(),
+        }
+    }
+
+    pub(super) fn snapshot(&self) -> ServerStats {
+        let global = &self.0.global;
+        ServerStats {
+            connections: global.current.load(Ordering::Relaxed),
+            peak: global.peak.load(Ordering::Relaxed),
+            total: global.total.load(Ordering::Relaxed),
+            max_connections: global.limit.load(Ordering::Relaxed),
+            throttled: global.throttled.load(Ordering::Relaxed),
+            rejected: global.rejected.load(Ordering::Relaxed),
+            listeners: self
+                .0
+                .listeners
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, name, counters)| ListenerStats {
+                    name: name.clone(),
+                    connections: counters.current.load(Ordering::Relaxed),
+                    peak: counters.peak.load(Ordering::Relaxed),
+                    total: counters.total.load(Ordering::Relaxed),
+                    max_connections: counters.limit.load(Ordering::Relaxed),
+                    throttled: counters.throttled.load(Ordering::Relaxed),
+                    rejected: counters.rejected.load(Ordering::Relaxed),
+                    paused: counters.paused.load(Ordering::Relaxed),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Active connection guard
+///
+/// Accept loop is notified if connection limit is not reached anymore.
+#[derive(Debug)]
+pub(super) struct ConnectionGuard {
+    stats: Stats,
+    listener: Arc<Counters>,
+    notify: AcceptNotify,
+    ip: Option<IpGuard>,
+}
+
+impl ConnectionGuard {
+    /// Release per-ip connection together with connection
+    pub(super) fn set_ip_guard(&mut self, guard: IpGuard) {
+        self.ip = Some(guard);
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let full = self.listener.release();
+        let global_full = self.stats.0.global.release();
+        if full || global_full {
+            self.notify.send(Command::ConnectionReleased);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters() {
+        let counters = Counters::with_limit(2);
+        assert!(!counters.is_full());
+        counters.acquire();
+        counters.acquire();
+        assert!(counters.is_full());
+        assert!(counters.release());
+        assert!(!counters.is_full());
+        assert!(!counters.release());
+        assert_eq!(counters.peak.load(Ordering::Relaxed), 2);
+        assert_eq!(counters.total.load(Ordering::Relaxed), 2);
+
+        let counters = Counters::default();
+        counters.acquire();
+        assert!(!counters.is_full());
+        assert!(!counters.release());
+    }
+}
@@ -0,0 +1,119 @@
+//! Socket activation support
+use std::os::unix::io::{FromRawFd, OwnedFd, RawFd};
+use std::{env, io, process};
+
+use socket2::SockRef;
+
+/// First file descriptor passed by service manager
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Collect listeners passed by service manager.
+///
+/// Returns names and file descriptors of inherited listeners defined
+/// by `LISTEN_PID`, `LISTEN_FDS` and `LISTEN_FDNAMES` environment variables.
+/// Listeners without name are named `unknown`. Variables are removed
+/// from environment, so child processes do not inherit them. Returns
+/// empty list if process is not socket activated.
+///
+/// # Safety
+///
+/// Function takes ownership of inherited file descriptors. Caller must ensure
+/// that descriptors referred by `LISTEN_FDS` are not owned, used or closed
+/// by any other code in the process.
+///
+/// ```rust,no_run
+/// use ntex::server::{listen_from_env, Server};
+/// use ntex::{fn_service, util::Ready};
+///
+/// #[ntex::main]
+/// async fn main() -> std::io::Result<()> {
+///     let mut builder = Server::build();
+///     for (name, fd) in unsafe { listen_from_env()? } {
+///         builder = builder.bind_fd(name, fd, |_| {
+///             fn_service(|_| Ready::Ok::<_, ()>(()))
+///         })?;
+///     }
+///     builder.run().await
+/// }
+/// ```
+pub unsafe fn listen_from_env() -> io::Result<Vec<(String, OwnedFd)>> {
+    let result = parse_env(
+        process::id(),
+        env::var("LISTEN_PID").ok(),
+        env::var("LISTEN_FDS").ok(),
+        env::var("LISTEN_FDNAMES").ok(),
+    );
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    let fds: Vec<_> = result?
+        .into_iter()
+        .map(|(name, fd)| (name, OwnedFd::from_raw_fd(fd)))
+        .collect();
+    for (_, fd) in &fds {
+        // do not leak listeners to child processes
+        SockRef::from(fd).set_cloexec(true)?;
+    }
+    Ok(fds)
+}
+
+fn parse_env(
+    pid: u32,
+    listen_pid: Option<String>,
+    listen_fds: Option<String>,
+    names: Option<String>,
+) -> io::Result<Vec<(String, RawFd)>> {
+    match listen_pid {
+        Some(listen_pid) if listen_pid.trim().parse::<u32>().ok() == Some(pid) => (),
+        _ => return Ok(Vec::new()),
+    }
+    let num = if let Some(num) = listen_fds {
+        num.trim().parse::<RawFd>().map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Cannot parse LISTEN_FDS")
+        })?
+    } else {
+        return Ok(Vec::new());
+    };
+
+    let names: Vec<String> = match names {
+        Some(names) => names.split(':').map(|s| s.to_string()).collect(),
+        None => (0..num).map(|_| "unknown".to_string()).collect(),
+    };
+    if names.len() != num as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "LISTEN_FDNAMES does not match LISTEN_FDS",
+        ));
+    }
+
+    Ok(names
+        .into_iter()
+        .zip(LISTEN_FDS_START..LISTEN_FDS_START + num)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env() {
+        let s = |v: &str| Some(v.to_string());
+
+        assert!(parse_env(10, None, s("1"), None).unwrap().is_empty());
+        assert!(parse_env(10, s("11"), s("1"), None).unwrap().is_empty());
+        assert!(parse_env(10, s("10"), None, None).unwrap().is_empty());
+        assert!(parse_env(10, s("10"), s("x"), None).is_err());
+        assert!(parse_env(10, s("10"), s("2"), s("http")).is_err());
+
+        assert_eq!(
+            parse_env(10, s("10"), s("2"), None).unwrap(),
+            vec![("unknown".to_string(), 3), ("unknown".to_string(), 4)]
+        );
+        assert_eq!(
+            parse_env(10, s("10"), s("2"), s("http:https")).unwrap(),
+            vec![("http".to_string(), 3), ("https".to_string(), 4)]
+        );
+    }
+}
@@ -11,8 +11,8 @@ use crate::util::{counter::Counter, join_all};
 
 use super::accept::{AcceptNotify, Command};
 use super::service::{BoxedServerService, InternalServiceFactory, ServerMessage};
-use super::socket::Stream;
-use super::Token;
+use super::{hooks::ConnectHooks, socket::Stream, stats::ConnectionGuard};
+use super::{PoolMonitor, Token};
 
 #[derive(Debug)]
 pub(super) struct WorkerCommand(Connection);
@@ -29,9 +29,11 @@ pub(super) struct StopCommand {
 pub(super) struct Connection {
     pub(super) io: Stream,
     pub(super) token: Token,
+    pub(super) guard: ConnectionGuard,
 }
 
 const STOP_TIMEOUT: Millis = Millis::ONE_SEC;
+const MEMORY_CHECK_INTERVAL: Millis = Millis(100);
 static MAX_CONNS: AtomicUsize = AtomicUsize::new(25600);
 
 /// Sets the maximum per-worker number of concurrent connections.
@@ -119,6 +121,32 @@ impl WorkerAvailability {
     }
 }
 
+/// Memory pools pressure limit
+///
+/// Worker stops accepting new connections if size of memory allocated
+/// by its memory pools reaches threshold, and resumes when allocated
+/// size drops below 90% of threshold.
+#[derive(Debug, Clone)]
+pub(super) struct MemoryLimit {
+    threshold: usize,
+    pools: PoolMonitor,
+}
+
+impl MemoryLimit {
+    pub(super) fn new(threshold: usize, pools: PoolMonitor) -> Self {
+        MemoryLimit { threshold, pools }
+    }
+
+    /// Size of allocated memory in current thread's pools
+    fn allocated(&self) -> usize {
+        self.pools
+            .pools()
+            .into_iter()
+            .map(|id| id.pool_ref().allocated())
+            .sum()
+    }
+}
+
 /// Service worker
 ///
 /// Worker accepts Socket objects via unbounded channel and starts stream
@@ -132,6 +160,9 @@ pub(super) struct Worker {
     factories: Vec<Box<dyn InternalServiceFactory>>,
     state: WorkerState,
     shutdown_timeout: Millis,
+    memory: Option<MemoryLimit>,
+    memory_check: Option<Sleep>,
+    hooks: ConnectHooks,
 }
 
 struct WorkerService {
@@ -163,6 +194,8 @@ impl Worker {
         factories: Vec<Box<dyn InternalServiceFactory>>,
         availability: WorkerAvailability,
         shutdown_timeout: Millis,
+        memory: Option<MemoryLimit>,
+        hooks: ConnectHooks,
     ) -> WorkerClient {
         let (tx1, rx1) = unbounded();
         let (tx2, rx2) = unbounded();
@@ -170,8 +203,16 @@ impl Worker {
 
         Arbiter::default().exec_fn(move || {
             let _ = spawn(async move {
-                match Worker::create(rx1, rx2, factories, availability, shutdown_timeout)
-                    .await
+                match Worker::create(
+                    rx1,
+                    rx2,
+                    factories,
+                    availability,
+                    shutdown_timeout,
+                    memory,
+                    hooks,
+                )
+                .await
                 {
                     Ok(wrk) => {
                         let _ = spawn(wrk);
@@ -193,6 +234,8 @@ impl Worker {
         factories: Vec<Box<dyn InternalServiceFactory>>,
         availability: WorkerAvailability,
         shutdown_timeout: Millis,
+        memory: Option<MemoryLimit>,
+        hooks: ConnectHooks,
     ) -> Result<Worker, ()> {
         availability.set(false);
         let mut wrk = MAX_CONNS_COUNTER.with(move |conns| Worker {
@@ -201,6 +244,9 @@ impl Worker {
             availability,
             factories,
             shutdown_timeout,
+            memory,
+            memory_check: None,
+            hooks,
             services: Vec::new(),
             conns: conns.priv_clone(),
             state: WorkerState::Unavailable,
@@ -265,8 +311,49 @@ impl Worker {
         }
     }
 
+    /// Check memory pools pressure, returns `false` if threshold is reached
+    fn check_memory(&mut self, cx: &mut Context<'_>) -> bool {
+        let limit = if let Some(ref limit) = self.memory {
+            limit
+        } else {
+            return true;
+        };
+
+        let allocated = limit.allocated();
+        if self.memory_check.is_some() {
+            if allocated < limit.threshold - limit.threshold / 10 {
+                info!(
+                    "Memory pools pressure is released ({} bytes), resume accepting",
+                    allocated
+                );
+                self.memory_check = None;
+                return true;
+            }
+        } else if allocated >= limit.threshold {
+            warn!(
+                "Memory pools threshold is reached ({} bytes), pause accepting",
+                allocated
+            );
+            self.memory_check = Some(sleep(MEMORY_CHECK_INTERVAL));
+        } else {
+            return true;
+        }
+
+        // re-check pressure after interval
+        if let Some(ref mut timer) = self.memory_check {
+            if timer.poll_elapsed(cx).is_ready() {
+                *timer = sleep(MEMORY_CHECK_INTERVAL);
+                let _ = timer.poll_elapsed(cx);
+            }
+        }
+        false
+    }
+
     fn check_readiness(&mut self, cx: &mut Context<'_>) -> Result<bool, (Token, usize)> {
         let mut ready = self.conns.available(cx);
+        if !self.check_memory(cx) {
+            ready = false;
+        }
         let mut failed = None;
         for (idx, srv) in &mut self.services.iter_mut().enumerate() {
             if srv.status == WorkerServiceStatus::Available
@@ -478,9 +565,17 @@ impl Future for Worker {
                                     self.factories[srv.factory].name(msg.token)
                                 );
                             }
-                            let _ = srv
-                                .service
-                                .call((Some(guard), ServerMessage::Connect(msg.io)));
+                            let mut fut = srv.service.call((
+                                Some(guard),
+                                ServerMessage::Connect(
+                                    msg.io,
+                                    msg.guard,
+                                    self.hooks.clone(),
+                                ),
+                            ));
+                            // connection is processed in spawned task,
+                            // call result is ready immediately
+                            let _ = Pin::new(&mut fut).poll(cx);
                         }
                         Poll::Pending => return Poll::Pending,
                         Poll::Ready(None) => return Poll::Ready(()),
@@ -497,9 +592,9 @@ mod tests {
 
     use super::*;
     use crate::io::Io;
-    use crate::server::service::Factory;
+    use crate::server::{service::Factory, PoolMonitor};
     use crate::service::{Service, ServiceFactory};
-    use crate::util::{lazy, Ready};
+    use crate::util::{lazy, PoolId, Ready};
 
     #[derive(Clone, Copy, Debug)]
     enum St {
@@ -590,9 +685,12 @@ mod tests {
                 Token(0),
                 move |_| f.clone(),
                 "127.0.0.1:8080".parse().unwrap(),
+                PoolMonitor::default(),
             )],
             avail.clone(),
             Millis(5_000),
+            None,
+            ConnectHooks::default(),
         )
         .await
         .unwrap();
@@ -662,9 +760,12 @@ mod tests {
                 Token(0),
                 move |_| f.clone(),
                 "127.0.0.1:8080".parse().unwrap(),
+                PoolMonitor::default(),
             )],
             avail.clone(),
             Millis(5_000),
+            None,
+            ConnectHooks::default(),
         )
         .await
         .unwrap();
@@ -686,4 +787,52 @@ mod tests {
         assert!(lazy(|cx| Pin::new(&mut worker).poll(cx)).await.is_ready());
         let _ = rx.await;
     }
+
+    #[crate::rt_test]
+    async fn memory_pressure() {
+        let (_tx1, rx1) = unbounded();
+        let (_tx2, rx2) = unbounded();
+        let (sync_tx, _sync_rx) = std::sync::mpsc::channel();
+        let poll = Arc::new(polling::Poller::new().unwrap());
+        let avail = WorkerAvailability::new(AcceptNotify::new(poll, sync_tx));
+
+        let f = SrvFactory {
+            st: Arc::new(Mutex::new(St::Ready)),
+            counter: Arc::new(Mutex::new(0)),
+        };
+        let pools = PoolMonitor::default();
+        pools.assign("test", PoolId::P11);
+
+        let mut worker = Worker::create(
+            rx1,
+            rx2,
+            vec![Factory::create(
+                "test".to_string(),
+                Token(0),
+                move |_| f.clone(),
+                "127.0.0.1:8080".parse().unwrap(),
+                pools.clone(),
+            )],
+            avail.clone(),
+            Millis(5_000),
+            Some(MemoryLimit::new(1024, pools)),
+            ConnectHooks::default(),
+        )
+        .await
+        .unwrap();
+
+        let _ = lazy(|cx| Pin::new(&mut worker).poll(cx)).await;
+        assert!(avail.available());
+
+        // threshold is reached
+        let buf = PoolId::P11.pool_ref().buf_with_capacity(2048);
+        let _ = lazy(|cx| Pin::new(&mut worker).poll(cx)).await;
+        assert!(!avail.available());
+
+        // worker re-checks pressure after interval
+        drop(buf);
+        sleep(Millis(250)).await;
+        let _ = lazy(|cx| Pin::new(&mut worker).poll(cx)).await;
+        assert!(avail.available());
+    }
 }
@@ -28,6 +28,11 @@ impl Extensions {
         self.map.contains_key(&TypeId::of::<T>())
     }
 
+    /// Check if container contains entry of specified type
+    pub(crate) fn contains_type(&self, id: &TypeId) -> bool {
+        self.map.contains_key(id)
+    }
+
     /// Get a reference to a type previously inserted on this `Extensions`.
     pub fn get<T: 'static>(&self) -> Option<&T> {
         self.map
@@ -10,7 +10,9 @@ pub mod variant;
 
 pub use self::extensions::Extensions;
 
-pub use ntex_bytes::{Buf, BufMut, ByteString, Bytes, BytesMut, Pool, PoolId, PoolRef};
+pub use ntex_bytes::{
+    Buf, BufMut, ByteString, Bytes, BytesChain, BytesMut, Pool, PoolId, PoolRef, PoolStats,
+};
 pub use ntex_util::{future::*, ready};
 
 pub type HashMap<K, V> = std::collections::HashMap<K, V, fxhash::FxBuildHasher>;
@@ -657,23 +657,60 @@ mod tests {
     }
 
     #[crate::rt_test]
-    async fn test_data_factory() {
+    async fn test_routes_info() {
         let srv = init_service(
             App::new()
-                .data_factory(|| async { Ok::<_, ()>(10usize) })
                 .service(
-                    web::resource("/")
-                        .to(|_: web::types::Data<usize>| async { HttpResponse::Ok() }),
+                    web::resource("/routes").name("routes").route(
+                        web::get()
+                            .name("list_routes")
+                            .tag("admin")
+                            .description("List routes")
+                            .auth("bearer")
+                            .to(|req: HttpRequest| async move {
+                                let routes = req.resource_map().routes();
+                                assert_eq!(routes[0].resource(), Some("routes"));
+                                let meta = routes[0].meta();
+                                assert_eq!(meta.name(), Some("list_routes"));
+                                assert_eq!(meta.tags(), &["admin".to_string()]);
+                                assert_eq!(meta.description(), Some("List routes"));
+                                assert_eq!(meta.auth(), &["bearer".to_string()]);
+                                assert_eq!(routes[1].resource(), None);
+                                assert_eq!(routes[1].meta(), &Default::default());
+
+                                let body: Vec<_> = routes
+                                    .iter()
+                                    .map(|r| format!("{:?} {}", r.methods(), r.path()))
+                                    .collect();
+                                HttpResponse::Ok().body(body.join("\n"))
+                            }),
+                    ),
+                )
+                .service(
+                    web::scope("/app").service(
+                        web::resource("/user/{id}")
+                            .route(web::get().to(|| async { HttpResponse::Ok() }))
+                            .route(web::post().to(|| async { HttpResponse::Ok() })),
+                    ),
                 ),
         )
         .await;
-        let req = TestRequest::default().to_request();
-        let resp = srv.call(req).await.unwrap();
-        assert_eq!(resp.status(), StatusCode::OK);
 
+        let req = TestRequest::with_uri("/routes").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(
+            read_body(resp).await,
+            Bytes::from_static(
+                b"[GET] /routes\n[GET] /app/user/{id}\n[POST] /app/user/{id}"
+            )
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_data_factory() {
         let srv = init_service(
             App::new()
-                .data_factory(|| async { Ok::<_, ()>(10u32) })
+                .data_factory(|| async { Ok::<_, ()>(10usize) })
                 .service(
                     web::resource("/")
                         .to(|_: web::types::Data<usize>| async { HttpResponse::Ok() }),
@@ -681,8 +718,19 @@ mod tests {
         )
         .await;
         let req = TestRequest::default().to_request();
-        let res = srv.call(req).await.unwrap();
-        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let srv = App::new()
+            .data_factory(|| async { Ok::<_, ()>(10u32) })
+            .service(
+                web::resource("/")
+                    .to(|_: web::types::Data<usize>| async { HttpResponse::Ok() }),
+            )
+            .into_factory()
+            .new_service(AppConfig::default())
+            .await;
+        assert!(srv.is_err());
     }
 
     #[crate::rt_test]
@@ -87,6 +87,7 @@ where
         std::mem::take(&mut *self.services.borrow_mut())
             .into_iter()
             .for_each(|mut srv| srv.register(&mut config));
+        let required_data = config.take_required_data();
         let (config, services) = config.into_services();
 
         // resource map
@@ -98,8 +99,8 @@ where
         // complete pipeline creation
         let services: Vec<_> = services
             .into_iter()
-            .map(|(mut rdef, srv, guards, nested)| {
-                rmap.add(&mut rdef, nested);
+            .map(|(mut rdef, srv, guards, nested, routes)| {
+                rmap.add_routes(&mut rdef, nested, routes);
                 (rdef, srv, RefCell::new(guards))
             })
             .collect();
@@ -154,6 +155,22 @@ where
                 }
             }
 
+            // check data required by routes
+            let mut missing = Vec::new();
+            for item in &required_data {
+                let container = item.container.as_deref().unwrap_or(&extensions);
+                for name in item.data.missing(container) {
+                    missing.push(format!("{}: {}", item.path, name));
+                }
+            }
+            if !missing.is_empty() {
+                log::error!(
+                    "Application data is not registered for routes:\n  {}",
+                    missing.join("\n  ")
+                );
+                return Err(());
+            }
+
             Ok(AppFactoryService {
                 rmap,
                 config,
@@ -84,6 +84,13 @@ pub enum DataExtractorError {
     NotConfigured,
 }
 
+/// Errors which can occur when request memory budget is exhausted
+#[derive(Debug, PartialEq, Display)]
+pub enum BudgetError {
+    #[display(fmt = "Request memory budget is exceeded")]
+    Exceeded,
+}
+
 /// Errors which can occur when attempting to generate resource uri.
 #[derive(Debug, PartialEq, Display, From)]
 pub enum UrlGenerationError {
@@ -143,6 +150,78 @@ pub enum JsonPayloadError {
     Payload(error::PayloadError),
 }
 
+/// A set of errors that can occur during parsing multipart payloads
+#[derive(Debug, Display)]
+pub enum MultipartError {
+    /// Content type error
+    #[display(fmt = "Content type error")]
+    ContentType,
+    /// Multipart boundary is not found
+    #[display(fmt = "Multipart boundary is not found")]
+    Boundary,
+    /// Part headers are malformed
+    #[display(fmt = "Multipart headers are malformed")]
+    Headers,
+    /// Content type of the part is not allowed
+    #[display(fmt = "Part content type is not allowed: {}", _0)]
+    PartContentType(String),
+    /// Part size is bigger than allowed
+    #[display(fmt = "Part size is bigger than allowed ({} bytes)", limit)]
+    FieldOverflow { limit: usize },
+    /// Payload size is bigger than allowed
+    #[display(
+        fmt = "Multipart payload size is bigger than allowed ({} bytes)",
+        limit
+    )]
+    Overflow { limit: usize },
+    /// Payload is terminated before closing boundary
+    #[display(fmt = "Multipart payload is incomplete")]
+    Incomplete,
+    /// Payload error
+    #[display(fmt = "Error that occur during reading payload: {}", _0)]
+    Payload(error::PayloadError),
+    /// Io error during saving part
+    #[display(fmt = "Io error: {}", _0)]
+    Io(std::io::Error),
+}
+
+impl From<error::PayloadError> for MultipartError {
+    fn from(err: error::PayloadError) -> Self {
+        MultipartError::Payload(err)
+    }
+}
+
+impl From<std::io::Error> for MultipartError {
+    fn from(err: std::io::Error) -> Self {
+        MultipartError::Io(err)
+    }
+}
+
+/// A set of errors that can occur during session handling
+#[cfg(feature = "session")]
+#[derive(Debug, Display)]
+pub enum SessionError {
+    /// Session middleware is not registered
+    #[display(fmt = "Session middleware is not configured")]
+    NotConfigured,
+    /// Session value serialization error
+    #[display(fmt = "Session serialization error: {}", _0)]
+    Serialize(serde_json::error::Error),
+    /// Session state does not fit into a cookie
+    #[display(fmt = "Session state exceeds cookie size limit")]
+    Overflow,
+    /// Session store error
+    #[display(fmt = "Session store error: {}", _0)]
+    Store(Box<dyn std::error::Error>),
+}
+
+#[cfg(feature = "session")]
+impl From<serde_json::error::Error> for SessionError {
+    fn from(err: serde_json::error::Error) -> Self {
+        SessionError::Serialize(err)
+    }
+}
+
 /// A set of errors that can occur during parsing request paths
 #[derive(Debug, Display, From)]
 pub enum PathError {
@@ -159,6 +238,17 @@ pub enum QueryPayloadError {
     Deserialize(serde::de::value::Error),
 }
 
+/// A set of errors that can occur during parsing pagination params
+#[derive(Debug, Display, From)]
+pub enum PageError {
+    /// Query deserialize error
+    #[display(fmt = "Page query deserialize error: {}", _0)]
+    Query(serde::de::value::Error),
+    /// Range header is malformed or cannot be satisfied
+    #[display(fmt = "Range is not satisfiable")]
+    Range,
+}
+
 #[derive(Debug, Display, From)]
 pub enum PayloadError {
     /// Http error.
@@ -818,6 +908,26 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
     }
 
+    #[test]
+    fn test_multipart_error() {
+        let req = TestRequest::default().to_http_request();
+        let resp: HttpResponse = WebResponseError::<DefaultError>::error_response(
+            &MultipartError::Overflow { limit: 0 },
+            &req,
+        );
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let resp: HttpResponse = WebResponseError::<DefaultError>::error_response(
+            &MultipartError::PartContentType("text/plain".to_string()),
+            &req,
+        );
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let resp: HttpResponse = WebResponseError::<DefaultError>::error_response(
+            &MultipartError::Boundary,
+            &req,
+        );
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[test]
     fn test_json_payload_error() {
         let req = TestRequest::default().to_http_request();
@@ -95,6 +95,9 @@ impl<E: WebResponseError<DefaultError>> WebResponseError<DefaultError> for Timeo
 /// `InternalServerError` for `DataExtractorError`
 impl WebResponseError<DefaultError> for error::DataExtractorError {}
 
+/// `InternalServerError` for `BudgetError`
+impl WebResponseError<DefaultError> for error::BudgetError {}
+
 /// `InternalServerError` for `JsonError`
 impl WebResponseError<DefaultError> for JsonError {}
 
@@ -164,6 +167,26 @@ impl WebResponseError<DefaultError> for error::UrlencodedError {
     }
 }
 
+/// `InternalServerError` for `SessionError`
+#[cfg(feature = "session")]
+impl WebResponseError<DefaultError> for error::SessionError {}
+
+/// Response renderer for `MultipartError`
+impl WebResponseError<DefaultError> for error::MultipartError {
+    fn status_code(&self) -> StatusCode {
+        match *self {
+            error::MultipartError::Overflow { .. }
+            | error::MultipartError::FieldOverflow { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            error::MultipartError::PartContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            error::MultipartError::Payload(ref err) => {
+                WebResponseError::<DefaultError>::status_code(err)
+            }
+            error::MultipartError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
 /// Return `BadRequest` for `JsonPayloadError`
 impl WebResponseError<DefaultError> for error::JsonPayloadError {
     fn status_code(&self) -> StatusCode {
@@ -188,20 +211,39 @@ impl WebResponseError<DefaultError> for error::QueryPayloadError {
     }
 }
 
+/// Return `BadRequest` for query errors, `RangeNotSatisfiable` for range errors
+impl WebResponseError<DefaultError> for error::PageError {
+    fn status_code(&self) -> StatusCode {
+        match *self {
+            error::PageError::Query(_) => StatusCode::BAD_REQUEST,
+            error::PageError::Range => StatusCode::RANGE_NOT_SATISFIABLE,
+        }
+    }
+}
+
+/// Return `PayloadTooLarge` for payload overflow, `UnprocessableEntity` for
+/// digest mismatch, `BadRequest` for other errors
 impl WebResponseError<DefaultError> for error::PayloadError {
     fn status_code(&self) -> StatusCode {
-        StatusCode::BAD_REQUEST
+        match *self {
+            error::PayloadError::Payload(ref err) => {
+                WebResponseError::<DefaultError>::status_code(err)
+            }
+            _ => StatusCode::BAD_REQUEST,
+        }
     }
 }
 
-/// `PayloadError` returns two possible results:
+/// `PayloadError` returns three possible results:
 ///
 /// - `Overflow` returns `PayloadTooLarge`
+/// - `DigestMismatch` returns `UnprocessableEntity`
 /// - Other errors returns `BadRequest`
 impl WebResponseError<DefaultError> for http::error::PayloadError {
     fn status_code(&self) -> StatusCode {
         match *self {
             http::error::PayloadError::Overflow => StatusCode::PAYLOAD_TOO_LARGE,
+            http::error::PayloadError::DigestMismatch => StatusCode::UNPROCESSABLE_ENTITY,
             _ => StatusCode::BAD_REQUEST,
         }
     }
@@ -3,6 +3,7 @@ use std::{future::Future, pin::Pin, task::Context, task::Poll};
 
 use super::error::ErrorRenderer;
 use super::httprequest::HttpRequest;
+use super::types::RequiredData;
 use crate::{http::Payload, util::Ready};
 
 /// Trait implemented by types that can be extracted from request.
@@ -24,6 +25,13 @@ pub trait FromRequest<Err>: Sized {
     fn extract(req: &HttpRequest) -> Self::Future {
         Self::from_request(req, &mut Payload::None)
     }
+
+    /// Register application data types required by extractor
+    ///
+    /// Application initialization fails if required data is not
+    /// registered for the route. By default extractor does not require
+    /// any data.
+    fn required_data(_: &mut RequiredData) {}
 }
 
 /// Optionally extract a field from the request
@@ -188,6 +196,10 @@ macro_rules! tuple_from_req ({$fut_type:ident, $(($n:tt, $T:ident)),+} => {
                 $($T: $T::from_request(req, payload),)+
             }
         }
+
+        fn required_data(data: &mut RequiredData) {
+            $($T::required_data(data);)+
+        }
     }
 
     pin_project_lite::pin_project! {
@@ -0,0 +1,743 @@
+//! Static files service
+//!
+//! ```rust
+//! use ntex::web::{self, files::Files};
+//!
+//! fn main() {
+//!     let app = web::App::new().service(
+//!         Files::new("/static", "./static")
+//!             .index_file("index.html")
+//!             .precompressed(true),
+//!     );
+//! }
+//! ```
+use std::io::{Read, Seek, SeekFrom};
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{error::Error, fs, future::Future, io, marker, path::Path, path::PathBuf};
+use std::{pin::Pin, rc::Rc};
+
+use percent_encoding::percent_decode_str;
+
+use crate::http::body::{Body, BodySize, MessageBody};
+use crate::http::header::{self, HeaderMap, HeaderValue};
+use crate::http::{Method, Response, StatusCode};
+use crate::router::ResourceDef;
+use crate::rt::{spawn_blocking, JoinHandle};
+use crate::service::{Service, ServiceFactory};
+use crate::util::{Bytes, BytesMut, Ready};
+
+use super::error::ErrorRenderer;
+use super::request::WebRequest;
+use super::response::WebResponse;
+use super::service::{WebServiceConfig, WebServiceFactory};
+
+/// Size of chunk read from file
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Precompressed file extensions in order of preference
+const PRECOMPRESSED: &[(&str, &str)] = &[("br", "br"), ("gzip", "gz")];
+
+/// Static files service
+///
+/// Service serves files from the directory, mounted at specified path.
+/// Service supports `Range` requests, conditional requests with
+/// `If-None-Match`, `If-Modified-Since` and `If-Range` headers,
+/// directory index files and precompressed `.br`/`.gz` files.
+///
+/// Request path is checked for traversal, paths with `..` segments and
+/// hidden files (segments started with `.`) are not served.
+/// Files are read on blocking thread pool.
+///
+/// Kernel `sendfile` is not used, response body is written to the
+/// connection's write buffer and passes through io filters (tls, etc),
+/// so file data could not be sent directly to the socket.
+pub struct Files {
+    path: String,
+    cfg: FilesConfig,
+}
+
+struct FilesConfig {
+    directory: PathBuf,
+    index: Option<String>,
+    precompressed: bool,
+    etag: bool,
+    last_modified: bool,
+    hidden_files: bool,
+}
+
+impl Files {
+    /// Create new `Files` instance for specified base directory.
+    ///
+    /// `mount_path` is a root of the service, remaining part of the request
+    /// path is used as a path of the file in `directory`.
+    pub fn new<T: Into<PathBuf>>(mount_path: &str, directory: T) -> Self {
+        Files {
+            path: mount_path.to_string(),
+            cfg: FilesConfig {
+                directory: directory.into(),
+                index: None,
+                precompressed: false,
+                etag: true,
+                last_modified: true,
+                hidden_files: false,
+            },
+        }
+    }
+
+    /// Set index file
+    ///
+    /// Index file is served for directory requests. By default directory
+    /// requests are not served.
+    pub fn index_file<T: Into<String>>(mut self, index: T) -> Self {
+        self.cfg.index = Some(index.into());
+        self
+    }
+
+    /// Serve precompressed files
+    ///
+    /// If request accepts `br` or `gzip` encodings and file with `.br` or
+    /// `.gz` extension exists next to requested file, compressed file is
+    /// served with `Content-Encoding` header. By default it is disabled.
+    pub fn precompressed(mut self, enabled: bool) -> Self {
+        self.cfg.precompressed = enabled;
+        self
+    }
+
+    /// Specifies whether to use `ETag` header. By default it is enabled.
+    pub fn use_etag(mut self, enabled: bool) -> Self {
+        self.cfg.etag = enabled;
+        self
+    }
+
+    /// Specifies whether to use `Last-Modified` header. By default it is enabled.
+    pub fn use_last_modified(mut self, enabled: bool) -> Self {
+        self.cfg.last_modified = enabled;
+        self
+    }
+
+    /// Serve hidden files, files which names start with `.`
+    pub fn show_hidden_files(mut self) -> Self {
+        self.cfg.hidden_files = true;
+        self
+    }
+}
+
+impl<Err: ErrorRenderer> WebServiceFactory<Err> for Files {
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        config.register_service(
+            ResourceDef::root_prefix(self.path.as_str()),
+            None,
+            FilesFactory(Rc::new(self.cfg), marker::PhantomData),
+            None,
+        )
+    }
+}
+
+struct FilesFactory<Err>(Rc<FilesConfig>, marker::PhantomData<Err>);
+
+impl<Err: ErrorRenderer> ServiceFactory<WebRequest<Err>> for FilesFactory<Err> {
+    type Response = WebResponse;
+    type Error = Err::Container;
+    type InitError = ();
+    type Service = FilesService<Err>;
+    type Future = Ready<Self::Service, ()>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        Ready::Ok(FilesService(self.0.clone(), marker::PhantomData))
+    }
+}
+
+struct FilesService<Err>(Rc<FilesConfig>, marker::PhantomData<Err>);
+
+impl<Err: ErrorRenderer> Service<WebRequest<Err>> for FilesService<Err> {
+    type Response = WebResponse;
+    type Error = Err::Container;
+    type Future = Pin<Box<dyn Future<Output = Result<WebResponse, Err::Container>>>>;
+
+    #[inline]
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: WebRequest<Err>) -> Self::Future {
+        let cfg = self.0.clone();
+
+        Box::pin(async move {
+            let res = if req.method() == Method::GET || req.method() == Method::HEAD {
+                let path = req.match_info().unprocessed().to_string();
+                serve(cfg, &path, req.headers()).await
+            } else {
+                Response::build(StatusCode::METHOD_NOT_ALLOWED)
+                    .header(header::ALLOW, "GET, HEAD")
+                    .finish()
+            };
+            Ok(req.into_response(res))
+        })
+    }
+}
+
+/// Opened file
+struct NamedFile {
+    file: fs::File,
+    md: fs::Metadata,
+    path: PathBuf,
+    encoding: Option<&'static str>,
+}
+
+async fn serve(cfg: Rc<FilesConfig>, path: &str, headers: &HeaderMap) -> Response {
+    let rel = match parse_path(path, cfg.hidden_files) {
+        Some(rel) => rel,
+        None => return Response::NotFound().finish(),
+    };
+    let mut path = cfg.directory.join(rel);
+    let index = cfg.index.clone();
+    let encodings = if cfg.precompressed {
+        accepted_encodings(headers)
+    } else {
+        Vec::new()
+    };
+
+    let result = spawn_blocking(move || {
+        if fs::metadata(&path)?.is_dir() {
+            match index {
+                Some(index) => path.push(index),
+                None => return Err(io::Error::from(io::ErrorKind::NotFound)),
+            }
+        }
+        let md = fs::metadata(&path)?;
+        if !md.is_file() {
+            return Err(io::Error::from(io::ErrorKind::NotFound));
+        }
+        for (enc, ext) in encodings {
+            let mut p = path.clone().into_os_string();
+            p.push(".");
+            p.push(ext);
+            if let Ok(md) = fs::metadata(&p) {
+                if md.is_file() {
+                    if let Ok(file) = fs::File::open(&p) {
+                        return Ok(NamedFile {
+                            file,
+                            md,
+                            path,
+                            encoding: Some(enc),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(NamedFile {
+            file: fs::File::open(&path)?,
+            md,
+            path,
+            encoding: None,
+        })
+    })
+    .await;
+
+    let file = match result {
+        Ok(Ok(file)) => file,
+        Ok(Err(err)) => {
+            return match err.kind() {
+                io::ErrorKind::NotFound => Response::NotFound().finish(),
+                io::ErrorKind::PermissionDenied => Response::Forbidden().finish(),
+                _ => Response::InternalServerError().finish(),
+            }
+        }
+        Err(_) => return Response::InternalServerError().finish(),
+    };
+    respond(&cfg, file, headers)
+}
+
+fn respond(cfg: &FilesConfig, file: NamedFile, headers: &HeaderMap) -> Response {
+    let len = file.md.len();
+    let modified = file.md.modified().ok();
+    let etag = if cfg.etag {
+        let mtime = modified
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        Some(format!("\"{:x}-{:x}\"", len, mtime))
+    } else {
+        None
+    };
+    let last_modified = if cfg.last_modified {
+        modified.map(httpdate::fmt_http_date)
+    } else {
+        None
+    };
+
+    let mut builder = Response::build(StatusCode::OK);
+    if let Some(ref etag) = etag {
+        builder.header(header::ETAG, etag.as_str());
+    }
+    if let Some(ref lm) = last_modified {
+        builder.header(header::LAST_MODIFIED, lm.as_str());
+    }
+    if cfg.precompressed {
+        builder.header(header::VARY, "Accept-Encoding");
+    }
+
+    // conditional request
+    let not_modified = if let Some(val) = headers.get(header::IF_NONE_MATCH) {
+        etag.as_ref()
+            .map(|etag| etag_matches(val, etag))
+            .unwrap_or(false)
+    } else if let Some(val) = headers.get(header::IF_MODIFIED_SINCE) {
+        match (modified, val.to_str().ok().map(httpdate::parse_http_date)) {
+            (Some(modified), Some(Ok(since))) => unix_secs(modified) <= unix_secs(since),
+            _ => false,
+        }
+    } else {
+        false
+    };
+    if not_modified {
+        return builder.status(StatusCode::NOT_MODIFIED).finish();
+    }
+
+    builder
+        .header(header::CONTENT_TYPE, content_type(&file.path))
+        .header(header::ACCEPT_RANGES, "bytes");
+    if let Some(enc) = file.encoding {
+        builder.header(header::CONTENT_ENCODING, enc);
+    }
+
+    // range request
+    let mut offset = 0;
+    let mut size = len;
+    if let Some(range) = headers.get(header::RANGE).and_then(|v| v.to_str().ok()) {
+        let if_range = match headers.get(header::IF_RANGE).and_then(|v| v.to_str().ok()) {
+            Some(val) => {
+                etag.as_deref() == Some(val) || last_modified.as_deref() == Some(val)
+            }
+            None => true,
+        };
+        if if_range {
+            match parse_range(range, len) {
+                Ok(Some((start, end))) => {
+                    offset = start;
+                    size = end - start + 1;
+                    builder.status(StatusCode::PARTIAL_CONTENT).header(
+                        header::CONTENT_RANGE,
+                        format!("bytes {}-{}/{}", start, end, len),
+                    );
+                }
+                Ok(None) => (),
+                Err(_) => {
+                    return builder
+                        .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                        .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+                        .finish();
+                }
+            }
+        }
+    }
+
+    builder.body(Body::from_message(FileBody {
+        offset,
+        size,
+        remaining: size,
+        file: Some(file.file),
+        fut: None,
+    }))
+}
+
+/// Parse request path, returns `None` for unsafe paths
+fn parse_path(path: &str, hidden_files: bool) -> Option<PathBuf> {
+    let path = percent_decode_str(path).decode_utf8().ok()?;
+    let mut buf = PathBuf::new();
+    for segment in path.split('/') {
+        if segment.is_empty() || segment == "." {
+            continue;
+        }
+        if segment == ".."
+            || segment.contains('\\')
+            || segment.contains('\0')
+            || (segment.starts_with('.') && !hidden_files)
+            || (cfg!(windows) && segment.contains(':'))
+        {
+            return None;
+        }
+        buf.push(segment);
+    }
+    Some(buf)
+}
+
+/// Precompressed encodings accepted by request
+fn accepted_encodings(headers: &HeaderMap) -> Vec<(&'static str, &'static str)> {
+    let mut accepted = Vec::new();
+    for val in headers.get_all(header::ACCEPT_ENCODING) {
+        if let Ok(val) = val.to_str() {
+            for item in val.split(',') {
+                let mut params = item.split(';');
+                let name = params.next().unwrap_or("").trim();
+                let disabled = params.any(|p| {
+                    p.trim()
+                        .strip_prefix("q=")
+                        .and_then(|q| q.trim().parse::<f32>().ok())
+                        == Some(0.0)
+                });
+                if !disabled {
+                    accepted.push(name.to_ascii_lowercase());
+                }
+            }
+        }
+    }
+    PRECOMPRESSED
+        .iter()
+        .filter(|(enc, _)| accepted.iter().any(|a| a == enc))
+        .copied()
+        .collect()
+}
+
+fn etag_matches(val: &HeaderValue, etag: &str) -> bool {
+    val.to_str()
+        .map(|val| {
+            val.split(',').any(|tag| {
+                let tag = tag.trim();
+                tag == "*" || tag.trim_start_matches("W/") == etag
+            })
+        })
+        .unwrap_or(false)
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Parse single byte range
+///
+/// Returns `Ok(None)` if range should be ignored and `Err` if range
+/// is not satisfiable.
+fn parse_range(val: &str, len: u64) -> Result<Option<(u64, u64)>, ()> {
+    let spec = match val.trim().strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return Ok(None),
+    };
+    let (start, end) = match spec.split_once('-') {
+        Some(range) => range,
+        None => return Ok(None),
+    };
+
+    if start.is_empty() {
+        // suffix range
+        match end.parse::<u64>() {
+            Ok(0) => Err(()),
+            Ok(_) if len == 0 => Err(()),
+            Ok(n) => Ok(Some((len.saturating_sub(n), len - 1))),
+            Err(_) => Ok(None),
+        }
+    } else {
+        let start = match start.parse::<u64>() {
+            Ok(start) => start,
+            Err(_) => return Ok(None),
+        };
+        let end = if end.is_empty() {
+            len.saturating_sub(1)
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) if end >= start => std::cmp::min(end, len.saturating_sub(1)),
+                _ => return Ok(None),
+            }
+        };
+        if start >= len {
+            Err(())
+        } else {
+            Ok(Some((start, end)))
+        }
+    }
+}
+
+/// Guess content type by file extension
+fn content_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "xml" => "text/xml; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Body that reads file chunks on blocking thread pool
+struct FileBody {
+    size: u64,
+    offset: u64,
+    remaining: u64,
+    file: Option<fs::File>,
+    fut: Option<JoinHandle<io::Result<(fs::File, Bytes)>>>,
+}
+
+impl MessageBody for FileBody {
+    fn size(&self) -> BodySize {
+        BodySize::Sized(self.size)
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if let Some(ref mut fut) = self.fut {
+            let result = match Pin::new(fut).poll(cx) {
+                Poll::Ready(result) => result,
+                Poll::Pending => return Poll::Pending,
+            };
+            self.fut = None;
+
+            return match result {
+                Ok(Ok((_, chunk))) if chunk.is_empty() => Poll::Ready(Some(Err(Box::new(
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "File is truncated"),
+                )))),
+                Ok(Ok((file, chunk))) => {
+                    self.offset += chunk.len() as u64;
+                    self.remaining -= chunk.len() as u64;
+                    self.file = Some(file);
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+                Ok(Err(err)) => Poll::Ready(Some(Err(Box::new(err)))),
+                Err(_) => Poll::Ready(Some(Err(Box::new(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "Blocking task is canceled",
+                ))))),
+            };
+        }
+
+        let mut file = match self.file.take() {
+            Some(file) if self.remaining > 0 => file,
+            _ => return Poll::Ready(None),
+        };
+        let offset = self.offset;
+        let size = std::cmp::min(self.remaining, CHUNK_SIZE as u64) as usize;
+        self.fut = Some(spawn_blocking(move || {
+            let mut buf = BytesMut::with_capacity(size);
+            buf.resize(size, 0);
+            file.seek(SeekFrom::Start(offset))?;
+            let n = file.read(&mut buf[..])?;
+            buf.truncate(n);
+            Ok((file, buf.freeze()))
+        }));
+        self.poll_next_chunk(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::{HeaderName, HeaderValue};
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::App;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "ntex-files-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("hello.txt"), "hello world").unwrap();
+        fs::write(dir.join(".hidden"), "hidden").unwrap();
+        fs::write(dir.join("sub").join("index.html"), "<html></html>").unwrap();
+        fs::write(dir.join("app.js"), "plain").unwrap();
+        fs::write(dir.join("app.js.gz"), "gzip").unwrap();
+        fs::write(dir.join("app.js.br"), "brotli").unwrap();
+        dir
+    }
+
+    fn header(resp: &WebResponse, name: HeaderName) -> &str {
+        resp.headers().get(name).unwrap().to_str().unwrap()
+    }
+
+    #[crate::rt_test]
+    async fn test_files() {
+        let dir = test_dir("files");
+        let srv = init_service(
+            App::new().service(Files::new("/static/", &dir).index_file("index.html")),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/static/hello.txt").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            header(&resp, header::CONTENT_TYPE),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(header(&resp, header::ACCEPT_RANGES), "bytes");
+        let etag = header(&resp, header::ETAG).to_string();
+        let lm = header(&resp, header::LAST_MODIFIED).to_string();
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"hello world"));
+
+        // conditional requests
+        let req = TestRequest::with_uri("/static/hello.txt")
+            .header(header::IF_NONE_MATCH, etag.as_str())
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        let req = TestRequest::with_uri("/static/hello.txt")
+            .header(header::IF_NONE_MATCH, "\"other\"")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/static/hello.txt")
+            .header(header::IF_MODIFIED_SINCE, lm.as_str())
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        // index file
+        let req = TestRequest::with_uri("/static/sub/").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            header(&resp, header::CONTENT_TYPE),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"<html></html>"));
+
+        // unsafe paths
+        for path in &[
+            "/static/../hello.txt",
+            "/static/sub/%2e%2e/%2e%2e/hello.txt",
+            "/static/.hidden",
+            "/static/missing.txt",
+        ] {
+            let req = TestRequest::with_uri(path).to_request();
+            let resp = call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{}", path);
+        }
+
+        let req = TestRequest::with_uri("/static/hello.txt")
+            .method(Method::POST)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[crate::rt_test]
+    async fn test_ranges() {
+        let dir = test_dir("ranges");
+        let srv = init_service(App::new().service(Files::new("/", &dir))).await;
+
+        let req = TestRequest::with_uri("/hello.txt")
+            .header(header::RANGE, "bytes=0-4")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(header(&resp, header::CONTENT_RANGE), "bytes 0-4/11");
+        let etag = header(&resp, header::ETAG).to_string();
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"hello"));
+
+        let req = TestRequest::with_uri("/hello.txt")
+            .header(header::RANGE, "bytes=-5")
+            .header(header::IF_RANGE, etag.as_str())
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"world"));
+
+        let req = TestRequest::with_uri("/hello.txt")
+            .header(header::RANGE, "bytes=6-")
+            .header(header::IF_RANGE, "\"other\"")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"hello world"));
+
+        let req = TestRequest::with_uri("/hello.txt")
+            .header(header::RANGE, "bytes=20-")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(header(&resp, header::CONTENT_RANGE), "bytes */11");
+
+        assert_eq!(parse_range("bytes=0-4,6-7", 11), Ok(None));
+        assert_eq!(parse_range("bytes=5-100", 11), Ok(Some((5, 10))));
+        assert_eq!(parse_range("bytes=5-2", 11), Ok(None));
+        assert_eq!(parse_range("bytes=-0", 11), Err(()));
+        assert_eq!(parse_range("items=0-1", 11), Ok(None));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[crate::rt_test]
+    async fn test_precompressed() {
+        let dir = test_dir("precompressed");
+        let srv = init_service(
+            App::new().service(Files::new("/", &dir).precompressed(true).use_etag(false)),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/app.js")
+            .header(header::ACCEPT_ENCODING, "gzip, br")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(header(&resp, header::CONTENT_ENCODING), "br");
+        assert_eq!(header(&resp, header::VARY), "Accept-Encoding");
+        assert_eq!(
+            header(&resp, header::CONTENT_TYPE),
+            "text/javascript; charset=utf-8"
+        );
+        assert!(!resp.headers().contains_key(header::ETAG));
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"brotli"));
+
+        let req = TestRequest::with_uri("/app.js")
+            .header(header::ACCEPT_ENCODING, "gzip;q=0.5, br;q=0")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(header(&resp, header::CONTENT_ENCODING), "gzip");
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"gzip"));
+
+        let req = TestRequest::with_uri("/app.js").to_request();
+        let resp = call_service(&srv, req).await;
+        assert!(!resp.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"plain"));
+
+        let req = TestRequest::with_uri("/hello.txt")
+            .header(
+                header::ACCEPT_ENCODING,
+                HeaderValue::from_static("gzip, br"),
+            )
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert!(!resp.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"hello world"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
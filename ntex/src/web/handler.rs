@@ -6,6 +6,7 @@ use super::httprequest::HttpRequest;
 use super::request::WebRequest;
 use super::responder::Responder;
 use super::response::WebResponse;
+use super::types::RequiredData;
 
 /// Async fn handler
 pub trait Handler<T, Err>: Clone + 'static
@@ -40,6 +41,8 @@ pub(super) trait HandlerFn<Err: ErrorRenderer> {
     ) -> Pin<Box<dyn Future<Output = Result<WebResponse, Err::Container>>>>;
 
     fn clone_handler(&self) -> Box<dyn HandlerFn<Err>>;
+
+    fn required_data(&self, data: &mut RequiredData);
 }
 
 pub(super) struct HandlerWrapper<F, T, Err>
@@ -99,6 +102,10 @@ where
             _t: PhantomData,
         })
     }
+
+    fn required_data(&self, data: &mut RequiredData) {
+        T::required_data(data)
+    }
 }
 
 pin_project_lite::pin_project! {
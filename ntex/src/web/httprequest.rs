@@ -112,6 +112,14 @@ impl HttpRequest {
         self.head().io.as_ref()
     }
 
+    /// Connection data
+    ///
+    /// Data is attached to connection by `ServerBuilder::on_worker_connect()` hooks.
+    #[inline]
+    pub fn conn_data<T: 'static>(&self) -> Option<Rc<T>> {
+        self.io().and_then(|io| io.data::<T>())
+    }
+
     /// Peer socket address
     ///
     /// Peer address is actual socket address, if proxy is used in front of
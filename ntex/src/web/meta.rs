@@ -0,0 +1,69 @@
+use crate::http::Method;
+
+/// Methods and metadata of resource routes
+pub(super) type RouteDefs = Vec<(Vec<Method>, RouteMeta)>;
+
+/// Route documentation metadata
+///
+/// Metadata does not affect request handling, it is available
+/// via routes introspection api.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RouteMeta {
+    pub(super) name: Option<String>,
+    pub(super) tags: Vec<String>,
+    pub(super) description: Option<String>,
+    pub(super) auth: Vec<String>,
+}
+
+impl RouteMeta {
+    /// Route name
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Route tags
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Route description
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Route auth requirements
+    pub fn auth(&self) -> &[String] {
+        &self.auth
+    }
+}
+
+/// Registered route information
+#[derive(Clone, Debug)]
+pub struct RouteInfo {
+    pub(super) path: String,
+    pub(super) resource: Option<String>,
+    pub(super) methods: Vec<Method>,
+    pub(super) meta: RouteMeta,
+}
+
+impl RouteInfo {
+    /// Full path pattern of the route
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Name of the route's resource
+    pub fn resource(&self) -> Option<&str> {
+        self.resource.as_deref()
+    }
+
+    /// Route methods, empty list matches any method
+    pub fn methods(&self) -> &[Method] {
+        &self.methods
+    }
+
+    /// Route documentation metadata
+    pub fn meta(&self) -> &RouteMeta {
+        &self.meta
+    }
+}
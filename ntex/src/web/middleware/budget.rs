@@ -0,0 +1,258 @@
+//! Middleware for per-request memory budget
+use std::task::{Context, Poll};
+use std::{cell::Cell, rc::Rc};
+
+use crate::http::{Response, StatusCode};
+use crate::service::{Service, Transform};
+use crate::util::{Either, Ready};
+use crate::web::error::BudgetError;
+use crate::web::{WebRequest, WebResponse};
+
+/// `Middleware` for per-request memory budget accounting.
+///
+/// Middleware charges request head to the budget, `Bytes`, `String`, `Json`
+/// and `Form` extractors charge request body. Requests with head exceeding
+/// budget are rejected with `413 Payload Too Large` response, extractors
+/// return payload overflow error if body exceeds remaining budget.
+///
+/// Budget handle is available via request extensions, handlers could use it
+/// to charge other allocations, `BudgetError` is rendered as
+/// `500 Internal Server Error` response.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::MemoryBudget::new(64 * 1024))
+///         .service(
+///             web::resource("/test")
+///                 .route(web::post().to(|body: String| async move { body }))
+///         );
+/// }
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct MemoryBudget {
+    limit: usize,
+}
+
+impl MemoryBudget {
+    /// Construct `MemoryBudget` middleware with budget in bytes.
+    pub fn new(limit: usize) -> Self {
+        MemoryBudget { limit }
+    }
+}
+
+impl<S> Transform<S> for MemoryBudget {
+    type Service = MemoryBudgetMiddleware<S>;
+
+    fn new_transform(&self, service: S) -> Self::Service {
+        MemoryBudgetMiddleware {
+            service,
+            limit: self.limit,
+        }
+    }
+}
+
+pub struct MemoryBudgetMiddleware<S> {
+    service: S,
+    limit: usize,
+}
+
+impl<S, E> Service<WebRequest<E>> for MemoryBudgetMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<WebResponse, S::Error>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let head = req.head();
+        let size = head.uri.path().len()
+            + head.uri.query().map(|q| q.len()).unwrap_or(0)
+            + head
+                .headers
+                .iter()
+                .map(|(name, value)| name.as_str().len() + value.len())
+                .sum::<usize>();
+
+        let budget = RequestBudget::new(self.limit);
+        if budget.charge(size).is_err() {
+            let res = req.into_response(Response::new(StatusCode::PAYLOAD_TOO_LARGE));
+            return Either::Right(Ready::Ok(res));
+        }
+        req.extensions_mut().insert(budget);
+        Either::Left(self.service.call(req))
+    }
+}
+
+/// Memory budget of the request
+///
+/// Budget handle is stored in request extensions by `MemoryBudget` middleware.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::RequestBudget, HttpRequest};
+///
+/// async fn index(req: HttpRequest) -> Result<String, web::Error> {
+///     let remaining = if let Some(budget) = req.extensions().get::<RequestBudget>() {
+///         budget.charge(1024)?;
+///         budget.remaining()
+///     } else {
+///         0
+///     };
+///     Ok(format!("remaining: {}", remaining))
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RequestBudget(Rc<Budget>);
+
+#[derive(Debug)]
+struct Budget {
+    limit: usize,
+    used: Cell<usize>,
+}
+
+impl RequestBudget {
+    /// Create new budget
+    pub fn new(limit: usize) -> Self {
+        RequestBudget(Rc::new(Budget {
+            limit,
+            used: Cell::new(0),
+        }))
+    }
+
+    /// Budget size in bytes
+    pub fn limit(&self) -> usize {
+        self.0.limit
+    }
+
+    /// Number of charged bytes
+    pub fn used(&self) -> usize {
+        self.0.used.get()
+    }
+
+    /// Number of available bytes
+    pub fn remaining(&self) -> usize {
+        self.0.limit - self.0.used.get()
+    }
+
+    /// Charge allocation to the budget
+    ///
+    /// Budget is not changed if allocation does not fit.
+    pub fn charge(&self, size: usize) -> Result<(), BudgetError> {
+        if size > self.remaining() {
+            Err(BudgetError::Exceeded)
+        } else {
+            self.0.used.set(self.0.used.get() + size);
+            Ok(())
+        }
+    }
+
+    /// Return allocation to the budget
+    pub fn release(&self, size: usize) {
+        self.0.used.set(self.0.used.get().saturating_sub(size));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{header, Method};
+    use crate::util::Bytes;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpRequest, HttpResponse};
+
+    #[crate::rt_test]
+    async fn test_budget() {
+        let srv = init_service(
+            App::new()
+                .wrap(MemoryBudget::new(1024))
+                .route(
+                    "/bytes",
+                    web::post().to(|req: HttpRequest, body: Bytes| async move {
+                        let used = req.extensions().get::<RequestBudget>().unwrap().used();
+                        assert!(used >= body.len());
+                        HttpResponse::Ok().body(body)
+                    }),
+                )
+                .route(
+                    "/json",
+                    web::post().to(|body: web::types::Json<Vec<u32>>| async move {
+                        HttpResponse::Ok().body(format!("{}", body.len()))
+                    }),
+                )
+                .route(
+                    "/charge",
+                    web::get().to(|req: HttpRequest| async move {
+                        let budget =
+                            req.extensions().get::<RequestBudget>().cloned().unwrap();
+                        budget.charge(512)?;
+                        budget.release(512);
+                        budget.charge(2048)?;
+                        Ok::<_, web::Error>(HttpResponse::Ok())
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/bytes")
+            .method(Method::POST)
+            .set_payload(Bytes::from_static(b"0123456789"))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, Bytes::from_static(b"0123456789"));
+
+        // body exceeds budget
+        let req = TestRequest::with_uri("/bytes")
+            .method(Method::POST)
+            .set_payload(Bytes::from(vec![b'0'; 2048]))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = TestRequest::with_uri("/json")
+            .method(Method::POST)
+            .header(header::CONTENT_TYPE, "application/json")
+            .set_payload(format!("[{}0]", "0,".repeat(1024)))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // head exceeds budget
+        let req = TestRequest::with_uri("/bytes")
+            .method(Method::POST)
+            .header("x-large", "0".repeat(2048))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // explicit charge
+        let req = TestRequest::with_uri("/charge").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_request_budget() {
+        let budget = RequestBudget::new(100);
+        assert!(budget.charge(60).is_ok());
+        assert_eq!(budget.used(), 60);
+        assert_eq!(budget.charge(50), Err(BudgetError::Exceeded));
+        assert_eq!(budget.remaining(), 40);
+        budget.release(100);
+        assert_eq!(budget.used(), 0);
+        assert_eq!(budget.limit(), 100);
+    }
+}
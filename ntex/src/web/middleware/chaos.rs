@@ -0,0 +1,387 @@
+//! Middleware for fault injection
+use std::task::{Context, Poll};
+use std::{error::Error, future::Future, io, pin::Pin, rc::Rc};
+
+use nanorand::{Rng, WyRand};
+
+use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::http::{Method, Response, StatusCode};
+use crate::service::{Service, Transform};
+use crate::time::{sleep, Millis};
+use crate::util::Bytes;
+use crate::web::{WebRequest, WebResponse};
+
+/// Injected fault
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Delay response
+    Delay(Millis),
+    /// Respond with specified status code, inner service is not called
+    Status(StatusCode),
+    /// Drop connection before response body
+    Reset,
+    /// Drop connection after specified number of body bytes
+    Truncate(usize),
+}
+
+/// Fault injection rule
+///
+/// By default rule matches all requests and fault is always injected.
+#[derive(Clone, Debug)]
+pub struct FaultRule {
+    fault: Fault,
+    probability: f64,
+    path: Option<String>,
+    method: Option<Method>,
+}
+
+impl FaultRule {
+    /// Create new rule for specified fault.
+    pub fn new(fault: Fault) -> Self {
+        FaultRule {
+            fault,
+            probability: 1.0,
+            path: None,
+            method: None,
+        }
+    }
+
+    /// Set probability of fault injection, value between 0.0 and 1.0
+    pub fn probability(mut self, probability: f64) -> Self {
+        self.probability = probability;
+        self
+    }
+
+    /// Match requests with path starting with specified prefix.
+    pub fn path<T: AsRef<str>>(mut self, prefix: T) -> Self {
+        self.path = Some(prefix.as_ref().to_string());
+        self
+    }
+
+    /// Match requests with specified method.
+    pub fn method(mut self, method: Method) -> Self {
+        self.method = Some(method);
+        self
+    }
+
+    fn matches<E>(&self, req: &WebRequest<E>) -> bool {
+        if let Some(ref method) = self.method {
+            if req.method() != method {
+                return false;
+            }
+        }
+        if let Some(ref path) = self.path {
+            if !req.path().starts_with(path.as_str()) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn triggered(&self, rng: &mut WyRand) -> bool {
+        if self.probability >= 1.0 {
+            true
+        } else if self.probability <= 0.0 {
+            false
+        } else {
+            (rng.generate::<u32>() as f64 / u32::MAX as f64) < self.probability
+        }
+    }
+}
+
+/// `Middleware` for injecting faults into request handling.
+///
+/// Middleware is intended for testing of client resiliency. It can delay
+/// responses, respond with error status codes, drop connections or truncate
+/// response bodies. Rules are checked in registration order, all triggered
+/// delays are applied, only first triggered fault of other kinds is applied.
+///
+/// Middleware is available only with `chaos` feature.
+///
+/// ```rust
+/// use ntex::http::StatusCode;
+/// use ntex::web::{self, middleware, middleware::{Fault, FaultRule}, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::Chaos::new()
+///                 .fault(Fault::Status(StatusCode::SERVICE_UNAVAILABLE), 0.1)
+///                 .rule(FaultRule::new(Fault::Truncate(16)).path("/test"))
+///         )
+///         .service(
+///             web::resource("/test")
+///                 .route(web::get().to(|| async { HttpResponse::Ok() }))
+///         );
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct Chaos {
+    rules: Rc<Vec<FaultRule>>,
+}
+
+impl Chaos {
+    /// Construct `Chaos` middleware without rules.
+    pub fn new() -> Self {
+        Chaos::default()
+    }
+
+    /// Inject fault into all requests with specified probability.
+    pub fn fault(self, fault: Fault, probability: f64) -> Self {
+        self.rule(FaultRule::new(fault).probability(probability))
+    }
+
+    /// Add fault injection rule.
+    pub fn rule(mut self, rule: FaultRule) -> Self {
+        Rc::get_mut(&mut self.rules)
+            .expect("Multiple copies exist")
+            .push(rule);
+        self
+    }
+}
+
+impl<S> Transform<S> for Chaos {
+    type Service = ChaosMiddleware<S>;
+
+    fn new_transform(&self, service: S) -> Self::Service {
+        ChaosMiddleware {
+            service,
+            rules: self.rules.clone(),
+        }
+    }
+}
+
+pub struct ChaosMiddleware<S> {
+    service: S,
+    rules: Rc<Vec<FaultRule>>,
+}
+
+impl<S, E> Service<WebRequest<E>> for ChaosMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    S::Future: 'static,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let mut rng = WyRand::new();
+        let mut delay = Millis::ZERO;
+        let mut fault = None;
+        for rule in self.rules.iter() {
+            if rule.matches(&req) && rule.triggered(&mut rng) {
+                match rule.fault {
+                    Fault::Delay(d) => delay = delay + d,
+                    f => {
+                        if fault.is_none() {
+                            fault = Some(f)
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(Fault::Status(status)) = fault {
+            let res = req.into_response(Response::new(status));
+            return Box::pin(async move {
+                if delay != Millis::ZERO {
+                    sleep(delay).await;
+                }
+                Ok(res)
+            });
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move {
+            let res = fut.await?;
+            if delay != Millis::ZERO {
+                sleep(delay).await;
+            }
+
+            let limit = match fault {
+                Some(Fault::Reset) => 0,
+                Some(Fault::Truncate(limit)) => limit,
+                _ => return Ok(res),
+            };
+            Ok(res.map_body(|_, body| {
+                ResponseBody::Other(Body::from_message(Truncated {
+                    body,
+                    limit,
+                    done: false,
+                }))
+            }))
+        })
+    }
+}
+
+/// Response body that fails after `limit` bytes
+struct Truncated {
+    body: ResponseBody<Body>,
+    limit: usize,
+    done: bool,
+}
+
+impl MessageBody for Truncated {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        if self.limit == 0 {
+            self.done = true;
+            return Poll::Ready(Some(Err(Box::new(io::Error::new(
+                io::ErrorKind::ConnectionReset,
+                "Injected fault",
+            )))));
+        }
+
+        match self.body.poll_next_chunk(cx) {
+            Poll::Ready(Some(Ok(mut chunk))) => {
+                if chunk.len() > self.limit {
+                    chunk.truncate(self.limit);
+                }
+                self.limit -= chunk.len();
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            res => res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::IntoService;
+    use crate::util::{poll_fn, BytesMut};
+    use crate::web::request::WebRequest;
+    use crate::web::test::{ok_service, TestRequest};
+    use crate::web::{DefaultError, HttpResponse};
+
+    async fn read_body(res: &mut WebResponse) -> Result<Bytes, Box<dyn Error>> {
+        let mut body = res.take_body();
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = poll_fn(|cx| body.poll_next_chunk(cx)).await {
+            buf.extend_from_slice(&chunk?);
+        }
+        Ok(buf.freeze())
+    }
+
+    #[crate::rt_test]
+    async fn test_status() {
+        let mw = Chaos::new()
+            .fault(Fault::Status(StatusCode::SERVICE_UNAVAILABLE), 1.0)
+            .new_transform(ok_service());
+
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let mw = Chaos::new()
+            .fault(Fault::Status(StatusCode::SERVICE_UNAVAILABLE), 0.0)
+            .new_transform(ok_service());
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_rules() {
+        let mw = Chaos::new()
+            .rule(
+                FaultRule::new(Fault::Status(StatusCode::BAD_GATEWAY))
+                    .path("/api")
+                    .method(Method::POST),
+            )
+            .new_transform(ok_service());
+
+        let req = TestRequest::with_uri("/api/v1").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/index")
+            .method(Method::POST)
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/api/v1")
+            .method(Method::POST)
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[crate::rt_test]
+    async fn test_delay() {
+        let mw = Chaos::new()
+            .fault(Fault::Delay(Millis(100)), 1.0)
+            .new_transform(ok_service());
+
+        let start = std::time::Instant::now();
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(start.elapsed() >= std::time::Duration::from_millis(100));
+    }
+
+    #[crate::rt_test]
+    async fn test_truncate_and_reset() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            Ok::<_, crate::web::Error>(
+                req.into_response(HttpResponse::Ok().body("0123456789")),
+            )
+        };
+
+        let mw = Chaos::new()
+            .fault(Fault::Truncate(4), 1.0)
+            .new_transform(srv.into_service());
+        let mut resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(resp.response().body().size(), BodySize::Sized(10));
+        let mut body = resp.take_body();
+        let chunk = poll_fn(|cx| body.poll_next_chunk(cx)).await;
+        assert_eq!(chunk.unwrap().unwrap(), Bytes::from_static(b"0123"));
+        let chunk = poll_fn(|cx| body.poll_next_chunk(cx)).await;
+        assert!(chunk.unwrap().is_err());
+
+        let mw = Chaos::new()
+            .fault(Fault::Reset, 1.0)
+            .new_transform(srv.into_service());
+        let mut resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert!(read_body(&mut resp).await.is_err());
+
+        let mw = Chaos::new()
+            .fault(Fault::Truncate(100), 1.0)
+            .new_transform(srv.into_service());
+        let mut resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(
+            read_body(&mut resp).await.unwrap(),
+            Bytes::from_static(b"0123456789")
+        );
+    }
+}
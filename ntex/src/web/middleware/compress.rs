@@ -1,24 +1,39 @@
 //! `Middleware` for compressing response body.
 use std::task::{Context, Poll};
-use std::{cmp, future::Future, marker, pin::Pin, str::FromStr};
+use std::{cmp, error::Error, future::Future, marker, mem, pin::Pin, rc::Rc, str::FromStr};
 
-use crate::http::encoding::Encoder;
-use crate::http::header::{ContentEncoding, ACCEPT_ENCODING};
+use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::http::encoding::{Encoder, EncoderConfig};
+use crate::http::header::{
+    ContentEncoding, HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_TYPE, VARY,
+};
 use crate::service::{Service, Transform};
+use crate::util::{Bytes, BytesMut};
 use crate::web::{BodyEncoding, ErrorRenderer, WebRequest, WebResponse};
 
 #[derive(Debug, Clone)]
 /// `Middleware` for compressing response body.
 ///
-/// Use `BodyEncoding` trait for overriding response compression.
-/// To disable compression set encoding to `ContentEncoding::Identity` value.
+/// Use `BodyEncoding` trait or `Route::encoding()` for overriding response
+/// compression. To disable compression set encoding to `ContentEncoding::Identity`
+/// value. Responses with explicitly set encoding are not checked against
+/// minimum size and content type filters.
+///
+/// Streaming responses are buffered up to minimum size before middleware
+/// decides to compress response. Supported encodings are `gzip`, `deflate`,
+/// `br` and `zstd`, negotiated responses get `Vary: Accept-Encoding` header.
 ///
 /// ```rust
 /// use ntex::web::{self, middleware, App, HttpResponse};
 ///
 /// fn main() {
 ///     let app = App::new()
-///         .wrap(middleware::Compress::default())
+///         .wrap(
+///             middleware::Compress::default()
+///                 .min_size(1024)
+///                 .level(6)
+///                 .exclude_content_type("image/")
+///         )
 ///         .service(
 ///             web::resource("/test")
 ///                 .route(web::get().to(|| async { HttpResponse::Ok() }))
@@ -27,13 +42,81 @@ use crate::web::{BodyEncoding, ErrorRenderer, WebRequest, WebResponse};
 /// }
 /// ```
 pub struct Compress {
+    inner: Rc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
     enc: ContentEncoding,
+    config: EncoderConfig,
+    min_size: usize,
+    allow: Vec<String>,
+    deny: Vec<String>,
 }
 
 impl Compress {
     /// Create new `Compress` middleware with default encoding.
     pub fn new(encoding: ContentEncoding) -> Self {
-        Compress { enc: encoding }
+        Compress {
+            inner: Rc::new(Inner {
+                enc: encoding,
+                config: EncoderConfig::new(),
+                min_size: 0,
+                allow: Vec::new(),
+                deny: Vec::new(),
+            }),
+        }
+    }
+
+    /// Set compression level.
+    ///
+    /// Level is clamped to the range of negotiated algorithm, gzip and deflate
+    /// levels are 0-9, brotli levels are 0-11 and zstd levels are 1-21.
+    /// By default fast compression level is used.
+    pub fn level(mut self, level: u32) -> Self {
+        let inner = self.inner_mut();
+        inner.config = mem::take(&mut inner.config).level(level);
+        self
+    }
+
+    /// Set encoder settings.
+    ///
+    /// Replaces previously set compression level. Allows to configure
+    /// per-algorithm levels, brotli and zstd windows and encoders budget.
+    pub fn encoder_config(mut self, config: EncoderConfig) -> Self {
+        self.inner_mut().config = config;
+        self
+    }
+
+    /// Set minimum size of response body for compression.
+    ///
+    /// Smaller responses are sent uncompressed. By default all responses
+    /// are compressed.
+    pub fn min_size(mut self, size: usize) -> Self {
+        self.inner_mut().min_size = size;
+        self
+    }
+
+    /// Compress responses with specified content type.
+    ///
+    /// Value is a prefix of content type, for example `text/` or
+    /// `application/json`. If no content types are allowed explicitly,
+    /// responses with any content type are compressed.
+    pub fn content_type(mut self, prefix: &str) -> Self {
+        self.inner_mut().allow.push(prefix.to_lowercase());
+        self
+    }
+
+    /// Do not compress responses with specified content type.
+    ///
+    /// Value is a prefix of content type, for example `image/`.
+    pub fn exclude_content_type(mut self, prefix: &str) -> Self {
+        self.inner_mut().deny.push(prefix.to_lowercase());
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("Multiple copies exist")
     }
 }
 
@@ -43,20 +126,37 @@ impl Default for Compress {
     }
 }
 
+impl Inner {
+    fn content_type_allowed(&self, res: &WebResponse) -> bool {
+        if self.allow.is_empty() && self.deny.is_empty() {
+            return true;
+        }
+        let ctype = res
+            .headers()
+            .get(&CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_lowercase())
+            .unwrap_or_default();
+
+        (self.allow.is_empty() || self.allow.iter().any(|p| ctype.starts_with(p)))
+            && !self.deny.iter().any(|p| ctype.starts_with(p))
+    }
+}
+
 impl<S> Transform<S> for Compress {
     type Service = CompressMiddleware<S>;
 
     fn new_transform(&self, service: S) -> Self::Service {
         CompressMiddleware {
             service,
-            encoding: self.enc,
+            inner: self.inner.clone(),
         }
     }
 }
 
 pub struct CompressMiddleware<S> {
     service: S,
-    encoding: ContentEncoding,
+    inner: Rc<Inner>,
 }
 
 impl<S, E> Service<WebRequest<E>> for CompressMiddleware<S>
@@ -82,7 +182,7 @@ where
         // negotiate content-encoding
         let encoding = if let Some(val) = req.headers().get(&ACCEPT_ENCODING) {
             if let Ok(enc) = val.to_str() {
-                AcceptEncoding::parse(enc, self.encoding)
+                AcceptEncoding::parse(enc, self.inner.enc)
             } else {
                 ContentEncoding::Identity
             }
@@ -92,6 +192,8 @@ where
 
         CompressResponse {
             encoding,
+            inner: self.inner.clone(),
+            buffer: None,
             fut: self.service.call(req),
             _t: marker::PhantomData,
         }
@@ -105,6 +207,8 @@ pin_project_lite::pin_project! {
         #[pin]
         fut: S::Future,
         encoding: ContentEncoding,
+        inner: Rc<Inner>,
+        buffer: Option<(WebResponse, ResponseBody<Body>, BytesMut)>,
         _t: marker::PhantomData<E>,
     }
 }
@@ -116,26 +220,151 @@ where
 {
     type Output = Result<WebResponse, S::Error>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.project();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.as_mut().project();
+
+        // buffer streaming response until min size
+        if let Some((resp, mut body, mut buf)) = this.buffer.take() {
+            let enc = *this.encoding;
+            loop {
+                match body.poll_next_chunk(cx) {
+                    Poll::Ready(Some(Ok(chunk))) => {
+                        buf.extend_from_slice(&chunk);
+                        if buf.len() >= this.inner.min_size {
+                            let body = Buffered {
+                                prefix: Some(buf.freeze()),
+                                body: Some(body),
+                                error: None,
+                            };
+                            return Poll::Ready(Ok(encode(resp, enc, this.inner, body)));
+                        }
+                    }
+                    Poll::Ready(None) => {
+                        let body = ResponseBody::Other(Body::Bytes(buf.freeze()));
+                        return Poll::Ready(Ok(resp.map_body(|_, _| body)));
+                    }
+                    Poll::Ready(Some(Err(e))) => {
+                        let body = Buffered {
+                            prefix: Some(buf.freeze()),
+                            body: None,
+                            error: Some(e),
+                        };
+                        return Poll::Ready(Ok(encode(
+                            resp,
+                            ContentEncoding::Identity,
+                            this.inner,
+                            body,
+                        )));
+                    }
+                    Poll::Pending => {
+                        *this.buffer = Some((resp, body, buf));
+                        return Poll::Pending;
+                    }
+                }
+            }
+        }
 
         match this.fut.poll(cx)? {
-            Poll::Ready(resp) => {
+            Poll::Ready(mut resp) => {
                 let enc = if let Some(enc) = resp.response().get_encoding() {
                     enc
+                } else if !this.inner.content_type_allowed(&resp) {
+                    ContentEncoding::Identity
                 } else {
-                    *this.encoding
+                    // response depends on request's accept-encoding
+                    add_vary(resp.headers_mut());
+
+                    match resp.response().body().size() {
+                        BodySize::Sized(size) if (size as usize) < this.inner.min_size => {
+                            ContentEncoding::Identity
+                        }
+                        BodySize::Stream
+                            if this.inner.min_size > 0
+                                && *this.encoding != ContentEncoding::Identity =>
+                        {
+                            let body = resp.take_body();
+                            *this.buffer = Some((resp, body, BytesMut::new()));
+                            return self.poll(cx);
+                        }
+                        _ => *this.encoding,
+                    }
                 };
 
-                Poll::Ready(Ok(
-                    resp.map_body(move |head, body| Encoder::response(enc, head, body))
-                ))
+                let inner = this.inner.clone();
+                Poll::Ready(Ok(resp.map_body(move |head, body| {
+                    Encoder::response_with_config(enc, &inner.config, head, body)
+                })))
             }
             Poll::Pending => Poll::Pending,
         }
     }
 }
 
+fn encode(
+    resp: WebResponse,
+    enc: ContentEncoding,
+    inner: &Inner,
+    body: Buffered,
+) -> WebResponse {
+    resp.map_body(move |head, _| {
+        let body = ResponseBody::Other(Body::from_message(body));
+        Encoder::response_with_config(enc, &inner.config, head, body)
+    })
+}
+
+/// Add `accept-encoding` to `Vary` header
+fn add_vary(headers: &mut HeaderMap) {
+    let exists = headers.get_all(VARY).any(|val| {
+        val.to_str()
+            .map(|s| {
+                s.split(',').any(|item| {
+                    let item = item.trim();
+                    item == "*" || item.eq_ignore_ascii_case("accept-encoding")
+                })
+            })
+            .unwrap_or(false)
+    });
+    if !exists {
+        headers.append(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+}
+
+/// Streaming body with buffered prefix
+struct Buffered {
+    prefix: Option<Bytes>,
+    body: Option<ResponseBody<Body>>,
+    error: Option<Box<dyn Error>>,
+}
+
+impl MessageBody for Buffered {
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if let Some(prefix) = self.prefix.take() {
+            if !prefix.is_empty() {
+                return Poll::Ready(Some(Ok(prefix)));
+            }
+        }
+        if let Some(e) = self.error.take() {
+            return Poll::Ready(Some(Err(e)));
+        }
+        if let Some(ref mut body) = self.body {
+            body.poll_next_chunk(cx)
+        } else {
+            Poll::Ready(None)
+        }
+    }
+
+    fn take_flush(&mut self) -> bool {
+        self.body.as_mut().map(|b| b.take_flush()).unwrap_or(false)
+    }
+}
+
 struct AcceptEncoding {
     encoding: ContentEncoding,
     quality: f64,
@@ -177,9 +406,17 @@ impl AcceptEncoding {
         };
         let quality = match parts.len() {
             1 => encoding.quality(),
-            _ => f64::from_str(parts[1]).unwrap_or(0.0),
+            _ => {
+                let q = parts[1].trim();
+                f64::from_str(q.strip_prefix("q=").unwrap_or(q)).unwrap_or(0.0)
+            }
         };
-        Some(AcceptEncoding { encoding, quality })
+        // "q=0" means encoding is not acceptable
+        if quality <= 0.0 {
+            None
+        } else {
+            Some(AcceptEncoding { encoding, quality })
+        }
     }
 
     /// Parse a raw Accept-Encoding header value into an ordered list.
@@ -201,3 +438,287 @@ impl AcceptEncoding {
         ContentEncoding::Identity
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+    use crate::http::header::{self, CONTENT_ENCODING};
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    fn chunks(n: usize) -> HttpResponse {
+        HttpResponse::Ok().streaming(stream::iter(
+            (0..n).map(|_| Ok::<_, std::io::Error>(Bytes::from_static(b"0123456789"))),
+        ))
+    }
+
+    #[crate::rt_test]
+    async fn test_min_size() {
+        let srv = init_service(
+            App::new()
+                .wrap(Compress::default().min_size(100))
+                .route("/small", web::get().to(|| async { "0123456789" }))
+                .route(
+                    "/large",
+                    web::get().to(|| async { "0123456789".repeat(20) }),
+                )
+                .route("/stream-small", web::get().to(|| async { chunks(2) }))
+                .route("/stream-large", web::get().to(|| async { chunks(20) })),
+        )
+        .await;
+
+        let req = |path| {
+            TestRequest::with_uri(path)
+                .header(ACCEPT_ENCODING, "gzip")
+                .to_request()
+        };
+
+        let res = call_service(&srv, req("/small")).await;
+        assert!(!res.headers().contains_key(CONTENT_ENCODING));
+        let res = call_service(&srv, req("/large")).await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+
+        let res = call_service(&srv, req("/stream-small")).await;
+        assert!(!res.headers().contains_key(CONTENT_ENCODING));
+        assert_eq!(res.response().body().size(), BodySize::Sized(20));
+        assert_eq!(read_body(res).await, "0123456789".repeat(2));
+
+        let res = call_service(&srv, req("/stream-large")).await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        let body = read_body(res).await;
+        assert!(!body.is_empty() && body.len() < 200);
+    }
+
+    #[crate::rt_test]
+    async fn test_content_type() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    Compress::default()
+                        .content_type("text/")
+                        .content_type("application/json")
+                        .exclude_content_type("text/event-stream"),
+                )
+                .route(
+                    "/{ct1}/{ct2}",
+                    web::get().to(|req: web::HttpRequest| async move {
+                        let ct = format!(
+                            "{}/{}",
+                            req.match_info().get("ct1").unwrap(),
+                            req.match_info().get("ct2").unwrap()
+                        );
+                        HttpResponse::Ok()
+                            .header(header::CONTENT_TYPE, ct)
+                            .body("0123456789")
+                    }),
+                ),
+        )
+        .await;
+
+        for (path, compressed) in &[
+            ("/text/plain", true),
+            ("/application/json", true),
+            ("/image/png", false),
+            ("/text/event-stream", false),
+        ] {
+            let req = TestRequest::with_uri(path)
+                .header(ACCEPT_ENCODING, "gzip")
+                .to_request();
+            let res = call_service(&srv, req).await;
+            assert_eq!(res.headers().contains_key(CONTENT_ENCODING), *compressed);
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_negotiation() {
+        let srv = init_service(
+            App::new()
+                .wrap(Compress::default().level(19))
+                .route("/", web::get().to(|| async { "0123456789".repeat(20) })),
+        )
+        .await;
+
+        let req = |enc| {
+            TestRequest::with_uri("/")
+                .header(ACCEPT_ENCODING, enc)
+                .to_request()
+        };
+
+        let res = call_service(&srv, req("gzip;q=0.5, zstd")).await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "zstd");
+        assert_eq!(res.headers().get(VARY).unwrap(), "accept-encoding");
+        let body = read_body(res).await;
+        assert_eq!(
+            zstd::stream::decode_all(&body[..]).unwrap(),
+            "0123456789".repeat(20).as_bytes()
+        );
+
+        let res = call_service(&srv, req("gzip;q=0.5, deflate;q=0.8")).await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "deflate");
+
+        // not acceptable encoding
+        let res = call_service(&srv, req("zstd;q=0, gzip;q=0.1")).await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+
+        // vary is set for uncompressed responses as well
+        let res = call_service(&srv, TestRequest::with_uri("/").to_request()).await;
+        assert!(!res.headers().contains_key(CONTENT_ENCODING));
+        assert_eq!(res.headers().get(VARY).unwrap(), "accept-encoding");
+    }
+
+    #[crate::rt_test]
+    async fn test_encoder_config() {
+        use std::io::Write;
+
+        let data = "0123456789".repeat(1024);
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    Compress::default().encoder_config(
+                        EncoderConfig::new()
+                            .brotli_quality(9)
+                            .brotli_window(18)
+                            .zstd_level(12)
+                            .zstd_window_log(20)
+                            .zstd_long_distance_matching(true),
+                    ),
+                )
+                .route("/", web::get().to(|| async { "0123456789".repeat(1024) }))
+                .service(
+                    web::scope("/budget")
+                        .wrap(
+                            Compress::default()
+                                .encoder_config(EncoderConfig::new().level(9).budget(0)),
+                        )
+                        .route("/", web::get().to(|| async { "0123456789".repeat(1024) })),
+                ),
+        )
+        .await;
+
+        let req = |path, enc| {
+            TestRequest::with_uri(path)
+                .header(ACCEPT_ENCODING, enc)
+                .to_request()
+        };
+
+        let res = call_service(&srv, req("/", "zstd")).await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "zstd");
+        let body = read_body(res).await;
+        assert_eq!(
+            zstd::stream::decode_all(&body[..]).unwrap(),
+            data.as_bytes()
+        );
+
+        let res = call_service(&srv, req("/", "br")).await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "br");
+        let body = read_body(res).await;
+        let mut decoder = brotli2::write::BrotliDecoder::new(Vec::new());
+        decoder.write_all(&body).unwrap();
+        assert_eq!(decoder.finish().unwrap(), data.as_bytes());
+
+        // budget is exceeded, fastest level is used
+        let res = call_service(&srv, req("/budget/", "gzip")).await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        let body = read_body(res).await;
+        let mut decoder = flate2::write::GzDecoder::new(Vec::new());
+        decoder.write_all(&body).unwrap();
+        assert_eq!(decoder.finish().unwrap(), data.as_bytes());
+    }
+
+    #[crate::rt_test]
+    async fn test_vary() {
+        let mut headers = HeaderMap::new();
+        add_vary(&mut headers);
+        add_vary(&mut headers);
+        assert_eq!(headers.get_all(VARY).count(), 1);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(VARY, HeaderValue::from_static("Origin, Accept-Encoding"));
+        add_vary(&mut headers);
+        assert_eq!(headers.get_all(VARY).count(), 1);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(VARY, HeaderValue::from_static("origin"));
+        add_vary(&mut headers);
+        assert_eq!(headers.get_all(VARY).count(), 2);
+    }
+
+    #[crate::rt_test]
+    async fn test_streaming_flush() {
+        use crate::http::body::BodyItem;
+        use crate::util::lazy;
+        use futures::StreamExt;
+
+        let srv = init_service(App::new().wrap(Compress::default()).route(
+            "/",
+            web::get().to(|| async {
+                HttpResponse::Ok().streaming(
+                    stream::iter(vec![
+                        Ok::<_, std::io::Error>(BodyItem::from(Bytes::from_static(
+                            b"data",
+                        ))),
+                        Ok(BodyItem::Flush),
+                    ])
+                    .chain(stream::pending()),
+                )
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::with_uri("/")
+            .header(ACCEPT_ENCODING, "gzip")
+            .to_request();
+        let mut res = call_service(&srv, req).await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+
+        // flushed compressed data is available before stream completes
+        let mut body = res.take_body();
+        let mut decoder = flate2::write::GzDecoder::new(Vec::new());
+        loop {
+            match lazy(|cx| body.poll_next_chunk(cx)).await {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    std::io::Write::write_all(&mut decoder, &chunk).unwrap();
+                    if body.take_flush() {
+                        break;
+                    }
+                }
+                _ => panic!("flush is expected"),
+            }
+        }
+        std::io::Write::flush(&mut decoder).unwrap();
+        assert_eq!(decoder.get_ref(), b"data");
+    }
+
+    #[crate::rt_test]
+    async fn test_route_encoding() {
+        let srv = init_service(
+            App::new().wrap(Compress::default().min_size(100)).service((
+                web::resource("/identity").route(
+                    web::get()
+                        .encoding(ContentEncoding::Identity)
+                        .to(|| async { "0123456789".repeat(20) }),
+                ),
+                web::resource("/deflate").route(
+                    web::get()
+                        .encoding(ContentEncoding::Deflate)
+                        .to(|| async { "0123456789" }),
+                ),
+            )),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/identity")
+            .header(ACCEPT_ENCODING, "gzip")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert!(!res.headers().contains_key(CONTENT_ENCODING));
+
+        let req = TestRequest::with_uri("/deflate")
+            .header(ACCEPT_ENCODING, "gzip")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "deflate");
+    }
+}
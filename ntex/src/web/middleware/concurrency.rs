@@ -0,0 +1,286 @@
+//! Middleware for limiting number of concurrent requests
+use std::task::{Context, Poll};
+use std::{
+    cell::Cell, cell::RefCell, collections::VecDeque, future::Future, pin::Pin, rc::Rc,
+};
+
+use crate::channel::oneshot;
+use crate::http::{Response, StatusCode};
+use crate::service::{Service, Transform};
+use crate::time::{timeout, Millis};
+use crate::web::{WebRequest, WebResponse};
+
+/// `Middleware` for limiting number of concurrently processed requests.
+///
+/// Middleware is intended for wrapping expensive resources or scopes,
+/// it does not affect readiness of the application, so other routes keep
+/// accepting requests while limit is reached. By default requests above
+/// the limit are rejected immediately with `503 Service Unavailable`
+/// response, with queuing enabled requests wait for a free slot in
+/// fifo order.
+///
+/// Limit is applied per worker thread.
+///
+/// ```rust
+/// use ntex::time::Seconds;
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/report")
+///             .wrap(middleware::ConcurrencyLimit::new(2).queue(Seconds(10)))
+///             .route(web::get().to(|| async { HttpResponse::Ok() })),
+///     );
+/// }
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct ConcurrencyLimit {
+    max: usize,
+    queue: bool,
+    max_queued: usize,
+    timeout: Millis,
+}
+
+impl ConcurrencyLimit {
+    /// Construct `ConcurrencyLimit` middleware with max number of
+    /// concurrent requests.
+    pub fn new(max: usize) -> Self {
+        ConcurrencyLimit {
+            max,
+            queue: false,
+            max_queued: usize::MAX,
+            timeout: Millis::ZERO,
+        }
+    }
+
+    /// Queue requests above the limit.
+    ///
+    /// Request is rejected if it could not be started within specified
+    /// timeout. Zero timeout means requests wait indefinitely.
+    pub fn queue<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        self.queue = true;
+        self.timeout = timeout.into();
+        self
+    }
+
+    /// Set max number of queued requests.
+    ///
+    /// Requests above this number are rejected immediately.
+    /// By default queue size is not limited.
+    pub fn max_queued(mut self, num: usize) -> Self {
+        self.max_queued = num;
+        self
+    }
+}
+
+impl<S> Transform<S> for ConcurrencyLimit {
+    type Service = ConcurrencyLimitMiddleware<S>;
+
+    fn new_transform(&self, service: S) -> Self::Service {
+        ConcurrencyLimitMiddleware {
+            service: Rc::new(service),
+            cfg: *self,
+            inner: Rc::new(Inner {
+                max: self.max,
+                active: Cell::new(0),
+                waiters: RefCell::new(VecDeque::new()),
+            }),
+        }
+    }
+}
+
+pub struct ConcurrencyLimitMiddleware<S> {
+    service: Rc<S>,
+    cfg: ConcurrencyLimit,
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    max: usize,
+    active: Cell<usize>,
+    waiters: RefCell<VecDeque<oneshot::Sender<Permit>>>,
+}
+
+impl Inner {
+    fn acquire(self: &Rc<Self>) -> Option<Permit> {
+        if self.active.get() < self.max {
+            self.active.set(self.active.get() + 1);
+            Some(Permit(Some(self.clone())))
+        } else {
+            None
+        }
+    }
+}
+
+/// Slot of the limit, released on drop
+struct Permit(Option<Rc<Inner>>);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(inner) = self.0.take() {
+            // pass slot to the first waiting request
+            let mut permit = Permit(Some(inner.clone()));
+            loop {
+                let tx = inner.waiters.borrow_mut().pop_front();
+                if let Some(tx) = tx {
+                    match tx.send(permit) {
+                        Ok(_) => return,
+                        Err(p) => permit = p,
+                    }
+                } else {
+                    permit.0 = None;
+                    inner.active.set(inner.active.get() - 1);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl<S, E> Service<WebRequest<E>> for ConcurrencyLimitMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse> + 'static,
+    E: 'static,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        if let Some(permit) = self.inner.acquire() {
+            let fut = self.service.call(req);
+            return Box::pin(async move {
+                let res = fut.await;
+                drop(permit);
+                res
+            });
+        }
+
+        let rx = if self.cfg.queue {
+            let mut waiters = self.inner.waiters.borrow_mut();
+            waiters.retain(|tx| !tx.is_canceled());
+            if waiters.len() < self.cfg.max_queued {
+                let (tx, rx) = oneshot::channel();
+                waiters.push_back(tx);
+                Some(rx)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        let rx = if let Some(rx) = rx {
+            rx
+        } else {
+            log::trace!("Concurrency limit exceeded, reject request");
+            let res = req.into_response(Response::new(StatusCode::SERVICE_UNAVAILABLE));
+            return Box::pin(async move { Ok(res) });
+        };
+
+        let service = self.service.clone();
+        let tm = self.cfg.timeout;
+        Box::pin(async move {
+            let permit = if tm.is_zero() {
+                rx.await.ok()
+            } else {
+                timeout(tm, rx).await.ok().and_then(|res| res.ok())
+            };
+            if let Some(permit) = permit {
+                let res = service.call(req).await;
+                drop(permit);
+                res
+            } else {
+                log::trace!("Concurrency limit queue timeout, reject request");
+                Ok(req.into_response(Response::new(StatusCode::SERVICE_UNAVAILABLE)))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::sleep;
+    use crate::util::{join_all, Bytes};
+    use crate::web::test::{init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    #[crate::rt_test]
+    async fn test_reject() {
+        let srv = init_service(
+            App::new().service(web::resource("/").wrap(ConcurrencyLimit::new(1)).to(
+                || async {
+                    sleep(Millis(50)).await;
+                    HttpResponse::Ok()
+                },
+            )),
+        )
+        .await;
+
+        let res = join_all(vec![
+            srv.call(TestRequest::default().to_request()),
+            srv.call(TestRequest::default().to_request()),
+        ])
+        .await;
+        assert_eq!(res[0].as_ref().unwrap().status(), StatusCode::OK);
+        assert_eq!(
+            res[1].as_ref().unwrap().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        // slot is released
+        let res = srv.call(TestRequest::default().to_request()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_queue() {
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::resource("/")
+                        .wrap(ConcurrencyLimit::new(1).queue(Millis(150)).max_queued(2))
+                        .to(|req: web::HttpRequest| async move {
+                            sleep(Millis(100)).await;
+                            HttpResponse::Ok().body(req.query_string().to_string())
+                        }),
+                )
+                .route("/other", web::to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let futs: Vec<_> = (0..4)
+            .map(|idx| srv.call(TestRequest::with_uri(&format!("/?{}", idx)).to_request()))
+            .collect();
+
+        // other routes are not affected
+        let res = srv
+            .call(TestRequest::with_uri("/other").to_request())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let mut res = join_all(futs).await.into_iter().map(|res| res.unwrap());
+        let res0 = res.next().unwrap();
+        assert_eq!(read_body(res0).await, Bytes::from_static(b"0"));
+        let res1 = res.next().unwrap();
+        assert_eq!(read_body(res1).await, Bytes::from_static(b"1"));
+        // queue timeout
+        let res2 = res.next().unwrap();
+        assert_eq!(res2.status(), StatusCode::SERVICE_UNAVAILABLE);
+        // queue is full
+        let res3 = res.next().unwrap();
+        assert_eq!(res3.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -0,0 +1,359 @@
+//! Middleware for response content digest generation
+use std::task::{Context, Poll};
+use std::{error::Error, future::Future, pin::Pin, rc::Rc};
+
+use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::http::digest::{self, Digest, DigestAlgorithm};
+use crate::http::header::{HeaderMap, CONTENT_ENCODING};
+use crate::service::{Service, Transform};
+use crate::util::{poll_fn, Bytes, BytesMut};
+use crate::web::{WebRequest, WebResponse};
+
+/// `Middleware` for generating `Content-Digest` response header,
+/// [RFC 9530](https://www.rfc-editor.org/rfc/rfc9530).
+///
+/// Digest algorithm is negotiated with `Want-Content-Digest` request header.
+/// Streaming response bodies are buffered up to the configured limit, larger
+/// bodies are sent without digest. Digest is not generated if response
+/// already contains `Content-Digest` header.
+///
+/// Middleware should be registered after `Compress` middleware, so digest
+/// is computed for encoded content.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::ContentDigest::new().repr_digest(true))
+///         .service(
+///             web::resource("/test")
+///                 .route(web::get().to(|| async { HttpResponse::Ok().body("data") }))
+///         );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ContentDigest {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    alg: DigestAlgorithm,
+    repr: bool,
+    max_buffer: usize,
+}
+
+impl Default for ContentDigest {
+    fn default() -> Self {
+        ContentDigest {
+            inner: Rc::new(Inner {
+                alg: DigestAlgorithm::Sha256,
+                repr: false,
+                max_buffer: 262_144,
+            }),
+        }
+    }
+}
+
+impl ContentDigest {
+    /// Construct `ContentDigest` middleware.
+    pub fn new() -> Self {
+        ContentDigest::default()
+    }
+
+    /// Set default digest algorithm.
+    ///
+    /// Algorithm is used if request does not contain `Want-Content-Digest`
+    /// header. By default `sha-256` is used.
+    pub fn algorithm(mut self, alg: DigestAlgorithm) -> Self {
+        self.inner_mut().alg = alg;
+        self
+    }
+
+    /// Generate `Repr-Digest` header for not encoded responses.
+    ///
+    /// By default `Repr-Digest` header is not generated.
+    pub fn repr_digest(mut self, enabled: bool) -> Self {
+        self.inner_mut().repr = enabled;
+        self
+    }
+
+    /// Set max size of buffered streaming response body.
+    ///
+    /// By default limit is set to 256Kb.
+    pub fn max_buffer(mut self, size: usize) -> Self {
+        self.inner_mut().max_buffer = size;
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("Multiple copies exist")
+    }
+}
+
+impl<S> Transform<S> for ContentDigest {
+    type Service = ContentDigestMiddleware<S>;
+
+    fn new_transform(&self, service: S) -> Self::Service {
+        ContentDigestMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct ContentDigestMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for ContentDigestMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    S::Future: 'static,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let inner = self.inner.clone();
+        let content_alg = negotiate(req.headers(), digest::WANT_CONTENT_DIGEST, inner.alg);
+        let repr_alg = if inner.repr {
+            negotiate(req.headers(), digest::WANT_REPR_DIGEST, inner.alg)
+        } else {
+            None
+        };
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let mut res = fut.await?;
+
+            let repr_alg = repr_alg.filter(|_| {
+                !res.headers().contains_key(&CONTENT_ENCODING)
+                    && !res.headers().contains_key(digest::REPR_DIGEST)
+            });
+            let content_alg =
+                content_alg.filter(|_| !res.headers().contains_key(digest::CONTENT_DIGEST));
+            if content_alg.is_none() && repr_alg.is_none() {
+                return Ok(res);
+            }
+
+            let body = match res.take_body() {
+                ResponseBody::Body(body) | ResponseBody::Other(body) => body,
+            };
+            let (body, data) = match body {
+                Body::None => return Ok(res.map_body(|_, _| Body::None.into())),
+                Body::Empty => (Body::Empty, Bytes::new()),
+                Body::Bytes(data) => (Body::Bytes(data.clone()), data),
+                Body::Message(body) => match buffer(body, inner.max_buffer).await {
+                    Ok(data) => (Body::Bytes(data.clone()), data),
+                    Err(body) => {
+                        log::trace!("Response body is too large, skip digest");
+                        return Ok(res.map_body(|_, _| body.into()));
+                    }
+                },
+            };
+
+            let headers = res.headers_mut();
+            if let Some(alg) = content_alg {
+                headers.insert(
+                    digest::header_name(digest::CONTENT_DIGEST),
+                    hash(alg, &data),
+                );
+            }
+            if let Some(alg) = repr_alg {
+                headers.insert(digest::header_name(digest::REPR_DIGEST), hash(alg, &data));
+            }
+            Ok(res.map_body(|_, _| body.into()))
+        })
+    }
+}
+
+fn negotiate(
+    headers: &HeaderMap,
+    name: &'static str,
+    default: DigestAlgorithm,
+) -> Option<DigestAlgorithm> {
+    if let Some(val) = headers.get(name) {
+        DigestAlgorithm::preferred(val)
+    } else {
+        Some(default)
+    }
+}
+
+fn hash(alg: DigestAlgorithm, data: &[u8]) -> crate::http::header::HeaderValue {
+    let mut digest = Digest::new(alg);
+    digest.update(data);
+    digest.header_value()
+}
+
+/// Read streaming body, returns streaming body if it is larger than limit
+async fn buffer(mut body: Box<dyn MessageBody>, limit: usize) -> Result<Bytes, Body> {
+    if let BodySize::Sized(size) = body.size() {
+        if size as usize > limit {
+            return Err(Body::Message(body));
+        }
+    }
+
+    let mut buf = BytesMut::new();
+    loop {
+        match poll_fn(|cx| body.poll_next_chunk(cx)).await {
+            Some(Ok(chunk)) => {
+                buf.extend_from_slice(&chunk);
+                if buf.len() > limit {
+                    return Err(Body::from_message(Buffered {
+                        prefix: Some(buf.freeze()),
+                        body: Some(body),
+                        error: None,
+                    }));
+                }
+            }
+            Some(Err(e)) => {
+                return Err(Body::from_message(Buffered {
+                    prefix: Some(buf.freeze()),
+                    body: None,
+                    error: Some(e),
+                }))
+            }
+            None => return Ok(buf.freeze()),
+        }
+    }
+}
+
+/// Streaming body with buffered prefix
+struct Buffered {
+    prefix: Option<Bytes>,
+    body: Option<Box<dyn MessageBody>>,
+    error: Option<Box<dyn Error>>,
+}
+
+impl MessageBody for Buffered {
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        if let Some(prefix) = self.prefix.take() {
+            if !prefix.is_empty() {
+                return Poll::Ready(Some(Ok(prefix)));
+            }
+        }
+        if let Some(e) = self.error.take() {
+            return Poll::Ready(Some(Err(e)));
+        }
+        if let Some(ref mut body) = self.body {
+            body.poll_next_chunk(cx)
+        } else {
+            Poll::Ready(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::HeaderValue;
+    use crate::service::IntoService;
+    use crate::util::Ready;
+    use crate::web::request::WebRequest;
+    use crate::web::test::{ok_service, read_body, TestRequest};
+    use crate::web::{DefaultError, Error, HttpResponse};
+    use futures::stream::iter;
+
+    fn sha256(data: &[u8]) -> HeaderValue {
+        hash(DigestAlgorithm::Sha256, data)
+    }
+
+    #[crate::rt_test]
+    async fn test_content_digest() {
+        let mw = ContentDigest::new()
+            .repr_digest(true)
+            .new_transform(ok_service());
+
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(
+            resp.headers().get(digest::CONTENT_DIGEST).unwrap(),
+            sha256(b"")
+        );
+        assert_eq!(
+            resp.headers().get(digest::REPR_DIGEST).unwrap(),
+            sha256(b"")
+        );
+
+        let srv = |req: WebRequest<DefaultError>| {
+            Ready::Ok::<_, Error>(
+                req.into_response(
+                    HttpResponse::Ok()
+                        .header(CONTENT_ENCODING, "gzip")
+                        .body("data"),
+                ),
+            )
+        };
+        let mw = ContentDigest::new()
+            .repr_digest(true)
+            .new_transform(srv.into_service());
+        let req = TestRequest::default()
+            .header(digest::WANT_CONTENT_DIGEST, "sha-256=1, sha-512=5")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(
+            resp.headers().get(digest::CONTENT_DIGEST).unwrap(),
+            hash(DigestAlgorithm::Sha512, b"data")
+        );
+        assert!(!resp.headers().contains_key(digest::REPR_DIGEST));
+
+        let req = TestRequest::default()
+            .header(digest::WANT_CONTENT_DIGEST, "md5=1")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert!(!resp.headers().contains_key(digest::CONTENT_DIGEST));
+    }
+
+    #[crate::rt_test]
+    async fn test_streaming() {
+        let srv = |req: WebRequest<DefaultError>| {
+            Ready::Ok::<_, Error>(req.into_response(HttpResponse::Ok().streaming(iter(
+                vec![
+                    Ok::<_, Error>(Bytes::from_static(b"hello ")),
+                    Ok::<_, Error>(Bytes::from_static(b"world")),
+                ],
+            ))))
+        };
+        let mw = ContentDigest::new().new_transform(srv.into_service());
+        let resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert_eq!(
+            resp.headers().get(digest::CONTENT_DIGEST).unwrap(),
+            sha256(b"hello world")
+        );
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"hello world"));
+
+        // body is too large
+        let mw = ContentDigest::new()
+            .max_buffer(8)
+            .new_transform(srv.into_service());
+        let resp = mw
+            .call(TestRequest::default().to_srv_request())
+            .await
+            .unwrap();
+        assert!(!resp.headers().contains_key(digest::CONTENT_DIGEST));
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"hello world"));
+    }
+}
@@ -0,0 +1,178 @@
+//! Middleware for setting `Strict-Transport-Security` header
+use std::task::{Context, Poll};
+use std::{future::Future, pin::Pin, rc::Rc};
+
+use crate::http::header::{HeaderValue, STRICT_TRANSPORT_SECURITY};
+use crate::service::{Service, Transform};
+use crate::tls::types::TlsInfo;
+use crate::web::{WebRequest, WebResponse};
+
+/// `Middleware` for setting `Strict-Transport-Security` header.
+///
+/// Header is set only for requests received over secure transport, tls
+/// connections or requests forwarded by proxy with `https` scheme.
+/// Browsers ignore the header over plain text http (RFC 6797, section 7.2).
+/// By default max-age is set to one year.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Hsts::new().max_age(3600).include_subdomains(true))
+///         .service(
+///             web::resource("/test").route(web::get().to(|| async { HttpResponse::Ok() }))
+///         );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Hsts {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    max_age: u64,
+    subdomains: bool,
+    value: HeaderValue,
+}
+
+impl Default for Hsts {
+    fn default() -> Self {
+        let mut inner = Inner {
+            max_age: 31_536_000,
+            subdomains: false,
+            value: HeaderValue::from_static(""),
+        };
+        inner.update();
+        Hsts {
+            inner: Rc::new(inner),
+        }
+    }
+}
+
+impl Hsts {
+    /// Construct `Hsts` middleware.
+    pub fn new() -> Hsts {
+        Hsts::default()
+    }
+
+    /// Set `max-age` directive, in seconds.
+    pub fn max_age(mut self, max_age: u64) -> Self {
+        let inner = Rc::get_mut(&mut self.inner).expect("Multiple copies exist");
+        inner.max_age = max_age;
+        inner.update();
+        self
+    }
+
+    /// Apply policy to all subdomains.
+    ///
+    /// By default policy is not applied to subdomains.
+    pub fn include_subdomains(mut self, val: bool) -> Self {
+        let inner = Rc::get_mut(&mut self.inner).expect("Multiple copies exist");
+        inner.subdomains = val;
+        inner.update();
+        self
+    }
+}
+
+impl Inner {
+    fn update(&mut self) {
+        let value = if self.subdomains {
+            format!("max-age={}; includeSubDomains", self.max_age)
+        } else {
+            format!("max-age={}", self.max_age)
+        };
+        self.value = HeaderValue::from_str(&value).unwrap();
+    }
+}
+
+impl<S> Transform<S> for Hsts {
+    type Service = HstsMiddleware<S>;
+
+    fn new_transform(&self, service: S) -> Self::Service {
+        HstsMiddleware {
+            service,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct HstsMiddleware<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S, E> Service<WebRequest<E>> for HstsMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    S::Future: 'static,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let secure = req
+            .io()
+            .map(|io| io.query::<TlsInfo>().as_ref().is_some())
+            .unwrap_or(false)
+            || req.connection_info().scheme() == "https";
+        let inner = self.inner.clone();
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let mut res = fut.await?;
+            if secure && !res.headers().contains_key(&STRICT_TRANSPORT_SECURITY) {
+                res.headers_mut()
+                    .insert(STRICT_TRANSPORT_SECURITY, inner.value.clone());
+            }
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{ok_service, TestRequest};
+
+    #[crate::rt_test]
+    async fn test_hsts() {
+        let mw = Hsts::new().new_transform(ok_service());
+
+        // plain text http
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert!(!resp.headers().contains_key(STRICT_TRANSPORT_SECURITY));
+
+        let req = TestRequest::default()
+            .header("x-forwarded-proto", "https")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(
+            resp.headers().get(STRICT_TRANSPORT_SECURITY).unwrap(),
+            "max-age=31536000"
+        );
+
+        let mw = Hsts::new()
+            .max_age(60)
+            .include_subdomains(true)
+            .new_transform(ok_service());
+        let req = TestRequest::with_uri("https://example.com/").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(
+            resp.headers().get(STRICT_TRANSPORT_SECURITY).unwrap(),
+            "max-age=60; includeSubDomains"
+        );
+    }
+}
@@ -5,8 +5,32 @@ mod compress;
 #[cfg(feature = "compress")]
 pub use self::compress::Compress;
 
+#[cfg(feature = "content-digest")]
+mod digest;
+#[cfg(feature = "content-digest")]
+pub use self::digest::ContentDigest;
+
+#[cfg(any(test, feature = "chaos"))]
+mod chaos;
+#[cfg(any(test, feature = "chaos"))]
+pub use self::chaos::{Chaos, Fault, FaultRule};
+
+#[cfg(feature = "tracing")]
+mod tracing;
+#[cfg(feature = "tracing")]
+pub use self::tracing::Tracing;
+
+mod budget;
+pub use self::budget::{MemoryBudget, RequestBudget};
+
+mod concurrency;
+pub use self::concurrency::ConcurrencyLimit;
+
 mod logger;
 pub use self::logger::Logger;
 
 mod defaultheaders;
 pub use self::defaultheaders::DefaultHeaders;
+
+mod hsts;
+pub use self::hsts::Hsts;
@@ -0,0 +1,247 @@
+//! Middleware for request tracing
+use std::task::{Context, Poll};
+use std::{error::Error, future::Future, pin::Pin};
+
+use tracing_pkg::{field, info_span, Instrument, Span};
+
+use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::io::{IoRef, IoStats};
+use crate::service::{Service, Transform};
+use crate::tls::types::HandshakeTime;
+use crate::util::Bytes;
+use crate::web::{WebRequest, WebResponse};
+
+/// `Middleware` for request tracing.
+///
+/// Middleware creates `http.request` span for each request, handler
+/// is executed within the span. Span records request method, path and
+/// response status, as well as connection level events, tls handshake
+/// duration, number of bytes read and written and time spent waiting for
+/// write buffer flush. Io counters belong to the connection, so for
+/// pipelined and http/2 requests they include traffic of concurrent
+/// requests. Io counters are recorded when response body is sent.
+///
+/// Middleware is available only with `tracing` feature.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Tracing::default())
+///         .service(
+///             web::resource("/test")
+///                 .route(web::get().to(|| async { HttpResponse::Ok() }))
+///         );
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Tracing;
+
+impl Tracing {
+    /// Construct `Tracing` middleware.
+    pub fn new() -> Self {
+        Tracing
+    }
+}
+
+impl<S> Transform<S> for Tracing {
+    type Service = TracingMiddleware<S>;
+
+    fn new_transform(&self, service: S) -> Self::Service {
+        TracingMiddleware { service }
+    }
+}
+
+pub struct TracingMiddleware<S> {
+    service: S,
+}
+
+impl<S, E> Service<WebRequest<E>> for TracingMiddleware<S>
+where
+    S: Service<WebRequest<E>, Response = WebResponse>,
+    S::Future: 'static,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let span = info_span!(
+            "http.request",
+            http.method = %req.method(),
+            http.target = %req.path(),
+            http.status_code = field::Empty,
+            tls.handshake_ms = field::Empty,
+            io.bytes_read = field::Empty,
+            io.bytes_written = field::Empty,
+            io.flush_waits = field::Empty,
+            io.flush_wait_ms = field::Empty,
+        );
+
+        let io = req.io().cloned();
+        let stats = io.as_ref().map(|io| {
+            if let Some(HandshakeTime(time)) = io.query::<HandshakeTime>().get() {
+                span.record("tls.handshake_ms", time.as_millis() as u64);
+            }
+            io.stats()
+        });
+
+        let fut = span.in_scope(|| self.service.call(req));
+        Box::pin(async move {
+            let res = fut.instrument(span.clone()).await?;
+            span.record("http.status_code", res.status().as_u16());
+
+            if let (Some(io), Some(stats)) = (io, stats) {
+                Ok(res.map_body(move |_, body| {
+                    ResponseBody::Other(Body::from_message(TracedBody {
+                        body,
+                        io,
+                        stats,
+                        span,
+                    }))
+                }))
+            } else {
+                Ok(res)
+            }
+        })
+    }
+}
+
+/// Response body that records io events to the request span
+struct TracedBody {
+    body: ResponseBody<Body>,
+    io: IoRef,
+    stats: IoStats,
+    span: Span,
+}
+
+impl MessageBody for TracedBody {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        self.body.poll_next_chunk(cx)
+    }
+}
+
+impl Drop for TracedBody {
+    fn drop(&mut self) {
+        let stats = self.io.stats();
+        let waits = stats.flush_waits - self.stats.flush_waits;
+        let wait_ms =
+            (stats.flush_wait_time - self.stats.flush_wait_time).as_millis() as u64;
+
+        self.span
+            .record("io.bytes_read", stats.bytes_read - self.stats.bytes_read)
+            .record(
+                "io.bytes_written",
+                stats.bytes_written - self.stats.bytes_written,
+            )
+            .record("io.flush_waits", waits)
+            .record("io.flush_wait_ms", wait_ms);
+        if waits > 0 {
+            tracing_pkg::debug!(parent: &self.span, waits, wait_ms, "io flush wait");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::{fmt, num::NonZeroU64};
+
+    use tracing_pkg::span::{Attributes, Id, Record};
+    use tracing_pkg::{field::Field, field::Visit, Event, Metadata, Subscriber};
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<(String, String)>>>);
+
+    impl Recorder {
+        fn get(&self, name: &str) -> Option<String> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.clone())
+        }
+    }
+
+    impl Visit for Recorder {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((field.name().to_string(), format!("{:?}", value)));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            span.record(&mut self.clone());
+            Id::from_non_zero_u64(NonZeroU64::new(1).unwrap())
+        }
+
+        fn record(&self, _: &Id, values: &Record<'_>) {
+            values.record(&mut self.clone());
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut self.clone());
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[crate::rt_test]
+    async fn test_tracing() {
+        let recorder = Recorder::default();
+        let _guard = tracing_pkg::subscriber::set_default(recorder.clone());
+
+        let srv = init_service(App::new().wrap(Tracing::new()).route(
+            "/test",
+            web::get().to(|| async {
+                tracing_pkg::info!(handler = true, "in handler");
+                HttpResponse::Created()
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::with_uri("/test").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        assert_eq!(recorder.get("http.method").unwrap(), "GET");
+        assert_eq!(recorder.get("http.target").unwrap(), "/test");
+        assert_eq!(recorder.get("http.status_code").unwrap(), "201");
+        assert_eq!(recorder.get("handler").unwrap(), "true");
+    }
+}
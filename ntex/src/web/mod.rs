@@ -61,6 +61,7 @@
 //! * `compress` - enables content encoding compression support
 //! * `openssl` - enables ssl support via `openssl` crate
 //! * `rustls` - enables ssl support via `rustls` crate
+//! * `session` - enables session management support
 
 mod app;
 mod app_service;
@@ -68,10 +69,12 @@ mod config;
 pub mod error;
 mod error_default;
 mod extract;
+pub mod files;
 pub mod guard;
 mod handler;
 mod httprequest;
 mod info;
+mod meta;
 pub mod middleware;
 mod request;
 mod resource;
@@ -82,6 +85,9 @@ mod route;
 mod scope;
 mod server;
 mod service;
+#[cfg(feature = "session")]
+pub mod session;
+pub mod sse;
 pub mod test;
 pub mod types;
 mod util;
@@ -128,6 +134,7 @@ pub mod dev {
     use super::Handler;
     pub use crate::web::config::AppConfig;
     pub use crate::web::info::ConnectionInfo;
+    pub use crate::web::meta::{RouteInfo, RouteMeta};
     pub use crate::web::rmap::ResourceMap;
     pub use crate::web::route::IntoRoutes;
     pub use crate::web::service::{WebServiceAdapter, WebServiceConfig, WebServiceFactory};
@@ -18,7 +18,8 @@ use super::request::WebRequest;
 use super::responder::Responder;
 use super::response::WebResponse;
 use super::route::{IntoRoutes, Route, RouteService};
-use super::{app::Filter, app::Stack, types::Data};
+use super::types::{Data, RequiredData};
+use super::{app::Filter, app::Stack};
 
 type HttpService<Err: ErrorRenderer> =
     BoxService<WebRequest<Err>, WebResponse, Err::Container>;
@@ -347,10 +348,19 @@ where
         if let Some(ref mut ext) = self.data {
             config.set_service_data(ext);
         }
+        let data = self.data.map(Rc::new);
 
+        // application data required by routes
+        let mut required = RequiredData::default();
+        for route in &self.routes {
+            route.required_data(&mut required);
+        }
+        config.require_data(rdef.pattern(), required, data.clone());
+
+        let routes = self.routes.iter().map(|r| r.info()).collect();
         let router_factory = ResourceRouterFactory {
+            data,
             routes: self.routes,
-            data: self.data.map(Rc::new),
             default: self.default,
         };
 
@@ -363,7 +373,8 @@ where
                 routing: router_factory,
             },
             None,
-        )
+        );
+        config.set_routes(routes);
     }
 }
 
@@ -164,6 +164,90 @@ where
     }
 }
 
+macro_rules! status_responder {
+    ($($tp:ty),*) => {$(
+        impl<Err: ErrorRenderer> Responder<Err> for (StatusCode, $tp) {
+            type Error = Err::Container;
+            type Future = CustomResponderFut<$tp, Err>;
+
+            fn respond_to(self, req: &HttpRequest) -> Self::Future {
+                CustomResponderFut {
+                    fut: Responder::<Err>::respond_to(self.1, req),
+                    status: Some(self.0),
+                    headers: None,
+                }
+            }
+        }
+    )*};
+}
+
+status_responder!(
+    Response,
+    ResponseBuilder,
+    &'static str,
+    &'static [u8],
+    String,
+    Bytes,
+    BytesMut
+);
+
+/// Override status code and set headers of the responder's response.
+///
+/// Headers replace headers with the same name set by inner responder.
+///
+/// ```rust
+/// use ntex::http::{header, StatusCode};
+/// use ntex::web::Responder;
+///
+/// async fn index() -> impl Responder {
+///     (
+///         StatusCode::CREATED,
+///         [(header::LOCATION, header::HeaderValue::from_static("/items/1"))],
+///         "created",
+///     )
+/// }
+/// # fn main() {}
+/// ```
+impl<T, Err, const N: usize> Responder<Err>
+    for (StatusCode, [(HeaderName, HeaderValue); N], T)
+where
+    T: Responder<Err>,
+    Err: ErrorRenderer,
+{
+    type Error = T::Error;
+    type Future = CustomResponderFut<T, Err>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        let mut headers = HeaderMap::with_capacity(N);
+        for (key, value) in self.1 {
+            headers.append(key, value);
+        }
+        CustomResponderFut {
+            fut: self.2.respond_to(req),
+            status: Some(self.0),
+            headers: Some(headers),
+        }
+    }
+}
+
+/// Override status code and set headers of the responder's response.
+impl<T, Err> Responder<Err> for (StatusCode, HeaderMap, T)
+where
+    T: Responder<Err>,
+    Err: ErrorRenderer,
+{
+    type Error = T::Error;
+    type Future = CustomResponderFut<T, Err>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        CustomResponderFut {
+            fut: self.2.respond_to(req),
+            status: Some(self.0),
+            headers: Some(self.1),
+        }
+    }
+}
+
 impl<Err: ErrorRenderer> Responder<Err> for &'static str {
     type Error = Err::Container;
     type Future = Ready<Response>;
@@ -616,4 +700,75 @@ pub(crate) mod tests {
             HeaderValue::from_static("json")
         );
     }
+
+    #[crate::rt_test]
+    async fn test_tuple_responder_status_first() {
+        let req = TestRequest::default().to_http_request();
+        let res = Responder::<DefaultError>::respond_to(
+            (StatusCode::CREATED, "test".to_string()),
+            &req,
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(res.body().get_ref(), b"test");
+
+        let res = Responder::<DefaultError>::respond_to(
+            (
+                StatusCode::BAD_REQUEST,
+                crate::web::types::Json("test".to_string()),
+            ),
+            &req,
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(res.body().get_ref(), b"\"test\"");
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("application/json")
+        );
+
+        let res = Responder::<DefaultError>::respond_to(
+            (
+                StatusCode::ACCEPTED,
+                [
+                    (CONTENT_TYPE, HeaderValue::from_static("json")),
+                    (
+                        HeaderName::from_static("x-test"),
+                        HeaderValue::from_static("111"),
+                    ),
+                ],
+                "test",
+            ),
+            &req,
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert_eq!(res.body().get_ref(), b"test");
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("json")
+        );
+        assert_eq!(
+            res.headers().get("x-test").unwrap(),
+            HeaderValue::from_static("111")
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("json"));
+        let res = Responder::<DefaultError>::respond_to(
+            (
+                StatusCode::NOT_FOUND,
+                headers,
+                crate::web::types::Json("test".to_string()),
+            ),
+            &req,
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.body().get_ref(), b"\"test\"");
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("json")
+        );
+    }
 }
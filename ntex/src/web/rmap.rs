@@ -5,6 +5,8 @@ use url_pkg::Url;
 
 use crate::router::ResourceDef;
 use crate::util::HashMap;
+
+use super::meta::{RouteDefs, RouteInfo};
 #[cfg(feature = "url")]
 use crate::web::httprequest::HttpRequest;
 
@@ -15,6 +17,7 @@ pub struct ResourceMap {
     parent: RefCell<Option<Rc<ResourceMap>>>,
     named: HashMap<String, ResourceDef>,
     patterns: Vec<(ResourceDef, Option<Rc<ResourceMap>>)>,
+    routes: Vec<(u16, RouteDefs)>,
 }
 
 impl ResourceMap {
@@ -24,6 +27,7 @@ impl ResourceMap {
             parent: RefCell::new(None),
             named: HashMap::default(),
             patterns: Vec::new(),
+            routes: Vec::new(),
         }
     }
 
@@ -36,6 +40,66 @@ impl ResourceMap {
         }
     }
 
+    pub(super) fn add_routes(
+        &mut self,
+        pattern: &mut ResourceDef,
+        nested: Option<Rc<ResourceMap>>,
+        routes: RouteDefs,
+    ) {
+        self.add(pattern, nested);
+        if !routes.is_empty() {
+            self.routes.push((pattern.id(), routes));
+        }
+    }
+
+    /// List of registered routes, including routes of nested scopes.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, App, HttpRequest, HttpResponse};
+    ///
+    /// async fn routes(req: HttpRequest) -> HttpResponse {
+    ///     let mut body = String::new();
+    ///     for route in req.resource_map().routes() {
+    ///         body.push_str(&format!("{:?} {}\n", route.methods(), route.path()));
+    ///     }
+    ///     HttpResponse::Ok().body(body)
+    /// }
+    ///
+    /// let app = App::new().service(
+    ///     web::resource("/routes").route(web::get().tag("admin").to(routes)),
+    /// );
+    /// ```
+    pub fn routes(&self) -> Vec<RouteInfo> {
+        let mut routes = Vec::new();
+        self.collect_routes("", &mut routes);
+        routes
+    }
+
+    fn collect_routes(&self, prefix: &str, routes: &mut Vec<RouteInfo>) {
+        for (pattern, nested) in &self.patterns {
+            let path = format!("{}{}", prefix, pattern.pattern());
+            if let Some(ref nested) = nested {
+                nested.collect_routes(&path, routes);
+            } else if let Some((_, defs)) =
+                self.routes.iter().find(|(id, _)| *id == pattern.id())
+            {
+                let resource = if pattern.name().is_empty() {
+                    None
+                } else {
+                    Some(pattern.name().to_string())
+                };
+                for (methods, meta) in defs {
+                    routes.push(RouteInfo {
+                        path: path.clone(),
+                        resource: resource.clone(),
+                        methods: methods.clone(),
+                        meta: meta.clone(),
+                    });
+                }
+            }
+        }
+    }
+
     pub(crate) fn finish(&self, current: Rc<ResourceMap>) {
         for (_, nested) in &self.patterns {
             if let Some(ref nested) = nested {
@@ -1,16 +1,25 @@
 use std::{future::Future, mem, pin::Pin, rc::Rc, task::Context, task::Poll};
 
-use crate::{http::Method, util::Ready, Service, ServiceFactory};
+use crate::http::{header::ContentEncoding, Method};
+use crate::service::boxed::{self, BoxService};
+use crate::util::{Either, Ready};
+use crate::{Service, ServiceFactory, Transform};
 
 use super::error::ErrorRenderer;
 use super::error_default::DefaultError;
 use super::extract::FromRequest;
 use super::guard::{self, Guard};
 use super::handler::{Handler, HandlerFn, HandlerWrapper};
+use super::meta::RouteMeta;
 use super::request::WebRequest;
 use super::responder::Responder;
 use super::response::WebResponse;
-use super::HttpResponse;
+use super::types::RequiredData;
+use super::{BodyEncoding, HttpResponse};
+
+type HttpService<Err> =
+    BoxService<WebRequest<Err>, WebResponse, <Err as ErrorRenderer>::Container>;
+type RouteMiddleware<Err> = Rc<dyn Fn(HttpService<Err>) -> HttpService<Err>>;
 
 /// Resource route definition
 ///
@@ -20,6 +29,9 @@ pub struct Route<Err: ErrorRenderer = DefaultError> {
     handler: Box<dyn HandlerFn<Err>>,
     methods: Vec<Method>,
     guards: Rc<Vec<Box<dyn Guard>>>,
+    encoding: Option<ContentEncoding>,
+    meta: RouteMeta,
+    middleware: Option<RouteMiddleware<Err>>,
 }
 
 impl<Err: ErrorRenderer> Route<Err> {
@@ -29,9 +41,20 @@ impl<Err: ErrorRenderer> Route<Err> {
             handler: Box::new(HandlerWrapper::new(|| async { HttpResponse::NotFound() })),
             methods: Vec::new(),
             guards: Rc::new(Vec::new()),
+            encoding: None,
+            meta: RouteMeta::default(),
+            middleware: None,
         }
     }
 
+    pub(super) fn info(&self) -> (Vec<Method>, RouteMeta) {
+        (self.methods.clone(), self.meta.clone())
+    }
+
+    pub(super) fn required_data(&self, data: &mut RequiredData) {
+        self.handler.required_data(data)
+    }
+
     pub(super) fn take_guards(&mut self) -> Vec<Box<dyn Guard>> {
         for m in &self.methods {
             Rc::get_mut(&mut self.guards)
@@ -43,8 +66,18 @@ impl<Err: ErrorRenderer> Route<Err> {
     }
 
     pub(super) fn service(&self) -> RouteService<Err> {
-        RouteService {
+        let handler = RouteHandler {
             handler: self.handler.clone_handler(),
+            encoding: self.encoding,
+        };
+        let service = if let Some(ref mw) = self.middleware {
+            Either::Right(mw(boxed::service(handler)))
+        } else {
+            Either::Left(handler)
+        };
+
+        RouteService {
+            service,
             guards: self.guards.clone(),
             methods: self.methods.clone(),
         }
@@ -64,7 +97,7 @@ impl<Err: ErrorRenderer> ServiceFactory<WebRequest<Err>> for Route<Err> {
 }
 
 pub struct RouteService<Err: ErrorRenderer> {
-    handler: Box<dyn HandlerFn<Err>>,
+    service: Either<RouteHandler<Err>, HttpService<Err>>,
     methods: Vec<Method>,
     guards: Rc<Vec<Box<dyn Guard>>>,
 }
@@ -89,6 +122,34 @@ impl<Err: ErrorRenderer> Service<WebRequest<Err>> for RouteService<Err> {
     type Error = Err::Container;
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
 
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.service {
+            Either::Left(ref srv) => srv.poll_ready(cx),
+            Either::Right(ref srv) => srv.poll_ready(cx),
+        }
+    }
+
+    #[inline]
+    fn call(&self, req: WebRequest<Err>) -> Self::Future {
+        match self.service {
+            Either::Left(ref srv) => srv.call(req),
+            Either::Right(ref srv) => srv.call(req),
+        }
+    }
+}
+
+/// Route handler service
+struct RouteHandler<Err: ErrorRenderer> {
+    handler: Box<dyn HandlerFn<Err>>,
+    encoding: Option<ContentEncoding>,
+}
+
+impl<Err: ErrorRenderer> Service<WebRequest<Err>> for RouteHandler<Err> {
+    type Response = WebResponse;
+    type Error = Err::Container;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
     #[inline]
     fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         Poll::Ready(Ok(()))
@@ -96,7 +157,18 @@ impl<Err: ErrorRenderer> Service<WebRequest<Err>> for RouteService<Err> {
 
     #[inline]
     fn call(&self, req: WebRequest<Err>) -> Self::Future {
-        self.handler.call(req)
+        if let Some(enc) = self.encoding {
+            let fut = self.handler.call(req);
+            Box::pin(async move {
+                let mut res = fut.await?;
+                if res.response().get_encoding().is_none() {
+                    res.response_mut().encoding(enc);
+                }
+                Ok(res)
+            })
+        } else {
+            self.handler.call(req)
+        }
     }
 }
 
@@ -137,6 +209,70 @@ impl<Err: ErrorRenderer> Route<Err> {
         self
     }
 
+    /// Set response content encoding for the route.
+    ///
+    /// Encoding is used by `Compress` middleware unless handler sets
+    /// response encoding explicitly with `BodyEncoding` trait. Use
+    /// `ContentEncoding::Identity` to disable compression for the route.
+    ///
+    /// ```rust
+    /// use ntex::http::header::ContentEncoding;
+    /// use ntex::web::{self, App, HttpResponse};
+    ///
+    /// let app = App::new().service(
+    ///     web::resource("/health").route(
+    ///         web::get()
+    ///             .encoding(ContentEncoding::Identity)
+    ///             .to(|| async { HttpResponse::Ok() }),
+    ///     ),
+    /// );
+    /// ```
+    pub fn encoding(mut self, encoding: ContentEncoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Set route name.
+    ///
+    /// Name is a documentation metadata, it is not used for url generation.
+    pub fn name(mut self, name: &str) -> Self {
+        self.meta.name = Some(name.to_string());
+        self
+    }
+
+    /// Add route tag.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.meta.tags.push(tag.to_string());
+        self
+    }
+
+    /// Set route description.
+    pub fn description(mut self, description: &str) -> Self {
+        self.meta.description = Some(description.to_string());
+        self
+    }
+
+    /// Add route auth requirement, for example auth scheme name.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, App, HttpResponse};
+    ///
+    /// let app = App::new().service(
+    ///     web::resource("/users/{id}").route(
+    ///         web::get()
+    ///             .name("get_user")
+    ///             .tag("users")
+    ///             .description("Get user by id")
+    ///             .auth("bearer")
+    ///             .to(|| async { HttpResponse::Ok() }),
+    ///     ),
+    /// );
+    /// ```
+    pub fn auth(mut self, requirement: &str) -> Self {
+        self.meta.auth.push(requirement.to_string());
+        self
+    }
+
     /// Set handler function, use request extractors for parameters.
     ///
     /// ```rust
@@ -193,6 +329,49 @@ impl<Err: ErrorRenderer> Route<Err> {
         self.handler = Box::new(HandlerWrapper::new(handler));
         self
     }
+
+    /// Register a route middleware.
+    ///
+    /// Middleware is applied only to the current route, it is called after
+    /// app, scope and resource middlewares. Middlewares get called in opposite
+    /// order of middlewares registration.
+    ///
+    /// ```rust
+    /// use ntex::http::header::{HeaderValue, CACHE_CONTROL};
+    /// use ntex::web::{self, middleware::DefaultHeaders, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new().service(
+    ///         web::resource("/index.html")
+    ///             .route(
+    ///                 web::get()
+    ///                     .to(|| async { HttpResponse::Ok() })
+    ///                     .wrap(DefaultHeaders::new().header(
+    ///                         CACHE_CONTROL,
+    ///                         HeaderValue::from_static("max-age=3600"),
+    ///                     )),
+    ///             )
+    ///             .route(web::post().to(|| async { HttpResponse::Ok() })),
+    ///     );
+    /// }
+    /// ```
+    pub fn wrap<M>(mut self, mw: M) -> Self
+    where
+        M: Transform<HttpService<Err>> + 'static,
+        M::Service: Service<WebRequest<Err>, Response = WebResponse, Error = Err::Container>
+            + 'static,
+    {
+        let inner = self.middleware.take();
+        self.middleware = Some(Rc::new(move |srv| {
+            let srv = if let Some(ref inner) = inner {
+                inner(srv)
+            } else {
+                srv
+            };
+            boxed::service(mw.new_transform(srv))
+        }));
+        self
+    }
 }
 
 /// Convert object to a vec of routes
@@ -269,9 +448,11 @@ array_routes!(12, a, b, c, d, e, f, g, h, i, j, k, l);
 
 #[cfg(test)]
 mod tests {
+    use crate::http::header::{self, HeaderValue};
     use crate::http::{Method, StatusCode};
     use crate::time::{sleep, Millis};
     use crate::util::Bytes;
+    use crate::web::middleware::DefaultHeaders;
     use crate::web::test::{call_service, init_service, read_body, TestRequest};
     use crate::web::{self, error, App, DefaultError, HttpResponse};
 
@@ -346,4 +527,59 @@ mod tests {
         let body = read_body(resp).await;
         assert_eq!(body, Bytes::from_static(b"{\"name\":\"test\"}"));
     }
+
+    #[crate::rt_test]
+    async fn test_route_middleware() {
+        fn hdr(val: &'static str) -> DefaultHeaders {
+            DefaultHeaders::new()
+                .header(header::CONTENT_TYPE, HeaderValue::from_static(val))
+        }
+
+        let srv = init_service(
+            App::new().wrap(hdr("app")).service(
+                web::scope("/admin").wrap(hdr("scope")).service(
+                    web::resource("/test")
+                        .wrap(hdr("resource"))
+                        .route(
+                            web::get()
+                                .to(|| async { HttpResponse::Ok() })
+                                .wrap(hdr("route1"))
+                                .wrap(hdr("route2")),
+                        )
+                        .route(web::post().to(|| async { HttpResponse::Created() })),
+                ),
+            ),
+        )
+        .await;
+
+        // innermost middleware sets header first
+        let req = TestRequest::with_uri("/admin/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("route1")
+        );
+
+        let req = TestRequest::with_uri("/admin/test")
+            .method(Method::POST)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("resource")
+        );
+
+        // method guard is checked before route middleware
+        let req = TestRequest::with_uri("/admin/test")
+            .method(Method::PUT)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("resource")
+        );
+    }
 }
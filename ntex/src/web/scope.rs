@@ -390,11 +390,25 @@ where
             *self.default.borrow_mut() = Some(config.default_service());
         }
 
+        // custom app data storage
+        if let Some(ref mut ext) = self.data {
+            config.set_service_data(ext);
+        }
+        let data = self.data.take().map(Rc::new);
+
         // register nested services
         let mut cfg = config.clone_config();
+        if let Some(ref data) = data {
+            cfg.set_data_container(data.clone());
+        }
+        let idx = cfg.required_data_len();
         self.services
             .into_iter()
             .for_each(|mut srv| srv.register(&mut cfg));
+        if let Some(prefix) = self.rdef.first() {
+            let rdef = ResourceDef::root_prefix(prefix.as_str());
+            cfg.prefix_required_data(idx, rdef.pattern().trim_end_matches('/'));
+        }
 
         let slesh = self.rdef.iter().any(|s| s.ends_with('/'));
         let mut rmap = ResourceMap::new(ResourceDef::root_prefix(self.rdef.clone()));
@@ -404,21 +418,16 @@ where
             rmap.add(&mut rdef, None);
         }
 
-        // custom app data storage
-        if let Some(ref mut ext) = self.data {
-            config.set_service_data(ext);
-        }
-
         // complete scope pipeline creation
         let router_factory = ScopeRouterFactory {
-            data: self.data.take().map(Rc::new),
+            data,
             default: self.default.clone(),
             case_insensitive: self.case_insensitive,
             services: Rc::new(
                 cfg.into_services()
                     .1
                     .into_iter()
-                    .map(|(rdef, srv, guards, nested)| {
+                    .map(|(rdef, srv, guards, nested, routes)| {
                         // case for scope prefix ends with '/' and
                         // resource is empty pattern
                         let mut rdef = if slesh && rdef.pattern() == "" {
@@ -426,7 +435,7 @@ where
                         } else {
                             rdef
                         };
-                        rmap.add(&mut rdef, nested);
+                        rmap.add_routes(&mut rdef, nested, routes);
                         (rdef, srv, RefCell::new(guards))
                     })
                     .collect(),
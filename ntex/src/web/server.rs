@@ -6,10 +6,11 @@ use tls_openssl::ssl::{AlpnError, SslAcceptor, SslAcceptorBuilder};
 use tls_rustls::ServerConfig as RustlsServerConfig;
 
 use crate::http::{
-    body::MessageBody, HttpService, KeepAlive, Request, Response, ResponseError,
+    body::MessageBody, h1::ExpectHandler, HpackConfig, HttpService, HttpsRedirect,
+    KeepAlive, Request, Response, ResponseError,
 };
 use crate::server::{Server, ServerBuilder};
-use crate::{service::map_config, IntoServiceFactory, ServiceFactory};
+use crate::service::{boxed, map_config, IntoServiceFactory, ServiceFactory};
 use crate::{time::Seconds, util::PoolId};
 
 use super::config::AppConfig;
@@ -18,9 +19,51 @@ struct Config {
     host: Option<String>,
     keep_alive: KeepAlive,
     client_timeout: Seconds,
+    headers_timeout: Seconds,
     client_disconnect: Seconds,
     handshake_timeout: Seconds,
+    pipeline: usize,
+    max_requests: usize,
+    hpack: HpackConfig,
     pool: PoolId,
+    expect: Option<Arc<dyn Fn() -> ExpectFactory + Send + Sync>>,
+}
+
+type ExpectFactory = boxed::BoxServiceFactory<(), Request, Request, ExpectError, ()>;
+
+impl Config {
+    fn expect(&self) -> ExpectFactory {
+        if let Some(ref f) = self.expect {
+            f()
+        } else {
+            boxed::factory(
+                ExpectHandler
+                    .map_err(|e| ExpectError(Box::new(e)))
+                    .map_init_err(|_| ()),
+            )
+        }
+    }
+}
+
+/// Error of expect handler
+struct ExpectError(Box<dyn ResponseError>);
+
+impl fmt::Debug for ExpectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for ExpectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl ResponseError for ExpectError {
+    fn error_response(&self) -> Response {
+        self.0.error_response()
+    }
 }
 
 /// An HTTP Server.
@@ -75,9 +118,14 @@ where
                 host: None,
                 keep_alive: KeepAlive::Timeout(Seconds(5)),
                 client_timeout: Seconds(5),
+                headers_timeout: Seconds::ZERO,
                 client_disconnect: Seconds(5),
                 handshake_timeout: Seconds(5),
+                pipeline: 1,
+                max_requests: 0,
+                hpack: HpackConfig::default(),
                 pool: PoolId::P0,
+                expect: None,
             })),
             backlog: 1024,
             builder: ServerBuilder::default(),
@@ -154,6 +202,19 @@ where
         self
     }
 
+    /// Set server client timeout in seconds for reading request headers.
+    ///
+    /// Defines a timeout for reading request headers once first bytes of
+    /// the request are received, for every request on the connection.
+    /// If headers are not received within this time, the request is terminated
+    /// with the 408 (Request Time-out) error.
+    ///
+    /// By default headers timeout is disabled.
+    pub fn client_headers_timeout(self, val: Seconds) -> Self {
+        self.config.lock().unwrap().headers_timeout = val;
+        self
+    }
+
     /// Set server connection disconnect timeout in seconds.
     ///
     /// Defines a timeout for shutdown connection. If a shutdown procedure does not complete
@@ -167,6 +228,64 @@ where
         self
     }
 
+    /// Set max number of concurrently processed pipelined requests.
+    ///
+    /// Pipelined http/1 requests without payload are processed concurrently,
+    /// responses are sent in order of requests.
+    ///
+    /// By default pipelined requests are processed sequentially.
+    pub fn pipeline_concurrency(self, max: usize) -> Self {
+        self.config.lock().unwrap().pipeline = max;
+        self
+    }
+
+    /// Set max number of http/1 requests served by one connection.
+    ///
+    /// Connection is closed after response to the last request.
+    ///
+    /// By default number of requests is not limited.
+    pub fn max_requests_per_connection(self, num: usize) -> Self {
+        self.config.lock().unwrap().max_requests = num;
+        self
+    }
+
+    /// Set HPACK header compression settings for http/2 connections.
+    ///
+    /// By default protocol defaults are used.
+    pub fn hpack(self, cfg: HpackConfig) -> Self {
+        self.config.lock().unwrap().hpack = cfg;
+        self
+    }
+
+    /// Set handler for requests with `Expect: 100-continue` header.
+    ///
+    /// Handler is called with request head before client sends request body.
+    /// Handler could inspect headers (authorization, content length, etc)
+    /// and return request back to continue processing, or reject request
+    /// with an error. Error response is sent to the client as a final response
+    /// instead of `100 Continue` and connection is closed.
+    ///
+    /// By default all requests are continued.
+    pub fn expect<X, XF, U>(self, f: X) -> Self
+    where
+        X: Fn() -> XF + Send + Sync + 'static,
+        XF: IntoServiceFactory<U, Request>,
+        U: ServiceFactory<Request, Response = Request> + 'static,
+        U::Error: ResponseError + 'static,
+        U::InitError: fmt::Debug,
+    {
+        self.config.lock().unwrap().expect = Some(Arc::new(move || {
+            boxed::factory(
+                f().into_factory()
+                    .map_err(|e| ExpectError(Box::new(e)))
+                    .map_init_err(|e| {
+                        log::error!("Cannot construct expect service: {:?}", e)
+                    }),
+            )
+        }));
+        self
+    }
+
     /// Set server ssl handshake timeout in seconds.
     ///
     /// Defines a timeout for connection ssl handshake negotiation.
@@ -249,6 +368,11 @@ where
                     HttpService::build()
                         .keep_alive(c.keep_alive)
                         .client_timeout(c.client_timeout)
+                        .client_headers_timeout(c.headers_timeout)
+                        .pipeline_concurrency(c.pipeline)
+                        .max_requests_per_connection(c.max_requests)
+                        .hpack(c.hpack.clone())
+                        .expect(c.expect())
                         .disconnect_timeout(c.client_disconnect)
                         .finish(map_config(factory(), move |_| cfg.clone()))
                 })?;
@@ -291,6 +415,11 @@ where
                     HttpService::build()
                         .keep_alive(c.keep_alive)
                         .client_timeout(c.client_timeout)
+                        .client_headers_timeout(c.headers_timeout)
+                        .pipeline_concurrency(c.pipeline)
+                        .max_requests_per_connection(c.max_requests)
+                        .hpack(c.hpack.clone())
+                        .expect(c.expect())
                         .disconnect_timeout(c.client_disconnect)
                         .ssl_handshake_timeout(c.handshake_timeout)
                         .finish(map_config(factory(), move |_| cfg.clone()))
@@ -336,6 +465,11 @@ where
                 HttpService::build()
                     .keep_alive(c.keep_alive)
                     .client_timeout(c.client_timeout)
+                    .client_headers_timeout(c.headers_timeout)
+                    .pipeline_concurrency(c.pipeline)
+                    .max_requests_per_connection(c.max_requests)
+                    .hpack(c.hpack.clone())
+                    .expect(c.expect())
                     .disconnect_timeout(c.client_disconnect)
                     .ssl_handshake_timeout(c.handshake_timeout)
                     .finish(map_config(factory(), move |_| cfg.clone()))
@@ -386,6 +520,48 @@ where
         }
     }
 
+    /// Start listening for plain text http connections and redirect
+    /// all requests to https version of the `host`.
+    ///
+    /// Use [`HttpsRedirect`](crate::http::HttpsRedirect) service directly
+    /// for custom configuration, and [`Hsts`](crate::web::middleware::Hsts)
+    /// middleware for setting `Strict-Transport-Security` header on https
+    /// application.
+    pub fn bind_redirect_to_https<A, H>(mut self, addr: A, host: H) -> io::Result<Self>
+    where
+        A: net::ToSocketAddrs,
+        H: AsRef<str>,
+    {
+        let host = host.as_ref().to_string();
+        let sockets = self.bind2(addr)?;
+
+        for lst in sockets {
+            let cfg = self.config.clone();
+            let host = host.clone();
+            let addr = lst.local_addr()?;
+
+            self.builder = self.builder.listen(
+                format!("ntex-web-redirect-{}", addr),
+                lst,
+                move |r| {
+                    let c = cfg.lock().unwrap();
+                    r.memory_pool(c.pool);
+
+                    HttpService::build()
+                        .keep_alive(c.keep_alive)
+                        .client_timeout(c.client_timeout)
+                        .client_headers_timeout(c.headers_timeout)
+                        .pipeline_concurrency(c.pipeline)
+                        .max_requests_per_connection(c.max_requests)
+                        .hpack(c.hpack.clone())
+                        .disconnect_timeout(c.client_disconnect)
+                        .finish(HttpsRedirect::new(&host))
+                },
+            )?;
+        }
+        Ok(self)
+    }
+
     #[cfg(feature = "openssl")]
     /// Start listening for incoming tls connections.
     ///
@@ -448,6 +624,11 @@ where
             HttpService::build()
                 .keep_alive(c.keep_alive)
                 .client_timeout(c.client_timeout)
+                .client_headers_timeout(c.headers_timeout)
+                .pipeline_concurrency(c.pipeline)
+                .max_requests_per_connection(c.max_requests)
+                .hpack(c.hpack.clone())
+                .expect(c.expect())
                 .finish(map_config(factory(), move |_| config.clone()))
         })?;
         Ok(self)
@@ -481,6 +662,11 @@ where
                 HttpService::build()
                     .keep_alive(c.keep_alive)
                     .client_timeout(c.client_timeout)
+                    .client_headers_timeout(c.headers_timeout)
+                    .pipeline_concurrency(c.pipeline)
+                    .max_requests_per_connection(c.max_requests)
+                    .hpack(c.hpack.clone())
+                    .expect(c.expect())
                     .finish(map_config(factory(), move |_| config.clone()))
             },
         )?;
@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::{cell::RefCell, rc::Rc};
 
 use crate::router::{IntoPattern, ResourceDef};
 use crate::service::{boxed, IntoServiceFactory, ServiceFactory};
@@ -8,10 +8,11 @@ use super::config::AppConfig;
 use super::dev::insert_slesh;
 use super::error::ErrorRenderer;
 use super::guard::Guard;
+use super::meta::RouteDefs;
 use super::request::WebRequest;
 use super::response::WebResponse;
 use super::rmap::ResourceMap;
-use super::types::data::DataFactory;
+use super::types::data::{DataFactory, RequiredData};
 
 pub trait WebServiceFactory<Err: ErrorRenderer> {
     fn register(self, config: &mut WebServiceConfig<Err>);
@@ -59,8 +60,19 @@ pub struct WebServiceConfig<Err: ErrorRenderer> {
         HttpServiceFactory<Err>,
         Option<Guards>,
         Option<Rc<ResourceMap>>,
+        RouteDefs,
     )>,
     service_data: Rc<Vec<Box<dyn DataFactory>>>,
+    data: Option<Rc<Extensions>>,
+    required_data: Rc<RefCell<Vec<DataRequirement>>>,
+}
+
+/// Application data required by resource routes
+pub(super) struct DataRequirement {
+    pub(super) path: String,
+    pub(super) data: RequiredData,
+    /// Resource's data container, `None` for application container
+    pub(super) container: Option<Rc<Extensions>>,
 }
 
 impl<Err: ErrorRenderer> WebServiceConfig<Err> {
@@ -76,6 +88,8 @@ impl<Err: ErrorRenderer> WebServiceConfig<Err> {
             service_data,
             root: true,
             services: Vec::new(),
+            data: None,
+            required_data: Rc::new(RefCell::new(Vec::new())),
         }
     }
 
@@ -93,6 +107,7 @@ impl<Err: ErrorRenderer> WebServiceConfig<Err> {
             HttpServiceFactory<Err>,
             Option<Guards>,
             Option<Rc<ResourceMap>>,
+            RouteDefs,
         )>,
     ) {
         (self.config, self.services)
@@ -105,6 +120,8 @@ impl<Err: ErrorRenderer> WebServiceConfig<Err> {
             services: Vec::new(),
             root: false,
             service_data: self.service_data.clone(),
+            data: self.data.clone(),
+            required_data: self.required_data.clone(),
         }
     }
 
@@ -142,8 +159,61 @@ impl<Err: ErrorRenderer> WebServiceConfig<Err> {
                 InitError = (),
             > + 'static,
     {
-        self.services
-            .push((rdef, boxed::factory(factory.into_factory()), guards, nested));
+        self.services.push((
+            rdef,
+            boxed::factory(factory.into_factory()),
+            guards,
+            nested,
+            Vec::new(),
+        ));
+    }
+
+    /// Set data container for services registered with this config
+    pub(super) fn set_data_container(&mut self, data: Rc<Extensions>) {
+        self.data = Some(data);
+    }
+
+    /// Register application data required by resource
+    ///
+    /// If resource does not have own data container, data must be
+    /// available in parent's container.
+    pub(super) fn require_data(
+        &self,
+        path: &str,
+        data: RequiredData,
+        container: Option<Rc<Extensions>>,
+    ) {
+        if !data.is_empty() {
+            self.required_data.borrow_mut().push(DataRequirement {
+                data,
+                path: path.to_string(),
+                container: container.or_else(|| self.data.clone()),
+            });
+        }
+    }
+
+    /// Number of registered data requirements
+    pub(super) fn required_data_len(&self) -> usize {
+        self.required_data.borrow().len()
+    }
+
+    /// Add prefix to paths of data requirements registered after `idx`
+    pub(super) fn prefix_required_data(&self, idx: usize, prefix: &str) {
+        for item in &mut self.required_data.borrow_mut()[idx..] {
+            item.path.insert_str(0, prefix);
+        }
+    }
+
+    /// Take registered data requirements
+    pub(super) fn take_required_data(&self) -> Vec<DataRequirement> {
+        std::mem::take(&mut *self.required_data.borrow_mut())
+    }
+
+    /// Set routes information of last registered service
+    pub(super) fn set_routes(&mut self, routes: RouteDefs) {
+        if let Some(srv) = self.services.last_mut() {
+            srv.4 = routes;
+        }
     }
 }
 
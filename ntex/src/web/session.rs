@@ -0,0 +1,1066 @@
+//! Session management
+//!
+//! `SessionMiddleware` loads session state at the start of a request and
+//! persists it after the response is generated. Session state is accessible
+//! via the `Session` extractor. State is persisted only if it was modified
+//! during request processing.
+//!
+//! Storage is pluggable via the `SessionStore` trait. `CookieSessionStore`
+//! keeps the whole state in the session cookie; other stores (e.g. redis)
+//! keep the state on the server and use the cookie only for the session key.
+//! Session cookie is always signed or encrypted.
+//!
+//! ```rust
+//! use ntex::web::{self, session, App, HttpResponse};
+//!
+//! async fn index(session: session::Session) -> Result<HttpResponse, web::Error> {
+//!     let counter = session.get::<u32>("counter")?.unwrap_or(0) + 1;
+//!     session.insert("counter", counter)?;
+//!     Ok(HttpResponse::Ok().body(format!("Counter: {}", counter)))
+//! }
+//!
+//! fn main() {
+//!     let app = App::new()
+//!         .wrap(session::SessionMiddleware::new(
+//!             session::CookieSessionStore::default(),
+//!             &[0; 32],
+//!         ))
+//!         .service(web::resource("/").to(index));
+//! }
+//! ```
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{cell::RefCell, collections::HashMap, fmt, future::Future, pin::Pin, rc::Rc};
+
+use coo_kie::{Cookie, SameSite};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{aead, hmac};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::http::{HttpMessage, Payload};
+use crate::service::{Service, Transform};
+use crate::util::Ready;
+use crate::web::error::{ErrorRenderer, SessionError, WebResponseError};
+use crate::web::{FromRequest, HttpRequest, WebRequest, WebResponse};
+
+/// Session state, values are stored as json strings
+pub type SessionState = HashMap<String, String>;
+
+/// Session store operation future
+pub type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, SessionError>>>>;
+
+/// Max size of the session cookie value
+const MAX_COOKIE_SIZE: usize = 4000;
+
+const NONCE_LEN: usize = 12;
+
+/// Session storage backend
+pub trait SessionStore: 'static {
+    /// Load session state for the session key.
+    ///
+    /// Returns `None` if session does not exist or is expired.
+    fn load(&self, key: String) -> StoreFuture<Option<SessionState>>;
+
+    /// Persist session state and return session key.
+    ///
+    /// Key is `None` for new sessions, store must generate new key.
+    fn save(
+        &self,
+        key: Option<String>,
+        state: SessionState,
+        ttl: Duration,
+    ) -> StoreFuture<String>;
+
+    /// Extend expiration of the unchanged session and return session key.
+    fn touch(&self, key: String, state: SessionState, ttl: Duration)
+        -> StoreFuture<String>;
+
+    /// Delete session.
+    fn delete(&self, key: String) -> StoreFuture<()>;
+}
+
+/// Session store that keeps session state in the session cookie.
+///
+/// Cookie size is limited, state that does not fit into a cookie
+/// causes `SessionError::Overflow` error.
+#[derive(Debug, Default, Clone)]
+pub struct CookieSessionStore;
+
+#[derive(Serialize, Deserialize)]
+struct CookieState {
+    exp: u64,
+    state: SessionState,
+}
+
+impl CookieSessionStore {
+    fn encode(state: SessionState, ttl: Duration) -> Result<String, SessionError> {
+        let exp = now().saturating_add(ttl.as_secs());
+        Ok(serde_json::to_string(&CookieState { exp, state })?)
+    }
+}
+
+impl SessionStore for CookieSessionStore {
+    fn load(&self, key: String) -> StoreFuture<Option<SessionState>> {
+        let state = serde_json::from_str::<CookieState>(&key)
+            .ok()
+            .filter(|st| st.exp > now())
+            .map(|st| st.state);
+        Box::pin(async move { Ok(state) })
+    }
+
+    fn save(
+        &self,
+        _: Option<String>,
+        state: SessionState,
+        ttl: Duration,
+    ) -> StoreFuture<String> {
+        let res = CookieSessionStore::encode(state, ttl);
+        Box::pin(async move { res })
+    }
+
+    fn touch(&self, _: String, state: SessionState, ttl: Duration) -> StoreFuture<String> {
+        let res = CookieSessionStore::encode(state, ttl);
+        Box::pin(async move { res })
+    }
+
+    fn delete(&self, _: String) -> StoreFuture<()> {
+        Box::pin(async move { Ok(()) })
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// Session cookie content protection
+pub enum CookieContentSecurity {
+    /// Cookie content is encrypted with AES-256-GCM
+    Private,
+    /// Cookie content is signed with HMAC-SHA256, content is readable by client
+    Signed,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// Session state status
+pub enum SessionStatus {
+    /// Session state is not modified
+    Unchanged,
+    /// Session state is modified
+    Changed,
+    /// Session key must be regenerated
+    Renewed,
+    /// Session must be removed
+    Purged,
+}
+
+/// The high-level interface you use to modify session data.
+///
+/// Session object is obtained with `Session` extractor. Middleware
+/// `SessionMiddleware` must be registered.
+#[derive(Clone)]
+pub struct Session(Rc<RefCell<SessionInner>>);
+
+struct SessionInner {
+    state: SessionState,
+    status: SessionStatus,
+}
+
+impl Session {
+    fn new(state: SessionState) -> Self {
+        Session(Rc::new(RefCell::new(SessionInner {
+            state,
+            status: SessionStatus::Unchanged,
+        })))
+    }
+
+    /// Get a `value` from the session.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, SessionError> {
+        if let Some(val) = self.0.borrow().state.get(key) {
+            Ok(Some(serde_json::from_str(val)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Check if session contains value for the `key`.
+    pub fn contains(&self, key: &str) -> bool {
+        self.0.borrow().state.contains_key(key)
+    }
+
+    /// Check if session state is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().state.is_empty()
+    }
+
+    /// Set a `value` in the session.
+    pub fn insert<T: Serialize>(
+        &self,
+        key: impl Into<String>,
+        value: T,
+    ) -> Result<(), SessionError> {
+        let val = serde_json::to_string(&value)?;
+        let mut inner = self.0.borrow_mut();
+        inner.state.insert(key.into(), val);
+        inner.changed();
+        Ok(())
+    }
+
+    /// Remove value from the session.
+    pub fn remove(&self, key: &str) {
+        let mut inner = self.0.borrow_mut();
+        if inner.state.remove(key).is_some() {
+            inner.changed();
+        }
+    }
+
+    /// Clear the session.
+    pub fn clear(&self) {
+        let mut inner = self.0.borrow_mut();
+        if !inner.state.is_empty() {
+            inner.state.clear();
+            inner.changed();
+        }
+    }
+
+    /// Removes session, both client and server side.
+    pub fn purge(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.state.clear();
+        inner.status = SessionStatus::Purged;
+    }
+
+    /// Renews the session key, assigning existing session state to new key.
+    pub fn renew(&self) {
+        self.0.borrow_mut().status = SessionStatus::Renewed;
+    }
+
+    /// Get session status.
+    pub fn status(&self) -> SessionStatus {
+        self.0.borrow().status
+    }
+
+    fn take(&self) -> (SessionStatus, SessionState) {
+        let mut inner = self.0.borrow_mut();
+        let status = std::mem::replace(&mut inner.status, SessionStatus::Unchanged);
+        (status, std::mem::take(&mut inner.state))
+    }
+}
+
+impl SessionInner {
+    fn changed(&mut self) {
+        self.status = match self.status {
+            SessionStatus::Unchanged | SessionStatus::Changed => SessionStatus::Changed,
+            // new state of the purged session gets new key
+            SessionStatus::Renewed | SessionStatus::Purged => SessionStatus::Renewed,
+        };
+    }
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.0.borrow();
+        f.debug_struct("Session")
+            .field("status", &inner.status)
+            .field("state", &inner.state)
+            .finish()
+    }
+}
+
+/// Extractor implementation for `Session` type.
+impl<Err: ErrorRenderer> FromRequest<Err> for Session {
+    type Error = SessionError;
+    type Future = Ready<Session, SessionError>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(session) = req.extensions().get::<Session>() {
+            Ready::Ok(session.clone())
+        } else {
+            log::debug!(
+                "Session middleware is not configured. Request path: {:?}",
+                req.path()
+            );
+            Ready::Err(SessionError::NotConfigured)
+        }
+    }
+}
+
+/// `Middleware` for session management.
+///
+/// Middleware loads session state from the store, makes it available
+/// via `Session` extractor and persists modified state after response
+/// is generated. Session key is stored in the signed or encrypted cookie.
+///
+/// With rolling expiration enabled, session expiration is extended
+/// on every request, otherwise only modified sessions get new expiration.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ntex::web::{self, session, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             session::SessionMiddleware::new(
+///                 session::CookieSessionStore::default(),
+///                 b"0123456789abcdef0123456789abcdef",
+///             )
+///             .cookie_name("sid")
+///             .ttl(Duration::from_secs(3600))
+///             .rolling(true),
+///         )
+///         .service(web::resource("/").to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+pub struct SessionMiddleware<St> {
+    inner: Rc<Inner<St>>,
+}
+
+struct Inner<St> {
+    store: St,
+    signing_key: hmac::Key,
+    encryption_key: aead::LessSafeKey,
+    rng: SystemRandom,
+    security: CookieContentSecurity,
+    name: String,
+    path: String,
+    domain: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+    ttl: Duration,
+    rolling: bool,
+}
+
+impl<St: SessionStore> SessionMiddleware<St> {
+    /// Construct `SessionMiddleware` middleware.
+    ///
+    /// Signing and encryption keys are derived from the `key`.
+    ///
+    /// Panics if `key` is shorter than 32 bytes.
+    pub fn new(store: St, key: &[u8]) -> Self {
+        assert!(key.len() >= 32, "Session key must be at least 32 bytes");
+
+        let master = hmac::Key::new(hmac::HMAC_SHA256, key);
+        let signing = hmac::sign(&master, b"ntex-session-signing");
+        let encryption = hmac::sign(&master, b"ntex-session-encryption");
+        let encryption_key = aead::UnboundKey::new(&aead::AES_256_GCM, encryption.as_ref())
+            .expect("Valid AES-256 key");
+
+        SessionMiddleware {
+            inner: Rc::new(Inner {
+                store,
+                signing_key: hmac::Key::new(hmac::HMAC_SHA256, signing.as_ref()),
+                encryption_key: aead::LessSafeKey::new(encryption_key),
+                rng: SystemRandom::new(),
+                security: CookieContentSecurity::Private,
+                name: "session".to_string(),
+                path: "/".to_string(),
+                domain: None,
+                secure: true,
+                http_only: true,
+                same_site: Some(SameSite::Lax),
+                ttl: Duration::from_secs(24 * 60 * 60),
+                rolling: false,
+            }),
+        }
+    }
+
+    /// Set session cookie name.
+    ///
+    /// By default "session" is used.
+    pub fn cookie_name(mut self, name: impl Into<String>) -> Self {
+        self.inner_mut().name = name.into();
+        self
+    }
+
+    /// Set session cookie path.
+    ///
+    /// By default "/" is used.
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.inner_mut().path = path.into();
+        self
+    }
+
+    /// Set session cookie domain.
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.inner_mut().domain = Some(domain.into());
+        self
+    }
+
+    /// Set `Secure` attribute of the session cookie.
+    ///
+    /// By default `Secure` attribute is set.
+    pub fn secure(mut self, value: bool) -> Self {
+        self.inner_mut().secure = value;
+        self
+    }
+
+    /// Set `HttpOnly` attribute of the session cookie.
+    ///
+    /// By default `HttpOnly` attribute is set.
+    pub fn http_only(mut self, value: bool) -> Self {
+        self.inner_mut().http_only = value;
+        self
+    }
+
+    /// Set `SameSite` attribute of the session cookie.
+    ///
+    /// By default `SameSite=Lax` is used.
+    pub fn same_site(mut self, value: Option<SameSite>) -> Self {
+        self.inner_mut().same_site = value;
+        self
+    }
+
+    /// Set session time-to-live.
+    ///
+    /// By default ttl is set to 24 hours.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.inner_mut().ttl = ttl;
+        self
+    }
+
+    /// Extend session expiration on every request.
+    ///
+    /// By default rolling expiration is disabled.
+    pub fn rolling(mut self, value: bool) -> Self {
+        self.inner_mut().rolling = value;
+        self
+    }
+
+    /// Set session cookie content protection.
+    ///
+    /// By default cookie content is encrypted.
+    pub fn content_security(mut self, value: CookieContentSecurity) -> Self {
+        self.inner_mut().security = value;
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner<St> {
+        Rc::get_mut(&mut self.inner).expect("Multiple copies exist")
+    }
+}
+
+impl<St> Inner<St> {
+    fn cookie(&self, value: String) -> Cookie<'static> {
+        let mut cookie = Cookie::new(self.name.clone(), value);
+        cookie.set_path(self.path.clone());
+        if let Some(ref domain) = self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        cookie.set_secure(self.secure);
+        cookie.set_http_only(self.http_only);
+        if let Some(same_site) = self.same_site {
+            cookie.set_same_site(same_site);
+        }
+        cookie.set_max_age(time::Duration::seconds(
+            self.ttl.as_secs().min(i64::MAX as u64) as i64,
+        ));
+        cookie
+    }
+
+    fn removal_cookie(&self) -> Cookie<'static> {
+        let mut cookie = self.cookie(String::new());
+        cookie.make_removal();
+        cookie
+    }
+
+    /// Sign or encrypt session key
+    fn encode(&self, key: &str) -> Result<String, SessionError> {
+        let value = match self.security {
+            CookieContentSecurity::Signed => {
+                let tag = hmac::sign(&self.signing_key, &self.signed_message(key));
+                format!(
+                    "{}.{}",
+                    base64::encode_config(key, base64::URL_SAFE_NO_PAD),
+                    base64::encode_config(tag.as_ref(), base64::URL_SAFE_NO_PAD)
+                )
+            }
+            CookieContentSecurity::Private => {
+                let mut nonce = [0; NONCE_LEN];
+                self.rng
+                    .fill(&mut nonce)
+                    .map_err(|_| SessionError::Store("Cannot generate nonce".into()))?;
+
+                let mut data = Vec::with_capacity(NONCE_LEN + key.len() + 16);
+                data.extend_from_slice(&nonce);
+                data.extend_from_slice(key.as_bytes());
+                let mut in_out = data.split_off(NONCE_LEN);
+                self.encryption_key
+                    .seal_in_place_append_tag(
+                        aead::Nonce::assume_unique_for_key(nonce),
+                        aead::Aad::from(self.name.as_bytes()),
+                        &mut in_out,
+                    )
+                    .map_err(|_| SessionError::Store("Cannot encrypt session".into()))?;
+                data.extend_from_slice(&in_out);
+                base64::encode_config(&data, base64::URL_SAFE_NO_PAD)
+            }
+        };
+
+        if value.len() > MAX_COOKIE_SIZE {
+            Err(SessionError::Overflow)
+        } else {
+            Ok(value)
+        }
+    }
+
+    /// Verify or decrypt session key
+    fn decode(&self, value: &str) -> Option<String> {
+        match self.security {
+            CookieContentSecurity::Signed => {
+                let (key, tag) = value.rsplit_once('.')?;
+                let key = base64::decode_config(key, base64::URL_SAFE_NO_PAD).ok()?;
+                let tag = base64::decode_config(tag, base64::URL_SAFE_NO_PAD).ok()?;
+                let key = String::from_utf8(key).ok()?;
+                hmac::verify(&self.signing_key, &self.signed_message(&key), &tag).ok()?;
+                Some(key)
+            }
+            CookieContentSecurity::Private => {
+                let data = base64::decode_config(value, base64::URL_SAFE_NO_PAD).ok()?;
+                if data.len() < NONCE_LEN {
+                    return None;
+                }
+                let (nonce, sealed) = data.split_at(NONCE_LEN);
+                let nonce = aead::Nonce::try_assume_unique_for_key(nonce).ok()?;
+                let mut in_out = sealed.to_vec();
+                let key = self
+                    .encryption_key
+                    .open_in_place(
+                        nonce,
+                        aead::Aad::from(self.name.as_bytes()),
+                        &mut in_out,
+                    )
+                    .ok()?;
+                String::from_utf8(key.to_vec()).ok()
+            }
+        }
+    }
+
+    /// Signature covers cookie name, so value cannot be moved to other cookie
+    fn signed_message(&self, key: &str) -> Vec<u8> {
+        let mut msg = Vec::with_capacity(self.name.len() + key.len() + 1);
+        msg.extend_from_slice(self.name.as_bytes());
+        msg.push(b'=');
+        msg.extend_from_slice(key.as_bytes());
+        msg
+    }
+}
+
+impl<S, St> Transform<S> for SessionMiddleware<St> {
+    type Service = SessionMiddlewareService<S, St>;
+
+    fn new_transform(&self, service: S) -> Self::Service {
+        SessionMiddlewareService {
+            service: Rc::new(service),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct SessionMiddlewareService<S, St> {
+    service: Rc<S>,
+    inner: Rc<Inner<St>>,
+}
+
+impl<S, St, E> Service<WebRequest<E>> for SessionMiddlewareService<S, St>
+where
+    S: Service<WebRequest<E>, Response = WebResponse> + 'static,
+    St: SessionStore,
+    E: ErrorRenderer + 'static,
+    SessionError: WebResponseError<E>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let srv = self.service.clone();
+        let inner = self.inner.clone();
+
+        Box::pin(async move {
+            let key = req
+                .cookie(&inner.name)
+                .and_then(|cookie| inner.decode(cookie.value()));
+            let (key, state) = if let Some(key) = key {
+                match inner.store.load(key.clone()).await {
+                    Ok(Some(state)) => (Some(key), state),
+                    Ok(None) => (None, SessionState::new()),
+                    Err(e) => return Ok(req.render_error(e)),
+                }
+            } else {
+                (None, SessionState::new())
+            };
+
+            let session = Session::new(state);
+            req.extensions_mut().insert(session.clone());
+            let mut res = srv.call(req).await?;
+
+            match persist(&inner, key, session.take()).await {
+                Ok(Some(cookie)) => {
+                    if let Err(e) = res.response_mut().add_cookie(&cookie) {
+                        log::error!("Cannot set session cookie: {}", e);
+                    }
+                    Ok(res)
+                }
+                Ok(None) => Ok(res),
+                Err(e) => {
+                    log::error!("Cannot persist session: {}", e);
+                    let req = res.request().clone();
+                    Ok(WebResponse::new(e.error_response(&req), req))
+                }
+            }
+        })
+    }
+}
+
+/// Persist session state, returns session cookie if it has to be updated
+async fn persist<St: SessionStore>(
+    inner: &Inner<St>,
+    key: Option<String>,
+    (status, state): (SessionStatus, SessionState),
+) -> Result<Option<Cookie<'static>>, SessionError> {
+    let key = match status {
+        SessionStatus::Unchanged => match key {
+            Some(key) if inner.rolling => inner.store.touch(key, state, inner.ttl).await?,
+            _ => return Ok(None),
+        },
+        SessionStatus::Changed if !state.is_empty() => {
+            inner.store.save(key, state, inner.ttl).await?
+        }
+        SessionStatus::Renewed if !state.is_empty() => {
+            if let Some(key) = key {
+                inner.store.delete(key).await?;
+            }
+            inner.store.save(None, state, inner.ttl).await?
+        }
+        // empty state, remove session
+        SessionStatus::Changed | SessionStatus::Renewed | SessionStatus::Purged => {
+            return if let Some(key) = key {
+                inner.store.delete(key).await?;
+                Ok(Some(inner.removal_cookie()))
+            } else {
+                Ok(None)
+            };
+        }
+    };
+    Ok(Some(inner.cookie(inner.encode(&key)?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::http::{header, StatusCode};
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    const KEY: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    #[derive(Clone, Default)]
+    struct MemoryStore {
+        sessions: Rc<RefCell<HashMap<String, SessionState>>>,
+        counter: Rc<Cell<usize>>,
+        saves: Rc<Cell<usize>>,
+        touches: Rc<Cell<usize>>,
+    }
+
+    impl SessionStore for MemoryStore {
+        fn load(&self, key: String) -> StoreFuture<Option<SessionState>> {
+            let state = self.sessions.borrow().get(&key).cloned();
+            Box::pin(async move { Ok(state) })
+        }
+
+        fn save(
+            &self,
+            key: Option<String>,
+            state: SessionState,
+            _: Duration,
+        ) -> StoreFuture<String> {
+            self.saves.set(self.saves.get() + 1);
+            let key = key.unwrap_or_else(|| {
+                self.counter.set(self.counter.get() + 1);
+                format!("key-{}", self.counter.get())
+            });
+            self.sessions.borrow_mut().insert(key.clone(), state);
+            Box::pin(async move { Ok(key) })
+        }
+
+        fn touch(&self, key: String, _: SessionState, _: Duration) -> StoreFuture<String> {
+            self.touches.set(self.touches.get() + 1);
+            Box::pin(async move { Ok(key) })
+        }
+
+        fn delete(&self, key: String) -> StoreFuture<()> {
+            self.sessions.borrow_mut().remove(&key);
+            Box::pin(async move { Ok(()) })
+        }
+    }
+
+    fn session_cookie(res: &WebResponse) -> Option<Cookie<'static>> {
+        res.response()
+            .cookies()
+            .find(|c| c.name() == "session")
+            .map(|c| c.into_owned())
+    }
+
+    async fn counter(session: Session) -> Result<String, web::Error> {
+        let counter = session.get::<u32>("counter")?.unwrap_or(0) + 1;
+        session.insert("counter", counter)?;
+        Ok(counter.to_string())
+    }
+
+    async fn get(session: Session) -> Result<String, web::Error> {
+        Ok(session.get::<u32>("counter")?.unwrap_or(0).to_string())
+    }
+
+    #[test]
+    fn test_session_status() {
+        let session = Session::new(SessionState::new());
+        assert_eq!(session.status(), SessionStatus::Unchanged);
+        assert!(session.is_empty());
+
+        session.remove("key");
+        session.clear();
+        assert_eq!(session.status(), SessionStatus::Unchanged);
+
+        session.insert("key", "value").unwrap();
+        assert_eq!(session.status(), SessionStatus::Changed);
+        assert!(session.contains("key"));
+        assert_eq!(
+            session.get::<String>("key").unwrap(),
+            Some("value".to_string())
+        );
+        assert!(session.get::<u32>("key").is_err());
+
+        session.renew();
+        session.insert("key2", 1).unwrap();
+        assert_eq!(session.status(), SessionStatus::Renewed);
+
+        session.purge();
+        assert_eq!(session.status(), SessionStatus::Purged);
+        assert!(session.is_empty());
+        session.remove("key");
+        assert_eq!(session.status(), SessionStatus::Purged);
+        session.insert("key", 1).unwrap();
+        assert_eq!(session.status(), SessionStatus::Renewed);
+    }
+
+    #[test]
+    fn test_cookie_content() {
+        let mw = SessionMiddleware::new(CookieSessionStore, KEY);
+        let value = mw.inner.encode("session-key").unwrap();
+        assert!(!value.contains("session-key"));
+        assert_eq!(mw.inner.decode(&value), Some("session-key".to_string()));
+        assert_ne!(mw.inner.encode("session-key").unwrap(), value);
+
+        let mut tampered = value.into_bytes();
+        tampered[14] = if tampered[14] == b'A' { b'B' } else { b'A' };
+        assert_eq!(
+            mw.inner.decode(std::str::from_utf8(&tampered).unwrap()),
+            None
+        );
+        assert_eq!(mw.inner.decode("garbage"), None);
+
+        let mw = SessionMiddleware::new(CookieSessionStore, KEY)
+            .content_security(CookieContentSecurity::Signed);
+        let value = mw.inner.encode("session-key").unwrap();
+        assert_eq!(mw.inner.decode(&value), Some("session-key".to_string()));
+        let (_, tag) = value.rsplit_once('.').unwrap();
+        let forged = format!(
+            "{}.{}",
+            base64::encode_config("other-key", base64::URL_SAFE_NO_PAD),
+            tag
+        );
+        assert_eq!(mw.inner.decode(&forged), None);
+
+        // signature is bound to cookie name
+        let other = SessionMiddleware::new(CookieSessionStore, KEY)
+            .content_security(CookieContentSecurity::Signed)
+            .cookie_name("other");
+        assert_eq!(other.inner.decode(&value), None);
+
+        // different key
+        let other = SessionMiddleware::new(CookieSessionStore, &[1; 32])
+            .content_security(CookieContentSecurity::Signed);
+        assert_eq!(other.inner.decode(&value), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Session key must be at least 32 bytes")]
+    fn test_short_key() {
+        let _ = SessionMiddleware::new(CookieSessionStore, &[0; 16]);
+    }
+
+    #[crate::rt_test]
+    async fn test_not_configured() {
+        let srv = init_service(App::new().service(web::resource("/").to(counter))).await;
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[crate::rt_test]
+    async fn test_cookie_store() {
+        let srv = init_service(
+            App::new()
+                .wrap(SessionMiddleware::new(CookieSessionStore, KEY))
+                .service(web::resource("/").to(counter))
+                .service(web::resource("/get").to(get))
+                .service(web::resource("/purge").to(|session: Session| async move {
+                    session.purge();
+                    HttpResponse::Ok()
+                })),
+        )
+        .await;
+
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let cookie = session_cookie(&res).unwrap();
+        assert!(cookie.secure().unwrap());
+        assert!(cookie.http_only().unwrap());
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.max_age(), Some(time::Duration::seconds(86400)));
+        assert_eq!(read_body(res).await, "1");
+
+        let req = TestRequest::default().cookie(cookie.clone()).to_request();
+        let res = call_service(&srv, req).await;
+        let cookie = session_cookie(&res).unwrap();
+        assert_eq!(read_body(res).await, "2");
+
+        // unchanged session is not persisted
+        let req = TestRequest::with_uri("/get")
+            .cookie(cookie.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert!(session_cookie(&res).is_none());
+        assert_eq!(read_body(res).await, "2");
+
+        // tampered cookie starts new session
+        let mut tampered = cookie.clone();
+        tampered.set_value(format!("A{}", cookie.value()));
+        let req = TestRequest::default().cookie(tampered).to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, "1");
+
+        let req = TestRequest::with_uri("/purge")
+            .cookie(cookie.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        let removal = session_cookie(&res).unwrap();
+        assert_eq!(removal.value(), "");
+        assert_eq!(removal.max_age(), Some(time::Duration::zero()));
+    }
+
+    #[crate::rt_test]
+    async fn test_cookie_store_expired() {
+        let mw = SessionMiddleware::new(CookieSessionStore, KEY);
+        let key = serde_json::to_string(&CookieState {
+            exp: now() - 1,
+            state: vec![("counter".to_string(), "5".to_string())]
+                .into_iter()
+                .collect(),
+        })
+        .unwrap();
+        let value = mw.inner.encode(&key).unwrap();
+
+        let srv =
+            init_service(App::new().wrap(mw).service(web::resource("/").to(counter))).await;
+        let req = TestRequest::default()
+            .cookie(Cookie::new("session", value))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, "1");
+    }
+
+    #[crate::rt_test]
+    async fn test_cookie_store_overflow() {
+        let srv = init_service(
+            App::new()
+                .wrap(SessionMiddleware::new(CookieSessionStore, KEY))
+                .service(web::resource("/").to(|session: Session| async move {
+                    session.insert("data", "x".repeat(MAX_COOKIE_SIZE))?;
+                    Ok::<_, web::Error>(HttpResponse::Ok())
+                })),
+        )
+        .await;
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(session_cookie(&res).is_none());
+    }
+
+    #[crate::rt_test]
+    async fn test_custom_store() {
+        let store = MemoryStore::default();
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    SessionMiddleware::new(store.clone(), KEY)
+                        .content_security(CookieContentSecurity::Signed)
+                        .cookie_name("session")
+                        .domain("example.com")
+                        .secure(false)
+                        .ttl(Duration::from_secs(60)),
+                )
+                .service(web::resource("/").to(counter))
+                .service(web::resource("/get").to(get))
+                .service(web::resource("/renew").to(|session: Session| async move {
+                    session.renew();
+                    HttpResponse::Ok()
+                }))
+                .service(web::resource("/clear").to(|session: Session| async move {
+                    session.clear();
+                    HttpResponse::Ok()
+                })),
+        )
+        .await;
+
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        let cookie = session_cookie(&res).unwrap();
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.secure(), None);
+        assert_eq!(cookie.max_age(), Some(time::Duration::seconds(60)));
+        assert_eq!(
+            SessionMiddleware::new(CookieSessionStore, KEY)
+                .content_security(CookieContentSecurity::Signed)
+                .inner
+                .decode(cookie.value()),
+            Some("key-1".to_string())
+        );
+        assert_eq!(store.saves.get(), 1);
+
+        let req = TestRequest::default().cookie(cookie.clone()).to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, "2");
+        assert_eq!(store.saves.get(), 2);
+
+        // rolling expiration is disabled
+        let req = TestRequest::with_uri("/get")
+            .cookie(cookie.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert!(session_cookie(&res).is_none());
+        assert_eq!(store.saves.get(), 2);
+        assert_eq!(store.touches.get(), 0);
+
+        // renew session key
+        let req = TestRequest::with_uri("/renew")
+            .cookie(cookie.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        let renewed = session_cookie(&res).unwrap();
+        assert_ne!(renewed.value(), cookie.value());
+        assert!(!store.sessions.borrow().contains_key("key-1"));
+        assert!(store.sessions.borrow().contains_key("key-2"));
+
+        let req = TestRequest::with_uri("/get")
+            .cookie(renewed.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, "2");
+
+        // empty session is removed
+        let req = TestRequest::with_uri("/clear")
+            .cookie(renewed.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(session_cookie(&res).unwrap().value(), "");
+        assert!(store.sessions.borrow().is_empty());
+
+        // empty new session is not persisted
+        let req = TestRequest::with_uri("/get").to_request();
+        let res = call_service(&srv, req).await;
+        assert!(session_cookie(&res).is_none());
+        assert_eq!(store.saves.get(), 3);
+    }
+
+    #[crate::rt_test]
+    async fn test_rolling() {
+        let store = MemoryStore::default();
+        let srv = init_service(
+            App::new()
+                .wrap(SessionMiddleware::new(store.clone(), KEY).rolling(true))
+                .service(web::resource("/").to(counter))
+                .service(web::resource("/get").to(get)),
+        )
+        .await;
+
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        let cookie = session_cookie(&res).unwrap();
+
+        let req = TestRequest::with_uri("/get")
+            .cookie(cookie.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert!(session_cookie(&res).is_some());
+        assert_eq!(read_body(res).await, "1");
+        assert_eq!(store.touches.get(), 1);
+        assert_eq!(store.saves.get(), 1);
+
+        // no session, nothing to extend
+        let req = TestRequest::with_uri("/get").to_request();
+        let res = call_service(&srv, req).await;
+        assert!(session_cookie(&res).is_none());
+        assert_eq!(store.touches.get(), 1);
+    }
+
+    #[crate::rt_test]
+    async fn test_store_error() {
+        struct FailStore;
+
+        impl SessionStore for FailStore {
+            fn load(&self, _: String) -> StoreFuture<Option<SessionState>> {
+                Box::pin(async move { Err(SessionError::Store("load".into())) })
+            }
+            fn save(
+                &self,
+                _: Option<String>,
+                _: SessionState,
+                _: Duration,
+            ) -> StoreFuture<String> {
+                Box::pin(async move { Ok("key".to_string()) })
+            }
+            fn touch(
+                &self,
+                _: String,
+                _: SessionState,
+                _: Duration,
+            ) -> StoreFuture<String> {
+                Box::pin(async move { Ok("key".to_string()) })
+            }
+            fn delete(&self, _: String) -> StoreFuture<()> {
+                Box::pin(async move { Ok(()) })
+            }
+        }
+
+        let mw = SessionMiddleware::new(FailStore, KEY);
+        let value = mw.inner.encode("key").unwrap();
+        let srv =
+            init_service(App::new().wrap(mw).service(web::resource("/").to(counter))).await;
+
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().contains_key(header::SET_COOKIE));
+
+        let req = TestRequest::default()
+            .cookie(Cookie::new("session", value))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,493 @@
+//! Server-Sent Events support
+//!
+//! ```rust
+//! use ntex::web::{self, sse, HttpRequest};
+//!
+//! async fn events(req: HttpRequest) -> sse::EventStream {
+//!     let (tx, stream) = sse::channel();
+//!
+//!     ntex::rt::spawn(async move {
+//!         let _ = tx.send(sse::Event::new("hello").event("greeting"));
+//!         // wait until client disconnects
+//!         tx.closed().await;
+//!     });
+//!
+//!     stream
+//!         .keep_alive(ntex::time::Seconds(15))
+//!         .replay(&req, |last_id| {
+//!             vec![sse::Event::new(format!("missed since {}", last_id))]
+//!         })
+//! }
+//! ```
+use std::task::{Context, Poll, Waker};
+use std::{cell::RefCell, collections::VecDeque, error::Error, fmt, rc::Rc};
+
+use crate::http::body::{Body, BodySize, MessageBody};
+use crate::http::header::{HeaderValue, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE};
+use crate::http::{Response, StatusCode};
+use crate::time::{sleep, Millis, Sleep};
+use crate::util::{poll_fn, BufMut, Bytes, BytesMut};
+
+use super::error::ErrorRenderer;
+use super::httprequest::HttpRequest;
+use super::responder::{Ready, Responder};
+
+/// `Last-Event-ID` header name
+pub const LAST_EVENT_ID: &str = "last-event-id";
+
+const KEEP_ALIVE: &[u8] = b": keep-alive\n\n";
+
+/// Create event stream and sender
+///
+/// Stream completes when all senders get dropped or stream is closed
+/// with `SseSender::close()`.
+pub fn channel() -> (SseSender, EventStream) {
+    let inner = Rc::new(RefCell::new(Inner {
+        queue: VecDeque::new(),
+        eof: false,
+        disconnected: false,
+        stream_task: None,
+        closed_tasks: Vec::new(),
+    }));
+    (
+        SseSender(inner.clone()),
+        EventStream {
+            inner,
+            keep_alive: None,
+        },
+    )
+}
+
+/// Get `Last-Event-ID` value of the reconnecting client
+pub fn last_event_id(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(LAST_EVENT_ID)
+        .and_then(|val| val.to_str().ok())
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// Server-sent event
+pub struct Event {
+    data: Option<String>,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Millis>,
+    comment: Option<String>,
+}
+
+impl Event {
+    /// Create event with data
+    ///
+    /// Multi-line data is sent as multiple `data` fields.
+    pub fn new<T: Into<String>>(data: T) -> Self {
+        Event {
+            data: Some(data.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Create event with data serialized to json
+    pub fn json<T: serde::Serialize>(value: &T) -> Result<Self, serde_json::Error> {
+        Ok(Event::new(serde_json::to_string(value)?))
+    }
+
+    /// Create comment, comments are ignored by client
+    pub fn comment<T: Into<String>>(text: T) -> Self {
+        Event {
+            comment: Some(text.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Set event type
+    pub fn event<T: Into<String>>(mut self, name: T) -> Self {
+        self.event = Some(name.into());
+        self
+    }
+
+    /// Set event id, client reports last seen id on reconnect
+    pub fn id<T: Into<String>>(mut self, id: T) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Set client reconnection time
+    pub fn retry<T: Into<Millis>>(mut self, timeout: T) -> Self {
+        self.retry = Some(timeout.into());
+        self
+    }
+
+    /// Encode event to `text/event-stream` format
+    pub fn encode(&self, dst: &mut BytesMut) {
+        if let Some(ref comment) = self.comment {
+            for line in lines(comment) {
+                put_field(dst, "", line);
+            }
+        }
+        if let Some(ref event) = self.event {
+            put_field(dst, "event", single_line(event));
+        }
+        if let Some(ref id) = self.id {
+            put_field(dst, "id", single_line(id));
+        }
+        if let Some(retry) = self.retry {
+            put_field(dst, "retry", &retry.0.to_string());
+        }
+        if let Some(ref data) = self.data {
+            for line in lines(data) {
+                put_field(dst, "data", line);
+            }
+        }
+        dst.put_u8(b'\n');
+    }
+}
+
+fn lines(s: &str) -> impl Iterator<Item = &str> {
+    s.split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+}
+
+fn single_line(s: &str) -> &str {
+    s.split(&['\r', '\n'][..]).next().unwrap_or("")
+}
+
+fn put_field(dst: &mut BytesMut, name: &str, value: &str) {
+    dst.extend_from_slice(name.as_bytes());
+    dst.extend_from_slice(b": ");
+    dst.extend_from_slice(value.as_bytes());
+    dst.put_u8(b'\n');
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, derive_more::Display)]
+#[display(fmt = "Event stream is disconnected")]
+/// Event stream is disconnected
+pub struct Disconnected;
+
+impl Error for Disconnected {}
+
+struct Inner {
+    queue: VecDeque<Bytes>,
+    eof: bool,
+    disconnected: bool,
+    stream_task: Option<Waker>,
+    closed_tasks: Vec<Waker>,
+}
+
+impl Inner {
+    fn wake_stream(&mut self) {
+        if let Some(waker) = self.stream_task.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Event stream sender
+///
+/// Sender could be cloned, stream completes when all senders are dropped.
+pub struct SseSender(Rc<RefCell<Inner>>);
+
+impl SseSender {
+    /// Send event to the client
+    pub fn send(&self, event: Event) -> Result<(), Disconnected> {
+        let mut inner = self.0.borrow_mut();
+        if inner.disconnected || inner.eof {
+            Err(Disconnected)
+        } else {
+            let mut buf = BytesMut::new();
+            event.encode(&mut buf);
+            inner.queue.push_back(buf.freeze());
+            inner.wake_stream();
+            Ok(())
+        }
+    }
+
+    /// Complete event stream
+    ///
+    /// Queued events are sent to the client.
+    pub fn close(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.eof = true;
+        inner.wake_stream();
+    }
+
+    /// Check if client is disconnected or stream is closed
+    pub fn is_closed(&self) -> bool {
+        let inner = self.0.borrow();
+        inner.disconnected || inner.eof
+    }
+
+    /// Check if client is disconnected
+    pub fn poll_closed(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut inner = self.0.borrow_mut();
+        if inner.disconnected {
+            Poll::Ready(())
+        } else {
+            if !inner.closed_tasks.iter().any(|w| w.will_wake(cx.waker())) {
+                inner.closed_tasks.push(cx.waker().clone());
+            }
+            Poll::Pending
+        }
+    }
+
+    /// Wait until client disconnects
+    pub async fn closed(&self) {
+        poll_fn(|cx| self.poll_closed(cx)).await
+    }
+}
+
+impl Clone for SseSender {
+    fn clone(&self) -> Self {
+        SseSender(self.0.clone())
+    }
+}
+
+impl Drop for SseSender {
+    fn drop(&mut self) {
+        // last sender is about to drop, stream has ended
+        if Rc::strong_count(&self.0) == 2 {
+            self.0.borrow_mut().wake_stream();
+        }
+    }
+}
+
+impl fmt::Debug for SseSender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SseSender")
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+/// `text/event-stream` response body
+///
+/// Stream could be returned from handler as a responder, response is
+/// not compressed and is not cached. Use `channel()` to create stream.
+pub struct EventStream {
+    inner: Rc<RefCell<Inner>>,
+    keep_alive: Option<(Millis, Sleep)>,
+}
+
+impl EventStream {
+    /// Send keep-alive comment if no events are sent during `interval`.
+    ///
+    /// Keep-alive comments prevent proxies from closing idle connections.
+    /// By default keep-alive is disabled.
+    pub fn keep_alive<T: Into<Millis>>(mut self, interval: T) -> Self {
+        let interval = interval.into();
+        self.keep_alive = if interval.is_zero() {
+            None
+        } else {
+            Some((interval, sleep(interval)))
+        };
+        self
+    }
+
+    /// Replay missed events for reconnecting client.
+    ///
+    /// If request contains `Last-Event-ID` header, `f` is called with header
+    /// value and returned events are sent before any other event.
+    pub fn replay<F>(self, req: &HttpRequest, f: F) -> Self
+    where
+        F: FnOnce(&str) -> Vec<Event>,
+    {
+        if let Some(id) = last_event_id(req) {
+            let mut buf = BytesMut::new();
+            for event in f(id) {
+                event.encode(&mut buf);
+            }
+            if !buf.is_empty() {
+                self.inner.borrow_mut().queue.push_front(buf.freeze());
+            }
+        }
+        self
+    }
+}
+
+impl MessageBody for EventStream {
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        let mut inner = self.inner.borrow_mut();
+        if let Some(chunk) = inner.queue.pop_front() {
+            if let Some((interval, ref timer)) = self.keep_alive {
+                timer.reset(interval);
+            }
+            return Poll::Ready(Some(Ok(chunk)));
+        }
+        if inner.eof || Rc::strong_count(&self.inner) == 1 {
+            return Poll::Ready(None);
+        }
+        inner.stream_task = Some(cx.waker().clone());
+        drop(inner);
+
+        if let Some((interval, ref timer)) = self.keep_alive {
+            if timer.poll_elapsed(cx).is_ready() {
+                timer.reset(interval);
+                let _ = timer.poll_elapsed(cx);
+                return Poll::Ready(Some(Ok(Bytes::from_static(KEEP_ALIVE))));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        // response body is dropped, client is disconnected
+        let mut inner = self.inner.borrow_mut();
+        inner.disconnected = true;
+        inner.queue.clear();
+        for waker in inner.closed_tasks.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+impl fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventStream")
+            .field("keep_alive", &self.keep_alive.as_ref().map(|item| item.0))
+            .finish()
+    }
+}
+
+impl<Err: ErrorRenderer> Responder<Err> for EventStream {
+    type Error = Err::Container;
+    type Future = Ready<Response>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        Response::build(StatusCode::OK)
+            .header(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"))
+            .header(CACHE_CONTROL, HeaderValue::from_static("no-cache"))
+            .header(CONTENT_ENCODING, HeaderValue::from_static("identity"))
+            .body(Body::from_message(self))
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::HeaderValue;
+    use crate::web::test::{read_body, TestRequest};
+    use crate::web::{self, App, DefaultError};
+
+    #[test]
+    fn test_event_encode() {
+        let mut buf = BytesMut::new();
+        Event::new("line1\r\nline2")
+            .event("update")
+            .id("1\n2")
+            .retry(Millis(3000))
+            .encode(&mut buf);
+        assert_eq!(
+            &buf[..],
+            &b"event: update\nid: 1\nretry: 3000\ndata: line1\ndata: line2\n\n"[..]
+        );
+
+        let mut buf = BytesMut::new();
+        Event::comment("ping").encode(&mut buf);
+        assert_eq!(&buf[..], &b": ping\n\n"[..]);
+
+        let mut buf = BytesMut::new();
+        Event::json(&serde_json::json!({"a": 1}))
+            .unwrap()
+            .encode(&mut buf);
+        assert_eq!(&buf[..], &b"data: {\"a\":1}\n\n"[..]);
+    }
+
+    #[crate::rt_test]
+    async fn test_stream() {
+        let (tx, mut stream) = channel();
+        let tx2 = tx.clone();
+        tx.send(Event::new("1")).unwrap();
+        assert_eq!(
+            poll_fn(|cx| stream.poll_next_chunk(cx))
+                .await
+                .unwrap()
+                .unwrap(),
+            Bytes::from_static(b"data: 1\n\n")
+        );
+        assert!(crate::util::lazy(|cx| stream.poll_next_chunk(cx))
+            .await
+            .is_pending());
+
+        // stream ends when all senders are dropped
+        drop(tx);
+        tx2.send(Event::new("2")).unwrap();
+        drop(tx2);
+        assert!(poll_fn(|cx| stream.poll_next_chunk(cx)).await.is_some());
+        assert!(poll_fn(|cx| stream.poll_next_chunk(cx)).await.is_none());
+
+        // client disconnect
+        let (tx, stream) = channel();
+        assert!(format!("{:?}", tx).contains("SseSender"));
+        assert!(format!("{:?}", stream).contains("EventStream"));
+        assert!(!tx.is_closed());
+        assert!(crate::util::lazy(|cx| tx.poll_closed(cx))
+            .await
+            .is_pending());
+        drop(stream);
+        tx.closed().await;
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(Event::new("1")), Err(Disconnected));
+    }
+
+    #[crate::rt_test]
+    async fn test_keep_alive() {
+        let (tx, stream) = channel();
+        let mut stream = stream.keep_alive(Millis(50));
+        assert_eq!(
+            poll_fn(|cx| stream.poll_next_chunk(cx))
+                .await
+                .unwrap()
+                .unwrap(),
+            Bytes::from_static(KEEP_ALIVE)
+        );
+        tx.close();
+        assert!(poll_fn(|cx| stream.poll_next_chunk(cx)).await.is_none());
+    }
+
+    #[crate::rt_test]
+    async fn test_responder() {
+        let srv = web::test::init_service(App::new().service(web::resource("/").to(
+            |req: HttpRequest| async move {
+                let (tx, stream) = channel();
+                tx.send(Event::new("new").id("2")).unwrap();
+                tx.close();
+                stream.replay(&req, |id| vec![Event::new(format!("since {}", id))])
+            },
+        )))
+        .await;
+
+        let req = TestRequest::with_uri("/")
+            .header(LAST_EVENT_ID, "1")
+            .to_request();
+        let resp = web::test::call_service(&srv, req).await;
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("text/event-stream")
+        );
+        assert_eq!(
+            resp.headers().get(CACHE_CONTROL).unwrap(),
+            HeaderValue::from_static("no-cache")
+        );
+        let body = read_body(resp).await;
+        assert_eq!(
+            body,
+            Bytes::from_static(b"data: since 1\n\nid: 2\ndata: new\n\n")
+        );
+
+        let resp = Responder::<DefaultError>::respond_to(
+            channel().1,
+            &TestRequest::default().to_http_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
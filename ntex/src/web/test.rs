@@ -9,10 +9,12 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::http::body::MessageBody;
+use crate::http::client::error::SendRequestError;
 use crate::http::client::{Client, ClientRequest, ClientResponse, Connector};
 use crate::http::error::{HttpError, PayloadError, ResponseError};
-use crate::http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use crate::http::header::{HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
 use crate::http::test::TestRequest as HttpTestRequest;
+pub use crate::http::test::{SseEvent, SseStream};
 use crate::http::{HttpService, Method, Payload, Request, StatusCode, Uri, Version};
 use crate::router::{Path, ResourceDef};
 use crate::service::{
@@ -20,8 +22,8 @@ use crate::service::{
 };
 use crate::time::{sleep, Millis, Seconds};
 use crate::util::{next, Bytes, BytesMut, Extensions, Ready};
-use crate::ws::{error::WsClientError, WsClient, WsConnection};
-use crate::{io::Sealed, rt::System, server::Server, Stream};
+use crate::ws::{self, error::WsClientError, WsClient, WsConnection};
+use crate::{io::Framed, io::Sealed, rt::System, server::Server, Stream};
 
 use crate::web::config::AppConfig;
 use crate::web::error::{DefaultError, ErrorRenderer};
@@ -950,6 +952,24 @@ impl TestServer {
         self.ws_at("/").await
     }
 
+    /// Connect to websocket server at a given path and return framed client
+    pub async fn ws_framed_at(
+        &self,
+        path: &str,
+    ) -> Result<Framed<ws::Codec>, WsClientError> {
+        let (io, codec, _) = self.ws_at(path).await?.into_inner();
+        Ok(Framed::new(io, codec))
+    }
+
+    /// Send `GET` request to a given path and consume response as server-sent events
+    pub async fn sse(&self, path: &str) -> Result<SseStream, SendRequestError> {
+        self.get(path)
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .map(SseStream::new)
+    }
+
     /// Gracefully stop http server
     pub async fn stop(self) {
         self.server.stop(true).await;
@@ -1225,4 +1245,32 @@ mod tests {
         assert_eq!(cookies.len(), 1);
         assert_eq!(cookies[0].name(), "name");
     }
+
+    #[crate::rt_test]
+    async fn test_sse() {
+        let srv = server(|| {
+            App::new().service(web::resource("/events").to(|| async {
+                HttpResponse::Ok().content_type("text/event-stream").body(
+                    ": comment\nevent: ping\nid: 1\ndata: first\n\n\
+                     data: multi\r\ndata: line\r\nretry: 100\r\n\r\ndata: incomplete",
+                )
+            }))
+        });
+
+        let mut events = srv.sse("/events").await.unwrap();
+        assert!(events.response().status().is_success());
+        assert_eq!(
+            events.recv().await.unwrap().unwrap(),
+            SseEvent {
+                event: Some("ping".to_string()),
+                data: "first".to_string(),
+                id: Some("1".to_string()),
+                retry: None,
+            }
+        );
+        let ev = events.recv().await.unwrap().unwrap();
+        assert_eq!(ev.data, "multi\nline");
+        assert_eq!(ev.retry, Some(100));
+        assert!(events.recv().await.is_none());
+    }
 }
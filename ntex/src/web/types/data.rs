@@ -1,4 +1,4 @@
-use std::{ops::Deref, sync::Arc};
+use std::{any::type_name, any::TypeId, ops::Deref, sync::Arc};
 
 use crate::http::Payload;
 use crate::util::{Extensions, Ready};
@@ -11,6 +11,38 @@ pub(crate) trait DataFactory {
     fn create(&self, extensions: &mut Extensions) -> bool;
 }
 
+/// Application data types required by request extractors
+///
+/// Extractors register data types they read with `HttpRequest::app_data()`,
+/// application checks that all required data is registered during
+/// application initialization.
+#[derive(Clone, Debug, Default)]
+pub struct RequiredData(Vec<(TypeId, &'static str)>);
+
+impl RequiredData {
+    /// Register required data type
+    pub fn add<T: 'static>(&mut self) {
+        let id = TypeId::of::<T>();
+        if !self.0.iter().any(|(t, _)| *t == id) {
+            self.0.push((id, type_name::<T>()));
+        }
+    }
+
+    /// Check if no data is required
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Names of data types that are not available in container
+    pub(crate) fn missing(&self, data: &Extensions) -> Vec<&'static str> {
+        self.0
+            .iter()
+            .filter(|(id, _)| !data.contains_type(id))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
 /// Application data.
 ///
 /// Application data is an arbitrary data attached to the app.
@@ -30,8 +62,8 @@ pub(crate) trait DataFactory {
 /// uses `Arc`. if your data implements `Send` + `Sync` traits you can
 /// use `web::types::Data::new()` and avoid double `Arc`.
 ///
-/// If route data is not set for a handler, using `Data<T>` extractor would
-/// cause *Internal Server Error* response.
+/// If route data is not set for a handler, application initialization
+/// fails and error with list of routes and missing data types is logged.
 ///
 /// ```rust
 /// use std::sync::Mutex;
@@ -114,6 +146,10 @@ impl<T: 'static, E: ErrorRenderer> FromRequest<E> for Data<T> {
             Ready::Err(DataExtractorError::NotConfigured)
         }
     }
+
+    fn required_data(data: &mut RequiredData) {
+        data.add::<Data<T>>();
+    }
 }
 
 impl<T: 'static> DataFactory for Data<T> {
@@ -133,9 +169,9 @@ mod tests {
 
     use super::*;
     use crate::http::StatusCode;
+    use crate::service::{IntoServiceFactory, Service, ServiceFactory};
     use crate::web::test::{self, init_service, TestRequest};
-    use crate::web::{self, App, HttpResponse};
-    use crate::Service;
+    use crate::web::{self, config::AppConfig, App, DefaultError, HttpResponse};
 
     #[crate::rt_test]
     async fn test_data_extractor() {
@@ -151,16 +187,17 @@ mod tests {
         let resp = srv.call(req).await.unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
 
-        let srv = init_service(
-            App::new().data(10u32).service(
+        // data is not registered
+        let srv = App::new()
+            .data(10u32)
+            .service(
                 web::resource("/")
                     .to(|_: web::types::Data<usize>| async { HttpResponse::Ok() }),
-            ),
-        )
-        .await;
-        let req = TestRequest::default().to_request();
-        let res = srv.call(req).await.unwrap();
-        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+            )
+            .into_factory()
+            .new_service(AppConfig::default())
+            .await;
+        assert!(srv.is_err());
     }
 
     #[crate::rt_test]
@@ -177,16 +214,16 @@ mod tests {
         let resp = srv.call(req).await.unwrap();
         assert_eq!(resp.status(), StatusCode::OK);
 
-        let srv = init_service(
-            App::new().app_data(Data::new(10u32)).service(
+        let srv = App::new()
+            .app_data(Data::new(10u32))
+            .service(
                 web::resource("/")
                     .to(|_: web::types::Data<usize>| async { HttpResponse::Ok() }),
-            ),
-        )
-        .await;
-        let req = TestRequest::default().to_request();
-        let res = srv.call(req).await.unwrap();
-        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+            )
+            .into_factory()
+            .new_service(AppConfig::default())
+            .await;
+        assert!(srv.is_err());
     }
 
     #[crate::rt_test]
@@ -204,13 +241,55 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
 
         // different type
-        let srv = init_service(App::new().service(web::resource("/").data(10u32).route(
-            web::get().to(|_: web::types::Data<usize>| async { HttpResponse::Ok() }),
-        )))
-        .await;
-        let req = TestRequest::default().to_request();
-        let res = srv.call(req).await.unwrap();
-        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let srv = App::new()
+            .service(web::resource("/").data(10u32).route(
+                web::get().to(|_: web::types::Data<usize>| async { HttpResponse::Ok() }),
+            ))
+            .into_factory()
+            .new_service(AppConfig::default())
+            .await;
+        assert!(srv.is_err());
+    }
+
+    #[crate::rt_test]
+    async fn test_required_data() {
+        let mut data = RequiredData::default();
+        assert!(data.is_empty());
+        type Args = (Data<usize>, Option<Data<u32>>, Data<usize>);
+        <Args as FromRequest<DefaultError>>::required_data(&mut data);
+        assert!(!data.is_empty());
+
+        let mut ext = Extensions::new();
+        assert_eq!(data.missing(&ext).len(), 1);
+        ext.insert(Data::new(1usize));
+        assert!(data.missing(&ext).is_empty());
+
+        // scope data
+        let srv =
+            init_service(App::new().service(web::scope("/app").data(10usize).service(
+                web::resource("/index.html").to(
+                    |_: Data<usize>, _: Option<Data<u32>>| async { HttpResponse::Ok() },
+                ),
+            )))
+            .await;
+        let req = TestRequest::with_uri("/app/index.html").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // resource's container does not include app_data
+        let srv = App::new()
+            .app_data(Data::new(10usize))
+            .service(
+                web::scope("/app").service(
+                    web::resource("/index.html")
+                        .data(1u32)
+                        .to(|_: Data<usize>| async { HttpResponse::Ok() }),
+                ),
+            )
+            .into_factory()
+            .new_service(AppConfig::default())
+            .await;
+        assert!(srv.is_err());
     }
 
     #[crate::rt_test]
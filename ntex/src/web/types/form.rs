@@ -7,9 +7,10 @@ use serde::{de::DeserializeOwned, Serialize};
 #[cfg(feature = "compress")]
 use crate::http::encoding::Decoder;
 use crate::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
-use crate::http::{HttpMessage, Payload, Response, StatusCode};
+use crate::http::{error::PayloadError, HttpMessage, Payload, Response, StatusCode};
 use crate::util::{next, BytesMut};
 use crate::web::error::{ErrorRenderer, UrlencodedError, WebResponseError};
+use crate::web::middleware::RequestBudget;
 use crate::web::responder::{Ready, Responder};
 use crate::web::{FromRequest, HttpRequest};
 
@@ -218,6 +219,7 @@ struct UrlEncoded<U> {
     length: Option<usize>,
     encoding: &'static Encoding,
     err: Option<UrlencodedError>,
+    budget: Option<RequestBudget>,
     fut: Option<Pin<Box<dyn Future<Output = Result<U, UrlencodedError>>>>>,
 }
 
@@ -258,6 +260,7 @@ impl<U> UrlEncoded<U> {
             length: len,
             fut: None,
             err: None,
+            budget: req.extensions().get::<RequestBudget>().cloned(),
         }
     }
 
@@ -269,12 +272,18 @@ impl<U> UrlEncoded<U> {
             err: Some(e),
             length: None,
             encoding: UTF_8,
+            budget: None,
         }
     }
 
     /// Change max size of payload. By default max size is 256Kb
     fn limit(mut self, limit: usize) -> Self {
         self.limit = limit;
+        // limit decoded size of compressed payload
+        #[cfg(feature = "compress")]
+        {
+            self.stream = self.stream.take().map(|s| s.limit(limit));
+        }
         self
     }
 }
@@ -296,10 +305,19 @@ where
 
         // payload size
         let limit = self.limit;
+        let budget = self.budget.take();
         if let Some(len) = self.length.take() {
             if len > limit {
                 return Poll::Ready(Err(UrlencodedError::Overflow { size: len, limit }));
             }
+            if let Some(ref b) = budget {
+                if len > b.remaining() {
+                    return Poll::Ready(Err(UrlencodedError::Overflow {
+                        size: len,
+                        limit: b.remaining(),
+                    }));
+                }
+            }
         }
 
         // future
@@ -310,15 +328,29 @@ where
             let mut body = BytesMut::with_capacity(8192);
 
             while let Some(item) = next(&mut stream).await {
-                let chunk = item?;
+                let chunk = match item {
+                    Err(PayloadError::Overflow) => {
+                        return Err(UrlencodedError::Overflow {
+                            size: body.len(),
+                            limit,
+                        })
+                    }
+                    item => item?,
+                };
                 if (body.len() + chunk.len()) > limit {
                     return Err(UrlencodedError::Overflow {
                         size: body.len() + chunk.len(),
                         limit,
                     });
-                } else {
-                    body.extend_from_slice(&chunk);
+                } else if let Some(ref b) = budget {
+                    if b.charge(chunk.len()).is_err() {
+                        return Err(UrlencodedError::Overflow {
+                            size: body.len() + chunk.len(),
+                            limit: body.len() + b.remaining(),
+                        });
+                    }
                 }
+                body.extend_from_slice(&chunk);
             }
 
             if encoding == UTF_8 {
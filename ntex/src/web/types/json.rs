@@ -3,12 +3,15 @@ use std::{fmt, future::Future, ops, pin::Pin, sync::Arc, task::Context, task::Po
 
 use serde::{de::DeserializeOwned, Serialize};
 
+use crate::http::body::{Body, BodySize, MessageBody};
 #[cfg(feature = "compress")]
 use crate::http::encoding::Decoder;
 use crate::http::header::CONTENT_LENGTH;
-use crate::http::{HttpMessage, Payload, Response, StatusCode};
-use crate::util::{next, BytesMut};
+use crate::http::helpers::Writer;
+use crate::http::{error::PayloadError, HttpMessage, Payload, Response, StatusCode};
+use crate::util::{next, Bytes, BytesMut, PoolId, PoolRef};
 use crate::web::error::{ErrorRenderer, JsonError, JsonPayloadError, WebResponseError};
+use crate::web::middleware::RequestBudget;
 use crate::web::responder::{Ready, Responder};
 use crate::web::{FromRequest, HttpRequest};
 
@@ -116,18 +119,169 @@ where
     type Future = Ready<Response>;
 
     fn respond_to(self, req: &HttpRequest) -> Self::Future {
-        let body = match serde_json::to_string(&self.0) {
-            Ok(body) => body,
-            Err(e) => return e.error_response(req).into(),
-        };
+        json_response(StatusCode::OK, &self.0, req)
+    }
+}
+
+impl<T: Serialize, Err: ErrorRenderer> Responder<Err> for (StatusCode, Json<T>)
+where
+    Err::Container: From<JsonError>,
+{
+    type Error = JsonError;
+    type Future = Ready<Response>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        json_response(self.0, &(self.1).0, req)
+    }
+}
+
+fn json_response<T: Serialize>(
+    status: StatusCode,
+    value: &T,
+    req: &HttpRequest,
+) -> Ready<Response> {
+    let body = match serde_json::to_string(value) {
+        Ok(body) => body,
+        Err(e) => return e.error_response(req).into(),
+    };
+
+    Response::build(status)
+        .content_type("application/json")
+        .body(body)
+        .into()
+}
+
+/// Streaming json array responder
+///
+/// Items are serialized lazily into chunks of `chunk_size` bytes, next
+/// chunk is serialized only after previous one is consumed by the
+/// connection. This keeps peak memory low for large collections.
+///
+/// ```rust
+/// use ntex::web;
+///
+/// async fn index() -> web::types::JsonStream<std::ops::Range<u64>> {
+///     web::types::JsonStream::new(0..1_000_000)
+/// }
+/// # fn main() {}
+/// ```
+pub struct JsonStream<I> {
+    iter: I,
+    chunk_size: usize,
+    pool: PoolRef,
+}
+
+impl<I> JsonStream<I>
+where
+    I: Iterator,
+    I::Item: Serialize,
+{
+    /// Create streaming json array responder
+    pub fn new<T>(items: T) -> Self
+    where
+        T: IntoIterator<IntoIter = I>,
+    {
+        JsonStream {
+            iter: items.into_iter(),
+            chunk_size: 16 * 1024,
+            pool: PoolId::DEFAULT.pool_ref(),
+        }
+    }
+
+    /// Set size of the serialized chunk
+    ///
+    /// By default chunk size is 16Kb
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = std::cmp::max(size, 64);
+        self
+    }
+
+    /// Set memory pool for chunk buffers
+    ///
+    /// By default default pool is used
+    pub fn memory_pool(mut self, id: PoolId) -> Self {
+        self.pool = id.pool_ref();
+        self
+    }
+}
+
+impl<I, Err: ErrorRenderer> Responder<Err> for JsonStream<I>
+where
+    I: Iterator + 'static,
+    I::Item: Serialize,
+{
+    type Error = Err::Container;
+    type Future = Ready<Response>;
 
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
         Response::build(StatusCode::OK)
             .content_type("application/json")
-            .body(body)
+            .body(Body::from_message(JsonStreamBody {
+                iter: self.iter,
+                chunk_size: self.chunk_size,
+                pool: self.pool,
+                state: JsonStreamState::Start,
+            }))
             .into()
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum JsonStreamState {
+    Start,
+    Items,
+    Done,
+}
+
+struct JsonStreamBody<I> {
+    iter: I,
+    chunk_size: usize,
+    pool: PoolRef,
+    state: JsonStreamState,
+}
+
+impl<I> MessageBody for JsonStreamBody<I>
+where
+    I: Iterator,
+    I::Item: Serialize,
+{
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn std::error::Error>>>> {
+        if self.state == JsonStreamState::Done {
+            return Poll::Ready(None);
+        }
+
+        let mut buf = self.pool.buf_with_capacity(self.chunk_size);
+        if self.state == JsonStreamState::Start {
+            buf.extend_from_slice(b"[");
+        }
+
+        while buf.len() < self.chunk_size {
+            if let Some(item) = self.iter.next() {
+                if self.state == JsonStreamState::Items {
+                    buf.extend_from_slice(b",");
+                }
+                self.state = JsonStreamState::Items;
+                if let Err(e) = serde_json::to_writer(Writer(&mut buf), &item) {
+                    self.state = JsonStreamState::Done;
+                    return Poll::Ready(Some(Err(Box::new(e))));
+                }
+            } else {
+                buf.extend_from_slice(b"]");
+                self.state = JsonStreamState::Done;
+                break;
+            }
+        }
+        Poll::Ready(Some(Ok(buf.freeze())))
+    }
+}
+
 /// Json extractor. Allow to extract typed information from request's
 /// payload.
 ///
@@ -269,6 +423,7 @@ struct JsonBody<U> {
     #[cfg(not(feature = "compress"))]
     stream: Option<Payload>,
     err: Option<JsonPayloadError>,
+    budget: Option<RequestBudget>,
     fut: Option<Pin<Box<dyn Future<Output = Result<U, JsonPayloadError>>>>>,
 }
 
@@ -298,6 +453,7 @@ where
                 stream: None,
                 fut: None,
                 err: Some(JsonPayloadError::ContentType),
+                budget: None,
             };
         }
 
@@ -318,12 +474,18 @@ where
             stream: Some(payload),
             fut: None,
             err: None,
+            budget: req.extensions().get::<RequestBudget>().cloned(),
         }
     }
 
     /// Change max size of payload. By default max size is 256Kb
     fn limit(mut self, limit: usize) -> Self {
         self.limit = limit;
+        // limit decoded size of compressed payload
+        #[cfg(feature = "compress")]
+        {
+            self.stream = self.stream.take().map(|s| s.limit(limit));
+        }
         self
     }
 }
@@ -344,8 +506,9 @@ where
         }
 
         let limit = self.limit;
+        let budget = self.budget.take();
         if let Some(len) = self.length.take() {
-            if len > limit {
+            if len > limit || matches!(budget, Some(ref b) if len > b.remaining()) {
                 return Poll::Ready(Err(JsonPayloadError::Overflow));
             }
         }
@@ -355,8 +518,13 @@ where
             let mut body = BytesMut::with_capacity(8192);
 
             while let Some(item) = next(&mut stream).await {
-                let chunk = item?;
-                if (body.len() + chunk.len()) > limit {
+                let chunk = match item {
+                    Err(PayloadError::Overflow) => return Err(JsonPayloadError::Overflow),
+                    item => item?,
+                };
+                if (body.len() + chunk.len()) > limit
+                    || matches!(budget, Some(ref b) if b.charge(chunk.len()).is_err())
+                {
                     return Err(JsonPayloadError::Overflow);
                 } else {
                     body.extend_from_slice(&chunk);
@@ -373,11 +541,10 @@ where
 mod tests {
     use super::*;
     use crate::http::header;
-    use crate::util::Bytes;
-    use crate::web::test::{from_request, respond_to, TestRequest};
+    use crate::web::test::{from_request, load_stream, respond_to, TestRequest};
 
     #[derive(
-        serde::Serialize, serde::Deserialize, PartialEq, Debug, derive_more::Display,
+        serde::Serialize, serde::Deserialize, PartialEq, Debug, Clone, derive_more::Display,
     )]
     struct MyObject {
         name: String,
@@ -425,6 +592,39 @@ mod tests {
         assert_eq!(resp.body().get_ref(), b"{\"name\":\"test\"}");
     }
 
+    #[crate::rt_test]
+    async fn test_json_stream() {
+        let req = TestRequest::default().to_http_request();
+
+        let mut resp = respond_to(JsonStream::new(Vec::<u32>::new()), &req).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            header::HeaderValue::from_static("application/json")
+        );
+        let body = load_stream(resp.take_body()).await.unwrap();
+        assert_eq!(body, Bytes::from_static(b"[]"));
+
+        let items: Vec<_> = (0..1000)
+            .map(|i| MyObject {
+                name: format!("item-{}", i),
+            })
+            .collect();
+        let mut resp =
+            respond_to(JsonStream::new(items.clone()).chunk_size(128), &req).await;
+        let mut body = resp.take_body();
+        let mut data = BytesMut::new();
+        let mut chunks = 0;
+        while let Some(chunk) = next(&mut body).await {
+            let chunk = chunk.unwrap();
+            assert!(chunk.len() < 256);
+            data.extend_from_slice(&chunk);
+            chunks += 1;
+        }
+        assert!(chunks > 100);
+        let result: Vec<MyObject> = serde_json::from_slice(&data).unwrap();
+        assert_eq!(result, items);
+    }
+
     #[crate::rt_test]
     async fn test_extract() {
         let (req, mut pl) = TestRequest::default()
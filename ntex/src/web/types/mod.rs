@@ -3,13 +3,17 @@
 pub(in crate::web) mod data;
 pub(in crate::web) mod form;
 pub(in crate::web) mod json;
+mod multipart;
+pub(in crate::web) mod page;
 mod path;
 pub(in crate::web) mod payload;
 mod query;
 
-pub use self::data::Data;
+pub use self::data::{Data, RequiredData};
 pub use self::form::{Form, FormConfig};
-pub use self::json::{Json, JsonConfig};
+pub use self::json::{Json, JsonConfig, JsonStream};
+pub use self::multipart::{Field, Multipart, MultipartConfig};
+pub use self::page::{Page, PageConfig, Paged};
 pub use self::path::Path;
-pub use self::payload::{Payload, PayloadConfig};
+pub use self::payload::{Payload, PayloadConfig, VerifiedPayload};
 pub use self::query::Query;
@@ -0,0 +1,708 @@
+//! Multipart form extractor
+use std::{fmt, fs, io, io::Write, path::PathBuf, rc::Rc};
+
+use mime::Mime;
+
+use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use crate::http::{HttpMessage, Payload};
+use crate::rt::spawn_blocking;
+use crate::util::{next, Bytes, BytesMut, Ready};
+use crate::web::error::{ErrorRenderer, MultipartError};
+use crate::web::middleware::RequestBudget;
+use crate::web::{FromRequest, HttpRequest};
+
+const MAX_HEADERS: usize = 32;
+const MAX_HEADERS_SIZE: usize = 8192;
+
+/// Multipart form extractor (`multipart/form-data`)
+///
+/// Extractor streams form fields and file parts of the request body.
+/// Each part is returned by `next_field()` method, part's content could
+/// be read chunk by chunk, loaded to memory or saved to a file. Unread
+/// content of the part is skipped by next call to `next_field()`.
+///
+/// [**MultipartConfig**](struct.MultipartConfig.html) allows to configure
+/// size limits and allowed content types of file parts.
+///
+/// ```rust
+/// use ntex::web::{self, error::MultipartError, types::Multipart};
+///
+/// async fn upload(mut form: Multipart) -> Result<String, MultipartError> {
+///     let mut names = Vec::new();
+///     while let Some(field) = form.next_field().await? {
+///         names.push(field.name().to_string());
+///         if let Some(filename) = field.filename() {
+///             let path = std::env::temp_dir().join(filename);
+///             field.save_to_file(path).await?;
+///         }
+///     }
+///     Ok(format!("Received: {:?}", names))
+/// }
+///
+/// fn main() {
+///     let app = web::App::new().service(
+///         web::resource("/upload")
+///             .app_data(web::types::MultipartConfig::default().limit(1_048_576))
+///             .route(web::post().to(upload))
+///     );
+/// }
+/// ```
+pub struct Multipart {
+    payload: Payload,
+    buf: BytesMut,
+    boundary: Bytes,
+    state: State,
+    eof: bool,
+    size: usize,
+    field_size: usize,
+    cfg: MultipartConfig,
+    budget: Option<RequestBudget>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Preamble,
+    Headers,
+    Body,
+    Eof,
+}
+
+impl Multipart {
+    /// Create multipart stream for request
+    pub fn new(
+        req: &HttpRequest,
+        payload: Payload,
+        cfg: MultipartConfig,
+    ) -> Result<Self, MultipartError> {
+        let mime = match req.mime_type() {
+            Ok(Some(mime)) => mime,
+            _ => return Err(MultipartError::ContentType),
+        };
+        if mime.type_() != mime::MULTIPART || mime.subtype() != mime::FORM_DATA {
+            return Err(MultipartError::ContentType);
+        }
+        let boundary = match mime.get_param(mime::BOUNDARY) {
+            Some(boundary) if !boundary.as_str().is_empty() => boundary,
+            _ => return Err(MultipartError::Boundary),
+        };
+        if let Some(len) = req.headers().get(&header::CONTENT_LENGTH) {
+            if let Some(len) = len.to_str().ok().and_then(|s| s.parse::<usize>().ok()) {
+                if len > cfg.limit {
+                    return Err(MultipartError::Overflow { limit: cfg.limit });
+                }
+            }
+        }
+
+        let mut delimiter = BytesMut::with_capacity(boundary.as_str().len() + 4);
+        delimiter.extend_from_slice(b"\r\n--");
+        delimiter.extend_from_slice(boundary.as_str().as_bytes());
+
+        // first boundary is not prefixed with new line
+        let mut buf = BytesMut::with_capacity(8192);
+        buf.extend_from_slice(b"\r\n");
+
+        Ok(Multipart {
+            payload,
+            buf,
+            cfg,
+            boundary: delimiter.freeze(),
+            state: State::Preamble,
+            eof: false,
+            size: 0,
+            field_size: 0,
+            budget: req.extensions().get::<RequestBudget>().cloned(),
+        })
+    }
+
+    /// Get next part of the form
+    ///
+    /// Returns `None` when closing boundary is reached.
+    pub async fn next_field(&mut self) -> Result<Option<Field<'_>>, MultipartError> {
+        // skip preamble and unread content of the previous part
+        while self.state == State::Preamble || self.state == State::Body {
+            self.read_chunk().await?;
+        }
+        if self.state == State::Eof {
+            return Ok(None);
+        }
+
+        let headers = self.read_headers().await?;
+        let (name, filename) = headers
+            .get(&header::CONTENT_DISPOSITION)
+            .and_then(|val| val.to_str().ok())
+            .and_then(parse_content_disposition)
+            .ok_or(MultipartError::Headers)?;
+
+        let content_type = if let Some(val) = headers.get(&header::CONTENT_TYPE) {
+            let ct = val
+                .to_str()
+                .ok()
+                .and_then(|s| s.parse::<Mime>().ok())
+                .ok_or(MultipartError::Headers)?;
+            Some(ct)
+        } else {
+            None
+        };
+        if filename.is_some() && !self.cfg.types.is_empty() {
+            let ct = content_type
+                .clone()
+                .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+            if !self.cfg.types.iter().any(|t| {
+                t.type_() == ct.type_()
+                    && (t.subtype() == mime::STAR || t.subtype() == ct.subtype())
+            }) {
+                self.state = State::Eof;
+                return Err(MultipartError::PartContentType(ct.to_string()));
+            }
+        }
+
+        self.state = State::Body;
+        self.field_size = 0;
+        Ok(Some(Field {
+            name,
+            filename,
+            headers,
+            content_type,
+            multipart: self,
+        }))
+    }
+
+    /// Read more data from payload
+    async fn fill(&mut self) -> Result<bool, MultipartError> {
+        if self.eof {
+            return Ok(false);
+        }
+        match next(&mut self.payload).await {
+            Some(Ok(chunk)) => {
+                self.size += chunk.len();
+                if self.size > self.cfg.limit {
+                    self.state = State::Eof;
+                    return Err(MultipartError::Overflow {
+                        limit: self.cfg.limit,
+                    });
+                }
+                self.buf.extend_from_slice(&chunk);
+                Ok(true)
+            }
+            Some(Err(err)) => {
+                self.state = State::Eof;
+                Err(err.into())
+            }
+            None => {
+                self.eof = true;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Read chunk of the current part, returns `None` at the end of part
+    async fn read_chunk(&mut self) -> Result<Option<Bytes>, MultipartError> {
+        if self.state != State::Preamble && self.state != State::Body {
+            return Ok(None);
+        }
+
+        loop {
+            if let Some(pos) = find(&self.buf, &self.boundary) {
+                if pos > 0 {
+                    return Ok(Some(self.buf.split_to(pos).freeze()));
+                }
+
+                // boundary is followed either by `--` or by new line
+                let len = self.boundary.len();
+                if self.buf.len() >= len + 2 {
+                    let _ = self.buf.split_to(len);
+                    let tail = self.buf.split_to(2);
+                    self.state = match &tail[..] {
+                        b"--" => State::Eof,
+                        b"\r\n" => State::Headers,
+                        _ => {
+                            self.state = State::Eof;
+                            return Err(MultipartError::Boundary);
+                        }
+                    };
+                    return Ok(None);
+                }
+            } else {
+                // keep data that could be a start of the boundary
+                let keep = self.boundary.len() - 1;
+                if self.buf.len() > keep {
+                    let len = self.buf.len() - keep;
+                    return Ok(Some(self.buf.split_to(len).freeze()));
+                }
+            }
+
+            if !self.fill().await? {
+                self.state = State::Eof;
+                return Err(MultipartError::Incomplete);
+            }
+        }
+    }
+
+    async fn read_headers(&mut self) -> Result<HeaderMap, MultipartError> {
+        loop {
+            // part without headers
+            if self.buf.starts_with(b"\r\n") {
+                let _ = self.buf.split_to(2);
+                return Ok(HeaderMap::new());
+            }
+            if let Some(pos) = find(&self.buf, b"\r\n\r\n") {
+                let buf = self.buf.split_to(pos + 4);
+                let res = parse_headers(&buf);
+                if res.is_err() {
+                    self.state = State::Eof;
+                }
+                return res;
+            }
+            if self.buf.len() > MAX_HEADERS_SIZE {
+                self.state = State::Eof;
+                return Err(MultipartError::Headers);
+            }
+            if !self.fill().await? {
+                self.state = State::Eof;
+                return Err(MultipartError::Incomplete);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Multipart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Multipart")
+            .field("boundary", &self.boundary)
+            .field("state", &self.state)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for Multipart {
+    type Error = MultipartError;
+    type Future = Ready<Multipart, MultipartError>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let cfg = req
+            .app_data::<MultipartConfig>()
+            .cloned()
+            .unwrap_or_default();
+        Multipart::new(req, payload.take(), cfg).into()
+    }
+}
+
+/// Part of the multipart form
+pub struct Field<'a> {
+    name: String,
+    filename: Option<String>,
+    headers: HeaderMap,
+    content_type: Option<Mime>,
+    multipart: &'a mut Multipart,
+}
+
+impl<'a> Field<'a> {
+    /// Name of the form field
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// File name of the file part
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Content type of the part
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// Headers of the part
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Read next chunk of the part's content
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, MultipartError> {
+        let mp = &mut *self.multipart;
+        if let Some(chunk) = mp.read_chunk().await? {
+            mp.field_size += chunk.len();
+            if mp.field_size > mp.cfg.field_limit {
+                mp.state = State::Eof;
+                Err(MultipartError::FieldOverflow {
+                    limit: mp.cfg.field_limit,
+                })
+            } else {
+                Ok(Some(chunk))
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Load part's content to memory
+    pub async fn bytes(mut self) -> Result<Bytes, MultipartError> {
+        let mut body = BytesMut::new();
+        while let Some(chunk) = self.chunk().await? {
+            if let Some(ref budget) = self.multipart.budget {
+                if budget.charge(chunk.len()).is_err() {
+                    self.multipart.state = State::Eof;
+                    return Err(MultipartError::Overflow {
+                        limit: budget.limit(),
+                    });
+                }
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body.freeze())
+    }
+
+    /// Stream part's content to a file.
+    ///
+    /// File is created or truncated, chunks are written on blocking
+    /// thread pool as they arrive. Resolves to number of written bytes.
+    pub async fn save_to_file<P: Into<PathBuf>>(
+        mut self,
+        path: P,
+    ) -> Result<u64, MultipartError> {
+        let path = path.into();
+        let mut file = blocking(move || fs::File::create(path)).await?;
+        let mut written = 0;
+
+        while let Some(chunk) = self.chunk().await? {
+            written += chunk.len() as u64;
+            file = blocking(move || file.write_all(&chunk).map(|_| file)).await?;
+        }
+        blocking(move || file.sync_all()).await?;
+        Ok(written)
+    }
+}
+
+impl<'a> fmt::Debug for Field<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Field")
+            .field("name", &self.name)
+            .field("filename", &self.filename)
+            .field("content_type", &self.content_type)
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
+
+/// Multipart extractor configuration
+///
+/// ```rust
+/// use ntex::web::{self, types::MultipartConfig};
+///
+/// fn main() {
+///     let app = web::App::new().service(
+///         web::resource("/upload")
+///             .app_data(
+///                 MultipartConfig::default()
+///                     .limit(10_485_760)
+///                     .field_limit(1_048_576)
+///                     .content_type(mime::IMAGE_STAR)
+///             )
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct MultipartConfig {
+    limit: usize,
+    field_limit: usize,
+    types: Rc<Vec<Mime>>,
+}
+
+impl MultipartConfig {
+    /// Change max size of payload. By default max size is 16Mb
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Change max size of a part. By default max size is 8Mb
+    pub fn field_limit(mut self, limit: usize) -> Self {
+        self.field_limit = limit;
+        self
+    }
+
+    /// Add allowed content type of file parts
+    ///
+    /// Types could use wildcard subtype, i.e. `image/*`. By default
+    /// file parts of any content type are allowed.
+    pub fn content_type(mut self, mime: Mime) -> Self {
+        Rc::make_mut(&mut self.types).push(mime);
+        self
+    }
+}
+
+impl Default for MultipartConfig {
+    fn default() -> Self {
+        MultipartConfig {
+            limit: 16_777_216,
+            field_limit: 8_388_608,
+            types: Rc::new(Vec::new()),
+        }
+    }
+}
+
+/// Execute io operation on blocking thread pool
+async fn blocking<F, T>(f: F) -> Result<T, MultipartError>
+where
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    match spawn_blocking(f).await {
+        Ok(res) => res.map_err(MultipartError::Io),
+        Err(_) => Err(MultipartError::Io(io::Error::new(
+            io::ErrorKind::Interrupted,
+            "Blocking task is canceled",
+        ))),
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn parse_headers(buf: &[u8]) -> Result<HeaderMap, MultipartError> {
+    let mut parsed = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    match httparse::parse_headers(buf, &mut parsed) {
+        Ok(httparse::Status::Complete((_, parsed))) => {
+            let mut headers = HeaderMap::new();
+            for h in parsed {
+                let name = HeaderName::from_bytes(h.name.as_bytes())
+                    .map_err(|_| MultipartError::Headers)?;
+                let value = HeaderValue::from_bytes(h.value)
+                    .map_err(|_| MultipartError::Headers)?;
+                headers.append(name, value);
+            }
+            Ok(headers)
+        }
+        _ => Err(MultipartError::Headers),
+    }
+}
+
+/// Parse `form-data` content disposition, returns field name and file name
+fn parse_content_disposition(val: &str) -> Option<(String, Option<String>)> {
+    let mut params = split_params(val).into_iter();
+    if !params.next()?.trim().eq_ignore_ascii_case("form-data") {
+        return None;
+    }
+
+    let mut name = None;
+    let mut filename = None;
+    for param in params {
+        if let Some((key, value)) = param.split_once('=') {
+            let value = unquote(value.trim());
+            match key.trim().to_ascii_lowercase().as_str() {
+                "name" => name = Some(value),
+                "filename" => filename = Some(value),
+                _ => (),
+            }
+        }
+    }
+    name.map(|name| (name, filename))
+}
+
+/// Split header value by `;`, quoted strings are preserved
+fn split_params(val: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (idx, ch) in val.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                params.push(&val[start..idx]);
+                start = idx + 1;
+            }
+            _ => (),
+        }
+    }
+    params.push(&val[start..]);
+    params
+}
+
+fn unquote(val: &str) -> String {
+    if val.len() >= 2 && val.starts_with('"') && val.ends_with('"') {
+        let mut res = String::with_capacity(val.len());
+        let mut escaped = false;
+        for ch in val[1..val.len() - 1].chars() {
+            if !escaped && ch == '\\' {
+                escaped = true;
+            } else {
+                escaped = false;
+                res.push(ch);
+            }
+        }
+        res
+    } else {
+        val.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{from_request, TestRequest};
+    use crate::web::{DefaultError, WebResponseError};
+
+    const BODY: &[u8] = b"preamble\r\n\
+        --abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+        hello\r\n\
+        --abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"a;b.txt\"\r\n\
+        Content-Type: text/plain\r\n\r\n\
+        line1\r\nline2\r\n--not-a-boundary\r\n\
+        --abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+        Content-Disposition: form-data; name=\"skip\"\r\n\r\n\
+        skipped\r\n\
+        --abbc761f78ff4d7cb7573b5a23f96ef0--\r\n";
+
+    fn request(body: &'static [u8], cfg: MultipartConfig) -> (HttpRequest, Payload) {
+        TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "multipart/form-data; boundary=abbc761f78ff4d7cb7573b5a23f96ef0",
+        )
+        .data(cfg)
+        .set_payload(Bytes::from_static(body))
+        .to_http_parts()
+    }
+
+    #[crate::rt_test]
+    async fn test_multipart() {
+        let (req, mut pl) = request(BODY, MultipartConfig::default());
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+        assert!(format!("{:?}", form).contains("Multipart"));
+
+        let field = form.next_field().await.unwrap().unwrap();
+        assert_eq!(field.name(), "title");
+        assert!(field.filename().is_none());
+        assert!(field.content_type().is_none());
+        assert_eq!(field.bytes().await.unwrap(), Bytes::from_static(b"hello"));
+
+        let mut field = form.next_field().await.unwrap().unwrap();
+        assert_eq!(field.name(), "file");
+        assert_eq!(field.filename(), Some("a;b.txt"));
+        assert_eq!(field.content_type(), Some(&mime::TEXT_PLAIN));
+        assert!(field.headers().contains_key(header::CONTENT_TYPE));
+        assert!(format!("{:?}", field).contains("Field"));
+
+        let mut body = BytesMut::new();
+        while let Some(chunk) = field.chunk().await.unwrap() {
+            body.extend_from_slice(&chunk);
+        }
+        assert_eq!(&body[..], b"line1\r\nline2\r\n--not-a-boundary");
+
+        let field = form.next_field().await.unwrap().unwrap();
+        assert_eq!(field.name(), "skip");
+        assert!(form.next_field().await.unwrap().is_none());
+        assert!(form.next_field().await.unwrap().is_none());
+    }
+
+    #[crate::rt_test]
+    async fn test_save_to_file() {
+        let (req, mut pl) = request(BODY, MultipartConfig::default());
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+
+        let path =
+            std::env::temp_dir().join(format!("ntex-multipart-{}.txt", std::process::id()));
+        let _ = form.next_field().await.unwrap().unwrap();
+        let field = form.next_field().await.unwrap().unwrap();
+        assert_eq!(field.save_to_file(path.clone()).await.unwrap(), 30);
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            b"line1\r\nline2\r\n--not-a-boundary".to_vec()
+        );
+        let _ = fs::remove_file(path);
+    }
+
+    #[crate::rt_test]
+    async fn test_limits() {
+        let (req, mut pl) = request(BODY, MultipartConfig::default().field_limit(8));
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+        let _ = form.next_field().await.unwrap().unwrap();
+        let field = form.next_field().await.unwrap().unwrap();
+        let err = field.bytes().await.err().unwrap();
+        assert!(matches!(err, MultipartError::FieldOverflow { limit: 8 }));
+        assert!(form.next_field().await.unwrap().is_none());
+
+        let (req, mut pl) = request(BODY, MultipartConfig::default().limit(64));
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+        let err = form.next_field().await.err().unwrap();
+        assert!(matches!(err, MultipartError::Overflow { limit: 64 }));
+
+        let (req, mut pl) = request(
+            BODY,
+            MultipartConfig::default().content_type(mime::IMAGE_STAR),
+        );
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+        let _ = form.next_field().await.unwrap().unwrap();
+        let err = form.next_field().await.err().unwrap();
+        assert!(matches!(err, MultipartError::PartContentType(_)));
+        let resp = WebResponseError::<DefaultError>::error_response(&err, &req);
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let (req, mut pl) = request(
+            BODY,
+            MultipartConfig::default().content_type(mime::TEXT_STAR),
+        );
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+        let _ = form.next_field().await.unwrap().unwrap();
+        assert!(form.next_field().await.unwrap().is_some());
+    }
+
+    #[crate::rt_test]
+    async fn test_errors() {
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "text/plain").to_http_parts();
+        let err = from_request::<Multipart>(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, MultipartError::ContentType));
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "multipart/form-data")
+                .to_http_parts();
+        let err = from_request::<Multipart>(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, MultipartError::Boundary));
+
+        let (req, mut pl) = request(
+            b"--abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+              Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+              hello",
+            MultipartConfig::default(),
+        );
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+        let field = form.next_field().await.unwrap().unwrap();
+        let err = field.bytes().await.err().unwrap();
+        assert!(matches!(err, MultipartError::Incomplete));
+
+        let (req, mut pl) = request(
+            b"--abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+              Content-Disposition: inline\r\n\r\n\
+              hello",
+            MultipartConfig::default(),
+        );
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+        let err = form.next_field().await.err().unwrap();
+        assert!(matches!(err, MultipartError::Headers));
+    }
+
+    #[test]
+    fn test_content_disposition() {
+        assert_eq!(
+            parse_content_disposition("form-data; name=a; filename=\"x\\\"y.txt\""),
+            Some(("a".to_string(), Some("x\"y.txt".to_string())))
+        );
+        assert_eq!(parse_content_disposition("attachment; name=a"), None);
+        assert_eq!(parse_content_disposition("form-data; filename=a"), None);
+    }
+}
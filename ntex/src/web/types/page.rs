@@ -0,0 +1,434 @@
+//! Pagination extractor/responder
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::http::header::{HeaderValue, ACCEPT_RANGES, CONTENT_RANGE, LINK, RANGE};
+use crate::http::{Payload, Response, StatusCode};
+use crate::util::Ready;
+use crate::web::error::{ErrorRenderer, JsonError, PageError, WebResponseError};
+use crate::web::responder::{Ready as ReadyResponse, Responder};
+use crate::web::{FromRequest, HttpRequest};
+
+/// Pagination params
+///
+/// Page could be requested with `Range` header (`Range: items=0-24`)
+/// or with `offset` and `limit` query parameters (`/users?offset=0&limit=25`).
+/// `Range` header takes precedence over query parameters.
+///
+/// [**PageConfig**](struct.PageConfig.html) allows to configure extraction
+/// process.
+///
+/// ## Example
+///
+/// ```rust
+/// use ntex::web;
+///
+/// async fn index(page: web::types::Page) -> web::types::Paged<u32> {
+///     let total = 1000;
+///     let items = (page.offset() as u32..total)
+///         .take(page.limit())
+///         .collect();
+///
+///     web::types::Paged::new(page, items).total(total as usize)
+/// }
+///
+/// fn main() {
+///     let app = web::App::new().service(
+///        web::resource("/items").route(web::get().to(index)));
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Page {
+    offset: usize,
+    limit: usize,
+    range: bool,
+}
+
+impl Page {
+    /// Create new page
+    pub fn new(offset: usize, limit: usize) -> Self {
+        Page {
+            offset,
+            limit: std::cmp::max(limit, 1),
+            range: false,
+        }
+    }
+
+    /// Index of first requested item
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Max number of requested items
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Check if page is requested with `Range` header
+    pub fn is_range(&self) -> bool {
+        self.range
+    }
+
+    fn from_request(req: &HttpRequest, cfg: &PageConfig) -> Result<Self, PageError> {
+        if let Some(hdr) = req.headers().get(RANGE) {
+            let (offset, limit) = hdr
+                .to_str()
+                .ok()
+                .and_then(|s| parse_range(s, cfg))
+                .ok_or(PageError::Range)?;
+            return Ok(Page {
+                offset,
+                limit,
+                range: true,
+            });
+        }
+
+        let query: PageQuery = serde_urlencoded::from_str(req.query_string())?;
+        let limit = query.limit.unwrap_or(cfg.default_limit);
+        Ok(Page::new(
+            query.offset.unwrap_or(0),
+            std::cmp::min(limit, cfg.max_limit),
+        ))
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct PageQuery {
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+/// Parse `unit=start-end` or `unit=start-` range
+fn parse_range(s: &str, cfg: &PageConfig) -> Option<(usize, usize)> {
+    let (unit, range) = s.split_once('=')?;
+    if unit.trim() != cfg.unit || range.contains(',') {
+        return None;
+    }
+    let (start, end) = range.trim().split_once('-')?;
+    let start = start.parse::<usize>().ok()?;
+    let limit = if end.is_empty() {
+        cfg.default_limit
+    } else {
+        let end = end.parse::<usize>().ok()?;
+        if end < start {
+            return None;
+        }
+        end - start + 1
+    };
+    Some((start, std::cmp::max(std::cmp::min(limit, cfg.max_limit), 1)))
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for Page {
+    type Error = PageError;
+    type Future = Ready<Self, Self::Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let res = if let Some(cfg) = req.app_data::<PageConfig>() {
+            Page::from_request(req, cfg)
+        } else {
+            Page::from_request(req, &PageConfig::default())
+        };
+
+        if let Err(ref e) = res {
+            log::debug!(
+                "Failed to extract page params: {}. Request path: {:?}",
+                e,
+                req.path()
+            );
+        }
+        res.into()
+    }
+}
+
+/// Page extractor configuration
+///
+/// ```rust
+/// use ntex::web::{self, App};
+///
+/// async fn index(page: web::types::Page) -> web::types::Paged<u32> {
+///     web::types::Paged::new(page, vec![1, 2, 3])
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/users")
+///             .app_data(
+///                 // change page extractor configuration
+///                 web::types::PageConfig::default()
+///                    .unit("users")
+///                    .default_limit(10)
+///                    .max_limit(50)
+///             )
+///             .route(web::get().to(index))
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PageConfig {
+    unit: String,
+    default_limit: usize,
+    max_limit: usize,
+}
+
+impl PageConfig {
+    /// Set range unit. By default unit is `items`
+    pub fn unit(mut self, unit: &str) -> Self {
+        self.unit = unit.to_string();
+        self
+    }
+
+    /// Set number of items if limit is not requested. By default it is 25
+    pub fn default_limit(mut self, limit: usize) -> Self {
+        self.default_limit = std::cmp::max(limit, 1);
+        self
+    }
+
+    /// Set max number of items per page. By default it is 100
+    pub fn max_limit(mut self, limit: usize) -> Self {
+        self.max_limit = std::cmp::max(limit, 1);
+        self
+    }
+}
+
+impl Default for PageConfig {
+    fn default() -> Self {
+        PageConfig {
+            unit: "items".to_string(),
+            default_limit: 25,
+            max_limit: 100,
+        }
+    }
+}
+
+/// Paginated json response
+///
+/// Items are serialized as json array. Response contains `Content-Range`
+/// header with served range and `Link` header with `first`, `prev`, `next`
+/// and `last` pages. If page is requested with `Range` header and response
+/// does not contain whole collection, response status is
+/// `206 Partial Content`.
+pub struct Paged<T> {
+    page: Page,
+    items: Vec<T>,
+    total: Option<usize>,
+}
+
+impl<T> Paged<T> {
+    /// Create paginated response for requested page
+    pub fn new(page: Page, items: Vec<T>) -> Self {
+        Paged {
+            page,
+            items,
+            total: None,
+        }
+    }
+
+    /// Set total number of items in collection
+    pub fn total(mut self, total: usize) -> Self {
+        self.total = Some(total);
+        self
+    }
+
+    fn content_range(&self, unit: &str) -> String {
+        let total = self
+            .total
+            .map(|t| t.to_string())
+            .unwrap_or_else(|| "*".to_string());
+        if self.items.is_empty() {
+            format!("{} */{}", unit, total)
+        } else {
+            let end = self.page.offset + self.items.len() - 1;
+            format!("{} {}-{}/{}", unit, self.page.offset, end, total)
+        }
+    }
+
+    fn links(&self, req: &HttpRequest) -> String {
+        let query: Vec<(String, String)> =
+            serde_urlencoded::from_str(req.query_string()).unwrap_or_default();
+        let uri = |offset: usize| {
+            let mut params: Vec<(&str, String)> = query
+                .iter()
+                .filter(|(k, _)| k != "offset" && k != "limit")
+                .map(|(k, v)| (k.as_str(), v.clone()))
+                .collect();
+            params.push(("offset", offset.to_string()));
+            params.push(("limit", self.page.limit.to_string()));
+            format!(
+                "{}?{}",
+                req.path(),
+                serde_urlencoded::to_string(&params).unwrap_or_default()
+            )
+        };
+
+        let Page { offset, limit, .. } = self.page;
+        let mut links = Vec::new();
+        links.push((uri(0), "first"));
+        if offset > 0 {
+            links.push((uri(offset.saturating_sub(limit)), "prev"));
+        }
+        let has_next = match self.total {
+            Some(total) => offset + limit < total,
+            None => self.items.len() >= limit,
+        };
+        if has_next {
+            links.push((uri(offset + limit), "next"));
+        }
+        if let Some(total) = self.total {
+            let last = if total == 0 {
+                0
+            } else {
+                (total - 1) / limit * limit
+            };
+            links.push((uri(last), "last"));
+        }
+
+        let mut s = String::new();
+        for (idx, (uri, rel)) in links.iter().enumerate() {
+            if idx != 0 {
+                s.push_str(", ");
+            }
+            let _ = write!(s, "<{}>; rel=\"{}\"", uri, rel);
+        }
+        s
+    }
+}
+
+impl<T: Serialize, Err: ErrorRenderer> Responder<Err> for Paged<T>
+where
+    Err::Container: From<JsonError>,
+{
+    type Error = JsonError;
+    type Future = ReadyResponse<Response>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        let unit = req
+            .app_data::<PageConfig>()
+            .map(|cfg| cfg.unit.as_str())
+            .unwrap_or("items");
+
+        if let Some(total) = self.total {
+            if self.page.range && self.page.offset >= total && total != 0 {
+                return Response::build(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(CONTENT_RANGE, format!("{} */{}", unit, total))
+                    .finish()
+                    .into();
+            }
+        }
+
+        let body = match serde_json::to_string(&self.items) {
+            Ok(body) => body,
+            Err(e) => return e.error_response(req).into(),
+        };
+
+        let complete = self.page.offset == 0
+            && self.total.map(|t| self.items.len() >= t).unwrap_or(false);
+        let status = if self.page.range && !complete {
+            StatusCode::PARTIAL_CONTENT
+        } else {
+            StatusCode::OK
+        };
+
+        let mut res = Response::build(status);
+        res.content_type("application/json")
+            .header(CONTENT_RANGE, self.content_range(unit))
+            .header(ACCEPT_RANGES, unit);
+        if let Ok(links) = HeaderValue::from_str(&self.links(req)) {
+            res.header(LINK, links);
+        }
+        res.body(body).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header;
+    use crate::web::test::{from_request, respond_to, TestRequest};
+
+    #[crate::rt_test]
+    async fn test_extract() {
+        let (req, mut pl) = TestRequest::with_uri("/items").to_http_parts();
+        let page = from_request::<Page>(&req, &mut pl).await.unwrap();
+        assert_eq!(page, Page::new(0, 25));
+        assert!(!page.is_range());
+
+        let (req, mut pl) =
+            TestRequest::with_uri("/items?offset=10&limit=500").to_http_parts();
+        let page = from_request::<Page>(&req, &mut pl).await.unwrap();
+        assert_eq!((page.offset(), page.limit()), (10, 100));
+
+        let (req, mut pl) = TestRequest::with_uri("/items?offset=a").to_http_parts();
+        let res = from_request::<Page>(&req, &mut pl).await;
+        assert!(matches!(res, Err(PageError::Query(_))));
+
+        let (req, mut pl) = TestRequest::with_uri("/items?offset=10")
+            .header(header::RANGE, "items=5-9")
+            .to_http_parts();
+        let page = from_request::<Page>(&req, &mut pl).await.unwrap();
+        assert_eq!((page.offset(), page.limit()), (5, 5));
+        assert!(page.is_range());
+
+        let (req, mut pl) = TestRequest::default()
+            .header(header::RANGE, "users=5-")
+            .data(PageConfig::default().unit("users").default_limit(10))
+            .to_http_parts();
+        let page = from_request::<Page>(&req, &mut pl).await.unwrap();
+        assert_eq!((page.offset(), page.limit()), (5, 10));
+
+        for hdr in &["items=9-5", "bytes=0-10", "items=0-1,5-6", "items=-5"] {
+            let (req, mut pl) = TestRequest::default()
+                .header(header::RANGE, *hdr)
+                .to_http_parts();
+            let res = from_request::<Page>(&req, &mut pl).await;
+            assert!(matches!(res, Err(PageError::Range)));
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_responder() {
+        let req =
+            TestRequest::with_uri("/items?offset=10&limit=10&q=test").to_http_request();
+        let page = Page::new(10, 10);
+        let resp = respond_to(Paged::new(page, vec![10, 11, 12]).total(33), &req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_RANGE).unwrap(),
+            "items 10-12/33"
+        );
+        assert_eq!(resp.headers().get(header::ACCEPT_RANGES).unwrap(), "items");
+        assert_eq!(
+            resp.headers().get(header::LINK).unwrap(),
+            "</items?q=test&offset=0&limit=10>; rel=\"first\", \
+             </items?q=test&offset=0&limit=10>; rel=\"prev\", \
+             </items?q=test&offset=20&limit=10>; rel=\"next\", \
+             </items?q=test&offset=30&limit=10>; rel=\"last\""
+        );
+
+        let req = TestRequest::default()
+            .header(header::RANGE, "items=0-9")
+            .to_http_request();
+        let page = Page::from_request(&req, &PageConfig::default()).unwrap();
+        let resp = respond_to(Paged::new(page, vec![0; 10]), &req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_RANGE).unwrap(),
+            "items 0-9/*"
+        );
+
+        let resp = respond_to(Paged::new(page, vec![0; 5]).total(5), &req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::default()
+            .header(header::RANGE, "items=10-19")
+            .to_http_request();
+        let page = Page::from_request(&req, &PageConfig::default()).unwrap();
+        let resp = respond_to(Paged::<u32>::new(page, vec![]).total(5), &req).await;
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_RANGE).unwrap(),
+            "items */5"
+        );
+    }
+}
@@ -7,6 +7,7 @@ use mime::Mime;
 use crate::http::{error, header, HttpMessage};
 use crate::util::{next, Bytes, BytesMut, Either, Ready};
 use crate::web::error::{ErrorRenderer, PayloadError};
+use crate::web::middleware::RequestBudget;
 use crate::web::{FromRequest, HttpRequest};
 use crate::Stream;
 
@@ -46,6 +47,15 @@ impl Payload {
     pub fn into_inner(self) -> crate::http::Payload {
         self.0
     }
+
+    /// Read payload trailers
+    ///
+    /// Remaining payload data is read and discarded.
+    pub async fn trailers(
+        &mut self,
+    ) -> Result<Option<crate::http::HeaderMap>, error::PayloadError> {
+        self.0.trailers().await
+    }
 }
 
 impl Stream for Payload {
@@ -98,6 +108,160 @@ impl<Err: ErrorRenderer> FromRequest<Err> for Payload {
     }
 }
 
+/// Payload extractor that verifies request's payload while streaming.
+///
+/// Received size is checked against `Content-Length` header and, with
+/// `content-digest` feature, payload is verified against `Content-Digest`
+/// header. Digest is computed over payload as it is received, content
+/// encoding is not decoded. The last chunk of the payload is held back
+/// until verification is completed, so handler never sees complete
+/// payload that does not match request headers.
+///
+/// Stream fails with `PayloadError::LengthMismatch` (400 Bad Request) or
+/// `PayloadError::DigestMismatch` (422 Unprocessable Entity).
+///
+/// ## Example
+///
+/// ```rust
+/// use ntex::util::BytesMut;
+/// use ntex::web::{self, error, App, HttpResponse};
+///
+/// async fn index(
+///     mut body: web::types::VerifiedPayload,
+/// ) -> Result<HttpResponse, error::PayloadError> {
+///     let mut bytes = BytesMut::new();
+///     while let Some(item) = ntex::util::next(&mut body).await {
+///         bytes.extend_from_slice(&item?);
+///     }
+///     Ok(HttpResponse::Ok().finish())
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/index.html").route(web::put().to(index))
+///     );
+/// }
+/// ```
+#[derive(Debug)]
+pub struct VerifiedPayload {
+    stream: crate::http::Payload,
+    length: Option<usize>,
+    received: usize,
+    #[cfg(feature = "content-digest")]
+    digest: Option<(crate::http::digest::Digest, Vec<u8>)>,
+    chunk: Option<Bytes>,
+    done: bool,
+}
+
+impl VerifiedPayload {
+    fn verify(&mut self) -> Result<(), error::PayloadError> {
+        if let Some(length) = self.length {
+            if length != self.received {
+                log::trace!(
+                    "Payload length mismatch, expected {} received {}",
+                    length,
+                    self.received
+                );
+                return Err(error::PayloadError::LengthMismatch);
+            }
+        }
+        #[cfg(feature = "content-digest")]
+        if let Some((digest, expected)) = self.digest.take() {
+            if digest.finish() != expected {
+                log::trace!("Payload digest mismatch");
+                return Err(error::PayloadError::DigestMismatch);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Stream for VerifiedPayload {
+    type Item = Result<Bytes, error::PayloadError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.as_mut().get_mut();
+
+        loop {
+            if this.done {
+                return Poll::Ready(this.chunk.take().map(Ok));
+            }
+
+            match Pin::new(&mut this.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    this.received += chunk.len();
+                    if this.length.map(|l| this.received > l).unwrap_or(false) {
+                        this.done = true;
+                        this.chunk = None;
+                        return Poll::Ready(Some(Err(error::PayloadError::LengthMismatch)));
+                    }
+                    #[cfg(feature = "content-digest")]
+                    if let Some((ref mut digest, _)) = this.digest {
+                        digest.update(&chunk);
+                    }
+                    if let Some(prev) = this.chunk.replace(chunk) {
+                        return Poll::Ready(Some(Ok(prev)));
+                    }
+                }
+                Poll::Ready(Some(Err(err))) => {
+                    this.done = true;
+                    this.chunk = None;
+                    return Poll::Ready(Some(Err(err)));
+                }
+                Poll::Ready(None) => {
+                    this.done = true;
+                    if let Err(err) = this.verify() {
+                        this.chunk = None;
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for VerifiedPayload {
+    type Error = PayloadError;
+    type Future = Ready<VerifiedPayload, Self::Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, payload: &mut crate::http::Payload) -> Self::Future {
+        let length = if let Some(l) = req.headers().get(&header::CONTENT_LENGTH) {
+            match l.to_str().ok().and_then(|s| s.parse::<usize>().ok()) {
+                Some(l) => Some(l),
+                None => {
+                    return Ready::Err(PayloadError::Payload(
+                        error::PayloadError::UnknownLength,
+                    ))
+                }
+            }
+        } else {
+            None
+        };
+
+        #[cfg(feature = "content-digest")]
+        let digest = {
+            use crate::http::digest;
+            digest::find(req.headers(), digest::CONTENT_DIGEST)
+                .map(|(alg, expected)| (digest::Digest::new(alg), expected))
+        };
+
+        Ready::Ok(VerifiedPayload {
+            length,
+            #[cfg(feature = "content-digest")]
+            digest,
+            stream: payload.take(),
+            received: 0,
+            chunk: None,
+            done: false,
+        })
+    }
+}
+
 /// Request binary data from a request's payload.
 ///
 /// Loads request's payload and construct Bytes instance.
@@ -298,6 +462,7 @@ struct HttpMessageBody {
     #[cfg(not(feature = "compress"))]
     stream: Option<crate::http::Payload>,
     err: Option<PayloadError>,
+    budget: Option<RequestBudget>,
     fut: Option<Pin<Box<dyn Future<Output = Result<Bytes, PayloadError>>>>>,
 }
 
@@ -335,12 +500,18 @@ impl HttpMessageBody {
             length: len,
             fut: None,
             err: None,
+            budget: req.extensions().get::<RequestBudget>().cloned(),
         }
     }
 
     /// Change max size of payload. By default max size is 256Kb
     fn limit(mut self, limit: usize) -> Self {
         self.limit = limit;
+        // limit decoded size of compressed payload
+        #[cfg(feature = "compress")]
+        {
+            self.stream = self.stream.take().map(|s| s.limit(limit));
+        }
         self
     }
 
@@ -351,6 +522,7 @@ impl HttpMessageBody {
             fut: None,
             err: Some(e),
             length: None,
+            budget: None,
         }
     }
 }
@@ -367,8 +539,9 @@ impl Future for HttpMessageBody {
             return Poll::Ready(Err(err));
         }
 
+        let budget = self.budget.take();
         if let Some(len) = self.length.take() {
-            if len > self.limit {
+            if len > self.limit || matches!(budget, Some(ref b) if len > b.remaining()) {
                 return Poll::Ready(Err(PayloadError::from(error::PayloadError::Overflow)));
             }
         }
@@ -381,7 +554,9 @@ impl Future for HttpMessageBody {
 
             while let Some(item) = next(&mut stream).await {
                 let chunk = item?;
-                if body.len() + chunk.len() > limit {
+                if body.len() + chunk.len() > limit
+                    || matches!(budget, Some(ref b) if b.charge(chunk.len()).is_err())
+                {
                     return Err(PayloadError::from(error::PayloadError::Overflow));
                 } else {
                     body.extend_from_slice(&chunk);
@@ -397,8 +572,10 @@ impl Future for HttpMessageBody {
 mod tests {
     use super::*;
     use crate::http::header;
+    use crate::http::StatusCode;
     use crate::util::Bytes;
     use crate::web::test::{from_request, TestRequest};
+    use crate::web::{DefaultError, WebResponseError};
 
     #[crate::rt_test]
     async fn test_payload_config() {
@@ -432,6 +609,74 @@ mod tests {
         assert_eq!(b, Bytes::from_static(b"hello=world"));
     }
 
+    #[crate::rt_test]
+    async fn test_verified_payload() {
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_LENGTH, "11")
+            .set_payload(Bytes::from_static(b"hello=world"))
+            .to_http_parts();
+        let mut s = from_request::<VerifiedPayload>(&req, &mut pl)
+            .await
+            .unwrap();
+        let b = next(&mut s).await.unwrap().unwrap();
+        assert_eq!(b, Bytes::from_static(b"hello=world"));
+        assert!(next(&mut s).await.is_none());
+
+        // payload is shorter than content-length
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_LENGTH, "12")
+            .set_payload(Bytes::from_static(b"hello=world"))
+            .to_http_parts();
+        let mut s = from_request::<VerifiedPayload>(&req, &mut pl)
+            .await
+            .unwrap();
+        let err = next(&mut s).await.unwrap().unwrap_err();
+        assert!(matches!(err, error::PayloadError::LengthMismatch));
+        assert_eq!(
+            WebResponseError::<DefaultError>::status_code(&err),
+            StatusCode::BAD_REQUEST
+        );
+        assert!(next(&mut s).await.is_none());
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_LENGTH, "xx").to_http_parts();
+        assert!(from_request::<VerifiedPayload>(&req, &mut pl)
+            .await
+            .is_err());
+    }
+
+    #[cfg(feature = "content-digest")]
+    #[crate::rt_test]
+    async fn test_verified_payload_digest() {
+        use crate::http::digest::{Digest, DigestAlgorithm, CONTENT_DIGEST};
+
+        let mut digest = Digest::new(DigestAlgorithm::Sha256);
+        digest.update(b"hello=world");
+        let value = digest.header_value();
+
+        let (req, mut pl) = TestRequest::with_header(CONTENT_DIGEST, value.clone())
+            .set_payload(Bytes::from_static(b"hello=world"))
+            .to_http_parts();
+        let mut s = from_request::<VerifiedPayload>(&req, &mut pl)
+            .await
+            .unwrap();
+        let b = next(&mut s).await.unwrap().unwrap();
+        assert_eq!(b, Bytes::from_static(b"hello=world"));
+        assert!(next(&mut s).await.is_none());
+
+        let (req, mut pl) = TestRequest::with_header(CONTENT_DIGEST, value)
+            .set_payload(Bytes::from_static(b"hello=World"))
+            .to_http_parts();
+        let mut s = from_request::<VerifiedPayload>(&req, &mut pl)
+            .await
+            .unwrap();
+        let err = next(&mut s).await.unwrap().unwrap_err();
+        assert!(matches!(err, error::PayloadError::DigestMismatch));
+        assert_eq!(
+            WebResponseError::<DefaultError>::status_code(&err),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert!(next(&mut s).await.is_none());
+    }
+
     #[crate::rt_test]
     async fn test_bytes() {
         let (req, mut pl) = TestRequest::with_header(header::CONTENT_LENGTH, "11")
@@ -448,6 +693,34 @@ mod tests {
         assert!(from_request::<Bytes>(&req, &mut pl).await.is_err());
     }
 
+    #[cfg(feature = "compress")]
+    #[crate::rt_test]
+    async fn test_bytes_decompress_limit() {
+        use std::io::Write;
+
+        let mut e = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        e.write_all(&[b'x'; 65_536]).unwrap();
+        let data = Bytes::from(e.finish().unwrap());
+
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_ENCODING, "gzip")
+            .header(header::CONTENT_LENGTH, data.len().to_string())
+            .set_payload(data.clone())
+            .to_http_parts();
+        let s = from_request::<Bytes>(&req, &mut pl).await.unwrap();
+        assert_eq!(s.len(), 65_536);
+
+        // compressed payload is small, decoded payload exceeds limit
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_ENCODING, "gzip")
+            .header(header::CONTENT_LENGTH, data.len().to_string())
+            .set_payload(data)
+            .data(PayloadConfig::new(1024))
+            .to_http_parts();
+        match from_request::<Bytes>(&req, &mut pl).await {
+            Err(PayloadError::Payload(error::PayloadError::Overflow)) => (),
+            _ => panic!("overflow error is expected"),
+        }
+    }
+
     #[crate::rt_test]
     async fn test_string() {
         let (req, mut pl) = TestRequest::with_header(header::CONTENT_LENGTH, "11")
@@ -15,7 +15,7 @@ use crate::connect::{Connect, ConnectError, Connector};
 use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
 use crate::http::{body::BodySize, client::ClientResponse, error::HttpError, h1};
 use crate::http::{ConnectionType, RequestHead, RequestHeadType, StatusCode, Uri};
-use crate::io::{Base, DispatchItem, Dispatcher, Filter, Io, Sealed};
+use crate::io::{Base, DispatchItem, Dispatcher, Filter, Io, IoRef, Sealed};
 use crate::service::{apply_fn, into_service, IntoService, Service};
 use crate::util::{sink, Either, Ready};
 use crate::{channel::mpsc, rt, time::timeout, time::Millis, ws};
@@ -672,6 +672,10 @@ impl<F> WsConnection<F> {
     pub fn response(&self) -> &ClientResponse {
         &self.res
     }
+
+    pub(super) fn io_ref(&self) -> IoRef {
+        self.io.get_ref()
+    }
 }
 
 impl<F> WsConnection<F> {
@@ -8,7 +8,7 @@ use crate::http::error::{HttpError, ParseError, ResponseError};
 use crate::http::{header::HeaderValue, header::ALLOW, Response, StatusCode};
 use crate::util::Either;
 
-use super::OpCode;
+use super::{CloseCode, OpCode};
 
 /// Websocket service errors
 #[derive(Debug, Display)]
@@ -57,6 +57,16 @@ pub enum ProtocolError {
 
 impl std::error::Error for ProtocolError {}
 
+impl ProtocolError {
+    /// Close code that should be sent to the peer for this error
+    pub fn close_code(&self) -> CloseCode {
+        match self {
+            ProtocolError::Overflow => CloseCode::Size,
+            _ => CloseCode::Protocol,
+        }
+    }
+}
+
 /// Websocket client error
 #[derive(Debug, Display, From)]
 pub enum WsClientBuilderError {
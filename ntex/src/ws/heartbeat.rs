@@ -0,0 +1,376 @@
+//! WebSockets keep-alive support
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use std::{any, cell::Cell, fmt, io, rc::Rc, rc::Weak};
+
+use crate::io::{Base, DispatchItem, Filter, FilterFactory, Io, IoRef};
+use crate::io::{ReadStatus, WriteStatus};
+use crate::service::Service;
+use crate::time::{sleep, Millis};
+use crate::util::{select, Bytes, BytesMut, Either, Ready};
+use crate::{rt, ws};
+
+/// WebSockets keep-alive
+///
+/// Heartbeat sends ping frames to the peer on an interval and tracks pong
+/// responses. If peer does not respond to `max_missed` pings in a row,
+/// connection is closed with `1001 Going Away` close code.
+///
+/// Heartbeat is a filter factory, it must be added to connection io stream
+/// and the same heartbeat must wrap dispatcher service with `wrap()` method.
+/// Wrapped service responds to peer's pings and records pong latency.
+/// Heartbeat handle is available via `IoRef::query::<Heartbeat>()`.
+/// Heartbeat tracks one connection, new heartbeat must be created for
+/// each connection.
+///
+/// ```rust
+/// use ntex::io::{Dispatcher, Io};
+/// use ntex::{service::fn_service, testing::IoTest, time::Millis, util::Ready, ws};
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let (_client, server) = IoTest::create();
+///
+///     let hb = ws::Heartbeat::new(Millis(30_000)).max_missed(3);
+///     let io = Io::new(server).add_filter(hb.clone()).await.unwrap();
+///
+///     let srv = fn_service(|_| Ready::<_, ()>::Ok(None));
+///     ntex::rt::spawn(Dispatcher::new(io, ws::Codec::new(), hb.wrap(srv), Default::default()));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Heartbeat {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    interval: Cell<Millis>,
+    max_missed: Cell<u16>,
+    codec: ws::Codec,
+    seq: Cell<u64>,
+    sent: Cell<Option<Instant>>,
+    missed: Cell<u16>,
+    latency: Cell<Option<Duration>>,
+}
+
+impl Heartbeat {
+    /// Create heartbeat with specified ping interval
+    ///
+    /// By default connection is closed after 3 missed pongs.
+    pub fn new<T: Into<Millis>>(interval: T) -> Self {
+        Self::with_codec(interval, ws::Codec::new())
+    }
+
+    /// Create heartbeat with specified ping interval and codec
+    ///
+    /// Codec is used for encoding ping and close frames, client connections
+    /// must use codec in client mode.
+    pub fn with_codec<T: Into<Millis>>(interval: T, codec: ws::Codec) -> Self {
+        Heartbeat {
+            inner: Rc::new(Inner {
+                codec,
+                interval: Cell::new(interval.into()),
+                max_missed: Cell::new(3),
+                seq: Cell::new(0),
+                sent: Cell::new(None),
+                missed: Cell::new(0),
+                latency: Cell::new(None),
+            }),
+        }
+    }
+
+    /// Set max number of missed pongs
+    ///
+    /// Connection is closed if peer does not respond to specified number
+    /// of pings in a row. By default it is set to 3.
+    pub fn max_missed(self, num: u16) -> Self {
+        self.inner.max_missed.set(std::cmp::max(num, 1));
+        self
+    }
+
+    /// Get ping interval
+    pub fn interval(&self) -> Millis {
+        self.inner.interval.get()
+    }
+
+    /// Latency of last received pong
+    pub fn latency(&self) -> Option<Duration> {
+        self.inner.latency.get()
+    }
+
+    /// Number of pings without response
+    pub fn missed(&self) -> u16 {
+        self.inner.missed.get()
+    }
+
+    /// Wrap dispatcher service
+    ///
+    /// Wrapped service responds to ping frames and consumes pong frames
+    /// for pings sent by heartbeat, all other items are passed to `service`.
+    pub fn wrap<S>(&self, service: S) -> HeartbeatService<S>
+    where
+        S: Service<DispatchItem<ws::Codec>, Response = Option<ws::Message>>,
+    {
+        HeartbeatService {
+            service,
+            hb: self.clone(),
+        }
+    }
+
+    fn pong(&self, data: &[u8]) -> bool {
+        let inner = &self.inner;
+        if data == inner.seq.get().to_be_bytes() {
+            if let Some(sent) = inner.sent.take() {
+                inner.latency.set(Some(sent.elapsed()));
+                inner.missed.set(0);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    async fn run(inner: Weak<Inner>, io: IoRef) {
+        loop {
+            let interval = if let Some(inner) = inner.upgrade() {
+                inner.interval.get()
+            } else {
+                return;
+            };
+            if let Either::Right(_) = select(sleep(interval), io.on_disconnect()).await {
+                return;
+            }
+
+            let inner = if let Some(inner) = inner.upgrade() {
+                inner
+            } else {
+                return;
+            };
+            if io.is_closed() {
+                return;
+            }
+
+            if inner.missed.get() >= inner.max_missed.get() {
+                log::trace!("Peer did not respond to pings, closing connection");
+                let _ = io.encode(
+                    ws::Message::Close(Some(ws::CloseCode::Away.into())),
+                    &inner.codec,
+                );
+                io.close();
+                return;
+            }
+
+            let seq = inner.seq.get().wrapping_add(1);
+            inner.seq.set(seq);
+            inner.sent.set(Some(Instant::now()));
+            inner.missed.set(inner.missed.get() + 1);
+
+            let ping = ws::Message::Ping(Bytes::copy_from_slice(&seq.to_be_bytes()));
+            if let Err(err) = io.encode(ping, &inner.codec) {
+                log::trace!("Cannot send ping: {:?}", err);
+                return;
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Heartbeat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Heartbeat")
+            .field("interval", &self.inner.interval.get())
+            .field("max_missed", &self.inner.max_missed.get())
+            .field("missed", &self.inner.missed.get())
+            .field("latency", &self.inner.latency.get())
+            .finish()
+    }
+}
+
+impl<F: Filter> FilterFactory<F> for Heartbeat {
+    type Filter = HeartbeatFilter<F>;
+
+    type Error = io::Error;
+    type Future = Ready<Io<Self::Filter>, Self::Error>;
+
+    fn create(self, st: Io<F>) -> Self::Future {
+        rt::spawn(Heartbeat::run(Rc::downgrade(&self.inner), st.get_ref()));
+
+        Ready::from(
+            st.map_filter(|inner: F| {
+                Ok::<_, io::Error>(HeartbeatFilter { inner, hb: self })
+            }),
+        )
+    }
+}
+
+/// WebSockets keep-alive filter
+pub struct HeartbeatFilter<F = Base> {
+    inner: F,
+    hb: Heartbeat,
+}
+
+impl<F: Filter> Filter for HeartbeatFilter<F> {
+    #[inline]
+    fn query(&self, id: any::TypeId) -> Option<Box<dyn any::Any>> {
+        if id == any::TypeId::of::<Heartbeat>() {
+            Some(Box::new(self.hb.clone()))
+        } else {
+            self.inner.query(id)
+        }
+    }
+
+    #[inline]
+    fn want_read(&self) {
+        self.inner.want_read()
+    }
+
+    #[inline]
+    fn want_shutdown(&self, err: Option<io::Error>) {
+        self.inner.want_shutdown(err)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self) -> Poll<io::Result<()>> {
+        self.inner.poll_shutdown()
+    }
+
+    #[inline]
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<ReadStatus> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    #[inline]
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<WriteStatus> {
+        self.inner.poll_write_ready(cx)
+    }
+
+    #[inline]
+    fn closed(&self, err: Option<io::Error>) {
+        self.inner.closed(err)
+    }
+
+    #[inline]
+    fn get_read_buf(&self) -> Option<BytesMut> {
+        self.inner.get_read_buf()
+    }
+
+    #[inline]
+    fn get_write_buf(&self) -> Option<BytesMut> {
+        self.inner.get_write_buf()
+    }
+
+    #[inline]
+    fn release_read_buf(
+        &self,
+        src: BytesMut,
+        dst: &mut Option<BytesMut>,
+        nbytes: usize,
+    ) -> io::Result<usize> {
+        self.inner.release_read_buf(src, dst, nbytes)
+    }
+
+    #[inline]
+    fn release_write_buf(&self, buf: BytesMut) -> io::Result<()> {
+        self.inner.release_write_buf(buf)
+    }
+}
+
+/// Service wrapped with websockets heartbeat
+pub struct HeartbeatService<S> {
+    service: S,
+    hb: Heartbeat,
+}
+
+impl<S> Service<DispatchItem<ws::Codec>> for HeartbeatService<S>
+where
+    S: Service<DispatchItem<ws::Codec>, Response = Option<ws::Message>>,
+{
+    type Response = Option<ws::Message>;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: DispatchItem<ws::Codec>) -> Self::Future {
+        match req {
+            DispatchItem::Item(ws::Frame::Ping(data)) => {
+                Either::Right(Ready::Ok(Some(ws::Message::Pong(data))))
+            }
+            DispatchItem::Item(ws::Frame::Pong(data)) if self.hb.pong(&data) => {
+                Either::Right(Ready::Ok(None))
+            }
+            req => Either::Left(self.service.call(req)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{io::Dispatcher, service::fn_service, testing::IoTest};
+
+    #[crate::rt_test]
+    async fn test_heartbeat() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        server.remote_buffer_cap(1024);
+
+        let hb = Heartbeat::new(Millis(50)).max_missed(2);
+        let io = Io::new(server).add_filter(hb.clone()).await.unwrap();
+        assert!(io.query::<Heartbeat>().as_ref().is_some());
+        assert!(format!("{:?}", hb).contains("Heartbeat"));
+
+        let srv = fn_service(|_| Ready::<_, ()>::Ok(None));
+        rt::spawn(Dispatcher::new(
+            io,
+            ws::Codec::new(),
+            hb.wrap(srv),
+            Default::default(),
+        ));
+
+        let codec = ws::Codec::new().client_mode();
+        let client = Io::new(client);
+
+        // peer's ping
+        client
+            .send(ws::Message::Ping(Bytes::from_static(b"test")), &codec)
+            .await
+            .unwrap();
+        let item = client.recv(&codec).await.unwrap().unwrap();
+        assert_eq!(item, ws::Frame::Pong(Bytes::from_static(b"test")));
+
+        // heartbeat's ping
+        let item = client.recv(&codec).await.unwrap().unwrap();
+        let data = if let ws::Frame::Ping(data) = item {
+            data
+        } else {
+            panic!()
+        };
+        client.send(ws::Message::Pong(data), &codec).await.unwrap();
+        sleep(Millis(20)).await;
+        assert!(hb.latency().is_some());
+        assert_eq!(hb.missed(), 0);
+
+        // no pongs
+        let mut pings = 0;
+        loop {
+            match client.recv(&codec).await.unwrap().unwrap() {
+                ws::Frame::Ping(_) => pings += 1,
+                ws::Frame::Close(reason) => {
+                    assert_eq!(reason, Some(ws::CloseCode::Away.into()));
+                    break;
+                }
+                _ => panic!(),
+            }
+        }
+        assert_eq!(pings, 2);
+        assert_eq!(hb.missed(), 2);
+    }
+}
@@ -0,0 +1,236 @@
+use std::cell::RefCell;
+
+use crate::codec::{Decoder, Encoder};
+use crate::util::{BufMut, BytesMut};
+
+use super::codec::{Codec, Frame, Item, Message};
+use super::error::ProtocolError;
+
+/// WebSocket messages codec
+///
+/// Codec reassembles fragmented frames into complete text and binary
+/// messages, so decoder never emits `Frame::Continuation` items. Control
+/// frames are passed through as is, they could be interleaved with
+/// fragments of a message.
+///
+/// If message size exceeds configured limit, decoder returns
+/// `ProtocolError::Overflow` error. Service could respond to decoder
+/// error with close frame, `ProtocolError::close_code()` returns
+/// `1009 Message Too Big` close code for overflow errors.
+///
+/// ```rust
+/// use ntex::io::DispatchItem;
+/// use ntex::{util::Ready, ws};
+///
+/// fn service(
+///     item: DispatchItem<ws::MessageCodec>,
+/// ) -> Ready<Option<ws::Message>, ws::error::ProtocolError> {
+///     match item {
+///         DispatchItem::Item(ws::Frame::Text(text)) => {
+///             Ready::Ok(Some(ws::Message::Binary(text)))
+///         }
+///         DispatchItem::DecoderError(err) => {
+///             Ready::Ok(Some(ws::Message::Close(Some(err.close_code().into()))))
+///         }
+///         _ => Ready::Ok(None),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MessageCodec {
+    codec: Codec,
+    max_size: usize,
+    buf: RefCell<Option<(bool, BytesMut)>>,
+}
+
+impl MessageCodec {
+    /// Create new websocket messages codec
+    pub fn new() -> Self {
+        Self::with(Codec::new())
+    }
+
+    /// Create new websocket messages codec with specified frames codec
+    pub fn with(codec: Codec) -> Self {
+        MessageCodec {
+            codec,
+            max_size: 1_048_576,
+            buf: RefCell::new(None),
+        }
+    }
+
+    /// Set max message size
+    ///
+    /// Max frame size is configured by frames codec.
+    /// By default max size is set to 1mb
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.max_size = size;
+        self
+    }
+
+    /// Set decoder to client mode.
+    ///
+    /// By default decoder works in server mode.
+    pub fn client_mode(mut self) -> Self {
+        self.codec = self.codec.client_mode();
+        self
+    }
+
+    /// Check if codec encoded `Close` message
+    pub fn is_closed(&self) -> bool {
+        self.codec.is_closed()
+    }
+
+    fn start(&self, text: bool, data: &[u8]) -> Result<(), ProtocolError> {
+        if data.len() > self.max_size {
+            return Err(ProtocolError::Overflow);
+        }
+        let mut buf = BytesMut::with_capacity(data.len());
+        buf.put_slice(data);
+        *self.buf.borrow_mut() = Some((text, buf));
+        Ok(())
+    }
+
+    fn extend(&self, data: &[u8]) -> Result<(), ProtocolError> {
+        let mut buf = self.buf.borrow_mut();
+        if let Some((_, ref mut buf)) = *buf {
+            if buf.len() + data.len() > self.max_size {
+                return Err(ProtocolError::Overflow);
+            }
+            buf.extend_from_slice(data);
+            Ok(())
+        } else {
+            Err(ProtocolError::ContinuationNotStarted)
+        }
+    }
+}
+
+impl Default for MessageCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Encoder for MessageCodec {
+    type Item = Message;
+    type Error = ProtocolError;
+
+    #[inline]
+    fn encode(&self, item: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.codec.encode(item, dst)
+    }
+}
+
+impl Decoder for MessageCodec {
+    type Item = Frame;
+    type Error = ProtocolError;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            let frame = match self.codec.decode(src)? {
+                Some(frame) => frame,
+                None => return Ok(None),
+            };
+
+            match frame {
+                Frame::Text(data) | Frame::Binary(data) if data.len() > self.max_size => {
+                    return Err(ProtocolError::Overflow)
+                }
+                Frame::Continuation(item) => match item {
+                    Item::FirstText(data) => self.start(true, &data)?,
+                    Item::FirstBinary(data) => self.start(false, &data)?,
+                    Item::Continue(data) => self.extend(&data)?,
+                    Item::Last(data) => {
+                        self.extend(&data)?;
+                        let (text, buf) = self.buf.borrow_mut().take().unwrap();
+                        return Ok(Some(if text {
+                            Frame::Text(buf.freeze())
+                        } else {
+                            Frame::Binary(buf.freeze())
+                        }));
+                    }
+                },
+                Frame::Text(_) | Frame::Binary(_) if self.buf.borrow().is_some() => {
+                    return Err(ProtocolError::ContinuationStarted)
+                }
+                frame => return Ok(Some(frame)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Bytes;
+    use crate::ws::CloseCode;
+
+    fn encode(items: Vec<Message>) -> BytesMut {
+        let codec = Codec::new().client_mode();
+        let mut buf = BytesMut::new();
+        for item in items {
+            codec.encode(item, &mut buf).unwrap();
+        }
+        buf
+    }
+
+    #[test]
+    fn test_message_codec() {
+        let codec = MessageCodec::new().max_size(8);
+
+        let mut buf = encode(vec![
+            Message::Continuation(Item::FirstText(Bytes::from_static(b"te"))),
+            Message::Ping(Bytes::from_static(b"ping")),
+            Message::Continuation(Item::Continue(Bytes::from_static(b"x"))),
+            Message::Continuation(Item::Last(Bytes::from_static(b"t"))),
+            Message::Binary(Bytes::from_static(b"bin")),
+        ]);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Ping(Bytes::from_static(b"ping")))
+        );
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Text(Bytes::from_static(b"text")))
+        );
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Frame::Binary(Bytes::from_static(b"bin")))
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+
+        let mut buf = encode(vec![
+            Message::Continuation(Item::FirstBinary(Bytes::from_static(b"12345"))),
+            Message::Continuation(Item::Last(Bytes::from_static(b"6789"))),
+        ]);
+        let err = codec.decode(&mut buf).err().unwrap();
+        assert!(matches!(err, ProtocolError::Overflow));
+        assert_eq!(err.close_code(), CloseCode::Size);
+
+        let codec = MessageCodec::new().max_size(8);
+        let mut buf = encode(vec![Message::Binary(Bytes::from_static(b"123456789"))]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(ProtocolError::Overflow)
+        ));
+        assert_eq!(ProtocolError::BadOpCode.close_code(), CloseCode::Protocol);
+
+        let codec = MessageCodec::new();
+        let mut buf = encode(vec![
+            Message::Continuation(Item::FirstText(Bytes::from_static(b"te"))),
+            Message::Text("text".into()),
+        ]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(ProtocolError::ContinuationStarted)
+        ));
+
+        let codec = MessageCodec::default().client_mode();
+        let mut buf = BytesMut::new();
+        codec.encode(Message::Close(None), &mut buf).unwrap();
+        assert!(codec.is_closed());
+        assert_eq!(
+            MessageCodec::new().decode(&mut buf).unwrap(),
+            Some(Frame::Close(None))
+        );
+    }
+}
@@ -7,8 +7,11 @@ mod client;
 mod codec;
 mod frame;
 mod handshake;
+mod heartbeat;
 mod mask;
+mod message;
 mod proto;
+mod reconnect;
 mod sink;
 mod stream;
 mod transport;
@@ -19,7 +22,10 @@ pub use self::client::{WsClient, WsClientBuilder, WsConnection};
 pub use self::codec::{Codec, Frame, Item, Message};
 pub use self::frame::Parser;
 pub use self::handshake::{handshake, handshake_response, verify_handshake};
+pub use self::heartbeat::{Heartbeat, HeartbeatFilter, HeartbeatService};
+pub use self::message::MessageCodec;
 pub use self::proto::{hash_key, CloseCode, CloseReason, OpCode};
+pub use self::reconnect::{WsReconnect, WsReconnectBuilder};
 pub use self::sink::WsSink;
-pub use self::stream::{StreamDecoder, StreamEncoder};
+pub use self::stream::{Chunk, MessageReader, MessageWriter, StreamDecoder, StreamEncoder};
 pub use self::transport::{WsTransport, WsTransportFactory};
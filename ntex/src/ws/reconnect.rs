@@ -0,0 +1,285 @@
+//! Automatically reconnecting websockets client
+use std::task::{Context, Poll};
+use std::{cell::Cell, cell::RefCell, cmp, fmt, rc::Rc};
+
+use crate::channel::mpsc;
+use crate::connect::{Connect, ConnectError, ConnectionState};
+use crate::http::Uri;
+use crate::io::{Filter, Io, IoRef};
+use crate::service::Service;
+use crate::task::LocalWaker;
+use crate::time::{sleep, Millis};
+use crate::util::{poll_fn, select, Either};
+
+use super::client::{WsClient, WsConnection};
+
+/// Automatically reconnecting websockets client
+///
+/// `WsReconnect` connects to websockets server with `WsClient` and passes
+/// established connection to the application. When connection is lost
+/// new connection is established, failed attempts are retried with
+/// exponential backoff. Each established connection is returned by
+/// `next()` method once.
+///
+/// ```rust,no_run
+/// use ntex::{time::Millis, ws};
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let client = ws::WsClient::build("http://localhost:8080/ws")
+///         .finish()
+///         .unwrap();
+///     let conn = client
+///         .reconnect()
+///         .backoff(Millis(100), Millis(5_000))
+///         .finish();
+///
+///     while let Some(ws) = conn.next().await {
+///         // use connection until it is disconnected
+///         let _ = ws.seal().start_default();
+///     }
+/// }
+/// ```
+pub struct WsReconnect<F>(Rc<Inner<F>>);
+
+/// Reconnecting websockets client builder
+pub struct WsReconnectBuilder<F, T> {
+    client: WsClient<F, T>,
+    base_delay: Millis,
+    max_delay: Millis,
+    max_attempts: usize,
+}
+
+struct Inner<F> {
+    state: Cell<ConnectionState>,
+    conn: RefCell<Option<WsConnection<F>>>,
+    current: RefCell<Option<IoRef>>,
+    handles: Cell<usize>,
+    waker: LocalWaker,
+    stop_waker: LocalWaker,
+    stopped: Cell<bool>,
+    subscribers: RefCell<Vec<mpsc::Sender<ConnectionState>>>,
+}
+
+impl<F, T> WsClient<F, T>
+where
+    F: Filter,
+    T: Service<Connect<Uri>, Response = Io<F>, Error = ConnectError> + 'static,
+{
+    /// Create automatically reconnecting client
+    pub fn reconnect(self) -> WsReconnectBuilder<F, T> {
+        WsReconnectBuilder {
+            client: self,
+            base_delay: Millis(100),
+            max_delay: Millis(10_000),
+            max_attempts: 0,
+        }
+    }
+}
+
+impl<F> WsReconnect<F> {
+    #[inline]
+    /// Current connection state
+    pub fn state(&self) -> ConnectionState {
+        self.0.state.get()
+    }
+
+    #[inline]
+    /// Check if connection is established
+    pub fn is_connected(&self) -> bool {
+        self.0.state.get() == ConnectionState::Connected
+    }
+
+    /// Subscribe to connection state changes
+    pub fn subscribe(&self) -> mpsc::Receiver<ConnectionState> {
+        let (tx, rx) = mpsc::channel();
+        self.0.subscribers.borrow_mut().push(tx);
+        rx
+    }
+
+    #[inline]
+    /// Wait for next established connection
+    ///
+    /// Returns `None` if reconnecting is stopped.
+    pub async fn next(&self) -> Option<WsConnection<F>> {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+
+    /// Poll for next established connection
+    pub fn poll_next(&self, cx: &mut Context<'_>) -> Poll<Option<WsConnection<F>>> {
+        if let Some(conn) = self.0.conn.borrow_mut().take() {
+            Poll::Ready(Some(conn))
+        } else if self.0.stopped.get() {
+            Poll::Ready(None)
+        } else {
+            self.0.waker.register(cx.waker());
+            Poll::Pending
+        }
+    }
+
+    /// Stop reconnecting and close current connection
+    pub fn stop(&self) {
+        self.0.stop();
+    }
+}
+
+impl<F> Clone for WsReconnect<F> {
+    fn clone(&self) -> Self {
+        self.0.handles.set(self.0.handles.get() + 1);
+        WsReconnect(self.0.clone())
+    }
+}
+
+impl<F> Drop for WsReconnect<F> {
+    fn drop(&mut self) {
+        self.0.handles.set(self.0.handles.get() - 1);
+        if self.0.handles.get() == 0 {
+            self.0.stop();
+        }
+    }
+}
+
+impl<F> fmt::Debug for WsReconnect<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WsReconnect")
+            .field("state", &self.state())
+            .finish()
+    }
+}
+
+impl<F, T> WsReconnectBuilder<F, T>
+where
+    F: Filter,
+    T: Service<Connect<Uri>, Response = Io<F>, Error = ConnectError> + 'static,
+{
+    /// Set exponential backoff parameters.
+    ///
+    /// Delay before next attempt is doubled after each failed attempt,
+    /// starting at `base` and limited by `max`. By default backoff starts
+    /// at 100 milliseconds and is limited by 10 seconds.
+    pub fn backoff<U: Into<Millis>>(mut self, base: U, max: U) -> Self {
+        self.base_delay = base.into();
+        self.max_delay = max.into();
+        self
+    }
+
+    /// Set max number of consecutive failed attempts.
+    ///
+    /// Reconnecting is stopped if limit is reached. By default
+    /// number of attempts is not limited.
+    pub fn max_attempts(mut self, num: usize) -> Self {
+        self.max_attempts = num;
+        self
+    }
+
+    /// Start connecting
+    pub fn finish(self) -> WsReconnect<F> {
+        let inner = Rc::new(Inner {
+            state: Cell::new(ConnectionState::Connecting),
+            conn: RefCell::new(None),
+            current: RefCell::new(None),
+            handles: Cell::new(1),
+            waker: LocalWaker::new(),
+            stop_waker: LocalWaker::new(),
+            stopped: Cell::new(false),
+            subscribers: RefCell::new(Vec::new()),
+        });
+        crate::rt::spawn(run(inner.clone(), self));
+        WsReconnect(inner)
+    }
+
+    fn delay(&self, attempt: usize) -> Millis {
+        let delay = self
+            .base_delay
+            .0
+            .saturating_mul(1u64 << cmp::min(attempt, 32) as u32);
+        Millis(cmp::min(delay, self.max_delay.0))
+    }
+}
+
+async fn run<F, T>(inner: Rc<Inner<F>>, cfg: WsReconnectBuilder<F, T>)
+where
+    F: Filter,
+    T: Service<Connect<Uri>, Response = Io<F>, Error = ConnectError> + 'static,
+{
+    let mut attempt = 0;
+
+    while !inner.stopped.get() {
+        inner.set_state(ConnectionState::Connecting);
+
+        let res = match select(cfg.client.connect(), inner.on_stop()).await {
+            Either::Left(res) => res,
+            Either::Right(_) => break,
+        };
+
+        match res {
+            Ok(conn) => {
+                log::trace!("Websockets connection is established");
+                attempt = 0;
+                let io = conn.io_ref();
+                let on_disconnect = io.on_disconnect();
+                *inner.current.borrow_mut() = Some(io);
+                *inner.conn.borrow_mut() = Some(conn);
+                inner.set_state(ConnectionState::Connected);
+                inner.waker.wake();
+
+                on_disconnect.await;
+                log::trace!("Websockets connection is lost");
+                inner.current.borrow_mut().take();
+                inner.conn.borrow_mut().take();
+                inner.set_state(ConnectionState::Disconnected);
+            }
+            Err(err) => {
+                attempt += 1;
+                log::error!("Websockets connect attempt {} failed: {:?}", attempt, err);
+                if cfg.max_attempts > 0 && attempt >= cfg.max_attempts {
+                    log::error!("Max number of connect attempts is reached");
+                    break;
+                }
+                inner.set_state(ConnectionState::Disconnected);
+
+                let delay = cfg.delay(attempt - 1);
+                if let Either::Right(_) = select(sleep(delay), inner.on_stop()).await {
+                    break;
+                }
+            }
+        }
+    }
+    inner.stop();
+}
+
+impl<F> Inner<F> {
+    fn set_state(&self, state: ConnectionState) {
+        if self.state.get() != ConnectionState::Closed && self.state.get() != state {
+            self.state.set(state);
+            self.subscribers
+                .borrow_mut()
+                .retain(|tx| tx.send(state).is_ok());
+        }
+    }
+
+    fn stop(&self) {
+        self.stopped.set(true);
+        if let Some(io) = self.current.borrow_mut().take() {
+            io.close();
+        }
+        self.conn.borrow_mut().take();
+        self.set_state(ConnectionState::Closed);
+        self.subscribers.borrow_mut().clear();
+        self.waker.wake();
+        self.stop_waker.wake();
+    }
+
+    /// Resolves when reconnecting is stopped
+    async fn on_stop(&self) {
+        poll_fn(|cx| {
+            if self.stopped.get() {
+                Poll::Ready(())
+            } else {
+                self.stop_waker.register(cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
@@ -1,9 +1,10 @@
 use std::{
-    cell::RefCell, fmt, marker::PhantomData, pin::Pin, rc::Rc, task::Context, task::Poll,
+    cell::RefCell, cmp, fmt, marker::PhantomData, pin::Pin, rc::Rc, task::Context,
+    task::Poll,
 };
 
-use super::{error::ProtocolError, Codec, Frame, Message};
-use crate::util::{Bytes, BytesMut};
+use super::{error::ProtocolError, Codec, Frame, Item, Message};
+use crate::util::{send, Bytes, BytesMut};
 use crate::{codec::Decoder, codec::Encoder, Sink, Stream};
 
 /// Stream error
@@ -107,6 +108,16 @@ impl<S> StreamEncoder<S> {
             codec: Rc::new(RefCell::new(codec)),
         }
     }
+
+    /// Create writer for streamed text message
+    pub fn text_writer<E>(&mut self) -> MessageWriter<'_, S, E> {
+        MessageWriter::new(self, true)
+    }
+
+    /// Create writer for streamed binary message
+    pub fn binary_writer<E>(&mut self) -> MessageWriter<'_, S, E> {
+        MessageWriter::new(self, false)
+    }
 }
 
 impl<S, E> Sink<Result<Message, E>> for StreamEncoder<S>
@@ -166,6 +177,163 @@ where
     }
 }
 
+/// Streamed message writer.
+///
+/// Message data is sent as a sequence of continuation frames, each frame
+/// is sent only when underlying sink is ready to accept it, so large
+/// messages do not need to be assembled in memory. Message must be
+/// completed with `finish()` method, encoder rejects new data messages
+/// until message is completed.
+pub struct MessageWriter<'a, S, E> {
+    encoder: &'a mut StreamEncoder<S>,
+    text: bool,
+    started: bool,
+    frame_size: usize,
+    _t: PhantomData<E>,
+}
+
+impl<'a, S, E> MessageWriter<'a, S, E> {
+    fn new(encoder: &'a mut StreamEncoder<S>, text: bool) -> Self {
+        MessageWriter {
+            encoder,
+            text,
+            started: false,
+            frame_size: 65_536,
+            _t: PhantomData,
+        }
+    }
+
+    /// Set max size of continuation frame payload.
+    ///
+    /// By default max size is set to 64kb
+    pub fn frame_size(mut self, size: usize) -> Self {
+        assert!(size > 0, "Frame size must be greater than 0");
+        self.frame_size = size;
+        self
+    }
+}
+
+impl<'a, S, E> MessageWriter<'a, S, E>
+where
+    S: Sink<Result<Bytes, E>> + Unpin,
+    S::Error: fmt::Debug,
+{
+    /// Write chunk of message data.
+    ///
+    /// Data is split into frames of `frame_size` bytes. Writer does not
+    /// validate text data, utf8 sequences could span multiple chunks.
+    pub async fn write(&mut self, mut data: Bytes) -> Result<(), StreamError<S::Error>> {
+        while !data.is_empty() {
+            let chunk = data.split_to(cmp::min(self.frame_size, data.len()));
+            let item = if self.started {
+                Item::Continue(chunk)
+            } else {
+                self.first(chunk)
+            };
+            send(&mut *self.encoder, Ok(Message::Continuation(item))).await?;
+        }
+        Ok(())
+    }
+
+    /// Complete message.
+    pub async fn finish(mut self) -> Result<(), StreamError<S::Error>> {
+        if !self.started {
+            let item = self.first(Bytes::new());
+            send(&mut *self.encoder, Ok(Message::Continuation(item))).await?;
+        }
+        send(
+            &mut *self.encoder,
+            Ok(Message::Continuation(Item::Last(Bytes::new()))),
+        )
+        .await
+    }
+
+    fn first(&mut self, chunk: Bytes) -> Item {
+        self.started = true;
+        if self.text {
+            Item::FirstText(chunk)
+        } else {
+            Item::FirstBinary(chunk)
+        }
+    }
+}
+
+/// Chunk of websocket message
+#[derive(Debug, PartialEq)]
+pub enum Chunk {
+    /// Text message data, flag is set for the last chunk of the message
+    Text(Bytes, bool),
+    /// Binary message data, flag is set for the last chunk of the message
+    Binary(Bytes, bool),
+    /// Ping, pong or close frame
+    Control(Frame),
+}
+
+pin_project_lite::pin_project! {
+    /// Stream of websocket message chunks.
+    ///
+    /// Converts stream of frames to a stream of message chunks. Single
+    /// frame messages and continuation frames are yielded the same way,
+    /// so large messages do not need to be assembled in memory.
+    pub struct MessageReader<S> {
+        #[pin]
+        stream: S,
+        text: bool,
+    }
+}
+
+impl<S> MessageReader<S> {
+    pub fn new(stream: S) -> Self {
+        MessageReader {
+            stream,
+            text: false,
+        }
+    }
+}
+
+impl<S, E> Stream for MessageReader<S>
+where
+    S: Stream<Item = Result<Frame, E>>,
+{
+    type Item = Result<Chunk, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+
+        let frame = match this.stream.poll_next(cx) {
+            Poll::Ready(Some(Ok(frame))) => frame,
+            Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let chunk = |data, text, last| {
+            if text {
+                Chunk::Text(data, last)
+            } else {
+                Chunk::Binary(data, last)
+            }
+        };
+
+        let item = match frame {
+            Frame::Text(data) => Chunk::Text(data, true),
+            Frame::Binary(data) => Chunk::Binary(data, true),
+            Frame::Continuation(Item::FirstText(data)) => {
+                *this.text = true;
+                Chunk::Text(data, false)
+            }
+            Frame::Continuation(Item::FirstBinary(data)) => {
+                *this.text = false;
+                Chunk::Binary(data, false)
+            }
+            Frame::Continuation(Item::Continue(data)) => chunk(data, *this.text, false),
+            Frame::Continuation(Item::Last(data)) => chunk(data, *this.text, true),
+            frame => Chunk::Control(frame),
+        };
+        Poll::Ready(Some(Ok(item)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -220,4 +388,67 @@ mod tests {
         assert_eq!(data, b"\x81\x04test".as_ref());
         assert!(next(&mut rx).await.is_none());
     }
+
+    #[crate::rt_test]
+    async fn test_message_writer() {
+        let (tx, rx) = mpsc::channel();
+        let mut encoder = StreamEncoder::new(tx);
+
+        let mut writer = encoder.text_writer::<()>().frame_size(4);
+        writer
+            .write(Bytes::from_static(b"0123456789"))
+            .await
+            .unwrap();
+        writer.finish().await.unwrap();
+
+        let writer = encoder.binary_writer::<()>();
+        writer.finish().await.unwrap();
+
+        send(
+            &mut encoder,
+            Ok::<_, ()>(Message::Ping(Bytes::from_static(b"ping"))),
+        )
+        .await
+        .unwrap();
+        drop(encoder);
+
+        let decoder = StreamDecoder::with(rx, Codec::new().client_mode());
+        let mut reader = MessageReader::new(decoder);
+        let mut chunks = Vec::new();
+        while let Some(item) = next(&mut reader).await {
+            chunks.push(item.unwrap());
+        }
+        assert_eq!(
+            chunks,
+            vec![
+                Chunk::Text(Bytes::from_static(b"0123"), false),
+                Chunk::Text(Bytes::from_static(b"4567"), false),
+                Chunk::Text(Bytes::from_static(b"89"), false),
+                Chunk::Text(Bytes::new(), true),
+                Chunk::Binary(Bytes::new(), false),
+                Chunk::Binary(Bytes::new(), true),
+                Chunk::Control(Frame::Ping(Bytes::from_static(b"ping"))),
+            ]
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_message_writer_unfinished() {
+        let (tx, _rx) = mpsc::channel();
+        let mut encoder = StreamEncoder::new(tx);
+
+        let mut writer = encoder.binary_writer::<()>();
+        writer.write(Bytes::from_static(b"data")).await.unwrap();
+        drop(writer);
+
+        let res = send(
+            &mut encoder,
+            Ok::<_, ()>(Message::Continuation(Item::FirstBinary(Bytes::new()))),
+        )
+        .await;
+        assert!(matches!(
+            res,
+            Err(StreamError::Protocol(ProtocolError::ContinuationStarted))
+        ));
+    }
 }
@@ -10,6 +10,7 @@ use futures::{future::ok, stream::once};
 use rand::Rng;
 
 use ntex::http::client::error::{JsonPayloadError, SendRequestError};
+use ntex::http::client::multipart::{Form, Part};
 use ntex::http::client::{Client, Connector};
 use ntex::http::test::server as test_server;
 use ntex::http::{header, HttpMessage, HttpService};
@@ -158,6 +159,136 @@ async fn test_form() {
     assert!(response.status().is_success());
 }
 
+#[ntex::test]
+async fn test_reader_body() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest, body: Bytes| async move {
+                let len = req
+                    .headers()
+                    .get(header::CONTENT_LENGTH)
+                    .map(|v| v.to_str().unwrap().to_string())
+                    .unwrap_or_else(|| "chunked".to_string());
+                HttpResponse::Ok().header("x-len", len).body(body)
+            },
+        )))
+    });
+
+    // known length
+    let mut response = srv
+        .post("/")
+        .send_reader(STR.as_bytes(), Some(STR.len() as u64))
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers().get("x-len").unwrap(),
+        STR.len().to_string().as_str()
+    );
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+
+    // unknown length
+    let mut response = srv
+        .post("/")
+        .send_reader(STR.as_bytes(), None)
+        .await
+        .unwrap();
+    assert_eq!(response.headers().get("x-len").unwrap(), "chunked");
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+
+    // length from headers
+    let request = srv
+        .post("/")
+        .header(header::CONTENT_LENGTH, STR.len())
+        .freeze()
+        .unwrap();
+    let mut response = request.send_reader(STR.as_bytes(), None).await.unwrap();
+    assert_eq!(
+        response.headers().get("x-len").unwrap(),
+        STR.len().to_string().as_str()
+    );
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+
+    // stream with content-length header
+    let mut response = srv
+        .post("/")
+        .header(header::CONTENT_LENGTH, STR.len())
+        .send_stream(once(ok::<_, std::io::Error>(Bytes::from_static(
+            STR.as_ref(),
+        ))))
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers().get("x-len").unwrap(),
+        STR.len().to_string().as_str()
+    );
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
+
+    // reader is shorter than declared length
+    let result = srv
+        .post("/")
+        .send_reader(STR.as_bytes(), Some(STR.len() as u64 + 10))
+        .await;
+    assert!(result.is_err());
+}
+
+#[ntex::test]
+async fn test_multipart_body() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest, mut form: web::types::Multipart| async move {
+                let mut result = Vec::new();
+                while let Some(field) = form.next_field().await? {
+                    let name = field.name().to_string();
+                    let filename = field.filename().unwrap_or("-").to_string();
+                    let body = field.bytes().await?;
+                    result.push(format!("{}:{}:{}", name, filename, body.len()));
+                }
+                let len = req
+                    .headers()
+                    .get(header::CONTENT_LENGTH)
+                    .map(|v| v.to_str().unwrap().to_string())
+                    .unwrap_or_else(|| "chunked".to_string());
+                Ok::<_, web::error::MultipartError>(
+                    HttpResponse::Ok()
+                        .header("x-len", len)
+                        .body(result.join(";")),
+                )
+            },
+        )))
+    });
+
+    // known length
+    let form = Form::new()
+        .text("title", "test")
+        .part(Part::bytes("file", STR).filename("data.txt"));
+    let len = form.content_length().unwrap();
+    let mut response = srv.post("/").send_multipart(form).await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers().get("x-len").unwrap(),
+        len.to_string().as_str()
+    );
+    let bytes = response.body().await.unwrap();
+    assert_eq!(
+        bytes,
+        Bytes::from(format!("title:-:4;file:data.txt:{}", STR.len()))
+    );
+
+    // streaming part
+    let form =
+        Form::new().part(Part::reader("file", STR.as_bytes(), None).filename("data.bin"));
+    let mut response = srv.post("/").send_multipart(form).await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.headers().get("x-len").unwrap(), "chunked");
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from(format!("file:data.bin:{}", STR.len())));
+}
+
 #[ntex::test]
 async fn test_timeout() {
     let srv = test::server(|| {
@@ -483,6 +614,32 @@ async fn test_client_gzip_encoding() {
     assert_eq!(bytes, Bytes::from_static(STR.as_ref()));
 }
 
+#[ntex::test]
+async fn test_client_decompress_limit() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(|| async {
+            let mut e = GzEncoder::new(Vec::new(), Compression::default());
+            e.write_all(STR.repeat(10).as_ref()).unwrap();
+            let data = e.finish().unwrap();
+
+            HttpResponse::Ok()
+                .header("content-encoding", "gzip")
+                .body(data)
+        })))
+    });
+
+    let client = Client::build().decompress_limit(1024).finish();
+    let mut response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+
+    // decoded payload exceeds limit
+    let res = response.body().limit(1_000_000).await;
+    assert!(matches!(
+        res,
+        Err(ntex::http::error::PayloadError::Overflow)
+    ));
+}
+
 #[ntex::test]
 async fn test_client_gzip_encoding_large() {
     let srv = test::server(|| {
@@ -877,3 +1034,430 @@ async fn client_bearer_auth() {
     let response = request.send().await.unwrap();
     assert!(response.status().is_success());
 }
+
+#[ntex::test]
+async fn client_signer() {
+    use ntex::http::body::SizedStream;
+    use ntex::http::client::{HmacSigner, SigV4};
+
+    let srv = test::server(|| {
+        App::new().route(
+            "/",
+            web::to(|req: HttpRequest, body: Bytes| async move {
+                let auth = req
+                    .headers()
+                    .get(header::AUTHORIZATION)
+                    .map(|v| v.to_str().unwrap().to_string())
+                    .unwrap_or_default();
+                let encoding = req
+                    .headers()
+                    .get(header::CONTENT_ENCODING)
+                    .map(|v| v.to_str().unwrap().to_string())
+                    .unwrap_or_default();
+                HttpResponse::Ok().body(format!("{}|{}|{}", auth, encoding, body.len()))
+            }),
+        )
+    });
+
+    let client = Client::build()
+        .signer(HmacSigner::new("key-1", b"secret"))
+        .finish();
+    let mut response = client.post(srv.url("/")).send_body("data").await.unwrap();
+    let body = response.body().await.unwrap();
+    let body = std::str::from_utf8(&body).unwrap();
+    assert!(body.starts_with("Signature keyId=\"key-1\",algorithm=\"hmac-sha256\""));
+    assert!(body.ends_with("||4"));
+
+    // frozen request
+    let req = client.get(srv.url("/")).freeze().unwrap();
+    let mut response = req.send().await.unwrap();
+    let body = response.body().await.unwrap();
+    assert!(body.starts_with(b"Signature keyId=\"key-1\""));
+
+    // aws chunked payload
+    let client = Client::build()
+        .signer(SigV4::new("AKID", "SECRET", "us-east-1", "s3"))
+        .finish();
+    let stream = once(ok::<_, Box<dyn std::error::Error>>(Bytes::from(vec![
+        b'a';
+        10000
+    ])));
+    let mut response = client
+        .put(srv.url("/"))
+        .send_body(SizedStream::new(10000, stream))
+        .await
+        .unwrap();
+    let body = response.body().await.unwrap();
+    let body = std::str::from_utf8(&body).unwrap();
+    assert!(body.starts_with("AWS4-HMAC-SHA256 Credential=AKID/"));
+    // one data chunk and final chunk
+    assert!(body.ends_with(&format!("|aws-chunked|{}", 10000 + 89 + 86)));
+}
+
+#[ntex::test]
+async fn client_retry() {
+    use ntex::http::client::RetryPolicy;
+    use ntex::http::StatusCode;
+
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+    let srv = test::server(move || {
+        let num = num2.clone();
+        App::new().route(
+            "/",
+            web::to(move |_: Bytes| {
+                let n = num.fetch_add(1, Ordering::Relaxed);
+                async move {
+                    if n % 3 == 2 {
+                        HttpResponse::Ok().body(n.to_string())
+                    } else {
+                        HttpResponse::ServiceUnavailable()
+                            .header(header::RETRY_AFTER, "0")
+                            .finish()
+                    }
+                }
+            }),
+        )
+    });
+
+    let client = Client::build()
+        .retry(RetryPolicy::new().backoff(Millis(10), Millis(100)))
+        .finish();
+
+    // idempotent request is retried
+    let mut response = client.get(srv.url("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.body().await.unwrap(), Bytes::from_static(b"2"));
+
+    // non-idempotent request is not retried
+    let response = client.post(srv.url("/")).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(num.load(Ordering::Relaxed), 4);
+
+    // per-request policy
+    let mut response = client
+        .post(srv.url("/"))
+        .retry(RetryPolicy::new().non_idempotent())
+        .send_body("data")
+        .await
+        .unwrap();
+    assert_eq!(response.body().await.unwrap(), Bytes::from_static(b"5"));
+
+    let response = client
+        .get(srv.url("/"))
+        .retry(RetryPolicy::new().max_retries(1))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(num.load(Ordering::Relaxed), 8);
+
+    let response = client.get(srv.url("/")).no_retry().send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // streaming body is not retried
+    let response = client
+        .get(srv.url("/"))
+        .send_stream(once(ok::<_, std::io::Error>(Bytes::from_static(b"data"))))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(num.load(Ordering::Relaxed), 10);
+
+    // custom retryable statuses
+    let response = client
+        .get(srv.url("/"))
+        .retry(RetryPolicy::new().statuses(vec![StatusCode::SERVICE_UNAVAILABLE]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[ntex::test]
+async fn client_redirect() {
+    use ntex::http::client::RedirectPolicy;
+    use ntex::http::StatusCode;
+
+    let srv2 = test::server(|| {
+        App::new().route(
+            "/auth",
+            web::to(|req: HttpRequest| async move {
+                HttpResponse::Ok().body(
+                    req.headers()
+                        .contains_key(header::AUTHORIZATION)
+                        .to_string(),
+                )
+            }),
+        )
+    });
+    let cross_url = srv2.url("/auth");
+
+    let srv = test::server(move || {
+        let cross_url = cross_url.clone();
+        App::new()
+            .route(
+                "/echo",
+                web::to(|req: HttpRequest, body: Bytes| async move {
+                    HttpResponse::Ok().body(format!(
+                        "{} {} {}",
+                        req.method(),
+                        String::from_utf8_lossy(&body),
+                        req.headers().contains_key(header::AUTHORIZATION)
+                    ))
+                }),
+            )
+            .route(
+                "/see-other",
+                web::to(|_: Bytes| async {
+                    HttpResponse::SeeOther()
+                        .header(header::LOCATION, "/echo")
+                        .finish()
+                }),
+            )
+            .route(
+                "/temporary",
+                web::to(|_: Bytes| async {
+                    HttpResponse::TemporaryRedirect()
+                        .header(header::LOCATION, "echo")
+                        .finish()
+                }),
+            )
+            .route(
+                "/loop",
+                web::to(|| async {
+                    HttpResponse::Found()
+                        .header(header::LOCATION, "/loop")
+                        .finish()
+                }),
+            )
+            .route(
+                "/cross",
+                web::to(move || {
+                    let cross_url = cross_url.clone();
+                    async move {
+                        HttpResponse::Found()
+                            .header(header::LOCATION, cross_url)
+                            .finish()
+                    }
+                }),
+            )
+    });
+
+    let client = Client::build().bearer_auth("token").finish();
+
+    // 303 is followed with GET method
+    let mut response = client
+        .post(srv.url("/see-other"))
+        .send_body("data")
+        .await
+        .unwrap();
+    assert_eq!(
+        response.body().await.unwrap(),
+        Bytes::from_static(b"GET  true")
+    );
+
+    // 307 keeps method and body
+    let mut response = client
+        .post(srv.url("/temporary"))
+        .send_body("data")
+        .await
+        .unwrap();
+    assert_eq!(
+        response.body().await.unwrap(),
+        Bytes::from_static(b"POST data true")
+    );
+
+    // streaming body could not be resent
+    let response = client
+        .post(srv.url("/temporary"))
+        .send_stream(once(ok::<_, std::io::Error>(Bytes::from_static(b"data"))))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+
+    // 303 method rewrite is disabled
+    let client2 = Client::build()
+        .redirect(RedirectPolicy::new().rewrite_see_other(false))
+        .finish();
+    let mut response = client2
+        .put(srv.url("/see-other"))
+        .send_body("data")
+        .await
+        .unwrap();
+    assert_eq!(
+        response.body().await.unwrap(),
+        Bytes::from_static(b"PUT data false")
+    );
+
+    // max redirects
+    let res = client.get(srv.url("/loop")).send().await;
+    assert!(matches!(res, Err(SendRequestError::TooManyRedirects)));
+
+    // auth headers are not forwarded to other origin
+    let mut response = client.get(srv.url("/cross")).send().await.unwrap();
+    assert_eq!(response.body().await.unwrap(), Bytes::from_static(b"false"));
+
+    let client2 = Client::build()
+        .bearer_auth("token")
+        .redirect(RedirectPolicy::new().forward_auth(true))
+        .finish();
+    let mut response = client2.get(srv.url("/cross")).send().await.unwrap();
+    assert_eq!(response.body().await.unwrap(), Bytes::from_static(b"true"));
+
+    // redirect filter
+    let client2 = Client::build()
+        .redirect(
+            RedirectPolicy::new().filter(|from, to, _| from.port_u16() == to.port_u16()),
+        )
+        .finish();
+    let response = client2.get(srv.url("/cross")).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FOUND);
+    let response = client2.get(srv.url("/loop")).send().await;
+    assert!(matches!(response, Err(SendRequestError::TooManyRedirects)));
+
+    // redirects are disabled
+    let client2 = Client::build().disable_redirects().finish();
+    let response = client2.get(srv.url("/loop")).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::FOUND);
+}
+
+#[ntex::test]
+async fn client_verify_digest() {
+    use ntex::http::error::PayloadError;
+    use ntex::web::middleware::ContentDigest;
+
+    let srv = test::server(|| {
+        App::new()
+            .wrap(ContentDigest::new())
+            .route("/", web::to(|| async { HttpResponse::Ok().body(STR) }))
+            .route(
+                "/invalid",
+                web::to(|| async {
+                    HttpResponse::Ok()
+                        .header("content-digest", "sha-256=:AAAA:")
+                        .body(STR)
+                }),
+            )
+            .route(
+                "/gzip",
+                web::to(|| async {
+                    let mut e = GzEncoder::new(Vec::new(), Compression::default());
+                    e.write_all(STR.as_ref()).unwrap();
+                    HttpResponse::Ok()
+                        .header("content-encoding", "gzip")
+                        .header(
+                            "repr-digest",
+                            "sha-256=:WtRAmr2sUuR3C1XO75DNfqsFSVzInZ+03LZEsHBdEMI=:",
+                        )
+                        .body(e.finish().unwrap())
+                }),
+            )
+    });
+
+    let mut response = srv.get("/").send().await.unwrap();
+    assert!(response.verify_digest());
+    let body = response.body().await.unwrap();
+    assert_eq!(body, Bytes::from_static(STR.as_ref()));
+
+    let mut response = srv.get("/invalid").send().await.unwrap();
+    assert!(response.verify_digest());
+    let res = response.body().await;
+    assert!(matches!(res, Err(PayloadError::DigestMismatch)));
+
+    // repr digest is used for decoded content
+    let mut response = srv.get("/gzip").send().await.unwrap();
+    assert!(response.verify_digest());
+    let body = response.body().await.unwrap();
+    assert_eq!(body, Bytes::from_static(STR.as_ref()));
+}
+
+#[ntex::test]
+async fn client_upgrade() {
+    use ntex::codec::BytesCodec;
+    use ntex::{io::Io, service::fn_service};
+
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let srv = ntex::server::test_server(move || {
+        let num2 = num2.clone();
+        fn_service(move |io: Io| {
+            num2.fetch_add(1, Ordering::Relaxed);
+            async move {
+                while let Some(req) = io.recv(&BytesCodec).await.unwrap() {
+                    let req = String::from_utf8_lossy(&req).to_lowercase();
+                    let res: &'static [u8] = if req.starts_with("connect") {
+                        b"HTTP/1.1 200 OK\r\n\r\nhello"
+                    } else if req.contains("upgrade: custom") {
+                        b"HTTP/1.1 101 Switching Protocols\r\n\
+                          connection: upgrade\r\nupgrade: custom\r\n\r\nhello"
+                    } else {
+                        io.send(
+                            Bytes::from_static(
+                                b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n",
+                            ),
+                            &BytesCodec,
+                        )
+                        .await
+                        .unwrap();
+                        continue;
+                    };
+                    io.send(Bytes::from_static(res), &BytesCodec).await.unwrap();
+
+                    // echo
+                    while let Some(msg) = io.recv(&BytesCodec).await.unwrap() {
+                        io.send(msg.freeze(), &BytesCodec).await.unwrap();
+                    }
+                    break;
+                }
+                Ok::<_, std::io::Error>(())
+            }
+        })
+    });
+    let client = Client::new();
+    let url = format!("http://{}/", srv.addr());
+
+    // regular response keeps connection in pool
+    let (res, io) = client.get(&url).upgrade().await.unwrap();
+    assert!(res.status().is_success());
+    assert!(io.is_none());
+
+    // switched connection is removed from pool
+    let (res, io) = client
+        .get(&url)
+        .header(header::CONNECTION, "upgrade")
+        .header(header::UPGRADE, "custom")
+        .upgrade()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), ntex::http::StatusCode::SWITCHING_PROTOCOLS);
+    let io = io.unwrap();
+    assert_eq!(num.load(Ordering::Relaxed), 1);
+
+    // buffered bytes are preserved
+    let msg = io.recv(&BytesCodec).await.unwrap().unwrap();
+    assert_eq!(msg, Bytes::from_static(b"hello"));
+    io.send(Bytes::from_static(b"ping"), &BytesCodec)
+        .await
+        .unwrap();
+    let msg = io.recv(&BytesCodec).await.unwrap().unwrap();
+    assert_eq!(msg, Bytes::from_static(b"ping"));
+
+    // connect tunnel
+    let (res, io) = client
+        .request(ntex::http::Method::CONNECT, &url)
+        .upgrade()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(num.load(Ordering::Relaxed), 2);
+    let io = io.unwrap();
+    let msg = io.recv(&BytesCodec).await.unwrap().unwrap();
+    assert_eq!(msg, Bytes::from_static(b"hello"));
+
+    // plain request opens new connection
+    let res = client.get(&url).send().await.unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(num.load(Ordering::Relaxed), 3);
+}
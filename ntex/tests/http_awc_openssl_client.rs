@@ -1,17 +1,18 @@
 #![cfg(feature = "openssl")]
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use futures::future::ok;
 use tls_openssl::ssl::{
-    AlpnError, SslAcceptor, SslConnector, SslFiletype, SslMethod, SslVerifyMode,
+    AlpnError, NameType, SslAcceptor, SslConnector, SslFiletype, SslMethod, SslVerifyMode,
 };
 
 use ntex::http::client::{Client, Connector};
 use ntex::http::test::server as test_server;
 use ntex::http::{HttpService, Version};
 use ntex::service::{map_config, pipeline_factory, ServiceFactory};
-use ntex::time::Seconds;
+use ntex::time::{sleep, Millis, Seconds};
+use ntex::util::{join_all, Bytes};
 use ntex::web::{self, dev::AppConfig, App, HttpResponse};
 
 fn ssl_acceptor() -> SslAcceptor {
@@ -89,3 +90,132 @@ async fn test_connection_reuse_h2() {
     // one connection
     assert_eq!(num.load(Ordering::Relaxed), 1);
 }
+
+#[ntex::test]
+async fn test_h2_multiplexing() {
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let srv = test_server(move || {
+        let num2 = num2.clone();
+        pipeline_factory(move |io| {
+            num2.fetch_add(1, Ordering::Relaxed);
+            ok(io)
+        })
+        .and_then(
+            HttpService::build()
+                .h2(map_config(
+                    App::new().service(web::resource("/").route(web::to(
+                        |body: Bytes| async move {
+                            sleep(Millis(50)).await;
+                            HttpResponse::Ok().body(body)
+                        },
+                    ))),
+                    |_| AppConfig::default(),
+                ))
+                .openssl(ssl_acceptor())
+                .map_err(|_| ()),
+        )
+    });
+
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+    let _ = builder
+        .set_alpn_protos(b"\x02h2\x08http/1.1")
+        .map_err(|e| log::error!("Cannot set alpn protocol: {:?}", e));
+
+    let client = Client::build()
+        .connector(
+            Connector::default()
+                .timeout(Seconds(30))
+                .h2_window_size(1024 * 1024, 4 * 1024 * 1024)
+                .openssl(builder.build())
+                .finish(),
+        )
+        .finish();
+
+    // concurrent requests share one connection
+    let data = Bytes::from(vec![b'x'; 256 * 1024]);
+    let futs: Vec<_> = (0..8)
+        .map(|_| client.post(srv.surl("/")).send_body(data.clone()))
+        .collect();
+    for res in join_all(futs).await {
+        let mut res = res.unwrap();
+        assert!(res.status().is_success());
+        assert_eq!(res.version(), Version::HTTP_2);
+        let body = res.body().limit(512 * 1024).await.unwrap();
+        assert_eq!(body, data);
+    }
+    assert_eq!(num.load(Ordering::Relaxed), 1);
+
+    // connection is reused by following requests
+    let res = client.get(srv.surl("/")).send().await.unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(num.load(Ordering::Relaxed), 1);
+}
+
+#[ntex::test]
+async fn test_server_name() {
+    let names = Arc::new(Mutex::new(Vec::new()));
+    let names2 = names.clone();
+
+    let srv = test_server(move || {
+        let names = names2.clone();
+        let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+        builder
+            .set_private_key_file("./tests/key.pem", SslFiletype::PEM)
+            .unwrap();
+        builder
+            .set_certificate_chain_file("./tests/cert.pem")
+            .unwrap();
+        builder.set_servername_callback(move |ssl, _| {
+            let name = ssl
+                .servername(NameType::HOST_NAME)
+                .unwrap_or("")
+                .to_string();
+            names.lock().unwrap().push(name);
+            Ok(())
+        });
+
+        HttpService::build()
+            .h1(map_config(
+                App::new().service(
+                    web::resource("/").route(web::to(|| async { HttpResponse::Ok() })),
+                ),
+                |_| AppConfig::default(),
+            ))
+            .openssl(builder.build())
+            .map_err(|_| ())
+    });
+
+    // disable ssl verification
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+
+    let client = Client::build()
+        .connector(
+            Connector::default()
+                .server_name("example.com")
+                .openssl(builder.build())
+                .finish(),
+        )
+        .finish();
+
+    // connector's server name
+    let response = client.get(srv.surl("/")).send().await.unwrap();
+    assert!(response.status().is_success());
+
+    // per-request server name, new connection
+    let response = client
+        .get(srv.surl("/"))
+        .server_name("api.example.com")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    assert_eq!(
+        &*names.lock().unwrap(),
+        &["example.com".to_string(), "api.example.com".to_string()]
+    );
+}
@@ -1,7 +1,9 @@
 use std::io;
 
-use futures::future::{self, ok};
+use futures::future::{self, ok, ready};
+use futures::stream::{once, StreamExt};
 
+use ntex::http::header::{HeaderMap, HeaderName, HeaderValue};
 use ntex::http::test::server as test_server;
 use ntex::http::{HttpService, Method, Request, Response};
 use ntex::service::ServiceFactory;
@@ -72,6 +74,44 @@ async fn test_connection_close() {
     assert!(response.status().is_success());
 }
 
+#[ntex::test]
+async fn test_h1_trailers() {
+    let srv = test_server(move || {
+        HttpService::build()
+            .h1(|_| {
+                ok::<_, io::Error>(
+                    Response::Ok()
+                        .trailers(|| {
+                            let mut map = HeaderMap::new();
+                            map.insert(
+                                HeaderName::from_static("x-checksum"),
+                                HeaderValue::from_static("1234"),
+                            );
+                            map
+                        })
+                        .streaming(once(ready(Ok::<_, io::Error>(Bytes::from(STR))))),
+                )
+            })
+            .map(|_| ())
+    });
+
+    // second request re-uses keep-alive connection
+    for _ in 0..2 {
+        let mut response = srv.request(Method::GET, "/").send().await.unwrap();
+        assert!(response.status().is_success());
+
+        let mut pl = response.take_payload();
+        let mut body = Vec::new();
+        while let Some(chunk) = pl.next().await {
+            body.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(body, STR.as_bytes());
+
+        let trailers = pl.trailers().await.unwrap().unwrap();
+        assert_eq!(trailers.get("x-checksum").unwrap(), "1234");
+    }
+}
+
 #[ntex::test]
 async fn test_with_query_parameter() {
     let srv = test_server(move || {
@@ -90,3 +130,30 @@ async fn test_with_query_parameter() {
     let response = request.send().await.unwrap();
     assert!(response.status().is_success());
 }
+
+#[cfg(target_os = "linux")]
+#[ntex::test]
+async fn test_local_address() {
+    let srv = test_server(move || {
+        HttpService::build()
+            .finish(|req: Request| {
+                let ip = req.peer_addr().unwrap().ip().to_string();
+                ok::<_, io::Error>(Response::Ok().body(ip))
+            })
+            .map(|_| ())
+    });
+
+    let mut response = srv
+        .request(Method::GET, "/")
+        .local_address("127.0.0.2".parse().unwrap())
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"127.0.0.2"));
+
+    let mut response = srv.request(Method::GET, "/").send().await.unwrap();
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from_static(b"127.0.0.1"));
+}
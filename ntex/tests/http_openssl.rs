@@ -1,20 +1,28 @@
 #![cfg(feature = "openssl")]
-use std::io;
+use std::{io, sync::Arc, sync::Mutex};
 
 use futures::future::{err, ok, ready};
 use futures::stream::{once, Stream, StreamExt};
-use tls_openssl::ssl::{AlpnError, SslAcceptor, SslFiletype, SslMethod};
+use tls_openssl::ssl::{
+    AlpnError, SslAcceptor, SslConnector, SslFiletype, SslMethod, SslVerifyMode,
+};
 
 use ntex::codec::BytesCodec;
+use ntex::http::client::{Client, Connector};
 use ntex::http::error::PayloadError;
+use ntex::http::grpc;
 use ntex::http::header::{self, HeaderName, HeaderValue};
 use ntex::http::test::server as test_server;
-use ntex::http::{body, h1, HttpService, Method, Request, Response, StatusCode, Version};
+use ntex::http::{
+    body, h1, HeaderMap, HpackConfig, HttpService, Method, Request, Response,
+};
+use ntex::http::{StatusCode, Version};
 use ntex::io::Io;
 use ntex::service::{fn_service, ServiceFactory};
+use ntex::time::{sleep, Millis, Seconds};
 use ntex::util::{Bytes, BytesMut, Ready};
 use ntex::ws::handshake_response;
-use ntex::{time::Seconds, web::error::InternalError, ws};
+use ntex::{web::error::InternalError, ws};
 
 async fn load_body<S>(stream: S) -> Result<BytesMut, PayloadError>
 where
@@ -72,6 +80,257 @@ async fn test_h2() -> io::Result<()> {
     Ok(())
 }
 
+#[ntex::test]
+async fn test_h2_hpack() -> io::Result<()> {
+    let srv = test_server(move || {
+        HttpService::build()
+            .hpack(
+                HpackConfig::new()
+                    .sensitive_header(header::SET_COOKIE)
+                    .max_header_list_size(8192),
+            )
+            .h2(|req: Request| {
+                let value = req.headers().get(header::AUTHORIZATION).unwrap().clone();
+                ok::<_, io::Error>(
+                    Response::Ok().header(header::SET_COOKIE, value).finish(),
+                )
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let mut builder = SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.set_verify(SslVerifyMode::NONE);
+    builder.set_alpn_protos(b"\x02h2").unwrap();
+    let client = Client::build()
+        .connector(
+            Connector::default()
+                .hpack(HpackConfig::new().static_only())
+                .openssl(builder.build())
+                .finish(),
+        )
+        .finish();
+
+    let response = client
+        .get(srv.surl("/"))
+        .header(header::AUTHORIZATION, "token")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.version(), Version::HTTP_2);
+    assert_eq!(response.headers().get(header::SET_COOKIE).unwrap(), "token");
+
+    // header list exceeds limit
+    let response = client
+        .get(srv.surl("/"))
+        .header(header::AUTHORIZATION, "t".repeat(16_384))
+        .send()
+        .await;
+    assert!(!matches!(response, Ok(ref res) if res.status().is_success()));
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_h2_settings() -> io::Result<()> {
+    let data = "test".repeat(16_384);
+    let srv = test_server(move || {
+        HttpService::build()
+            .h2_initial_window_size(1_048_576)
+            .h2_initial_connection_window_size(4_194_304)
+            .h2_max_concurrent_streams(16)
+            .h2_max_frame_size(32_768)
+            .h2_max_header_list_size(8192)
+            .h2_ping(Seconds(1), Seconds(1))
+            .h2(|mut req: Request| async move {
+                let body = load_body(req.take_payload())
+                    .await
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                Ok::<_, io::Error>(Response::Ok().body(body))
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let mut response = srv
+        .srequest(Method::POST, "/")
+        .send_body(data.clone())
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.version(), Version::HTTP_2);
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from(data.clone()));
+
+    // keep-alive pings are acknowledged by client
+    sleep(Millis(2500)).await;
+
+    let mut response = srv
+        .srequest(Method::POST, "/")
+        .send_body(data.clone())
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let bytes = response.body().await.unwrap();
+    assert_eq!(bytes, Bytes::from(data));
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_h2_trailers() -> io::Result<()> {
+    let srv =
+        test_server(move || {
+            HttpService::build()
+                .h2(|req: Request| async move {
+                    let streaming = req.path() == "/stream";
+                    let mut res = Response::Ok();
+                    res.trailers(|| {
+                        let mut map = HeaderMap::new();
+                        map.insert(
+                            HeaderName::from_static("grpc-status"),
+                            HeaderValue::from_static("0"),
+                        );
+                        map
+                    });
+                    if streaming {
+                        Ok::<_, io::Error>(res.streaming(once(ok::<_, io::Error>(
+                            Bytes::from_static(b"data"),
+                        ))))
+                    } else {
+                        Ok::<_, io::Error>(res.body("data"))
+                    }
+                })
+                .openssl(ssl_acceptor())
+                .map_err(|_| ())
+        });
+
+    for path in &["/", "/stream"] {
+        let mut response = srv.srequest(Method::GET, path).send().await.unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(response.version(), Version::HTTP_2);
+
+        let mut pl = response.take_payload();
+        let body = load_body(&mut pl).await.unwrap();
+        assert_eq!(&body[..], b"data");
+        let trailers = pl.trailers().await.unwrap().unwrap();
+        assert_eq!(trailers.get("grpc-status").unwrap(), "0");
+    }
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_h2_grpc() -> io::Result<()> {
+    let srv = test_server(move || {
+        HttpService::build()
+            .h2(grpc::GrpcServer::new()
+                .unary("/test.Test/Echo", |_, msg: Bytes| async move { Ok(msg) })
+                .unary("/test.Test/Fail", |_, _| async move {
+                    Err::<Bytes, _>(grpc::Status::new(
+                        grpc::Code::InvalidArgument,
+                        "bad request",
+                    ))
+                })
+                .unary("/test.Test/Slow", |req: Request, msg| async move {
+                    assert!(req.extensions().get::<grpc::Deadline>().is_some());
+                    sleep(Millis(500)).await;
+                    Ok(msg)
+                })
+                .client_streaming("/test.Test/Count", |_, mut messages| async move {
+                    let mut count = 0;
+                    while let Some(msg) = messages.next().await {
+                        count += msg?.len();
+                    }
+                    Ok(Bytes::from(count.to_string()))
+                })
+                .server_streaming("/test.Test/Split", |_, msg: Bytes| async move {
+                    let items: Vec<_> = msg
+                        .chunks(2)
+                        .map(|c| Ok(Bytes::copy_from_slice(c)))
+                        .collect();
+                    Ok(futures::stream::iter(items))
+                })
+                .default_service(|_| ok::<_, io::Error>(Response::Ok().body("rest"))))
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let call = |path: &'static str, msgs: &[&[u8]]| {
+        let mut body = BytesMut::new();
+        for msg in msgs {
+            grpc::encode_message(msg, &mut body);
+        }
+        srv.srequest(Method::POST, path)
+            .header(header::CONTENT_TYPE, "application/grpc")
+            .send_body(body.freeze())
+    };
+
+    // unary
+    let mut response = call("/test.Test/Echo", &[b"hello"]).await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.version(), Version::HTTP_2);
+    let mut messages = grpc::Streaming::new(response.take_payload());
+    assert_eq!(
+        messages.next().await.unwrap().unwrap(),
+        Bytes::from_static(b"hello")
+    );
+    assert!(messages.next().await.is_none());
+    let trailers = messages.trailers().await.unwrap().unwrap();
+    assert_eq!(
+        grpc::Status::from_headers(&trailers).unwrap(),
+        grpc::Status::ok()
+    );
+
+    // client streaming
+    let mut response = call("/test.Test/Count", &[b"ab", b"cde"]).await.unwrap();
+    let mut messages = grpc::Streaming::new(response.take_payload());
+    assert_eq!(
+        messages.next().await.unwrap().unwrap(),
+        Bytes::from_static(b"5")
+    );
+
+    // server streaming
+    let mut response = call("/test.Test/Split", &[b"abcde"]).await.unwrap();
+    let mut messages = grpc::Streaming::new(response.take_payload());
+    let mut items = Vec::new();
+    while let Some(msg) = messages.next().await {
+        items.push(msg.unwrap());
+    }
+    assert_eq!(items, vec!["ab", "cd", "e"]);
+    let trailers = messages.trailers().await.unwrap().unwrap();
+    assert_eq!(
+        grpc::Status::from_headers(&trailers).unwrap().code(),
+        grpc::Code::Ok
+    );
+
+    // error status, trailers-only response
+    let response = call("/test.Test/Fail", &[b"hello"]).await.unwrap();
+    let status = grpc::Status::from_headers(response.headers()).unwrap();
+    assert_eq!(status.code(), grpc::Code::InvalidArgument);
+    assert_eq!(status.message(), "bad request");
+
+    let response = call("/test.Test/Unknown", &[b"hello"]).await.unwrap();
+    let status = grpc::Status::from_headers(response.headers()).unwrap();
+    assert_eq!(status.code(), grpc::Code::Unimplemented);
+
+    // deadline
+    let response = srv
+        .srequest(Method::POST, "/test.Test/Slow")
+        .header(header::CONTENT_TYPE, "application/grpc")
+        .header("grpc-timeout", "50m")
+        .send_body(Bytes::from_static(b"\0\0\0\0\x01a"))
+        .await
+        .unwrap();
+    let status = grpc::Status::from_headers(response.headers()).unwrap();
+    assert_eq!(status.code(), grpc::Code::DeadlineExceeded);
+
+    // default service
+    let mut response = srv.srequest(Method::GET, "/").send().await.unwrap();
+    assert!(response.status().is_success());
+    let body = response.body().await.unwrap();
+    assert_eq!(&body[..], b"rest");
+    Ok(())
+}
+
 #[ntex::test]
 async fn test_h1() -> io::Result<()> {
     let srv = test_server(move || {
@@ -139,6 +398,50 @@ async fn test_h2_body() -> io::Result<()> {
     Ok(())
 }
 
+#[ntex::test]
+async fn test_h2_access_log() -> io::Result<()> {
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let records2 = records.clone();
+    let srv = test_server(move || {
+        let records = records2.clone();
+        HttpService::build()
+            .on_access_log(move |rec| records.lock().unwrap().push(rec))
+            .h2(|mut req: Request| async move {
+                let body = load_body(req.take_payload())
+                    .await
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                Ok::<_, io::Error>(Response::Ok().body(body))
+            })
+            .openssl(ssl_acceptor())
+            .map_err(|_| ())
+    });
+
+    let response = srv
+        .srequest(Method::POST, "/test")
+        .send_body("HELLOWORLD")
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    sleep(Millis(100)).await;
+
+    let records = records.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    let rec = &records[0];
+    assert_eq!(rec.method, Method::POST);
+    assert_eq!(rec.uri.path(), "/test");
+    assert_eq!(rec.version, Version::HTTP_2);
+    assert_eq!(rec.status, Some(StatusCode::OK));
+    assert_eq!(rec.stream_id, Some(1));
+    assert_eq!(rec.bytes_received, 10);
+    assert_eq!(rec.bytes_sent, 10);
+    assert!(rec.completed);
+    assert!(rec.peer_addr.is_some());
+    let tls = rec.tls.as_ref().unwrap();
+    assert!(tls.version.starts_with("TLS"));
+    assert_eq!(tls.alpn.as_deref(), Some(&b"h2"[..]));
+    Ok(())
+}
+
 #[ntex::test]
 async fn test_h2_content_length() {
     let srv = test_server(move || {
@@ -1,13 +1,16 @@
+use std::sync::{Arc, Mutex};
 use std::{io, io::Read, io::Write, net};
 
 use futures::future::{self, ready, FutureExt};
 use futures::stream::{once, StreamExt};
 use regex::Regex;
 
+use ntex::codec::BytesCodec;
 use ntex::http::header::{HeaderName, HeaderValue};
 use ntex::http::test::server as test_server;
 use ntex::http::{
-    body, header, HttpService, KeepAlive, Method, Request, Response, StatusCode,
+    body, h1, header, HeaderMap, HttpService, KeepAlive, Method, Request, Response,
+    StatusCode,
 };
 use ntex::time::{sleep, Millis, Seconds};
 use ntex::{service::fn_service, util::Bytes, util::Ready, web::error};
@@ -144,6 +147,218 @@ async fn test_chunked_payload() {
     assert_eq!(returned_size, total_size);
 }
 
+#[ntex::test]
+async fn test_h1_trailers() {
+    let srv = test_server(|| {
+        HttpService::build().h1(|mut req: Request| async move {
+            let mut pl = req.take_payload();
+            let mut body = Vec::new();
+            while let Some(chunk) = pl.next().await {
+                body.extend_from_slice(&chunk.unwrap());
+            }
+            let trailers = pl.trailers().await.unwrap().unwrap();
+            let checksum = trailers.get("x-checksum").unwrap().clone();
+
+            Ok::<_, io::Error>(
+                Response::Ok()
+                    .trailers(move || {
+                        let mut map = HeaderMap::new();
+                        map.insert(HeaderName::from_static("x-checksum"), checksum);
+                        map
+                    })
+                    .streaming(once(ready(Ok::<_, io::Error>(Bytes::from(body))))),
+            )
+        })
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"POST /test HTTP/1.1\r\nConnection: close\r\nTransfer-Encoding: chunked\r\n\r\n\
+          4\r\ndata\r\n0\r\nx-checksum: 1234\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.ends_with("\r\n\r\n4\r\ndata\r\n0\r\nx-checksum: 1234\r\n\r\n"));
+}
+
+#[ntex::test]
+async fn test_request_target() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1_authority_form(false)
+            .h1(|req: Request| {
+                let body = format!("{:?} {}", req.target(), req.uri());
+                future::ok::<_, io::Error>(Response::Ok().body(body))
+            })
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream
+        .write_all(b"GET http://example.org/test HTTP/1.1\r\nConnection: close\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.ends_with("Absolute http://example.org/test"));
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"CONNECT example.org:443 HTTP/1.1\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+}
+
+#[ntex::test]
+async fn test_h1_connect() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .h1_connect(|(req, tunnel): (Request, h1::Tunnel)| async move {
+                if req.uri().host() != Some("example.org") {
+                    return tunnel.reject(Response::Forbidden().finish()).await;
+                }
+                let io = tunnel.accept(Response::Ok().finish()).await?;
+                while let Some(data) =
+                    io.recv(&BytesCodec).await.map_err(|e| e.into_inner())?
+                {
+                    io.send(data.freeze(), &BytesCodec)
+                        .await
+                        .map_err(|e| e.into_inner())?;
+                }
+                Ok::<_, io::Error>(())
+            })
+            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"CONNECT example.org:443 HTTP/1.1\r\n\r\nhello");
+    let mut data = vec![0; 1024];
+    let mut len = 0;
+    while !data[..len].ends_with(b"hello") {
+        len += stream.read(&mut data[len..]).unwrap();
+    }
+    let data = String::from_utf8_lossy(&data[..len]);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+
+    // raw data is transferred after response
+    let _ = stream.write_all(b"world");
+    let mut data = [0; 5];
+    let _ = stream.read_exact(&mut data);
+    assert_eq!(&data, b"world");
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"CONNECT example.com:443 HTTP/1.1\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+}
+
+#[ntex::test]
+async fn test_h1_on_upgrade() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .on_upgrade("echo", |(req, up): (Request, h1::Upgraded)| async move {
+                if req.path() != "/echo" {
+                    return up.reject(Response::NotFound().finish()).await;
+                }
+                let (io, leftover) = up
+                    .accept(
+                        Response::build(StatusCode::SWITCHING_PROTOCOLS)
+                            .header(header::UPGRADE, "echo")
+                            .finish(),
+                    )
+                    .await?;
+                io.send(leftover, &BytesCodec)
+                    .await
+                    .map_err(|e| e.into_inner())?;
+                while let Some(data) =
+                    io.recv(&BytesCodec).await.map_err(|e| e.into_inner())?
+                {
+                    io.send(data.freeze(), &BytesCodec)
+                        .await
+                        .map_err(|e| e.into_inner())?;
+                }
+                Ok::<_, io::Error>(())
+            })
+            .h1(|_| future::ok::<_, io::Error>(Response::Ok().finish()))
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET /echo HTTP/1.1\r\nconnection: upgrade\r\nupgrade: h2c, Echo\r\n\r\nhello",
+    );
+    let mut data = vec![0; 1024];
+    let mut len = 0;
+    while !data[..len].ends_with(b"hello") {
+        len += stream.read(&mut data[len..]).unwrap();
+    }
+    let data = String::from_utf8_lossy(&data[..len]);
+    assert!(data.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+
+    let _ = stream.write_all(b"world");
+    let mut data = [0; 5];
+    let _ = stream.read_exact(&mut data);
+    assert_eq!(&data, b"world");
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ =
+        stream.write_all(b"GET / HTTP/1.1\r\nconnection: upgrade\r\nupgrade: echo\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+    // unknown protocols are handled by main service
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream
+        .write_all(b"GET / HTTP/1.1\r\nconnection: upgrade\r\nupgrade: other\r\n\r\n");
+    let mut data = vec![0; 1024];
+    let len = stream.read(&mut data).unwrap();
+    assert!(data[..len].starts_with(b"HTTP/1.1 200 OK\r\n"));
+}
+
+#[ntex::test]
+async fn test_h1_access_log() {
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let records2 = records.clone();
+    let srv = test_server(move || {
+        let records = records2.clone();
+        HttpService::build()
+            .on_access_log(move |rec| records.lock().unwrap().push(rec))
+            .h1(|mut req: Request| async move {
+                let mut body = 0;
+                let mut pl = req.take_payload();
+                while let Some(chunk) = pl.next().await {
+                    body += chunk.unwrap().len();
+                }
+                Ok::<_, io::Error>(Response::Ok().body(format!("{}", body)))
+            })
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"POST /test HTTP/1.1\r\ncontent-length: 5\r\n\r\nhello\
+          GET /test2 HTTP/1.1\r\nconnection: close\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    sleep(Millis(100)).await;
+
+    let records = records.lock().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].method, Method::POST);
+    assert_eq!(records[0].uri.path(), "/test");
+    assert_eq!(records[0].status, Some(StatusCode::OK));
+    assert_eq!(records[0].bytes_received, 5);
+    assert_eq!(records[0].bytes_sent, 1);
+    assert!(records[0].completed);
+    assert!(records[0].peer_addr.is_some());
+    assert!(records[0].tls.is_none());
+    assert_eq!(records[0].stream_id, None);
+    assert_eq!(records[1].method, Method::GET);
+    assert_eq!(records[1].uri.path(), "/test2");
+    assert_eq!(records[1].bytes_received, 0);
+    assert!(records[1].completed);
+}
+
 #[ntex::test]
 async fn test_slow_request() {
     let srv = test_server(|| {
@@ -159,6 +374,32 @@ async fn test_slow_request() {
     assert!(data.starts_with("HTTP/1.1 408 Request Timeout"));
 }
 
+#[ntex::test]
+async fn test_headers_timeout() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .keep_alive(KeepAlive::Timeout(Seconds(10)))
+            .client_timeout(Seconds(10))
+            .client_headers_timeout(Seconds(1))
+            .h1(|_| Ready::Ok::<_, io::Error>(Response::Ok().finish()))
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    stream
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .unwrap();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n\r\n");
+    let mut buf = [0; 1024];
+    let n = stream.read(&mut buf).unwrap();
+    assert!(buf[..n].starts_with(b"HTTP/1.1 200 OK"));
+
+    // second request trickles headers
+    let _ = stream.write_all(b"GET /test/tests/test HTTP/1.1\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 408 Request Timeout"));
+}
+
 #[ntex::test]
 async fn test_http1_malformed_request() {
     let srv = test_server(|| {
@@ -172,6 +413,33 @@ async fn test_http1_malformed_request() {
     assert!(data.starts_with("HTTP/1.1 400 Bad Request"));
 }
 
+#[ntex::test]
+async fn test_http1_on_request_error() {
+    let srv = test_server(|| {
+        HttpService::build()
+            .on_request_error(|err| {
+                Response::build(err.status_code())
+                    .content_type("application/problem+json")
+                    .body(format!(
+                        "{{\"status\":{},\"title\":\"{}\"}}",
+                        err.status_code().as_u16(),
+                        err
+                    ))
+            })
+            .h1(|_| Ready::Ok::<_, io::Error>(Response::Ok().finish()))
+    });
+
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(b"GET /test/tests/test HTTP1.1\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 400 Bad Request"));
+    assert!(data.contains("content-type: application/problem+json"));
+    assert!(data.ends_with(
+        "\"status\":400,\"title\":\"Malformed request: Invalid HTTP version specified\"}"
+    ));
+}
+
 #[ntex::test]
 async fn test_http1_keepalive() {
     let srv = test_server(|| {
@@ -6,9 +6,9 @@ use futures::future::{ok, FutureExt};
 
 use ntex::codec::BytesCodec;
 use ntex::io::Io;
-use ntex::server::{Server, TestServer};
-use ntex::service::fn_service;
-use ntex::util::{Bytes, Ready};
+use ntex::server::{ConnectionInfo, Server, TestServer};
+use ntex::service::{fn_factory_with_config, fn_service, request_config};
+use ntex::util::{Bytes, PoolId, Ready};
 
 #[test]
 fn test_bind() {
@@ -36,6 +36,512 @@ fn test_bind() {
     let _ = h.join();
 }
 
+#[test]
+#[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+fn test_bind_with() {
+    use ntex::server::SocketConfig;
+
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        let srv = sys.exec(move || {
+            let cfg = SocketConfig::new()
+                .backlog(16)
+                .reuse_port(true)
+                .nodelay(false);
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind_with("test1", addr, cfg.clone(), move |_| {
+                    fn_service(|_| ok::<_, ()>(()))
+                })
+                .unwrap()
+                .bind_with("test2", addr, cfg, move |_| fn_service(|_| ok::<_, ()>(())))
+                .unwrap()
+                .run()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    thread::sleep(time::Duration::from_millis(300));
+    assert!(net::TcpStream::connect(addr).is_ok());
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_max_connections() {
+    use ntex::server::ServerStatus;
+
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let (st_tx, st_rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        let srv = sys.exec(move || {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .listener_max_connections("test", 1)
+                .status_handler(move |st| {
+                    let _ = st_tx.send(st);
+                })
+                .bind("test", addr, move |_| {
+                    fn_service(|io: Io| async move {
+                        while let Ok(Some(_)) = io.recv(&BytesCodec).await {}
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let conn1 = net::TcpStream::connect(addr).unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+    let stats = srv.stats();
+    assert_eq!(stats.connections, 1);
+    assert_eq!(stats.listeners[0].name, "test");
+    assert_eq!(stats.listeners[0].max_connections, 1);
+    assert_eq!(stats.listeners[0].connections, 1);
+
+    // second connection waits in backlog
+    let _conn2 = net::TcpStream::connect(addr).unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+    let stats = srv.stats();
+    assert_eq!(stats.connections, 1);
+    assert_eq!(stats.total, 1);
+    assert_eq!(stats.listeners[0].throttled, 1);
+    assert!(st_rx.try_iter().any(|st| st == ServerStatus::Throttled));
+
+    // listener resumes after connection get closed
+    drop(conn1);
+    thread::sleep(time::Duration::from_millis(300));
+    let stats = srv.stats();
+    assert_eq!(stats.connections, 1);
+    assert_eq!(stats.peak, 1);
+    assert_eq!(stats.total, 2);
+    assert_eq!(stats.listeners[0].total, 2);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_shutdown_phases() {
+    use ntex::server::ShutdownPhase;
+    use ntex::time::Millis;
+
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        let srv = sys.exec(move || {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .shutdown_timeout(Millis(500))
+                .bind("test", addr, move |_| {
+                    fn_service(|io: Io| async move {
+                        while let Ok(Some(_)) = io.recv(&BytesCodec).await {}
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    let subscriber = srv.on_shutdown();
+
+    // active connection is closed after shutdown timeout
+    let _conn = net::TcpStream::connect(addr).unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+    let _ = srv.stop(true);
+
+    let phases: Vec<_> = futures::executor::block_on_stream(subscriber).collect();
+    assert_eq!(
+        phases,
+        vec![
+            ShutdownPhase::StopAccepting,
+            ShutdownPhase::Draining(Millis(500)),
+            ShutdownPhase::ForceClose,
+            ShutdownPhase::Stopped
+        ]
+    );
+
+    // subscriber created after stop receives last phase
+    let phases: Vec<_> = futures::executor::block_on_stream(srv.on_shutdown()).collect();
+    assert_eq!(phases, vec![ShutdownPhase::Stopped]);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_set_workers() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let num = Arc::new(AtomicUsize::new(0));
+    let num2 = num.clone();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        let srv = sys.exec(move || {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind("test", addr, move |_| {
+                    num2.fetch_add(1, Relaxed);
+                    fn_service(|_| ok::<_, ()>(()))
+                })
+                .unwrap()
+                .run()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+    assert_eq!(num.load(Relaxed), 1);
+
+    futures::executor::block_on(srv.set_workers(3));
+    thread::sleep(time::Duration::from_millis(300));
+    assert_eq!(num.load(Relaxed), 3);
+    assert!(net::TcpStream::connect(addr).is_ok());
+
+    futures::executor::block_on(srv.set_workers(1));
+    thread::sleep(time::Duration::from_millis(300));
+    for _ in 0..4 {
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        // connection is handled and closed by service
+        let _ = conn.set_read_timeout(Some(time::Duration::from_millis(500)));
+        let mut buf = [0u8; 1];
+        assert_eq!(conn.read(&mut buf).unwrap(), 0);
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_accept_rate() {
+    use ntex::server::OverloadPolicy;
+
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        let srv = sys.exec(move || {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .accept_rate(1, 2)
+                .overload_policy(OverloadPolicy::Reset)
+                .bind("test", addr, move |_| {
+                    fn_service(|io: Io| async move {
+                        io.send(Bytes::from_static(b"test"), &BytesCodec)
+                            .await
+                            .unwrap();
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    // burst is served
+    for _ in 0..2 {
+        let mut buf = [0u8; 4];
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, b"test"[..]);
+    }
+
+    // connections over limit are reset
+    let mut buf = [0u8; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    assert!(conn.read_exact(&mut buf).is_err());
+    assert_eq!(srv.stats().rejected, 1);
+
+    // limit is refilled
+    thread::sleep(time::Duration::from_millis(1100));
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, b"test"[..]);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_ip_limit() {
+    use ntex::server::IpLimiter;
+
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        let srv = sys.exec(move || {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .listener_ip_limit("test", IpLimiter::new().max_connections(1))
+                .bind("test", addr, move |_| {
+                    fn_service(|io: Io| async move {
+                        io.send(Bytes::from_static(b"test"), &BytesCodec)
+                            .await
+                            .unwrap();
+                        while let Ok(Some(_)) = io.recv(&BytesCodec).await {}
+                        Ok::<_, ()>(())
+                    })
+                })
+                .unwrap()
+                .run()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut buf = [0u8; 4];
+    let mut conn1 = net::TcpStream::connect(addr).unwrap();
+    conn1.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, b"test"[..]);
+
+    // second connection from same address is rejected
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    assert!(conn.read_exact(&mut buf).is_err());
+    assert_eq!(srv.stats().rejected, 1);
+    assert_eq!(srv.stats().listeners[0].rejected, 1);
+
+    // limit is released with connection
+    drop(conn1);
+    thread::sleep(time::Duration::from_millis(300));
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, b"test"[..]);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_connect_hooks() {
+    use ntex::server::DisconnectReason;
+
+    struct ConnId(usize);
+
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let connected = Arc::new(AtomicUsize::new(0));
+    let connected2 = connected.clone();
+    let disconnected = Arc::new(AtomicUsize::new(0));
+    let disconnected2 = disconnected.clone();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.exec(move || {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .on_worker_connect(move |_| ConnId(connected2.fetch_add(1, Relaxed)))
+                .on_disconnect(move |io, reason| {
+                    assert!(matches!(reason, DisconnectReason::Closed));
+                    if io.data::<ConnId>().is_some() {
+                        disconnected2.fetch_add(1, Relaxed);
+                    }
+                })
+                .bind("test", addr, move |_| {
+                    fn_service(|io: Io| async move {
+                        let id = io.data::<ConnId>().unwrap().0;
+                        io.send(Bytes::from(format!("{}", id)), &BytesCodec)
+                            .await
+                            .map_err(|_| ())?;
+                        io.shutdown().await.map_err(|_| ())
+                    })
+                })
+                .unwrap()
+                .run()
+        });
+        let _ = tx.send(ntex::rt::System::current());
+        let _ = sys.run();
+    });
+    let sys = rx.recv().unwrap();
+
+    thread::sleep(time::Duration::from_millis(300));
+    for idx in 0..2 {
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        let mut data = String::new();
+        let _ = conn.read_to_string(&mut data);
+        assert_eq!(data, format!("{}", idx));
+    }
+    thread::sleep(time::Duration::from_millis(100));
+    assert_eq!(connected.load(Relaxed), 2);
+    assert_eq!(disconnected.load(Relaxed), 2);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_pause_listener() {
+    let addr1 = TestServer::unused_addr();
+    let addr2 = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        let srv = sys.exec(move || {
+            let factory = |_| {
+                fn_service(|io: Io| async move {
+                    io.send(Bytes::from_static(b"test"), &BytesCodec)
+                        .await
+                        .unwrap();
+                    Ok::<_, ()>(())
+                })
+            };
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind("first", addr1, factory)
+                .unwrap()
+                .bind("second", addr2, factory)
+                .unwrap()
+                .run()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    assert!(futures::executor::block_on(srv.pause_listener("first")));
+    assert!(!futures::executor::block_on(srv.pause_listener("unknown")));
+    thread::sleep(time::Duration::from_millis(100));
+    let stats = srv.stats();
+    assert!(stats
+        .listeners
+        .iter()
+        .any(|l| l.name == "first" && l.paused));
+    assert!(stats
+        .listeners
+        .iter()
+        .any(|l| l.name == "second" && !l.paused));
+
+    // paused listener keeps connection in backlog
+    let mut buf = [0u8; 4];
+    let mut conn1 = net::TcpStream::connect(addr1).unwrap();
+    conn1
+        .set_read_timeout(Some(time::Duration::from_millis(300)))
+        .unwrap();
+    assert!(conn1.read_exact(&mut buf).is_err());
+
+    let mut conn = net::TcpStream::connect(addr2).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, b"test"[..]);
+
+    // pending connection is accepted after resume
+    assert!(futures::executor::block_on(srv.resume_listener("first")));
+    conn1.set_read_timeout(None).unwrap();
+    conn1.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, b"test"[..]);
+    assert!(!srv.stats().listeners.iter().any(|l| l.paused));
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_bind_fd() {
+    use std::os::unix::io::OwnedFd;
+
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        let fd = OwnedFd::from(net::TcpListener::bind(addr).unwrap());
+        let srv = sys.exec(move || {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind_fd("test", fd, move |_| fn_service(|_| ok::<_, ()>(())))
+                .unwrap()
+                .run()
+        });
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    thread::sleep(time::Duration::from_millis(300));
+    assert!(net::TcpStream::connect(addr).is_ok());
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn test_bind_uds_abstract() {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixStream};
+
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.exec(move || {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind_uds_abstract("test", "ntex-test-bind-uds-abstract", move |_| {
+                    fn_service(|io: Io| async move {
+                        io.send(Bytes::from_static(b"test"), &BytesCodec)
+                            .await
+                            .map_err(|_| ())?;
+                        io.shutdown().await.map_err(|_| ())
+                    })
+                })
+                .unwrap()
+                .run()
+        });
+        let _ = tx.send(ntex::rt::System::current());
+        let _ = sys.run();
+    });
+    // listener is bound before server is started
+    let sys = rx.recv().unwrap();
+
+    let addr = SocketAddr::from_abstract_name(b"ntex-test-bind-uds-abstract").unwrap();
+    let mut conn = UnixStream::connect_addr(&addr).unwrap();
+    let mut data = String::new();
+    let _ = conn.read_to_string(&mut data);
+    assert_eq!(data, "test");
+
+    sys.stop();
+    let _ = h.join();
+}
+
 #[test]
 fn test_listen() {
     let addr = TestServer::unused_addr();
@@ -63,6 +569,117 @@ fn test_listen() {
     let _ = h.join();
 }
 
+#[test]
+fn test_connection_info() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let created = Arc::new(AtomicUsize::new(0));
+    let created2 = created.clone();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        sys.exec(move || {
+            Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind("info", addr, move |cfg| {
+                    assert_eq!(cfg.name(), "info");
+                    assert_eq!(cfg.local_addr(), Some(addr));
+
+                    let created = created2.clone();
+                    request_config(
+                        fn_factory_with_config(move |info: ConnectionInfo| {
+                            created.fetch_add(1, Relaxed);
+                            async move {
+                                Ok::<_, ()>(fn_service(move |io: Io| {
+                                    let msg = format!(
+                                        "{}:{}:{}",
+                                        info.name(),
+                                        info.local_addr().unwrap().port(),
+                                        info.peer_addr().is_some()
+                                    );
+                                    async move {
+                                        io.send(Bytes::from(msg), &BytesCodec)
+                                            .await
+                                            .map_err(|_| ())?;
+                                        io.shutdown().await.map_err(|_| ())
+                                    }
+                                }))
+                            }
+                        }),
+                        move |io: &Io| cfg.connection_info(io),
+                    )
+                })
+                .unwrap()
+                .run()
+        });
+        let _ = tx.send(ntex::rt::System::current());
+        let _ = sys.run();
+    });
+    let sys = rx.recv().unwrap();
+
+    thread::sleep(time::Duration::from_millis(300));
+    for _ in 0..2 {
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        let mut data = String::new();
+        let _ = conn.read_to_string(&mut data);
+        assert_eq!(data, format!("info:{}:true", addr.port()));
+    }
+    assert_eq!(created.load(Relaxed), 2);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_memory_pool() {
+    let addr1 = TestServer::unused_addr();
+    let addr2 = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+        let monitor = sys.exec(move || {
+            let builder = Server::build()
+                .workers(1)
+                .disable_signals()
+                .memory_pool("public", PoolId::P7)
+                .bind("public", addr1, move |cfg| {
+                    cfg.memory_pool(PoolId::P6);
+                    fn_service(|_| ok::<_, ()>(()))
+                })
+                .unwrap()
+                .bind("admin", addr2, move |cfg| {
+                    cfg.memory_pool(PoolId::P8);
+                    fn_service(|_| ok::<_, ()>(()))
+                })
+                .unwrap();
+            let monitor = builder.pool_monitor();
+            builder.run();
+            monitor
+        });
+        let _ = tx.send((monitor, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (monitor, sys) = rx.recv().unwrap();
+    assert_eq!(monitor.pool("public"), Some(PoolId::P7));
+
+    thread::sleep(time::Duration::from_millis(300));
+    assert!(net::TcpStream::connect(addr1).is_ok());
+    assert_eq!(monitor.pool("public"), Some(PoolId::P7));
+    assert_eq!(monitor.pool("admin"), Some(PoolId::P8));
+    assert_eq!(monitor.pool("unknown"), None);
+    assert!(monitor.stats("admin").is_some());
+
+    let listeners = monitor.listeners();
+    assert_eq!(listeners.len(), 2);
+    assert_eq!(listeners[0].0, "admin");
+    assert_eq!(listeners[1].1, PoolId::P7);
+
+    sys.stop();
+    let _ = h.join();
+}
+
 #[test]
 #[cfg(unix)]
 fn test_run() {
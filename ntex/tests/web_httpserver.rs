@@ -27,6 +27,7 @@ async fn test_run() {
             .maxconn(10)
             .maxconnrate(10)
             .keep_alive(10)
+            .max_requests_per_connection(100)
             .client_timeout(Seconds(5))
             .disconnect_timeout(Seconds(1))
             .ssl_handshake_timeout(Seconds(1))
@@ -60,6 +61,126 @@ async fn test_run() {
     sys.stop();
 }
 
+#[ntex::test]
+async fn test_expect() {
+    use std::io::{Read, Write};
+
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+
+        let srv = sys.exec(move || {
+            HttpServer::new(|| {
+                App::new().service(web::resource("/").route(web::to(
+                    |body: ntex::util::Bytes| async move { HttpResponse::Ok().body(body) },
+                )))
+            })
+            .expect(|| {
+                ntex::service::fn_service(|req: ntex::http::Request| async move {
+                    if req.headers().contains_key("authorization") {
+                        Ok(req)
+                    } else {
+                        Err(web::error::InternalError::default(
+                            "unauthorized",
+                            ntex::http::StatusCode::UNAUTHORIZED,
+                        ))
+                    }
+                })
+            })
+            .workers(1)
+            .stop_runtime()
+            .disable_signals()
+            .bind(format!("{}", addr))
+            .unwrap()
+            .run()
+        });
+
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    let _ = stream
+        .write_all(b"POST / HTTP/1.1\r\ncontent-length: 4\r\nexpect: 100-continue\r\n\r\n");
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    let _ = stream.write_all(
+        b"POST / HTTP/1.1\r\nauthorization: test\r\ncontent-length: 4\r\nexpect: 100-continue\r\nconnection: close\r\n\r\n",
+    );
+    let mut data = [0; 25];
+    let _ = stream.read_exact(&mut data[..]);
+    assert_eq!(&data, b"HTTP/1.1 100 Continue\r\n\r\n");
+
+    let mut data = String::new();
+    let _ = stream.write_all(b"test");
+    let _ = stream.read_to_string(&mut data);
+    assert!(data.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(data.ends_with("test"));
+
+    // stop
+    let _ = srv.stop(false);
+
+    sleep(Duration::from_millis(100)).await;
+    sys.stop();
+}
+
+#[ntex::test]
+async fn test_bind_redirect_to_https() {
+    let addr = TestServer::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let sys = ntex::rt::System::new("test");
+
+        let srv = sys.exec(move || {
+            HttpServer::new(|| {
+                App::new().service(
+                    web::resource("/")
+                        .route(web::to(|| async { HttpResponse::Ok().body("test") })),
+                )
+            })
+            .workers(1)
+            .shutdown_timeout(Seconds(1))
+            .stop_runtime()
+            .disable_signals()
+            .bind_redirect_to_https(format!("{}", addr), "example.com")
+            .unwrap()
+            .run()
+        });
+
+        let _ = tx.send((srv, ntex::rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let client = ntex::http::client::Client::build()
+        .disable_redirects()
+        .finish();
+    let response = client
+        .get(format!("http://{}/index.html?q=1", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), ntex::http::StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(
+        response.headers().get("location").unwrap(),
+        "https://example.com/index.html?q=1"
+    );
+    assert!(!response.headers().contains_key("strict-transport-security"));
+
+    // stop
+    let _ = srv.stop(false);
+
+    sleep(Duration::from_millis(100)).await;
+    sys.stop();
+}
+
 #[cfg(feature = "openssl")]
 fn ssl_acceptor() -> std::io::Result<SslAcceptorBuilder> {
     use tls_openssl::ssl::{SslAcceptor, SslFiletype, SslMethod, SslVerifyMode};
@@ -1,9 +1,11 @@
 use std::io;
 
 use futures::StreamExt;
+use ntex::connect::ConnectionState;
 use ntex::http::StatusCode;
 use ntex::service::{fn_factory_with_config, fn_service};
-use ntex::util::{ByteString, Bytes};
+use ntex::time::Millis;
+use ntex::util::{poll_fn, ByteString, Bytes};
 use ntex::web::{self, test, ws, App, HttpRequest};
 
 async fn service(msg: ws::Frame) -> Result<Option<ws::Message>, io::Error> {
@@ -119,3 +121,98 @@ async fn web_ws_client() {
     // TODO fix
     // on_disconnect.await
 }
+
+#[ntex::test]
+async fn web_ws_framed() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/ws").route(web::to(
+            |req: HttpRequest, pl: web::types::Payload| async move {
+                ws::start::<_, _, _, web::Error>(
+                    req,
+                    pl,
+                    fn_factory_with_config(|_| async {
+                        Ok::<_, web::Error>(fn_service(service))
+                    }),
+                )
+                .await
+            },
+        )))
+    });
+
+    let framed = srv.ws_framed_at("/ws").await.unwrap();
+    framed
+        .send(ws::Message::Text(ByteString::from_static("text")))
+        .await
+        .unwrap();
+    let item = framed.recv().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"text")));
+
+    framed
+        .send(ws::Message::Close(Some(ws::CloseCode::Normal.into())))
+        .await
+        .unwrap();
+    let item = framed.recv().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Close(Some(ws::CloseCode::Away.into())));
+}
+
+#[ntex::test]
+async fn web_ws_reconnect() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest, pl: web::types::Payload| async move {
+                ws::start::<_, _, _, web::Error>(
+                    req,
+                    pl,
+                    fn_factory_with_config(|_| async {
+                        Ok::<_, web::Error>(fn_service(service))
+                    }),
+                )
+                .await
+            },
+        )))
+    });
+
+    let conn = ntex::ws::WsClient::build(srv.url("/"))
+        .finish()
+        .unwrap()
+        .reconnect()
+        .backoff(Millis(10), Millis(50))
+        .finish();
+    let events = conn.subscribe();
+
+    let (io, codec, _) = conn.next().await.unwrap().into_inner();
+    assert!(conn.is_connected());
+    io.send(ws::Message::Text(ByteString::from_static("text")), &codec)
+        .await
+        .unwrap();
+    let item = io.recv(&codec).await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"text")));
+
+    // connection is lost, new connection is established
+    io.force_close();
+    drop(io);
+    let (io, codec, _) = conn.next().await.unwrap().into_inner();
+    io.send(ws::Message::Binary("text".into()), &codec)
+        .await
+        .unwrap();
+    let item = io.recv(&codec).await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Binary(Bytes::from_static(b"text")));
+
+    conn.stop();
+    assert!(conn.next().await.is_none());
+
+    let mut states = Vec::new();
+    while let Some(state) = poll_fn(|cx| events.poll_recv(cx)).await {
+        states.push(state);
+    }
+    assert_eq!(
+        states,
+        vec![
+            ConnectionState::Connected,
+            ConnectionState::Disconnected,
+            ConnectionState::Connecting,
+            ConnectionState::Connected,
+            ConnectionState::Closed,
+        ]
+    );
+}